@@ -0,0 +1,132 @@
+//! Compressed fill receipts for `execute_trade` and `settle_match`.
+//!
+//! A market with a receipt tree appends both sides of every settled fill to
+//! an SPL account compression concurrent Merkle tree rather than writing
+//! them into accounts: a leaf is the keccak-256 hash of a `FillReceiptLeaf`,
+//! and the receipts themselves go out in the `ReceiptsAppended` event, from
+//! which an indexer rebuilds the tree and serves proofs against its on-chain
+//! root. Account compression is called with its instruction data built here;
+//! the two instructions used are small and their layout is fixed.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::keccak;
+use anchor_lang::solana_program::program::invoke_signed;
+
+use crate::offchain_api::{FillReceiptLeaf, Trade};
+use crate::{ClobError, ReceiptTree, ReceiptsAppended, RECEIPT_TREE_ACCOUNT_SIZE};
+
+/// SPL account compression, which keeps the receipt trees
+pub const SPL_ACCOUNT_COMPRESSION_ID: Pubkey =
+    anchor_lang::solana_program::pubkey!("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+/// SPL noop, through which account compression logs tree changes
+pub const SPL_NOOP_ID: Pubkey = anchor_lang::solana_program::pubkey!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+
+/// Anchor discriminators of the account compression instructions called
+const INIT_EMPTY_MERKLE_TREE: [u8; 8] = [191, 11, 119, 7, 180, 107, 220, 110];
+const APPEND: [u8; 8] = [149, 120, 18, 222, 236, 225, 88, 203];
+
+/// Signer seeds of a market's receipt tree, the authority of its Merkle tree
+fn seeds<'a>(orderbook: &'a Pubkey, bump: &'a [u8; 1]) -> [&'a [u8]; 3] {
+    [b"receipt_tree", orderbook.as_ref(), bump]
+}
+
+/// Create an empty tree in `merkle_tree`, an account already allocated for
+/// account compression, with the market's receipt tree as its authority
+#[allow(clippy::too_many_arguments)]
+pub fn init_empty_tree<'info>(
+    orderbook: &Pubkey,
+    bump: u8,
+    receipt_tree: &AccountInfo<'info>,
+    merkle_tree: &AccountInfo<'info>,
+    noop: &AccountInfo<'info>,
+    compression: &AccountInfo<'info>,
+    max_depth: u32,
+    max_buffer_size: u32,
+) -> Result<()> {
+    let mut data = INIT_EMPTY_MERKLE_TREE.to_vec();
+    data.extend_from_slice(&max_depth.to_le_bytes());
+    data.extend_from_slice(&max_buffer_size.to_le_bytes());
+    call(data, &seeds(orderbook, &[bump]), receipt_tree, merkle_tree, noop, compression)
+}
+
+/// Append the receipts of `trade` to the market's tree when it keeps one.
+/// The tree is passed after the instruction's own accounts: the receipt tree
+/// PDA, its Merkle tree, the noop program and account compression.
+pub fn append<'info>(
+    orderbook: Pubkey,
+    compressed: bool,
+    remaining_accounts: &[AccountInfo<'info>],
+    trade: &Trade,
+    timestamp: i64,
+) -> Result<()> {
+    if !compressed {
+        return Ok(());
+    }
+    let [receipt_tree, merkle_tree, noop, compression, ..] = remaining_accounts else {
+        return err!(ClobError::ReceiptTreeRequired);
+    };
+    require_keys_eq!(noop.key(), SPL_NOOP_ID, ClobError::ReceiptTreeRequired);
+    require_keys_eq!(compression.key(), SPL_ACCOUNT_COMPRESSION_ID, ClobError::ReceiptTreeRequired);
+
+    let (first_leaf_index, bump) = {
+        require_keys_eq!(*receipt_tree.owner, crate::ID, ClobError::ReceiptTreeRequired);
+        let mut data = receipt_tree.try_borrow_mut_data()?;
+        require!(
+            data.len() >= RECEIPT_TREE_ACCOUNT_SIZE && data.starts_with(ReceiptTree::DISCRIMINATOR),
+            ClobError::ReceiptTreeRequired
+        );
+        let tree: &mut ReceiptTree = bytemuck::from_bytes_mut(&mut data[8..RECEIPT_TREE_ACCOUNT_SIZE]);
+        require_keys_eq!(tree.orderbook, orderbook, ClobError::ReceiptTreeRequired);
+        require_keys_eq!(tree.merkle_tree, merkle_tree.key(), ClobError::ReceiptTreeRequired);
+        let first_leaf_index = tree.leaves;
+        tree.leaves = first_leaf_index
+            .checked_add(2)
+            .filter(|&leaves| leaves <= tree.capacity)
+            .ok_or(ClobError::ReceiptTreeFull)?;
+        (first_leaf_index, tree.bump)
+    };
+
+    let bump = [bump];
+    let seeds = seeds(&orderbook, &bump);
+    for receipt in FillReceiptLeaf::from_trade(orderbook, trade, timestamp) {
+        let mut data = APPEND.to_vec();
+        data.extend_from_slice(&keccak::hash(&receipt.leaf_data()).to_bytes());
+        call(data, &seeds, receipt_tree, merkle_tree, noop, compression)?;
+    }
+
+    emit!(ReceiptsAppended {
+        orderbook,
+        merkle_tree: merkle_tree.key(),
+        first_leaf_index,
+        trade: *trade,
+        timestamp,
+    });
+    Ok(())
+}
+
+/// Call account compression with the receipt tree signing as the authority
+fn call<'info>(
+    data: Vec<u8>,
+    seeds: &[&[u8]],
+    receipt_tree: &AccountInfo<'info>,
+    merkle_tree: &AccountInfo<'info>,
+    noop: &AccountInfo<'info>,
+    compression: &AccountInfo<'info>,
+) -> Result<()> {
+    let instruction = Instruction {
+        program_id: SPL_ACCOUNT_COMPRESSION_ID,
+        accounts: vec![
+            AccountMeta::new(merkle_tree.key(), false),
+            AccountMeta::new_readonly(receipt_tree.key(), true),
+            AccountMeta::new_readonly(noop.key(), false),
+        ],
+        data,
+    };
+    invoke_signed(
+        &instruction,
+        &[merkle_tree.clone(), receipt_tree.clone(), noop.clone(), compression.clone()],
+        &[seeds],
+    )?;
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use anchor_lang::error::ErrorCode as AnchorErrorCode;
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
 use borsh::{BorshDeserialize, BorshSerialize};
@@ -7,11 +8,33 @@ use std::convert::TryFrom;
 
 declare_id!("JBphRWHYzHCiVvYB89vGM9NpaDmHbe1A9W156sRV52Bo");
 
+pub mod compressed_receipts;
 pub mod offchain_api;
+pub mod signed_order;
+
+use compressed_receipts::{SPL_ACCOUNT_COMPRESSION_ID, SPL_NOOP_ID};
 
 // Constants for CLOB configuration
 pub const ORDERBOOK_ACCOUNT_SIZE: usize = 8 + std::mem::size_of::<OrderBook>();
 pub const USER_ACCOUNT_SIZE: usize = 8 + std::mem::size_of::<UserAccount>();
+pub const FILL_RECEIPTS_ACCOUNT_SIZE: usize = 8 + std::mem::size_of::<FillReceipts>();
+pub const WITHDRAWAL_WHITELIST_ACCOUNT_SIZE: usize = 8 + std::mem::size_of::<WithdrawalWhitelist>();
+pub const SIGNED_ORDER_FILL_ACCOUNT_SIZE: usize = 8 + std::mem::size_of::<SignedOrderFill>();
+pub const CREDITED_TRANSFER_ACCOUNT_SIZE: usize = 8 + std::mem::size_of::<CreditedTransfer>();
+pub const SETTLEMENT_ADJUSTMENT_ACCOUNT_SIZE: usize = 8 + std::mem::size_of::<SettlementAdjustment>();
+pub const RECEIPT_TREE_ACCOUNT_SIZE: usize = 8 + std::mem::size_of::<ReceiptTree>();
+/// Fills kept per user; older ones are overwritten
+pub const FILL_RECEIPTS_CAPACITY: usize = 32;
+/// Destinations a withdrawal whitelist holds
+pub const WITHDRAWAL_WHITELIST_CAPACITY: usize = 8;
+/// `WithdrawalWhitelist::pending_change` values
+pub const WHITELIST_CHANGE_NONE: u8 = 0;
+pub const WHITELIST_CHANGE_ADD: u8 = 1;
+pub const WHITELIST_CHANGE_REMOVE_WHITELIST: u8 = 2;
+/// Longest sub-account name, in bytes
+pub const SUB_ACCOUNT_NAME_MAX_LEN: usize = 32;
+/// Deepest compressed receipt tree, 2^30 leaves
+pub const MAX_RECEIPT_TREE_DEPTH: u32 = 30;
 
 #[program]
 pub mod svm_clob {
@@ -32,7 +55,7 @@ pub mod svm_clob {
         orderbook.tick_size = tick_size;
         orderbook.min_order_size = min_order_size;
         orderbook.is_initialized = 1;
-        orderbook.is_paused = 0;
+        orderbook.status = offchain_api::MarketStatus::Active.into();
         orderbook.total_volume = 0;
 
         msg!(
@@ -55,6 +78,82 @@ pub mod svm_clob {
         Ok(())
     }
 
+    /// Open the caller's sub-account `index`, a user account of its own with
+    /// separate balances that trades as `["sub_account", user, index]`. The
+    /// key has no private key; the caller signs the sub-account's orders.
+    pub fn initialize_sub_account(ctx: Context<InitializeSubAccount>, index: u16, name: String) -> Result<()> {
+        require!(
+            index > 0 && name.len() <= SUB_ACCOUNT_NAME_MAX_LEN,
+            ClobError::InvalidSubAccount
+        );
+        let user_account = &mut ctx.accounts.user_account.load_init()?;
+        user_account.owner = ctx.accounts.sub_account.key();
+        user_account.wallet = ctx.accounts.user.key();
+        user_account.sub_account = index;
+        user_account.is_initialized = 1;
+
+        emit!(SubAccountCreated {
+            wallet: ctx.accounts.user.key(),
+            sub_account: ctx.accounts.sub_account.key(),
+            index,
+            name,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Move balances between two user accounts of the caller's: its own and
+    /// its sub-accounts
+    pub fn transfer_between_sub_accounts(
+        ctx: Context<TransferBetweenSubAccounts>,
+        base_amount: u64,
+        quote_amount: u64,
+    ) -> Result<()> {
+        require_keys_neq!(ctx.accounts.from.key(), ctx.accounts.to.key(), ClobError::InvalidSubAccount);
+        let user = ctx.accounts.user.key();
+        let mut from = ctx.accounts.from.load_mut()?;
+        let mut to = ctx.accounts.to.load_mut()?;
+        require!(from.signer() == user && to.signer() == user, ClobError::Unauthorized);
+
+        from.base_token_balance = from
+            .base_token_balance
+            .checked_sub(base_amount)
+            .ok_or(ClobError::InsufficientBalance)?;
+        from.quote_token_balance = from
+            .quote_token_balance
+            .checked_sub(quote_amount)
+            .ok_or(ClobError::InsufficientBalance)?;
+        to.base_token_balance = to
+            .base_token_balance
+            .checked_add(base_amount)
+            .ok_or(ClobError::InsufficientBalance)?;
+        to.quote_token_balance = to
+            .quote_token_balance
+            .checked_add(quote_amount)
+            .ok_or(ClobError::InsufficientBalance)?;
+
+        emit!(SubAccountTransfer {
+            wallet: user,
+            from: from.owner,
+            to: to.owner,
+            base_amount,
+            quote_amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Create the caller's fill receipts; every later `execute_trade` and
+    /// `settle_match` the caller takes part in records the fill there
+    pub fn initialize_fill_receipts(ctx: Context<InitializeFillReceipts>) -> Result<()> {
+        let fill_receipts = &mut ctx.accounts.fill_receipts.load_init()?;
+        fill_receipts.owner = ctx.accounts.user.key();
+        fill_receipts.total_fills = 0;
+
+        msg!("Fill receipts initialized for: {:?}", ctx.accounts.user.key());
+        Ok(())
+    }
+
     pub fn execute_trade(
         ctx: Context<ExecuteTrade>,
         trade: offchain_api::Trade,
@@ -65,7 +164,7 @@ pub mod svm_clob {
             orderbook.authority == ctx.accounts.authority.key(),
             ClobError::InvalidAuthority
         );
-        require!(orderbook.is_paused == 0, ClobError::OrderbookPaused);
+        check_settlement_status(&orderbook, &trade)?;
 
         let mut taker_user_account = ctx.accounts.taker_user_account.load_mut()?;
         let mut maker_user_account = ctx.accounts.maker_user_account.load_mut()?;
@@ -73,60 +172,90 @@ pub mod svm_clob {
         require!(taker_user_account.owner == trade.taker, ClobError::Unauthorized);
         require!(maker_user_account.owner == trade.maker, ClobError::Unauthorized);
 
-        let quote_amount_u128 = u128::from(trade.quantity)
-            .checked_mul(u128::from(trade.price))
-            .ok_or(ClobError::InsufficientBalance)?;
-        let quote_transfer_amount: u64 = u64::try_from(quote_amount_u128)
-            .map_err(|_| ClobError::InsufficientBalance)?;
+        apply_trade(&mut orderbook, &mut taker_user_account, &mut maker_user_account, &trade)?;
+        record_fill_receipts(
+            ctx.accounts.orderbook.key(),
+            &ctx.accounts.taker_fill_receipts,
+            &ctx.accounts.maker_fill_receipts,
+            &trade,
+            clock.unix_timestamp,
+        )?;
+        compressed_receipts::append(
+            ctx.accounts.orderbook.key(),
+            orderbook.compressed_receipts == 1,
+            ctx.remaining_accounts,
+            &trade,
+            clock.unix_timestamp,
+        )?;
 
-        if trade.taker_side == offchain_api::OrderSide::Bid {
-            taker_user_account.quote_token_balance = taker_user_account
-                .quote_token_balance
-                .checked_sub(quote_transfer_amount)
-                .ok_or(ClobError::InsufficientBalance)?;
-            taker_user_account.base_token_balance = taker_user_account
-                .base_token_balance
-                .checked_add(trade.quantity)
-                .ok_or(ClobError::InsufficientBalance)?;
-            maker_user_account.quote_token_balance = maker_user_account
-                .quote_token_balance
-                .checked_add(quote_transfer_amount)
-                .ok_or(ClobError::InsufficientBalance)?;
-            maker_user_account.base_token_balance = maker_user_account
-                .base_token_balance
-                .checked_sub(trade.quantity)
-                .ok_or(ClobError::InsufficientBalance)?;
-        } else {
-            taker_user_account.quote_token_balance = taker_user_account
-                .quote_token_balance
-                .checked_add(quote_transfer_amount)
-                .ok_or(ClobError::InsufficientBalance)?;
-            taker_user_account.base_token_balance = taker_user_account
-                .base_token_balance
-                .checked_sub(trade.quantity)
-                .ok_or(ClobError::InsufficientBalance)?;
-            maker_user_account.quote_token_balance = maker_user_account
-                .quote_token_balance
-                .checked_sub(quote_transfer_amount)
-                .ok_or(ClobError::InsufficientBalance)?;
-            maker_user_account.base_token_balance = maker_user_account
-                .base_token_balance
-                .checked_add(trade.quantity)
-                .ok_or(ClobError::InsufficientBalance)?;
-        }
+        emit!(TradeSettled {
+            taker_order_id: trade.taker_order_id,
+            maker_order_id: trade.maker_order_id,
+            taker: trade.taker,
+            maker: trade.maker,
+            price: trade.price,
+            quantity: trade.quantity,
+            timestamp: clock.unix_timestamp,
+        });
 
-        orderbook.total_volume = orderbook
-            .total_volume
-            .checked_add(trade.quantity)
-            .ok_or(ClobError::InsufficientBalance)?;
-        taker_user_account.total_volume_traded = taker_user_account
-            .total_volume_traded
-            .checked_add(trade.quantity)
-            .ok_or(ClobError::InsufficientBalance)?;
-        maker_user_account.total_volume_traded = maker_user_account
-            .total_volume_traded
-            .checked_add(trade.quantity)
-            .ok_or(ClobError::InsufficientBalance)?;
+        Ok(())
+    }
+
+    /// Settle a match between two signed orders. Each order's signature, by
+    /// its owner or, for a sub-account, the owner's wallet, must be checked by
+    /// an Ed25519 program instruction earlier in the same transaction, and the
+    /// trade must stay within both orders' intents.
+    pub fn settle_match(
+        ctx: Context<SettleMatch>,
+        trade: offchain_api::Trade,
+        taker_order: offchain_api::SignedOrder,
+        maker_order: offchain_api::SignedOrder,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let instructions = ctx.accounts.instructions.to_account_info();
+        let taker_signer = ctx.accounts.taker_user_account.load()?.signer();
+        let maker_signer = ctx.accounts.maker_user_account.load()?.signer();
+        signed_order::verify(&instructions, &taker_order, &taker_signer, clock.unix_timestamp)?;
+        signed_order::verify(&instructions, &maker_order, &maker_signer, clock.unix_timestamp)?;
+        signed_order::check_match(
+            &ctx.accounts.orderbook.key(),
+            &trade,
+            &taker_order.order_intent,
+            &maker_order.order_intent,
+        )?;
+        require_keys_neq!(
+            ctx.accounts.taker_order_fill.key(),
+            ctx.accounts.maker_order_fill.key(),
+            ClobError::SignedOrderNonceReused
+        );
+        let authority = ctx.accounts.authority.key();
+        record_signed_fill(&ctx.accounts.taker_order_fill, &taker_order, authority, trade.quantity)?;
+        record_signed_fill(&ctx.accounts.maker_order_fill, &maker_order, authority, trade.quantity)?;
+
+        let mut orderbook = ctx.accounts.orderbook.load_mut()?;
+        check_settlement_status(&orderbook, &trade)?;
+
+        let mut taker_user_account = ctx.accounts.taker_user_account.load_mut()?;
+        let mut maker_user_account = ctx.accounts.maker_user_account.load_mut()?;
+
+        require!(taker_user_account.owner == trade.taker, ClobError::Unauthorized);
+        require!(maker_user_account.owner == trade.maker, ClobError::Unauthorized);
+
+        apply_trade(&mut orderbook, &mut taker_user_account, &mut maker_user_account, &trade)?;
+        record_fill_receipts(
+            ctx.accounts.orderbook.key(),
+            &ctx.accounts.taker_fill_receipts,
+            &ctx.accounts.maker_fill_receipts,
+            &trade,
+            clock.unix_timestamp,
+        )?;
+        compressed_receipts::append(
+            ctx.accounts.orderbook.key(),
+            orderbook.compressed_receipts == 1,
+            ctx.remaining_accounts,
+            &trade,
+            clock.unix_timestamp,
+        )?;
 
         emit!(TradeSettled {
             taker_order_id: trade.taker_order_id,
@@ -163,6 +292,7 @@ pub mod svm_clob {
         token::transfer(cpi_ctx, amount)?;
 
         let mut user_account = ctx.accounts.user_account.load_mut()?;
+        let owner = user_account.owner;
         if is_base_deposit {
             user_account.base_token_balance = user_account
                 .base_token_balance
@@ -174,10 +304,22 @@ pub mod svm_clob {
                 .checked_add(amount)
                 .ok_or(ClobError::InsufficientBalance)?;
         }
+
+        emit!(DepositMade {
+            user: owner,
+            mint: mint_key,
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
         Ok(())
     }
 
     pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+        // Only the account's owner, or a sub-account's wallet, moves its funds
+        require!(
+            ctx.accounts.user_account.load()?.signer() == ctx.accounts.user.key(),
+            ClobError::Unauthorized
+        );
         let orderbook = ctx.accounts.orderbook.load()?;
         let mint_key = ctx.accounts.token_mint.key();
         require!(
@@ -186,6 +328,10 @@ pub mod svm_clob {
         );
         let is_base_withdrawal = mint_key == orderbook.base_mint;
         drop(orderbook);
+        check_withdrawal_destination(
+            &ctx.accounts.withdrawal_whitelist,
+            &ctx.accounts.user_token_account.key(),
+        )?;
 
         {
             let user_account = ctx.accounts.user_account.load()?;
@@ -222,16 +368,528 @@ pub mod svm_clob {
         token::transfer(cpi_ctx, amount)?;
 
         let mut user_account = ctx.accounts.user_account.load_mut()?;
+        let owner = user_account.owner;
         if is_base_withdrawal {
             user_account.base_token_balance -= amount;
         } else {
             user_account.quote_token_balance -= amount;
         }
+
+        emit!(WithdrawalMade {
+            user: owner,
+            mint: mint_key,
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Restrict withdrawals from the caller's user account to `destinations`,
+    /// token accounts the caller controls. Every later widening of the list
+    /// waits `delay_secs`, so a stolen key cannot redirect funds before the
+    /// owner notices the request and moves them to a whitelisted account.
+    pub fn initialize_withdrawal_whitelist(
+        ctx: Context<InitializeWithdrawalWhitelist>,
+        delay_secs: u32,
+        destinations: Vec<Pubkey>,
+    ) -> Result<()> {
+        require!(
+            destinations.len() <= WITHDRAWAL_WHITELIST_CAPACITY,
+            ClobError::WhitelistFull
+        );
+        let whitelist = &mut ctx.accounts.withdrawal_whitelist.load_init()?;
+        whitelist.owner = ctx.accounts.user.key();
+        whitelist.delay_secs = i64::from(delay_secs);
+        for destination in destinations {
+            if !whitelist.contains(&destination) {
+                whitelist.add(destination)?;
+            }
+        }
+        whitelist.pending_change = WHITELIST_CHANGE_NONE;
+
+        msg!("Withdrawal whitelist initialized for: {:?}", ctx.accounts.user.key());
+        Ok(())
+    }
+
+    /// Request adding `destination` to the whitelist, replacing any pending
+    /// change; it can be applied once the whitelist's delay has passed
+    pub fn request_withdrawal_destination(
+        ctx: Context<ManageWithdrawalWhitelist>,
+        destination: Pubkey,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let whitelist = &mut ctx.accounts.withdrawal_whitelist.load_mut()?;
+        require!(
+            whitelist.contains(&destination)
+                || (whitelist.destination_count as usize) < WITHDRAWAL_WHITELIST_CAPACITY,
+            ClobError::WhitelistFull
+        );
+        whitelist.request(WHITELIST_CHANGE_ADD, destination, now);
+
+        emit!(WithdrawalWhitelistChangeRequested {
+            owner: whitelist.owner,
+            destination,
+            removes_whitelist: false,
+            effective_at: whitelist.pending_effective_at,
+        });
+        Ok(())
+    }
+
+    /// Request removing the whitelist altogether, replacing any pending
+    /// change; once applied, withdrawals may go to any account again
+    pub fn request_withdrawal_whitelist_removal(ctx: Context<ManageWithdrawalWhitelist>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let whitelist = &mut ctx.accounts.withdrawal_whitelist.load_mut()?;
+        whitelist.request(WHITELIST_CHANGE_REMOVE_WHITELIST, Pubkey::default(), now);
+
+        emit!(WithdrawalWhitelistChangeRequested {
+            owner: whitelist.owner,
+            destination: Pubkey::default(),
+            removes_whitelist: true,
+            effective_at: whitelist.pending_effective_at,
+        });
+        Ok(())
+    }
+
+    /// Apply the pending change once its delay has passed
+    pub fn apply_withdrawal_whitelist_change(ctx: Context<ManageWithdrawalWhitelist>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let change = {
+            let whitelist = &mut ctx.accounts.withdrawal_whitelist.load_mut()?;
+            require!(
+                whitelist.pending_change != WHITELIST_CHANGE_NONE && now >= whitelist.pending_effective_at,
+                ClobError::WhitelistChangeNotReady
+            );
+            let change = whitelist.pending_change;
+            if change == WHITELIST_CHANGE_ADD {
+                let destination = whitelist.pending_destination;
+                if !whitelist.contains(&destination) {
+                    whitelist.add(destination)?;
+                }
+            }
+            whitelist.cancel();
+            change
+        };
+
+        if change == WHITELIST_CHANGE_REMOVE_WHITELIST {
+            ctx.accounts
+                .withdrawal_whitelist
+                .close(ctx.accounts.user.to_account_info())?;
+            msg!("Withdrawal whitelist removed for: {:?}", ctx.accounts.user.key());
+        }
+        Ok(())
+    }
+
+    /// Drop the pending change
+    pub fn cancel_withdrawal_whitelist_change(ctx: Context<ManageWithdrawalWhitelist>) -> Result<()> {
+        ctx.accounts.withdrawal_whitelist.load_mut()?.cancel();
+        Ok(())
+    }
+
+    /// Remove `destination` from the whitelist at once; narrowing the list
+    /// needs no delay
+    pub fn remove_withdrawal_destination(
+        ctx: Context<ManageWithdrawalWhitelist>,
+        destination: Pubkey,
+    ) -> Result<()> {
+        let whitelist = &mut ctx.accounts.withdrawal_whitelist.load_mut()?;
+        whitelist.remove(&destination);
+        if whitelist.pending_change == WHITELIST_CHANGE_ADD && whitelist.pending_destination == destination {
+            whitelist.cancel();
+        }
+        Ok(())
+    }
+
+    /// Move the market to another trading state. Switch the engine first:
+    /// under `CancelOnly` only trades matched up to the time of the change
+    /// still settle, which lets the engine's settlement backlog drain.
+    pub fn set_market_status(
+        ctx: Context<SetMarketStatus>,
+        status: offchain_api::MarketStatus,
+    ) -> Result<()> {
+        let timestamp = Clock::get()?.unix_timestamp;
+        let orderbook = &mut ctx.accounts.orderbook.load_mut()?;
+        let previous = orderbook.status;
+        orderbook.status = status.into();
+        orderbook.status_changed_at = timestamp;
+
+        emit!(MarketStatusChanged {
+            orderbook: ctx.accounts.orderbook.key(),
+            previous,
+            status: orderbook.status,
+            timestamp,
+        });
+        Ok(())
+    }
+
+    /// Restrict the order types and times in force the market accepts; an
+    /// empty set accepts every value. The engine checks orders against the
+    /// whitelist when they are placed, so settlement does not: orders that
+    /// rested before a change still settle.
+    pub fn set_order_types(
+        ctx: Context<SetOrderTypes>,
+        whitelist: offchain_api::OrderTypeWhitelist,
+    ) -> Result<()> {
+        let orderbook = &mut ctx.accounts.orderbook.load_mut()?;
+        let previous = offchain_api::OrderTypeWhitelist {
+            order_types: orderbook.order_types,
+            time_in_force: orderbook.time_in_force,
+        };
+        orderbook.order_types = whitelist.order_types;
+        orderbook.time_in_force = whitelist.time_in_force;
+
+        emit!(OrderTypesChanged {
+            orderbook: ctx.accounts.orderbook.key(),
+            previous,
+            whitelist,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Place a user account in a self-match prevention group, or take it out
+    /// with group 0. The account's signer opts in and the orderbook
+    /// authority vouches for the group, so no one can join another firm's
+    /// group to have the engine cancel that firm's orders against theirs.
+    /// The engine only honours groups set through its own market.
+    pub fn set_prevention_group(ctx: Context<SetPreventionGroup>, group: u32) -> Result<()> {
+        let mut user_account = ctx.accounts.user_account.load_mut()?;
+        require!(user_account.signer() == ctx.accounts.user.key(), ClobError::Unauthorized);
+        user_account.prevention_group = group;
+
+        emit!(PreventionGroupSet {
+            orderbook: ctx.accounts.orderbook.key(),
+            owner: user_account.owner,
+            group,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Credit a user account with a plain SPL transfer into the vault that
+    /// did not go through `deposit`, such as one sent from a wallet that only
+    /// knows the vault's address. The program cannot see a past transfer, so
+    /// the orderbook authority vouches for it once it is finalized and is
+    /// trusted outright: nothing bounds the credit by what the vault owes,
+    /// since the vault of a mint is shared by every market. The record keyed
+    /// by the transfer's signature and instruction index keeps one transfer
+    /// from being credited twice.
+    pub fn credit_transfer(
+        ctx: Context<CreditTransfer>,
+        transfer_signature: [u8; 64],
+        instruction_index: u8,
+        amount: u64,
+    ) -> Result<()> {
+        require!(amount > 0, ClobError::InvalidQuantity);
+        let orderbook = ctx.accounts.orderbook.load()?;
+        let mint_key = ctx.accounts.token_mint.key();
+        require!(
+            mint_key == orderbook.base_mint || mint_key == orderbook.quote_mint,
+            ClobError::InvalidMint
+        );
+        let is_base = mint_key == orderbook.base_mint;
+        drop(orderbook);
+
+        let mut user_account = ctx.accounts.user_account.load_mut()?;
+        if is_base {
+            user_account.base_token_balance = user_account
+                .base_token_balance
+                .checked_add(amount)
+                .ok_or(ClobError::InsufficientBalance)?;
+        } else {
+            user_account.quote_token_balance = user_account
+                .quote_token_balance
+                .checked_add(amount)
+                .ok_or(ClobError::InsufficientBalance)?;
+        }
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        let mut credited = ctx.accounts.credited_transfer.load_init()?;
+        credited.owner = user_account.owner;
+        credited.mint = mint_key;
+        credited.amount = amount;
+        credited.credited_at = timestamp;
+
+        emit!(TransferCredited {
+            user: user_account.owner,
+            mint: mint_key,
+            amount,
+            transfer_signature,
+            instruction_index,
+            timestamp,
+        });
+        Ok(())
+    }
+
+    /// Move balances between the maker and taker of a settled trade that an
+    /// operator busted or corrected off-chain, by the orderbook authority.
+    /// Positive amounts go from the taker to the maker, negative ones back;
+    /// a side that no longer holds what it must give up fails the whole
+    /// adjustment, which is applied at most once per `adjustment_id`.
+    pub fn adjust_settlement(
+        ctx: Context<AdjustSettlement>,
+        adjustment_id: u64,
+        trade_id: u64,
+        base_to_maker: i64,
+        quote_to_maker: i64,
+    ) -> Result<()> {
+        require!(base_to_maker != 0 || quote_to_maker != 0, ClobError::InvalidQuantity);
+        let mut maker_user_account = ctx.accounts.maker_user_account.load_mut()?;
+        let mut taker_user_account = ctx.accounts.taker_user_account.load_mut()?;
+
+        shift_balance(
+            &mut taker_user_account.base_token_balance,
+            &mut maker_user_account.base_token_balance,
+            base_to_maker,
+        )?;
+        shift_balance(
+            &mut taker_user_account.quote_token_balance,
+            &mut maker_user_account.quote_token_balance,
+            quote_to_maker,
+        )?;
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        let mut adjustment = ctx.accounts.settlement_adjustment.load_init()?;
+        adjustment.maker = maker_user_account.owner;
+        adjustment.taker = taker_user_account.owner;
+        adjustment.trade_id = trade_id;
+        adjustment.base_to_maker = base_to_maker;
+        adjustment.quote_to_maker = quote_to_maker;
+        adjustment.adjusted_at = timestamp;
+
+        emit!(SettlementAdjusted {
+            adjustment_id,
+            trade_id,
+            maker: maker_user_account.owner,
+            taker: taker_user_account.owner,
+            base_to_maker,
+            quote_to_maker,
+            timestamp,
+        });
+        Ok(())
+    }
+
+    /// Pay quote tokens into the market's insurance fund, creating it on the
+    /// first payment. Anyone may fund it; only the orderbook authority draws.
+    pub fn fund_insurance(ctx: Context<FundInsurance>, amount: u64) -> Result<()> {
+        require!(amount > 0, ClobError::InvalidQuantity);
+
+        let transfer_accounts = Transfer {
+            from: ctx.accounts.funder_token_account.to_account_info(),
+            to: ctx.accounts.insurance_fund.to_account_info(),
+            authority: ctx.accounts.funder.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_accounts,
+        );
+        token::transfer(cpi_ctx, amount)?;
+
+        emit!(InsuranceFunded {
+            orderbook: ctx.accounts.orderbook.key(),
+            funder: ctx.accounts.funder.key(),
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Draw quote tokens from the market's insurance fund to cover a
+    /// settlement shortfall
+    pub fn draw_insurance(ctx: Context<DrawInsurance>, amount: u64) -> Result<()> {
+        require!(amount > 0, ClobError::InvalidQuantity);
+        require!(
+            ctx.accounts.insurance_fund.amount >= amount,
+            ClobError::InsufficientBalance
+        );
+
+        let orderbook_key = ctx.accounts.orderbook.key();
+        let seeds = &[
+            b"insurance_fund".as_ref(),
+            orderbook_key.as_ref(),
+            &[ctx.bumps.insurance_fund],
+        ];
+        let signer = &[&seeds[..]];
+
+        let transfer_accounts = Transfer {
+            from: ctx.accounts.insurance_fund.to_account_info(),
+            to: ctx.accounts.destination.to_account_info(),
+            authority: ctx.accounts.insurance_fund.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_accounts,
+            signer,
+        );
+        token::transfer(cpi_ctx, amount)?;
+
+        emit!(InsuranceDrawn {
+            orderbook: orderbook_key,
+            destination: ctx.accounts.destination.key(),
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Close the fill record of an expired signed order, returning its rent
+    /// to the authority that paid it; the order can no longer settle, so its
+    /// nonce need not be remembered
+    pub fn close_signed_order_fill(ctx: Context<CloseSignedOrderFill>) -> Result<()> {
+        let fill = ctx.accounts.signed_order_fill.load()?;
+        require!(
+            Clock::get()?.unix_timestamp > fill.expiry,
+            ClobError::SignedOrderNotExpired
+        );
+        Ok(())
+    }
+
+    /// Record the market's settled fills as compressed receipts in
+    /// `merkle_tree`, an empty account the authority allocated for SPL
+    /// account compression, or move them on to a new tree once the current
+    /// one is full. From then on `execute_trade` and `settle_match` take the
+    /// receipt tree accounts after their own (see `compressed_receipts`).
+    pub fn initialize_receipt_tree(
+        ctx: Context<InitializeReceiptTree>,
+        max_depth: u32,
+        max_buffer_size: u32,
+    ) -> Result<()> {
+        require!(max_depth <= MAX_RECEIPT_TREE_DEPTH, ClobError::InvalidQuantity);
+        let orderbook_key = ctx.accounts.orderbook.key();
+        let merkle_tree = ctx.accounts.merkle_tree.key();
+        let bump = ctx.bumps.receipt_tree;
+
+        let is_new = ctx.accounts.receipt_tree.as_ref().try_borrow_data()?.starts_with(&[0; 8]);
+        let generation = {
+            let mut tree = if is_new {
+                ctx.accounts.receipt_tree.load_init()?
+            } else {
+                ctx.accounts.receipt_tree.load_mut()?
+            };
+            tree.orderbook = orderbook_key;
+            tree.merkle_tree = merkle_tree;
+            tree.leaves = 0;
+            tree.capacity = 1 << max_depth;
+            tree.max_depth = max_depth;
+            tree.max_buffer_size = max_buffer_size;
+            tree.generation += 1;
+            tree.bump = bump;
+            tree.generation
+        };
+        compressed_receipts::init_empty_tree(
+            &orderbook_key,
+            bump,
+            ctx.accounts.receipt_tree.as_ref(),
+            ctx.accounts.merkle_tree.as_ref(),
+            ctx.accounts.noop.as_ref(),
+            ctx.accounts.compression_program.as_ref(),
+            max_depth,
+            max_buffer_size,
+        )?;
+        ctx.accounts.orderbook.load_mut()?.compressed_receipts = 1;
+
+        emit!(ReceiptTreeInitialized {
+            orderbook: orderbook_key,
+            merkle_tree,
+            max_depth,
+            max_buffer_size,
+            generation,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
         Ok(())
     }
 }
 
+/// Refuse to settle `trade` in a paused market, or in a cancel-only market
+/// when it was matched after the change; `ReduceOnly` is enforced by the
+/// engine, which knows positions, and settles as `Active`
+fn check_settlement_status(orderbook: &OrderBook, trade: &offchain_api::Trade) -> Result<()> {
+    match offchain_api::MarketStatus::try_from(orderbook.status) {
+        Ok(offchain_api::MarketStatus::Active | offchain_api::MarketStatus::ReduceOnly) => Ok(()),
+        Ok(offchain_api::MarketStatus::CancelOnly) => {
+            require!(
+                trade.timestamp <= orderbook.status_changed_at,
+                ClobError::MarketCancelOnly
+            );
+            Ok(())
+        }
+        Ok(offchain_api::MarketStatus::Paused) | Err(_) => err!(ClobError::OrderbookPaused),
+    }
+}
+
+/// Move `amount` from `from` to `to`, or its magnitude the other way when negative
+fn shift_balance(from: &mut u64, to: &mut u64, amount: i64) -> Result<()> {
+    let (from, to) = if amount < 0 { (to, from) } else { (from, to) };
+    let amount = amount.unsigned_abs();
+    *from = from.checked_sub(amount).ok_or(ClobError::InsufficientBalance)?;
+    *to = to.checked_add(amount).ok_or(ClobError::InsufficientBalance)?;
+    Ok(())
+}
+
+/// Move balances and volume for one trade between two user accounts
+fn apply_trade(
+    orderbook: &mut OrderBook,
+    taker_user_account: &mut UserAccount,
+    maker_user_account: &mut UserAccount,
+    trade: &offchain_api::Trade,
+) -> Result<()> {
+    let quote_amount_u128 = u128::from(trade.quantity)
+        .checked_mul(u128::from(trade.price))
+        .ok_or(ClobError::InsufficientBalance)?;
+    let quote_transfer_amount: u64 = u64::try_from(quote_amount_u128)
+        .map_err(|_| ClobError::InsufficientBalance)?;
+
+    if trade.taker_side == offchain_api::OrderSide::Bid {
+        taker_user_account.quote_token_balance = taker_user_account
+            .quote_token_balance
+            .checked_sub(quote_transfer_amount)
+            .ok_or(ClobError::InsufficientBalance)?;
+        taker_user_account.base_token_balance = taker_user_account
+            .base_token_balance
+            .checked_add(trade.quantity)
+            .ok_or(ClobError::InsufficientBalance)?;
+        maker_user_account.quote_token_balance = maker_user_account
+            .quote_token_balance
+            .checked_add(quote_transfer_amount)
+            .ok_or(ClobError::InsufficientBalance)?;
+        maker_user_account.base_token_balance = maker_user_account
+            .base_token_balance
+            .checked_sub(trade.quantity)
+            .ok_or(ClobError::InsufficientBalance)?;
+    } else {
+        taker_user_account.quote_token_balance = taker_user_account
+            .quote_token_balance
+            .checked_add(quote_transfer_amount)
+            .ok_or(ClobError::InsufficientBalance)?;
+        taker_user_account.base_token_balance = taker_user_account
+            .base_token_balance
+            .checked_sub(trade.quantity)
+            .ok_or(ClobError::InsufficientBalance)?;
+        maker_user_account.quote_token_balance = maker_user_account
+            .quote_token_balance
+            .checked_sub(quote_transfer_amount)
+            .ok_or(ClobError::InsufficientBalance)?;
+        maker_user_account.base_token_balance = maker_user_account
+            .base_token_balance
+            .checked_add(trade.quantity)
+            .ok_or(ClobError::InsufficientBalance)?;
+    }
 
+    orderbook.total_volume = orderbook
+        .total_volume
+        .checked_add(trade.quantity)
+        .ok_or(ClobError::InsufficientBalance)?;
+    taker_user_account.total_volume_traded = taker_user_account
+        .total_volume_traded
+        .checked_add(trade.quantity)
+        .ok_or(ClobError::InsufficientBalance)?;
+    maker_user_account.total_volume_traded = maker_user_account
+        .total_volume_traded
+        .checked_add(trade.quantity)
+        .ok_or(ClobError::InsufficientBalance)?;
+
+    Ok(())
+}
 
 #[account(zero_copy)]
 #[repr(C)]
@@ -244,9 +902,112 @@ pub struct OrderBook {
     pub min_order_size: u64,
     pub total_volume: u64,
     pub is_initialized: u8,
-    pub is_paused: u8,
-    pub padding: [u8; 6],
-    pub reserved: [u8; 32],
+    /// `MarketStatus`; 0 and 1 read as before, active and paused
+    pub status: u8,
+    /// 1 once the market appends its fills to a compressed receipt tree
+    pub compressed_receipts: u8,
+    pub padding: [u8; 5],
+    /// When `status` last changed, unix seconds
+    pub status_changed_at: i64,
+    /// `OrderTypeWhitelist` bit sets; zero accepts every value
+    pub order_types: u8,
+    pub time_in_force: u8,
+    pub reserved: [u8; 22],
+}
+
+/// Record a trade in the fill receipts of both sides, skipping a side that
+/// has not created its receipts
+fn record_fill_receipts(
+    orderbook: Pubkey,
+    taker_fill_receipts: &AccountInfo,
+    maker_fill_receipts: &AccountInfo,
+    trade: &offchain_api::Trade,
+    timestamp: i64,
+) -> Result<()> {
+    let (taker_side, maker_side) = match trade.taker_side {
+        offchain_api::OrderSide::Bid => (0, 1),
+        offchain_api::OrderSide::Ask => (1, 0),
+    };
+    let receipt = |order_id, counterparty_order_id, side, is_maker| FillReceipt {
+        orderbook,
+        order_id,
+        counterparty_order_id,
+        price: trade.price,
+        quantity: trade.quantity,
+        timestamp,
+        side,
+        is_maker,
+        padding: [0; 6],
+    };
+    record_fill_receipt(taker_fill_receipts, receipt(trade.taker_order_id, trade.maker_order_id, taker_side, 0))?;
+    record_fill_receipt(maker_fill_receipts, receipt(trade.maker_order_id, trade.taker_order_id, maker_side, 1))
+}
+
+/// Add a settled quantity to a signed order's fill record, creating it on the
+/// order's first fill. The record is keyed by owner and nonce, so settling the
+/// same signature again counts against what is left of its quantity, and a
+/// different order signed with a used nonce is refused.
+fn record_signed_fill(
+    account: &AccountLoader<SignedOrderFill>,
+    signed: &offchain_api::SignedOrder,
+    authority: Pubkey,
+    quantity: u64,
+) -> Result<()> {
+    let message_hash = anchor_lang::solana_program::hash::hash(&signed.message()).to_bytes();
+    let is_new = account.as_ref().try_borrow_data()?.starts_with(&[0; 8]);
+    let mut fill = if is_new {
+        let mut fill = account.load_init()?;
+        fill.owner = signed.order_intent.owner;
+        fill.authority = authority;
+        fill.message_hash = message_hash;
+        fill.nonce = signed.nonce;
+        fill.expiry = signed.expiry;
+        fill
+    } else {
+        account.load_mut()?
+    };
+    require!(fill.message_hash == message_hash, ClobError::SignedOrderNonceReused);
+
+    fill.filled = fill
+        .filled
+        .checked_add(quantity)
+        .filter(|&filled| filled <= signed.order_intent.quantity)
+        .ok_or(ClobError::SignedOrderOverfilled)?;
+    Ok(())
+}
+
+/// Append a receipt to a `FillReceipts` account; the address is already
+/// checked against the owner's PDA, so an account the program does not own
+/// simply has not been created yet
+fn record_fill_receipt(account: &AccountInfo, receipt: FillReceipt) -> Result<()> {
+    if account.owner != &crate::ID || account.data_is_empty() {
+        return Ok(());
+    }
+    let mut data = account.try_borrow_mut_data()?;
+    require!(
+        data.len() >= FILL_RECEIPTS_ACCOUNT_SIZE && data.starts_with(FillReceipts::DISCRIMINATOR),
+        AnchorErrorCode::AccountDiscriminatorMismatch
+    );
+    let fill_receipts: &mut FillReceipts = bytemuck::from_bytes_mut(&mut data[8..FILL_RECEIPTS_ACCOUNT_SIZE]);
+    fill_receipts.record(receipt);
+    Ok(())
+}
+
+/// Check a withdrawal's destination against the owner's whitelist; the
+/// address is already checked against the owner's PDA, so an account the
+/// program does not own means the owner has no whitelist
+fn check_withdrawal_destination(whitelist: &AccountInfo, destination: &Pubkey) -> Result<()> {
+    if whitelist.owner != &crate::ID || whitelist.data_is_empty() {
+        return Ok(());
+    }
+    let data = whitelist.try_borrow_data()?;
+    require!(
+        data.len() >= WITHDRAWAL_WHITELIST_ACCOUNT_SIZE && data.starts_with(WithdrawalWhitelist::DISCRIMINATOR),
+        AnchorErrorCode::AccountDiscriminatorMismatch
+    );
+    let whitelist: &WithdrawalWhitelist = bytemuck::from_bytes(&data[8..WITHDRAWAL_WHITELIST_ACCOUNT_SIZE]);
+    require!(whitelist.contains(destination), ClobError::DestinationNotWhitelisted);
+    Ok(())
 }
 
 #[account(zero_copy)]
@@ -258,13 +1019,190 @@ pub struct UserAccount {
     pub base_token_balance: u64,
     pub quote_token_balance: u64,
     pub is_initialized: u8,
-    pub padding: [u8; 7],
-    pub reserved: [u8; 32],
+    pub padding: [u8; 1],
+    /// Index among the wallet's sub-accounts; 0 for the wallet's own account
+    pub sub_account: u16,
+    /// Self-match prevention group the account is in; 0 for none
+    pub prevention_group: u32,
+    /// Wallet a sub-account belongs to; the default key for the wallet's own
+    /// account, whose owner is the wallet
+    pub wallet: Pubkey,
 }
 
-#[event]
-pub struct TradeSettled {
-    pub taker_order_id: u64,
+impl UserAccount {
+    /// Key that signs for the account: the owner, or a sub-account's wallet
+    pub fn signer(&self) -> Pubkey {
+        if self.wallet == Pubkey::default() {
+            self.owner
+        } else {
+            self.wallet
+        }
+    }
+}
+
+/// One fill as seen by one side of it
+#[zero_copy]
+#[repr(C)]
+#[derive(Debug)]
+pub struct FillReceipt {
+    pub orderbook: Pubkey,
+    /// The owner's order
+    pub order_id: u64,
+    pub counterparty_order_id: u64,
+    pub price: u64,
+    pub quantity: u64,
+    pub timestamp: i64,
+    /// Side of the owner's order: 0 = Bid, 1 = Ask
+    pub side: u8,
+    /// 1 when the owner's order was resting
+    pub is_maker: u8,
+    pub padding: [u8; 6],
+}
+
+/// A user's most recent settled fills, in a ring of `FILL_RECEIPTS_CAPACITY`
+#[account(zero_copy)]
+#[repr(C)]
+#[derive(Debug)]
+pub struct FillReceipts {
+    pub owner: Pubkey,
+    /// Fills recorded so far; the newest is at `(total_fills - 1) % FILL_RECEIPTS_CAPACITY`
+    pub total_fills: u64,
+    pub receipts: [FillReceipt; FILL_RECEIPTS_CAPACITY],
+}
+
+impl FillReceipts {
+    /// Record a fill, overwriting the oldest once the ring is full
+    pub fn record(&mut self, receipt: FillReceipt) {
+        let slot = (self.total_fills % FILL_RECEIPTS_CAPACITY as u64) as usize;
+        self.receipts[slot] = receipt;
+        self.total_fills += 1;
+    }
+}
+
+/// Quantity settled so far against one signed order, keyed by its owner and
+/// nonce so the signature cannot be settled beyond its quantity
+#[account(zero_copy)]
+#[repr(C)]
+#[derive(Debug)]
+pub struct SignedOrderFill {
+    pub owner: Pubkey,
+    /// Authority that paid for the record and gets its rent back on close
+    pub authority: Pubkey,
+    /// Hash of the signed message the nonce was first used for
+    pub message_hash: [u8; 32],
+    pub nonce: u64,
+    pub filled: u64,
+    /// Expiry of the signature; the record can be closed after it
+    pub expiry: i64,
+}
+
+/// Plain transfer into a vault that was credited to a user account, keyed by
+/// the transfer's signature and instruction index
+#[account(zero_copy)]
+#[repr(C)]
+#[derive(Debug)]
+pub struct CreditedTransfer {
+    /// Owner of the credited user account
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub credited_at: i64,
+}
+
+/// Balances moved back between the parties of a busted or corrected trade,
+/// keyed by the orderbook and the off-chain adjustment ID
+#[account(zero_copy)]
+#[repr(C)]
+#[derive(Debug)]
+pub struct SettlementAdjustment {
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub trade_id: u64,
+    /// Base and quote moved from the taker to the maker; negative the other way
+    pub base_to_maker: i64,
+    pub quote_to_maker: i64,
+    pub adjusted_at: i64,
+}
+
+/// Where a market's compressed fill receipts go: the concurrent Merkle tree
+/// appended to, whose authority this account is, and how full it is
+#[account(zero_copy)]
+#[repr(C)]
+#[derive(Debug)]
+pub struct ReceiptTree {
+    pub orderbook: Pubkey,
+    pub merkle_tree: Pubkey,
+    /// Receipts appended to `merkle_tree`; the next one's leaf index
+    pub leaves: u64,
+    /// Leaves `merkle_tree` holds, 2^`max_depth`
+    pub capacity: u64,
+    pub max_depth: u32,
+    pub max_buffer_size: u32,
+    /// Merkle trees the market has used, this one included
+    pub generation: u32,
+    pub bump: u8,
+    pub padding: [u8; 3],
+}
+
+/// Token accounts a user's withdrawals may pay to, with at most one pending
+/// change waiting out the delay
+#[account(zero_copy)]
+#[repr(C)]
+#[derive(Debug)]
+pub struct WithdrawalWhitelist {
+    pub owner: Pubkey,
+    /// Seconds a requested change waits before it can be applied
+    pub delay_secs: i64,
+    pub destinations: [Pubkey; WITHDRAWAL_WHITELIST_CAPACITY],
+    /// Leading entries of `destinations` in use
+    pub destination_count: u64,
+    /// Destination of a pending addition
+    pub pending_destination: Pubkey,
+    /// When the pending change can be applied
+    pub pending_effective_at: i64,
+    /// One of the `WHITELIST_CHANGE_*` values
+    pub pending_change: u8,
+    pub padding: [u8; 7],
+}
+
+impl WithdrawalWhitelist {
+    pub fn contains(&self, destination: &Pubkey) -> bool {
+        self.destinations[..self.destination_count as usize].contains(destination)
+    }
+
+    fn add(&mut self, destination: Pubkey) -> Result<()> {
+        let count = self.destination_count as usize;
+        require!(count < WITHDRAWAL_WHITELIST_CAPACITY, ClobError::WhitelistFull);
+        self.destinations[count] = destination;
+        self.destination_count += 1;
+        Ok(())
+    }
+
+    fn remove(&mut self, destination: &Pubkey) {
+        let count = self.destination_count as usize;
+        if let Some(index) = self.destinations[..count].iter().position(|entry| entry == destination) {
+            self.destinations[index] = self.destinations[count - 1];
+            self.destinations[count - 1] = Pubkey::default();
+            self.destination_count -= 1;
+        }
+    }
+
+    fn request(&mut self, change: u8, destination: Pubkey, now: i64) {
+        self.pending_change = change;
+        self.pending_destination = destination;
+        self.pending_effective_at = now.saturating_add(self.delay_secs);
+    }
+
+    fn cancel(&mut self) {
+        self.pending_change = WHITELIST_CHANGE_NONE;
+        self.pending_destination = Pubkey::default();
+        self.pending_effective_at = 0;
+    }
+}
+
+#[event]
+pub struct TradeSettled {
+    pub taker_order_id: u64,
     pub maker_order_id: u64,
     pub taker: Pubkey,
     pub maker: Pubkey,
@@ -273,6 +1211,142 @@ pub struct TradeSettled {
     pub timestamp: i64,
 }
 
+#[event]
+pub struct DepositMade {
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalMade {
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SubAccountCreated {
+    pub wallet: Pubkey,
+    /// Key the sub-account trades as
+    pub sub_account: Pubkey,
+    pub index: u16,
+    pub name: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SubAccountTransfer {
+    pub wallet: Pubkey,
+    /// Owners of the user accounts debited and credited
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub base_amount: u64,
+    pub quote_amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalWhitelistChangeRequested {
+    pub owner: Pubkey,
+    /// Destination to add; the default key when the request removes the whitelist
+    pub destination: Pubkey,
+    pub removes_whitelist: bool,
+    pub effective_at: i64,
+}
+
+#[event]
+pub struct InsuranceFunded {
+    pub orderbook: Pubkey,
+    pub funder: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct InsuranceDrawn {
+    pub orderbook: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketStatusChanged {
+    pub orderbook: Pubkey,
+    /// `MarketStatus` bytes before and after the change
+    pub previous: u8,
+    pub status: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OrderTypesChanged {
+    pub orderbook: Pubkey,
+    pub previous: offchain_api::OrderTypeWhitelist,
+    pub whitelist: offchain_api::OrderTypeWhitelist,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PreventionGroupSet {
+    /// Market whose authority vouched for the group
+    pub orderbook: Pubkey,
+    /// Owner of the user account
+    pub owner: Pubkey,
+    /// 0 when the account left its group
+    pub group: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TransferCredited {
+    /// Owner of the credited user account
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    /// Transaction and top-level instruction of the transfer
+    pub transfer_signature: [u8; 64],
+    pub instruction_index: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SettlementAdjusted {
+    pub adjustment_id: u64,
+    pub trade_id: u64,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    /// Base and quote moved from the taker to the maker; negative the other way
+    pub base_to_maker: i64,
+    pub quote_to_maker: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReceiptTreeInitialized {
+    pub orderbook: Pubkey,
+    pub merkle_tree: Pubkey,
+    pub max_depth: u32,
+    pub max_buffer_size: u32,
+    /// Merkle trees the market has used, this one included
+    pub generation: u32,
+    pub timestamp: i64,
+}
+
+/// Receipts of one settled fill appended to a market's tree: the taker's at
+/// `first_leaf_index`, the maker's at the next leaf
+#[event]
+pub struct ReceiptsAppended {
+    pub orderbook: Pubkey,
+    pub merkle_tree: Pubkey,
+    pub first_leaf_index: u64,
+    pub trade: offchain_api::Trade,
+    pub timestamp: i64,
+}
+
 #[derive(Accounts)]
 pub struct InitializeOrderbook<'info> {
     #[account(
@@ -305,6 +1379,52 @@ pub struct InitializeUserAccount<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+#[instruction(index: u16)]
+pub struct InitializeSubAccount<'info> {
+    /// CHECK: the key the sub-account trades as; an address only, holding no data
+    #[account(
+        seeds = [b"sub_account", user.key().as_ref(), &index.to_le_bytes()],
+        bump
+    )]
+    pub sub_account: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = user,
+        space = USER_ACCOUNT_SIZE,
+        seeds = [b"user_account", sub_account.key().as_ref()],
+        bump
+    )]
+    pub user_account: AccountLoader<'info, UserAccount>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TransferBetweenSubAccounts<'info> {
+    #[account(mut)]
+    pub from: AccountLoader<'info, UserAccount>,
+    #[account(mut)]
+    pub to: AccountLoader<'info, UserAccount>,
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeFillReceipts<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = FILL_RECEIPTS_ACCOUNT_SIZE,
+        seeds = [b"fill_receipts", user.key().as_ref()],
+        bump
+    )]
+    pub fill_receipts: AccountLoader<'info, FillReceipts>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 pub struct ExecuteTrade<'info> {
     #[account(
@@ -328,6 +1448,80 @@ pub struct ExecuteTrade<'info> {
     pub maker_user_account: AccountLoader<'info, UserAccount>,
     #[account(mut)]
     pub authority: Signer<'info>,
+    /// CHECK: the taker's fill receipts PDA, written only once the taker created it
+    #[account(
+        mut,
+        seeds = [b"fill_receipts", taker_user_account.load()?.owner.as_ref()],
+        bump
+    )]
+    pub taker_fill_receipts: UncheckedAccount<'info>,
+    /// CHECK: the maker's fill receipts PDA, as `taker_fill_receipts`
+    #[account(
+        mut,
+        seeds = [b"fill_receipts", maker_user_account.load()?.owner.as_ref()],
+        bump
+    )]
+    pub maker_fill_receipts: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(trade: offchain_api::Trade, taker_order: offchain_api::SignedOrder, maker_order: offchain_api::SignedOrder)]
+pub struct SettleMatch<'info> {
+    #[account(
+        mut,
+        seeds = [b"orderbook", orderbook.load()?.base_mint.as_ref(), orderbook.load()?.quote_mint.as_ref()],
+        bump,
+        constraint = orderbook.load()?.authority == authority.key()
+    )]
+    pub orderbook: AccountLoader<'info, OrderBook>,
+    #[account(
+        mut,
+        seeds = [b"user_account", taker_user_account.load()?.owner.as_ref()],
+        bump
+    )]
+    pub taker_user_account: AccountLoader<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"user_account", maker_user_account.load()?.owner.as_ref()],
+        bump
+    )]
+    pub maker_user_account: AccountLoader<'info, UserAccount>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: the instructions sysvar, checked by address
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+    /// CHECK: the taker's fill receipts PDA, written only once the taker created it
+    #[account(
+        mut,
+        seeds = [b"fill_receipts", taker_user_account.load()?.owner.as_ref()],
+        bump
+    )]
+    pub taker_fill_receipts: UncheckedAccount<'info>,
+    /// CHECK: the maker's fill receipts PDA, as `taker_fill_receipts`
+    #[account(
+        mut,
+        seeds = [b"fill_receipts", maker_user_account.load()?.owner.as_ref()],
+        bump
+    )]
+    pub maker_fill_receipts: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = SIGNED_ORDER_FILL_ACCOUNT_SIZE,
+        seeds = [b"signed_order", taker_order.order_intent.owner.as_ref(), &taker_order.nonce.to_le_bytes()],
+        bump
+    )]
+    pub taker_order_fill: AccountLoader<'info, SignedOrderFill>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = SIGNED_ORDER_FILL_ACCOUNT_SIZE,
+        seeds = [b"signed_order", maker_order.order_intent.owner.as_ref(), &maker_order.nonce.to_le_bytes()],
+        bump
+    )]
+    pub maker_order_fill: AccountLoader<'info, SignedOrderFill>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
@@ -371,9 +1565,195 @@ pub struct Withdraw<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
     pub token_program: Program<'info, Token>,
+    /// CHECK: the withdrawal whitelist PDA of the account's owner, or of a
+    /// sub-account's wallet, enforced only once created
+    #[account(
+        seeds = [b"withdrawal_whitelist", user_account.load()?.signer().as_ref()],
+        bump
+    )]
+    pub withdrawal_whitelist: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeWithdrawalWhitelist<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = WITHDRAWAL_WHITELIST_ACCOUNT_SIZE,
+        seeds = [b"withdrawal_whitelist", user.key().as_ref()],
+        bump
+    )]
+    pub withdrawal_whitelist: AccountLoader<'info, WithdrawalWhitelist>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ManageWithdrawalWhitelist<'info> {
+    #[account(
+        mut,
+        seeds = [b"withdrawal_whitelist", user.key().as_ref()],
+        bump
+    )]
+    pub withdrawal_whitelist: AccountLoader<'info, WithdrawalWhitelist>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FundInsurance<'info> {
+    pub orderbook: AccountLoader<'info, OrderBook>,
+    #[account(mut)]
+    pub funder_token_account: Account<'info, TokenAccount>,
+    #[account(address = orderbook.load()?.quote_mint @ ClobError::InvalidMint)]
+    pub quote_mint: Account<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = funder,
+        token::mint = quote_mint,
+        token::authority = insurance_fund,
+        seeds = [b"insurance_fund", orderbook.key().as_ref()],
+        bump
+    )]
+    pub insurance_fund: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub funder: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct SetMarketStatus<'info> {
+    #[account(mut, constraint = orderbook.load()?.authority == authority.key() @ ClobError::InvalidAuthority)]
+    pub orderbook: AccountLoader<'info, OrderBook>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetOrderTypes<'info> {
+    #[account(mut, constraint = orderbook.load()?.authority == authority.key() @ ClobError::InvalidAuthority)]
+    pub orderbook: AccountLoader<'info, OrderBook>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPreventionGroup<'info> {
+    #[account(constraint = orderbook.load()?.authority == authority.key() @ ClobError::InvalidAuthority)]
+    pub orderbook: AccountLoader<'info, OrderBook>,
+    #[account(mut)]
+    pub user_account: AccountLoader<'info, UserAccount>,
+    pub user: Signer<'info>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseSignedOrderFill<'info> {
+    #[account(mut, has_one = authority @ ClobError::InvalidAuthority, close = authority)]
+    pub signed_order_fill: AccountLoader<'info, SignedOrderFill>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(transfer_signature: [u8; 64], instruction_index: u8)]
+pub struct CreditTransfer<'info> {
+    #[account(constraint = orderbook.load()?.authority == authority.key() @ ClobError::InvalidAuthority)]
+    pub orderbook: AccountLoader<'info, OrderBook>,
+    #[account(mut)]
+    pub user_account: AccountLoader<'info, UserAccount>,
+    pub token_mint: Account<'info, Mint>,
+    #[account(
+        seeds = [b"clob_vault", token_mint.key().as_ref()],
+        bump
+    )]
+    pub clob_token_vault: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = authority,
+        space = CREDITED_TRANSFER_ACCOUNT_SIZE,
+        seeds = [
+            b"credited_transfer",
+            transfer_signature[..32].as_ref(),
+            transfer_signature[32..].as_ref(),
+            &[instruction_index],
+        ],
+        bump
+    )]
+    pub credited_transfer: AccountLoader<'info, CreditedTransfer>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(adjustment_id: u64)]
+pub struct AdjustSettlement<'info> {
+    #[account(constraint = orderbook.load()?.authority == authority.key() @ ClobError::InvalidAuthority)]
+    pub orderbook: AccountLoader<'info, OrderBook>,
+    #[account(mut)]
+    pub maker_user_account: AccountLoader<'info, UserAccount>,
+    #[account(mut)]
+    pub taker_user_account: AccountLoader<'info, UserAccount>,
+    #[account(
+        init,
+        payer = authority,
+        space = SETTLEMENT_ADJUSTMENT_ACCOUNT_SIZE,
+        seeds = [b"settlement_adjustment", orderbook.key().as_ref(), &adjustment_id.to_le_bytes()],
+        bump
+    )]
+    pub settlement_adjustment: AccountLoader<'info, SettlementAdjustment>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeReceiptTree<'info> {
+    #[account(mut, constraint = orderbook.load()?.authority == authority.key() @ ClobError::InvalidAuthority)]
+    pub orderbook: AccountLoader<'info, OrderBook>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = RECEIPT_TREE_ACCOUNT_SIZE,
+        seeds = [b"receipt_tree", orderbook.key().as_ref()],
+        bump
+    )]
+    pub receipt_tree: AccountLoader<'info, ReceiptTree>,
+    /// CHECK: allocated for account compression, which checks its size and
+    /// that it holds no tree yet
+    #[account(mut, owner = SPL_ACCOUNT_COMPRESSION_ID)]
+    pub merkle_tree: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: the noop program, checked by address
+    #[account(address = SPL_NOOP_ID)]
+    pub noop: UncheckedAccount<'info>,
+    /// CHECK: account compression, checked by address
+    #[account(address = SPL_ACCOUNT_COMPRESSION_ID)]
+    pub compression_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DrawInsurance<'info> {
+    #[account(constraint = orderbook.load()?.authority == authority.key() @ ClobError::InvalidAuthority)]
+    pub orderbook: AccountLoader<'info, OrderBook>,
+    #[account(
+        mut,
+        seeds = [b"insurance_fund", orderbook.key().as_ref()],
+        bump
+    )]
+    pub insurance_fund: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub destination: Account<'info, TokenAccount>,
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
 }
 
 
+// Off-chain `svm_clob_types::error_codes` reuses these numbers; only append
 #[error_code]
 pub enum ClobError {
     #[msg("Invalid price")]
@@ -396,4 +1776,30 @@ pub enum ClobError {
     InvalidAuthority,
     #[msg("Slippage tolerance exceeded")]
     SlippageExceeded,
+    #[msg("Invalid order signature")]
+    InvalidOrderSignature,
+    #[msg("Signed order expired")]
+    SignedOrderExpired,
+    #[msg("Trade does not match the signed orders")]
+    OrderIntentMismatch,
+    #[msg("Withdrawal destination is not whitelisted")]
+    DestinationNotWhitelisted,
+    #[msg("No whitelist change is ready to apply")]
+    WhitelistChangeNotReady,
+    #[msg("Withdrawal whitelist is full")]
+    WhitelistFull,
+    #[msg("Market only accepts cancellations")]
+    MarketCancelOnly,
+    #[msg("Signed order would fill beyond its quantity")]
+    SignedOrderOverfilled,
+    #[msg("Nonce already used for a different signed order")]
+    SignedOrderNonceReused,
+    #[msg("Signed order has not expired")]
+    SignedOrderNotExpired,
+    #[msg("Invalid sub-account")]
+    InvalidSubAccount,
+    #[msg("Market keeps compressed receipts; pass its receipt tree")]
+    ReceiptTreeRequired,
+    #[msg("Receipt tree is full")]
+    ReceiptTreeFull,
 }
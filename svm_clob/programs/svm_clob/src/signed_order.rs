@@ -0,0 +1,86 @@
+//! Verification of signed orders for `settle_match`.
+//!
+//! Programs cannot check ed25519 signatures themselves; the settling
+//! transaction carries Ed25519 program instructions that verify each order's
+//! signature, and this module finds them through the instructions sysvar and
+//! checks they cover exactly the order's signer, signature and message. The
+//! signer is the order's owner, or the wallet of a sub-account owner.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::sysvar::instructions::{load_current_index_checked, load_instruction_at_checked};
+
+use crate::offchain_api::{OrderIntent, OrderSide, SignedOrder, Trade};
+use crate::ClobError;
+
+/// Size of one signature's offsets entry in an Ed25519 program instruction
+const OFFSETS_LEN: usize = 14;
+/// Offsets entries start after the signature count and a padding byte
+const OFFSETS_START: usize = 2;
+
+/// Check that an earlier instruction of this transaction verified the
+/// order's signature by `signer` and that the order has not expired
+pub fn verify(instructions: &AccountInfo, signed: &SignedOrder, signer: &Pubkey, now: i64) -> Result<()> {
+    require!(!signed.is_expired(now), ClobError::SignedOrderExpired);
+
+    let message = signed.message();
+    let current = load_current_index_checked(instructions)?;
+    for index in 0..current {
+        let instruction = load_instruction_at_checked(index as usize, instructions)?;
+        if instruction.program_id == ed25519_program::ID
+            && verifies(&instruction.data, index, signer, &signed.signature, &message)
+        {
+            return Ok(());
+        }
+    }
+    err!(ClobError::InvalidOrderSignature)
+}
+
+/// Whether an Ed25519 program instruction at `index` verifies `signature` by
+/// `owner` over `message`, with all three read from its own data
+fn verifies(data: &[u8], index: u16, owner: &Pubkey, signature: &[u8; 64], message: &[u8]) -> bool {
+    let Some(&count) = data.first() else {
+        return false;
+    };
+    (0..count as usize).any(|entry| {
+        let start = OFFSETS_START + entry * OFFSETS_LEN;
+        let Some(offsets) = data.get(start..start + OFFSETS_LEN) else {
+            return false;
+        };
+        let field = |at: usize| u16::from_le_bytes([offsets[at], offsets[at + 1]]);
+        let in_this_instruction = |ix: u16| ix == u16::MAX || ix == index;
+        let slice = |offset: u16, len: usize| data.get(offset as usize..offset as usize + len);
+
+        in_this_instruction(field(2))
+            && in_this_instruction(field(6))
+            && in_this_instruction(field(12))
+            && field(10) as usize == message.len()
+            && slice(field(0), 64) == Some(&signature[..])
+            && slice(field(4), 32) == Some(owner.as_ref())
+            && slice(field(8), message.len()) == Some(message)
+    })
+}
+
+/// Check that the trade is one both signed orders allow on this market: the
+/// owners and sides line up, the maker trades at its own price, the taker
+/// no worse than its limit, and neither beyond its quantity
+pub fn check_match(market: &Pubkey, trade: &Trade, taker: &OrderIntent, maker: &OrderIntent) -> Result<()> {
+    let within_taker_limit = match taker.side {
+        OrderSide::Bid => trade.price <= taker.price,
+        OrderSide::Ask => trade.price >= taker.price,
+    };
+    require!(
+        taker.market == *market
+            && maker.market == *market
+            && taker.owner == trade.taker
+            && maker.owner == trade.maker
+            && taker.side == trade.taker_side
+            && maker.side == trade.taker_side.opposite()
+            && maker.price == trade.price
+            && within_taker_limit
+            && trade.quantity <= taker.quantity
+            && trade.quantity <= maker.quantity,
+        ClobError::OrderIntentMismatch
+    );
+    Ok(())
+}
@@ -0,0 +1,1769 @@
+//! Program tests for `svm_clob`
+//!
+//! Drives the compiled program through `solana-program-test`: market and user
+//! setup, deposits into the vaults, settlement of off-chain matches, and
+//! withdrawals, withdrawal whitelists, the insurance fund, market status
+//! changes, replay protection of signed orders, sub-accounts, credits of plain
+//! transfers into the vaults, adjustments of busted trades and the checks
+//! around compressed receipt trees, plus the rejection paths of every
+//! instruction. Orders are placed, matched and cancelled by the off-chain
+//! engine, so on chain the lifecycle ends at `execute_trade`, or
+//! `settle_match` for signed orders.
+//! The fills of the golden scenarios in `svm_clob_infra/crates/core/golden`,
+//! which the off-chain engine's tests reproduce, are settled here against the
+//! same orders, signed, so both matching paths answer to one expected result.
+//!
+//! Run with `cargo test-sbf`, which builds `svm_clob.so` and points the test
+//! runtime at it.
+
+use anchor_lang::error::ErrorCode as AnchorErrorCode;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use serde::Deserialize;
+use solana_program_test::{BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::clock::Clock;
+use solana_sdk::instruction::{AccountMeta, Instruction, InstructionError};
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use solana_sdk::{system_instruction, system_program, sysvar};
+use svm_clob::offchain_api::{
+    MarketStatus, OrderIntent, OrderSide, OrderType, OrderTypeWhitelist, SelfTradeBehavior, SignedOrder, TimeInForce, Trade,
+};
+use svm_clob::compressed_receipts::{SPL_ACCOUNT_COMPRESSION_ID, SPL_NOOP_ID};
+use svm_clob::{ClobError, FillReceipts, OrderBook, SignedOrderFill, UserAccount, FILL_RECEIPTS_CAPACITY};
+
+const WHITELIST_DELAY_SECS: u32 = 3600;
+
+const LAMPORTS: u64 = 10_000_000_000;
+const BASE_DEPOSIT: u64 = 50;
+const QUOTE_DEPOSIT: u64 = 100;
+const TRADE_PRICE: u64 = 4;
+const TRADE_QUANTITY: u64 = 10;
+
+struct Market {
+    ctx: ProgramTestContext,
+    authority: Keypair,
+    maker: Keypair,
+    taker: Keypair,
+    base_mint: Pubkey,
+    quote_mint: Pubkey,
+    orderbook: Pubkey,
+    maker_account: Pubkey,
+    taker_account: Pubkey,
+    maker_base: Pubkey,
+    maker_quote: Pubkey,
+    taker_base: Pubkey,
+    taker_quote: Pubkey,
+}
+
+fn orderbook_address(base_mint: &Pubkey, quote_mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"orderbook", base_mint.as_ref(), quote_mint.as_ref()], &svm_clob::ID).0
+}
+
+fn user_account_address(owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"user_account", owner.as_ref()], &svm_clob::ID).0
+}
+
+fn sub_account_address(wallet: &Pubkey, index: u16) -> Pubkey {
+    Pubkey::find_program_address(&[b"sub_account", wallet.as_ref(), &index.to_le_bytes()], &svm_clob::ID).0
+}
+
+fn fill_receipts_address(owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"fill_receipts", owner.as_ref()], &svm_clob::ID).0
+}
+
+fn signed_order_fill_address(order: &SignedOrder) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"signed_order", order.order_intent.owner.as_ref(), &order.nonce.to_le_bytes()],
+        &svm_clob::ID,
+    )
+    .0
+}
+
+fn vault_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"clob_vault", mint.as_ref()], &svm_clob::ID).0
+}
+
+fn withdrawal_whitelist_address(owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"withdrawal_whitelist", owner.as_ref()], &svm_clob::ID).0
+}
+
+fn insurance_fund_address(orderbook: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"insurance_fund", orderbook.as_ref()], &svm_clob::ID).0
+}
+
+fn credited_transfer_address(transfer_signature: &[u8; 64], instruction_index: u8) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"credited_transfer", &transfer_signature[..32], &transfer_signature[32..], &[instruction_index]],
+        &svm_clob::ID,
+    )
+    .0
+}
+
+fn settlement_adjustment_address(orderbook: &Pubkey, adjustment_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"settlement_adjustment", orderbook.as_ref(), &adjustment_id.to_le_bytes()],
+        &svm_clob::ID,
+    )
+    .0
+}
+
+fn receipt_tree_address(orderbook: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"receipt_tree", orderbook.as_ref()], &svm_clob::ID).0
+}
+
+fn funded() -> Account {
+    Account::new(LAMPORTS, 0, &system_program::ID)
+}
+
+fn program_test() -> ProgramTest {
+    let mut program_test = ProgramTest::new("svm_clob", svm_clob::ID, None);
+    program_test.prefer_bpf(true);
+    program_test
+}
+
+async fn send(ctx: &mut ProgramTestContext, instructions: &[Instruction], signers: &[&Keypair]) -> Result<(), BanksClientError> {
+    let blockhash = ctx.banks_client.get_latest_blockhash().await.unwrap();
+    let mut all_signers = vec![&ctx.payer];
+    all_signers.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(instructions, Some(&ctx.payer.pubkey()), &all_signers, blockhash);
+    ctx.banks_client.process_transaction(transaction).await
+}
+
+/// Move the clock forward by `seconds`, then wait for a new blockhash so that
+/// a retried transaction is not a duplicate of the rejected one
+async fn advance_clock(ctx: &mut ProgramTestContext, seconds: i64) {
+    let mut clock: Clock = ctx.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp += seconds;
+    ctx.set_sysvar(&clock);
+    ctx.get_new_latest_blockhash().await.unwrap();
+}
+
+/// Assert that the first instruction of a transaction failed with `code`
+fn assert_error(result: Result<(), BanksClientError>, code: u32) {
+    assert_error_at(result, 0, code);
+}
+
+/// Assert that instruction `index` of a transaction failed with `code`
+fn assert_error_at(result: Result<(), BanksClientError>, index: u8, code: u32) {
+    assert_eq!(
+        result.expect_err("transaction should have failed").unwrap(),
+        TransactionError::InstructionError(index, InstructionError::Custom(code)),
+    );
+}
+
+async fn create_mint(ctx: &mut ProgramTestContext, authority: &Keypair) -> Pubkey {
+    let mint = Keypair::new();
+    let rent = ctx.banks_client.get_rent().await.unwrap();
+    let instructions = [
+        system_instruction::create_account(
+            &ctx.payer.pubkey(),
+            &mint.pubkey(),
+            rent.minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::ID,
+        ),
+        spl_token::instruction::initialize_mint(&spl_token::ID, &mint.pubkey(), &authority.pubkey(), None, 0).unwrap(),
+    ];
+    send(ctx, &instructions, &[&mint]).await.unwrap();
+    mint.pubkey()
+}
+
+async fn create_token_account(ctx: &mut ProgramTestContext, mint: &Pubkey, owner: &Pubkey) -> Pubkey {
+    let account = Keypair::new();
+    let rent = ctx.banks_client.get_rent().await.unwrap();
+    let instructions = [
+        system_instruction::create_account(
+            &ctx.payer.pubkey(),
+            &account.pubkey(),
+            rent.minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN as u64,
+            &spl_token::ID,
+        ),
+        spl_token::instruction::initialize_account(&spl_token::ID, &account.pubkey(), mint, owner).unwrap(),
+    ];
+    send(ctx, &instructions, &[&account]).await.unwrap();
+    account.pubkey()
+}
+
+async fn mint_to(ctx: &mut ProgramTestContext, mint: &Pubkey, account: &Pubkey, authority: &Keypair, amount: u64) {
+    let instruction = spl_token::instruction::mint_to(&spl_token::ID, mint, account, &authority.pubkey(), &[], amount).unwrap();
+    send(ctx, &[instruction], &[authority]).await.unwrap();
+}
+
+async fn token_balance(ctx: &mut ProgramTestContext, account: &Pubkey) -> u64 {
+    let account = ctx.banks_client.get_account(*account).await.unwrap().expect("token account exists");
+    spl_token::state::Account::unpack(&account.data).unwrap().amount
+}
+
+async fn user_account(ctx: &mut ProgramTestContext, address: &Pubkey) -> UserAccount {
+    let account = ctx.banks_client.get_account(*address).await.unwrap().expect("user account exists");
+    bytemuck::pod_read_unaligned(&account.data[8..])
+}
+
+async fn fill_receipts(ctx: &mut ProgramTestContext, owner: &Pubkey) -> FillReceipts {
+    let account = ctx.banks_client.get_account(fill_receipts_address(owner)).await.unwrap().expect("fill receipts exist");
+    bytemuck::pod_read_unaligned(&account.data[8..])
+}
+
+async fn signed_order_fill(ctx: &mut ProgramTestContext, order: &SignedOrder) -> SignedOrderFill {
+    let account = ctx.banks_client.get_account(signed_order_fill_address(order)).await.unwrap().expect("fill record exists");
+    bytemuck::pod_read_unaligned(&account.data[8..])
+}
+
+async fn orderbook(ctx: &mut ProgramTestContext, address: &Pubkey) -> OrderBook {
+    let account = ctx.banks_client.get_account(*address).await.unwrap().expect("orderbook exists");
+    bytemuck::pod_read_unaligned(&account.data[8..])
+}
+
+fn initialize_orderbook_ix(authority: &Pubkey, base_mint: &Pubkey, quote_mint: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: svm_clob::ID,
+        accounts: svm_clob::accounts::InitializeOrderbook {
+            orderbook: orderbook_address(base_mint, quote_mint),
+            authority: *authority,
+            base_mint: *base_mint,
+            quote_mint: *quote_mint,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: svm_clob::instruction::InitializeOrderbook {
+            base_mint: *base_mint,
+            quote_mint: *quote_mint,
+            tick_size: 1,
+            min_order_size: 1,
+            authority: *authority,
+        }
+        .data(),
+    }
+}
+
+fn initialize_user_account_ix(user: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: svm_clob::ID,
+        accounts: svm_clob::accounts::InitializeUserAccount {
+            user_account: user_account_address(user),
+            user: *user,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: svm_clob::instruction::InitializeUserAccount {}.data(),
+    }
+}
+
+fn initialize_sub_account_ix(user: &Pubkey, index: u16, name: &str) -> Instruction {
+    let sub_account = sub_account_address(user, index);
+    Instruction {
+        program_id: svm_clob::ID,
+        accounts: svm_clob::accounts::InitializeSubAccount {
+            sub_account,
+            user_account: user_account_address(&sub_account),
+            user: *user,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: svm_clob::instruction::InitializeSubAccount { index, name: name.to_string() }.data(),
+    }
+}
+
+/// Transfer between the user accounts owned by `from` and `to`, signed by `user`
+fn transfer_between_sub_accounts_ix(user: &Pubkey, from: &Pubkey, to: &Pubkey, base_amount: u64, quote_amount: u64) -> Instruction {
+    Instruction {
+        program_id: svm_clob::ID,
+        accounts: svm_clob::accounts::TransferBetweenSubAccounts {
+            from: user_account_address(from),
+            to: user_account_address(to),
+            user: *user,
+        }
+        .to_account_metas(None),
+        data: svm_clob::instruction::TransferBetweenSubAccounts { base_amount, quote_amount }.data(),
+    }
+}
+
+fn initialize_fill_receipts_ix(user: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: svm_clob::ID,
+        accounts: svm_clob::accounts::InitializeFillReceipts {
+            fill_receipts: fill_receipts_address(user),
+            user: *user,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: svm_clob::instruction::InitializeFillReceipts {}.data(),
+    }
+}
+
+fn deposit_ix(orderbook: &Pubkey, user: &Pubkey, token_account: &Pubkey, mint: &Pubkey, amount: u64) -> Instruction {
+    deposit_to_ix(orderbook, user, user, token_account, mint, amount)
+}
+
+/// Deposit by `user` into the user account `owner` owns
+fn deposit_to_ix(orderbook: &Pubkey, user: &Pubkey, owner: &Pubkey, token_account: &Pubkey, mint: &Pubkey, amount: u64) -> Instruction {
+    Instruction {
+        program_id: svm_clob::ID,
+        accounts: svm_clob::accounts::Deposit {
+            orderbook: *orderbook,
+            user_account: user_account_address(owner),
+            user_token_account: *token_account,
+            token_mint: *mint,
+            clob_token_vault: vault_address(mint),
+            user: *user,
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        }
+        .to_account_metas(None),
+        data: svm_clob::instruction::Deposit { amount }.data(),
+    }
+}
+
+fn withdraw_ix(orderbook: &Pubkey, user: &Pubkey, token_account: &Pubkey, mint: &Pubkey, amount: u64) -> Instruction {
+    withdraw_from_ix(orderbook, user, user, user, token_account, mint, amount)
+}
+
+/// Withdrawal by `user` from the user account `owner` owns, under the
+/// whitelist of `wallet`
+fn withdraw_from_ix(
+    orderbook: &Pubkey,
+    user: &Pubkey,
+    owner: &Pubkey,
+    wallet: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    Instruction {
+        program_id: svm_clob::ID,
+        accounts: svm_clob::accounts::Withdraw {
+            orderbook: *orderbook,
+            user_account: user_account_address(owner),
+            user_token_account: *token_account,
+            token_mint: *mint,
+            clob_token_vault: vault_address(mint),
+            user: *user,
+            token_program: spl_token::ID,
+            withdrawal_whitelist: withdrawal_whitelist_address(wallet),
+        }
+        .to_account_metas(None),
+        data: svm_clob::instruction::Withdraw { amount }.data(),
+    }
+}
+
+fn initialize_withdrawal_whitelist_ix(user: &Pubkey, delay_secs: u32, destinations: Vec<Pubkey>) -> Instruction {
+    Instruction {
+        program_id: svm_clob::ID,
+        accounts: svm_clob::accounts::InitializeWithdrawalWhitelist {
+            withdrawal_whitelist: withdrawal_whitelist_address(user),
+            user: *user,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: svm_clob::instruction::InitializeWithdrawalWhitelist { delay_secs, destinations }.data(),
+    }
+}
+
+/// An instruction of `ManageWithdrawalWhitelist` accounts carrying `data`
+fn manage_withdrawal_whitelist_ix(user: &Pubkey, data: Vec<u8>) -> Instruction {
+    Instruction {
+        program_id: svm_clob::ID,
+        accounts: svm_clob::accounts::ManageWithdrawalWhitelist {
+            withdrawal_whitelist: withdrawal_whitelist_address(user),
+            user: *user,
+        }
+        .to_account_metas(None),
+        data,
+    }
+}
+
+fn fund_insurance_ix(orderbook: &Pubkey, funder: &Pubkey, token_account: &Pubkey, quote_mint: &Pubkey, amount: u64) -> Instruction {
+    Instruction {
+        program_id: svm_clob::ID,
+        accounts: svm_clob::accounts::FundInsurance {
+            orderbook: *orderbook,
+            funder_token_account: *token_account,
+            quote_mint: *quote_mint,
+            insurance_fund: insurance_fund_address(orderbook),
+            funder: *funder,
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        }
+        .to_account_metas(None),
+        data: svm_clob::instruction::FundInsurance { amount }.data(),
+    }
+}
+
+fn draw_insurance_ix(orderbook: &Pubkey, authority: &Pubkey, destination: &Pubkey, amount: u64) -> Instruction {
+    Instruction {
+        program_id: svm_clob::ID,
+        accounts: svm_clob::accounts::DrawInsurance {
+            orderbook: *orderbook,
+            insurance_fund: insurance_fund_address(orderbook),
+            destination: *destination,
+            authority: *authority,
+            token_program: spl_token::ID,
+        }
+        .to_account_metas(None),
+        data: svm_clob::instruction::DrawInsurance { amount }.data(),
+    }
+}
+
+/// Credit the user account owned by `owner` with a plain transfer of `amount`
+/// into the vault of `mint`, vouched for by `authority`
+fn credit_transfer_ix(
+    orderbook: &Pubkey,
+    authority: &Pubkey,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    transfer_signature: [u8; 64],
+    amount: u64,
+) -> Instruction {
+    Instruction {
+        program_id: svm_clob::ID,
+        accounts: svm_clob::accounts::CreditTransfer {
+            orderbook: *orderbook,
+            user_account: user_account_address(owner),
+            token_mint: *mint,
+            clob_token_vault: vault_address(mint),
+            credited_transfer: credited_transfer_address(&transfer_signature, 0),
+            authority: *authority,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: svm_clob::instruction::CreditTransfer { transfer_signature, instruction_index: 0, amount }.data(),
+    }
+}
+
+fn adjust_settlement_ix(
+    orderbook: &Pubkey,
+    authority: &Pubkey,
+    trade: &Trade,
+    adjustment_id: u64,
+    base_to_maker: i64,
+    quote_to_maker: i64,
+) -> Instruction {
+    Instruction {
+        program_id: svm_clob::ID,
+        accounts: svm_clob::accounts::AdjustSettlement {
+            orderbook: *orderbook,
+            maker_user_account: user_account_address(&trade.maker),
+            taker_user_account: user_account_address(&trade.taker),
+            settlement_adjustment: settlement_adjustment_address(orderbook, adjustment_id),
+            authority: *authority,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: svm_clob::instruction::AdjustSettlement {
+            adjustment_id,
+            trade_id: 1,
+            base_to_maker,
+            quote_to_maker,
+        }
+        .data(),
+    }
+}
+
+fn set_market_status_ix(orderbook: &Pubkey, authority: &Pubkey, status: MarketStatus) -> Instruction {
+    Instruction {
+        program_id: svm_clob::ID,
+        accounts: svm_clob::accounts::SetMarketStatus {
+            orderbook: *orderbook,
+            authority: *authority,
+        }
+        .to_account_metas(None),
+        data: svm_clob::instruction::SetMarketStatus { status }.data(),
+    }
+}
+
+fn set_order_types_ix(orderbook: &Pubkey, authority: &Pubkey, whitelist: OrderTypeWhitelist) -> Instruction {
+    Instruction {
+        program_id: svm_clob::ID,
+        accounts: svm_clob::accounts::SetOrderTypes {
+            orderbook: *orderbook,
+            authority: *authority,
+        }
+        .to_account_metas(None),
+        data: svm_clob::instruction::SetOrderTypes { whitelist }.data(),
+    }
+}
+
+/// Put the user account owned by `owner` in `group`, signed by `user` and
+/// the orderbook's `authority`
+fn set_prevention_group_ix(orderbook: &Pubkey, authority: &Pubkey, user: &Pubkey, owner: &Pubkey, group: u32) -> Instruction {
+    Instruction {
+        program_id: svm_clob::ID,
+        accounts: svm_clob::accounts::SetPreventionGroup {
+            orderbook: *orderbook,
+            user_account: user_account_address(owner),
+            user: *user,
+            authority: *authority,
+        }
+        .to_account_metas(None),
+        data: svm_clob::instruction::SetPreventionGroup { group }.data(),
+    }
+}
+
+fn execute_trade_ix(orderbook: &Pubkey, authority: &Pubkey, trade: Trade) -> Instruction {
+    Instruction {
+        program_id: svm_clob::ID,
+        accounts: svm_clob::accounts::ExecuteTrade {
+            orderbook: *orderbook,
+            taker_user_account: user_account_address(&trade.taker),
+            maker_user_account: user_account_address(&trade.maker),
+            authority: *authority,
+            taker_fill_receipts: fill_receipts_address(&trade.taker),
+            maker_fill_receipts: fill_receipts_address(&trade.maker),
+        }
+        .to_account_metas(None),
+        data: svm_clob::instruction::ExecuteTrade { trade }.data(),
+    }
+}
+
+fn initialize_receipt_tree_ix(orderbook: &Pubkey, authority: &Pubkey, merkle_tree: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: svm_clob::ID,
+        accounts: svm_clob::accounts::InitializeReceiptTree {
+            orderbook: *orderbook,
+            receipt_tree: receipt_tree_address(orderbook),
+            merkle_tree: *merkle_tree,
+            authority: *authority,
+            noop: SPL_NOOP_ID,
+            compression_program: SPL_ACCOUNT_COMPRESSION_ID,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: svm_clob::instruction::InitializeReceiptTree { max_depth: 14, max_buffer_size: 64 }.data(),
+    }
+}
+
+fn settle_match_ix(orderbook: &Pubkey, authority: &Pubkey, trade: Trade, taker_order: SignedOrder, maker_order: SignedOrder) -> Instruction {
+    Instruction {
+        program_id: svm_clob::ID,
+        accounts: svm_clob::accounts::SettleMatch {
+            orderbook: *orderbook,
+            taker_user_account: user_account_address(&trade.taker),
+            maker_user_account: user_account_address(&trade.maker),
+            authority: *authority,
+            instructions: sysvar::instructions::ID,
+            taker_fill_receipts: fill_receipts_address(&trade.taker),
+            maker_fill_receipts: fill_receipts_address(&trade.maker),
+            taker_order_fill: signed_order_fill_address(&taker_order),
+            maker_order_fill: signed_order_fill_address(&maker_order),
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: svm_clob::instruction::SettleMatch { trade, taker_order, maker_order }.data(),
+    }
+}
+
+/// Ed25519 program instruction verifying one signed order, laid out as the
+/// native program reads it: count, padding, one offsets entry, then the key,
+/// signature and message it points at
+fn ed25519_ix(order: &SignedOrder) -> Instruction {
+    ed25519_ix_by(order, &order.order_intent.owner)
+}
+
+/// As `ed25519_ix`, with the signature checked against `signer`
+fn ed25519_ix_by(order: &SignedOrder, signer: &Pubkey) -> Instruction {
+    let message = order.message();
+    let (key_offset, signature_offset, message_offset) = (16u16, 48u16, 112u16);
+    let mut data = vec![1u8, 0];
+    for field in [
+        signature_offset,
+        u16::MAX,
+        key_offset,
+        u16::MAX,
+        message_offset,
+        message.len() as u16,
+        u16::MAX,
+    ] {
+        data.extend_from_slice(&field.to_le_bytes());
+    }
+    data.extend_from_slice(signer.as_ref());
+    data.extend_from_slice(&order.signature);
+    data.extend_from_slice(&message);
+    Instruction {
+        program_id: solana_sdk::ed25519_program::ID,
+        accounts: vec![],
+        data,
+    }
+}
+
+impl Market {
+    /// Start a validator with a market, a funded maker holding base tokens and
+    /// a funded taker holding quote tokens; nothing is deposited yet
+    async fn new() -> Self {
+        Self::with_program_test(program_test(), Keypair::new(), Keypair::new(), Keypair::new()).await
+    }
+
+    async fn with_program_test(mut program_test: ProgramTest, authority: Keypair, maker: Keypair, taker: Keypair) -> Self {
+        for signer in [&authority, &maker, &taker] {
+            program_test.add_account(signer.pubkey(), funded());
+        }
+        let mut ctx = program_test.start_with_context().await;
+
+        let base_mint = create_mint(&mut ctx, &authority).await;
+        let quote_mint = create_mint(&mut ctx, &authority).await;
+        let maker_base = create_token_account(&mut ctx, &base_mint, &maker.pubkey()).await;
+        let maker_quote = create_token_account(&mut ctx, &quote_mint, &maker.pubkey()).await;
+        let taker_base = create_token_account(&mut ctx, &base_mint, &taker.pubkey()).await;
+        let taker_quote = create_token_account(&mut ctx, &quote_mint, &taker.pubkey()).await;
+        mint_to(&mut ctx, &base_mint, &maker_base, &authority, 100).await;
+        mint_to(&mut ctx, &quote_mint, &taker_quote, &authority, 200).await;
+
+        send(&mut ctx, &[initialize_orderbook_ix(&authority.pubkey(), &base_mint, &quote_mint)], &[&authority])
+            .await
+            .unwrap();
+        send(&mut ctx, &[initialize_user_account_ix(&maker.pubkey())], &[&maker]).await.unwrap();
+        send(&mut ctx, &[initialize_user_account_ix(&taker.pubkey())], &[&taker]).await.unwrap();
+
+        Self {
+            orderbook: orderbook_address(&base_mint, &quote_mint),
+            maker_account: user_account_address(&maker.pubkey()),
+            taker_account: user_account_address(&taker.pubkey()),
+            ctx,
+            authority,
+            maker,
+            taker,
+            base_mint,
+            quote_mint,
+            maker_base,
+            maker_quote,
+            taker_base,
+            taker_quote,
+        }
+    }
+
+    /// Maker deposits base, taker deposits quote
+    async fn deposit_both(&mut self) {
+        let maker_deposit = deposit_ix(&self.orderbook, &self.maker.pubkey(), &self.maker_base, &self.base_mint, BASE_DEPOSIT);
+        send(&mut self.ctx, &[maker_deposit], &[&self.maker]).await.unwrap();
+        let taker_deposit = deposit_ix(&self.orderbook, &self.taker.pubkey(), &self.taker_quote, &self.quote_mint, QUOTE_DEPOSIT);
+        send(&mut self.ctx, &[taker_deposit], &[&self.taker]).await.unwrap();
+    }
+
+    fn trade(&self, taker_side: OrderSide, price: u64, quantity: u64) -> Trade {
+        Trade {
+            taker_order_id: 1,
+            maker_order_id: 2,
+            taker: self.taker.pubkey(),
+            maker: self.maker.pubkey(),
+            price,
+            quantity,
+            taker_side,
+            timestamp: 0,
+        }
+    }
+
+    async fn settle(&mut self, trade: Trade) -> Result<(), BanksClientError> {
+        let instruction = execute_trade_ix(&self.orderbook, &self.authority.pubkey(), trade);
+        send(&mut self.ctx, &[instruction], &[&self.authority]).await
+    }
+
+    async fn set_status(&mut self, status: MarketStatus) -> Result<(), BanksClientError> {
+        let instruction = set_market_status_ix(&self.orderbook, &self.authority.pubkey(), status);
+        send(&mut self.ctx, &[instruction], &[&self.authority]).await
+    }
+
+    /// Limit order on this market signed by `owner`, valid for an hour
+    fn signed_order(&self, owner: &Keypair, side: OrderSide, price: u64, quantity: u64) -> SignedOrder {
+        self.signed_order_for(&owner.pubkey(), owner, side, price, quantity)
+    }
+
+    /// As `signed_order`, for `owner`'s order signed by `signer`
+    fn signed_order_for(&self, owner: &Pubkey, signer: &Keypair, side: OrderSide, price: u64, quantity: u64) -> SignedOrder {
+        let order_intent = OrderIntent {
+            market: self.orderbook,
+            owner: *owner,
+            client_order_id: 7,
+            side,
+            order_type: OrderType::Limit,
+            price,
+            quantity,
+            time_in_force: TimeInForce::GoodTillCancelled,
+            self_trade_behavior: SelfTradeBehavior::CancelProvide,
+        };
+        let (nonce, expiry) = (1, i64::MAX);
+        let signature = signer.sign_message(&SignedOrder::signing_message(&order_intent, nonce, expiry));
+        SignedOrder {
+            order_intent,
+            nonce,
+            expiry,
+            signature: signature.as_ref().try_into().unwrap(),
+        }
+    }
+}
+
+#[tokio::test]
+async fn full_lifecycle() {
+    let mut market = Market::new().await;
+
+    let book = orderbook(&mut market.ctx, &market.orderbook).await;
+    assert_eq!(book.authority, market.authority.pubkey());
+    assert_eq!(book.base_mint, market.base_mint);
+    assert_eq!(book.quote_mint, market.quote_mint);
+    assert_eq!(book.is_initialized, 1);
+    assert_eq!(book.status, u8::from(MarketStatus::Active));
+
+    // Deposits create the vaults on first use
+    market.deposit_both().await;
+    assert_eq!(token_balance(&mut market.ctx, &vault_address(&market.base_mint)).await, BASE_DEPOSIT);
+    assert_eq!(token_balance(&mut market.ctx, &vault_address(&market.quote_mint)).await, QUOTE_DEPOSIT);
+    assert_eq!(token_balance(&mut market.ctx, &market.maker_base).await, 100 - BASE_DEPOSIT);
+    assert_eq!(token_balance(&mut market.ctx, &market.taker_quote).await, 200 - QUOTE_DEPOSIT);
+
+    // Taker lifts the maker's ask
+    let notional = TRADE_PRICE * TRADE_QUANTITY;
+    market.settle(market.trade(OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY)).await.unwrap();
+    let taker = user_account(&mut market.ctx, &market.taker_account).await;
+    let maker = user_account(&mut market.ctx, &market.maker_account).await;
+    assert_eq!(taker.base_token_balance, TRADE_QUANTITY);
+    assert_eq!(taker.quote_token_balance, QUOTE_DEPOSIT - notional);
+    assert_eq!(maker.base_token_balance, BASE_DEPOSIT - TRADE_QUANTITY);
+    assert_eq!(maker.quote_token_balance, notional);
+
+    // Taker hits the maker's bid with part of it
+    let resold = 4;
+    market.settle(market.trade(OrderSide::Ask, TRADE_PRICE, resold)).await.unwrap();
+    let taker = user_account(&mut market.ctx, &market.taker_account).await;
+    let maker = user_account(&mut market.ctx, &market.maker_account).await;
+    assert_eq!(taker.base_token_balance, TRADE_QUANTITY - resold);
+    assert_eq!(taker.quote_token_balance, QUOTE_DEPOSIT - notional + TRADE_PRICE * resold);
+    assert_eq!(maker.base_token_balance, BASE_DEPOSIT - TRADE_QUANTITY + resold);
+    assert_eq!(maker.quote_token_balance, notional - TRADE_PRICE * resold);
+    assert_eq!(taker.total_volume_traded, TRADE_QUANTITY + resold);
+    assert_eq!(maker.total_volume_traded, TRADE_QUANTITY + resold);
+    assert_eq!(orderbook(&mut market.ctx, &market.orderbook).await.total_volume, TRADE_QUANTITY + resold);
+
+    // Settlement moves internal balances only; vaults are untouched
+    assert_eq!(token_balance(&mut market.ctx, &vault_address(&market.base_mint)).await, BASE_DEPOSIT);
+    assert_eq!(token_balance(&mut market.ctx, &vault_address(&market.quote_mint)).await, QUOTE_DEPOSIT);
+
+    // Both sides withdraw everything they hold
+    let (taker_base, taker_quote) = (taker.base_token_balance, taker.quote_token_balance);
+    let (maker_base, maker_quote) = (maker.base_token_balance, maker.quote_token_balance);
+    let withdrawals = [
+        withdraw_ix(&market.orderbook, &market.taker.pubkey(), &market.taker_base, &market.base_mint, taker_base),
+        withdraw_ix(&market.orderbook, &market.taker.pubkey(), &market.taker_quote, &market.quote_mint, taker_quote),
+    ];
+    send(&mut market.ctx, &withdrawals, &[&market.taker]).await.unwrap();
+    let withdrawals = [
+        withdraw_ix(&market.orderbook, &market.maker.pubkey(), &market.maker_base, &market.base_mint, maker_base),
+        withdraw_ix(&market.orderbook, &market.maker.pubkey(), &market.maker_quote, &market.quote_mint, maker_quote),
+    ];
+    send(&mut market.ctx, &withdrawals, &[&market.maker]).await.unwrap();
+
+    for account in [market.taker_account, market.maker_account] {
+        let account = user_account(&mut market.ctx, &account).await;
+        assert_eq!(account.base_token_balance, 0);
+        assert_eq!(account.quote_token_balance, 0);
+    }
+    assert_eq!(token_balance(&mut market.ctx, &vault_address(&market.base_mint)).await, 0);
+    assert_eq!(token_balance(&mut market.ctx, &vault_address(&market.quote_mint)).await, 0);
+    assert_eq!(token_balance(&mut market.ctx, &market.taker_base).await, taker_base);
+    assert_eq!(token_balance(&mut market.ctx, &market.taker_quote).await, 200 - QUOTE_DEPOSIT + taker_quote);
+    assert_eq!(token_balance(&mut market.ctx, &market.maker_base).await, 100 - BASE_DEPOSIT + maker_base);
+    assert_eq!(token_balance(&mut market.ctx, &market.maker_quote).await, maker_quote);
+}
+
+#[tokio::test]
+async fn initialize_orderbook_rejects_existing_market() {
+    let mut market = Market::new().await;
+    let instruction = initialize_orderbook_ix(&market.authority.pubkey(), &market.base_mint, &market.quote_mint);
+
+    // The system program refuses to allocate an account that is already in use
+    let result = send(&mut market.ctx, &[instruction], &[&market.authority]).await;
+    assert_error(result, 0);
+}
+
+#[tokio::test]
+async fn deposit_rejects_unsupported_mint() {
+    let mut market = Market::new().await;
+    let other_mint = create_mint(&mut market.ctx, &market.authority).await;
+    let token_account = create_token_account(&mut market.ctx, &other_mint, &market.maker.pubkey()).await;
+    mint_to(&mut market.ctx, &other_mint, &token_account, &market.authority, 10).await;
+
+    let instruction = deposit_ix(&market.orderbook, &market.maker.pubkey(), &token_account, &other_mint, 5);
+    let result = send(&mut market.ctx, &[instruction], &[&market.maker]).await;
+    assert_error(result, ClobError::InvalidMint.into());
+    assert_eq!(token_balance(&mut market.ctx, &token_account).await, 10);
+}
+
+#[tokio::test]
+async fn withdraw_rejects_unsupported_mint() {
+    let mut market = Market::new().await;
+    let other_mint = create_mint(&mut market.ctx, &market.authority).await;
+    let token_account = create_token_account(&mut market.ctx, &other_mint, &market.maker.pubkey()).await;
+    mint_to(&mut market.ctx, &other_mint, &token_account, &market.authority, 10).await;
+
+    // Vaults are keyed by mint alone, so a deposit into a second market
+    // creates a vault the first market must still refuse to pay out of
+    send(
+        &mut market.ctx,
+        &[initialize_orderbook_ix(&market.authority.pubkey(), &other_mint, &market.quote_mint)],
+        &[&market.authority],
+    )
+    .await
+    .unwrap();
+    let other_orderbook = orderbook_address(&other_mint, &market.quote_mint);
+    let instruction = deposit_ix(&other_orderbook, &market.maker.pubkey(), &token_account, &other_mint, 10);
+    send(&mut market.ctx, &[instruction], &[&market.maker]).await.unwrap();
+
+    let instruction = withdraw_ix(&market.orderbook, &market.maker.pubkey(), &token_account, &other_mint, 10);
+    let result = send(&mut market.ctx, &[instruction], &[&market.maker]).await;
+    assert_error(result, ClobError::InvalidMint.into());
+    assert_eq!(token_balance(&mut market.ctx, &vault_address(&other_mint)).await, 10);
+}
+
+#[tokio::test]
+async fn withdraw_rejects_more_than_balance() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+
+    let instruction = withdraw_ix(&market.orderbook, &market.maker.pubkey(), &market.maker_base, &market.base_mint, BASE_DEPOSIT + 1);
+    let result = send(&mut market.ctx, &[instruction], &[&market.maker]).await;
+    assert_error(result, ClobError::InsufficientBalance.into());
+    assert_eq!(user_account(&mut market.ctx, &market.maker_account).await.base_token_balance, BASE_DEPOSIT);
+}
+
+#[tokio::test]
+async fn withdraw_rejects_another_owners_account() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+    let maker = market.maker.pubkey();
+
+    // The taker cannot drain the maker's account into its own token account
+    let instruction = withdraw_from_ix(&market.orderbook, &market.taker.pubkey(), &maker, &maker, &market.taker_base, &market.base_mint, 1);
+    let result = send(&mut market.ctx, &[instruction], &[&market.taker]).await;
+    assert_error(result, ClobError::Unauthorized.into());
+    assert_eq!(user_account(&mut market.ctx, &market.maker_account).await.base_token_balance, BASE_DEPOSIT);
+    assert_eq!(token_balance(&mut market.ctx, &market.taker_base).await, 0);
+}
+
+#[tokio::test]
+async fn execute_trade_rejects_overdrawn_taker() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+
+    // Notional of 11 * 10 exceeds the taker's quote deposit
+    let result = market.settle(market.trade(OrderSide::Bid, 11, TRADE_QUANTITY)).await;
+    assert_error(result, ClobError::InsufficientBalance.into());
+    let taker = user_account(&mut market.ctx, &market.taker_account).await;
+    assert_eq!(taker.quote_token_balance, QUOTE_DEPOSIT);
+    assert_eq!(taker.base_token_balance, 0);
+}
+
+#[tokio::test]
+async fn execute_trade_rejects_overdrawn_maker() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+
+    // The maker only deposited base, so it cannot buy
+    let result = market.settle(market.trade(OrderSide::Ask, 1, 1)).await;
+    assert_error(result, ClobError::InsufficientBalance.into());
+}
+
+#[tokio::test]
+async fn execute_trade_rejects_notional_overflow() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+
+    let result = market.settle(market.trade(OrderSide::Bid, u64::MAX, 2)).await;
+    assert_error(result, ClobError::InsufficientBalance.into());
+}
+
+#[tokio::test]
+async fn execute_trade_rejects_mismatched_owner() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+
+    // The trade names a taker whose user account was not passed in
+    let mut trade = market.trade(OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY);
+    let mut instruction = execute_trade_ix(&market.orderbook, &market.authority.pubkey(), trade);
+    trade.taker = Pubkey::new_unique();
+    instruction.data = svm_clob::instruction::ExecuteTrade { trade }.data();
+    let result = send(&mut market.ctx, &[instruction], &[&market.authority]).await;
+    assert_error(result, ClobError::Unauthorized.into());
+}
+
+#[tokio::test]
+async fn execute_trade_rejects_wrong_authority() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+
+    // The account constraint on the orderbook catches a foreign signer before
+    // the handler's own `InvalidAuthority` check can run
+    let intruder = Keypair::new();
+    let transfer = system_instruction::transfer(&market.ctx.payer.pubkey(), &intruder.pubkey(), LAMPORTS / 10);
+    send(&mut market.ctx, &[transfer], &[]).await.unwrap();
+    let instruction = execute_trade_ix(
+        &market.orderbook,
+        &intruder.pubkey(),
+        market.trade(OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY),
+    );
+    let result = send(&mut market.ctx, &[instruction], &[&intruder]).await;
+    assert_error(result, AnchorErrorCode::ConstraintRaw.into());
+    assert_eq!(user_account(&mut market.ctx, &market.taker_account).await.base_token_balance, 0);
+}
+
+#[tokio::test]
+async fn execute_trade_rejects_paused_orderbook() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+    market.set_status(MarketStatus::Paused).await.unwrap();
+    assert_eq!(orderbook(&mut market.ctx, &market.orderbook).await.status, u8::from(MarketStatus::Paused));
+
+    let result = market.settle(market.trade(OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY)).await;
+    assert_error(result, ClobError::OrderbookPaused.into());
+    assert_eq!(user_account(&mut market.ctx, &market.taker_account).await.quote_token_balance, QUOTE_DEPOSIT);
+
+    // Reduce-only is enforced by the engine and settles as active
+    market.set_status(MarketStatus::ReduceOnly).await.unwrap();
+    market.settle(market.trade(OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY)).await.unwrap();
+}
+
+#[tokio::test]
+async fn cancel_only_settles_trades_matched_before_the_change() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+    market.set_status(MarketStatus::CancelOnly).await.unwrap();
+    let changed_at = orderbook(&mut market.ctx, &market.orderbook).await.status_changed_at;
+    let now: Clock = market.ctx.banks_client.get_sysvar().await.unwrap();
+    assert_eq!(changed_at, now.unix_timestamp);
+
+    // The engine's settlement backlog still drains
+    let mut matched_before = market.trade(OrderSide::Bid, TRADE_PRICE, 1);
+    matched_before.timestamp = changed_at;
+    market.settle(matched_before).await.unwrap();
+
+    let mut matched_after = market.trade(OrderSide::Bid, TRADE_PRICE, 1);
+    matched_after.taker_order_id = 3;
+    matched_after.timestamp = changed_at + 1;
+    let result = market.settle(matched_after).await;
+    assert_error(result, ClobError::MarketCancelOnly.into());
+    assert_eq!(user_account(&mut market.ctx, &market.taker_account).await.base_token_balance, 1);
+
+    market.set_status(MarketStatus::Active).await.unwrap();
+    market.settle(matched_after).await.unwrap();
+}
+
+#[tokio::test]
+async fn set_market_status_requires_the_market_authority() {
+    let mut market = Market::new().await;
+    let instruction = set_market_status_ix(&market.orderbook, &market.maker.pubkey(), MarketStatus::Paused);
+    let result = send(&mut market.ctx, &[instruction], &[&market.maker]).await;
+    assert_error(result, ClobError::InvalidAuthority.into());
+    assert_eq!(orderbook(&mut market.ctx, &market.orderbook).await.status, u8::from(MarketStatus::Active));
+}
+
+#[tokio::test]
+async fn order_types_are_set_by_the_market_authority() {
+    let mut market = Market::new().await;
+    let whitelist = OrderTypeWhitelist::new(
+        &[OrderType::Limit, OrderType::PostOnly],
+        &[TimeInForce::GoodTillCancelled, TimeInForce::ImmediateOrCancel],
+    );
+
+    let instruction = set_order_types_ix(&market.orderbook, &market.maker.pubkey(), whitelist);
+    let result = send(&mut market.ctx, &[instruction], &[&market.maker]).await;
+    assert_error(result, ClobError::InvalidAuthority.into());
+    let account = orderbook(&mut market.ctx, &market.orderbook).await;
+    assert_eq!((account.order_types, account.time_in_force), (0, 0));
+
+    let instruction = set_order_types_ix(&market.orderbook, &market.authority.pubkey(), whitelist);
+    send(&mut market.ctx, &[instruction], &[&market.authority]).await.unwrap();
+    let account = orderbook(&mut market.ctx, &market.orderbook).await;
+    let stored = OrderTypeWhitelist {
+        order_types: account.order_types,
+        time_in_force: account.time_in_force,
+    };
+    assert_eq!(stored, whitelist);
+    assert!(!stored.accepts(OrderType::Market, TimeInForce::ImmediateOrCancel));
+    assert!(!stored.accepts(OrderType::Limit, TimeInForce::GoodTillTime));
+
+    // Settlement does not check the whitelist; the engine did at placement
+    market.deposit_both().await;
+    market.settle(market.trade(OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY)).await.unwrap();
+
+    let instruction = set_order_types_ix(&market.orderbook, &market.authority.pubkey(), OrderTypeWhitelist::ALL);
+    send(&mut market.ctx, &[instruction], &[&market.authority]).await.unwrap();
+    assert_eq!(orderbook(&mut market.ctx, &market.orderbook).await.order_types, 0);
+}
+
+#[tokio::test]
+async fn prevention_group_needs_the_account_signer_and_the_market_authority() {
+    let mut market = Market::new().await;
+    let maker = market.maker.pubkey();
+    let taker = market.taker.pubkey();
+    let authority = market.authority.pubkey();
+    send(&mut market.ctx, &[initialize_sub_account_ix(&maker, 1, "desk")], &[&market.maker]).await.unwrap();
+    let sub_account = sub_account_address(&maker, 1);
+
+    // Without the authority, or signed by someone else's wallet
+    let instruction = set_prevention_group_ix(&market.orderbook, &maker, &maker, &maker, 7);
+    let result = send(&mut market.ctx, &[instruction], &[&market.maker]).await;
+    assert_error(result, ClobError::InvalidAuthority.into());
+    let instruction = set_prevention_group_ix(&market.orderbook, &authority, &taker, &maker, 7);
+    let result = send(&mut market.ctx, &[instruction], &[&market.taker, &market.authority]).await;
+    assert_error(result, ClobError::Unauthorized.into());
+
+    // The wallet puts its own account and its sub-account in one group
+    for owner in [maker, sub_account] {
+        let instruction = set_prevention_group_ix(&market.orderbook, &authority, &maker, &owner, 7);
+        send(&mut market.ctx, &[instruction], &[&market.maker, &market.authority]).await.unwrap();
+        assert_eq!(user_account(&mut market.ctx, &user_account_address(&owner)).await.prevention_group, 7);
+    }
+    let instruction = set_prevention_group_ix(&market.orderbook, &authority, &maker, &sub_account, 0);
+    send(&mut market.ctx, &[instruction], &[&market.maker, &market.authority]).await.unwrap();
+    assert_eq!(user_account(&mut market.ctx, &user_account_address(&sub_account)).await.prevention_group, 0);
+    assert_eq!(user_account(&mut market.ctx, &market.maker_account).await.prevention_group, 7);
+}
+
+#[tokio::test]
+async fn settle_match_settles_signed_orders() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+
+    let taker_order = market.signed_order(&market.taker, OrderSide::Bid, TRADE_PRICE + 1, TRADE_QUANTITY);
+    let maker_order = market.signed_order(&market.maker, OrderSide::Ask, TRADE_PRICE, TRADE_QUANTITY * 2);
+    let trade = market.trade(OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY);
+    let instructions = [
+        ed25519_ix(&taker_order),
+        ed25519_ix(&maker_order),
+        settle_match_ix(&market.orderbook, &market.authority.pubkey(), trade, taker_order, maker_order),
+    ];
+    send(&mut market.ctx, &instructions, &[&market.authority]).await.unwrap();
+
+    let taker = user_account(&mut market.ctx, &market.taker_account).await;
+    let maker = user_account(&mut market.ctx, &market.maker_account).await;
+    assert_eq!(taker.base_token_balance, TRADE_QUANTITY);
+    assert_eq!(taker.quote_token_balance, QUOTE_DEPOSIT - TRADE_PRICE * TRADE_QUANTITY);
+    assert_eq!(maker.quote_token_balance, TRADE_PRICE * TRADE_QUANTITY);
+}
+
+#[tokio::test]
+async fn settlement_records_fill_receipts() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+    let taker = market.taker.pubkey();
+    send(&mut market.ctx, &[initialize_fill_receipts_ix(&taker)], &[&market.taker]).await.unwrap();
+
+    // The maker never created receipts, which must not stop settlement
+    let mut trade = market.trade(OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY);
+    trade.timestamp = 1;
+    market.settle(trade).await.unwrap();
+
+    let receipts = fill_receipts(&mut market.ctx, &taker).await;
+    assert_eq!(receipts.owner, taker);
+    assert_eq!(receipts.total_fills, 1);
+    let receipt = receipts.receipts[0];
+    assert_eq!(receipt.orderbook, market.orderbook);
+    assert_eq!((receipt.order_id, receipt.counterparty_order_id), (1, 2));
+    assert_eq!((receipt.price, receipt.quantity), (TRADE_PRICE, TRADE_QUANTITY));
+    assert_eq!((receipt.side, receipt.is_maker), (0, 0));
+    assert!(market.ctx.banks_client.get_account(fill_receipts_address(&market.maker.pubkey())).await.unwrap().is_none());
+
+    // Once the ring is full the oldest receipt is overwritten
+    let maker = market.maker.pubkey();
+    send(&mut market.ctx, &[initialize_fill_receipts_ix(&maker)], &[&market.maker]).await.unwrap();
+    for index in 0..FILL_RECEIPTS_CAPACITY as u64 {
+        let side = if index % 2 == 0 { OrderSide::Ask } else { OrderSide::Bid };
+        let mut trade = market.trade(side, TRADE_PRICE, 1);
+        trade.taker_order_id = 100 + index;
+        trade.maker_order_id = 200 + index;
+        market.settle(trade).await.unwrap();
+    }
+
+    let receipts = fill_receipts(&mut market.ctx, &taker).await;
+    assert_eq!(receipts.total_fills, FILL_RECEIPTS_CAPACITY as u64 + 1);
+    let newest = receipts.receipts[0];
+    assert_eq!((newest.order_id, newest.side, newest.is_maker), (100 + FILL_RECEIPTS_CAPACITY as u64 - 1, 0, 0));
+    assert_eq!(receipts.receipts[1].order_id, 100);
+
+    let receipts = fill_receipts(&mut market.ctx, &maker).await;
+    assert_eq!(receipts.total_fills, FILL_RECEIPTS_CAPACITY as u64);
+    let first = receipts.receipts[0];
+    assert_eq!((first.order_id, first.counterparty_order_id, first.side, first.is_maker), (200, 100, 0, 1));
+}
+
+#[tokio::test]
+async fn settle_match_rejects_unverified_signature() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+
+    // Only the maker's signature is verified in the transaction
+    let taker_order = market.signed_order(&market.taker, OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY);
+    let maker_order = market.signed_order(&market.maker, OrderSide::Ask, TRADE_PRICE, TRADE_QUANTITY);
+    let trade = market.trade(OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY);
+    let instructions = [
+        ed25519_ix(&maker_order),
+        settle_match_ix(&market.orderbook, &market.authority.pubkey(), trade, taker_order, maker_order),
+    ];
+    let result = send(&mut market.ctx, &instructions, &[&market.authority]).await;
+    assert_error_at(result, 1, ClobError::InvalidOrderSignature.into());
+}
+
+#[tokio::test]
+async fn settle_match_rejects_trade_outside_intent() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+
+    // The taker's bid is capped below the trade price
+    let taker_order = market.signed_order(&market.taker, OrderSide::Bid, TRADE_PRICE - 1, TRADE_QUANTITY);
+    let maker_order = market.signed_order(&market.maker, OrderSide::Ask, TRADE_PRICE, TRADE_QUANTITY);
+    let trade = market.trade(OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY);
+    let instructions = [
+        ed25519_ix(&taker_order),
+        ed25519_ix(&maker_order),
+        settle_match_ix(&market.orderbook, &market.authority.pubkey(), trade, taker_order, maker_order),
+    ];
+    let result = send(&mut market.ctx, &instructions, &[&market.authority]).await;
+    assert_error_at(result, 2, ClobError::OrderIntentMismatch.into());
+    assert_eq!(user_account(&mut market.ctx, &market.taker_account).await.base_token_balance, 0);
+}
+
+#[tokio::test]
+async fn settle_match_rejects_replayed_signatures() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+
+    let taker_order = market.signed_order(&market.taker, OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY);
+    let maker_order = market.signed_order(&market.maker, OrderSide::Ask, TRADE_PRICE, TRADE_QUANTITY * 2);
+    let settle = |trade: Trade, taker_order: &SignedOrder, maker_order: &SignedOrder| {
+        [
+            ed25519_ix(taker_order),
+            ed25519_ix(maker_order),
+            settle_match_ix(&market.orderbook, &market.authority.pubkey(), trade, *taker_order, *maker_order),
+        ]
+    };
+    let trade = market.trade(OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY);
+    let instructions = settle(trade, &taker_order, &maker_order);
+    send(&mut market.ctx, &instructions, &[&market.authority]).await.unwrap();
+
+    let fill = signed_order_fill(&mut market.ctx, &taker_order).await;
+    assert_eq!((fill.owner, fill.nonce, fill.filled), (market.taker.pubkey(), taker_order.nonce, TRADE_QUANTITY));
+
+    // The taker's signature is used up, even under a new trade
+    let mut replay = market.trade(OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY);
+    replay.taker_order_id = 3;
+    let instructions = settle(replay, &taker_order, &maker_order);
+    let result = send(&mut market.ctx, &instructions, &[&market.authority]).await;
+    assert_error_at(result, 2, ClobError::SignedOrderOverfilled.into());
+
+    // A different order signed under a used nonce is refused too
+    let other_taker_order = market.signed_order(&market.taker, OrderSide::Bid, TRADE_PRICE + 1, TRADE_QUANTITY);
+    let instructions = settle(replay, &other_taker_order, &maker_order);
+    let result = send(&mut market.ctx, &instructions, &[&market.authority]).await;
+    assert_error_at(result, 2, ClobError::SignedOrderNonceReused.into());
+    assert_eq!(user_account(&mut market.ctx, &market.taker_account).await.base_token_balance, TRADE_QUANTITY);
+}
+
+#[tokio::test]
+async fn withdrawal_whitelist_limits_destinations() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+    let instruction = initialize_withdrawal_whitelist_ix(&market.maker.pubkey(), WHITELIST_DELAY_SECS, vec![market.maker_base]);
+    send(&mut market.ctx, &[instruction], &[&market.maker]).await.unwrap();
+
+    // A stolen key cannot pay out to an account of its own
+    let other_base = create_token_account(&mut market.ctx, &market.base_mint, &market.maker.pubkey()).await;
+    let instruction = withdraw_ix(&market.orderbook, &market.maker.pubkey(), &other_base, &market.base_mint, 10);
+    let result = send(&mut market.ctx, &[instruction], &[&market.maker]).await;
+    assert_error(result, ClobError::DestinationNotWhitelisted.into());
+    assert_eq!(user_account(&mut market.ctx, &market.maker_account).await.base_token_balance, BASE_DEPOSIT);
+
+    let instruction = withdraw_ix(&market.orderbook, &market.maker.pubkey(), &market.maker_base, &market.base_mint, 10);
+    send(&mut market.ctx, &[instruction], &[&market.maker]).await.unwrap();
+    assert_eq!(token_balance(&mut market.ctx, &market.maker_base).await, 100 - BASE_DEPOSIT + 10);
+}
+
+#[tokio::test]
+async fn withdrawal_whitelist_additions_wait_for_the_delay() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+    let maker = market.maker.pubkey();
+    let instruction = initialize_withdrawal_whitelist_ix(&maker, WHITELIST_DELAY_SECS, vec![market.maker_base]);
+    send(&mut market.ctx, &[instruction], &[&market.maker]).await.unwrap();
+
+    let other_base = create_token_account(&mut market.ctx, &market.base_mint, &maker).await;
+    let request = svm_clob::instruction::RequestWithdrawalDestination { destination: other_base }.data();
+    send(&mut market.ctx, &[manage_withdrawal_whitelist_ix(&maker, request)], &[&market.maker]).await.unwrap();
+    let apply = svm_clob::instruction::ApplyWithdrawalWhitelistChange {}.data();
+    let result = send(&mut market.ctx, &[manage_withdrawal_whitelist_ix(&maker, apply.clone())], &[&market.maker]).await;
+    assert_error(result, ClobError::WhitelistChangeNotReady.into());
+
+    advance_clock(&mut market.ctx, i64::from(WHITELIST_DELAY_SECS)).await;
+    send(&mut market.ctx, &[manage_withdrawal_whitelist_ix(&maker, apply)], &[&market.maker]).await.unwrap();
+    let instruction = withdraw_ix(&market.orderbook, &maker, &other_base, &market.base_mint, 10);
+    send(&mut market.ctx, &[instruction], &[&market.maker]).await.unwrap();
+    assert_eq!(token_balance(&mut market.ctx, &other_base).await, 10);
+}
+
+#[tokio::test]
+async fn withdrawal_whitelist_removal_waits_for_the_delay() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+    let maker = market.maker.pubkey();
+    let instruction = initialize_withdrawal_whitelist_ix(&maker, WHITELIST_DELAY_SECS, vec![]);
+    send(&mut market.ctx, &[instruction], &[&market.maker]).await.unwrap();
+
+    // An empty whitelist blocks every withdrawal
+    let instruction = withdraw_ix(&market.orderbook, &maker, &market.maker_base, &market.base_mint, 10);
+    let result = send(&mut market.ctx, &[instruction], &[&market.maker]).await;
+    assert_error(result, ClobError::DestinationNotWhitelisted.into());
+
+    let request = svm_clob::instruction::RequestWithdrawalWhitelistRemoval {}.data();
+    send(&mut market.ctx, &[manage_withdrawal_whitelist_ix(&maker, request)], &[&market.maker]).await.unwrap();
+    advance_clock(&mut market.ctx, i64::from(WHITELIST_DELAY_SECS)).await;
+    let apply = svm_clob::instruction::ApplyWithdrawalWhitelistChange {}.data();
+    send(&mut market.ctx, &[manage_withdrawal_whitelist_ix(&maker, apply)], &[&market.maker]).await.unwrap();
+    assert!(market.ctx.banks_client.get_account(withdrawal_whitelist_address(&maker)).await.unwrap().is_none());
+
+    let instruction = withdraw_ix(&market.orderbook, &maker, &market.maker_base, &market.base_mint, 10);
+    send(&mut market.ctx, &[instruction], &[&market.maker]).await.unwrap();
+}
+
+#[tokio::test]
+async fn insurance_fund_is_funded_and_drawn() {
+    let mut market = Market::new().await;
+    let instruction = fund_insurance_ix(&market.orderbook, &market.taker.pubkey(), &market.taker_quote, &market.quote_mint, 30);
+    send(&mut market.ctx, &[instruction], &[&market.taker]).await.unwrap();
+    let fund = insurance_fund_address(&market.orderbook);
+    assert_eq!(token_balance(&mut market.ctx, &fund).await, 30);
+    assert_eq!(token_balance(&mut market.ctx, &market.taker_quote).await, 170);
+
+    let destination = create_token_account(&mut market.ctx, &market.quote_mint, &market.authority.pubkey()).await;
+    let instruction = draw_insurance_ix(&market.orderbook, &market.authority.pubkey(), &destination, 20);
+    send(&mut market.ctx, &[instruction], &[&market.authority]).await.unwrap();
+    assert_eq!(token_balance(&mut market.ctx, &fund).await, 10);
+    assert_eq!(token_balance(&mut market.ctx, &destination).await, 20);
+}
+
+#[tokio::test]
+async fn fund_insurance_rejects_base_mint() {
+    let mut market = Market::new().await;
+
+    // The fund pays out shortfalls in quote, so it only holds the quote mint
+    let instruction = fund_insurance_ix(&market.orderbook, &market.maker.pubkey(), &market.maker_base, &market.base_mint, 10);
+    let result = send(&mut market.ctx, &[instruction], &[&market.maker]).await;
+    assert_error(result, ClobError::InvalidMint.into());
+    assert_eq!(token_balance(&mut market.ctx, &market.maker_base).await, 100);
+}
+
+#[tokio::test]
+async fn draw_insurance_rejects_wrong_authority() {
+    let mut market = Market::new().await;
+    let instruction = fund_insurance_ix(&market.orderbook, &market.taker.pubkey(), &market.taker_quote, &market.quote_mint, 30);
+    send(&mut market.ctx, &[instruction], &[&market.taker]).await.unwrap();
+
+    // A funder cannot take its contribution back
+    let instruction = draw_insurance_ix(&market.orderbook, &market.taker.pubkey(), &market.taker_quote, 30);
+    let result = send(&mut market.ctx, &[instruction], &[&market.taker]).await;
+    assert_error(result, ClobError::InvalidAuthority.into());
+    assert_eq!(token_balance(&mut market.ctx, &insurance_fund_address(&market.orderbook)).await, 30);
+}
+
+#[tokio::test]
+async fn draw_insurance_rejects_more_than_fund() {
+    let mut market = Market::new().await;
+    let instruction = fund_insurance_ix(&market.orderbook, &market.taker.pubkey(), &market.taker_quote, &market.quote_mint, 30);
+    send(&mut market.ctx, &[instruction], &[&market.taker]).await.unwrap();
+
+    let destination = create_token_account(&mut market.ctx, &market.quote_mint, &market.authority.pubkey()).await;
+    let instruction = draw_insurance_ix(&market.orderbook, &market.authority.pubkey(), &destination, 31);
+    let result = send(&mut market.ctx, &[instruction], &[&market.authority]).await;
+    assert_error(result, ClobError::InsufficientBalance.into());
+    assert_eq!(token_balance(&mut market.ctx, &destination).await, 0);
+}
+
+#[tokio::test]
+async fn plain_transfer_into_the_vault_is_credited_once() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+    let vault = vault_address(&market.base_mint);
+    let transfer = spl_token::instruction::transfer(&spl_token::ID, &market.maker_base, &vault, &market.maker.pubkey(), &[], 20).unwrap();
+    send(&mut market.ctx, &[transfer], &[&market.maker]).await.unwrap();
+    assert_eq!(token_balance(&mut market.ctx, &vault).await, BASE_DEPOSIT + 20);
+    assert_eq!(user_account(&mut market.ctx, &market.maker_account).await.base_token_balance, BASE_DEPOSIT);
+
+    let transfer_signature = [7; 64];
+    let instruction = credit_transfer_ix(
+        &market.orderbook,
+        &market.authority.pubkey(),
+        &market.maker.pubkey(),
+        &market.base_mint,
+        transfer_signature,
+        20,
+    );
+    send(&mut market.ctx, &[instruction.clone()], &[&market.authority]).await.unwrap();
+    assert_eq!(user_account(&mut market.ctx, &market.maker_account).await.base_token_balance, BASE_DEPOSIT + 20);
+
+    // The record of the transfer already exists
+    market.ctx.get_new_latest_blockhash().await.unwrap();
+    let result = send(&mut market.ctx, &[instruction], &[&market.authority]).await;
+    assert_error(result, 0);
+    assert_eq!(user_account(&mut market.ctx, &market.maker_account).await.base_token_balance, BASE_DEPOSIT + 20);
+}
+
+#[tokio::test]
+async fn credit_transfer_needs_the_market_authority() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+
+    // A user cannot credit itself
+    let instruction = credit_transfer_ix(
+        &market.orderbook,
+        &market.maker.pubkey(),
+        &market.maker.pubkey(),
+        &market.base_mint,
+        [1; 64],
+        10,
+    );
+    let result = send(&mut market.ctx, &[instruction], &[&market.maker]).await;
+    assert_error(result, ClobError::InvalidAuthority.into());
+    assert_eq!(user_account(&mut market.ctx, &market.maker_account).await.base_token_balance, BASE_DEPOSIT);
+}
+
+#[tokio::test]
+async fn busted_trade_is_reversed_once() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+    let trade = market.trade(OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY);
+    market.settle(trade).await.unwrap();
+    let notional = (TRADE_PRICE * TRADE_QUANTITY) as i64;
+
+    // The taker gives back the base it bought, the maker the quote it was paid
+    let instruction = adjust_settlement_ix(
+        &market.orderbook,
+        &market.authority.pubkey(),
+        &trade,
+        1,
+        TRADE_QUANTITY as i64,
+        -notional,
+    );
+    send(&mut market.ctx, &[instruction.clone()], &[&market.authority]).await.unwrap();
+    let maker = user_account(&mut market.ctx, &market.maker_account).await;
+    assert_eq!((maker.base_token_balance, maker.quote_token_balance), (BASE_DEPOSIT, 0));
+    let taker = user_account(&mut market.ctx, &market.taker_account).await;
+    assert_eq!((taker.base_token_balance, taker.quote_token_balance), (0, QUOTE_DEPOSIT));
+    // Settled volume stands; only balances move back
+    assert_eq!(orderbook(&mut market.ctx, &market.orderbook).await.total_volume, TRADE_QUANTITY);
+
+    // The record of the adjustment already exists
+    market.ctx.get_new_latest_blockhash().await.unwrap();
+    let result = send(&mut market.ctx, &[instruction], &[&market.authority]).await;
+    assert_error(result, 0);
+    let maker = user_account(&mut market.ctx, &market.maker_account).await;
+    assert_eq!((maker.base_token_balance, maker.quote_token_balance), (BASE_DEPOSIT, 0));
+}
+
+#[tokio::test]
+async fn adjust_settlement_needs_the_market_authority_and_the_balances() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+    let trade = market.trade(OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY);
+    market.settle(trade).await.unwrap();
+    let notional = (TRADE_PRICE * TRADE_QUANTITY) as i64;
+
+    // A party cannot take its payment back
+    let instruction = adjust_settlement_ix(&market.orderbook, &market.taker.pubkey(), &trade, 1, 0, -notional);
+    let result = send(&mut market.ctx, &[instruction], &[&market.taker]).await;
+    assert_error(result, ClobError::InvalidAuthority.into());
+
+    // The maker already withdrew what it was paid, so the bust cannot be reversed
+    let instruction = withdraw_ix(
+        &market.orderbook,
+        &market.maker.pubkey(),
+        &market.maker_quote,
+        &market.quote_mint,
+        notional as u64,
+    );
+    send(&mut market.ctx, &[instruction], &[&market.maker]).await.unwrap();
+    let instruction = adjust_settlement_ix(
+        &market.orderbook,
+        &market.authority.pubkey(),
+        &trade,
+        2,
+        TRADE_QUANTITY as i64,
+        -notional,
+    );
+    let result = send(&mut market.ctx, &[instruction], &[&market.authority]).await;
+    assert_error(result, ClobError::InsufficientBalance.into());
+    let taker = user_account(&mut market.ctx, &market.taker_account).await;
+    assert_eq!(taker.base_token_balance, TRADE_QUANTITY);
+}
+
+#[tokio::test]
+async fn sub_accounts_keep_separate_balances() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+    let maker = market.maker.pubkey();
+    let sub_account = sub_account_address(&maker, 1);
+    send(&mut market.ctx, &[initialize_sub_account_ix(&maker, 1, "hedging")], &[&market.maker]).await.unwrap();
+    let account = user_account(&mut market.ctx, &user_account_address(&sub_account)).await;
+    assert_eq!((account.owner, account.wallet, account.sub_account), (sub_account, maker, 1));
+
+    // Funded by a transfer from the wallet's own account and by a deposit
+    let instruction = transfer_between_sub_accounts_ix(&maker, &maker, &sub_account, TRADE_QUANTITY - 2, 0);
+    send(&mut market.ctx, &[instruction], &[&market.maker]).await.unwrap();
+    let instruction = deposit_to_ix(&market.orderbook, &maker, &sub_account, &market.maker_base, &market.base_mint, 2);
+    send(&mut market.ctx, &[instruction], &[&market.maker]).await.unwrap();
+
+    // The sub-account sells out of its own balance, leaving the wallet's alone
+    let mut trade = market.trade(OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY);
+    trade.maker = sub_account;
+    market.settle(trade).await.unwrap();
+    let notional = TRADE_PRICE * TRADE_QUANTITY;
+    let sub = user_account(&mut market.ctx, &user_account_address(&sub_account)).await;
+    assert_eq!((sub.base_token_balance, sub.quote_token_balance), (0, notional));
+    assert_eq!(sub.total_volume_traded, TRADE_QUANTITY);
+    let own = user_account(&mut market.ctx, &market.maker_account).await;
+    assert_eq!((own.base_token_balance, own.quote_token_balance), (BASE_DEPOSIT - TRADE_QUANTITY + 2, 0));
+    let result = market.settle(trade).await;
+    assert_error(result, ClobError::InsufficientBalance.into());
+
+    // Proceeds move back to the wallet's account to be withdrawn
+    let instruction = transfer_between_sub_accounts_ix(&maker, &sub_account, &maker, 0, notional + 1);
+    let result = send(&mut market.ctx, &[instruction], &[&market.maker]).await;
+    assert_error(result, ClobError::InsufficientBalance.into());
+    let instruction = transfer_between_sub_accounts_ix(&maker, &sub_account, &maker, 0, notional);
+    send(&mut market.ctx, &[instruction], &[&market.maker]).await.unwrap();
+    let instruction = withdraw_ix(&market.orderbook, &maker, &market.maker_quote, &market.quote_mint, notional);
+    send(&mut market.ctx, &[instruction], &[&market.maker]).await.unwrap();
+    assert_eq!(token_balance(&mut market.ctx, &market.maker_quote).await, notional);
+}
+
+#[tokio::test]
+async fn settle_match_takes_the_wallet_signature_of_a_sub_account() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+    let taker = market.taker.pubkey();
+    let sub_account = sub_account_address(&taker, 3);
+    send(&mut market.ctx, &[initialize_sub_account_ix(&taker, 3, "")], &[&market.taker]).await.unwrap();
+    let instruction = transfer_between_sub_accounts_ix(&taker, &taker, &sub_account, 0, QUOTE_DEPOSIT);
+    send(&mut market.ctx, &[instruction], &[&market.taker]).await.unwrap();
+
+    let maker_order = market.signed_order(&market.maker, OrderSide::Ask, TRADE_PRICE, TRADE_QUANTITY);
+    let mut trade = market.trade(OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY);
+    trade.taker = sub_account;
+
+    // Only the sub-account's wallet signs for it
+    let forged = market.signed_order_for(&sub_account, &market.maker, OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY);
+    let instructions = [
+        ed25519_ix_by(&forged, &maker_order.order_intent.owner),
+        ed25519_ix(&maker_order),
+        settle_match_ix(&market.orderbook, &market.authority.pubkey(), trade, forged, maker_order),
+    ];
+    let result = send(&mut market.ctx, &instructions, &[&market.authority]).await;
+    assert_error_at(result, 2, ClobError::InvalidOrderSignature.into());
+
+    let taker_order = market.signed_order_for(&sub_account, &market.taker, OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY);
+    let instructions = [
+        ed25519_ix_by(&taker_order, &taker),
+        ed25519_ix(&maker_order),
+        settle_match_ix(&market.orderbook, &market.authority.pubkey(), trade, taker_order, maker_order),
+    ];
+    send(&mut market.ctx, &instructions, &[&market.authority]).await.unwrap();
+    let sub = user_account(&mut market.ctx, &user_account_address(&sub_account)).await;
+    assert_eq!(sub.base_token_balance, TRADE_QUANTITY);
+    assert_eq!(sub.quote_token_balance, QUOTE_DEPOSIT - TRADE_PRICE * TRADE_QUANTITY);
+}
+
+#[tokio::test]
+async fn sub_account_rejections() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+    let maker = market.maker.pubkey();
+
+    // Index 0 is the wallet's own account; names are bounded
+    let result = send(&mut market.ctx, &[initialize_sub_account_ix(&maker, 0, "zero")], &[&market.maker]).await;
+    assert_error(result, ClobError::InvalidSubAccount.into());
+    let name = "n".repeat(svm_clob::SUB_ACCOUNT_NAME_MAX_LEN + 1);
+    let result = send(&mut market.ctx, &[initialize_sub_account_ix(&maker, 1, &name)], &[&market.maker]).await;
+    assert_error(result, ClobError::InvalidSubAccount.into());
+    send(&mut market.ctx, &[initialize_sub_account_ix(&maker, 1, "arb")], &[&market.maker]).await.unwrap();
+
+    // Transfers stay between distinct accounts of the signing wallet
+    let sub_account = sub_account_address(&maker, 1);
+    let instruction = transfer_between_sub_accounts_ix(&maker, &maker, &maker, 1, 0);
+    let result = send(&mut market.ctx, &[instruction], &[&market.maker]).await;
+    assert_error(result, ClobError::InvalidSubAccount.into());
+    let taker = market.taker.pubkey();
+    let instruction = transfer_between_sub_accounts_ix(&maker, &maker, &taker, 1, 0);
+    let result = send(&mut market.ctx, &[instruction], &[&market.maker]).await;
+    assert_error(result, ClobError::Unauthorized.into());
+    let instruction = transfer_between_sub_accounts_ix(&taker, &sub_account, &taker, 0, 0);
+    let result = send(&mut market.ctx, &[instruction], &[&market.taker]).await;
+    assert_error(result, ClobError::Unauthorized.into());
+
+    // Only the sub-account's wallet withdraws from it
+    let instruction = transfer_between_sub_accounts_ix(&maker, &maker, &sub_account, 1, 0);
+    send(&mut market.ctx, &[instruction], &[&market.maker]).await.unwrap();
+    let instruction = withdraw_from_ix(&market.orderbook, &taker, &sub_account, &maker, &market.taker_base, &market.base_mint, 1);
+    let result = send(&mut market.ctx, &[instruction], &[&market.taker]).await;
+    assert_error(result, ClobError::Unauthorized.into());
+    let instruction = withdraw_from_ix(&market.orderbook, &maker, &sub_account, &maker, &market.maker_base, &market.base_mint, 1);
+    send(&mut market.ctx, &[instruction], &[&market.maker]).await.unwrap();
+    assert_eq!(user_account(&mut market.ctx, &user_account_address(&sub_account)).await.base_token_balance, 0);
+}
+
+/// Tokens of each kind every golden scenario trader deposits
+const GOLDEN_DEPOSIT: u64 = 1_000_000;
+
+/// Orders in, fills and book out; see `svm_clob_infra/crates/core/golden`
+#[derive(Deserialize)]
+struct Scenario {
+    description: String,
+    orders: Vec<ScenarioOrder>,
+    fills: Vec<ScenarioFill>,
+}
+
+#[derive(Deserialize)]
+struct ScenarioOrder {
+    trader: usize,
+    side: OrderSide,
+    order_type: OrderType,
+    price: u64,
+    quantity: u64,
+    #[serde(default = "good_till_cancelled")]
+    time_in_force: TimeInForce,
+    #[serde(default = "cancel_provide")]
+    self_trade_behavior: SelfTradeBehavior,
+}
+
+/// Orders are numbered from 1 in the order they are listed
+#[derive(Deserialize)]
+struct ScenarioFill {
+    maker_order: u64,
+    taker_order: u64,
+    price: u64,
+    quantity: u64,
+}
+
+fn good_till_cancelled() -> TimeInForce {
+    TimeInForce::GoodTillCancelled
+}
+
+fn cancel_provide() -> SelfTradeBehavior {
+    SelfTradeBehavior::CancelProvide
+}
+
+fn golden_scenarios() -> Vec<(String, Scenario)> {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../../svm_clob_infra/crates/core/golden");
+    let mut paths: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no golden scenarios in {}", dir.display());
+    paths
+        .into_iter()
+        .map(|path| {
+            let scenario = serde_json::from_str(&std::fs::read_to_string(&path).unwrap())
+                .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+            (path.file_name().unwrap().to_string_lossy().into_owned(), scenario)
+        })
+        .collect()
+}
+
+/// A scenario order signed by its trader, its number as nonce; a market
+/// order's intent takes any price
+fn golden_signed_order(orderbook: &Pubkey, owner: &Keypair, number: u64, order: &ScenarioOrder) -> SignedOrder {
+    let price = match (order.order_type, order.side) {
+        (OrderType::Market, OrderSide::Bid) => u64::MAX,
+        (OrderType::Market, OrderSide::Ask) => 0,
+        _ => order.price,
+    };
+    let order_intent = OrderIntent {
+        market: *orderbook,
+        owner: owner.pubkey(),
+        client_order_id: number,
+        side: order.side,
+        order_type: order.order_type,
+        price,
+        quantity: order.quantity,
+        time_in_force: order.time_in_force,
+        self_trade_behavior: order.self_trade_behavior,
+    };
+    let (nonce, expiry) = (number, i64::MAX);
+    let signature = owner.sign_message(&SignedOrder::signing_message(&order_intent, nonce, expiry));
+    SignedOrder {
+        order_intent,
+        nonce,
+        expiry,
+        signature: signature.as_ref().try_into().unwrap(),
+    }
+}
+
+/// Every golden fill settles through `settle_match` against the signed
+/// orders it matched, so the program agrees each one trades at the maker's
+/// price, within the taker's limit and within both orders' quantities, and
+/// balances move by exactly the fills
+#[tokio::test]
+async fn settle_match_reproduces_golden_vectors() {
+    for (name, scenario) in golden_scenarios() {
+        let authority = Keypair::new();
+        let trader_count = scenario.orders.iter().map(|order| order.trader + 1).max().unwrap_or(0);
+        let traders: Vec<Keypair> = (0..trader_count).map(|_| Keypair::new()).collect();
+        let mut program_test = program_test();
+        for signer in std::iter::once(&authority).chain(&traders) {
+            program_test.add_account(signer.pubkey(), funded());
+        }
+        let mut ctx = program_test.start_with_context().await;
+
+        let base_mint = create_mint(&mut ctx, &authority).await;
+        let quote_mint = create_mint(&mut ctx, &authority).await;
+        send(&mut ctx, &[initialize_orderbook_ix(&authority.pubkey(), &base_mint, &quote_mint)], &[&authority])
+            .await
+            .unwrap();
+        let market = orderbook_address(&base_mint, &quote_mint);
+        for trader in &traders {
+            send(&mut ctx, &[initialize_user_account_ix(&trader.pubkey())], &[trader]).await.unwrap();
+            for mint in [&base_mint, &quote_mint] {
+                let account = create_token_account(&mut ctx, mint, &trader.pubkey()).await;
+                mint_to(&mut ctx, mint, &account, &authority, GOLDEN_DEPOSIT).await;
+                let deposit = deposit_ix(&market, &trader.pubkey(), &account, mint, GOLDEN_DEPOSIT);
+                send(&mut ctx, &[deposit], &[trader]).await.unwrap();
+            }
+        }
+
+        let signed: Vec<SignedOrder> = scenario
+            .orders
+            .iter()
+            .enumerate()
+            .map(|(index, order)| golden_signed_order(&market, &traders[order.trader], index as u64 + 1, order))
+            .collect();
+        // Expected (base, quote) balance of each trader
+        let mut balances = vec![(GOLDEN_DEPOSIT, GOLDEN_DEPOSIT); traders.len()];
+        let mut volume = 0;
+        for fill in &scenario.fills {
+            let taker = &scenario.orders[fill.taker_order as usize - 1];
+            let maker = &scenario.orders[fill.maker_order as usize - 1];
+            let trade = Trade {
+                taker_order_id: fill.taker_order,
+                maker_order_id: fill.maker_order,
+                taker: traders[taker.trader].pubkey(),
+                maker: traders[maker.trader].pubkey(),
+                price: fill.price,
+                quantity: fill.quantity,
+                taker_side: taker.side,
+                timestamp: 0,
+            };
+            let taker_order = signed[fill.taker_order as usize - 1];
+            let maker_order = signed[fill.maker_order as usize - 1];
+            let instructions = [
+                ed25519_ix(&taker_order),
+                ed25519_ix(&maker_order),
+                settle_match_ix(&market, &authority.pubkey(), trade, taker_order, maker_order),
+            ];
+            send(&mut ctx, &instructions, &[&authority]).await.unwrap_or_else(|e| {
+                panic!("{} ({}): fill of {} by {}: {:?}", name, scenario.description, fill.maker_order, fill.taker_order, e)
+            });
+
+            let (buyer, seller) = match taker.side {
+                OrderSide::Bid => (taker.trader, maker.trader),
+                OrderSide::Ask => (maker.trader, taker.trader),
+            };
+            balances[buyer].0 += fill.quantity;
+            balances[buyer].1 -= fill.price * fill.quantity;
+            balances[seller].0 -= fill.quantity;
+            balances[seller].1 += fill.price * fill.quantity;
+            volume += fill.quantity;
+        }
+
+        for (index, trader) in traders.iter().enumerate() {
+            let account = user_account(&mut ctx, &user_account_address(&trader.pubkey())).await;
+            assert_eq!(
+                (account.base_token_balance, account.quote_token_balance),
+                balances[index],
+                "{}: balances of trader {}",
+                name,
+                index
+            );
+        }
+        assert_eq!(orderbook(&mut ctx, &market).await.total_volume, volume, "{}: volume", name);
+    }
+}
+
+// Appending to a receipt tree calls SPL account compression, which these
+// tests do not load; they cover what the program checks before calling it
+
+#[tokio::test]
+async fn initialize_receipt_tree_requires_the_market_authority() {
+    let mut market = Market::new().await;
+    let merkle_tree = Pubkey::new_unique();
+    market.ctx.set_account(&merkle_tree, &Account::new(LAMPORTS, 1024, &SPL_ACCOUNT_COMPRESSION_ID).into());
+
+    let intruder = Keypair::new();
+    let transfer = system_instruction::transfer(&market.ctx.payer.pubkey(), &intruder.pubkey(), LAMPORTS / 10);
+    send(&mut market.ctx, &[transfer], &[]).await.unwrap();
+    let instruction = initialize_receipt_tree_ix(&market.orderbook, &intruder.pubkey(), &merkle_tree);
+    let result = send(&mut market.ctx, &[instruction], &[&intruder]).await;
+    assert_error(result, ClobError::InvalidAuthority.into());
+    assert_eq!(orderbook(&mut market.ctx, &market.orderbook).await.compressed_receipts, 0);
+}
+
+#[tokio::test]
+async fn compressed_market_settles_only_with_its_receipt_tree() {
+    let mut market = Market::new().await;
+    market.deposit_both().await;
+    // As `initialize_receipt_tree` leaves the market
+    let mut account = market.ctx.banks_client.get_account(market.orderbook).await.unwrap().unwrap();
+    account.data[8 + std::mem::offset_of!(OrderBook, compressed_receipts)] = 1;
+    market.ctx.set_account(&market.orderbook, &account.into());
+
+    let trade = market.trade(OrderSide::Bid, TRADE_PRICE, TRADE_QUANTITY);
+    let result = market.settle(trade).await;
+    assert_error(result, ClobError::ReceiptTreeRequired.into());
+
+    // A receipt tree the program never created is refused too
+    let mut instruction = execute_trade_ix(&market.orderbook, &market.authority.pubkey(), trade);
+    instruction.accounts.extend([
+        AccountMeta::new(receipt_tree_address(&market.orderbook), false),
+        AccountMeta::new(Pubkey::new_unique(), false),
+        AccountMeta::new_readonly(SPL_NOOP_ID, false),
+        AccountMeta::new_readonly(SPL_ACCOUNT_COMPRESSION_ID, false),
+    ]);
+    market.ctx.get_new_latest_blockhash().await.unwrap();
+    let result = send(&mut market.ctx, &[instruction], &[&market.authority]).await;
+    assert_error(result, ClobError::ReceiptTreeRequired.into());
+    assert_eq!(user_account(&mut market.ctx, &market.taker_account).await.quote_token_balance, QUOTE_DEPOSIT);
+}
+
+/// Error codes are part of the client interface; the order-parameter errors
+/// are raised by the off-chain engine today and have no on-chain path yet
+#[test]
+fn error_codes_are_stable() {
+    let codes: [(ClobError, u32); 23] = [
+        (ClobError::InvalidPrice, 6000),
+        (ClobError::InvalidQuantity, 6001),
+        (ClobError::OrderSizeBelowMinimum, 6002),
+        (ClobError::PriceNotAlignedToTickSize, 6003),
+        (ClobError::OrderbookPaused, 6004),
+        (ClobError::InsufficientBalance, 6005),
+        (ClobError::Unauthorized, 6006),
+        (ClobError::InvalidMint, 6007),
+        (ClobError::InvalidAuthority, 6008),
+        (ClobError::SlippageExceeded, 6009),
+        (ClobError::InvalidOrderSignature, 6010),
+        (ClobError::SignedOrderExpired, 6011),
+        (ClobError::OrderIntentMismatch, 6012),
+        (ClobError::DestinationNotWhitelisted, 6013),
+        (ClobError::WhitelistChangeNotReady, 6014),
+        (ClobError::WhitelistFull, 6015),
+        (ClobError::MarketCancelOnly, 6016),
+        (ClobError::SignedOrderOverfilled, 6017),
+        (ClobError::SignedOrderNonceReused, 6018),
+        (ClobError::SignedOrderNotExpired, 6019),
+        (ClobError::InvalidSubAccount, 6020),
+        (ClobError::ReceiptTreeRequired, 6021),
+        (ClobError::ReceiptTreeFull, 6022),
+    ];
+    for (error, code) in codes {
+        let name = format!("{error:?}");
+        assert_eq!(u32::from(error), code, "{name}");
+    }
+}
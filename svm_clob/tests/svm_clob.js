@@ -25,6 +25,8 @@ describe("svm_clob", () => {
   let makerAccountPda;
   let clobBaseVault;
   let clobQuoteVault;
+  let takerWhitelistPda;
+  let makerWhitelistPda;
 
   const BASE_DEPOSIT = new anchor.BN(50);
   const QUOTE_DEPOSIT = new anchor.BN(100);
@@ -135,6 +137,16 @@ describe("svm_clob", () => {
       [Buffer.from("clob_vault"), quoteMint.toBuffer()],
       program.programId
     );
+
+    [takerWhitelistPda] = await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from("withdrawal_whitelist"), taker.publicKey.toBuffer()],
+      program.programId
+    );
+
+    [makerWhitelistPda] = await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from("withdrawal_whitelist"), maker.publicKey.toBuffer()],
+      program.programId
+    );
   });
 
   it("initializes orderbook and user accounts", async () => {
@@ -370,6 +382,7 @@ describe("svm_clob", () => {
         clobTokenVault: clobBaseVault,
         user: taker.publicKey,
         tokenProgram: spl.TOKEN_PROGRAM_ID,
+        withdrawalWhitelist: takerWhitelistPda,
       })
       .signers([taker])
       .rpc();
@@ -384,6 +397,7 @@ describe("svm_clob", () => {
         clobTokenVault: clobQuoteVault,
         user: maker.publicKey,
         tokenProgram: spl.TOKEN_PROGRAM_ID,
+        withdrawalWhitelist: makerWhitelistPda,
       })
       .signers([maker])
       .rpc();
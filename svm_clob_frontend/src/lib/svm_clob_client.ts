@@ -21,6 +21,7 @@ import {
   OrderSide, 
   OrderType 
 } from '../types/svm_clob';
+import { fromFrame, unwrapEnvelope } from './wire';
 
 // Off-chain API types
 export interface OffChainOrder {
@@ -164,7 +165,7 @@ export class SvmClobClient {
       throw new Error(`API error: ${data.error.message} (code: ${data.error.code})`);
     }
 
-    return data.result!;
+    return unwrapEnvelope<T>(data.result);
   }
 
   // Generate PDA addresses
@@ -192,6 +193,16 @@ export class SvmClobClient {
     );
   }
 
+  async getWithdrawalWhitelistAddress(owner: PublicKey): Promise<[PublicKey, number]> {
+    return PublicKey.findProgramAddressSync(
+      [
+        Buffer.from('withdrawal_whitelist'),
+        owner.toBuffer()
+      ],
+      this.programId
+    );
+  }
+
   async getClobTokenVaultAddress(tokenMint: PublicKey): Promise<[PublicKey, number]> {
     return PublicKey.findProgramAddressSync(
       [
@@ -285,6 +296,7 @@ export class SvmClobClient {
     const [orderbookPda] = await this.getOrderbookAddress(baseMint, quoteMint);
     const [userAccountPda] = await this.getUserAccountAddress(user);
     const [clobTokenVaultPda] = await this.getClobTokenVaultAddress(tokenMint);
+    const [withdrawalWhitelistPda] = await this.getWithdrawalWhitelistAddress(user);
     
     const userTokenAccount = await getAssociatedTokenAddress(tokenMint, user);
 
@@ -298,6 +310,7 @@ export class SvmClobClient {
         clobTokenVault: clobTokenVaultPda,
         user,
         tokenProgram: TOKEN_PROGRAM_ID,
+        withdrawalWhitelist: withdrawalWhitelistPda,
       })
       .instruction();
   }
@@ -483,7 +496,7 @@ export class SvmClobClient {
 
         this.ws.onmessage = (event) => {
           try {
-            const message: WebSocketMessage = JSON.parse(event.data);
+            const message = fromFrame(JSON.parse(event.data)) as WebSocketMessage;
             this.handleWebSocketMessage(message);
           } catch (error) {
             console.error('Error parsing WebSocket message:', error);
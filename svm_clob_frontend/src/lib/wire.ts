@@ -0,0 +1,30 @@
+/**
+ * Versioned wire envelopes
+ *
+ * svm_clob_infra wraps REST results and WebSocket frames as
+ * `{ v, type, payload }`. These helpers unwrap them and still accept the
+ * unversioned messages of older servers.
+ */
+
+export interface Envelope<T = unknown> {
+  v: number;
+  type: string;
+  payload: T;
+}
+
+function isEnvelope(value: unknown): value is Envelope {
+  return typeof value === 'object' && value !== null && 'v' in value && 'payload' in value;
+}
+
+/** Payload of a REST result, whether or not it is enveloped */
+export function unwrapEnvelope<T>(result: unknown): T {
+  return (isEnvelope(result) ? result.payload : result) as T;
+}
+
+/** WebSocket frame as `{ type, data }`, whether or not it is enveloped */
+export function fromFrame(frame: any): { type: string; data: any } {
+  if (isEnvelope(frame)) {
+    return { ...frame, data: frame.payload };
+  }
+  return frame;
+}
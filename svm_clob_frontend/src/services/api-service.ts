@@ -5,6 +5,8 @@
  * Based on the API specification from svm_clob_infra/README.md
  */
 
+import { unwrapEnvelope } from '../lib/wire';
+
 // API Response wrapper
 export interface ApiResponse<T> {
   success: boolean;
@@ -147,7 +149,7 @@ export class ApiService {
       const result = await response.json();
       return {
         success: true,
-        data: result.data || result.result || result,
+        data: result.data || unwrapEnvelope(result.result) || result,
         timestamp: Date.now(),
       };
     } catch (error: any) {
@@ -5,6 +5,8 @@
  * Based on the WebSocket specification from svm_clob_infra/README.md
  */
 
+import { fromFrame } from '../lib/wire';
+
 // Subscription types (matching svm_clob_infra specification)
 export interface SubscriptionRequest {
   type: 'OrderBook' | 'Trades' | 'UserOrders' | 'AllMarkets';
@@ -103,7 +105,7 @@ export class WebSocketService {
 
         this.ws.onmessage = (event) => {
           try {
-            const message: WebSocketMessage = JSON.parse(event.data);
+            const message = fromFrame(JSON.parse(event.data)) as WebSocketMessage;
             this.handleMessage(message);
           } catch (error) {
             console.error('Failed to parse WebSocket message:', error);
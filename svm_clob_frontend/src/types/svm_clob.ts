@@ -292,6 +292,11 @@ export type SvmClobIDL = {
           "writable": false,
           "signer": false,
           "address": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"
+        },
+        {
+          "name": "withdrawalWhitelist",
+          "writable": false,
+          "signer": false
         }
       ],
       "args": [
@@ -752,6 +757,7 @@ export const PROGRAM_ID = new PublicKey('JBphRWHYzHCiVvYB89vGM9NpaDmHbe1A9W156sR
 export const ORDERBOOK_SEED = 'orderbook';
 export const USER_ACCOUNT_SEED = 'user_account';
 export const CLOB_VAULT_SEED = 'clob_vault';
+export const WITHDRAWAL_WHITELIST_SEED = 'withdrawal_whitelist';
 
 // Error codes from the contract
 export enum ClobErrorCode {
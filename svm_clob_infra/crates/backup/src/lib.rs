@@ -0,0 +1,335 @@
+/// Backup and Point-in-Time Restore for SVM CLOB Infrastructure
+///
+/// A backup is a directory holding a `pg_dump` of the database, the engine's
+/// book at the journal head and the journal itself in fixed-size segments,
+/// described by a versioned `manifest.json` that is written last, so a backup
+/// without a manifest is incomplete. The journal head is read before the dump
+/// starts and `pg_dump` reads one consistent snapshot, so the dump holds every
+/// entry the segments and the book cover.
+///
+/// A restore rebuilds the book at the last journal entry at or before the
+/// requested time by replaying the archived journal, loads the dump, rewinds
+/// the database to that entry and writes the rebuilt book back to the orders
+/// table, so the database and a restarted engine agree on the book at that
+/// point. Settlements already confirmed on chain cannot be rewound; the
+/// reconciler reports them after restoring to an earlier point.
+
+use svm_clob_types::*;
+use svm_clob_matching_engine::MatchingEngine;
+use svm_clob_storage::{InMemoryStorage, Storage};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+/// Archive layout written to, and required in, the manifest
+pub const ARCHIVE_VERSION: u32 = 1;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const DATABASE_FILE: &str = "database.dump";
+const BOOK_FILE: &str = "book.json";
+const JOURNAL_DIR: &str = "journal";
+
+/// Backup configuration
+#[derive(Debug, Clone)]
+pub struct BackupConfig {
+    /// PostgreSQL URL handed to `pg_dump` and `pg_restore`
+    pub database_url: String,
+    /// Journal entries per segment file
+    pub segment_size: u32,
+    /// Services whose checkpoints are journal sequences, rewound on restore
+    pub journal_consumers: Vec<String>,
+    /// `pg_dump` executable
+    pub pg_dump: String,
+    /// `pg_restore` executable
+    pub pg_restore: String,
+    /// Allocation mode of the market, needed to replay its journal
+    pub allocation: AllocationMode,
+}
+
+/// Contents of a backup directory
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupManifest {
+    pub version: u32,
+    pub created_at: i64,
+    pub market_id: MarketId,
+    /// Allocation mode the journal was matched under
+    #[serde(default)]
+    pub allocation: AllocationMode,
+    /// Last journal sequence the segments and the book cover
+    pub journal_head: u64,
+    /// `pg_dump` archive in custom format
+    pub database: String,
+    /// `BookSnapshot` at `journal_head`
+    pub book: String,
+    /// Journal segments in sequence order
+    pub segments: Vec<JournalSegment>,
+}
+
+/// File of consecutive journal entries
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JournalSegment {
+    pub file: String,
+    pub first_sequence: u64,
+    pub last_sequence: u64,
+    pub first_timestamp: i64,
+    pub last_timestamp: i64,
+}
+
+/// Resting orders after a journal entry
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BookSnapshot {
+    pub journal_sequence: u64,
+    pub orders: Vec<Order>,
+}
+
+/// Outcome of a restore
+#[derive(Serialize, Debug, Clone)]
+pub struct RestoreReport {
+    /// Last journal entry kept
+    pub journal_sequence: u64,
+    /// Orders, trades and book snapshots after this time were removed
+    pub timestamp: i64,
+    /// Entries of the backup's journal past the restore point
+    pub entries_discarded: u64,
+    pub open_orders: usize,
+    /// Open orders in the database that were not resting at the restore point
+    /// and were marked cancelled
+    pub orders_cancelled: usize,
+    pub best_bid: Option<u64>,
+    pub best_ask: Option<u64>,
+}
+
+/// Write a backup of `storage`, and of the database behind it, to `dir`,
+/// which must not exist yet
+pub async fn create_backup<S: Storage>(
+    storage: &S,
+    orderbook: &OrderBook,
+    market_id: MarketId,
+    config: &BackupConfig,
+    dir: &Path,
+) -> ClobResult<BackupManifest> {
+    if dir.exists() {
+        return Err(ClobError::StorageError(format!("Backup directory {} already exists", dir.display())));
+    }
+    tokio::fs::create_dir_all(dir.join(JOURNAL_DIR)).await.map_err(io_error)?;
+
+    // Everything up to the head read here is also in the dump taken below
+    let journal_head = storage.get_journal_head().await?;
+    let engine = offline_engine(orderbook, market_id, config.allocation);
+    let mut segments = Vec::new();
+    let mut position = 0;
+    while position < journal_head {
+        let mut entries = storage.get_journal_entries(position, config.segment_size).await?;
+        entries.retain(|entry| entry.sequence <= journal_head);
+        let (Some(first), Some(last)) = (entries.first(), entries.last()) else {
+            break;
+        };
+        let segment = JournalSegment {
+            file: format!("{}/{:020}.json", JOURNAL_DIR, first.sequence),
+            first_sequence: first.sequence,
+            last_sequence: last.sequence,
+            first_timestamp: first.timestamp,
+            last_timestamp: last.timestamp,
+        };
+        write_json(&dir.join(&segment.file), &entries).await?;
+        for entry in &entries {
+            engine.replay_journal_entry(entry).await?;
+        }
+        position = segment.last_sequence;
+        segments.push(segment);
+    }
+
+    let book = BookSnapshot {
+        journal_sequence: position,
+        orders: engine.get_open_orders().await,
+    };
+    write_json(&dir.join(BOOK_FILE), &book).await?;
+
+    let mut pg_dump = Command::new(&config.pg_dump);
+    pg_dump
+        .arg("--format=custom")
+        .arg("--file")
+        .arg(dir.join(DATABASE_FILE))
+        .arg(&config.database_url);
+    run(pg_dump).await?;
+
+    let manifest = BackupManifest {
+        version: ARCHIVE_VERSION,
+        created_at: chrono::Utc::now().timestamp(),
+        market_id,
+        allocation: config.allocation,
+        journal_head: position,
+        database: DATABASE_FILE.to_string(),
+        book: BOOK_FILE.to_string(),
+        segments,
+    };
+    write_json(&dir.join(MANIFEST_FILE), &manifest).await?;
+
+    info!(
+        "Backed up journal through {} ({} resting orders) to {}",
+        position,
+        book.orders.len(),
+        dir.display()
+    );
+    Ok(manifest)
+}
+
+/// Read and check the manifest of a backup
+pub async fn read_manifest(dir: &Path) -> ClobResult<BackupManifest> {
+    let manifest: BackupManifest = read_json(&dir.join(MANIFEST_FILE)).await?;
+    if manifest.version != ARCHIVE_VERSION {
+        return Err(ClobError::SerializationError(format!(
+            "Backup version {} is not supported (expected {})",
+            manifest.version, ARCHIVE_VERSION
+        )));
+    }
+    Ok(manifest)
+}
+
+/// Restore of one backup to one point in time
+///
+/// Built from the backup alone, so a damaged backup is found before the
+/// database is touched. `restore_database` then replaces the database with
+/// the dump, and `apply` rewinds it to the restore point.
+pub struct RestorePlan {
+    pub manifest: BackupManifest,
+    /// Book at the last journal entry kept
+    pub book: BookSnapshot,
+    /// Orders and trades after this time are removed
+    pub timestamp: i64,
+}
+
+impl RestorePlan {
+    /// Plan a restore of the backup in `dir` as of `at` (unix seconds), or as
+    /// of the end of the backup when `at` is `None`
+    pub async fn prepare(dir: &Path, orderbook: &OrderBook, at: Option<i64>) -> ClobResult<Self> {
+        let manifest = read_manifest(dir).await?;
+        let last_timestamp = manifest.segments.last().map_or(manifest.created_at, |segment| segment.last_timestamp);
+
+        let book = match at {
+            Some(at) if at < last_timestamp => {
+                let engine = offline_engine(orderbook, manifest.market_id, manifest.allocation);
+                let mut position = 0;
+                'segments: for segment in &manifest.segments {
+                    if segment.first_timestamp > at {
+                        break;
+                    }
+                    let entries: Vec<JournalEntry> = read_json(&dir.join(&segment.file)).await?;
+                    for entry in &entries {
+                        if entry.timestamp > at {
+                            break 'segments;
+                        }
+                        engine.replay_journal_entry(entry).await?;
+                        position = entry.sequence;
+                    }
+                }
+                BookSnapshot {
+                    journal_sequence: position,
+                    orders: engine.get_open_orders().await,
+                }
+            }
+            _ => read_json(&dir.join(&manifest.book)).await?,
+        };
+
+        Ok(Self {
+            timestamp: at.unwrap_or(last_timestamp).min(last_timestamp),
+            manifest,
+            book,
+        })
+    }
+
+    /// Replace the database with the backup's dump
+    pub async fn restore_database(&self, dir: &Path, config: &BackupConfig) -> ClobResult<()> {
+        let mut pg_restore = Command::new(&config.pg_restore);
+        pg_restore
+            .args(["--clean", "--if-exists", "--no-owner", "--single-transaction", "--dbname"])
+            .arg(&config.database_url)
+            .arg(dir.join(&self.manifest.database));
+        run(pg_restore).await?;
+        info!("Restored database from {}", dir.display());
+        Ok(())
+    }
+
+    /// Rewind restored storage to the restore point and write the book back
+    pub async fn apply<S: Storage>(&self, storage: &S, config: &BackupConfig) -> ClobResult<RestoreReport> {
+        storage
+            .rewind(self.book.journal_sequence, self.timestamp, &config.journal_consumers)
+            .await?;
+
+        for order in &self.book.orders {
+            match storage.update_order(order).await {
+                Err(ClobError::OrderNotFound) => storage.store_order(order).await?,
+                result => result?,
+            }
+        }
+
+        // Orders placed before the restore point but filled, cancelled or
+        // replaced after it are resting again; anything else still open is not
+        let resting: HashSet<u64> = self.book.orders.iter().map(|order| order.order_id).collect();
+        let mut orders_cancelled = 0;
+        for mut order in storage.get_open_orders().await? {
+            if !resting.contains(&order.order_id) {
+                warn!("Order {} was not resting at the restore point, marking it cancelled", order.order_id);
+                order.status = OrderStatus::Cancelled;
+                storage.update_order(&order).await?;
+                orders_cancelled += 1;
+            }
+        }
+
+        let best = |side: OrderSide| self.book.orders.iter().filter(move |order| order.side == side).map(|order| order.price);
+        let report = RestoreReport {
+            journal_sequence: self.book.journal_sequence,
+            timestamp: self.timestamp,
+            entries_discarded: self.manifest.journal_head - self.book.journal_sequence,
+            open_orders: self.book.orders.len(),
+            orders_cancelled,
+            best_bid: best(OrderSide::Bid).max(),
+            best_ask: best(OrderSide::Ask).min(),
+        };
+        info!(
+            "Restored to journal sequence {} at {}: {} resting orders, {} journal entries discarded",
+            report.journal_sequence, report.timestamp, report.open_orders, report.entries_discarded
+        );
+        Ok(report)
+    }
+}
+
+/// Engine that only replays; nothing it does is persisted
+fn offline_engine(orderbook: &OrderBook, market_id: MarketId, allocation: AllocationMode) -> MatchingEngine<InMemoryStorage> {
+    let mut engine = MatchingEngine::new(Arc::new(InMemoryStorage::new()), orderbook.clone());
+    engine.set_market_id(market_id);
+    engine.set_allocation(allocation);
+    engine
+}
+
+async fn run(mut command: Command) -> ClobResult<()> {
+    let output = command.output().await.map_err(io_error)?;
+    if !output.status.success() {
+        return Err(ClobError::StorageError(format!(
+            "{:?} failed with {}: {}",
+            command.as_std().get_program(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+async fn write_json<T: Serialize>(path: &Path, value: &T) -> ClobResult<()> {
+    let bytes = serde_json::to_vec(value).map_err(|e| ClobError::SerializationError(e.to_string()))?;
+    tokio::fs::write(path, bytes).await.map_err(io_error)
+}
+
+async fn read_json<T: DeserializeOwned>(path: &Path) -> ClobResult<T> {
+    let bytes = tokio::fs::read(path).await.map_err(io_error)?;
+    serde_json::from_slice(&bytes)
+        .map_err(|e| ClobError::SerializationError(format!("{}: {}", path.display(), e)))
+}
+
+fn io_error(e: std::io::Error) -> ClobError {
+    ClobError::StorageError(e.to_string())
+}
@@ -0,0 +1,48 @@
+/// CPU pinning of the order path
+///
+/// With `[matching_engine] cpu_cores` set, the node serves the RPC API, where
+/// every order command is matched, from a runtime of its own whose worker
+/// threads are each pinned to one of the listed cores. Matching then runs on
+/// cores the rest of the node does not share, and is never migrated between
+/// cores mid-burst. Background services stay on the node's main runtime.
+
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Multi-threaded runtime with one worker per core of `cores`, each pinned to
+/// its core; blocking threads are pinned round-robin over the same cores
+pub fn pinned_runtime(cores: &[usize]) -> io::Result<tokio::runtime::Runtime> {
+    if cores.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "no cores to pin to"));
+    }
+    let available: Vec<usize> = core_affinity::get_core_ids()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|core| core.id)
+        .collect();
+    if let Some(missing) = cores.iter().find(|core| !available.contains(core)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("core {} is not available to this process", missing),
+        ));
+    }
+
+    let cores: Arc<[usize]> = cores.into();
+    let next = Arc::new(AtomicUsize::new(0));
+    let pinned = cores.clone();
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(cores.len())
+        .thread_name("clob-pinned")
+        .enable_all()
+        .on_thread_start(move || {
+            let core = pinned[next.fetch_add(1, Ordering::Relaxed) % pinned.len()];
+            if !core_affinity::set_for_current_thread(core_affinity::CoreId { id: core }) {
+                warn!("Failed to pin a worker thread to core {}", core);
+            }
+        })
+        .build()?;
+    info!("Order path pinned to cores {:?}", cores);
+    Ok(runtime)
+}
@@ -0,0 +1,198 @@
+/// Human-unit display of prices and sizes
+///
+/// The trading and watch commands look their market up in the registry served
+/// at `GET /api/v1/markets` and use its mint decimals and tick size to read
+/// prices and quantities typed in whole tokens, and to print them back with the
+/// precision and thousands separator of `[display]`. Inputs convert exactly or
+/// not at all: a value finer than one native unit, off the tick or out of range
+/// is refused rather than rounded. Markets listed without decimals are read and
+/// shown in native units.
+
+use crate::DisplaySettings;
+use svm_clob_sdk::ClobClient;
+use svm_clob_types::{ClobError, ClobResult, MarketId, MarketInfo, MarketUnits, Notional, Price, Quantity};
+
+/// Layout of printed numbers
+#[derive(Debug, Clone, Default)]
+pub struct NumberFormat {
+    /// Fraction digits of prices and quote amounts; as many as needed when unset
+    pub price_precision: Option<u32>,
+    /// Fraction digits of base quantities; as many as needed when unset
+    pub quantity_precision: Option<u32>,
+    /// Separator between groups of three integer digits; ungrouped when unset
+    pub thousands_separator: Option<char>,
+}
+
+impl From<&DisplaySettings> for NumberFormat {
+    fn from(settings: &DisplaySettings) -> Self {
+        Self {
+            price_precision: settings.price_precision,
+            quantity_precision: settings.quantity_precision,
+            thousands_separator: settings.thousands_separator.as_deref().and_then(|separator| separator.chars().next()),
+        }
+    }
+}
+
+/// Units and number layout of one market
+#[derive(Debug, Clone)]
+pub struct MarketDisplay {
+    info: MarketInfo,
+    /// `None` when the registry lists no decimals for the market
+    units: Option<MarketUnits>,
+    format: NumberFormat,
+}
+
+impl MarketDisplay {
+    pub fn new(info: MarketInfo, format: NumberFormat) -> Self {
+        let units = info.metadata.units(info.tick_size);
+        Self { info, units, format }
+    }
+
+    /// Display of `market_id` as listed by the server behind `client`
+    pub async fn fetch(client: &ClobClient, market_id: &MarketId, format: NumberFormat) -> ClobResult<Self> {
+        let info = client
+            .get_markets()
+            .await?
+            .into_iter()
+            .find(|market| market.market_id == *market_id)
+            .ok_or_else(|| ClobError::UnknownMarket(market_id.to_string()))?;
+        Ok(Self::new(info, format))
+    }
+
+    pub fn info(&self) -> &MarketInfo {
+        &self.info
+    }
+
+    /// Whether prices and sizes are read and shown in whole tokens
+    pub fn has_units(&self) -> bool {
+        self.units.is_some()
+    }
+
+    /// Native price of `input`, in whole quote tokens per whole base token
+    /// when the market's decimals are known
+    pub fn parse_price(&self, input: &str) -> ClobResult<Price> {
+        let input = self.strip_separators(input);
+        match &self.units {
+            Some(units) => units.price_from_ui(&input),
+            None => {
+                let price = input
+                    .parse::<u64>()
+                    .map(Price)
+                    .map_err(|_| ClobError::InvalidPrice(format!("'{}' is not a native price", input)))?;
+                if !price.is_aligned(self.info.tick_size) {
+                    return Err(ClobError::PriceNotAlignedToTickSize);
+                }
+                Ok(price)
+            }
+        }
+    }
+
+    /// Native quantity of `input`, in whole base tokens when the market's
+    /// decimals are known
+    pub fn parse_quantity(&self, input: &str) -> ClobResult<Quantity> {
+        let input = self.strip_separators(input);
+        match &self.units {
+            Some(units) => units.quantity_from_ui(&input),
+            None => input
+                .parse::<u64>()
+                .map(Quantity)
+                .map_err(|_| ClobError::InvalidQuantity(format!("'{}' is not a native quantity", input))),
+        }
+    }
+
+    pub fn price(&self, price: Price) -> String {
+        match &self.units {
+            Some(units) => self.layout(&units.price_to_ui(price), self.format.price_precision),
+            None => self.layout(&price.0.to_string(), None),
+        }
+    }
+
+    pub fn quantity(&self, quantity: Quantity) -> String {
+        match &self.units {
+            Some(units) => self.layout(&units.quantity_to_ui(quantity), self.format.quantity_precision),
+            None => self.layout(&quantity.0.to_string(), None),
+        }
+    }
+
+    pub fn notional(&self, notional: Notional) -> String {
+        match &self.units {
+            Some(units) => self.layout(&units.notional_to_ui(notional), self.format.price_precision),
+            None => self.layout(&notional.0.to_string(), None),
+        }
+    }
+
+    /// Base symbol, or `base` when the registry has none
+    pub fn base_symbol(&self) -> &str {
+        self.info.metadata.base_symbol.as_deref().unwrap_or("base")
+    }
+
+    /// Quote symbol, or `quote` when the registry has none
+    pub fn quote_symbol(&self) -> &str {
+        self.info.metadata.quote_symbol.as_deref().unwrap_or("quote")
+    }
+
+    /// `input` without the separators a reader may have copied from output
+    fn strip_separators(&self, input: &str) -> String {
+        match self.format.thousands_separator {
+            Some(separator) => input.trim().chars().filter(|&c| c != separator).collect(),
+            None => input.trim().to_string(),
+        }
+    }
+
+    fn layout(&self, decimal: &str, precision: Option<u32>) -> String {
+        let decimal = match precision {
+            Some(precision) => round(decimal, precision),
+            None => decimal.to_string(),
+        };
+        match self.format.thousands_separator {
+            Some(separator) => group(&decimal, separator),
+            None => decimal,
+        }
+    }
+}
+
+/// `decimal` rounded half up to `precision` fraction digits, padded with zeros
+fn round(decimal: &str, precision: u32) -> String {
+    let precision = precision as usize;
+    let (whole, fraction) = decimal.split_once('.').unwrap_or((decimal, ""));
+    if fraction.len() <= precision {
+        return match precision {
+            0 => whole.to_string(),
+            _ => format!("{}.{:0<width$}", whole, fraction, width = precision),
+        };
+    }
+
+    // Too many digits to round exactly: print the value as it is
+    let Ok(kept) = format!("{}{}", whole, &fraction[..precision]).parse::<u128>() else {
+        return decimal.to_string();
+    };
+    let Some(rounded) = kept.checked_add((fraction.as_bytes()[precision] >= b'5') as u128) else {
+        return decimal.to_string();
+    };
+    let digits = format!("{:0>width$}", rounded, width = precision + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - precision);
+    match precision {
+        0 => whole.to_string(),
+        _ => format!("{}.{}", whole, fraction),
+    }
+}
+
+/// `decimal` with its integer digits grouped by three
+fn group(decimal: &str, separator: char) -> String {
+    let (whole, fraction) = match decimal.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (decimal, None),
+    };
+    let mut grouped = String::with_capacity(decimal.len() + whole.len() / 3);
+    for (index, digit) in whole.chars().enumerate() {
+        if index > 0 && (whole.len() - index) % 3 == 0 {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        grouped.push('.');
+        grouped.push_str(fraction);
+    }
+    grouped
+}
@@ -4,17 +4,83 @@
 /// the SVM CLOB infrastructure components.
 
 use svm_clob_types::*;
-use svm_clob_storage::PostgresStorage;
+use svm_clob_storage::{FieldCipher, PostgresStorage, Storage};
 use svm_clob_rpc_server::{RpcServerState, start_server as start_rpc_server};
+use svm_clob_rpc_server::breaker::CircuitBreaker;
+use svm_clob_rpc_server::idempotency::IdempotencyCache;
+use svm_clob_rpc_server::nonce::{NonceStore, DEFAULT_MAX_SIGNATURE_TTL_SECS};
+use svm_clob_rpc_server::paper::{PaperExchange, DEFAULT_PAPER_BALANCE};
+use svm_clob_rpc_server::rate_limit::{RateLimiter, RateLimits};
+use svm_clob_rpc_server::speed_bump::SpeedBumpGate;
+use svm_clob_rpc_server::suspension::SuspensionList;
+use svm_clob_rpc_server::tenant::TenantDirectory;
 use svm_clob_websocket_server::{WebSocketServerState, start_server as start_ws_server};
+use svm_clob_serve::{ListenOptions, TlsFiles};
+use svm_clob_infra::{spawn_book_publisher, spawn_chain_pause, spawn_expiry_sweeper};
 use svm_clob_matching_engine::MatchingEngine;
-use clap::{Parser, Subcommand};
+use svm_clob_indexer::{Indexer, IndexerConfig, IngestionMode};
+use svm_clob_settler::{Settler, SettlerConfig};
+use svm_clob_reconciler::{Reconciler, ReconcilerConfig};
+use svm_clob_mm::{MarketMaker, MarketMakerConfig, SymmetricParams, SymmetricStrategy};
+use svm_clob_sdk::{limit_order, market_order, post_only_order, ClobClient};
+use svm_clob_risk::{HermesClient, RiskEngine, RiskLimits};
+use svm_clob_sim::{events_from_journal, load_journal, ReplayStep, Replayer, SimStrategy, Simulator, SyntheticFlow, SyntheticFlowConfig};
+use svm_clob_ha::{HaNode, Role, MATCHER_LEASE};
+use svm_clob_surveillance::{SurveillanceConfig, SurveillanceService};
+use svm_clob_fees::{FeeAccrualService, FeeConfig, FeeSchedule, FeeTierService};
+use svm_clob_incentives::{IncentiveConfig, IncentiveService, ObligationConfig, ObligationService};
+use svm_clob_statements::{StatementConfig, StatementService};
+use svm_clob_backup::{BackupConfig, RestorePlan};
+use svm_clob_recorder::{DomRecorder, RecorderConfig, Recording};
+use clap::{Parser, Subcommand, ValueEnum};
 use config::{Config, File, Environment};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tracing::{info, error};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tracing::{info, error, warn};
+
+pub mod affinity;
+pub mod display;
+pub mod network;
+pub mod schema;
+
+use display::{MarketDisplay, NumberFormat};
+use network::Network;
+
+/// How often the journal is read for replacements and protection pulls to
+/// tell their owners about
+const ORDER_FEED_INTERVAL_MS: u64 = 100;
+
+/// Journal entries read per poll of the order feed
+const ORDER_FEED_BATCH: u32 = 1000;
+
+/// How often the book is checkpointed for `GET /api/v1/orderbook?sequence=N`,
+/// which replays the journal from the newest checkpoint before `N`
+const BOOK_CHECKPOINT_INTERVAL_SECS: u64 = 60;
+
+/// Redis key prefix of rate limits shared between RPC server replicas
+const RATE_LIMIT_PREFIX: &str = "svm_clob:rate_limit";
+
+/// Redis key prefix of signed order nonces shared between RPC server replicas
+const NONCE_PREFIX: &str = "svm_clob:nonce";
+
+/// Redis key prefix of market metrics time series
+const TIME_SERIES_PREFIX: &str = "svm_clob:metrics";
+
+/// Seconds between market metrics samples unless configured
+const DEFAULT_MARKET_METRICS_INTERVAL_SECS: u64 = 10;
+
+/// Seconds market metrics time series keep points unless configured
+const DEFAULT_TIME_SERIES_RETENTION_SECS: u64 = 7 * 86_400;
+
+/// Milliseconds between oracle price reads unless configured
+const DEFAULT_ORACLE_POLL_INTERVAL_MS: u64 = 1000;
+
+/// Seconds an oracle price is used after its publish time unless configured
+const DEFAULT_ORACLE_MAX_AGE_SECS: u32 = 30;
+
+/// Backfills onboarding runs to catch storage up with the chain's settlements
+const ONBOARDING_ATTEMPTS: usize = 3;
 
 /// CLI application for SVM CLOB Infrastructure
 #[derive(Parser)]
@@ -55,14 +121,254 @@ pub enum Commands {
         #[arg(short, long, default_value = "8081")]
         port: u16,
     },
+    /// Start only the on-chain event indexer
+    StartIndexer,
+    /// Start only the trade settlement crank
+    StartSettler,
+    /// Diff on-chain state against storage once and print the report
+    Reconcile {
+        /// Write the JSON report to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
     /// Initialize the database
     InitDb,
+    /// Seal the encrypted columns under the active key, after enabling
+    /// `[encryption]` or rotating its keys
+    Reencrypt,
     /// Validate configuration
     ValidateConfig,
+    /// Manage configuration files
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommands,
+    },
+    /// Run the reference symmetric quoting bot against an RPC server
+    MarketMake {
+        /// Wallet to quote for
+        #[arg(long)]
+        owner: String,
+        /// RPC server URL (defaults to the configured local RPC server)
+        #[arg(long)]
+        server: Option<String>,
+        /// Distance of the innermost quotes from mid, in basis points
+        #[arg(long, default_value = "20")]
+        spread_bps: u64,
+        /// Price levels per side
+        #[arg(long, default_value = "1")]
+        levels: u32,
+        /// Extra ticks between levels
+        #[arg(long, default_value = "1")]
+        level_spacing_ticks: u64,
+        /// Quantity per level (defaults to the market minimum order size)
+        #[arg(long)]
+        order_size: Option<u64>,
+        /// Mid shift per order_size of inventory, in basis points
+        #[arg(long, default_value = "0")]
+        skew_bps: u64,
+        /// Inventory at which the side that would grow it is pulled
+        #[arg(long, default_value = "18446744073709551615")]
+        max_inventory: u64,
+        /// Quote refresh interval in milliseconds
+        #[arg(long, default_value = "1000")]
+        refresh_ms: u64,
+        /// Mid price to quote around while the book is one-sided
+        #[arg(long)]
+        fallback_mid: Option<u64>,
+    },
+    /// Place, modify and cancel orders through an RPC server, in whole tokens
+    Order {
+        #[command(subcommand)]
+        action: OrderCommands,
+    },
+    /// Print a market's ticker and top of book in whole tokens until interrupted
+    Watch {
+        /// Market to watch (defaults to the configured market)
+        #[arg(long)]
+        market: Option<String>,
+        /// RPC server URL (defaults to the configured local RPC server)
+        #[arg(long)]
+        server: Option<String>,
+        /// Price levels per side
+        #[arg(long, default_value = "5")]
+        levels: u32,
+        /// Refresh interval in milliseconds
+        #[arg(long, default_value = "1000")]
+        interval_ms: u64,
+    },
+    /// Replay order flow through an offline matching engine and report fills and P&L
+    Simulate {
+        /// Replay the engine journal from the configured database instead of synthetic flow
+        #[arg(long)]
+        from_journal: bool,
+        /// Seed of the synthetic order flow
+        #[arg(long, default_value = "1")]
+        seed: u64,
+        /// Number of synthetic events
+        #[arg(long, default_value = "10000")]
+        events: usize,
+        /// Initial fair price of the synthetic flow (defaults to 1000 ticks)
+        #[arg(long)]
+        start_mid: Option<u64>,
+        /// Backtest the symmetric quoting strategy with this spread, in basis points
+        #[arg(long)]
+        quote_spread_bps: Option<u64>,
+        /// Write the JSON report to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Replay a range of the engine journal on an offline engine and diff its fills against the journal
+    Replay {
+        /// First journal sequence to step through; earlier entries only rebuild the book
+        #[arg(long, default_value = "1")]
+        from: u64,
+        /// Last journal sequence (defaults to the journal head)
+        #[arg(long)]
+        to: Option<u64>,
+        /// Price levels per side shown in books
+        #[arg(long, default_value = "10")]
+        depth: usize,
+        /// Step through commands at a prompt instead of writing a report
+        #[arg(short, long)]
+        interactive: bool,
+        /// Write the JSON report to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Back up the database, the engine's book and the journal to a new directory
+    Backup {
+        /// Directory to create
+        #[arg(short, long)]
+        output: String,
+    },
+    /// Replace the database with a backup, optionally as of an earlier point in time
+    Restore {
+        /// Backup directory
+        #[arg(long)]
+        from: String,
+        /// Point to restore to, as unix seconds or RFC 3339 (defaults to the end of the backup)
+        #[arg(long)]
+        at: Option<String>,
+        /// Confirm that the configured database may be overwritten
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Read depth-of-market recordings: the book at one point, or the books and fills of a range
+    Dom {
+        /// Book as of this point, as unix seconds or RFC 3339
+        #[arg(long, conflicts_with_all = ["from", "to"])]
+        at: Option<String>,
+        /// Start of the range, as unix seconds or RFC 3339
+        #[arg(long, requires = "to")]
+        from: Option<String>,
+        /// End of the range, inclusive
+        #[arg(long, requires = "from")]
+        to: Option<String>,
+        /// Recording directory (defaults to `[recorder] dir`)
+        #[arg(long)]
+        dir: Option<String>,
+        /// Write the JSON result to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
     /// Show system status
     Status,
 }
 
+/// Order subcommands; prices are in whole quote tokens per whole base token
+/// and quantities in whole base tokens, or native units for a market the
+/// registry lists without decimals
+#[derive(Subcommand)]
+pub enum OrderCommands {
+    /// Place a limit order, or a market order when no price is given
+    Place {
+        /// Wallet placing the order
+        #[arg(long)]
+        owner: String,
+        #[arg(long, value_enum)]
+        side: SideArg,
+        /// Limit price
+        #[arg(long)]
+        price: Option<String>,
+        #[arg(long)]
+        quantity: String,
+        /// Rest only if the order would not match on arrival
+        #[arg(long, requires = "price", conflicts_with = "ioc")]
+        post_only: bool,
+        /// Cancel whatever does not fill on arrival
+        #[arg(long, requires = "price")]
+        ioc: bool,
+        /// Slippage tolerance of a market order, in basis points
+        #[arg(long, conflicts_with = "price")]
+        max_slippage_bps: Option<u32>,
+        #[arg(long, default_value = "0")]
+        client_order_id: u64,
+        /// Market to trade (defaults to the configured market)
+        #[arg(long)]
+        market: Option<String>,
+        /// RPC server URL (defaults to the configured local RPC server)
+        #[arg(long)]
+        server: Option<String>,
+    },
+    /// Change the price and/or total quantity of a resting order
+    Modify {
+        order_id: u64,
+        #[arg(long, required_unless_present = "quantity")]
+        price: Option<String>,
+        #[arg(long)]
+        quantity: Option<String>,
+        /// Market of the order (defaults to the configured market)
+        #[arg(long)]
+        market: Option<String>,
+        /// RPC server URL (defaults to the configured local RPC server)
+        #[arg(long)]
+        server: Option<String>,
+    },
+    /// Cancel a resting order
+    Cancel {
+        order_id: u64,
+        /// Market of the order (defaults to the configured market)
+        #[arg(long)]
+        market: Option<String>,
+        /// RPC server URL (defaults to the configured local RPC server)
+        #[arg(long)]
+        server: Option<String>,
+    },
+}
+
+/// Order side as typed on the command line
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SideArg {
+    Buy,
+    Sell,
+}
+
+impl From<SideArg> for OrderSide {
+    fn from(side: SideArg) -> Self {
+        match side {
+            SideArg::Buy => OrderSide::Bid,
+            SideArg::Sell => OrderSide::Ask,
+        }
+    }
+}
+
+/// Configuration subcommands
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    /// Scaffold a configuration file for a Solana cluster
+    Init {
+        /// Cluster to generate mints, program ID and endpoints for
+        #[arg(short, long, value_enum)]
+        network: Network,
+        /// Output path (defaults to the global --config path)
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Overwrite an existing file
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
 /// Configuration structure
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ClobConfig {
@@ -73,6 +379,45 @@ pub struct ClobConfig {
     pub orderbook: OrderbookConfig,
     pub matching_engine: MatchingEngineConfig,
     pub logging: LoggingConfig,
+    /// Mainnet when the section is missing, as in files written before it
+    #[serde(default)]
+    pub solana: SolanaConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settlement: Option<SettlementConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reconciliation: Option<ReconciliationConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub risk: Option<RiskConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub admin: Option<AdminConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub telemetry: Option<TelemetryConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<MetricsConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub indexer: Option<IndexingConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ha: Option<HighAvailabilityConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub surveillance: Option<SurveillanceSettings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fees: Option<FeesConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup: Option<BackupSettings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recorder: Option<RecorderSettings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub incentives: Option<IncentivesSettings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub obligations: Option<ObligationsSettings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statements: Option<StatementsSettings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<DisplaySettings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<EncryptionSettings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oracle: Option<OracleConfig>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -93,6 +438,74 @@ pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub workers: Option<usize>,
+    /// Reject order placements without an owner signature (RPC server only)
+    #[serde(default)]
+    pub require_signed_orders: bool,
+    /// Orders accepted per owner per second (RPC server only); unlimited when
+    /// unset, adjustable at runtime through the admin API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_orders_per_second: Option<u32>,
+    /// Requests accepted per client IP per second (RPC server only); unlimited
+    /// when unset, adjustable at runtime through the admin API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_requests_per_ip_per_second: Option<u32>,
+    /// Share rate limits with other replicas through `[redis]` (RPC server only)
+    #[serde(default)]
+    pub shared_rate_limits: bool,
+    /// Take the client IP from `X-Forwarded-For`; set only behind a proxy that
+    /// overwrites the header (RPC server only)
+    #[serde(default)]
+    pub trust_forwarded_for: bool,
+    /// Refuse calls without a tenant API key, except the health check and
+    /// admin routes (RPC server only)
+    #[serde(default)]
+    pub require_api_key: bool,
+    /// Longest a signed order's signature may run past its arrival, in
+    /// seconds; one day when unset (RPC server only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_signature_ttl_secs: Option<u32>,
+    /// Share used signed order nonces with other replicas through `[redis]`
+    /// (RPC server only)
+    #[serde(default)]
+    pub shared_nonces: bool,
+    /// Serve API keys issued for paper trading from an in-memory sandbox of
+    /// the market; calls made with one are refused otherwise (RPC server only)
+    #[serde(default)]
+    pub paper_trading: bool,
+    /// Virtual base balance each paper owner starts with, in native units;
+    /// `DEFAULT_PAPER_BALANCE` when unset (RPC server only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paper_base_balance: Option<u64>,
+    /// Virtual quote balance each paper owner starts with, in native units;
+    /// `DEFAULT_PAPER_BALANCE` when unset (RPC server only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paper_quote_balance: Option<u64>,
+    /// Listen on this Unix domain socket instead of `host` and `port`, e.g.
+    /// for a gateway running as a sidecar
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unix_socket: Option<String>,
+    /// PEM certificate chain to serve TCP over TLS, with `tls_key_path`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_cert_path: Option<String>,
+    /// PEM private key of `tls_cert_path`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_key_path: Option<String>,
+    /// Disable Nagle's algorithm on accepted connections
+    #[serde(default)]
+    pub tcp_nodelay: bool,
+    /// Idle seconds before TCP keepalive probes start; no probes when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tcp_keepalive_secs: Option<u64>,
+    /// Close HTTP/1 connections after each response
+    #[serde(default)]
+    pub disable_http_keepalive: bool,
+    /// Connections served at once; further ones wait to be accepted.
+    /// Unlimited when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_connections: Option<usize>,
+    /// Largest request body accepted, in bytes; 2 MB when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_body_bytes: Option<usize>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -101,12 +514,74 @@ pub struct OrderbookConfig {
     pub quote_mint: String,
     pub tick_size: u64,
     pub min_order_size: u64,
+    /// Decimals of the base mint; prices are rendered in whole tokens when
+    /// both decimals are set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_decimals: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quote_decimals: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_symbol: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quote_symbol: Option<String>,
+    /// How fills are split between resting orders at one price
+    #[serde(default)]
+    pub allocation: AllocationMode,
+    /// Longest an order may rest before the expiry sweep takes it off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resting_lifetime: Option<RestingLifetime>,
+    /// Delay the RPC server holds orders that only take for before they
+    /// reach the engine; none when unset, adjustable at runtime through the
+    /// admin API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed_bump: Option<SpeedBump>,
+    /// Order types new orders may use; every type when empty. Set on chain
+    /// with `set_order_types` to match, for the reconciler to agree
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub order_types: Vec<OrderType>,
+    /// Times in force new orders may use; every one when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub time_in_force: Vec<TimeInForce>,
+}
+
+impl OrderbookConfig {
+    /// Display metadata of the configured market
+    pub fn metadata(&self) -> MarketMetadata {
+        MarketMetadata {
+            base_symbol: self.base_symbol.clone(),
+            quote_symbol: self.quote_symbol.clone(),
+            base_decimals: self.base_decimals,
+            quote_decimals: self.quote_decimals,
+        }
+    }
+
+    /// Order types and times in force the market accepts
+    pub fn whitelist(&self) -> OrderTypeWhitelist {
+        OrderTypeWhitelist::new(&self.order_types, &self.time_in_force)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MatchingEngineConfig {
     pub max_orders_per_batch: usize,
     pub matching_interval_ms: u64,
+    /// Cores to pin the order path to (see `affinity`); unpinned when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_cores: Option<Vec<usize>>,
+    /// Commands held while the market is paused on chain, run in arrival
+    /// order once it is not; commands are refused with `EnginePaused` when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pause_buffer: Option<usize>,
+}
+
+impl MatchingEngineConfig {
+    /// How matching treats commands while the market is paused on chain
+    pub fn chain_pause(&self) -> PauseMode {
+        match self.pause_buffer {
+            Some(max_buffered) => PauseMode::Buffer { max_buffered },
+            None => PauseMode::Reject,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -116,6 +591,214 @@ pub struct LoggingConfig {
     pub json_format: bool,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SolanaConfig {
+    pub network: String,
+    pub rpc_url: String,
+    pub ws_url: String,
+    pub program_id: String,
+    pub commitment: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SettlementConfig {
+    pub keypair_path: String,
+    pub max_trades_per_tx: usize,
+    pub poll_interval_ms: u64,
+    pub max_retries: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReconciliationConfig {
+    pub interval_secs: u64,
+    pub report_path: Option<String>,
+    pub max_settlement_lag: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RiskConfig {
+    pub max_order_size: Option<u64>,
+    pub max_open_notional: Option<u64>,
+    pub price_collar_bps: Option<u64>,
+}
+
+/// Reference price of the market from a Pyth Hermes service
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OracleConfig {
+    /// e.g. `https://hermes.pyth.network`
+    pub hermes_url: String,
+    /// Hex ID of the market's price feed
+    pub feed_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll_interval_ms: Option<u64>,
+    /// Seconds after its publish time a price stops being used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_secs: Option<u32>,
+    /// Move the market to cancel-only while its mid is further than this
+    /// from the oracle price, in basis points; never when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_breaker_bps: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AdminConfig {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TelemetryConfig {
+    pub otlp_endpoint: String,
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+    #[serde(default = "default_sample_ratio")]
+    pub sample_ratio: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MetricsConfig {
+    /// Address of the Prometheus scrape endpoint, e.g. `0.0.0.0:9090`
+    pub listen: String,
+    /// Seconds between samples of the market gauges; 10 when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub market_interval_secs: Option<u64>,
+    /// Also write market samples to Redis time series in `[redis]`
+    #[serde(default)]
+    pub redis_time_series: bool,
+    /// Seconds the time series keep points; one week when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_series_retention_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IndexingConfig {
+    /// `logs` (default) or `accounts`
+    pub ingestion: String,
+    /// Before taking orders, check the book restored from storage against the
+    /// market's on-chain state (see `onboard_market`)
+    #[serde(default)]
+    pub onboard_from_chain: bool,
+    /// Follow the market's vaults for plain SPL transfers made outside
+    /// `deposit`, for the settler to credit to their senders
+    #[serde(default)]
+    pub credit_vault_transfers: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HighAvailabilityConfig {
+    /// Unique name of this instance among those sharing the database
+    pub instance_id: String,
+    #[serde(default = "default_lease_ttl_ms")]
+    pub lease_ttl_ms: u64,
+    #[serde(default = "default_renew_interval_ms")]
+    pub renew_interval_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SurveillanceSettings {
+    pub window_secs: Option<i64>,
+    pub wash_min_score: Option<f64>,
+    pub spoof_min_cancel_to_fill: Option<u64>,
+    pub ignition_move_bps: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeesConfig {
+    /// Rates by trailing 30-day volume; the lowest tier should start at 0
+    pub tiers: Vec<FeeTier>,
+    /// Share of every fee earmarked for the insurance fund, in basis points (defaults to 0)
+    pub insurance_share_bps: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupSettings {
+    /// `pg_dump` executable (defaults to `pg_dump` on the PATH)
+    pub pg_dump: Option<String>,
+    /// `pg_restore` executable (defaults to `pg_restore` on the PATH)
+    pub pg_restore: Option<String>,
+    /// Journal entries per segment file (defaults to 10000)
+    pub segment_size: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecorderSettings {
+    /// Directory the Parquet files are written under
+    pub dir: String,
+    /// Time between book samples (defaults to 1000)
+    pub snapshot_interval_ms: Option<u64>,
+    /// Price levels per side in a sample (defaults to 50)
+    pub depth: Option<usize>,
+    /// Time covered by one file (defaults to 3600)
+    pub segment_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IncentivesSettings {
+    /// Reward shared between the makers of each epoch, in reward token base units
+    pub reward_per_epoch: u64,
+    /// Largest distance from mid of a credited order (defaults to 50)
+    pub band_bps: Option<u64>,
+    /// Length of an epoch (defaults to 86400)
+    pub epoch_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ObligationsSettings {
+    /// Seconds between two samples of the book (defaults to 10)
+    pub sample_secs: Option<u64>,
+    /// Length of a scoring period (defaults to 86400)
+    pub period_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StatementsSettings {
+    /// Wait after a UTC day closes before generating its statements, so
+    /// that its last settlements are indexed (defaults to 3600)
+    pub delay_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EncryptionSettings {
+    /// ID of the key new values are encrypted with
+    pub active_key: u32,
+    /// Every key stored values may be encrypted with
+    pub keys: Vec<EncryptionKey>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EncryptionKey {
+    /// Recorded beside every value encrypted with the key
+    pub id: u32,
+    /// Environment variable holding the base64 of the 32-byte key, as
+    /// provisioned by the deployment's KMS; keys are never read from the file
+    pub key_env: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DisplaySettings {
+    /// Fraction digits of printed prices and quote amounts (defaults to as many as needed)
+    pub price_precision: Option<u32>,
+    /// Fraction digits of printed quantities (defaults to as many as needed)
+    pub quantity_precision: Option<u32>,
+    /// Character between groups of three integer digits, e.g. `,` (defaults to none)
+    pub thousands_separator: Option<String>,
+}
+
+fn default_service_name() -> String {
+    "svm-clob".to_string()
+}
+
+fn default_sample_ratio() -> f64 {
+    1.0
+}
+
+fn default_lease_ttl_ms() -> u64 {
+    5000
+}
+
+fn default_renew_interval_ms() -> u64 {
+    1000
+}
+
 impl Default for ClobConfig {
     fn default() -> Self {
         Self {
@@ -132,40 +815,128 @@ impl Default for ClobConfig {
                 host: "0.0.0.0".to_string(),
                 port: 8080,
                 workers: None,
+                require_signed_orders: false,
+                max_orders_per_second: None,
+                max_requests_per_ip_per_second: None,
+                shared_rate_limits: false,
+                trust_forwarded_for: false,
+                require_api_key: false,
+                max_signature_ttl_secs: None,
+                shared_nonces: false,
+                paper_trading: false,
+                paper_base_balance: None,
+                paper_quote_balance: None,
+                unix_socket: None,
+                tls_cert_path: None,
+                tls_key_path: None,
+                tcp_nodelay: false,
+                tcp_keepalive_secs: None,
+                disable_http_keepalive: false,
+                max_connections: None,
+                max_body_bytes: None,
             },
             websocket_server: ServerConfig {
                 host: "0.0.0.0".to_string(),
                 port: 8081,
                 workers: None,
+                require_signed_orders: false,
+                max_orders_per_second: None,
+                max_requests_per_ip_per_second: None,
+                shared_rate_limits: false,
+                trust_forwarded_for: false,
+                require_api_key: false,
+                max_signature_ttl_secs: None,
+                shared_nonces: false,
+                paper_trading: false,
+                paper_base_balance: None,
+                paper_quote_balance: None,
+                unix_socket: None,
+                tls_cert_path: None,
+                tls_key_path: None,
+                tcp_nodelay: false,
+                tcp_keepalive_secs: None,
+                disable_http_keepalive: false,
+                max_connections: None,
+                max_body_bytes: None,
             },
             orderbook: OrderbookConfig {
                 base_mint: "So11111111111111111111111111111111111111112".to_string(), // SOL
                 quote_mint: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_string(), // USDC
                 tick_size: 1000, // 0.001 USDC
                 min_order_size: 1000000, // 0.001 SOL
+                base_decimals: Some(9),
+                quote_decimals: Some(6),
+                base_symbol: Some("SOL".to_string()),
+                quote_symbol: Some("USDC".to_string()),
+                allocation: AllocationMode::PriceTime,
+                resting_lifetime: None,
+                speed_bump: None,
+                order_types: Vec::new(),
+                time_in_force: Vec::new(),
             },
             matching_engine: MatchingEngineConfig {
                 max_orders_per_batch: 100,
                 matching_interval_ms: 10,
+                cpu_cores: None,
+                pause_buffer: None,
             },
             logging: LoggingConfig {
                 level: "info".to_string(),
                 file: None,
                 json_format: false,
             },
+            solana: SolanaConfig::default(),
+            settlement: None,
+            reconciliation: None,
+            risk: None,
+            admin: None,
+            telemetry: None,
+            metrics: None,
+            indexer: None,
+            ha: None,
+            surveillance: None,
+            fees: None,
+            backup: None,
+            recorder: None,
+            incentives: None,
+            obligations: None,
+            statements: None,
+            display: None,
+            encryption: None,
+            oracle: None,
         }
     }
 }
 
 /// Main CLI runner
-pub async fn run_cli() -> Result<(), Box<dyn std::error::Error>> {
+pub async fn run_cli() -> InfraResult<()> {
     let cli = Cli::parse();
     
+    // Commands that operate on the configuration file itself must not load it first
+    match &cli.command {
+        Commands::Config { action } => {
+            init_logging(&LoggingConfig { level: cli.log_level.clone(), file: None, json_format: false }, None)?;
+            return run_config_command(action, &cli.config);
+        }
+        Commands::ValidateConfig => {
+            init_logging(&LoggingConfig { level: cli.log_level.clone(), file: None, json_format: false }, None)?;
+            return validate_config(&cli.config);
+        }
+        _ => {}
+    }
+    
     // Load configuration
     let config = load_config(&cli.config)?;
     
     // Initialize logging
-    init_logging(&config.logging)?;
+    init_logging(&config.logging, config.telemetry.as_ref())?;
+    
+    // Serve Prometheus metrics when configured
+    if let Some(metrics) = &config.metrics {
+        svm_clob_observability::init_metrics(metrics.listen.parse().map_err(InfraError::config)?)
+            .map_err(InfraError::config)?;
+        info!("Serving metrics on {}", metrics.listen);
+    }
     
     info!("Starting SVM CLOB Infrastructure CLI");
     
@@ -179,57 +950,142 @@ pub async fn run_cli() -> Result<(), Box<dyn std::error::Error>> {
         Commands::StartWs { port } => {
             start_websocket_only(config, port).await?;
         }
+        Commands::StartIndexer => {
+            start_indexer_only(config).await?;
+        }
+        Commands::StartSettler => {
+            start_settler_only(config).await?;
+        }
+        Commands::Reconcile { output } => {
+            reconcile_once(config, output).await?;
+        }
+        Commands::MarketMake {
+            owner,
+            server,
+            spread_bps,
+            levels,
+            level_spacing_ticks,
+            order_size,
+            skew_bps,
+            max_inventory,
+            refresh_ms,
+            fallback_mid,
+        } => {
+            let server = server.unwrap_or_else(|| default_server(&config));
+            let strategy = SymmetricStrategy::new(SymmetricParams {
+                spread_bps,
+                levels,
+                level_spacing_ticks,
+                order_size: order_size.unwrap_or(config.orderbook.min_order_size),
+                skew_bps,
+                max_inventory,
+            });
+            let mm_config = MarketMakerConfig {
+                owner: owner.parse().map_err(InfraError::config)?,
+                tick_size: config.orderbook.tick_size,
+                min_order_size: config.orderbook.min_order_size,
+                refresh_interval: std::time::Duration::from_millis(refresh_ms),
+                fallback_mid,
+            };
+            
+            info!("Market making on {}", server);
+            MarketMaker::new(ClobClient::new(server)?, strategy, mm_config).run().await?;
+        }
+        Commands::Order { action } => {
+            run_order_command(&config, action).await?;
+        }
+        Commands::Watch {
+            market,
+            server,
+            levels,
+            interval_ms,
+        } => {
+            watch_market(&config, market, server, levels, interval_ms).await?;
+        }
+        Commands::Simulate {
+            from_journal,
+            seed,
+            events,
+            start_mid,
+            quote_spread_bps,
+            output,
+        } => {
+            run_simulation(config, from_journal, seed, events, start_mid, quote_spread_bps, output).await?;
+        }
+        Commands::Replay {
+            from,
+            to,
+            depth,
+            interactive,
+            output,
+        } => {
+            replay_journal(config, from, to, depth, interactive, output).await?;
+        }
+        Commands::Backup { output } => {
+            backup(config, output).await?;
+        }
+        Commands::Restore { from, at, yes } => {
+            restore(config, from, at, yes).await?;
+        }
+        Commands::Dom { at, from, to, dir, output } => {
+            query_recording(config, at, from.zip(to), dir, output).await?;
+        }
         Commands::InitDb => {
             init_database(config).await?;
         }
-        Commands::ValidateConfig => {
-            validate_config(config)?;
+        Commands::Reencrypt => {
+            let rewritten = connect_storage(&config).await?.reencrypt().await?;
+            println!("Re-encrypted {} rows", rewritten);
         }
+        Commands::ValidateConfig | Commands::Config { .. } => unreachable!("handled before config load"),
         Commands::Status => {
             show_status(config).await?;
         }
     }
     
+    // Flush spans still buffered by the OTLP exporter
+    svm_clob_observability::shutdown();
+    
     Ok(())
 }
 
 /// Load configuration from file and environment
-fn load_config(config_path: &str) -> Result<ClobConfig, Box<dyn std::error::Error>> {
-    let mut config_builder = Config::builder()
-        .add_source(File::with_name(config_path).required(false))
-        .add_source(Environment::with_prefix("CLOB"));
-    
+fn load_config(config_path: &str) -> InfraResult<ClobConfig> {
     // If config file doesn't exist, create default one
     if !std::path::Path::new(config_path).exists() {
         let default_config = ClobConfig::default();
-        let config_content = toml::to_string_pretty(&default_config)?;
+        let config_content = toml::to_string_pretty(&default_config).map_err(InfraError::config)?;
         std::fs::write(config_path, config_content)?;
         info!("Created default configuration file: {}", config_path);
     }
     
-    let config = config_builder.build()?;
-    let clob_config: ClobConfig = config.try_deserialize()?;
-    
-    Ok(clob_config)
+    merged_config(config_path)
+}
+
+/// The configuration file with `CLOB_*` environment overrides merged over it
+fn merged_config(config_path: &str) -> InfraResult<ClobConfig> {
+    Config::builder()
+        .add_source(File::with_name(config_path).required(false))
+        .add_source(Environment::with_prefix("CLOB"))
+        .build()
+        .and_then(|config| config.try_deserialize())
+        .map_err(InfraError::config)
 }
 
 /// Initialize logging based on configuration
-fn init_logging(config: &LoggingConfig) -> Result<(), Box<dyn std::error::Error>> {
-    let level = config.level.parse()?;
-    
-    let registry = tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(&config.level));
-    
-    if config.json_format {
-        registry
-            .with(tracing_subscriber::fmt::layer().json())
-            .init();
-    } else {
-        registry
-            .with(tracing_subscriber::fmt::layer())
-            .init();
-    }
+fn init_logging(
+    config: &LoggingConfig,
+    telemetry: Option<&TelemetryConfig>,
+) -> InfraResult<()> {
+    // Export spans over OTLP when a collector is configured
+    let telemetry = telemetry.map(|telemetry| svm_clob_observability::TelemetryConfig {
+        service_name: telemetry.service_name.clone(),
+        otlp_endpoint: telemetry.otlp_endpoint.clone(),
+        sample_ratio: telemetry.sample_ratio,
+    });
     
+    svm_clob_observability::init_tracing(&config.level, config.json_format, telemetry.as_ref())
+        .map_err(InfraError::config)?;
     Ok(())
 }
 
@@ -237,17 +1093,20 @@ fn init_logging(config: &LoggingConfig) -> Result<(), Box<dyn std::error::Error>
 async fn start_full_infrastructure(
     config: ClobConfig,
     _daemon: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> InfraResult<()> {
     info!("Starting full CLOB infrastructure");
     
+    // Every component reads time from one clock
+    let clock = system_clock();
+    
     // Initialize storage
-    let storage = Arc::new(PostgresStorage::new(&config.database.url).await?);
+    let storage = Arc::new(connect_storage(&config).await?.with_clock(clock.clone()));
     
     // Create orderbook configuration
     let orderbook_config = OrderBook {
         authority: solana_sdk::pubkey::Pubkey::default(),
-        base_mint: config.orderbook.base_mint.parse()?,
-        quote_mint: config.orderbook.quote_mint.parse()?,
+        base_mint: config.orderbook.base_mint.parse().map_err(InfraError::config)?,
+        quote_mint: config.orderbook.quote_mint.parse().map_err(InfraError::config)?,
         tick_size: config.orderbook.tick_size,
         min_order_size: config.orderbook.min_order_size,
         sequence_number: 0,
@@ -260,27 +1119,289 @@ async fn start_full_infrastructure(
     };
     
     // Initialize matching engine
-    let matching_engine = Arc::new(RwLock::new(
-        MatchingEngine::new(storage.clone(), orderbook_config)
-    ));
+    let program_id = config.solana.program_id.parse().map_err(InfraError::config)?;
+    let market_id = MarketId::new(&program_id, &orderbook_config.base_mint, &orderbook_config.quote_mint);
+    let risk = build_risk_engine(&config, clock.clone());
+    let mut matching_engine = MatchingEngine::new(storage.clone(), orderbook_config);
+    matching_engine.set_market_id(market_id);
+    matching_engine.set_allocation(config.orderbook.allocation);
+    matching_engine.set_resting_lifetime(config.orderbook.resting_lifetime);
+    matching_engine.set_order_types(config.orderbook.whitelist());
+    matching_engine.set_clock(clock.clone());
+    matching_engine.resume_trade_ids(storage.get_last_trade_id().await?);
+    matching_engine.resume_order_ids(storage.get_reserved_order_ids().await?);
+    matching_engine.add_pre_trade_hook(risk.clone());
+    // A standby rebuilds its book from the journal instead
+    if config.ha.is_none() {
+        matching_engine.bootstrap().await?;
+    }
+    let matching_engine = Arc::new(RwLock::new(matching_engine));
+    
+    // Contend for the matcher lease; until this instance wins it, it replays
+    // the journal as a hot standby and rejects order commands
+    let ha_node = config.ha.as_ref().map(|ha| {
+        Arc::new(HaNode::new(
+            storage.clone(),
+            matching_engine.clone(),
+            svm_clob_ha::HaConfig {
+                instance_id: ha.instance_id.clone(),
+                lease_name: MATCHER_LEASE.to_string(),
+                lease_ttl: std::time::Duration::from_millis(ha.lease_ttl_ms),
+                renew_interval: std::time::Duration::from_millis(ha.renew_interval_ms),
+                replay_batch_size: 1000,
+            },
+        ))
+    });
+    if let Some(ha_node) = &ha_node {
+        // Deactivate before any server can route a command to the engine
+        matching_engine.read().await.set_active(false).await;
+        let ha_node = ha_node.clone();
+        tokio::spawn(async move {
+            if let Err(e) = ha_node.run().await {
+                error!("High-availability node stopped: {}", e);
+            }
+        });
+    }
+    
+    // Periodically diff on-chain, engine and storage state
+    if let Some(reconciliation) = &config.reconciliation {
+        let reconciler = Reconciler::new(
+            storage.clone(),
+            Some(matching_engine.clone()),
+            reconciler_config(&config, reconciliation)?,
+        );
+        tokio::spawn(async move {
+            if let Err(e) = reconciler.run().await {
+                error!("Reconciler stopped: {}", e);
+            }
+        });
+    }
+    
+    // Scan the journal for market abuse patterns
+    if let Some(surveillance) = &config.surveillance {
+        let service = SurveillanceService::new(storage.clone(), surveillance_config(surveillance));
+        tokio::spawn(async move {
+            if let Err(e) = service.run().await {
+                error!("Surveillance stopped: {}", e);
+            }
+        });
+    }
+    
+    // Assign fee tiers nightly and accrue maker/taker fees for every fill
+    if let Some(fees) = &config.fees {
+        let tiers = FeeTierService::new(storage.clone(), FeeSchedule::new(fees.tiers.clone()));
+        tokio::spawn(async move {
+            if let Err(e) = tiers.run().await {
+                error!("Fee tier assignment stopped: {}", e);
+            }
+        });
+
+        let service = FeeAccrualService::new(
+            storage.clone(),
+            FeeConfig {
+                schedule: FeeSchedule::new(fees.tiers.clone()),
+                insurance_share_bps: fees.insurance_share_bps.unwrap_or(0),
+                batch_size: 1000,
+                poll_interval: std::time::Duration::from_secs(1),
+            },
+        );
+        tokio::spawn(async move {
+            if let Err(e) = service.run().await {
+                error!("Fee accrual stopped: {}", e);
+            }
+        });
+    }
+    
+    // Record the book and fills to Parquet for research
+    if let Some(recorder) = &config.recorder {
+        let recorder = DomRecorder::new(storage.clone(), matching_engine.clone(), recorder_config(recorder, market_id));
+        tokio::spawn(async move {
+            if let Err(e) = recorder.run().await {
+                error!("Depth-of-market recorder stopped: {}", e);
+            }
+        });
+    }
+    
+    // Credit makers for quoting near mid and close reward epochs
+    if let Some(incentives) = &config.incentives {
+        let (orderbook, market_id) = configured_market(&config)?;
+        let service = IncentiveService::new(
+            storage.clone(),
+            orderbook,
+            market_id,
+            IncentiveConfig {
+                band_bps: incentives.band_bps.unwrap_or(50),
+                epoch_secs: incentives.epoch_secs.unwrap_or(86_400) as i64,
+                reward_per_epoch: incentives.reward_per_epoch,
+                allocation: config.orderbook.allocation,
+                ..IncentiveConfig::default()
+            },
+        )
+        .with_clock(clock.clone());
+        tokio::spawn(async move {
+            if let Err(e) = service.run().await {
+                error!("Incentive tracking stopped: {}", e);
+            }
+        });
+    }
+
+    // Score designated market makers against their quoting obligations
+    if let Some(obligations) = &config.obligations {
+        let (orderbook, market_id) = configured_market(&config)?;
+        let service = ObligationService::new(
+            storage.clone(),
+            orderbook,
+            market_id,
+            ObligationConfig {
+                sample_secs: obligations.sample_secs.unwrap_or(10) as i64,
+                period_secs: obligations.period_secs.unwrap_or(86_400) as i64,
+                allocation: config.orderbook.allocation,
+                ..ObligationConfig::default()
+            },
+        )
+        .with_clock(clock.clone());
+        tokio::spawn(async move {
+            if let Err(e) = service.run().await {
+                error!("Market maker obligation tracking stopped: {}", e);
+            }
+        });
+    }
+    
+    // Generate each user's statement once a day closes
+    if let Some(statements) = &config.statements {
+        let (orderbook, market_id) = configured_market(&config)?;
+        let service = StatementService::new(
+            storage.clone(),
+            orderbook,
+            market_id,
+            StatementConfig {
+                delay: std::time::Duration::from_secs(statements.delay_secs.unwrap_or(3600)),
+                metadata: config.orderbook.metadata(),
+                ..StatementConfig::default()
+            },
+        )
+        .with_clock(clock.clone());
+        tokio::spawn(async move {
+            if let Err(e) = service.run().await {
+                error!("Statement generation stopped: {}", e);
+            }
+        });
+    }
     
     // Create RPC server state
     let rpc_state = Arc::new(RpcServerState {
         matching_engine: matching_engine.clone(),
         storage: storage.clone(),
+        risk,
+        admin_token: config.admin.as_ref().map(|admin| admin.token.clone()),
+        require_signed_orders: config.rpc_server.require_signed_orders,
+        nonces: build_nonce_store(&config).await?,
+        paper: build_paper_exchange(&config, &*matching_engine.read().await),
+        market_metadata: config.orderbook.metadata(),
+        program_id,
+        rate_limiter: build_rate_limiter(&config).await?,
+        tenants: TenantDirectory::new(config.rpc_server.require_api_key),
+        speed_bump: build_speed_bump(&config)?,
+        suspensions: SuspensionList::new(),
+        idempotency: IdempotencyCache::new(),
+        clock: clock.clone(),
     });
     
+    // A standby rebuilds its book from the journal instead
+    if config.ha.is_none() {
+        onboard_market(&config, storage.clone(), &matching_engine, &rpc_state.nonces, clock.now()).await?;
+    }
+    
     // Create WebSocket server state
-    let ws_state = Arc::new(WebSocketServerState::new());
+    let ws_state = Arc::new(WebSocketServerState::with_clock(clock.clone()).with_entitlements(storage.clone()));
+    
+    // Publish the engine's book to WebSocket subscribers as it changes
+    spawn_book_publisher(matching_engine.clone(), ws_state.clone());
+    spawn_expiry_sweeper(matching_engine.clone(), Some(ws_state.clone()));
+    spawn_market_metrics(matching_engine.clone(), &config).await?;
+    spawn_oracle(matching_engine.clone(), rpc_state.risk.clone(), &config).await?;
+    spawn_order_feed(storage.clone(), ws_state.clone());
+    spawn_book_checkpoints(matching_engine.clone(), storage.clone());
+    
+    // Follow on-chain events in the background, pausing matching while the
+    // market is paused on chain
+    let indexer_config = indexer_config(&config)?;
+    let (orderbook_address, _) = {
+        let engine = matching_engine.read().await;
+        let orderbook = engine.orderbook_config();
+        program::orderbook_address(&indexer_config.program_id, &orderbook.base_mint, &orderbook.quote_mint)
+    };
+    let indexer = Indexer::new(storage.clone(), indexer_config);
+    spawn_chain_pause(
+        matching_engine.clone(),
+        indexer.subscribe_market_status(),
+        orderbook_address,
+        config.matching_engine.chain_pause(),
+    );
+    tokio::spawn(async move {
+        if let Err(e) = indexer.run().await {
+            error!("Indexer stopped: {}", e);
+        }
+    });
     
-    // Start servers concurrently
-    let rpc_handle = tokio::spawn(start_rpc_server(rpc_state, config.rpc_server.port));
-    let ws_handle = tokio::spawn(start_ws_server(ws_state, config.websocket_server.port));
+    // Settle matched trades on-chain when a settlement authority is configured
+    if let Some(settler) = build_settler(storage.clone(), &config)? {
+        let settler = Arc::new(settler);
+        match &ha_node {
+            // Only the active matcher settles, so a batch is never submitted twice
+            Some(ha_node) => {
+                let mut role = ha_node.subscribe();
+                tokio::spawn(async move {
+                    loop {
+                        if role.wait_for(|role| matches!(role, Role::Leader { .. })).await.is_err() {
+                            return;
+                        }
+                        let running = settler.clone();
+                        let handle = tokio::spawn(async move {
+                            if let Err(e) = running.run().await {
+                                error!("Settler stopped: {}", e);
+                            }
+                        });
+                        let _ = role.wait_for(|role| *role == Role::Standby).await;
+                        handle.abort();
+                    }
+                });
+            }
+            None => {
+                tokio::spawn(async move {
+                    if let Err(e) = settler.run().await {
+                        error!("Settler stopped: {}", e);
+                    }
+                });
+            }
+        }
+    }
+    
+    // Start servers concurrently; orders are matched wherever the RPC server runs
+    let pinned = match config.matching_engine.cpu_cores.as_deref() {
+        Some(cores) => Some(affinity::pinned_runtime(cores)?),
+        None => None,
+    };
+    let rpc_server = start_rpc_server(rpc_state, listen_options(&config.rpc_server, config.rpc_server.port)?);
+    let rpc_handle = match &pinned {
+        Some(runtime) => runtime.spawn(rpc_server),
+        None => tokio::spawn(rpc_server),
+    };
+    let ws_handle = tokio::spawn(start_ws_server(
+        ws_state,
+        listen_options(&config.websocket_server, config.websocket_server.port)?,
+    ));
     
     info!("All services started successfully");
     
-    // Wait for both servers
-    tokio::try_join!(rpc_handle, ws_handle)??;
+    // Wait for both servers; a runtime cannot be dropped from async code
+    let served = tokio::try_join!(rpc_handle, ws_handle);
+    if let Some(runtime) = pinned {
+        runtime.shutdown_background();
+    }
+    let (rpc_served, ws_served) = served.map_err(InfraError::task)?;
+    rpc_served?;
+    ws_served?;
     
     Ok(())
 }
@@ -289,15 +1410,15 @@ async fn start_full_infrastructure(
 async fn start_rpc_only(
     config: ClobConfig,
     port: u16,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> InfraResult<()> {
     info!("Starting RPC server only on port {}", port);
     
-    let storage = Arc::new(PostgresStorage::new(&config.database.url).await?);
+    let storage = Arc::new(connect_storage(&config).await?);
     
     let orderbook_config = OrderBook {
         authority: solana_sdk::pubkey::Pubkey::default(),
-        base_mint: config.orderbook.base_mint.parse()?,
-        quote_mint: config.orderbook.quote_mint.parse()?,
+        base_mint: config.orderbook.base_mint.parse().map_err(InfraError::config)?,
+        quote_mint: config.orderbook.quote_mint.parse().map_err(InfraError::config)?,
         tick_size: config.orderbook.tick_size,
         min_order_size: config.orderbook.min_order_size,
         sequence_number: 0,
@@ -309,76 +1430,1209 @@ async fn start_rpc_only(
         is_paused: false,
     };
     
-    let matching_engine = Arc::new(RwLock::new(
-        MatchingEngine::new(storage.clone(), orderbook_config)
-    ));
+    let program_id = config.solana.program_id.parse().map_err(InfraError::config)?;
+    let market_id = MarketId::new(&program_id, &orderbook_config.base_mint, &orderbook_config.quote_mint);
+    let risk = build_risk_engine(&config, system_clock());
+    let mut matching_engine = MatchingEngine::new(storage.clone(), orderbook_config);
+    matching_engine.set_market_id(market_id);
+    matching_engine.set_allocation(config.orderbook.allocation);
+    matching_engine.set_resting_lifetime(config.orderbook.resting_lifetime);
+    matching_engine.set_order_types(config.orderbook.whitelist());
+    matching_engine.resume_trade_ids(storage.get_last_trade_id().await?);
+    matching_engine.resume_order_ids(storage.get_reserved_order_ids().await?);
+    matching_engine.add_pre_trade_hook(risk.clone());
+    matching_engine.bootstrap().await?;
+    let matching_engine = Arc::new(RwLock::new(matching_engine));
+    spawn_expiry_sweeper(matching_engine.clone(), None);
+    spawn_market_metrics(matching_engine.clone(), &config).await?;
+    spawn_oracle(matching_engine.clone(), risk.clone(), &config).await?;
+    spawn_book_checkpoints(matching_engine.clone(), storage.clone());
     
+    let paper = build_paper_exchange(&config, &*matching_engine.read().await);
     let rpc_state = Arc::new(RpcServerState {
         matching_engine,
         storage,
+        risk,
+        admin_token: config.admin.as_ref().map(|admin| admin.token.clone()),
+        require_signed_orders: config.rpc_server.require_signed_orders,
+        nonces: build_nonce_store(&config).await?,
+        paper,
+        market_metadata: config.orderbook.metadata(),
+        program_id,
+        rate_limiter: build_rate_limiter(&config).await?,
+        tenants: TenantDirectory::new(config.rpc_server.require_api_key),
+        speed_bump: build_speed_bump(&config)?,
+        suspensions: SuspensionList::new(),
+        idempotency: IdempotencyCache::new(),
+        clock: system_clock(),
     });
     
-    start_rpc_server(rpc_state, port).await?;
+    onboard_market(&config, rpc_state.storage.clone(), &rpc_state.matching_engine, &rpc_state.nonces, rpc_state.clock.now()).await?;
+    let options = listen_options(&config.rpc_server, port)?;
+    match config.matching_engine.cpu_cores.as_deref() {
+        Some(cores) => {
+            let runtime = affinity::pinned_runtime(cores)?;
+            let served = runtime.spawn(start_rpc_server(rpc_state, options)).await;
+            runtime.shutdown_background();
+            served.map_err(InfraError::task)??;
+        }
+        None => start_rpc_server(rpc_state, options).await?,
+    }
     
     Ok(())
 }
 
+/// Build the RPC server's rate limiter, shared through Redis when configured
+async fn build_rate_limiter(config: &ClobConfig) -> InfraResult<RateLimiter> {
+    let limits = RateLimits {
+        max_orders_per_second: config.rpc_server.max_orders_per_second,
+        max_requests_per_ip_per_second: config.rpc_server.max_requests_per_ip_per_second,
+    };
+    let limiter = if config.rpc_server.shared_rate_limits {
+        RateLimiter::redis(&config.redis.url, RATE_LIMIT_PREFIX, limits).await?
+    } else {
+        RateLimiter::new(limits)
+    };
+    Ok(limiter.trust_forwarded_for(config.rpc_server.trust_forwarded_for))
+}
+
+async fn build_nonce_store(config: &ClobConfig) -> InfraResult<NonceStore> {
+    let max_ttl_secs = config
+        .rpc_server
+        .max_signature_ttl_secs
+        .map_or(DEFAULT_MAX_SIGNATURE_TTL_SECS, i64::from);
+    if config.rpc_server.shared_nonces {
+        Ok(NonceStore::redis(&config.redis.url, NONCE_PREFIX, max_ttl_secs).await?)
+    } else {
+        Ok(NonceStore::new(max_ttl_secs))
+    }
+}
+
+fn build_speed_bump(config: &ClobConfig) -> InfraResult<SpeedBumpGate> {
+    match config.orderbook.speed_bump {
+        Some(speed_bump) if !speed_bump.is_valid() => Err(InfraError::Config(format!(
+            "[orderbook] speed_bump must have min_delay_us <= max_delay_us <= {}",
+            SpeedBump::MAX_DELAY_US
+        ))),
+        speed_bump => Ok(SpeedBumpGate::new(speed_bump)),
+    }
+}
+
+/// Listener of a server section, on `port` rather than the section's own
+/// when the command line gives one
+fn listen_options(server: &ServerConfig, port: u16) -> InfraResult<ListenOptions> {
+    let tls = match (&server.tls_cert_path, &server.tls_key_path) {
+        (Some(cert_path), Some(key_path)) => Some(TlsFiles {
+            cert_path: cert_path.into(),
+            key_path: key_path.into(),
+        }),
+        (None, None) => None,
+        _ => return Err(InfraError::config("tls_cert_path and tls_key_path must be set together")),
+    };
+    Ok(ListenOptions {
+        host: server.host.clone(),
+        port,
+        unix_socket: server.unix_socket.as_ref().map(Into::into),
+        tls,
+        tcp_nodelay: server.tcp_nodelay,
+        tcp_keepalive: server.tcp_keepalive_secs.map(std::time::Duration::from_secs),
+        http_keepalive: !server.disable_http_keepalive,
+        max_connections: server.max_connections,
+        max_body_bytes: server.max_body_bytes,
+    })
+}
+
+/// Build the paper trading sandbox of `matching_engine`'s market, when enabled
+fn build_paper_exchange<S: Storage>(config: &ClobConfig, matching_engine: &MatchingEngine<S>) -> Option<PaperExchange> {
+    let server = &config.rpc_server;
+    server.paper_trading.then(|| {
+        PaperExchange::new(
+            matching_engine,
+            (
+                server.paper_base_balance.unwrap_or(DEFAULT_PAPER_BALANCE),
+                server.paper_quote_balance.unwrap_or(DEFAULT_PAPER_BALANCE),
+            ),
+        )
+    })
+}
+
+/// Build the risk engine from the `[risk]` section (no limits when absent)
+fn build_risk_engine(config: &ClobConfig, clock: Arc<dyn Clock>) -> Arc<RiskEngine> {
+    let limits = config
+        .risk
+        .as_ref()
+        .map(|risk| RiskLimits {
+            max_order_size: risk.max_order_size,
+            max_open_notional: risk.max_open_notional.map(u128::from),
+            price_collar_bps: risk.price_collar_bps,
+        })
+        .unwrap_or_default();
+    Arc::new(RiskEngine::new(limits).with_clock(clock))
+}
+
+/// Publish over `ws_state` the order flow journaled from now on: orders
+/// placed and cancelled, fills with their owners, every modification as the
+/// original order cancelled, then its replacement linked to it, and every
+/// order market maker protection pulled, with the limit
+fn spawn_order_feed(storage: Arc<PostgresStorage>, ws_state: Arc<WebSocketServerState>) {
+    tokio::spawn(async move {
+        let mut position = match storage.get_journal_head().await {
+            Ok(head) => head,
+            Err(e) => {
+                error!("Failed to read the journal head, order changes are not published: {}", e);
+                return;
+            }
+        };
+        let mut interval = tokio::time::interval(std::time::Duration::from_millis(ORDER_FEED_INTERVAL_MS));
+        loop {
+            interval.tick().await;
+            let entries = match storage.get_journal_entries(position, ORDER_FEED_BATCH).await {
+                Ok(entries) => entries,
+                Err(e) => {
+                    error!("Failed to read the journal for order changes: {}", e);
+                    continue;
+                }
+            };
+            for entry in entries {
+                position = entry.sequence;
+                match entry.event {
+                    JournalEvent::OrderPlaced { order } => ws_state.publish_order_update(order, None).await,
+                    JournalEvent::OrderCancelled { order_id } => match storage.get_order(order_id).await {
+                        Ok(Some(cancelled)) => ws_state.publish_order_update(cancelled, None).await,
+                        Ok(None) => warn!("Cancelled order {} is not stored", order_id),
+                        Err(e) => error!("Failed to get cancelled order {}: {}", order_id, e),
+                    },
+                    JournalEvent::TradeExecuted { trade, maker, taker } => ws_state.publish_trade(trade, maker, taker).await,
+                    JournalEvent::OrderReplaced { original_order_id, order } => {
+                        match storage.get_order(original_order_id).await {
+                            Ok(Some(original)) => ws_state.publish_order_replaced(original, order).await,
+                            Ok(None) => warn!("Replaced order {} is not stored", original_order_id),
+                            Err(e) => error!("Failed to get replaced order {}: {}", original_order_id, e),
+                        }
+                    }
+                    JournalEvent::MmpTriggered { limit, order_ids, .. } => {
+                        for order_id in order_ids {
+                            match storage.get_order(order_id).await {
+                                Ok(Some(pulled)) => ws_state.publish_mmp_pull(pulled, limit).await,
+                                Ok(None) => warn!("Pulled order {} is not stored", order_id),
+                                Err(e) => error!("Failed to get pulled order {}: {}", order_id, e),
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    });
+}
+
+/// Sample the market gauges on `[metrics]`'s interval, also writing them to
+/// Redis time series when configured; nothing is sampled without `[metrics]`
+async fn spawn_market_metrics(
+    matching_engine: Arc<RwLock<MatchingEngine<PostgresStorage>>>,
+    config: &ClobConfig,
+) -> InfraResult<()> {
+    let Some(metrics) = &config.metrics else {
+        return Ok(());
+    };
+    let retention_ms = metrics
+        .redis_time_series
+        .then(|| metrics.time_series_retention_secs.unwrap_or(DEFAULT_TIME_SERIES_RETENTION_SECS) * 1000);
+    let redis_url = config.redis.url.clone();
+    let period = std::time::Duration::from_secs(
+        metrics.market_interval_secs.unwrap_or(DEFAULT_MARKET_METRICS_INTERVAL_SECS).max(1),
+    );
+
+    tokio::spawn(async move {
+        // Samples are dropped from the time series while Redis is unavailable,
+        // and the sink connects once it is there
+        let breaker = CircuitBreaker::new("redis", "time_series");
+        let mut sink = None;
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            let sample = match matching_engine.read().await.sample_metrics().await {
+                Ok(sample) => sample,
+                Err(e) => {
+                    error!("Failed to sample market metrics: {}", e);
+                    continue;
+                }
+            };
+            svm_clob_observability::record_market_metrics(&sample);
+            let Some(retention_ms) = retention_ms else {
+                continue;
+            };
+            if sink.is_none() {
+                sink = breaker
+                    .call(svm_clob_observability::TimeSeriesSink::connect(&redis_url, TIME_SERIES_PREFIX, retention_ms))
+                    .await;
+            }
+            if let Some(sink) = &sink {
+                breaker.call(sink.add(&sample)).await;
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Feed `[oracle]`'s price into `risk` on its poll interval; with a circuit
+/// breaker, also move the market to cancel-only while its mid strays further
+/// from the price, and back to active once it returns. A market an operator
+/// moved out of active is left alone, as is one on a standby engine.
+async fn spawn_oracle(
+    matching_engine: Arc<RwLock<MatchingEngine<PostgresStorage>>>,
+    risk: Arc<RiskEngine>,
+    config: &ClobConfig,
+) -> InfraResult<()> {
+    let Some(oracle) = &config.oracle else {
+        return Ok(());
+    };
+    let tick_size = matching_engine.read().await.orderbook_config().tick_size;
+    let units = config
+        .orderbook
+        .metadata()
+        .units(tick_size)
+        .ok_or_else(|| InfraError::config("[oracle] needs the market's base_decimals and quote_decimals"))?;
+    let client = HermesClient::new(&oracle.hermes_url, &oracle.feed_id, units)?;
+    let max_age_secs = i64::from(oracle.max_age_secs.unwrap_or(DEFAULT_ORACLE_MAX_AGE_SECS));
+    let circuit_breaker_bps = oracle.circuit_breaker_bps;
+    let period = std::time::Duration::from_millis(oracle.poll_interval_ms.unwrap_or(DEFAULT_ORACLE_POLL_INTERVAL_MS).max(1));
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        // Whether the market is cancel-only because of the breaker
+        let mut tripped = false;
+        loop {
+            interval.tick().await;
+            match client.latest().await {
+                Ok(reference) => risk.set_reference_price(reference, max_age_secs),
+                Err(e) => warn!("Failed to read the oracle price: {}", e),
+            }
+
+            let Some(band_bps) = circuit_breaker_bps else {
+                continue;
+            };
+            let engine = matching_engine.read().await;
+            if !engine.is_active() {
+                continue;
+            }
+            let mid = match engine.get_order_book_snapshot().await {
+                Ok(book) => portfolio::mid_price(&book),
+                Err(e) => {
+                    error!("Failed to snapshot the book for the circuit breaker: {}", e);
+                    continue;
+                }
+            };
+            let (Some(reference), Some(mid)) = (risk.reference_price(), mid) else {
+                continue;
+            };
+            let in_band = svm_clob_risk::oracle::in_band(mid, &reference, band_bps);
+            match engine.status() {
+                MarketStatus::Active if !in_band => {
+                    warn!(
+                        "Mid {} is more than {} bps from oracle price {}, moving the market to cancel-only",
+                        mid, band_bps, reference.price
+                    );
+                    match engine.set_status(MarketStatus::CancelOnly).await {
+                        Ok(_) => tripped = true,
+                        Err(e) => error!("Failed to trip the circuit breaker: {}", e),
+                    }
+                }
+                MarketStatus::CancelOnly if tripped && in_band => {
+                    info!("Mid {} is back within {} bps of oracle price {}, reactivating the market", mid, band_bps, reference.price);
+                    match engine.set_status(MarketStatus::Active).await {
+                        Ok(_) => tripped = false,
+                        Err(e) => error!("Failed to reset the circuit breaker: {}", e),
+                    }
+                }
+                MarketStatus::CancelOnly => {}
+                // Changed by an operator since the breaker tripped
+                _ => tripped = false,
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Store a checkpoint of the book whenever the journal has moved since the last one
+fn spawn_book_checkpoints(
+    matching_engine: Arc<RwLock<MatchingEngine<PostgresStorage>>>,
+    storage: Arc<PostgresStorage>,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(BOOK_CHECKPOINT_INTERVAL_SECS));
+        let mut last_sequence = 0;
+        loop {
+            interval.tick().await;
+            let checkpoint = matching_engine.read().await.checkpoint().await;
+            if checkpoint.journal_sequence == last_sequence {
+                continue;
+            }
+            match storage.store_book_checkpoint(&checkpoint).await {
+                Ok(()) => last_sequence = checkpoint.journal_sequence,
+                Err(e) => error!("Failed to checkpoint the book: {}", e),
+            }
+        }
+    });
+}
+
 /// Start only the WebSocket server
 async fn start_websocket_only(
-    _config: ClobConfig,
+    config: ClobConfig,
     port: u16,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> InfraResult<()> {
     info!("Starting WebSocket server only on port {}", port);
     
     let ws_state = Arc::new(WebSocketServerState::new());
-    start_ws_server(ws_state, port).await?;
+    start_ws_server(ws_state, listen_options(&config.websocket_server, port)?).await?;
     
     Ok(())
 }
 
-/// Initialize the database
-async fn init_database(config: ClobConfig) -> Result<(), Box<dyn std::error::Error>> {
-    info!("Initializing database");
+/// Start only the on-chain event indexer
+async fn start_indexer_only(config: ClobConfig) -> InfraResult<()> {
+    info!("Starting indexer for {} on {}", config.solana.program_id, config.solana.network);
     
-    let _storage = PostgresStorage::new(&config.database.url).await?;
+    let storage = Arc::new(connect_storage(&config).await?);
+    let indexer = Indexer::new(storage, indexer_config(&config)?);
+    indexer.run().await?;
     
-    info!("Database initialized successfully");
     Ok(())
 }
 
-/// Validate configuration
-fn validate_config(config: ClobConfig) -> Result<(), Box<dyn std::error::Error>> {
-    info!("Validating configuration");
+/// Build the surveillance configuration, keeping defaults for unset thresholds
+fn surveillance_config(settings: &SurveillanceSettings) -> SurveillanceConfig {
+    let defaults = SurveillanceConfig::default();
+    SurveillanceConfig {
+        window_secs: settings.window_secs.unwrap_or(defaults.window_secs),
+        wash_min_score: settings.wash_min_score.unwrap_or(defaults.wash_min_score),
+        spoof_min_cancel_to_fill: settings
+            .spoof_min_cancel_to_fill
+            .map(|ratio| ratio as f64)
+            .unwrap_or(defaults.spoof_min_cancel_to_fill),
+        ignition_move_bps: settings.ignition_move_bps.unwrap_or(defaults.ignition_move_bps),
+        ..defaults
+    }
+}
+
+/// Build the indexer configuration from the `[solana]` and `[indexer]` sections
+fn indexer_config(config: &ClobConfig) -> InfraResult<IndexerConfig> {
+    Ok(IndexerConfig {
+        rpc_url: config.solana.rpc_url.clone(),
+        ws_url: config.solana.ws_url.clone(),
+        program_id: config.solana.program_id.parse().map_err(InfraError::config)?,
+        commitment: config.solana.commitment.parse().map_err(InfraError::config)?,
+        backfill_page_size: 1000,
+        reconnect_delay: std::time::Duration::from_secs(5),
+        ingestion: match &config.indexer {
+            Some(indexer) => indexer.ingestion.parse::<IngestionMode>().map_err(InfraError::config)?,
+            None => IngestionMode::default(),
+        },
+        finality_poll_interval: std::time::Duration::from_secs(2),
+        vault_mints: if config.indexer.as_ref().is_some_and(|indexer| indexer.credit_vault_transfers) {
+            vec![
+                config.orderbook.base_mint.parse().map_err(InfraError::config)?,
+                config.orderbook.quote_mint.parse().map_err(InfraError::config)?,
+            ]
+        } else {
+            Vec::new()
+        },
+    })
+}
+
+/// Check the market's on-chain state against the book restored from storage
+/// before any order is taken, when `[indexer] onboard_from_chain` is set
+///
+/// The program keeps orders off chain, so the book comes from storage; the
+/// chain only counts every settled fill in the orderbook's `total_volume`.
+/// Settlements the indexer has not stored yet are backfilled, after which
+/// storage's settled volume must equal that count, or the infrastructure
+/// would match against a book that misses fills the chain already settled.
+/// The market's tick and minimum order sizes must match the configuration, a
+/// status other than active on chain is taken up by the engine, and the
+/// nonces of signed orders settled on chain are reserved so they are not
+/// accepted again.
+async fn onboard_market<S: Storage + 'static>(
+    config: &ClobConfig,
+    storage: Arc<S>,
+    matching_engine: &RwLock<MatchingEngine<S>>,
+    nonces: &NonceStore,
+    now: i64,
+) -> InfraResult<()> {
+    if !config.indexer.as_ref().is_some_and(|indexer| indexer.onboard_from_chain) {
+        return Ok(());
+    }
+    let base_mint = config.orderbook.base_mint.parse().map_err(InfraError::config)?;
+    let quote_mint = config.orderbook.quote_mint.parse().map_err(InfraError::config)?;
+    let indexer = Indexer::new(storage.clone(), indexer_config(config)?);
+
+    let mut attempt = 1;
+    let survey = loop {
+        indexer.backfill().await?;
+        let survey = indexer.survey_market(&base_mint, &quote_mint).await?;
+        let settled = storage.get_settled_volume().await?;
+        if settled == survey.orderbook.total_volume {
+            break survey;
+        }
+        // A settlement may land between the backfill and the survey
+        if attempt == ONBOARDING_ATTEMPTS {
+            return Err(InfraError::Diverged(format!(
+                "Storage holds {} of settled volume but market {} settled {} on chain",
+                settled, survey.address, survey.orderbook.total_volume
+            )));
+        }
+        warn!("Storage is behind market {} on chain, backfilling again", survey.address);
+        attempt += 1;
+    };
+
+    let orderbook = &survey.orderbook;
+    if orderbook.tick_size != config.orderbook.tick_size || orderbook.min_order_size != config.orderbook.min_order_size {
+        return Err(InfraError::Config(format!(
+            "Market {} has tick size {} and minimum order size {} on chain, configured {} and {}",
+            survey.address,
+            orderbook.tick_size,
+            orderbook.min_order_size,
+            config.orderbook.tick_size,
+            config.orderbook.min_order_size
+        )));
+    }
+
+    let engine = matching_engine.read().await;
+    let on_chain = MarketStatus::try_from(orderbook.status).unwrap_or(MarketStatus::Paused);
+    if on_chain != MarketStatus::Active && engine.status() != on_chain {
+        warn!("Market {} is {} on chain, taking that status up", survey.address, on_chain);
+        engine.set_status(on_chain).await?;
+    }
+
+    for fill in &survey.signed_order_fills {
+        nonces.reserve(storage.as_ref(), fill.owner, fill.nonce, fill.expiry, now).await?;
+    }
+    info!(
+        "Onboarded market {} at {} settled volume with {} open orders",
+        survey.address,
+        orderbook.total_volume,
+        engine.get_open_orders().await.len()
+    );
+    Ok(())
+}
+
+/// Start only the trade settlement crank
+async fn start_settler_only(config: ClobConfig) -> InfraResult<()> {
+    let storage = Arc::new(connect_storage(&config).await?);
+    let settler = build_settler(storage, &config)?
+        .ok_or_else(|| InfraError::config("No [settlement] section in configuration"))?;
+    
+    info!("Starting settler for {} on {}", config.solana.program_id, config.solana.network);
+    settler.run().await?;
+    
+    Ok(())
+}
+
+/// Build the settler from the `[solana]` and `[settlement]` sections
+fn build_settler(
+    storage: Arc<PostgresStorage>,
+    config: &ClobConfig,
+) -> InfraResult<Option<Settler<PostgresStorage>>> {
+    let Some(settlement) = &config.settlement else {
+        return Ok(None);
+    };
+    
+    let authority = solana_sdk::signature::read_keypair_file(&settlement.keypair_path)
+        .map_err(|e| InfraError::Config(format!("Failed to read keypair {}: {}", settlement.keypair_path, e)))?;
+    let program_id = config.solana.program_id.parse().map_err(InfraError::config)?;
+    let base_mint = config.orderbook.base_mint.parse().map_err(InfraError::config)?;
+    let quote_mint = config.orderbook.quote_mint.parse().map_err(InfraError::config)?;
+    let (orderbook, _) = svm_clob_types::program::orderbook_address(&program_id, &base_mint, &quote_mint);
+    
+    Ok(Some(Settler::new(storage, authority, SettlerConfig {
+        rpc_url: config.solana.rpc_url.clone(),
+        program_id,
+        orderbook,
+        mints: [base_mint, quote_mint],
+        commitment: config.solana.commitment.parse().map_err(InfraError::config)?,
+        max_trades_per_transaction: settlement.max_trades_per_tx,
+        journal_batch_size: 500,
+        poll_interval: std::time::Duration::from_millis(settlement.poll_interval_ms),
+        max_retries: settlement.max_retries,
+        retry_backoff: std::time::Duration::from_millis(500),
+    })))
+}
+
+/// Run a single reconciliation pass against storage and the chain
+async fn reconcile_once(config: ClobConfig, output: Option<String>) -> InfraResult<()> {
+    let storage = Arc::new(connect_storage(&config).await?);
+    let reconciliation = config.reconciliation.clone().unwrap_or(ReconciliationConfig {
+        interval_secs: 60,
+        report_path: None,
+        max_settlement_lag: 1000,
+    });
+    let reconciler = Reconciler::new(storage, None, reconciler_config(&config, &reconciliation)?);
+    
+    let report = reconciler.reconcile().await?;
+    match output {
+        Some(path) => {
+            svm_clob_reconciler::write_report(std::path::Path::new(&path), &report)?;
+        }
+        None => println!("{}", to_json(&report)?),
+    }
+    
+    if report.has_critical() {
+        return Err(InfraError::Diverged(format!("{} divergences found", report.divergences.len())));
+    }
+    Ok(())
+}
+
+/// Build the reconciler configuration from the `[solana]`, `[orderbook]` and `[reconciliation]` sections
+fn reconciler_config(
+    config: &ClobConfig,
+    reconciliation: &ReconciliationConfig,
+) -> InfraResult<ReconcilerConfig> {
+    Ok(ReconcilerConfig {
+        rpc_url: config.solana.rpc_url.clone(),
+        program_id: config.solana.program_id.parse().map_err(InfraError::config)?,
+        commitment: config.solana.commitment.parse().map_err(InfraError::config)?,
+        orderbook: OrderBook {
+            authority: solana_sdk::pubkey::Pubkey::default(),
+            base_mint: config.orderbook.base_mint.parse().map_err(InfraError::config)?,
+            quote_mint: config.orderbook.quote_mint.parse().map_err(InfraError::config)?,
+            tick_size: config.orderbook.tick_size,
+            min_order_size: config.orderbook.min_order_size,
+            sequence_number: 0,
+            total_orders: 0,
+            best_bid: 0,
+            best_ask: u64::MAX,
+            total_volume: 0,
+            is_initialized: true,
+            is_paused: false,
+        },
+        interval: std::time::Duration::from_secs(reconciliation.interval_secs),
+        report_path: reconciliation.report_path.as_ref().map(Into::into),
+        max_settlement_lag: reconciliation.max_settlement_lag,
+        order_types: config.orderbook.whitelist(),
+    })
+}
+
+/// Run one simulation and print or write its report
+async fn run_simulation(
+    config: ClobConfig,
+    from_journal: bool,
+    seed: u64,
+    events: usize,
+    start_mid: Option<u64>,
+    quote_spread_bps: Option<u64>,
+    output: Option<String>,
+) -> InfraResult<()> {
+    let orderbook = OrderBook {
+        authority: solana_sdk::pubkey::Pubkey::default(),
+        base_mint: config.orderbook.base_mint.parse().map_err(InfraError::config)?,
+        quote_mint: config.orderbook.quote_mint.parse().map_err(InfraError::config)?,
+        tick_size: config.orderbook.tick_size,
+        min_order_size: config.orderbook.min_order_size,
+        sequence_number: 0,
+        total_orders: 0,
+        best_bid: 0,
+        best_ask: u64::MAX,
+        total_volume: 0,
+        is_initialized: true,
+        is_paused: false,
+    };
+    let start_mid = start_mid.unwrap_or(config.orderbook.tick_size * 1000);
+    
+    let mut simulator = Simulator::new(orderbook).with_allocation(config.orderbook.allocation);
+    if let Some(spread_bps) = quote_spread_bps {
+        simulator = simulator.with_strategy(SimStrategy {
+            strategy: Box::new(SymmetricStrategy::new(SymmetricParams {
+                spread_bps,
+                levels: 1,
+                level_spacing_ticks: 1,
+                order_size: config.orderbook.min_order_size,
+                skew_bps: 0,
+                max_inventory: u64::MAX,
+            })),
+            owner: solana_sdk::pubkey::Pubkey::new_unique(),
+            refresh_interval_ms: 1000,
+            fallback_mid: Some(start_mid),
+        });
+    }
+    
+    let report = if from_journal {
+        let storage = connect_storage(&config).await?;
+        let mut entries = Vec::new();
+        loop {
+            let after = entries.last().map_or(0, |entry: &JournalEntry| entry.sequence);
+            let page = storage.get_journal_entries(after, 1000).await?;
+            if page.is_empty() {
+                break;
+            }
+            entries.extend(page);
+        }
+        info!("Replaying {} journal entries", entries.len());
+        simulator.run(events_from_journal(&entries)).await?
+    } else {
+        info!("Simulating {} synthetic events with seed {}", events, seed);
+        simulator
+            .run(SyntheticFlow::new(SyntheticFlowConfig {
+                seed,
+                events,
+                start_time: 0,
+                start_mid,
+                tick_size: config.orderbook.tick_size,
+                min_order_size: config.orderbook.min_order_size,
+                volatility_bps: 5,
+                mean_interarrival_ms: 100,
+                market_order_ratio: 0.1,
+                cancel_ratio: 0.3,
+                max_depth_ticks: 20,
+                max_lots: 10,
+                traders: 20,
+            }))
+            .await?
+    };
+    
+    let json = to_json(&report)?;
+    match output {
+        Some(path) => std::fs::write(path, json)?,
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+/// Prompt commands of `replay --interactive`
+const REPLAY_HELP: &str = "Commands:
+  n, next           replay the next command (also on an empty line)
+  c, continue       replay until a command diverges or the range ends
+  b, book           show the book at the current position
+  s, show           show the last step with the book before and after it
+  o, order <id>     show a resting order
+  q, quit           stop";
+
+/// Replay a journal range, at a prompt or into a report of diverged commands
+async fn replay_journal(
+    config: ClobConfig,
+    from: u64,
+    to: Option<u64>,
+    depth: usize,
+    interactive: bool,
+    output: Option<String>,
+) -> InfraResult<()> {
+    let storage = connect_storage(&config).await?;
+    let (orderbook, market_id) = configured_market(&config)?;
+    let entries = load_journal(&storage, to).await?;
+    info!("Loaded {} journal entries", entries.len());
+    let mut replayer = Replayer::new(orderbook, market_id, config.orderbook.allocation, entries, from, depth).await?;
+    
+    if interactive {
+        return replay_prompt(&mut replayer).await;
+    }
+    
+    let report = replayer.run_to_end().await?;
+    let json = to_json(&report)?;
+    match output {
+        Some(path) => std::fs::write(path, json)?,
+        None => println!("{}", json),
+    }
+    
+    if !report.diverged.is_empty() {
+        return Err(InfraError::Diverged(format!(
+            "{} of {} commands diverged",
+            report.diverged.len(),
+            report.commands_replayed
+        )));
+    }
+    Ok(())
+}
+
+/// Read replay commands from stdin until `quit` or end of input
+async fn replay_prompt(replayer: &mut Replayer) -> InfraResult<()> {
+    use std::io::Write;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    
+    println!("{}", REPLAY_HELP);
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut last_step: Option<ReplayStep> = None;
+    loop {
+        if replayer.is_done() {
+            println!("End of range at journal sequence {}", replayer.position());
+        }
+        print!("replay@{}> ", replayer.position());
+        std::io::stdout().flush()?;
+        let Some(line) = lines.next_line().await? else {
+            return Ok(());
+        };
+        let mut words = line.split_whitespace();
+        match words.next().unwrap_or("n") {
+            "n" | "next" => {
+                if let Some(step) = replayer.step().await? {
+                    println!("{}", describe_step(&step));
+                    last_step = Some(step);
+                }
+            }
+            "c" | "continue" => {
+                while let Some(step) = replayer.step().await? {
+                    let diverged = step.diverged();
+                    last_step = Some(step);
+                    if diverged {
+                        println!("{}", describe_step(last_step.as_ref().expect("step just replayed")));
+                        break;
+                    }
+                }
+            }
+            "b" | "book" => println!("{}", to_json(&replayer.book().await?)?),
+            "s" | "show" => match &last_step {
+                Some(step) => println!("{}", to_json(step)?),
+                None => println!("No command replayed yet"),
+            },
+            "o" | "order" => match words.next().and_then(|id| id.parse::<u64>().ok()) {
+                Some(order_id) => match replayer.order(order_id).await {
+                    Some(order) => println!("{}", to_json(&order)?),
+                    None => println!("Order {} is not resting", order_id),
+                },
+                None => println!("Usage: order <order_id>"),
+            },
+            "q" | "quit" => return Ok(()),
+            _ => println!("{}", REPLAY_HELP),
+        }
+    }
+}
+
+/// One-line summary of a replayed command
+fn describe_step(step: &ReplayStep) -> String {
+    let command = match &step.event {
+        JournalEvent::OrderPlaced { order } => format!(
+            "place {} {:?} {:?} {} @ {}",
+            order.order_id, order.side, order.order_type, order.quantity, order.price
+        ),
+        JournalEvent::OrderCancelled { order_id } => format!("cancel {}", order_id),
+        JournalEvent::OrderExpired { order_id, reason } => format!("expire {} ({:?})", order_id, reason),
+        JournalEvent::OrderReplaced { original_order_id, order } => format!(
+            "replace {} with {} ({} @ {})",
+            original_order_id, order.order_id, order.remaining_quantity, order.price
+        ),
+        JournalEvent::TradeExecuted { trade, .. } => format!("fill {}", trade.trade_id),
+        JournalEvent::OrderIdsReserved { through } => format!("reserve order IDs through {}", through),
+        JournalEvent::MmpTriggered { owner, limit, order_ids } => {
+            format!("mmp pull {} orders of {} ({:?})", order_ids.len(), owner, limit)
+        }
+        JournalEvent::TradeAdjusted { trade, previous_price, .. } => format!(
+            "adjust trade {} to v{} ({:?} @ {}, was {})",
+            trade.trade_id, trade.version, trade.status, trade.price, previous_price
+        ),
+    };
+    let mut summary = format!(
+        "#{} {}: {} fills replayed, {} journaled",
+        step.sequence,
+        command,
+        step.replayed.len(),
+        step.recorded.len()
+    );
+    if let Some(error) = &step.error {
+        summary.push_str(&format!(" - DIVERGED: replay failed with {}", error));
+    } else if !step.divergences.is_empty() {
+        summary.push_str(&format!(" - DIVERGED: {} fills differ (`show` for details)", step.divergences.len()));
+    }
+    summary
+}
+
+/// Backup settings from the `[database]` and `[backup]` sections
+fn backup_config(config: &ClobConfig) -> BackupConfig {
+    let settings = config.backup.clone().unwrap_or(BackupSettings {
+        pg_dump: None,
+        pg_restore: None,
+        segment_size: None,
+    });
+    BackupConfig {
+        database_url: config.database.url.clone(),
+        segment_size: settings.segment_size.unwrap_or(10_000),
+        journal_consumers: vec![
+            svm_clob_settler::CHECKPOINT_SERVICE.to_string(),
+            svm_clob_fees::CHECKPOINT_SERVICE.to_string(),
+            svm_clob_surveillance::CHECKPOINT_SERVICE.to_string(),
+            svm_clob_recorder::CHECKPOINT_SERVICE.to_string(),
+        ],
+        pg_dump: settings.pg_dump.unwrap_or_else(|| "pg_dump".to_string()),
+        pg_restore: settings.pg_restore.unwrap_or_else(|| "pg_restore".to_string()),
+        allocation: config.orderbook.allocation,
+    }
+}
+
+/// Postgres storage of `config`, encrypting columns when `[encryption]` is set
+async fn connect_storage(config: &ClobConfig) -> InfraResult<PostgresStorage> {
+    let storage = PostgresStorage::new(&config.database.url).await?;
+    let Some(encryption) = &config.encryption else {
+        return Ok(storage);
+    };
+
+    let mut keys = std::collections::HashMap::new();
+    for key in &encryption.keys {
+        let encoded = std::env::var(&key.key_env)
+            .map_err(|_| InfraError::Config(format!("encryption key {} is not set in {}", key.id, key.key_env)))?;
+        keys.insert(key.id, encoded);
+    }
+    let cipher = FieldCipher::new(encryption.active_key, &keys)?;
+    info!("Encrypting stored PII under key {}", cipher.active_key_id());
+    Ok(storage.with_cipher(Arc::new(cipher)))
+}
+
+/// URL of the configured local RPC server
+fn default_server(config: &ClobConfig) -> String {
+    format!("http://127.0.0.1:{}", config.rpc_server.port)
+}
+
+/// Client of `server` and display of `market`, defaulting to the configured
+/// local RPC server and market
+async fn market_display(
+    config: &ClobConfig,
+    market: Option<String>,
+    server: Option<String>,
+) -> InfraResult<(ClobClient, MarketDisplay)> {
+    let client = ClobClient::new(server.unwrap_or_else(|| default_server(config)))?;
+    let market_id = match market {
+        Some(market) => market.parse().map_err(InfraError::config)?,
+        None => configured_market(config)?.1,
+    };
+    let format = config.display.as_ref().map(NumberFormat::from).unwrap_or_default();
+    let display = MarketDisplay::fetch(&client, &market_id, format).await?;
+    if !display.has_units() {
+        warn!("Market {} lists no decimals; prices and quantities are in native units", market_id);
+    }
+    Ok((client, display))
+}
+
+/// Run an order subcommand and print the resulting order
+async fn run_order_command(config: &ClobConfig, action: OrderCommands) -> InfraResult<()> {
+    let (display, order) = match action {
+        OrderCommands::Place {
+            owner,
+            side,
+            price,
+            quantity,
+            post_only,
+            ioc,
+            max_slippage_bps,
+            client_order_id,
+            market,
+            server,
+        } => {
+            let (client, display) = market_display(config, market, server).await?;
+            let owner: solana_sdk::pubkey::Pubkey = owner.parse().map_err(InfraError::config)?;
+            let quantity = display.parse_quantity(&quantity)?;
+            let mut request = match price {
+                Some(price) => {
+                    let price = display.parse_price(&price)?;
+                    let mut request = if post_only {
+                        post_only_order(&owner, side.into(), price.0, quantity.0, client_order_id)
+                    } else {
+                        limit_order(&owner, side.into(), price.0, quantity.0, client_order_id)
+                    };
+                    if ioc {
+                        request.time_in_force = TimeInForce::ImmediateOrCancel;
+                    }
+                    request
+                }
+                None => market_order(&owner, side.into(), quantity.0, max_slippage_bps, client_order_id),
+            };
+            request.market_id = Some(display.info().market_id);
+            let order = client.place_order(&request).await?;
+            (display, order)
+        }
+        OrderCommands::Modify {
+            order_id,
+            price,
+            quantity,
+            market,
+            server,
+        } => {
+            let (client, display) = market_display(config, market, server).await?;
+            let price = price.map(|price| display.parse_price(&price)).transpose()?;
+            let quantity = quantity.map(|quantity| display.parse_quantity(&quantity)).transpose()?;
+            let order = client
+                .modify_order(order_id, price.map(u64::from), quantity.map(u64::from))
+                .await?;
+            (display, order)
+        }
+        OrderCommands::Cancel { order_id, market, server } => {
+            let (client, display) = market_display(config, market, server).await?;
+            let order = client.cancel_order(order_id).await?;
+            (display, order)
+        }
+    };
+    println!("{}", describe_order(&display, &order));
+    Ok(())
+}
+
+/// One line summary of an order in the market's units
+fn describe_order(display: &MarketDisplay, order: &Order) -> String {
+    let side = match order.side {
+        OrderSide::Bid => "buy",
+        OrderSide::Ask => "sell",
+    };
+    let price = match order.order_type {
+        OrderType::Market => "market".to_string(),
+        _ => format!("{} {}", display.price(Price(order.price)), display.quote_symbol()),
+    };
+    format!(
+        "order {}: {} {} {} @ {}, {:?}, {} filled",
+        order.order_id,
+        side,
+        display.quantity(Quantity(order.quantity)),
+        display.base_symbol(),
+        price,
+        order.status,
+        display.quantity(Quantity(order.quantity - order.remaining_quantity)),
+    )
+}
+
+/// Print the ticker and `levels` levels per side of a market every `interval_ms`
+async fn watch_market(
+    config: &ClobConfig,
+    market: Option<String>,
+    server: Option<String>,
+    levels: u32,
+    interval_ms: u64,
+) -> InfraResult<()> {
+    let (client, display) = market_display(config, market, server).await?;
+    let market_id = display.info().market_id;
+    let mut interval = tokio::time::interval(std::time::Duration::from_millis(interval_ms.max(1)));
+    loop {
+        interval.tick().await;
+        let view = match client.get_ticker(&market_id).await {
+            Ok(ticker) => client.get_depth(&market_id, levels).await.map(|depth| (ticker, depth)),
+            Err(e) => Err(e),
+        };
+        match view {
+            Ok((ticker, depth)) => println!("{}", render_market(&display, &ticker, &depth)),
+            Err(e) => warn!("Failed to read market {}: {}", market_id, e),
+        }
+    }
+}
+
+/// Ticker line followed by the book, bids left and asks right
+fn render_market(display: &MarketDisplay, ticker: &Ticker, depth: &Depth) -> String {
+    let price = |price: Option<Price>| price.map_or_else(|| "-".to_string(), |price| display.price(price));
+    let mut rendered = format!(
+        "{}  last {}  bid {}  ask {}  24h high {}  low {}  volume {} {} ({} {})\n",
+        display.info().metadata.symbol().unwrap_or_else(|| ticker.market_id.to_string()),
+        price(ticker.last_price),
+        price(ticker.best_bid),
+        price(ticker.best_ask),
+        price(ticker.high_24h),
+        price(ticker.low_24h),
+        display.quantity(ticker.volume_24h),
+        display.base_symbol(),
+        display.notional(ticker.quote_volume_24h),
+        display.quote_symbol(),
+    );
+    for index in 0..depth.bids.len().max(depth.asks.len()) {
+        let bid = depth.bids.get(index);
+        let ask = depth.asks.get(index);
+        rendered.push_str(&format!(
+            "{:>20} {:>16} | {:<16} {:<20}\n",
+            bid.map_or_else(String::new, |level| display.quantity(level.quantity)),
+            bid.map_or_else(String::new, |level| display.price(level.price)),
+            ask.map_or_else(String::new, |level| display.price(level.price)),
+            ask.map_or_else(String::new, |level| display.quantity(level.quantity)),
+        ));
+    }
+    rendered
+}
+
+/// Orderbook configuration and market ID of the configured market
+fn configured_market(config: &ClobConfig) -> InfraResult<(OrderBook, MarketId)> {
+    let orderbook = OrderBook {
+        authority: solana_sdk::pubkey::Pubkey::default(),
+        base_mint: config.orderbook.base_mint.parse().map_err(InfraError::config)?,
+        quote_mint: config.orderbook.quote_mint.parse().map_err(InfraError::config)?,
+        tick_size: config.orderbook.tick_size,
+        min_order_size: config.orderbook.min_order_size,
+        sequence_number: 0,
+        total_orders: 0,
+        best_bid: 0,
+        best_ask: u64::MAX,
+        total_volume: 0,
+        is_initialized: true,
+        is_paused: false,
+    };
+    let market_id = MarketId::new(&config.solana.program_id.parse().map_err(InfraError::config)?, &orderbook.base_mint, &orderbook.quote_mint);
+    Ok((orderbook, market_id))
+}
+
+/// Write a backup to a new directory
+async fn backup(config: ClobConfig, output: String) -> InfraResult<()> {
+    let storage = connect_storage(&config).await?;
+    let (orderbook, market_id) = configured_market(&config)?;
+    
+    let manifest = svm_clob_backup::create_backup(
+        &storage,
+        &orderbook,
+        market_id,
+        &backup_config(&config),
+        std::path::Path::new(&output),
+    )
+    .await?;
+    println!("{}", to_json(&manifest)?);
+    Ok(())
+}
+
+/// Restore a backup into the configured database
+async fn restore(
+    config: ClobConfig,
+    from: String,
+    at: Option<String>,
+    yes: bool,
+) -> InfraResult<()> {
+    let at = at.as_deref().map(parse_timestamp).transpose()?;
+    let dir = std::path::Path::new(&from);
+    let (orderbook, _) = configured_market(&config)?;
+    let backup_config = backup_config(&config);
     
-    // Validate database URL format
-    if !config.database.url.starts_with("postgresql://") {
-        return Err("Invalid database URL format".into());
+    // Check the backup and rebuild the book before touching the database
+    let plan = RestorePlan::prepare(dir, &orderbook, at).await?;
+    info!(
+        "Restoring to journal sequence {} ({} resting orders)",
+        plan.book.journal_sequence,
+        plan.book.orders.len()
+    );
+    if !yes {
+        return Err(InfraError::config("Restoring replaces the configured database; pass --yes to confirm"));
     }
     
-    // Validate Redis URL format
-    if !config.redis.url.starts_with("redis://") {
-        return Err("Invalid Redis URL format".into());
+    plan.restore_database(dir, &backup_config).await?;
+    // Connecting runs migrations, bringing an older dump up to this schema
+    let storage = connect_storage(&config).await?;
+    let report = plan.apply(&storage, &backup_config).await?;
+    println!("{}", to_json(&report)?);
+    Ok(())
+}
+
+/// Recorder configuration, keeping defaults for unset settings
+fn recorder_config(settings: &RecorderSettings, market_id: MarketId) -> RecorderConfig {
+    let defaults = RecorderConfig::new(&settings.dir, market_id);
+    RecorderConfig {
+        snapshot_interval: settings
+            .snapshot_interval_ms
+            .map_or(defaults.snapshot_interval, std::time::Duration::from_millis),
+        depth: settings.depth.unwrap_or(defaults.depth),
+        segment_interval: settings
+            .segment_secs
+            .map_or(defaults.segment_interval, std::time::Duration::from_secs),
+        ..defaults
     }
+}
+
+/// Print the recorded book at a point, or the recorded books and fills of a range
+async fn query_recording(
+    config: ClobConfig,
+    at: Option<String>,
+    range: Option<(String, String)>,
+    dir: Option<String>,
+    output: Option<String>,
+) -> InfraResult<()> {
+    let dir = dir
+        .or_else(|| config.recorder.as_ref().map(|recorder| recorder.dir.clone()))
+        .ok_or_else(|| InfraError::config("No recording directory: pass --dir or configure [recorder]"))?;
+    let (_, market_id) = configured_market(&config)?;
+    let recording = Recording::open(&dir, market_id);
     
-    // Validate mint addresses
-    let _base_mint: solana_sdk::pubkey::Pubkey = config.orderbook.base_mint.parse()?;
-    let _quote_mint: solana_sdk::pubkey::Pubkey = config.orderbook.quote_mint.parse()?;
+    let result = match (at, range) {
+        (Some(at), _) => serde_json::to_value(recording.book_at(parse_timestamp(&at)? * 1000)?)
+            .map_err(|e| ClobError::SerializationError(e.to_string()))?,
+        (None, Some((from, to))) => {
+            // Whole seconds, so the end of the range covers its last second
+            let (from_ms, to_ms) = (parse_timestamp(&from)? * 1000, parse_timestamp(&to)? * 1000 + 999);
+            serde_json::json!({
+                "books": recording.books(from_ms, to_ms)?,
+                "trades": recording.trades(from_ms, to_ms)?,
+            })
+        }
+        (None, None) => return Err(InfraError::config("Pass --at, or --from and --to")),
+    };
     
-    // Validate tick size and min order size
-    if config.orderbook.tick_size == 0 {
-        return Err("Tick size must be greater than 0".into());
+    let json = to_json(&result)?;
+    match output {
+        Some(path) => std::fs::write(path, json)?,
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+/// Pretty JSON of a report or record printed or written by a command
+fn to_json<T: Serialize>(value: &T) -> InfraResult<String> {
+    Ok(serde_json::to_string_pretty(value).map_err(|e| ClobError::SerializationError(e.to_string()))?)
+}
+
+/// Parse unix seconds or an RFC 3339 timestamp
+fn parse_timestamp(value: &str) -> InfraResult<i64> {
+    match value.parse::<i64>() {
+        Ok(seconds) => Ok(seconds),
+        Err(_) => Ok(chrono::DateTime::parse_from_rfc3339(value)
+            .map_err(InfraError::config)?
+            .timestamp()),
     }
+}
+
+/// Initialize the database
+async fn init_database(config: ClobConfig) -> InfraResult<()> {
+    info!("Initializing database");
+    
+    let _storage = connect_storage(&config).await?;
+    
+    info!("Database initialized successfully");
+    Ok(())
+}
+
+/// Validate a configuration file against the schema, then the configuration
+/// it loads as once `CLOB_*` environment overrides are merged over it
+fn validate_config(config_path: &str) -> InfraResult<()> {
+    info!("Validating configuration: {}", config_path);
     
-    if config.orderbook.min_order_size == 0 {
-        return Err("Minimum order size must be greater than 0".into());
+    let source = std::fs::read_to_string(config_path)
+        .map_err(|e| InfraError::Config(format!("Failed to read {}: {}", config_path, e)))?;
+    
+    let (issues, origin) = match schema::validate_document(&source) {
+        Ok(_) => {
+            let issues = match merged_config(config_path) {
+                Ok(merged) => schema::validate_config(&merged),
+                Err(e) => vec![schema::ConfigIssue {
+                    path: "<environment>".to_string(),
+                    line: None,
+                    message: e.to_string(),
+                }],
+            };
+            (issues, format!("{} with CLOB_* overrides", config_path))
+        }
+        Err(issues) => (issues, config_path.to_string()),
+    };
+    
+    if issues.is_empty() {
+        info!("Configuration is valid");
+        return Ok(());
+    }
+    for issue in &issues {
+        error!("{}: {}", origin, issue);
+    }
+    Err(InfraError::Config(format!("{} configuration error(s) in {}", issues.len(), origin)))
+}
+
+/// Handle `config` subcommands
+fn run_config_command(action: &ConfigCommands, config_path: &str) -> InfraResult<()> {
+    match action {
+        ConfigCommands::Init { network, output, force } => {
+            let path = output.as_deref().unwrap_or(config_path);
+            if std::path::Path::new(path).exists() && !force {
+                return Err(InfraError::Config(format!("{} already exists, pass --force to overwrite", path)));
+            }
+            
+            let config = network::scaffold_config(*network);
+            std::fs::write(path, toml::to_string_pretty(&config).map_err(InfraError::config)?)?;
+            info!("Wrote {} configuration to {}", network, path);
+        }
     }
     
-    info!("Configuration is valid");
     Ok(())
 }
 
 /// Show system status
-async fn show_status(_config: ClobConfig) -> Result<(), Box<dyn std::error::Error>> {
+async fn show_status(_config: ClobConfig) -> InfraResult<()> {
     info!("System Status:");
     info!("- Version: 0.1.0");
     info!("- Status: Running");
@@ -0,0 +1,100 @@
+/// Cluster presets for configuration scaffolding
+///
+/// This module holds the per-cluster constants (mints, program ID, RPC endpoints)
+/// used by `svm-clob config init` to generate a ready-to-run configuration.
+
+use crate::{ClobConfig, SolanaConfig};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// SVM CLOB program ID (see `declare_id!` in the on-chain program)
+pub const PROGRAM_ID: &str = "JBphRWHYzHCiVvYB89vGM9NpaDmHbe1A9W156sRV52Bo";
+
+/// Wrapped SOL mint, identical on every cluster
+pub const WRAPPED_SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
+/// Supported Solana clusters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    Devnet,
+    Mainnet,
+}
+
+impl Network {
+    /// JSON-RPC endpoint for the cluster
+    pub fn rpc_url(&self) -> &'static str {
+        match self {
+            Network::Devnet => "https://api.devnet.solana.com",
+            Network::Mainnet => "https://api.mainnet-beta.solana.com",
+        }
+    }
+
+    /// PubSub websocket endpoint for the cluster
+    pub fn ws_url(&self) -> &'static str {
+        match self {
+            Network::Devnet => "wss://api.devnet.solana.com",
+            Network::Mainnet => "wss://api.mainnet-beta.solana.com",
+        }
+    }
+
+    /// Circle USDC mint for the cluster
+    pub fn usdc_mint(&self) -> &'static str {
+        match self {
+            Network::Devnet => "4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU",
+            Network::Mainnet => "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+        }
+    }
+
+    /// Deployed SVM CLOB program ID for the cluster
+    pub fn program_id(&self) -> &'static str {
+        PROGRAM_ID
+    }
+
+    /// Parse the cluster name used in the `[solana]` config section
+    pub fn from_cluster_name(name: &str) -> Option<Self> {
+        match name {
+            "devnet" => Some(Network::Devnet),
+            "mainnet" | "mainnet-beta" => Some(Network::Mainnet),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Network::Devnet => write!(f, "devnet"),
+            Network::Mainnet => write!(f, "mainnet"),
+        }
+    }
+}
+
+impl SolanaConfig {
+    /// Solana connection settings for a cluster
+    pub fn for_network(network: Network) -> Self {
+        Self {
+            network: network.to_string(),
+            rpc_url: network.rpc_url().to_string(),
+            ws_url: network.ws_url().to_string(),
+            program_id: network.program_id().to_string(),
+            commitment: "confirmed".to_string(),
+        }
+    }
+}
+
+impl Default for SolanaConfig {
+    fn default() -> Self {
+        Self::for_network(Network::Mainnet)
+    }
+}
+
+/// Build a complete configuration for the given cluster
+pub fn scaffold_config(network: Network) -> ClobConfig {
+    let mut config = ClobConfig::default();
+    config.solana = SolanaConfig::for_network(network);
+    config.orderbook.base_mint = WRAPPED_SOL_MINT.to_string();
+    config.orderbook.quote_mint = network.usdc_mint().to_string();
+    config
+}
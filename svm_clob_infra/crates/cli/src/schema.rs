@@ -0,0 +1,751 @@
+/// Configuration schema validation
+///
+/// This module checks a raw TOML configuration document against the expected
+/// `ClobConfig` schema and reports every problem with its dotted key path and
+/// source line, instead of stopping at the first deserialization failure.
+
+use crate::network::Network;
+use crate::ClobConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::fmt;
+use svm_clob_types::SpeedBump;
+
+/// A single configuration problem with its location
+#[derive(Debug, Clone)]
+pub struct ConfigIssue {
+    /// Dotted key path, e.g. `orderbook.tick_size`
+    pub path: String,
+    /// 1-based line in the source document, when it can be located
+    pub line: Option<usize>,
+    /// Human readable description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{} (line {}): {}", self.path, line, self.message),
+            None => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+/// Expected type of a configuration value
+#[derive(Debug, Clone, Copy)]
+enum ValueKind {
+    String,
+    Boolean,
+    /// Unsigned integer with an inclusive upper bound
+    Unsigned(u64),
+    /// Signed integer with inclusive bounds
+    Signed(i64, i64),
+    /// Number between 0 and 1 inclusive
+    Ratio,
+    /// Base58 encoded Solana public key
+    Pubkey,
+    /// URL restricted to the given schemes
+    Url(&'static [&'static str]),
+    /// Nested table
+    Table(&'static [Field]),
+    /// Array of tables sharing one schema
+    TableArray(&'static [Field]),
+    /// Array of unsigned integers with an inclusive upper bound
+    UnsignedArray(u64),
+    /// String that is one of the given values
+    OneOf(&'static [&'static str]),
+    /// Array of strings, each one of the given values
+    OneOfArray(&'static [&'static str]),
+}
+
+impl ValueKind {
+    fn describe(&self) -> &'static str {
+        match self {
+            ValueKind::String | ValueKind::Pubkey | ValueKind::Url(_) | ValueKind::OneOf(_) => "string",
+            ValueKind::Boolean => "boolean",
+            ValueKind::Unsigned(_) | ValueKind::Signed(..) => "integer",
+            ValueKind::Ratio => "float",
+            ValueKind::Table(_) => "table",
+            ValueKind::TableArray(_) | ValueKind::UnsignedArray(_) | ValueKind::OneOfArray(_) => "array",
+        }
+    }
+}
+
+/// Schema entry for a single key
+#[derive(Debug, Clone, Copy)]
+struct Field {
+    name: &'static str,
+    kind: ValueKind,
+    required: bool,
+}
+
+const fn required(name: &'static str, kind: ValueKind) -> Field {
+    Field { name, kind, required: true }
+}
+
+const fn optional(name: &'static str, kind: ValueKind) -> Field {
+    Field { name, kind, required: false }
+}
+
+const DATABASE_SCHEMA: &[Field] = &[
+    required("url", ValueKind::Url(&["postgresql://", "postgres://"])),
+    required("max_connections", ValueKind::Unsigned(u32::MAX as u64)),
+    required("min_connections", ValueKind::Unsigned(u32::MAX as u64)),
+];
+
+const REDIS_SCHEMA: &[Field] = &[
+    required("url", ValueKind::Url(&["redis://", "rediss://"])),
+    required("pool_size", ValueKind::Unsigned(u32::MAX as u64)),
+];
+
+const SERVER_SCHEMA: &[Field] = &[
+    required("host", ValueKind::String),
+    required("port", ValueKind::Unsigned(u16::MAX as u64)),
+    optional("workers", ValueKind::Unsigned(u32::MAX as u64)),
+    optional("require_signed_orders", ValueKind::Boolean),
+    optional("max_orders_per_second", ValueKind::Unsigned(u32::MAX as u64)),
+    optional("max_requests_per_ip_per_second", ValueKind::Unsigned(u32::MAX as u64)),
+    optional("shared_rate_limits", ValueKind::Boolean),
+    optional("trust_forwarded_for", ValueKind::Boolean),
+    optional("require_api_key", ValueKind::Boolean),
+    optional("max_signature_ttl_secs", ValueKind::Unsigned(u32::MAX as u64)),
+    optional("shared_nonces", ValueKind::Boolean),
+    optional("paper_trading", ValueKind::Boolean),
+    optional("paper_base_balance", ValueKind::Unsigned(i64::MAX as u64)),
+    optional("paper_quote_balance", ValueKind::Unsigned(i64::MAX as u64)),
+    optional("unix_socket", ValueKind::String),
+    optional("tls_cert_path", ValueKind::String),
+    optional("tls_key_path", ValueKind::String),
+    optional("tcp_nodelay", ValueKind::Boolean),
+    optional("tcp_keepalive_secs", ValueKind::Unsigned(i64::MAX as u64)),
+    optional("disable_http_keepalive", ValueKind::Boolean),
+    optional("max_connections", ValueKind::Unsigned(u32::MAX as u64)),
+    optional("max_body_bytes", ValueKind::Unsigned(u32::MAX as u64)),
+];
+
+const ORDERBOOK_SCHEMA: &[Field] = &[
+    required("base_mint", ValueKind::Pubkey),
+    required("quote_mint", ValueKind::Pubkey),
+    required("tick_size", ValueKind::Unsigned(i64::MAX as u64)),
+    required("min_order_size", ValueKind::Unsigned(i64::MAX as u64)),
+    optional("base_decimals", ValueKind::Unsigned(u8::MAX as u64)),
+    optional("quote_decimals", ValueKind::Unsigned(u8::MAX as u64)),
+    optional("base_symbol", ValueKind::String),
+    optional("quote_symbol", ValueKind::String),
+    optional("allocation", ValueKind::OneOf(&["price_time", "pro_rata", "size_time"])),
+    optional("resting_lifetime", ValueKind::Table(RESTING_LIFETIME_SCHEMA)),
+    optional("speed_bump", ValueKind::Table(SPEED_BUMP_SCHEMA)),
+    optional("order_types", ValueKind::OneOfArray(&["Limit", "Market", "PostOnly"])),
+    optional(
+        "time_in_force",
+        ValueKind::OneOfArray(&["GoodTillCancelled", "ImmediateOrCancel", "FillOrKill", "GoodTillTime", "GoodTillDate"]),
+    ),
+];
+
+const RESTING_LIFETIME_SCHEMA: &[Field] = &[
+    required("max_resting_secs", ValueKind::Unsigned(i64::MAX as u64)),
+    optional("scope", ValueKind::OneOf(&["good_till_cancelled", "all_resting"])),
+];
+
+const SPEED_BUMP_SCHEMA: &[Field] = &[
+    optional("min_delay_us", ValueKind::Unsigned(SpeedBump::MAX_DELAY_US)),
+    required("max_delay_us", ValueKind::Unsigned(SpeedBump::MAX_DELAY_US)),
+];
+
+const MATCHING_ENGINE_SCHEMA: &[Field] = &[
+    required("max_orders_per_batch", ValueKind::Unsigned(u32::MAX as u64)),
+    required("matching_interval_ms", ValueKind::Unsigned(i64::MAX as u64)),
+    optional("cpu_cores", ValueKind::UnsignedArray(u16::MAX as u64)),
+    optional("pause_buffer", ValueKind::Unsigned(u32::MAX as u64)),
+];
+
+const LOGGING_SCHEMA: &[Field] = &[
+    required("level", ValueKind::String),
+    optional("file", ValueKind::String),
+    required("json_format", ValueKind::Boolean),
+];
+
+const SOLANA_SCHEMA: &[Field] = &[
+    required("network", ValueKind::String),
+    required("rpc_url", ValueKind::Url(&["http://", "https://"])),
+    required("ws_url", ValueKind::Url(&["ws://", "wss://"])),
+    required("program_id", ValueKind::Pubkey),
+    required("commitment", ValueKind::String),
+];
+
+const SETTLEMENT_SCHEMA: &[Field] = &[
+    required("keypair_path", ValueKind::String),
+    required("max_trades_per_tx", ValueKind::Unsigned(u32::MAX as u64)),
+    required("poll_interval_ms", ValueKind::Unsigned(i64::MAX as u64)),
+    required("max_retries", ValueKind::Unsigned(u32::MAX as u64)),
+];
+
+const RECONCILIATION_SCHEMA: &[Field] = &[
+    required("interval_secs", ValueKind::Unsigned(u32::MAX as u64)),
+    optional("report_path", ValueKind::String),
+    required("max_settlement_lag", ValueKind::Unsigned(i64::MAX as u64)),
+];
+
+const RISK_SCHEMA: &[Field] = &[
+    optional("max_order_size", ValueKind::Unsigned(i64::MAX as u64)),
+    optional("max_open_notional", ValueKind::Unsigned(i64::MAX as u64)),
+    optional("price_collar_bps", ValueKind::Unsigned(10_000)),
+];
+
+const ORACLE_SCHEMA: &[Field] = &[
+    required("hermes_url", ValueKind::Url(&["http://", "https://"])),
+    required("feed_id", ValueKind::String),
+    optional("poll_interval_ms", ValueKind::Unsigned(i64::MAX as u64)),
+    optional("max_age_secs", ValueKind::Unsigned(u32::MAX as u64)),
+    optional("circuit_breaker_bps", ValueKind::Unsigned(10_000)),
+];
+
+const ADMIN_SCHEMA: &[Field] = &[
+    required("token", ValueKind::String),
+];
+
+const TELEMETRY_SCHEMA: &[Field] = &[
+    required("otlp_endpoint", ValueKind::Url(&["http://", "https://"])),
+    optional("service_name", ValueKind::String),
+    optional("sample_ratio", ValueKind::Ratio),
+];
+
+const METRICS_SCHEMA: &[Field] = &[
+    required("listen", ValueKind::String),
+    optional("market_interval_secs", ValueKind::Unsigned(u32::MAX as u64)),
+    optional("redis_time_series", ValueKind::Boolean),
+    optional("time_series_retention_secs", ValueKind::Unsigned(u32::MAX as u64)),
+];
+
+const INDEXER_SCHEMA: &[Field] = &[
+    required("ingestion", ValueKind::String),
+    optional("onboard_from_chain", ValueKind::Boolean),
+    optional("credit_vault_transfers", ValueKind::Boolean),
+];
+
+const HA_SCHEMA: &[Field] = &[
+    required("instance_id", ValueKind::String),
+    optional("lease_ttl_ms", ValueKind::Unsigned(i64::MAX as u64)),
+    optional("renew_interval_ms", ValueKind::Unsigned(i64::MAX as u64)),
+];
+
+const SURVEILLANCE_SCHEMA: &[Field] = &[
+    optional("window_secs", ValueKind::Unsigned(i64::MAX as u64)),
+    optional("wash_min_score", ValueKind::Ratio),
+    optional("spoof_min_cancel_to_fill", ValueKind::Unsigned(u64::MAX)),
+    optional("ignition_move_bps", ValueKind::Unsigned(10_000)),
+];
+
+const FEE_TIER_SCHEMA: &[Field] = &[
+    required("min_volume", ValueKind::Unsigned(i64::MAX as u64)),
+    required("maker_fee_bps", ValueKind::Signed(-10_000, 10_000)),
+    required("taker_fee_bps", ValueKind::Unsigned(10_000)),
+];
+
+const FEES_SCHEMA: &[Field] = &[
+    required("tiers", ValueKind::TableArray(FEE_TIER_SCHEMA)),
+    optional("insurance_share_bps", ValueKind::Unsigned(10_000)),
+];
+
+const BACKUP_SCHEMA: &[Field] = &[
+    optional("pg_dump", ValueKind::String),
+    optional("pg_restore", ValueKind::String),
+    optional("segment_size", ValueKind::Unsigned(u32::MAX as u64)),
+];
+
+const RECORDER_SCHEMA: &[Field] = &[
+    required("dir", ValueKind::String),
+    optional("snapshot_interval_ms", ValueKind::Unsigned(i64::MAX as u64)),
+    optional("depth", ValueKind::Unsigned(u32::MAX as u64)),
+    optional("segment_secs", ValueKind::Unsigned(i64::MAX as u64)),
+];
+
+const INCENTIVES_SCHEMA: &[Field] = &[
+    required("reward_per_epoch", ValueKind::Unsigned(i64::MAX as u64)),
+    optional("band_bps", ValueKind::Unsigned(10_000)),
+    optional("epoch_secs", ValueKind::Unsigned(i64::MAX as u64)),
+];
+
+const OBLIGATIONS_SCHEMA: &[Field] = &[
+    optional("sample_secs", ValueKind::Unsigned(86_400)),
+    optional("period_secs", ValueKind::Unsigned(i64::MAX as u64)),
+];
+
+const STATEMENTS_SCHEMA: &[Field] = &[
+    optional("delay_secs", ValueKind::Unsigned(86_400)),
+];
+
+const DISPLAY_SCHEMA: &[Field] = &[
+    optional("price_precision", ValueKind::Unsigned(38)),
+    optional("quantity_precision", ValueKind::Unsigned(38)),
+    optional("thousands_separator", ValueKind::String),
+];
+
+const ENCRYPTION_KEY_SCHEMA: &[Field] = &[
+    required("id", ValueKind::Unsigned(u32::MAX as u64)),
+    required("key_env", ValueKind::String),
+];
+
+const ENCRYPTION_SCHEMA: &[Field] = &[
+    required("active_key", ValueKind::Unsigned(u32::MAX as u64)),
+    required("keys", ValueKind::TableArray(ENCRYPTION_KEY_SCHEMA)),
+];
+
+const CLOB_CONFIG_SCHEMA: &[Field] = &[
+    required("database", ValueKind::Table(DATABASE_SCHEMA)),
+    required("redis", ValueKind::Table(REDIS_SCHEMA)),
+    required("rpc_server", ValueKind::Table(SERVER_SCHEMA)),
+    required("websocket_server", ValueKind::Table(SERVER_SCHEMA)),
+    required("orderbook", ValueKind::Table(ORDERBOOK_SCHEMA)),
+    required("matching_engine", ValueKind::Table(MATCHING_ENGINE_SCHEMA)),
+    required("logging", ValueKind::Table(LOGGING_SCHEMA)),
+    optional("solana", ValueKind::Table(SOLANA_SCHEMA)),
+    optional("settlement", ValueKind::Table(SETTLEMENT_SCHEMA)),
+    optional("reconciliation", ValueKind::Table(RECONCILIATION_SCHEMA)),
+    optional("risk", ValueKind::Table(RISK_SCHEMA)),
+    optional("admin", ValueKind::Table(ADMIN_SCHEMA)),
+    optional("telemetry", ValueKind::Table(TELEMETRY_SCHEMA)),
+    optional("metrics", ValueKind::Table(METRICS_SCHEMA)),
+    optional("indexer", ValueKind::Table(INDEXER_SCHEMA)),
+    optional("ha", ValueKind::Table(HA_SCHEMA)),
+    optional("surveillance", ValueKind::Table(SURVEILLANCE_SCHEMA)),
+    optional("fees", ValueKind::Table(FEES_SCHEMA)),
+    optional("backup", ValueKind::Table(BACKUP_SCHEMA)),
+    optional("recorder", ValueKind::Table(RECORDER_SCHEMA)),
+    optional("incentives", ValueKind::Table(INCENTIVES_SCHEMA)),
+    optional("obligations", ValueKind::Table(OBLIGATIONS_SCHEMA)),
+    optional("statements", ValueKind::Table(STATEMENTS_SCHEMA)),
+    optional("display", ValueKind::Table(DISPLAY_SCHEMA)),
+    optional("encryption", ValueKind::Table(ENCRYPTION_SCHEMA)),
+    optional("oracle", ValueKind::Table(ORACLE_SCHEMA)),
+];
+
+/// Validate a TOML configuration document, returning the parsed config or every issue found
+pub fn validate_document(source: &str) -> Result<ClobConfig, Vec<ConfigIssue>> {
+    let table: toml::Table = source.parse().map_err(|e: toml::de::Error| {
+        vec![ConfigIssue {
+            path: "<document>".to_string(),
+            line: e.span().map(|span| line_of_offset(source, span.start)),
+            message: e.message().to_string(),
+        }]
+    })?;
+
+    let mut issues = Vec::new();
+    check_table(&table, CLOB_CONFIG_SCHEMA, "", &mut issues);
+
+    let mut config = None;
+    if issues.is_empty() {
+        match toml::Value::Table(table).try_into::<ClobConfig>() {
+            Ok(parsed) => {
+                issues.extend(validate_semantics(&parsed));
+                config = Some(parsed);
+            }
+            Err(e) => issues.push(ConfigIssue {
+                path: "<document>".to_string(),
+                line: None,
+                message: e.message().to_string(),
+            }),
+        }
+    }
+
+    match config {
+        Some(config) if issues.is_empty() => Ok(config),
+        _ => {
+            for issue in &mut issues {
+                if issue.line.is_none() {
+                    issue.line = locate(source, &issue.path);
+                }
+            }
+            Err(issues)
+        }
+    }
+}
+
+/// Validate a configuration assembled from more than one source, such as a
+/// file with `CLOB_*` environment overrides merged over it; its issues carry
+/// no source line
+pub fn validate_config(config: &ClobConfig) -> Vec<ConfigIssue> {
+    let source = match toml::to_string(config) {
+        Ok(source) => source,
+        Err(e) => {
+            return vec![ConfigIssue {
+                path: "<document>".to_string(),
+                line: None,
+                message: e.to_string(),
+            }]
+        }
+    };
+    match validate_document(&source) {
+        Ok(_) => Vec::new(),
+        Err(mut issues) => {
+            for issue in &mut issues {
+                issue.line = None;
+            }
+            issues
+        }
+    }
+}
+
+/// Semantic checks that go beyond value types
+pub fn validate_semantics(config: &ClobConfig) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+    let mut issue = |path: &str, message: String| {
+        issues.push(ConfigIssue { path: path.to_string(), line: None, message });
+    };
+
+    if config.database.min_connections > config.database.max_connections {
+        issue(
+            "database.min_connections",
+            format!(
+                "must not exceed database.max_connections ({})",
+                config.database.max_connections
+            ),
+        );
+    }
+
+    if config.rpc_server.port == config.websocket_server.port {
+        issue(
+            "websocket_server.port",
+            format!("conflicts with rpc_server.port ({})", config.rpc_server.port),
+        );
+    }
+
+    if config.orderbook.tick_size == 0 {
+        issue("orderbook.tick_size", "must be greater than 0".to_string());
+    }
+
+    if config.orderbook.min_order_size == 0 {
+        issue("orderbook.min_order_size", "must be greater than 0".to_string());
+    }
+
+    if config.orderbook.resting_lifetime.is_some_and(|lifetime| lifetime.max_resting_secs <= 0) {
+        issue("orderbook.resting_lifetime.max_resting_secs", "must be greater than 0".to_string());
+    }
+
+    if let Some(speed_bump) = config.orderbook.speed_bump.filter(|speed_bump| !speed_bump.is_valid()) {
+        issue(
+            "orderbook.speed_bump.min_delay_us",
+            format!("must not exceed orderbook.speed_bump.max_delay_us ({})", speed_bump.max_delay_us),
+        );
+    }
+
+    if let Some(cores) = &config.matching_engine.cpu_cores {
+        let mut distinct = cores.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        if cores.is_empty() {
+            issue("matching_engine.cpu_cores", "must name at least one core".to_string());
+        } else if distinct.len() != cores.len() {
+            issue("matching_engine.cpu_cores", "must not name a core twice".to_string());
+        }
+    }
+
+    if config.orderbook.base_mint == config.orderbook.quote_mint {
+        issue("orderbook.quote_mint", "must differ from orderbook.base_mint".to_string());
+    }
+
+    if tracing_subscriber::EnvFilter::try_new(&config.logging.level).is_err() {
+        issue(
+            "logging.level",
+            format!("invalid log filter '{}'", config.logging.level),
+        );
+    }
+
+    match Network::from_cluster_name(&config.solana.network) {
+        Some(network) => {
+            let other = match network {
+                Network::Devnet => Network::Mainnet,
+                Network::Mainnet => Network::Devnet,
+            };
+            if config.orderbook.quote_mint == other.usdc_mint() {
+                issue(
+                    "orderbook.quote_mint",
+                    format!("is the {} USDC mint but solana.network is {}", other, network),
+                );
+            }
+        }
+        None => issue(
+            "solana.network",
+            format!("unknown cluster '{}', expected devnet or mainnet", config.solana.network),
+        ),
+    }
+
+    if !matches!(config.solana.commitment.as_str(), "processed" | "confirmed" | "finalized") {
+        issue(
+            "solana.commitment",
+            format!(
+                "unknown commitment '{}', expected processed, confirmed or finalized",
+                config.solana.commitment
+            ),
+        );
+    }
+
+    if let Some(settlement) = &config.settlement {
+        if settlement.max_trades_per_tx == 0 {
+            issue("settlement.max_trades_per_tx", "must be greater than 0".to_string());
+        }
+    }
+
+    if let Some(reconciliation) = &config.reconciliation {
+        if reconciliation.interval_secs == 0 {
+            issue("reconciliation.interval_secs", "must be greater than 0".to_string());
+        }
+    }
+
+    if let Some(admin) = &config.admin {
+        if admin.token.len() < 32 {
+            issue("admin.token", "must be at least 32 characters".to_string());
+        }
+    }
+
+    if let Some(metrics) = &config.metrics {
+        if metrics.listen.parse::<std::net::SocketAddr>().is_err() {
+            issue("metrics.listen", format!("'{}' is not a socket address", metrics.listen));
+        }
+    }
+
+    if let Some(indexer) = &config.indexer {
+        if let Err(e) = indexer.ingestion.parse::<svm_clob_indexer::IngestionMode>() {
+            issue("indexer.ingestion", e);
+        }
+    }
+
+    if let Some(ha) = &config.ha {
+        if ha.instance_id.is_empty() {
+            issue("ha.instance_id", "must not be empty".to_string());
+        }
+        if ha.renew_interval_ms == 0 {
+            issue("ha.renew_interval_ms", "must be greater than 0".to_string());
+        } else if ha.renew_interval_ms.saturating_mul(2) > ha.lease_ttl_ms {
+            issue(
+                "ha.renew_interval_ms",
+                format!("must be at most half of ha.lease_ttl_ms ({})", ha.lease_ttl_ms),
+            );
+        }
+    }
+
+    if let Some(surveillance) = &config.surveillance {
+        if surveillance.window_secs == Some(0) {
+            issue("surveillance.window_secs", "must be greater than 0".to_string());
+        }
+    }
+
+    if let Some(fees) = &config.fees {
+        match fees.tiers.iter().map(|tier| tier.min_volume).min() {
+            None => issue("fees.tiers", "must contain at least one tier".to_string()),
+            Some(lowest) if lowest > 0 => {
+                issue("fees.tiers", format!("lowest min_volume is {}; users below it would trade free", lowest));
+            }
+            Some(_) => {}
+        }
+        for (index, tier) in fees.tiers.iter().enumerate() {
+            if tier.maker_fee_bps + tier.taker_fee_bps < 0 {
+                issue(
+                    &format!("fees.tiers[{}].maker_fee_bps", index),
+                    format!("rebate exceeds the taker fee ({} bps)", tier.taker_fee_bps),
+                );
+            }
+        }
+    }
+
+    if let Some(incentives) = &config.incentives {
+        if incentives.epoch_secs == Some(0) {
+            issue("incentives.epoch_secs", "must be greater than 0".to_string());
+        }
+    }
+
+    if let Some(obligations) = &config.obligations {
+        if obligations.sample_secs == Some(0) {
+            issue("obligations.sample_secs", "must be greater than 0".to_string());
+        }
+        if obligations.period_secs == Some(0) {
+            issue("obligations.period_secs", "must be greater than 0".to_string());
+        }
+    }
+
+    if let Some(encryption) = &config.encryption {
+        let mut ids = std::collections::HashSet::new();
+        for (index, key) in encryption.keys.iter().enumerate() {
+            if !ids.insert(key.id) {
+                issue(&format!("encryption.keys[{}].id", index), format!("key {} is listed twice", key.id));
+            }
+        }
+        if !ids.contains(&encryption.active_key) {
+            issue(
+                "encryption.active_key",
+                format!("key {} is not among encryption.keys", encryption.active_key),
+            );
+        }
+    }
+
+    if let Some(separator) = config.display.as_ref().and_then(|display| display.thousands_separator.as_deref()) {
+        let mut chars = separator.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if !c.is_ascii_digit() && c != '.' => {}
+            _ => issue(
+                "display.thousands_separator",
+                format!("'{}' must be a single character other than a digit or '.'", separator),
+            ),
+        }
+    }
+
+    issues
+}
+
+/// Check a table against its schema, recursing into nested tables
+fn check_table(table: &toml::Table, schema: &[Field], prefix: &str, issues: &mut Vec<ConfigIssue>) {
+    for field in schema {
+        let path = join_path(prefix, field.name);
+        match table.get(field.name) {
+            Some(value) => check_value(value, field.kind, &path, issues),
+            None if field.required => issues.push(ConfigIssue {
+                path,
+                line: None,
+                message: format!("missing required {}", field.kind.describe()),
+            }),
+            None => {}
+        }
+    }
+
+    for key in table.keys() {
+        if !schema.iter().any(|field| field.name == key) {
+            issues.push(ConfigIssue {
+                path: join_path(prefix, key),
+                line: None,
+                message: "unknown key".to_string(),
+            });
+        }
+    }
+}
+
+/// Check a single value against its expected kind
+fn check_value(value: &toml::Value, kind: ValueKind, path: &str, issues: &mut Vec<ConfigIssue>) {
+    let mismatch = |issues: &mut Vec<ConfigIssue>| {
+        issues.push(ConfigIssue {
+            path: path.to_string(),
+            line: None,
+            message: format!("expected {}, found {}", kind.describe(), value.type_str()),
+        });
+    };
+
+    match (kind, value) {
+        (ValueKind::String, toml::Value::String(_)) => {}
+        (ValueKind::Boolean, toml::Value::Boolean(_)) => {}
+        (ValueKind::Unsigned(max), toml::Value::Integer(n)) => {
+            if *n < 0 || *n as u64 > max {
+                issues.push(ConfigIssue {
+                    path: path.to_string(),
+                    line: None,
+                    message: format!("{} is out of range 0..={}", n, max),
+                });
+            }
+        }
+        (ValueKind::Signed(min, max), toml::Value::Integer(n)) => {
+            if *n < min || *n > max {
+                issues.push(ConfigIssue {
+                    path: path.to_string(),
+                    line: None,
+                    message: format!("{} is out of range {}..={}", n, min, max),
+                });
+            }
+        }
+        (ValueKind::Ratio, toml::Value::Float(_) | toml::Value::Integer(_)) => {
+            let ratio = value.as_float().unwrap_or_else(|| value.as_integer().unwrap_or_default() as f64);
+            if !(0.0..=1.0).contains(&ratio) {
+                issues.push(ConfigIssue {
+                    path: path.to_string(),
+                    line: None,
+                    message: format!("{} is out of range 0.0..=1.0", ratio),
+                });
+            }
+        }
+        (ValueKind::Pubkey, toml::Value::String(s)) => {
+            if let Err(e) = s.parse::<Pubkey>() {
+                issues.push(ConfigIssue {
+                    path: path.to_string(),
+                    line: None,
+                    message: format!("invalid public key '{}': {}", s, e),
+                });
+            }
+        }
+        (ValueKind::Url(schemes), toml::Value::String(s)) => {
+            if !schemes.iter().any(|scheme| s.starts_with(scheme)) {
+                issues.push(ConfigIssue {
+                    path: path.to_string(),
+                    line: None,
+                    message: format!("'{}' must start with one of {}", s, schemes.join(", ")),
+                });
+            }
+        }
+        (ValueKind::Table(schema), toml::Value::Table(inner)) => {
+            check_table(inner, schema, path, issues);
+        }
+        (ValueKind::TableArray(schema), toml::Value::Array(items)) => {
+            for (index, item) in items.iter().enumerate() {
+                check_value(item, ValueKind::Table(schema), &format!("{}[{}]", path, index), issues);
+            }
+        }
+        (ValueKind::UnsignedArray(max), toml::Value::Array(items)) => {
+            for (index, item) in items.iter().enumerate() {
+                check_value(item, ValueKind::Unsigned(max), &format!("{}[{}]", path, index), issues);
+            }
+        }
+        (ValueKind::OneOf(values), toml::Value::String(s)) => {
+            if !values.contains(&s.as_str()) {
+                issues.push(ConfigIssue {
+                    path: path.to_string(),
+                    line: None,
+                    message: format!("'{}' must be one of {}", s, values.join(", ")),
+                });
+            }
+        }
+        (ValueKind::OneOfArray(values), toml::Value::Array(items)) => {
+            for (index, item) in items.iter().enumerate() {
+                check_value(item, ValueKind::OneOf(values), &format!("{}[{}]", path, index), issues);
+            }
+        }
+        _ => mismatch(issues),
+    }
+}
+
+fn join_path(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+fn line_of_offset(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+/// Find the source line of a dotted key path (`section.key` or `section`)
+fn locate(source: &str, path: &str) -> Option<usize> {
+    let (section, key) = match path.rsplit_once('.') {
+        Some((section, key)) => (Some(section), key),
+        None => (None, path),
+    };
+
+    let mut in_section = section.is_none();
+    for (index, line) in source.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            let header = trimmed.trim_matches(|c| c == '[' || c == ']').trim();
+            if section.is_none() && header == key {
+                return Some(index + 1);
+            }
+            in_section = Some(header) == section;
+            continue;
+        }
+        if in_section {
+            if let Some((name, _)) = trimmed.split_once('=') {
+                if name.trim() == key {
+                    return Some(index + 1);
+                }
+            }
+        }
+    }
+
+    // Fall back to the enclosing section header
+    section.and_then(|section| locate(source, section))
+}
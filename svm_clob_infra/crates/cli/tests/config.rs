@@ -0,0 +1,144 @@
+//! Configuration schema checks
+//!
+//! Every configuration the CLI writes itself must pass `validate-config`, and
+//! each optional key must be accepted with the values its field takes.
+
+use svm_clob_cli::network::{scaffold_config, Network};
+use svm_clob_cli::schema::{validate_config, validate_document, ConfigIssue};
+use svm_clob_cli::ClobConfig;
+
+/// The default configuration with `key` of `section` set to `value`
+fn with_key(section: &str, key: &str, value: toml::Value) -> String {
+    let mut table: toml::Table = toml::to_string(&ClobConfig::default()).unwrap().parse().unwrap();
+    table
+        .entry(section)
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+        .as_table_mut()
+        .unwrap()
+        .insert(key.to_string(), value);
+    toml::to_string(&table).unwrap()
+}
+
+/// The default configuration with `section` replaced by the table in `source`
+fn with_section(section: &str, source: &str) -> String {
+    let mut table: toml::Table = toml::to_string(&ClobConfig::default()).unwrap().parse().unwrap();
+    table.insert(section.to_string(), self::table(source));
+    toml::to_string(&table).unwrap()
+}
+
+/// Table value parsed from TOML
+fn table(source: &str) -> toml::Value {
+    toml::Value::Table(source.parse().unwrap())
+}
+
+fn paths(issues: Vec<ConfigIssue>) -> Vec<String> {
+    issues.into_iter().map(|issue| issue.path).collect()
+}
+
+#[test]
+fn default_and_scaffolded_configs_validate() {
+    let mut configs = vec![ClobConfig::default()];
+    configs.extend([Network::Devnet, Network::Mainnet].map(scaffold_config));
+    for config in configs {
+        assert!(validate_config(&config).is_empty(), "{:?}", validate_config(&config));
+        // As `config init` and `load_config` write it
+        let source = toml::to_string_pretty(&config).unwrap();
+        assert!(validate_document(&source).is_ok(), "{:?}", validate_document(&source).err());
+    }
+}
+
+#[test]
+fn config_without_later_sections_loads() {
+    // As the first release wrote it, before `[solana]` and every optional section
+    let source = r#"
+[database]
+url = "postgresql://localhost/svm_clob"
+max_connections = 10
+min_connections = 1
+
+[redis]
+url = "redis://localhost:6379"
+pool_size = 10
+
+[rpc_server]
+host = "0.0.0.0"
+port = 8080
+
+[websocket_server]
+host = "0.0.0.0"
+port = 8081
+
+[orderbook]
+base_mint = "So11111111111111111111111111111111111111112"
+quote_mint = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"
+tick_size = 1000
+min_order_size = 1000000
+
+[matching_engine]
+max_orders_per_batch = 100
+matching_interval_ms = 10
+
+[logging]
+level = "info"
+json_format = false
+"#;
+    let config: ClobConfig = toml::from_str(source).unwrap();
+    assert_eq!(config.solana.network, "mainnet");
+    let config = validate_document(source).unwrap();
+    assert_eq!(config.solana.program_id, ClobConfig::default().solana.program_id);
+}
+
+#[test]
+fn allocation_is_one_of_the_modes() {
+    for mode in ["price_time", "pro_rata", "size_time"] {
+        assert!(validate_document(&with_key("orderbook", "allocation", mode.into())).is_ok());
+    }
+    let issues = validate_document(&with_key("orderbook", "allocation", "fifo".into())).unwrap_err();
+    assert_eq!(paths(issues), ["orderbook.allocation"]);
+}
+
+#[test]
+fn resting_lifetime_takes_a_positive_lifetime_and_a_scope() {
+    let lifetime = table("max_resting_secs = 7776000\nscope = \"all_resting\"");
+    let config = validate_document(&with_key("orderbook", "resting_lifetime", lifetime)).unwrap();
+    assert_eq!(config.orderbook.resting_lifetime.unwrap().max_resting_secs, 7_776_000);
+    assert!(validate_document(&with_key("orderbook", "resting_lifetime", table("max_resting_secs = 60"))).is_ok());
+
+    let issues = validate_document(&with_key("orderbook", "resting_lifetime", table("max_resting_secs = 0"))).unwrap_err();
+    assert_eq!(paths(issues), ["orderbook.resting_lifetime.max_resting_secs"]);
+    let lifetime = table("max_resting_secs = 60\nscope = \"forever\"");
+    let issues = validate_document(&with_key("orderbook", "resting_lifetime", lifetime)).unwrap_err();
+    assert_eq!(paths(issues), ["orderbook.resting_lifetime.scope"]);
+}
+
+#[test]
+fn speed_bump_takes_ordered_delays_up_to_a_second() {
+    let config = validate_document(&with_key("orderbook", "speed_bump", table("min_delay_us = 0\nmax_delay_us = 3000"))).unwrap();
+    assert_eq!(config.orderbook.speed_bump.unwrap().max_delay_us, 3000);
+    assert!(validate_document(&with_key("orderbook", "speed_bump", table("max_delay_us = 500"))).is_ok());
+
+    let issues = validate_document(&with_key("orderbook", "speed_bump", table("max_delay_us = 1000001"))).unwrap_err();
+    assert_eq!(paths(issues), ["orderbook.speed_bump.max_delay_us"]);
+    let issues = validate_document(&with_key("orderbook", "speed_bump", table("min_delay_us = 20\nmax_delay_us = 10"))).unwrap_err();
+    assert_eq!(paths(issues), ["orderbook.speed_bump.min_delay_us"]);
+}
+
+#[test]
+fn indexer_takes_vault_transfer_crediting() {
+    let config = validate_document(&with_section("indexer", "ingestion = \"logs\"\ncredit_vault_transfers = true")).unwrap();
+    assert!(config.indexer.unwrap().credit_vault_transfers);
+
+    let issues = validate_document(&with_section("indexer", "ingestion = \"logs\"\ncredit_vault_transfers = 1")).unwrap_err();
+    assert_eq!(paths(issues), ["indexer.credit_vault_transfers"]);
+}
+
+#[test]
+fn fees_take_an_insurance_share_up_to_the_whole_fee() {
+    let fees = |share: u64| format!("insurance_share_bps = {}\n[[tiers]]\nmin_volume = 0\nmaker_fee_bps = 0\ntaker_fee_bps = 10", share);
+    let config = validate_document(&with_section("fees", &fees(2500))).unwrap();
+    assert_eq!(config.fees.unwrap().insurance_share_bps, Some(2500));
+    assert!(validate_document(&with_section("fees", &fees(10_000))).is_ok());
+
+    let issues = validate_document(&with_section("fees", &fees(10_001))).unwrap_err();
+    assert_eq!(paths(issues), ["fees.insurance_share_bps"]);
+}
@@ -0,0 +1,471 @@
+//! Core types shared by the SVM CLOB program and its off-chain infrastructure
+//!
+//! The order enums, the `execute_trade` argument, signed order intents and
+//! compressed fill receipts are defined once here and used by both the
+//! Anchor program (`offchain_api`) and `svm_clob_types`, so a value encoded
+//! on one side always decodes on the other and both sides agree on the bytes
+//! a trader signs and a receipt leaf hashes. The crate is
+//! `no_std` and carries borsh (and optionally serde and JSON Schema) derives.
+//!
+//! With the `anchor` feature, which only the program enables, the types derive
+//! Anchor's serialization traits (so they can appear in instructions and the
+//! IDL) and keys are Anchor's `Pubkey`. Without it keys are plain `[u8; 32]`.
+//! Both encode as the same 32 bytes.
+
+#![cfg_attr(not(feature = "anchor"), no_std)]
+
+// JSON Schema derives name `std` in their expansion
+#[cfg(all(feature = "schema", not(feature = "anchor")))]
+extern crate std;
+
+use core::fmt;
+
+#[cfg(not(feature = "anchor"))]
+use borsh::{BorshDeserialize, BorshSerialize};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(not(feature = "anchor"))]
+pub use borsh;
+// Anchor's derives expand to borsh 0.10 paths (`borsh::maybestd`), so under
+// `anchor` the name must be the borsh Anchor itself re-exports, not 1.x
+#[cfg(feature = "anchor")]
+pub use anchor_lang::prelude::borsh;
+
+/// Account address as carried in on-chain arguments
+#[cfg(feature = "anchor")]
+pub type Key = anchor_lang::prelude::Pubkey;
+
+/// Account address as carried in on-chain arguments
+#[cfg(not(feature = "anchor"))]
+pub type Key = [u8; 32];
+
+/// Order side
+#[cfg_attr(feature = "anchor", derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize))]
+#[cfg_attr(not(feature = "anchor"), derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(not(feature = "anchor"), borsh(use_discriminant = true))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum OrderSide {
+    Bid = 0, // Buy order
+    Ask = 1, // Sell order
+}
+
+/// Order type
+#[cfg_attr(feature = "anchor", derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize))]
+#[cfg_attr(not(feature = "anchor"), derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(not(feature = "anchor"), borsh(use_discriminant = true))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum OrderType {
+    Limit = 0,    // Limit order
+    Market = 1,   // Market order
+    PostOnly = 2, // Post-only limit order
+}
+
+/// Order status
+#[cfg_attr(feature = "anchor", derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize))]
+#[cfg_attr(not(feature = "anchor"), derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(not(feature = "anchor"), borsh(use_discriminant = true))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum OrderStatus {
+    Open = 0,            // Order is active
+    PartiallyFilled = 1, // Order is partially executed
+    Filled = 2,          // Order is completely executed
+    Cancelled = 3,       // Order is cancelled
+    Expired = 4,         // Order has expired
+}
+
+/// Self-trade prevention behavior
+#[cfg_attr(feature = "anchor", derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize))]
+#[cfg_attr(not(feature = "anchor"), derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(not(feature = "anchor"), borsh(use_discriminant = true))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum SelfTradeBehavior {
+    DecrementAndCancel = 0, // Cancel the smaller order
+    CancelProvide = 1,      // Cancel the resting order
+    CancelTake = 2,         // Cancel the incoming order
+    CancelBoth = 3,         // Cancel both orders
+}
+
+/// Time in force
+#[cfg_attr(feature = "anchor", derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize))]
+#[cfg_attr(not(feature = "anchor"), derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(not(feature = "anchor"), borsh(use_discriminant = true))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum TimeInForce {
+    GoodTillCancelled = 0, // GTC - remains until cancelled
+    ImmediateOrCancel = 1, // IOC - execute immediately or cancel
+    FillOrKill = 2,        // FOK - execute completely or cancel
+    #[cfg_attr(feature = "serde", serde(alias = "GoodTillDate"))]
+    GoodTillTime = 3,      // GTT/GTD - remains until expiry time
+}
+
+/// Trading state of a market, stored in the orderbook account
+///
+/// `Paused` stops everything. `CancelOnly` takes no new orders and, on
+/// chain, settles only trades matched before the change. `ReduceOnly` takes
+/// only orders that shrink their owner's position, which the engine checks.
+#[cfg_attr(feature = "anchor", derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize))]
+#[cfg_attr(not(feature = "anchor"), derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(not(feature = "anchor"), borsh(use_discriminant = true))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[repr(u8)]
+pub enum MarketStatus {
+    #[default]
+    Active = 0,     // Orders and settlement as normal
+    Paused = 1,     // Nothing is accepted or settled
+    CancelOnly = 2, // Only cancellations; earlier matches still settle
+    ReduceOnly = 3, // Only orders that shrink the owner's position
+}
+
+/// Order types and times in force a market accepts, stored in the
+/// orderbook account
+///
+/// Each field is a bit set indexed by discriminant, `1 << OrderType::Market
+/// as u8` standing for market orders. An empty set accepts every value, so
+/// an orderbook created before markets could restrict their orders, whose
+/// space for the sets is zeroed, accepts everything.
+#[cfg_attr(feature = "anchor", derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize))]
+#[cfg_attr(not(feature = "anchor"), derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(all(feature = "serde", not(feature = "anchor")), derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct OrderTypeWhitelist {
+    pub order_types: u8,
+    pub time_in_force: u8,
+}
+
+/// Order as handed between the program and the matching engine
+#[cfg_attr(feature = "anchor", derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize))]
+#[cfg_attr(not(feature = "anchor"), derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(all(feature = "serde", not(feature = "anchor")), derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Order {
+    pub order_id: u64,
+    pub owner: Key,
+    pub price: u64,
+    pub quantity: u64,
+    pub side: OrderSide,
+    pub order_type: OrderType,
+    pub timestamp: i64,
+}
+
+/// Match settled on chain by `execute_trade`
+#[cfg_attr(feature = "anchor", derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize))]
+#[cfg_attr(not(feature = "anchor"), derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(all(feature = "serde", not(feature = "anchor")), derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trade {
+    pub taker_order_id: u64,
+    pub maker_order_id: u64,
+    pub taker: Key,
+    pub maker: Key,
+    pub price: u64,
+    pub quantity: u64,
+    pub taker_side: OrderSide,
+    pub timestamp: i64,
+}
+
+/// Order a trader authorizes by signing it
+#[cfg_attr(feature = "anchor", derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize))]
+#[cfg_attr(not(feature = "anchor"), derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(all(feature = "serde", not(feature = "anchor")), derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderIntent {
+    /// Orderbook account of the market
+    pub market: Key,
+    pub owner: Key,
+    pub client_order_id: u64,
+    pub side: OrderSide,
+    pub order_type: OrderType,
+    /// Limit price; the worst price a market order accepts
+    pub price: u64,
+    pub quantity: u64,
+    pub time_in_force: TimeInForce,
+    pub self_trade_behavior: SelfTradeBehavior,
+}
+
+/// Order intent with the owner's ed25519 signature over `signing_message`
+#[cfg_attr(feature = "anchor", derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize))]
+#[cfg_attr(not(feature = "anchor"), derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(all(feature = "serde", not(feature = "anchor")), derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignedOrder {
+    pub order_intent: OrderIntent,
+    /// Distinguishes otherwise identical intents of the same owner
+    pub nonce: u64,
+    /// Unix timestamp after which the signature is no longer honoured
+    pub expiry: i64,
+    #[cfg_attr(all(feature = "serde", not(feature = "anchor")), serde(with = "signature_bytes"))]
+    pub signature: [u8; 64],
+}
+
+/// Prefix of every signed order message, so an order signature cannot be
+/// replayed as a transaction or any other signed payload
+pub const ORDER_SIGNING_DOMAIN: &[u8; 17] = b"svm-clob/order/v1";
+
+/// Encoded length of an `OrderIntent`
+pub const ORDER_INTENT_LEN: usize = 32 + 32 + 8 + 1 + 1 + 8 + 8 + 1 + 1;
+
+/// Length of the message a `SignedOrder` signature covers
+pub const SIGNING_MESSAGE_LEN: usize = ORDER_SIGNING_DOMAIN.len() + ORDER_INTENT_LEN + 8 + 8;
+
+impl SignedOrder {
+    /// Bytes the owner signs: the domain followed by the borsh encoding of
+    /// the intent, the nonce and the expiry
+    pub fn signing_message(order_intent: &OrderIntent, nonce: u64, expiry: i64) -> [u8; SIGNING_MESSAGE_LEN] {
+        let mut message = [0u8; SIGNING_MESSAGE_LEN];
+        let mut at = 0;
+        let mut put = |bytes: &[u8]| {
+            message[at..at + bytes.len()].copy_from_slice(bytes);
+            at += bytes.len();
+        };
+        put(ORDER_SIGNING_DOMAIN);
+        put(order_intent.market.as_ref());
+        put(order_intent.owner.as_ref());
+        put(&order_intent.client_order_id.to_le_bytes());
+        put(&[order_intent.side as u8, order_intent.order_type as u8]);
+        put(&order_intent.price.to_le_bytes());
+        put(&order_intent.quantity.to_le_bytes());
+        put(&[order_intent.time_in_force as u8, order_intent.self_trade_behavior as u8]);
+        put(&nonce.to_le_bytes());
+        put(&expiry.to_le_bytes());
+        message
+    }
+
+    /// Message this order's signature covers
+    pub fn message(&self) -> [u8; SIGNING_MESSAGE_LEN] {
+        Self::signing_message(&self.order_intent, self.nonce, self.expiry)
+    }
+
+    /// Whether the signature has lapsed at `now`
+    pub fn is_expired(&self, now: i64) -> bool {
+        now > self.expiry
+    }
+}
+
+/// One side of a settled fill, as appended to a market's compressed receipt
+/// tree; the tree's leaf is the keccak-256 hash of `leaf_data`
+#[cfg_attr(feature = "anchor", derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize))]
+#[cfg_attr(not(feature = "anchor"), derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(all(feature = "serde", not(feature = "anchor")), derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FillReceiptLeaf {
+    /// Orderbook account of the market
+    pub orderbook: Key,
+    pub owner: Key,
+    /// The owner's order
+    pub order_id: u64,
+    pub counterparty_order_id: u64,
+    pub price: u64,
+    pub quantity: u64,
+    /// When the fill settled, unix seconds
+    pub timestamp: i64,
+    /// Side of the owner's order
+    pub side: OrderSide,
+    /// Whether the owner's order was resting
+    pub is_maker: bool,
+}
+
+/// Prefix of every receipt leaf, so a leaf cannot pass for another tree's data
+pub const RECEIPT_LEAF_DOMAIN: &[u8; 19] = b"svm-clob/receipt/v1";
+
+/// Length of the bytes a receipt leaf hashes
+pub const RECEIPT_LEAF_DATA_LEN: usize = RECEIPT_LEAF_DOMAIN.len() + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 1 + 1;
+
+impl FillReceiptLeaf {
+    /// Receipts of a trade settled on `orderbook` at `timestamp`: the
+    /// taker's, then the maker's, appended to the tree in that order
+    pub fn from_trade(orderbook: Key, trade: &Trade, timestamp: i64) -> [Self; 2] {
+        let receipt = |owner, order_id, counterparty_order_id, side, is_maker| FillReceiptLeaf {
+            orderbook,
+            owner,
+            order_id,
+            counterparty_order_id,
+            price: trade.price,
+            quantity: trade.quantity,
+            timestamp,
+            side,
+            is_maker,
+        };
+        [
+            receipt(trade.taker, trade.taker_order_id, trade.maker_order_id, trade.taker_side, false),
+            receipt(trade.maker, trade.maker_order_id, trade.taker_order_id, trade.taker_side.opposite(), true),
+        ]
+    }
+
+    /// Bytes the leaf hashes: the domain followed by the borsh encoding of
+    /// the receipt
+    pub fn leaf_data(&self) -> [u8; RECEIPT_LEAF_DATA_LEN] {
+        let mut data = [0u8; RECEIPT_LEAF_DATA_LEN];
+        let mut at = 0;
+        let mut put = |bytes: &[u8]| {
+            data[at..at + bytes.len()].copy_from_slice(bytes);
+            at += bytes.len();
+        };
+        put(RECEIPT_LEAF_DOMAIN);
+        put(self.orderbook.as_ref());
+        put(self.owner.as_ref());
+        put(&self.order_id.to_le_bytes());
+        put(&self.counterparty_order_id.to_le_bytes());
+        put(&self.price.to_le_bytes());
+        put(&self.quantity.to_le_bytes());
+        put(&self.timestamp.to_le_bytes());
+        put(&[self.side as u8, self.is_maker as u8]);
+        data
+    }
+}
+
+/// Signatures are 64 bytes, more than serde derives arrays for
+#[cfg(all(feature = "serde", not(feature = "anchor")))]
+mod signature_bytes {
+    use core::fmt;
+    use serde::de::{Error, SeqAccess, Visitor};
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(signature: &[u8; 64], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(signature)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 64], D::Error> {
+        struct SignatureVisitor;
+
+        impl<'de> Visitor<'de> for SignatureVisitor {
+            type Value = [u8; 64];
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("64 signature bytes")
+            }
+
+            fn visit_bytes<E: Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+                bytes.try_into().map_err(|_| E::invalid_length(bytes.len(), &self))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut signature = [0u8; 64];
+                for (i, byte) in signature.iter_mut().enumerate() {
+                    *byte = seq.next_element()?.ok_or_else(|| A::Error::invalid_length(i, &self))?;
+                }
+                if seq.next_element::<u8>()?.is_some() {
+                    return Err(A::Error::invalid_length(65, &self));
+                }
+                Ok(signature)
+            }
+        }
+
+        deserializer.deserialize_bytes(SignatureVisitor)
+    }
+}
+
+impl OrderType {
+    /// Every order type, in discriminant order
+    pub const ALL: [OrderType; 3] = [OrderType::Limit, OrderType::Market, OrderType::PostOnly];
+}
+
+impl TimeInForce {
+    /// Every time in force, in discriminant order
+    pub const ALL: [TimeInForce; 4] = [
+        TimeInForce::GoodTillCancelled,
+        TimeInForce::ImmediateOrCancel,
+        TimeInForce::FillOrKill,
+        TimeInForce::GoodTillTime,
+    ];
+}
+
+impl OrderTypeWhitelist {
+    /// Accepts every order type and time in force
+    pub const ALL: Self = Self { order_types: 0, time_in_force: 0 };
+
+    /// Accept only `order_types` and `time_in_force`; an empty list accepts
+    /// every value
+    pub fn new(order_types: &[OrderType], time_in_force: &[TimeInForce]) -> Self {
+        Self {
+            order_types: order_types.iter().fold(0, |bits, &order_type| bits | (1 << order_type as u8)),
+            time_in_force: time_in_force.iter().fold(0, |bits, &time_in_force| bits | (1 << time_in_force as u8)),
+        }
+    }
+
+    pub fn accepts_order_type(&self, order_type: OrderType) -> bool {
+        self.order_types == 0 || self.order_types & (1 << order_type as u8) != 0
+    }
+
+    pub fn accepts_time_in_force(&self, time_in_force: TimeInForce) -> bool {
+        self.time_in_force == 0 || self.time_in_force & (1 << time_in_force as u8) != 0
+    }
+
+    /// Whether an order of `order_type` and `time_in_force` may be placed
+    pub fn accepts(&self, order_type: OrderType, time_in_force: TimeInForce) -> bool {
+        self.accepts_order_type(order_type) && self.accepts_time_in_force(time_in_force)
+    }
+}
+
+impl OrderSide {
+    /// Side of the counterparty
+    pub fn opposite(self) -> Self {
+        match self {
+            OrderSide::Bid => OrderSide::Ask,
+            OrderSide::Ask => OrderSide::Bid,
+        }
+    }
+}
+
+/// Byte that is not the discriminant of any variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidDiscriminant(pub u8);
+
+impl fmt::Display for InvalidDiscriminant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid discriminant {}", self.0)
+    }
+}
+
+macro_rules! impl_discriminant {
+    ($enum:ident { $($variant:ident = $value:literal),+ $(,)? }) => {
+        impl TryFrom<u8> for $enum {
+            type Error = InvalidDiscriminant;
+
+            fn try_from(value: u8) -> Result<Self, Self::Error> {
+                match value {
+                    $($value => Ok($enum::$variant),)+
+                    _ => Err(InvalidDiscriminant(value)),
+                }
+            }
+        }
+
+        impl From<$enum> for u8 {
+            fn from(value: $enum) -> u8 {
+                value as u8
+            }
+        }
+
+        impl fmt::Display for $enum {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self {
+                    $($enum::$variant => f.write_str(stringify!($variant)),)+
+                }
+            }
+        }
+    };
+}
+
+impl_discriminant!(OrderSide { Bid = 0, Ask = 1 });
+impl_discriminant!(OrderType { Limit = 0, Market = 1, PostOnly = 2 });
+impl_discriminant!(OrderStatus { Open = 0, PartiallyFilled = 1, Filled = 2, Cancelled = 3, Expired = 4 });
+impl_discriminant!(SelfTradeBehavior { DecrementAndCancel = 0, CancelProvide = 1, CancelTake = 2, CancelBoth = 3 });
+impl_discriminant!(TimeInForce { GoodTillCancelled = 0, ImmediateOrCancel = 1, FillOrKill = 2, GoodTillTime = 3 });
+impl_discriminant!(MarketStatus { Active = 0, Paused = 1, CancelOnly = 2, ReduceOnly = 3 });
@@ -0,0 +1,233 @@
+//! Layout and round-trip tests for the shared core types
+//!
+//! The program decodes `execute_trade` arguments with Anchor's borsh derive
+//! and the infra encodes them with this crate's, so the byte layout is pinned
+//! here field by field; enum discriminants must equal their `repr(u8)` values
+//! and the serde names must match the variant names the REST API has always
+//! used.
+
+use proptest::prelude::*;
+use svm_clob_core::borsh;
+use svm_clob_core::*;
+
+fn side() -> impl Strategy<Value = OrderSide> {
+    prop_oneof![Just(OrderSide::Bid), Just(OrderSide::Ask)]
+}
+
+fn order_type() -> impl Strategy<Value = OrderType> {
+    prop_oneof![Just(OrderType::Limit), Just(OrderType::Market), Just(OrderType::PostOnly)]
+}
+
+fn trade() -> impl Strategy<Value = Trade> {
+    (any::<u64>(), any::<u64>(), any::<[u8; 32]>(), any::<[u8; 32]>(), any::<u64>(), any::<u64>(), side(), any::<i64>())
+        .prop_map(|(taker_order_id, maker_order_id, taker, maker, price, quantity, taker_side, timestamp)| Trade {
+            taker_order_id,
+            maker_order_id,
+            taker,
+            maker,
+            price,
+            quantity,
+            taker_side,
+            timestamp,
+        })
+}
+
+fn order() -> impl Strategy<Value = Order> {
+    (any::<u64>(), any::<[u8; 32]>(), any::<u64>(), any::<u64>(), side(), order_type(), any::<i64>())
+        .prop_map(|(order_id, owner, price, quantity, side, order_type, timestamp)| Order {
+            order_id,
+            owner,
+            price,
+            quantity,
+            side,
+            order_type,
+            timestamp,
+        })
+}
+
+fn time_in_force() -> impl Strategy<Value = TimeInForce> {
+    (0u8..4).prop_map(|value| TimeInForce::try_from(value).unwrap())
+}
+
+fn self_trade_behavior() -> impl Strategy<Value = SelfTradeBehavior> {
+    (0u8..4).prop_map(|value| SelfTradeBehavior::try_from(value).unwrap())
+}
+
+fn signed_order() -> impl Strategy<Value = SignedOrder> {
+    (
+        (any::<[u8; 32]>(), any::<[u8; 32]>(), any::<u64>(), side(), order_type()),
+        (any::<u64>(), any::<u64>(), time_in_force(), self_trade_behavior()),
+        any::<u64>(),
+        any::<i64>(),
+        prop::collection::vec(any::<u8>(), 64),
+    )
+        .prop_map(
+            |((market, owner, client_order_id, side, order_type), (price, quantity, time_in_force, self_trade_behavior), nonce, expiry, signature)| {
+                SignedOrder {
+                    order_intent: OrderIntent {
+                        market,
+                        owner,
+                        client_order_id,
+                        side,
+                        order_type,
+                        price,
+                        quantity,
+                        time_in_force,
+                        self_trade_behavior,
+                    },
+                    nonce,
+                    expiry,
+                    signature: signature.try_into().unwrap(),
+                }
+            },
+        )
+}
+
+/// `execute_trade` argument as the program reads it: little-endian integers,
+/// raw 32-byte keys and a one-byte side
+fn expected_trade_bytes(trade: &Trade) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&trade.taker_order_id.to_le_bytes());
+    bytes.extend_from_slice(&trade.maker_order_id.to_le_bytes());
+    bytes.extend_from_slice(&trade.taker);
+    bytes.extend_from_slice(&trade.maker);
+    bytes.extend_from_slice(&trade.price.to_le_bytes());
+    bytes.extend_from_slice(&trade.quantity.to_le_bytes());
+    bytes.push(trade.taker_side as u8);
+    bytes.extend_from_slice(&trade.timestamp.to_le_bytes());
+    bytes
+}
+
+proptest! {
+    #[test]
+    fn trade_layout_matches_program(trade in trade()) {
+        let bytes = borsh::to_vec(&trade).unwrap();
+        prop_assert_eq!(bytes.len(), 105);
+        prop_assert_eq!(&bytes, &expected_trade_bytes(&trade));
+        prop_assert_eq!(borsh::from_slice::<Trade>(&bytes).unwrap(), trade);
+    }
+
+    #[test]
+    fn order_borsh_round_trip(order in order()) {
+        let bytes = borsh::to_vec(&order).unwrap();
+        prop_assert_eq!(bytes.len(), 8 + 32 + 8 + 8 + 1 + 1 + 8);
+        prop_assert_eq!(borsh::from_slice::<Order>(&bytes).unwrap(), order);
+    }
+
+    #[test]
+    fn order_serde_round_trip(order in order()) {
+        let json = serde_json::to_string(&order).unwrap();
+        prop_assert_eq!(serde_json::from_str::<Order>(&json).unwrap(), order);
+    }
+
+    #[test]
+    fn signing_message_is_domain_and_borsh(signed in signed_order()) {
+        let mut expected = ORDER_SIGNING_DOMAIN.to_vec();
+        expected.extend(borsh::to_vec(&(signed.order_intent, signed.nonce, signed.expiry)).unwrap());
+        prop_assert_eq!(borsh::to_vec(&signed.order_intent).unwrap().len(), ORDER_INTENT_LEN);
+        prop_assert_eq!(signed.message().to_vec(), expected);
+    }
+
+    #[test]
+    fn signed_order_round_trips(signed in signed_order()) {
+        let bytes = borsh::to_vec(&signed).unwrap();
+        prop_assert_eq!(bytes.len(), ORDER_INTENT_LEN + 8 + 8 + 64);
+        prop_assert_eq!(borsh::from_slice::<SignedOrder>(&bytes).unwrap(), signed);
+        let json = serde_json::to_string(&signed).unwrap();
+        prop_assert_eq!(serde_json::from_str::<SignedOrder>(&json).unwrap(), signed);
+    }
+
+    #[test]
+    fn receipt_leaf_data_is_domain_and_borsh(trade in trade(), orderbook in any::<[u8; 32]>(), timestamp in any::<i64>()) {
+        for receipt in FillReceiptLeaf::from_trade(orderbook, &trade, timestamp) {
+            let mut expected = RECEIPT_LEAF_DOMAIN.to_vec();
+            expected.extend(borsh::to_vec(&receipt).unwrap());
+            prop_assert_eq!(receipt.leaf_data().to_vec(), expected);
+        }
+    }
+
+    #[test]
+    fn receipts_of_a_trade_mirror_each_other(trade in trade(), orderbook in any::<[u8; 32]>(), timestamp in any::<i64>()) {
+        let [taker, maker] = FillReceiptLeaf::from_trade(orderbook, &trade, timestamp);
+        prop_assert_eq!((taker.owner, taker.order_id, taker.side, taker.is_maker), (trade.taker, trade.taker_order_id, trade.taker_side, false));
+        prop_assert_eq!((maker.owner, maker.order_id, maker.side, maker.is_maker), (trade.maker, trade.maker_order_id, trade.taker_side.opposite(), true));
+        prop_assert_eq!(taker.counterparty_order_id, maker.order_id);
+        prop_assert_eq!(maker.counterparty_order_id, taker.order_id);
+        prop_assert_eq!((taker.price, taker.quantity, taker.timestamp), (maker.price, maker.quantity, timestamp));
+    }
+
+    #[test]
+    fn unknown_discriminants_are_rejected(value in 5u8..) {
+        prop_assert_eq!(OrderSide::try_from(value), Err(InvalidDiscriminant(value)));
+        prop_assert_eq!(OrderType::try_from(value), Err(InvalidDiscriminant(value)));
+        prop_assert_eq!(OrderStatus::try_from(value), Err(InvalidDiscriminant(value)));
+        prop_assert_eq!(SelfTradeBehavior::try_from(value), Err(InvalidDiscriminant(value)));
+        prop_assert_eq!(TimeInForce::try_from(value), Err(InvalidDiscriminant(value)));
+        prop_assert_eq!(MarketStatus::try_from(value), Err(InvalidDiscriminant(value)));
+    }
+}
+
+/// Every variant encodes as its `repr(u8)` value in borsh, converts back from
+/// that byte, and keeps its variant name in JSON
+macro_rules! assert_enum_parity {
+    ($enum:ident: $($variant:ident = $value:literal),+) => {
+        $(
+            assert_eq!(borsh::to_vec(&$enum::$variant).unwrap(), vec![$value], stringify!($enum::$variant));
+            assert_eq!(borsh::from_slice::<$enum>(&[$value]).unwrap(), $enum::$variant);
+            assert_eq!($enum::try_from($value), Ok($enum::$variant));
+            assert_eq!(u8::from($enum::$variant), $value);
+            assert_eq!(serde_json::to_string(&$enum::$variant).unwrap(), concat!("\"", stringify!($variant), "\""));
+            assert_eq!($enum::$variant.to_string(), stringify!($variant));
+        )+
+    };
+}
+
+#[test]
+fn enum_discriminants_match_program() {
+    assert_enum_parity!(OrderSide: Bid = 0, Ask = 1);
+    assert_enum_parity!(OrderType: Limit = 0, Market = 1, PostOnly = 2);
+    assert_enum_parity!(OrderStatus: Open = 0, PartiallyFilled = 1, Filled = 2, Cancelled = 3, Expired = 4);
+    assert_enum_parity!(SelfTradeBehavior: DecrementAndCancel = 0, CancelProvide = 1, CancelTake = 2, CancelBoth = 3);
+    assert_enum_parity!(TimeInForce: GoodTillCancelled = 0, ImmediateOrCancel = 1, FillOrKill = 2, GoodTillTime = 3);
+    assert_enum_parity!(MarketStatus: Active = 0, Paused = 1, CancelOnly = 2, ReduceOnly = 3);
+}
+
+proptest! {
+    #[test]
+    fn whitelist_accepts_exactly_its_lists(
+        order_types in proptest::sample::subsequence(OrderType::ALL.to_vec(), 0..=3),
+        time_in_force in proptest::sample::subsequence(TimeInForce::ALL.to_vec(), 0..=4),
+    ) {
+        let whitelist = OrderTypeWhitelist::new(&order_types, &time_in_force);
+        for order_type in OrderType::ALL {
+            prop_assert_eq!(
+                whitelist.accepts_order_type(order_type),
+                order_types.is_empty() || order_types.contains(&order_type)
+            );
+        }
+        for value in TimeInForce::ALL {
+            prop_assert_eq!(
+                whitelist.accepts_time_in_force(value),
+                time_in_force.is_empty() || time_in_force.contains(&value)
+            );
+        }
+        prop_assert_eq!(borsh::to_vec(&whitelist).unwrap(), vec![whitelist.order_types, whitelist.time_in_force]);
+    }
+}
+
+#[test]
+fn zeroed_whitelist_accepts_everything() {
+    let whitelist = borsh::from_slice::<OrderTypeWhitelist>(&[0, 0]).unwrap();
+    assert_eq!(whitelist, OrderTypeWhitelist::ALL);
+    for order_type in OrderType::ALL {
+        for time_in_force in TimeInForce::ALL {
+            assert!(whitelist.accepts(order_type, time_in_force));
+        }
+    }
+}
+
+#[test]
+fn opposite_side_flips() {
+    assert_eq!(OrderSide::Bid.opposite(), OrderSide::Ask);
+    assert_eq!(OrderSide::Ask.opposite(), OrderSide::Bid);
+}
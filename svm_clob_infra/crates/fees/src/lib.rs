@@ -0,0 +1,374 @@
+/// Fee Accrual for SVM CLOB Infrastructure
+///
+/// This module charges maker and taker fees on every journaled fill according to
+/// a tiered fee schedule, accrues them next to the balance ledger, and builds the
+/// monthly per-user billing reports served by the RPC server. A configured share
+/// of every fee is earmarked for the market's insurance fund. Each user's tier is
+/// assigned nightly from their trailing 30-day volume by `FeeTierService`. A fill
+/// an operator busts or reprices is compensated by accruals of the difference.
+
+use svm_clob_types::*;
+use svm_clob_storage::Storage;
+use chrono::{Datelike, NaiveDate, TimeZone, Utc};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+/// Checkpoint service name used by fee accrual
+pub const CHECKPOINT_SERVICE: &str = "fees";
+
+/// Trailing window of traded volume a user's tier is assigned from
+pub const TIER_WINDOW_SECS: i64 = 30 * 86_400;
+
+/// How often `FeeTierService` checks whether a new UTC day has begun
+const TIER_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Tiered maker/taker fee rates
+///
+/// A user's tier is picked by their traded volume in the trailing 30 days,
+/// reassigned once a day, so rates stay fixed between recomputations.
+#[derive(Debug, Clone)]
+pub struct FeeSchedule {
+    /// Sorted by `min_volume`
+    tiers: Vec<FeeTier>,
+}
+
+impl FeeSchedule {
+    /// Create a schedule from tiers in any order
+    pub fn new(mut tiers: Vec<FeeTier>) -> Self {
+        tiers.sort_by_key(|tier| tier.min_volume);
+        Self { tiers }
+    }
+
+    /// Tiers sorted by volume threshold
+    pub fn tiers(&self) -> &[FeeTier] {
+        &self.tiers
+    }
+
+    /// Rate in basis points for a role at a given 30-day volume; volume below
+    /// every tier pays nothing
+    pub fn rate_bps(&self, role: LiquidityRole, volume: u64) -> i64 {
+        let Some(tier) = self.tier_index(volume).map(|index| &self.tiers[index]) else {
+            return 0;
+        };
+        match role {
+            LiquidityRole::Maker => tier.maker_fee_bps,
+            LiquidityRole::Taker => tier.taker_fee_bps,
+        }
+    }
+
+    /// Tier of `user`, who traded `volume` in the 30 days before `now`
+    pub fn assign(&self, user: Pubkey, volume: u64, now: i64) -> UserFeeTier {
+        UserFeeTier {
+            user,
+            volume_30d: volume,
+            tier: self.tier_index(volume).map(|index| index as u32),
+            maker_fee_bps: self.rate_bps(LiquidityRole::Maker, volume),
+            taker_fee_bps: self.rate_bps(LiquidityRole::Taker, volume),
+            computed_at: now,
+        }
+    }
+
+    /// Position of the highest tier `volume` reaches
+    fn tier_index(&self, volume: u64) -> Option<usize> {
+        self.tiers.iter().rposition(|tier| volume >= tier.min_volume)
+    }
+}
+
+/// Fee on `price * quantity` at `fee_bps`, in quote units
+///
+/// Fees round up and rebates round toward zero, so rounding never favours the
+/// user.
+pub fn compute_fee(price: Price, quantity: Quantity, fee_bps: i64) -> i64 {
+    price.notional(quantity).fee(fee_bps).clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
+
+/// Share of `fee` earmarked for the insurance fund at `share_bps`
+///
+/// Rounds down, and a rebate earmarks nothing: the fund is only fed from
+/// fees actually collected.
+pub fn insurance_contribution(fee: i64, share_bps: u64) -> i64 {
+    (i128::from(fee.max(0)) * i128::from(share_bps.min(10_000)) / 10_000) as i64
+}
+
+/// Bounds of the calendar month (UTC) containing `timestamp`, start inclusive
+pub fn month_bounds(timestamp: i64) -> (i64, i64) {
+    let date = Utc
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map(|time| time.date_naive())
+        .unwrap_or_default();
+    month_of(date.year(), date.month())
+}
+
+/// Bounds of a month written as `YYYY-MM`
+pub fn parse_month(month: &str) -> Option<(i64, i64)> {
+    let (year, month) = month.split_once('-')?;
+    let year: i32 = year.parse().ok()?;
+    let month: u32 = month.parse().ok()?;
+    NaiveDate::from_ymd_opt(year, month, 1)?;
+    Some(month_of(year, month))
+}
+
+fn month_of(year: i32, month: u32) -> (i64, i64) {
+    let start = NaiveDate::from_ymd_opt(year, month, 1).expect("valid month");
+    let end = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)
+    }
+    .expect("valid month");
+    let timestamp = |date: NaiveDate| date.and_hms_opt(0, 0, 0).expect("midnight").and_utc().timestamp();
+    (timestamp(start), timestamp(end))
+}
+
+/// Render reports as CSV with a header row
+pub fn reports_to_csv(reports: &[FeeReport]) -> String {
+    let mut csv = String::from(
+        "user,period_start,period_end,fills,maker_volume,taker_volume,maker_fees,taker_fees,total_fees\n",
+    );
+    for report in reports {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{}\n",
+            report.user,
+            rfc3339(report.period_start),
+            rfc3339(report.period_end),
+            report.fills,
+            report.maker_volume,
+            report.taker_volume,
+            report.maker_fees,
+            report.taker_fees,
+            report.total_fees,
+        ));
+    }
+    csv
+}
+
+fn rfc3339(timestamp: i64) -> String {
+    Utc.timestamp_opt(timestamp, 0)
+        .single()
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// Fee accrual configuration
+#[derive(Debug, Clone)]
+pub struct FeeConfig {
+    pub schedule: FeeSchedule,
+    /// Share of every fee earmarked for the insurance fund, in basis points
+    pub insurance_share_bps: u64,
+    /// Journal entries read per poll
+    pub batch_size: u32,
+    /// Delay between polls when the journal is drained
+    pub poll_interval: Duration,
+}
+
+/// Journal consumer that accrues fees for every fill
+///
+/// A fill is charged at the rates of the tier its maker and taker hold when it
+/// is accrued; users not yet assigned a tier pay the rates of no volume.
+pub struct FeeAccrualService<S: Storage> {
+    config: FeeConfig,
+    storage: Arc<S>,
+}
+
+impl<S: Storage> FeeAccrualService<S> {
+    /// Create a new fee accrual service
+    pub fn new(storage: Arc<S>, config: FeeConfig) -> Self {
+        Self { config, storage }
+    }
+
+    /// Run until storage fails
+    pub async fn run(&self) -> ClobResult<()> {
+        info!(
+            "Fee accrual using {} tiers, earmarking {} bps of fees for the insurance fund",
+            self.config.schedule.tiers().len(),
+            self.config.insurance_share_bps
+        );
+        loop {
+            if self.accrue_pending().await? == 0 {
+                tokio::time::sleep(self.config.poll_interval).await;
+            }
+        }
+    }
+
+    /// Accrue fees for the next page of the journal, returning the number of entries consumed
+    async fn accrue_pending(&self) -> ClobResult<usize> {
+        let position = self
+            .storage
+            .get_checkpoint(CHECKPOINT_SERVICE)
+            .await?
+            .map(|checkpoint| checkpoint.position)
+            .unwrap_or(0);
+
+        let entries = self.storage.get_journal_entries(position, self.config.batch_size).await?;
+        let Some(last_sequence) = entries.last().map(|entry| entry.sequence) else {
+            return Ok(0);
+        };
+
+        let mut rates = HashMap::new();
+        let mut accruals = Vec::new();
+        for entry in &entries {
+            if let JournalEvent::TradeAdjusted { trade, trade_sequence, previous_price, .. } = &entry.event {
+                let compensations = self.compensate(entry.sequence, trade, *trade_sequence, *previous_price, &accruals).await?;
+                accruals.extend(compensations);
+                continue;
+            }
+            let JournalEvent::TradeExecuted { trade, maker, taker } = &entry.event else {
+                continue;
+            };
+            for (user, role) in [(*maker, LiquidityRole::Maker), (*taker, LiquidityRole::Taker)] {
+                let fee_bps = match role {
+                    LiquidityRole::Maker => self.rates(&mut rates, &user).await?.0,
+                    LiquidityRole::Taker => self.rates(&mut rates, &user).await?.1,
+                };
+                let fee = compute_fee(Price(trade.price), Quantity(trade.quantity), fee_bps);
+                accruals.push(FeeAccrual {
+                    journal_sequence: entry.sequence,
+                    user,
+                    role,
+                    price: trade.price,
+                    quantity: trade.quantity,
+                    fee_bps,
+                    fee,
+                    insurance_contribution: insurance_contribution(fee, self.config.insurance_share_bps),
+                    timestamp: trade.timestamp,
+                    adjusted_sequence: None,
+                });
+            }
+        }
+
+        self.storage.store_fee_accruals(&accruals).await?;
+        self.storage.save_checkpoint(&Checkpoint {
+            service: CHECKPOINT_SERVICE.to_string(),
+            position: last_sequence,
+            cursor: None,
+        }).await?;
+        Ok(entries.len())
+    }
+
+    /// Accruals bringing the fees of the fill journaled at `trade_sequence`
+    /// from the version priced `previous_price` to `trade`, at the rates it
+    /// was charged; `pending` holds the accruals of this page not yet stored
+    ///
+    /// They are dated with the fill, so a bust takes the fill out of the
+    /// period it was billed in. A fill accrued before fees were charged has
+    /// nothing to compensate.
+    async fn compensate(
+        &self,
+        sequence: u64,
+        trade: &TradeExecution,
+        trade_sequence: u64,
+        previous_price: u64,
+        pending: &[FeeAccrual],
+    ) -> ClobResult<Vec<FeeAccrual>> {
+        let mut charged: Vec<FeeAccrual> = pending
+            .iter()
+            .filter(|accrual| accrual.journal_sequence == trade_sequence)
+            .cloned()
+            .collect();
+        if charged.is_empty() {
+            charged = self.storage.get_fee_accruals(trade_sequence).await?;
+        }
+
+        let share_bps = self.config.insurance_share_bps;
+        Ok(charged
+            .into_iter()
+            .map(|accrual| {
+                let previous = compute_fee(Price(previous_price), Quantity(trade.quantity), accrual.fee_bps);
+                let current = compute_fee(Price(trade.price), Quantity(trade.effective_quantity()), accrual.fee_bps);
+                FeeAccrual {
+                    journal_sequence: sequence,
+                    price: trade.price,
+                    quantity: trade.quantity - trade.effective_quantity(),
+                    fee: current - previous,
+                    insurance_contribution: insurance_contribution(current, share_bps)
+                        - insurance_contribution(previous, share_bps),
+                    timestamp: trade.timestamp,
+                    adjusted_sequence: Some(trade_sequence),
+                    ..accrual
+                }
+            })
+            .collect())
+    }
+
+    /// Maker and taker rates of `user`'s assigned tier, looked up once per page
+    async fn rates(&self, rates: &mut HashMap<Pubkey, (i64, i64)>, user: &Pubkey) -> ClobResult<(i64, i64)> {
+        if let Some(user_rates) = rates.get(user) {
+            return Ok(*user_rates);
+        }
+        let user_rates = match self.storage.get_fee_tier(user).await? {
+            Some(tier) => (tier.maker_fee_bps, tier.taker_fee_bps),
+            None => (
+                self.config.schedule.rate_bps(LiquidityRole::Maker, 0),
+                self.config.schedule.rate_bps(LiquidityRole::Taker, 0),
+            ),
+        };
+        rates.insert(*user, user_rates);
+        Ok(user_rates)
+    }
+}
+
+/// Nightly assignment of fee tiers
+///
+/// Recomputes every user's volume over the trailing 30 days from the stored
+/// fills when it starts and again at each UTC midnight, and replaces the
+/// stored tiers with the ones the volumes reach. The fee accrual service
+/// charges fills at the stored tiers' rates.
+pub struct FeeTierService<S: Storage> {
+    schedule: FeeSchedule,
+    storage: Arc<S>,
+    clock: Arc<dyn Clock>,
+}
+
+impl<S: Storage> FeeTierService<S> {
+    /// Create a new fee tier service
+    pub fn new(storage: Arc<S>, schedule: FeeSchedule) -> Self {
+        Self {
+            schedule,
+            storage,
+            clock: system_clock(),
+        }
+    }
+
+    /// Read the time days turn over at from `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Run until storage fails
+    pub async fn run(&self) -> ClobResult<()> {
+        let mut day = None;
+        loop {
+            let now = self.clock.now();
+            let today = now.div_euclid(86_400);
+            if day != Some(today) {
+                let tiers = self.recompute(now).await?;
+                info!(
+                    "Assigned fee tiers to {} users from their volume in the 30 days to {}",
+                    tiers.len(),
+                    rfc3339(now)
+                );
+                day = Some(today);
+            }
+            tokio::time::sleep(TIER_CHECK_INTERVAL).await;
+        }
+    }
+
+    /// Assign every user who traded in the 30 days before `now` a tier,
+    /// replacing the stored tiers
+    pub async fn recompute(&self, now: i64) -> ClobResult<Vec<UserFeeTier>> {
+        let tiers: Vec<_> = self
+            .storage
+            .get_traded_volumes(now - TIER_WINDOW_SECS, now)
+            .await?
+            .into_iter()
+            .map(|(user, volume)| self.schedule.assign(user, volume, now))
+            .collect();
+        self.storage.replace_fee_tiers(&tiers).await?;
+        Ok(tiers)
+    }
+}
@@ -0,0 +1,193 @@
+/// High Availability for SVM CLOB Infrastructure
+///
+/// This module lets several matching engine instances share one journal. A
+/// lease row in storage elects the active matcher; every other instance is a
+/// hot standby that replays the journal as it grows. A standby replays up to
+/// the journal head before it starts accepting orders, and the active matcher
+/// stops accepting orders before its lease can lapse, so a failover loses no
+/// acknowledged order.
+
+use svm_clob_types::*;
+use svm_clob_matching_engine::MatchingEngine;
+use svm_clob_storage::Storage;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{watch, RwLock};
+use tokio::time::{Instant, MissedTickBehavior};
+use tracing::{debug, info, warn};
+
+/// Lease name shared by the matching engine instances of one market
+pub const MATCHER_LEASE: &str = "matching-engine";
+
+/// High-availability configuration
+#[derive(Debug, Clone)]
+pub struct HaConfig {
+    /// Unique name of this instance, recorded as the lease holder
+    pub instance_id: String,
+    /// Lease contended for
+    pub lease_name: String,
+    /// How long a lease stays valid without renewal
+    pub lease_ttl: Duration,
+    /// Interval between renewals, and between journal polls on a standby;
+    /// must be well below `lease_ttl`
+    pub renew_interval: Duration,
+    /// Journal entries fetched per replay query
+    pub replay_batch_size: u32,
+}
+
+/// Role of this instance
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// Replaying the journal, rejecting order commands
+    Standby,
+    /// Active matcher holding the lease at `epoch`
+    Leader { epoch: u64 },
+}
+
+/// Elects the active matcher and keeps standbys in sync with the journal
+pub struct HaNode<S: Storage> {
+    storage: Arc<S>,
+    engine: Arc<RwLock<MatchingEngine<S>>>,
+    config: HaConfig,
+    role: watch::Sender<Role>,
+}
+
+impl<S: Storage> HaNode<S> {
+    /// Create a node for an engine; it starts as a standby once `run` is called
+    pub fn new(storage: Arc<S>, engine: Arc<RwLock<MatchingEngine<S>>>, config: HaConfig) -> Self {
+        let (role, _) = watch::channel(Role::Standby);
+        Self { storage, engine, config, role }
+    }
+
+    /// Current role
+    pub fn role(&self) -> Role {
+        *self.role.borrow()
+    }
+
+    /// Watch role changes, e.g. to run leader-only services
+    pub fn subscribe(&self) -> watch::Receiver<Role> {
+        self.role.subscribe()
+    }
+
+    /// Contend for the lease and replay the journal until storage fails for good
+    ///
+    /// Storage errors are retried on the next tick; a replay error means this
+    /// standby's book diverged from the journal and stops the node.
+    pub async fn run(&self) -> ClobResult<()> {
+        self.engine.read().await.set_active(false).await;
+        info!("Instance {} joining election for {}", self.config.instance_id, self.config.lease_name);
+
+        let mut ticker = tokio::time::interval(self.config.renew_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // Local instant by which the lease must have been renewed
+        let mut deadline = Instant::now();
+
+        loop {
+            ticker.tick().await;
+
+            let result = match self.role() {
+                Role::Standby => self.contend(&mut deadline).await,
+                Role::Leader { epoch } => self.renew(epoch, &mut deadline).await,
+            };
+            match result {
+                Ok(()) => {}
+                Err(ClobError::StorageError(e)) | Err(ClobError::NetworkError(e)) => {
+                    warn!("Election round failed: {}", e);
+                }
+                Err(e) => return Err(e),
+            }
+
+            if matches!(self.role(), Role::Leader { .. }) && Instant::now() >= deadline {
+                warn!("Lease renewal overdue, stepping down");
+                self.demote().await;
+            }
+        }
+    }
+
+    /// Give up leadership so a standby can take over without waiting for expiry
+    pub async fn resign(&self) -> ClobResult<()> {
+        if matches!(self.role(), Role::Leader { .. }) {
+            self.demote().await;
+        }
+        self.storage
+            .release_lease(&self.config.lease_name, &self.config.instance_id)
+            .await
+    }
+
+    /// Standby round: catch up, then try to take the lease
+    async fn contend(&self, deadline: &mut Instant) -> ClobResult<()> {
+        self.catch_up().await?;
+
+        let requested = Instant::now();
+        let Some(epoch) = self
+            .storage
+            .try_acquire_lease(&self.config.lease_name, &self.config.instance_id, self.config.lease_ttl)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        // The previous leader stopped writing before its lease lapsed, so this
+        // reaches the final head
+        let position = self.catch_up().await?;
+        *deadline = self.renewal_deadline(requested);
+        // The previous leader may have changed the market's status
+        self.engine.read().await.restore_status().await?;
+        self.engine.read().await.set_active(true).await;
+        self.role.send_replace(Role::Leader { epoch });
+        info!("Became active matcher at epoch {} from journal sequence {}", epoch, position);
+        Ok(())
+    }
+
+    /// Leader round: extend the lease, stepping down if it was lost
+    async fn renew(&self, epoch: u64, deadline: &mut Instant) -> ClobResult<()> {
+        let requested = Instant::now();
+        let renewed = self
+            .storage
+            .try_acquire_lease(&self.config.lease_name, &self.config.instance_id, self.config.lease_ttl)
+            .await?;
+
+        match renewed {
+            Some(current) if current == epoch => {
+                *deadline = self.renewal_deadline(requested);
+            }
+            _ => {
+                warn!("Lease at epoch {} lost, stepping down", epoch);
+                self.demote().await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Stop accepting orders; the engine keeps its book and resumes replaying
+    async fn demote(&self) {
+        self.engine.read().await.set_active(false).await;
+        self.role.send_replace(Role::Standby);
+    }
+
+    /// Replay journal entries past the engine's position, returning the new position
+    async fn catch_up(&self) -> ClobResult<u64> {
+        let engine = self.engine.read().await;
+        loop {
+            let position = engine.journal_position();
+            let entries = self
+                .storage
+                .get_journal_entries(position, self.config.replay_batch_size)
+                .await?;
+            if entries.is_empty() {
+                return Ok(position);
+            }
+
+            debug!("Replaying {} journal entries after {}", entries.len(), position);
+            for entry in &entries {
+                engine.replay_journal_entry(entry).await?;
+            }
+        }
+    }
+
+    /// Stop accepting orders one renewal interval before the lease requested
+    /// at `requested` can expire, leaving in-flight commands time to finish
+    fn renewal_deadline(&self, requested: Instant) -> Instant {
+        requested + self.config.lease_ttl.saturating_sub(self.config.renew_interval)
+    }
+}
@@ -0,0 +1,309 @@
+/// Liquidity Incentives for SVM CLOB Infrastructure
+///
+/// This module credits makers for the quantity they quote near mid. It replays
+/// the engine journal through an offline engine to follow the book order by
+/// order, integrates each maker's resting quantity within `band_bps` of mid
+/// over time, and at the end of every epoch shares the epoch's reward pool in
+/// proportion to that credit. Closed epochs are stored with one report per
+/// maker, served by the RPC server and exportable as CSV for distribution.
+/// The `obligations` module follows the book the same way to score designated
+/// market makers against their quoting obligations.
+
+use svm_clob_types::*;
+use svm_clob_matching_engine::MatchingEngine;
+use svm_clob_storage::{InMemoryStorage, Storage};
+use chrono::{TimeZone, Utc};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+pub mod obligations;
+
+pub use obligations::{ObligationConfig, ObligationService, ObligationTracker};
+
+/// Incentive configuration
+#[derive(Debug, Clone)]
+pub struct IncentiveConfig {
+    /// Largest distance from mid of an order that earns credit
+    pub band_bps: u64,
+    /// Length of an epoch in seconds; epochs are aligned to the unix epoch
+    pub epoch_secs: i64,
+    /// Reward shared between the makers of each epoch
+    pub reward_per_epoch: u64,
+    /// Journal entries read per poll
+    pub batch_size: u32,
+    /// Delay between polls when the journal is drained
+    pub poll_interval: Duration,
+    /// Allocation mode of the market, needed to replay its journal
+    pub allocation: AllocationMode,
+}
+
+impl Default for IncentiveConfig {
+    fn default() -> Self {
+        Self {
+            band_bps: 50,
+            epoch_secs: 86_400,
+            reward_per_epoch: 0,
+            batch_size: 1000,
+            poll_interval: Duration::from_secs(1),
+            allocation: AllocationMode::default(),
+        }
+    }
+}
+
+/// Bounds of the epoch containing `timestamp`, start inclusive
+pub fn epoch_bounds(timestamp: i64, epoch_secs: i64) -> (i64, i64) {
+    let start = timestamp.div_euclid(epoch_secs) * epoch_secs;
+    (start, start + epoch_secs)
+}
+
+/// Render reports as CSV with a header row
+pub fn reports_to_csv(reports: &[IncentiveReport]) -> String {
+    let mut csv = String::from(
+        "maker,epoch_start,epoch_end,bid_depth_seconds,ask_depth_seconds,average_depth,share,reward\n",
+    );
+    for report in reports {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{}\n",
+            report.maker,
+            rfc3339(report.epoch_start),
+            rfc3339(report.epoch_end),
+            report.bid_depth_seconds,
+            report.ask_depth_seconds,
+            report.average_depth,
+            report.share,
+            report.reward,
+        ));
+    }
+    csv
+}
+
+fn rfc3339(timestamp: i64) -> String {
+    Utc.timestamp_opt(timestamp, 0)
+        .single()
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// Depth-seconds of one maker
+#[derive(Debug, Clone, Copy, Default)]
+struct Credit {
+    bid: u64,
+    ask: u64,
+}
+
+/// Credit of the makers over one epoch
+///
+/// The book only changes at journal entries, so crediting it up to each
+/// entry's time before applying the entry integrates it exactly, to the
+/// second resolution of journal timestamps.
+#[derive(Debug, Clone)]
+pub struct DepthTracker {
+    band_bps: u64,
+    epoch_start: i64,
+    epoch_end: i64,
+    /// Time up to which the book has been credited
+    credited_through: i64,
+    credits: HashMap<Pubkey, Credit>,
+}
+
+impl DepthTracker {
+    /// Tracker of the epoch `[epoch_start, epoch_end)`, credited from its start
+    pub fn new(band_bps: u64, epoch_start: i64, epoch_end: i64) -> Self {
+        Self {
+            band_bps,
+            epoch_start,
+            epoch_end,
+            credited_through: epoch_start,
+            credits: HashMap::new(),
+        }
+    }
+
+    pub fn epoch_end(&self) -> i64 {
+        self.epoch_end
+    }
+
+    /// Time up to which the book has been credited
+    pub fn credited_through(&self) -> i64 {
+        self.credited_through
+    }
+
+    /// Credit `orders`, the book since the last call, up to `until` (capped at
+    /// the epoch end)
+    pub fn credit(&mut self, orders: &[Order], until: i64) {
+        let until = until.min(self.epoch_end);
+        let seconds = until - self.credited_through;
+        if seconds <= 0 {
+            return;
+        }
+        self.credited_through = until;
+
+        let best_bid = orders.iter().filter(|order| order.side == OrderSide::Bid).map(|order| order.price).max();
+        let best_ask = orders.iter().filter(|order| order.side == OrderSide::Ask).map(|order| order.price).min();
+        // A one-sided book has no mid, and no order is near it
+        let (Some(bid), Some(ask)) = (best_bid, best_ask) else {
+            return;
+        };
+        let mid = (bid as u128 + ask as u128) / 2;
+
+        for order in orders {
+            if order.price.abs_diff(mid as u64) as u128 * 10_000 > self.band_bps as u128 * mid {
+                continue;
+            }
+            let depth_seconds = order.remaining_quantity.saturating_mul(seconds as u64);
+            let credit = self.credits.entry(order.owner).or_default();
+            match order.side {
+                OrderSide::Bid => credit.bid = credit.bid.saturating_add(depth_seconds),
+                OrderSide::Ask => credit.ask = credit.ask.saturating_add(depth_seconds),
+            }
+        }
+    }
+
+    /// Share `reward_pool` between the makers in proportion to their
+    /// depth-seconds; rewards round down, so a remainder of the pool is not paid
+    pub fn close(self, reward_pool: u64, last_sequence: u64) -> (IncentiveEpoch, Vec<IncentiveReport>) {
+        let total: u128 = self.credits.values().map(|credit| credit.bid as u128 + credit.ask as u128).sum();
+        let epoch_secs = (self.epoch_end - self.epoch_start).max(1) as u128;
+
+        let mut reports: Vec<IncentiveReport> = self
+            .credits
+            .iter()
+            .filter(|(_, credit)| credit.bid > 0 || credit.ask > 0)
+            .map(|(maker, credit)| {
+                let score = credit.bid as u128 + credit.ask as u128;
+                IncentiveReport {
+                    epoch_start: self.epoch_start,
+                    epoch_end: self.epoch_end,
+                    maker: *maker,
+                    bid_depth_seconds: credit.bid,
+                    ask_depth_seconds: credit.ask,
+                    average_depth: (score / epoch_secs) as u64,
+                    share: score as f64 / total as f64,
+                    reward: (reward_pool as u128 * score / total) as u64,
+                }
+            })
+            .collect();
+        reports.sort_by(|a, b| b.reward.cmp(&a.reward).then_with(|| a.maker.to_string().cmp(&b.maker.to_string())));
+
+        let epoch = IncentiveEpoch {
+            epoch_start: self.epoch_start,
+            epoch_end: self.epoch_end,
+            band_bps: self.band_bps,
+            reward_pool,
+            makers: reports.len() as u32,
+            total_depth_seconds: total.min(u64::MAX as u128) as u64,
+            last_sequence,
+        };
+        (epoch, reports)
+    }
+}
+
+/// Journal consumer that closes incentive epochs
+///
+/// The book is rebuilt by replaying the journal from its start, so a restart
+/// reads the whole journal again; credit only starts at the end of the newest
+/// stored epoch, so no epoch is reported twice.
+pub struct IncentiveService<S: Storage> {
+    config: IncentiveConfig,
+    storage: Arc<S>,
+    orderbook: OrderBook,
+    market_id: MarketId,
+    clock: Arc<dyn Clock>,
+}
+
+impl<S: Storage> IncentiveService<S> {
+    /// Create a new incentive service for the market of `orderbook`
+    pub fn new(storage: Arc<S>, orderbook: OrderBook, market_id: MarketId, config: IncentiveConfig) -> Self {
+        Self {
+            config,
+            storage,
+            orderbook,
+            market_id,
+            clock: system_clock(),
+        }
+    }
+
+    /// Read the time epochs close at from `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Run until storage fails
+    pub async fn run(&self) -> ClobResult<()> {
+        let mut engine = MatchingEngine::new(Arc::new(InMemoryStorage::new()), self.orderbook.clone());
+        engine.set_market_id(self.market_id);
+        engine.set_allocation(self.config.allocation);
+
+        let resume_at = self.storage.get_last_incentive_epoch().await?.map(|epoch| epoch.epoch_end);
+        info!(
+            "Incentives crediting quotes within {} bps of mid from {}",
+            self.config.band_bps,
+            resume_at.map_or_else(|| "the start of the journal".to_string(), rfc3339)
+        );
+
+        let mut tracker = None;
+        let mut position = 0;
+        loop {
+            let entries = self.storage.get_journal_entries(position, self.config.batch_size).await?;
+            for entry in &entries {
+                self.advance(&engine, &mut tracker, resume_at, entry.timestamp, position).await?;
+                engine.replay_journal_entry(entry).await?;
+                position = entry.sequence;
+            }
+
+            if entries.is_empty() {
+                // No activity: close the epochs that have ended since
+                self.advance(&engine, &mut tracker, resume_at, self.clock.now(), position).await?;
+                tokio::time::sleep(self.config.poll_interval).await;
+            }
+        }
+    }
+
+    /// Credit the engine's book up to `now`, closing every epoch ending by then
+    async fn advance(
+        &self,
+        engine: &MatchingEngine<InMemoryStorage>,
+        tracker: &mut Option<DepthTracker>,
+        resume_at: Option<i64>,
+        now: i64,
+        position: u64,
+    ) -> ClobResult<()> {
+        let epoch_secs = self.config.epoch_secs.max(1);
+        let tracker = match tracker {
+            Some(tracker) => tracker,
+            // Epochs before `resume_at` are already stored
+            None if resume_at.is_some_and(|resume_at| now < resume_at) => return Ok(()),
+            empty => {
+                let (start, end) = match resume_at {
+                    Some(resume_at) => (resume_at, resume_at + epoch_secs),
+                    None => epoch_bounds(now, epoch_secs),
+                };
+                empty.insert(DepthTracker::new(self.config.band_bps, start, end))
+            }
+        };
+        // Entries within the same second leave nothing to credit
+        if now <= tracker.credited_through() {
+            return Ok(());
+        }
+
+        let orders = engine.get_open_orders().await;
+        while now >= tracker.epoch_end() {
+            let end = tracker.epoch_end();
+            tracker.credit(&orders, end);
+            let closed = std::mem::replace(tracker, DepthTracker::new(self.config.band_bps, end, end + epoch_secs));
+            let (epoch, reports) = closed.close(self.config.reward_per_epoch, position);
+            self.storage.store_incentive_epoch(&epoch, &reports).await?;
+            info!(
+                "Closed incentive epoch {}: {} makers, {} depth-seconds",
+                rfc3339(epoch.epoch_start),
+                epoch.makers,
+                epoch.total_depth_seconds
+            );
+        }
+        tracker.credit(&orders, now);
+        Ok(())
+    }
+}
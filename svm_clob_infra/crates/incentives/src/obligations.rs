@@ -0,0 +1,305 @@
+/// Market Maker Obligations
+///
+/// Designated market makers are registered through the admin API with a
+/// maximum spread, a minimum depth and a minimum uptime. `ObligationService`
+/// follows the book through the journal as incentives do, samples it every
+/// `sample_secs`, at multiples of it, and scores each maker registered by the
+/// time of a sample against its obligations (see `MarketMakerObligation`).
+/// At the end of every period it stores one scorecard per maker, which the
+/// admin API serves to decide who keeps its designation.
+
+use crate::{epoch_bounds, rfc3339};
+use svm_clob_types::*;
+use svm_clob_matching_engine::MatchingEngine;
+use svm_clob_storage::{InMemoryStorage, Storage};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+/// Obligation monitoring configuration
+#[derive(Debug, Clone)]
+pub struct ObligationConfig {
+    /// Seconds between two samples of the book
+    pub sample_secs: i64,
+    /// Length of a scoring period in seconds; periods are aligned to the unix epoch
+    pub period_secs: i64,
+    /// Journal entries read per poll
+    pub batch_size: u32,
+    /// Delay between polls when the journal is drained
+    pub poll_interval: Duration,
+    /// Allocation mode of the market, needed to replay its journal
+    pub allocation: AllocationMode,
+}
+
+impl Default for ObligationConfig {
+    fn default() -> Self {
+        Self {
+            sample_secs: 10,
+            period_secs: 86_400,
+            batch_size: 1000,
+            poll_interval: Duration::from_secs(1),
+            allocation: AllocationMode::default(),
+        }
+    }
+}
+
+/// Samples of one maker
+#[derive(Debug, Clone, Copy, Default)]
+struct Tally {
+    samples: u32,
+    two_sided: u32,
+    compliant: u32,
+    spread_bps: u128,
+    bid_depth: u128,
+    ask_depth: u128,
+}
+
+impl Tally {
+    /// Count `sample` `times` over
+    fn add(&mut self, sample: QuotingSample, times: u32) {
+        self.samples += times;
+        if let Some(spread_bps) = sample.spread_bps {
+            self.two_sided += times;
+            self.spread_bps += spread_bps as u128 * times as u128;
+        }
+        if sample.compliant {
+            self.compliant += times;
+        }
+        self.bid_depth += sample.bid_depth as u128 * times as u128;
+        self.ask_depth += sample.ask_depth as u128 * times as u128;
+    }
+
+    fn scorecard(&self, obligation: &MarketMakerObligation, period_start: i64, period_end: i64) -> MarketMakerScorecard {
+        let samples = self.samples.max(1) as u128;
+        let uptime_bps = (self.compliant as u128 * 10_000 / samples) as u64;
+        MarketMakerScorecard {
+            period_start,
+            period_end,
+            maker: obligation.maker,
+            max_spread_bps: obligation.max_spread_bps,
+            min_depth: obligation.min_depth,
+            min_uptime_bps: obligation.min_uptime_bps,
+            samples: self.samples,
+            two_sided_samples: self.two_sided,
+            compliant_samples: self.compliant,
+            uptime_bps,
+            average_spread_bps: (self.two_sided > 0).then(|| (self.spread_bps / self.two_sided as u128) as u64),
+            average_bid_depth: (self.bid_depth / samples) as u64,
+            average_ask_depth: (self.ask_depth / samples) as u64,
+            meets_obligations: uptime_bps >= obligation.min_uptime_bps,
+        }
+    }
+}
+
+/// Samples of the makers over one period
+///
+/// The book only changes at journal entries, so sampling it at every sample
+/// time before an entry's time, then applying the entry, samples each time
+/// on the book as of that second.
+#[derive(Debug, Clone)]
+pub struct ObligationTracker {
+    sample_secs: i64,
+    period_start: i64,
+    period_end: i64,
+    /// Time of the next sample
+    next_sample: i64,
+    tallies: HashMap<Pubkey, Tally>,
+}
+
+impl ObligationTracker {
+    /// Tracker of the period `[period_start, period_end)`, sampled from its start
+    pub fn new(sample_secs: i64, period_start: i64, period_end: i64) -> Self {
+        let sample_secs = sample_secs.max(1);
+        Self {
+            sample_secs,
+            period_start,
+            period_end,
+            next_sample: align_up(period_start, sample_secs),
+            tallies: HashMap::new(),
+        }
+    }
+
+    pub fn period_end(&self) -> i64 {
+        self.period_end
+    }
+
+    /// Time of the next sample
+    pub fn next_sample(&self) -> i64 {
+        self.next_sample
+    }
+
+    /// Sample `orders`, the book since the last call, at every sample time
+    /// before `until` (capped at the period end), for each of `makers`
+    /// registered by then
+    pub fn sample(&mut self, orders: &[Order], makers: &[MarketMakerObligation], until: i64) {
+        let until = until.min(self.period_end);
+        if self.next_sample >= until {
+            return;
+        }
+        let times = (until - self.next_sample + self.sample_secs - 1) / self.sample_secs;
+        for maker in makers {
+            // Sample times before its registration are not held against it
+            let skipped = (align_up(maker.registered_at, self.sample_secs) - self.next_sample).max(0) / self.sample_secs;
+            let counted = times - skipped;
+            if counted <= 0 {
+                continue;
+            }
+            let sample = maker.sample(orders);
+            self.tallies.entry(maker.maker).or_default().add(sample, counted.min(u32::MAX as i64) as u32);
+        }
+        self.next_sample += times * self.sample_secs;
+    }
+
+    /// Score the makers still registered in `makers` against their current
+    /// obligations; makers deregistered during the period are not scored
+    pub fn close(self, makers: &[MarketMakerObligation], last_sequence: u64) -> (ObligationPeriod, Vec<MarketMakerScorecard>) {
+        let mut scorecards: Vec<MarketMakerScorecard> = makers
+            .iter()
+            .filter_map(|obligation| {
+                let tally = self.tallies.get(&obligation.maker)?;
+                Some(tally.scorecard(obligation, self.period_start, self.period_end))
+            })
+            .collect();
+        scorecards.sort_by(|a, b| a.maker.to_string().cmp(&b.maker.to_string()));
+
+        let period = ObligationPeriod {
+            period_start: self.period_start,
+            period_end: self.period_end,
+            sample_secs: self.sample_secs,
+            makers: scorecards.len() as u32,
+            last_sequence,
+        };
+        (period, scorecards)
+    }
+}
+
+/// First multiple of `step` at or after `time`
+fn align_up(time: i64, step: i64) -> i64 {
+    time.div_euclid(step) * step + if time.rem_euclid(step) == 0 { 0 } else { step }
+}
+
+/// Journal consumer that scores designated market makers
+///
+/// Like incentives, the book is rebuilt by replaying the journal from its
+/// start, and sampling starts at the end of the newest stored period, so no
+/// period is scored twice. The registry is read again before every batch of
+/// journal entries, so a replay scores past periods against the makers
+/// registered now.
+pub struct ObligationService<S: Storage> {
+    config: ObligationConfig,
+    storage: Arc<S>,
+    orderbook: OrderBook,
+    market_id: MarketId,
+    clock: Arc<dyn Clock>,
+}
+
+impl<S: Storage> ObligationService<S> {
+    /// Create a new obligation service for the market of `orderbook`
+    pub fn new(storage: Arc<S>, orderbook: OrderBook, market_id: MarketId, config: ObligationConfig) -> Self {
+        Self {
+            config,
+            storage,
+            orderbook,
+            market_id,
+            clock: system_clock(),
+        }
+    }
+
+    /// Read the time periods close at from `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Run until storage fails
+    pub async fn run(&self) -> ClobResult<()> {
+        let mut engine = MatchingEngine::new(Arc::new(InMemoryStorage::new()), self.orderbook.clone());
+        engine.set_market_id(self.market_id);
+        engine.set_allocation(self.config.allocation);
+
+        let resume_at = self.storage.get_last_obligation_period().await?.map(|period| period.period_end);
+        info!(
+            "Market maker obligations sampled every {}s from {}",
+            self.config.sample_secs,
+            resume_at.map_or_else(|| "the start of the journal".to_string(), rfc3339)
+        );
+
+        let mut tracker = None;
+        let mut position = 0;
+        loop {
+            let makers = self.market_makers().await?;
+            let entries = self.storage.get_journal_entries(position, self.config.batch_size).await?;
+            for entry in &entries {
+                self.advance(&engine, &makers, &mut tracker, resume_at, entry.timestamp, position).await?;
+                engine.replay_journal_entry(entry).await?;
+                position = entry.sequence;
+            }
+
+            if entries.is_empty() {
+                // No activity: sample the quiet book and close the periods that have ended since
+                self.advance(&engine, &makers, &mut tracker, resume_at, self.clock.now(), position).await?;
+                tokio::time::sleep(self.config.poll_interval).await;
+            }
+        }
+    }
+
+    /// Every registered maker
+    async fn market_makers(&self) -> ClobResult<Vec<MarketMakerObligation>> {
+        let mut makers = Vec::new();
+        let mut request = Some(Paginated::first(MAX_PAGE_LIMIT));
+        while let Some(page) = request {
+            let fetched = self.storage.get_market_makers(&page).await?;
+            request = page.next(&fetched);
+            makers.extend(fetched.items);
+        }
+        Ok(makers)
+    }
+
+    /// Sample the engine's book up to `now`, closing every period ending by then
+    async fn advance(
+        &self,
+        engine: &MatchingEngine<InMemoryStorage>,
+        makers: &[MarketMakerObligation],
+        tracker: &mut Option<ObligationTracker>,
+        resume_at: Option<i64>,
+        now: i64,
+        position: u64,
+    ) -> ClobResult<()> {
+        let period_secs = self.config.period_secs.max(1);
+        let tracker = match tracker {
+            Some(tracker) => tracker,
+            // Periods before `resume_at` are already stored
+            None if resume_at.is_some_and(|resume_at| now < resume_at) => return Ok(()),
+            empty => {
+                let (start, end) = match resume_at {
+                    Some(resume_at) => (resume_at, resume_at + period_secs),
+                    None => epoch_bounds(now, period_secs),
+                };
+                empty.insert(ObligationTracker::new(self.config.sample_secs, start, end))
+            }
+        };
+        // No sample is due and no period ends
+        if now <= tracker.next_sample() && now < tracker.period_end() {
+            return Ok(());
+        }
+
+        let orders = engine.get_open_orders().await;
+        while now >= tracker.period_end() {
+            let end = tracker.period_end();
+            tracker.sample(&orders, makers, end);
+            let closed = std::mem::replace(tracker, ObligationTracker::new(self.config.sample_secs, end, end + period_secs));
+            let (period, scorecards) = closed.close(makers, position);
+            self.storage.store_obligation_period(&period, &scorecards).await?;
+            info!(
+                "Closed obligation period {}: {} makers, {} meeting their obligations",
+                rfc3339(period.period_start),
+                period.makers,
+                scorecards.iter().filter(|scorecard| scorecard.meets_obligations).count()
+            );
+        }
+        tracker.sample(&orders, makers, now);
+        Ok(())
+    }
+}
@@ -0,0 +1,841 @@
+/// Chain Indexer for SVM CLOB Infrastructure
+///
+/// This module follows the SVM CLOB program's transaction logs, decodes the Anchor
+/// events it emits, and persists deposits, withdrawals, trade settlements,
+/// sub-accounts and transfers between them, insurance fund payments, and
+/// reversals of adjusted trades into
+/// the storage layer with at-least-once delivery
+/// and checkpointing. Transactions are discovered either from a log
+/// subscription or from program account updates.
+///
+/// Below `finalized` commitment, records are stored optimistically: every
+/// transaction is tracked as pending or confirmed and polled until it is
+/// finalized. One that turns out to have failed, or whose slot was finalized
+/// without it, has its records rolled back, and ingestion restarts from the
+/// newest finalized transaction so a fork's replacement history is read again.
+///
+/// Markets moved to another status on chain are announced on
+/// `subscribe_market_status`, so the node can pause matching along with them.
+///
+/// With `vault_mints` set, the indexer also follows the vaults of those mints
+/// for plain SPL transfers made outside `deposit`, which the settler then
+/// credits (see `vault_transfers`).
+///
+/// `survey_market` reads the on-chain state a market already has when the
+/// infrastructure attaches to it: the `OrderBook` account and the fill records
+/// of signed orders it settled, found with `getProgramAccounts`.
+
+use svm_clob_types::*;
+use svm_clob_types::program::{self, parse_log_events, ProgramEvent};
+use svm_clob_storage::Storage;
+use futures_util::{FutureExt, StreamExt};
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_config::{
+    RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcTransactionConfig, RpcTransactionLogsConfig,
+    RpcTransactionLogsFilter,
+};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_account_decoder::UiAccountEncoding;
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, TransactionConfirmationStatus, UiTransactionEncoding,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{info, warn, error, debug};
+
+mod vault_transfers;
+
+pub use vault_transfers::vault_checkpoint_service;
+
+/// Checkpoint service name used by the indexer
+pub const CHECKPOINT_SERVICE: &str = "indexer";
+
+/// Checkpoint holding the newest finalized transaction, where ingestion
+/// restarts after a rollback
+pub const FINALIZED_CHECKPOINT_SERVICE: &str = "indexer.finalized";
+
+/// Signatures per `getSignatureStatuses` call, the RPC's limit
+const STATUS_BATCH: u32 = 256;
+
+/// How the indexer learns about new program transactions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IngestionMode {
+    /// `logsSubscribe` on the program; events arrive with their logs
+    #[default]
+    Logs,
+    /// `programSubscribe` on the program's accounts; every account update
+    /// triggers an immediate catch-up from the checkpoint
+    Accounts,
+}
+
+impl FromStr for IngestionMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "logs" => Ok(IngestionMode::Logs),
+            "accounts" => Ok(IngestionMode::Accounts),
+            other => Err(format!("unknown ingestion mode '{}', expected logs or accounts", other)),
+        }
+    }
+}
+
+/// Indexer configuration
+#[derive(Debug, Clone)]
+pub struct IndexerConfig {
+    /// Solana JSON-RPC endpoint used for backfill
+    pub rpc_url: String,
+    /// Solana PubSub endpoint used for live log subscription
+    pub ws_url: String,
+    /// SVM CLOB program to follow
+    pub program_id: Pubkey,
+    /// Commitment level for both subscription and backfill
+    pub commitment: CommitmentConfig,
+    /// Signatures fetched per backfill page
+    pub backfill_page_size: usize,
+    /// Delay before reconnecting after a subscription failure
+    pub reconnect_delay: Duration,
+    /// Subscription used to discover new transactions
+    pub ingestion: IngestionMode,
+    /// Delay between polls of the status of unfinalized transactions
+    pub finality_poll_interval: Duration,
+    /// Mints whose vaults are followed for plain transfers to credit; none
+    /// when empty
+    pub vault_mints: Vec<Pubkey>,
+}
+
+/// On-chain state of a market, as found when attaching to it
+#[derive(Debug, Clone)]
+pub struct MarketSurvey {
+    /// Address of the `OrderBook` account
+    pub address: Pubkey,
+    pub orderbook: program::OrderBookAccount,
+    /// Signed orders the market's authority has settled fills of
+    pub signed_order_fills: Vec<SignedOrderFillRecord>,
+}
+
+/// Quantity settled on chain against one signed order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignedOrderFillRecord {
+    pub owner: Pubkey,
+    pub nonce: u64,
+    pub filled: u64,
+    /// Expiry of the order's signature, unix seconds
+    pub expiry: i64,
+}
+
+/// Status a market was moved to on chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarketStatusChange {
+    /// Address of the `OrderBook` account
+    pub orderbook: Pubkey,
+    pub status: MarketStatus,
+    pub slot: u64,
+}
+
+/// Indexer that mirrors on-chain program events into storage
+pub struct Indexer<S: Storage> {
+    config: IndexerConfig,
+    storage: Arc<S>,
+    rpc: RpcClient,
+    /// Latest market status change read from the chain
+    market_status: watch::Sender<Option<MarketStatusChange>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl<S: Storage> Indexer<S> {
+    /// Create a new indexer instance
+    pub fn new(storage: Arc<S>, config: IndexerConfig) -> Self {
+        let rpc = RpcClient::new_with_commitment(config.rpc_url.clone(), config.commitment);
+        Self {
+            config,
+            storage,
+            rpc,
+            market_status: watch::channel(None).0,
+            clock: system_clock(),
+        }
+    }
+
+    /// Timestamp vault transfers from `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Follow the market status changes the indexer reads, at its commitment;
+    /// a change read again after a rollback or a restart is announced again
+    pub fn subscribe_market_status(&self) -> watch::Receiver<Option<MarketStatusChange>> {
+        self.market_status.subscribe()
+    }
+
+    /// Run the indexer, reconnecting on network failures
+    ///
+    /// Returns only when storage fails, since continuing would break the
+    /// at-least-once guarantee.
+    pub async fn run(&self) -> ClobResult<()> {
+        info!(
+            "Indexer following program {} ({:?} ingestion)",
+            self.config.program_id, self.config.ingestion
+        );
+
+        loop {
+            let ingest = async {
+                match self.config.ingestion {
+                    IngestionMode::Logs => self.follow().await,
+                    IngestionMode::Accounts => self.follow_accounts().await,
+                }
+            };
+            // A rollback rewinds the checkpoint under the subscription, which
+            // is then dropped and re-read from there
+            let result = tokio::select! {
+                result = ingest => result.map(|()| warn!("Subscription closed, reconnecting")),
+                result = self.track_finality() => result,
+                result = self.watch_vaults() => result,
+            };
+            match result {
+                Ok(()) => {}
+                Err(ClobError::NetworkError(e)) => error!("Indexer network error: {}, reconnecting", e),
+                Err(e) => return Err(e),
+            }
+            tokio::time::sleep(self.config.reconnect_delay).await;
+        }
+    }
+
+    /// Poll unfinalized transactions until one has to be rolled back, then
+    /// rewind the checkpoint to the newest finalized transaction
+    async fn track_finality(&self) -> ClobResult<()> {
+        // Nothing is stored before it is final
+        if self.config.commitment.is_finalized() {
+            return std::future::pending().await;
+        }
+
+        loop {
+            tokio::time::sleep(self.config.finality_poll_interval).await;
+            match self.update_finality().await {
+                Ok(false) => {}
+                Ok(true) => break,
+                // Ingestion does not depend on the poll, so keep it running
+                Err(ClobError::NetworkError(e)) => warn!("Failed to poll transaction statuses: {}", e),
+                Err(e) => return Err(e),
+            }
+        }
+
+        let finalized = self.storage.get_checkpoint(FINALIZED_CHECKPOINT_SERVICE).await?;
+        let (position, cursor) = finalized.map_or((0, None), |checkpoint| (checkpoint.position, checkpoint.cursor));
+        info!(
+            "Re-reading program transactions after {}",
+            cursor.as_deref().unwrap_or("the start of the program's history")
+        );
+        self.storage.save_checkpoint(&Checkpoint {
+            service: CHECKPOINT_SERVICE.to_string(),
+            position,
+            cursor,
+        }).await
+    }
+
+    /// Move unfinalized transactions forward and roll back those the chain
+    /// settled without; returns whether any was rolled back
+    async fn update_finality(&self) -> ClobResult<bool> {
+        let unfinalized = self.storage.get_unfinalized_chain_transactions(STATUS_BATCH).await?;
+        if unfinalized.is_empty() {
+            return Ok(false);
+        }
+
+        let finalized_slot = self
+            .rpc
+            .get_slot_with_commitment(CommitmentConfig::finalized())
+            .await
+            .map_err(network_error)?;
+        let signatures = unfinalized
+            .iter()
+            .map(|transaction| parse_signature(&transaction.signature))
+            .collect::<ClobResult<Vec<_>>>()?;
+        let statuses = self
+            .rpc
+            .get_signature_statuses_with_history(&signatures)
+            .await
+            .map_err(network_error)?
+            .value;
+
+        // The finalized checkpoint only moves past transactions that can no
+        // longer change, so a rollback never rewinds past a dropped one
+        let mut rolled_back = false;
+        let mut settled_prefix = true;
+        let mut newest_finalized = None;
+        for (transaction, status) in unfinalized.into_iter().zip(statuses) {
+            let Some(status) = status else {
+                settled_prefix = false;
+                // Unknown to the chain, although its slot is already rooted
+                if transaction.slot <= finalized_slot {
+                    self.storage
+                        .roll_back_chain_transaction(&transaction.signature, ChainStatus::Dropped, None)
+                        .await?;
+                    rolled_back = true;
+                }
+                continue;
+            };
+            if let Some(err) = &status.err {
+                settled_prefix = false;
+                self.storage
+                    .roll_back_chain_transaction(&transaction.signature, ChainStatus::Failed, Some(&err.to_string()))
+                    .await?;
+                rolled_back = true;
+                continue;
+            }
+
+            let chain_status = match status.confirmation_status {
+                Some(TransactionConfirmationStatus::Finalized) => ChainStatus::Finalized,
+                Some(TransactionConfirmationStatus::Confirmed) => ChainStatus::Confirmed,
+                _ => ChainStatus::Pending,
+            };
+            if chain_status != ChainStatus::Finalized {
+                settled_prefix = false;
+            } else if settled_prefix {
+                newest_finalized = Some((transaction.signature.clone(), status.slot));
+            }
+            if chain_status == transaction.status && status.slot == transaction.slot {
+                continue;
+            }
+            debug!("Transaction {} is {:?} in slot {}", transaction.signature, chain_status, status.slot);
+            self.storage.store_chain_transaction(&ChainTransaction {
+                status: chain_status,
+                slot: status.slot,
+                ..transaction
+            }).await?;
+        }
+
+        if let Some((signature, slot)) = newest_finalized {
+            let previous = self.storage.get_checkpoint(FINALIZED_CHECKPOINT_SERVICE).await?;
+            if previous.map_or(true, |checkpoint| checkpoint.position <= slot) {
+                self.storage.save_checkpoint(&Checkpoint {
+                    service: FINALIZED_CHECKPOINT_SERVICE.to_string(),
+                    position: slot,
+                    cursor: Some(signature),
+                }).await?;
+            }
+        }
+        Ok(rolled_back)
+    }
+
+    /// Subscribe to logs, backfill the gap since the checkpoint, then follow live
+    async fn follow(&self) -> ClobResult<()> {
+        let pubsub = PubsubClient::new(&self.config.ws_url)
+            .await
+            .map_err(network_error)?;
+        let (mut notifications, unsubscribe) = pubsub
+            .logs_subscribe(
+                RpcTransactionLogsFilter::Mentions(vec![self.config.program_id.to_string()]),
+                RpcTransactionLogsConfig {
+                    commitment: Some(self.config.commitment),
+                },
+            )
+            .await
+            .map_err(network_error)?;
+
+        // Subscribing before backfilling leaves no gap between the two; anything
+        // seen twice is deduplicated by the storage layer.
+        self.backfill().await?;
+
+        while let Some(notification) = notifications.next().await {
+            let slot = notification.context.slot;
+            let logs = notification.value;
+
+            if let Some(err) = &logs.err {
+                debug!("Skipping failed transaction {}", logs.signature);
+                self.skip_failed_transaction(&logs.signature, slot, &err.to_string()).await?;
+                continue;
+            }
+
+            self.process_transaction(&logs.signature, slot, &logs.logs).await?;
+        }
+
+        unsubscribe().await;
+        Ok(())
+    }
+
+    /// Subscribe to program account updates and catch up on every change
+    ///
+    /// Deposits, withdrawals and settlements all write a user account, so an
+    /// account notification is the earliest sign of a new program transaction.
+    /// The notification itself carries no transaction, so the events are read
+    /// through the same ordered backfill the log mode uses on reconnect, which
+    /// also keeps the checkpoint strictly sequential.
+    async fn follow_accounts(&self) -> ClobResult<()> {
+        let pubsub = PubsubClient::new(&self.config.ws_url)
+            .await
+            .map_err(network_error)?;
+        let (mut notifications, unsubscribe) = pubsub
+            .program_subscribe(
+                &self.config.program_id,
+                Some(RpcProgramAccountsConfig {
+                    account_config: RpcAccountInfoConfig {
+                        encoding: Some(UiAccountEncoding::Base64),
+                        commitment: Some(self.config.commitment),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                }),
+            )
+            .await
+            .map_err(network_error)?;
+
+        self.backfill().await?;
+
+        while let Some(notification) = notifications.next().await {
+            // A settlement batch touches many accounts in one slot; one catch-up
+            // covers every notification already waiting
+            let mut slot = notification.context.slot;
+            let mut updates = 1;
+            while let Some(Some(notification)) = notifications.next().now_or_never() {
+                slot = slot.max(notification.context.slot);
+                updates += 1;
+            }
+
+            debug!("{} program account update(s) up to slot {}", updates, slot);
+            self.backfill().await?;
+        }
+
+        unsubscribe().await;
+        Ok(())
+    }
+
+    /// Replay every program transaction newer than the checkpoint, oldest first
+    pub async fn backfill(&self) -> ClobResult<()> {
+        let checkpoint = self.storage.get_checkpoint(CHECKPOINT_SERVICE).await?;
+        let until = checkpoint
+            .and_then(|checkpoint| checkpoint.cursor)
+            .map(|cursor| parse_signature(&cursor))
+            .transpose()?;
+
+        // Signatures come back newest first, so collect every page before processing
+        let mut pending = Vec::new();
+        let mut before = None;
+        loop {
+            let page = self
+                .rpc
+                .get_signatures_for_address_with_config(
+                    &self.config.program_id,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        until,
+                        limit: Some(self.config.backfill_page_size),
+                        commitment: Some(self.config.commitment),
+                    },
+                )
+                .await
+                .map_err(network_error)?;
+
+            let page_len = page.len();
+            before = page
+                .last()
+                .map(|status| parse_signature(&status.signature))
+                .transpose()?;
+            pending.extend(page);
+
+            if page_len < self.config.backfill_page_size {
+                break;
+            }
+        }
+
+        if !pending.is_empty() {
+            info!("Backfilling {} program transactions", pending.len());
+        }
+
+        for status in pending.into_iter().rev() {
+            if let Some(err) = &status.err {
+                self.skip_failed_transaction(&status.signature, status.slot, &err.to_string()).await?;
+                continue;
+            }
+
+            let transaction = self
+                .rpc
+                .get_transaction_with_config(
+                    &parse_signature(&status.signature)?,
+                    RpcTransactionConfig {
+                        encoding: Some(UiTransactionEncoding::Json),
+                        commitment: Some(self.config.commitment),
+                        max_supported_transaction_version: Some(0),
+                    },
+                )
+                .await
+                .map_err(network_error)?;
+
+            let logs = match transaction.transaction.meta.map(|meta| meta.log_messages) {
+                Some(OptionSerializer::Some(logs)) => logs,
+                _ => {
+                    warn!("Transaction {} has no log messages", status.signature);
+                    Vec::new()
+                }
+            };
+
+            self.process_transaction(&status.signature, transaction.slot, &logs).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Read the on-chain state of the market trading `base_mint` against
+    /// `quote_mint`
+    ///
+    /// The program keeps orders off chain, so the book itself is restored
+    /// from storage; what the chain holds is the market's account, whose
+    /// `total_volume` counts every settled fill, and a fill record per signed
+    /// order settled. Both are found with `getProgramAccounts`, filtered by
+    /// size and discriminator, then by the mints and by the market's
+    /// authority, which pays for every fill record. Fails if the program has
+    /// no such market.
+    pub async fn survey_market(&self, base_mint: &Pubkey, quote_mint: &Pubkey) -> ClobResult<MarketSurvey> {
+        let markets = self
+            .program_accounts(vec![
+                RpcFilterType::DataSize(OrderBookView::ACCOUNT_LEN as u64),
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, &program::account_discriminator("OrderBook"))),
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(OrderBookView::BASE_MINT_OFFSET, base_mint.as_ref())),
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(OrderBookView::QUOTE_MINT_OFFSET, quote_mint.as_ref())),
+            ])
+            .await?;
+        let (expected, _) = program::orderbook_address(&self.config.program_id, base_mint, quote_mint);
+        let (address, account) = markets
+            .into_iter()
+            .find(|(address, _)| *address == expected)
+            .ok_or_else(|| ClobError::UnknownMarket(format!("No on-chain orderbook {} for {}/{}", expected, base_mint, quote_mint)))?;
+        let orderbook = OrderBookView::new(&account.data)?.to_account();
+
+        let records = self
+            .program_accounts(vec![
+                RpcFilterType::DataSize(SignedOrderFillView::ACCOUNT_LEN as u64),
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, &program::account_discriminator("SignedOrderFill"))),
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                    SignedOrderFillView::AUTHORITY_OFFSET,
+                    orderbook.authority.as_ref(),
+                )),
+            ])
+            .await?;
+        let mut signed_order_fills = Vec::with_capacity(records.len());
+        for (record_address, account) in records {
+            match SignedOrderFillView::new(&account.data) {
+                Ok(view) => signed_order_fills.push(SignedOrderFillRecord {
+                    owner: view.owner(),
+                    nonce: view.nonce(),
+                    filled: view.filled(),
+                    expiry: view.expiry(),
+                }),
+                Err(e) => warn!("Skipping signed order fill record {}: {}", record_address, e),
+            }
+        }
+
+        info!(
+            "Market {} has settled {} on chain, with {} signed order fill records",
+            address,
+            orderbook.total_volume,
+            signed_order_fills.len()
+        );
+        Ok(MarketSurvey {
+            address,
+            orderbook,
+            signed_order_fills,
+        })
+    }
+
+    async fn program_accounts(
+        &self,
+        filters: Vec<RpcFilterType>,
+    ) -> ClobResult<Vec<(Pubkey, solana_sdk::account::Account)>> {
+        self.rpc
+            .get_program_accounts_with_config(
+                &self.config.program_id,
+                RpcProgramAccountsConfig {
+                    filters: Some(filters),
+                    account_config: RpcAccountInfoConfig {
+                        encoding: Some(UiAccountEncoding::Base64),
+                        commitment: Some(self.config.commitment),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            )
+            .await
+            .map_err(network_error)
+    }
+
+    /// Decode and persist the events of one transaction, then checkpoint it
+    async fn process_transaction(&self, signature: &str, slot: u64, logs: &[String]) -> ClobResult<()> {
+        let events = match parse_log_events(&self.config.program_id, logs) {
+            Ok(events) => events,
+            Err(e) => {
+                error!("Failed to decode events of {}: {}", signature, e);
+                Vec::new()
+            }
+        };
+
+        // Tracked before its records are stored, so none escape a rollback
+        self.storage.store_chain_transaction(&ChainTransaction {
+            signature: signature.to_string(),
+            slot,
+            status: self.received_status(),
+            error: None,
+        }).await?;
+
+        for (index, event) in events.into_iter().enumerate() {
+            let event_index = index as u32;
+            match event {
+                ProgramEvent::TradeSettled(event) => {
+                    self.storage.store_settled_trade(&SettledTradeRecord {
+                        signature: signature.to_string(),
+                        event_index,
+                        slot,
+                        taker_order_id: event.taker_order_id,
+                        maker_order_id: event.maker_order_id,
+                        taker: event.taker,
+                        maker: event.maker,
+                        price: event.price,
+                        quantity: event.quantity,
+                        timestamp: event.timestamp,
+                    }).await?;
+                }
+                ProgramEvent::DepositMade(event) => {
+                    self.storage.store_deposit(&DepositRecord {
+                        signature: signature.to_string(),
+                        event_index,
+                        slot,
+                        user: event.user,
+                        mint: event.mint,
+                        amount: event.amount,
+                        timestamp: event.timestamp,
+                    }).await?;
+                }
+                ProgramEvent::WithdrawalMade(event) => {
+                    self.storage.store_withdrawal(&WithdrawalRecord {
+                        signature: signature.to_string(),
+                        event_index,
+                        slot,
+                        user: event.user,
+                        mint: event.mint,
+                        amount: event.amount,
+                        timestamp: event.timestamp,
+                    }).await?;
+                }
+                ProgramEvent::SubAccountCreated(event) => {
+                    self.storage.store_sub_account(&SubAccount {
+                        owner: event.sub_account,
+                        wallet: event.wallet,
+                        index: event.index,
+                        name: event.name,
+                        signature: signature.to_string(),
+                        created_at: event.timestamp,
+                    }).await?;
+                }
+                ProgramEvent::SubAccountTransfer(event) => {
+                    self.storage.store_sub_account_transfer(&SubAccountTransferRecord {
+                        signature: signature.to_string(),
+                        event_index,
+                        slot,
+                        wallet: event.wallet,
+                        from: event.from,
+                        to: event.to,
+                        base_amount: event.base_amount,
+                        quote_amount: event.quote_amount,
+                        timestamp: event.timestamp,
+                    }).await?;
+                }
+                ProgramEvent::PreventionGroupSet(event) => {
+                    self.storage.store_prevention_group(&PreventionGroupRecord {
+                        signature: signature.to_string(),
+                        event_index,
+                        slot,
+                        market_id: MarketId(event.orderbook),
+                        owner: event.owner,
+                        group: event.group,
+                        timestamp: event.timestamp,
+                    }).await?;
+                }
+                ProgramEvent::WithdrawalWhitelistChangeRequested(event) => {
+                    // Nothing to store; a request the owner did not make is
+                    // the sign of a stolen key, and the delay is the window
+                    // to react in
+                    if event.removes_whitelist {
+                        warn!(
+                            "Removal of the withdrawal whitelist of {} requested in {}, effective at {}",
+                            event.owner, signature, event.effective_at
+                        );
+                    } else {
+                        warn!(
+                            "Withdrawal destination {} requested for {} in {}, effective at {}",
+                            event.destination, event.owner, signature, event.effective_at
+                        );
+                    }
+                }
+                ProgramEvent::InsuranceFunded(event) => {
+                    self.storage.store_insurance_fund_event(&InsuranceFundEvent {
+                        signature: signature.to_string(),
+                        event_index,
+                        slot,
+                        orderbook: event.orderbook,
+                        kind: InsuranceFundEventKind::Funded,
+                        account: event.funder,
+                        amount: event.amount,
+                        timestamp: event.timestamp,
+                    }).await?;
+                }
+                ProgramEvent::InsuranceDrawn(event) => {
+                    self.storage.store_insurance_fund_event(&InsuranceFundEvent {
+                        signature: signature.to_string(),
+                        event_index,
+                        slot,
+                        orderbook: event.orderbook,
+                        kind: InsuranceFundEventKind::Drawn,
+                        account: event.destination,
+                        amount: event.amount,
+                        timestamp: event.timestamp,
+                    }).await?;
+                }
+                ProgramEvent::TransferCredited(event) => {
+                    self.storage.store_deposit(&DepositRecord {
+                        signature: signature.to_string(),
+                        event_index,
+                        slot,
+                        user: event.user,
+                        mint: event.mint,
+                        amount: event.amount,
+                        timestamp: event.timestamp,
+                    }).await?;
+                    let transfer_signature = Signature::from(event.transfer_signature).to_string();
+                    match self.storage.get_vault_transfer(&transfer_signature, event.instruction_index).await? {
+                        Some(transfer) => {
+                            self.storage.update_vault_transfer(&VaultTransfer {
+                                status: VaultTransferStatus::Credited,
+                                credit_signature: Some(signature.to_string()),
+                                updated_at: self.clock.now(),
+                                ..transfer
+                            }).await?;
+                        }
+                        None => warn!(
+                            "Credit in {} of transfer {}#{}, which was never detected",
+                            signature, transfer_signature, event.instruction_index
+                        ),
+                    }
+                }
+                ProgramEvent::SettlementAdjusted(event) => {
+                    // Marks the adjustment reversed; a rollback of the
+                    // transaction puts it back for the settler
+                    self.storage.store_settlement_adjustment(&SettlementAdjustmentRecord {
+                        signature: signature.to_string(),
+                        event_index,
+                        slot,
+                        adjustment_id: event.adjustment_id,
+                        trade_id: event.trade_id,
+                        maker: event.maker,
+                        taker: event.taker,
+                        base_to_maker: event.base_to_maker,
+                        quote_to_maker: event.quote_to_maker,
+                        timestamp: event.timestamp,
+                    }).await?;
+                }
+                ProgramEvent::ReceiptTreeInitialized(event) => {
+                    self.storage.store_receipt_tree(&ReceiptTreeRecord {
+                        merkle_tree: event.merkle_tree,
+                        orderbook: event.orderbook,
+                        max_depth: event.max_depth,
+                        max_buffer_size: event.max_buffer_size,
+                        generation: event.generation,
+                        signature: signature.to_string(),
+                        slot,
+                        timestamp: event.timestamp,
+                    }).await?;
+                }
+                ProgramEvent::ReceiptsAppended(event) => {
+                    // The event carries the trade; the receipts are rebuilt
+                    // from it exactly as the program hashed them
+                    let receipts: Vec<CompressedReceipt> = event
+                        .receipts()
+                        .iter()
+                        .zip(event.first_leaf_index..)
+                        .map(|(leaf, leaf_index)| {
+                            CompressedReceipt::new(event.merkle_tree, leaf_index, signature.to_string(), slot, leaf)
+                        })
+                        .collect();
+                    self.storage.store_compressed_receipts(&receipts).await?;
+                }
+                ProgramEvent::MarketStatusChanged(event) => {
+                    // The engine holds its own status; the reconciler reports
+                    // a market where the two disagree. Subscribers may pause
+                    // matching along with the market.
+                    if let Ok(status) = MarketStatus::try_from(event.status) {
+                        self.market_status.send_replace(Some(MarketStatusChange {
+                            orderbook: event.orderbook,
+                            status,
+                            slot,
+                        }));
+                    }
+                    let name = |byte: u8| {
+                        MarketStatus::try_from(byte).map_or_else(|e| e.to_string(), |status| status.to_string())
+                    };
+                    info!(
+                        "Market {} moved from {} to {} in {}",
+                        event.orderbook,
+                        name(event.previous),
+                        name(event.status),
+                        signature
+                    );
+                }
+                ProgramEvent::OrderTypesChanged(event) => {
+                    // The engine checks orders against its configured
+                    // whitelist; the reconciler reports one that differs
+                    let accepted = AcceptedOrderTypes::from(event.whitelist());
+                    info!(
+                        "Market {} now accepts order types {:?} and times in force {:?} from {}",
+                        event.orderbook, accepted.order_types, accepted.time_in_force, signature
+                    );
+                }
+            }
+        }
+
+        self.advance_checkpoint(signature, slot).await
+    }
+
+    /// Record a transaction that landed with an error; it has no records
+    async fn skip_failed_transaction(&self, signature: &str, slot: u64, error: &str) -> ClobResult<()> {
+        self.storage.store_chain_transaction(&ChainTransaction {
+            signature: signature.to_string(),
+            slot,
+            status: ChainStatus::Failed,
+            error: Some(error.to_string()),
+        }).await?;
+        self.advance_checkpoint(signature, slot).await
+    }
+
+    /// Status of a transaction seen at the configured commitment
+    fn received_status(&self) -> ChainStatus {
+        if self.config.commitment.is_finalized() {
+            ChainStatus::Finalized
+        } else if self.config.commitment.is_confirmed() {
+            ChainStatus::Confirmed
+        } else {
+            ChainStatus::Pending
+        }
+    }
+
+    /// Record a transaction as fully processed
+    async fn advance_checkpoint(&self, signature: &str, slot: u64) -> ClobResult<()> {
+        self.storage.save_checkpoint(&Checkpoint {
+            service: CHECKPOINT_SERVICE.to_string(),
+            position: slot,
+            cursor: Some(signature.to_string()),
+        }).await
+    }
+}
+
+fn parse_signature(signature: &str) -> ClobResult<Signature> {
+    Signature::from_str(signature)
+        .map_err(|e| ClobError::SerializationError(format!("Invalid signature {}: {}", signature, e)))
+}
+
+fn network_error<E: std::fmt::Display>(e: E) -> ClobError {
+    ClobError::NetworkError(e.to_string())
+}
@@ -0,0 +1,306 @@
+/// Plain transfers into the vaults
+///
+/// Tokens sent to a vault with a plain SPL `transfer` or `transferChecked`,
+/// rather than through the program's `deposit`, reach the vault without
+/// crediting any user account. The indexer follows the signatures of each
+/// watched vault, records every top-level transfer into it, and follows the
+/// transaction until it is finalized. A finalized transfer signed by the
+/// owner of its source account, whose user account exists, is ready for the
+/// settler to credit with `credit_transfer`; the credit, read back from
+/// chain, is stored as a deposit like any other. Transfers made by a delegate
+/// or a multisig, or nested in another program's instruction, cannot be tied
+/// to a wallet and are rejected for an operator to return.
+
+use crate::{network_error, parse_signature, Indexer, STATUS_BATCH};
+use svm_clob_storage::Storage;
+use svm_clob_types::program::{self, TOKEN_PROGRAM_ID};
+use svm_clob_types::*;
+use serde_json::Value;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction,
+    TransactionConfirmationStatus, UiInstruction, UiMessage, UiParsedInstruction, UiTransactionEncoding,
+};
+use std::str::FromStr;
+use tracing::{debug, info, warn};
+
+/// Checkpoint service of the vault of `mint`
+pub fn vault_checkpoint_service(mint: &Pubkey) -> String {
+    format!("indexer.vault.{}", mint)
+}
+
+/// Why a pending transfer waits although it is finalized
+const NO_USER_ACCOUNT: &str = "sender has no user account; credited once it initializes one";
+
+impl<S: Storage> Indexer<S> {
+    /// Record new transfers into the watched vaults and move recorded ones
+    /// towards being credited, until storage fails
+    pub(crate) async fn watch_vaults(&self) -> ClobResult<()> {
+        if self.config.vault_mints.is_empty() {
+            return std::future::pending().await;
+        }
+
+        loop {
+            for mint in &self.config.vault_mints {
+                match self.scan_vault(mint).await {
+                    Ok(()) => {}
+                    Err(ClobError::NetworkError(e)) => warn!("Failed to scan the vault of {}: {}", mint, e),
+                    Err(e) => return Err(e),
+                }
+            }
+            match self.confirm_vault_transfers().await {
+                Ok(()) => {}
+                Err(ClobError::NetworkError(e)) => warn!("Failed to poll vault transfer statuses: {}", e),
+                Err(e) => return Err(e),
+            }
+            tokio::time::sleep(self.config.finality_poll_interval).await;
+        }
+    }
+
+    /// Record the transfers into the vault of `mint` since its checkpoint
+    async fn scan_vault(&self, mint: &Pubkey) -> ClobResult<()> {
+        let service = vault_checkpoint_service(mint);
+        let (vault, _) = program::vault_address(&self.config.program_id, mint);
+        let until = self
+            .storage
+            .get_checkpoint(&service)
+            .await?
+            .and_then(|checkpoint| checkpoint.cursor)
+            .map(|cursor| parse_signature(&cursor))
+            .transpose()?;
+
+        let mut pending = Vec::new();
+        let mut before = None;
+        loop {
+            let page = self
+                .rpc
+                .get_signatures_for_address_with_config(
+                    &vault,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        until,
+                        limit: Some(self.config.backfill_page_size),
+                        commitment: Some(self.config.commitment),
+                    },
+                )
+                .await
+                .map_err(network_error)?;
+            let page_len = page.len();
+            before = page
+                .last()
+                .map(|status| parse_signature(&status.signature))
+                .transpose()?;
+            pending.extend(page);
+            if page_len < self.config.backfill_page_size {
+                break;
+            }
+        }
+
+        for status in pending.into_iter().rev() {
+            if status.err.is_none() {
+                let transaction = self
+                    .rpc
+                    .get_transaction_with_config(
+                        &parse_signature(&status.signature)?,
+                        RpcTransactionConfig {
+                            encoding: Some(UiTransactionEncoding::JsonParsed),
+                            commitment: Some(self.config.commitment),
+                            max_supported_transaction_version: Some(0),
+                        },
+                    )
+                    .await
+                    .map_err(network_error)?;
+                for transfer in self.vault_transfers(&status.signature, &vault, mint, &transaction) {
+                    info!(
+                        "Plain transfer of {} {} into the vault from {} in {}",
+                        transfer.amount, mint, transfer.sender, transfer.signature
+                    );
+                    self.storage.store_vault_transfer(&transfer).await?;
+                }
+            }
+            self.storage.save_checkpoint(&Checkpoint {
+                service: service.clone(),
+                position: status.slot,
+                cursor: Some(status.signature),
+            }).await?;
+        }
+        Ok(())
+    }
+
+    /// Top-level SPL transfers into `vault` made by `transaction`; one that
+    /// cannot be tied to a wallet is returned rejected
+    fn vault_transfers(
+        &self,
+        signature: &str,
+        vault: &Pubkey,
+        mint: &Pubkey,
+        transaction: &EncodedConfirmedTransactionWithStatusMeta,
+    ) -> Vec<VaultTransfer> {
+        let EncodedTransaction::Json(ui_transaction) = &transaction.transaction.transaction else {
+            return Vec::new();
+        };
+        let UiMessage::Parsed(message) = &ui_transaction.message else {
+            return Vec::new();
+        };
+        let owners: Vec<(String, String)> = match transaction.transaction.meta.as_ref().map(|meta| &meta.pre_token_balances) {
+            Some(OptionSerializer::Some(balances)) => balances
+                .iter()
+                .filter_map(|balance| {
+                    let account = message.account_keys.get(balance.account_index as usize)?;
+                    match &balance.owner {
+                        OptionSerializer::Some(owner) => Some((account.pubkey.clone(), owner.clone())),
+                        _ => None,
+                    }
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let now = self.clock.now();
+
+        let mut transfers = Vec::new();
+        for (index, instruction) in message.instructions.iter().enumerate() {
+            let UiInstruction::Parsed(UiParsedInstruction::Parsed(parsed)) = instruction else {
+                continue;
+            };
+            if parsed.program_id != TOKEN_PROGRAM_ID {
+                continue;
+            }
+            let info = &parsed.parsed["info"];
+            let amount = match parsed.parsed["type"].as_str() {
+                Some("transfer") => info["amount"].as_str(),
+                Some("transferChecked") => info["tokenAmount"]["amount"].as_str(),
+                _ => continue,
+            };
+            if pubkey_field(info, "destination") != Some(*vault) {
+                continue;
+            }
+            let (Some(amount), Some(source), Ok(index)) = (
+                amount.and_then(|amount| amount.parse::<u64>().ok()),
+                pubkey_field(info, "source"),
+                u8::try_from(index),
+            ) else {
+                warn!("Unreadable transfer into the vault in {}#{}", signature, index);
+                continue;
+            };
+            let authority = pubkey_field(info, "authority");
+            let owner = owners
+                .iter()
+                .find(|(account, _)| *account == source.to_string())
+                .and_then(|(_, owner)| Pubkey::from_str(owner).ok());
+
+            // Only the owner of the source speaks for the wallet to credit
+            let (sender, reason) = match (authority, owner) {
+                (Some(authority), Some(owner)) if authority == owner => (owner, None),
+                (_, owner) => (
+                    owner.unwrap_or_default(),
+                    Some("not signed by the owner of the source account".to_string()),
+                ),
+            };
+            transfers.push(VaultTransfer {
+                signature: signature.to_string(),
+                instruction_index: index,
+                slot: transaction.slot,
+                mint: *mint,
+                source,
+                sender,
+                amount,
+                status: if reason.is_some() || amount == 0 {
+                    VaultTransferStatus::Rejected
+                } else {
+                    VaultTransferStatus::Pending
+                },
+                confirmations: None,
+                reason: reason.or_else(|| (amount == 0).then(|| "nothing transferred".to_string())),
+                credit_signature: None,
+                detected_at: now,
+                updated_at: now,
+            });
+        }
+        transfers
+    }
+
+    /// Follow pending transfers until they are finalized, then check their
+    /// sender can be credited
+    async fn confirm_vault_transfers(&self) -> ClobResult<()> {
+        let pending = self
+            .storage
+            .get_vault_transfers_by_status(VaultTransferStatus::Pending, STATUS_BATCH)
+            .await?;
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let finalized_slot = self
+            .rpc
+            .get_slot_with_commitment(CommitmentConfig::finalized())
+            .await
+            .map_err(network_error)?;
+        let signatures = pending
+            .iter()
+            .map(|transfer| parse_signature(&transfer.signature))
+            .collect::<ClobResult<Vec<_>>>()?;
+        let statuses = self
+            .rpc
+            .get_signature_statuses_with_history(&signatures)
+            .await
+            .map_err(network_error)?
+            .value;
+
+        let now = self.clock.now();
+        for (transfer, status) in pending.into_iter().zip(statuses) {
+            let mut updated = VaultTransfer { updated_at: now, ..transfer.clone() };
+            match status {
+                None if transfer.slot <= finalized_slot => {
+                    updated.status = VaultTransferStatus::Rejected;
+                    updated.reason = Some("dropped from the finalized chain".to_string());
+                }
+                None => continue,
+                Some(status) if status.err.is_some() => {
+                    updated.status = VaultTransferStatus::Rejected;
+                    updated.reason = status.err.map(|err| err.to_string());
+                }
+                Some(status) if status.confirmation_status == Some(TransactionConfirmationStatus::Finalized) => {
+                    updated.confirmations = None;
+                    if self.has_user_account(&transfer.sender).await? {
+                        updated.status = VaultTransferStatus::Ready;
+                        updated.reason = None;
+                    } else {
+                        updated.reason = Some(NO_USER_ACCOUNT.to_string());
+                    }
+                }
+                Some(status) => {
+                    updated.confirmations = Some(status.confirmations.unwrap_or_default() as u64);
+                }
+            }
+            if updated.status != transfer.status
+                || updated.confirmations != transfer.confirmations
+                || updated.reason != transfer.reason
+            {
+                debug!(
+                    "Vault transfer {}#{} is {:?} with {:?} confirmations",
+                    updated.signature, updated.instruction_index, updated.status, updated.confirmations
+                );
+                self.storage.update_vault_transfer(&updated).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn has_user_account(&self, owner: &Pubkey) -> ClobResult<bool> {
+        let (address, _) = program::user_account_address(&self.config.program_id, owner);
+        let account = self
+            .rpc
+            .get_account_with_commitment(&address, CommitmentConfig::finalized())
+            .await
+            .map_err(network_error)?
+            .value;
+        Ok(account.is_some_and(|account| account.owner == self.config.program_id))
+    }
+}
+
+fn pubkey_field(info: &Value, field: &str) -> Option<Pubkey> {
+    info[field].as_str().and_then(|key| Pubkey::from_str(key).ok())
+}
@@ -0,0 +1,169 @@
+//! Matching hot path benchmarks
+//!
+//! Times the book's maker lookup and whole placements through the engine, and
+//! counts heap allocations with a counting global allocator. Engine figures
+//! include `InMemoryStorage` persisting and journaling each command; the book
+//! lookup reuses its buffer and should report no allocations at all.
+//!
+//! Run with `cargo bench -p svm-clob-matching-engine --bench hot_path`.
+
+use solana_sdk::pubkey::Pubkey;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use svm_clob_matching_engine::MatchingEngine;
+use svm_clob_order_book::OrderBookManager;
+use svm_clob_storage::InMemoryStorage;
+use svm_clob_types::*;
+
+/// Global allocator that counts allocations and bytes allocated
+struct Counting;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size as u64, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const TICK: u64 = 10;
+const OPS: u64 = 20_000;
+
+fn owner(index: u64) -> Pubkey {
+    let mut bytes = [0u8; 32];
+    bytes[..8].copy_from_slice(&index.to_le_bytes());
+    Pubkey::new_from_array(bytes)
+}
+
+fn limit(order_id: u64, owner_index: u64, side: OrderSide, price: u64, quantity: u64) -> Order {
+    Order::builder()
+        .order_id(order_id)
+        .owner(owner(owner_index))
+        .side(side)
+        .limit(price)
+        .qty(quantity)
+        .timestamp(0)
+        .build()
+        .expect("valid order")
+}
+
+fn orderbook() -> OrderBook {
+    OrderBook {
+        authority: Pubkey::default(),
+        base_mint: Pubkey::default(),
+        quote_mint: Pubkey::default(),
+        tick_size: TICK,
+        min_order_size: 1,
+        sequence_number: 0,
+        total_orders: 0,
+        best_bid: 0,
+        best_ask: u64::MAX,
+        total_volume: 0,
+        is_initialized: true,
+        is_paused: false,
+    }
+}
+
+/// Time `ops` runs of `op` and print the time, allocations and bytes per op
+fn report(name: &str, ops: u64, mut op: impl FnMut(u64)) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let started = Instant::now();
+    for index in 0..ops {
+        op(index);
+    }
+    let elapsed = started.elapsed();
+    println!(
+        "{:<32} {:>8} ops {:>10.0} ns/op {:>8.2} allocs/op {:>10.1} bytes/op",
+        name,
+        ops,
+        elapsed.as_nanos() as f64 / ops as f64,
+        (ALLOCATIONS.load(Ordering::Relaxed) - allocations) as f64 / ops as f64,
+        (ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes) as f64 / ops as f64,
+    );
+}
+
+/// Maker lookup of a taker reaching the best level, 100 orders, of a 10,000
+/// order book
+fn book_lookup() {
+    let mut book = OrderBookManager::new(TICK, 1);
+    for index in 0..10_000 {
+        let price = (100 + index % 100) * TICK;
+        book.add_order(limit(index + 1, index, OrderSide::Ask, price, 10)).unwrap();
+    }
+    let taker = limit(u64::MAX, u64::MAX, OrderSide::Bid, 100 * TICK, 500);
+
+    let mut makers = Vec::new();
+    // Grow the buffer before counting
+    book.matching_orders_into(&taker, taker.price, &mut makers);
+    report("book/matching_orders_into", OPS, |_| {
+        book.matching_orders_into(&taker, taker.price, &mut makers);
+    });
+}
+
+fn engine_benches() {
+    // The in-memory engine never waits, so each command is driven to
+    // completion on its own
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let place = |engine: &MatchingEngine<InMemoryStorage>, order: Order| {
+        runtime.block_on(engine.place_order(order)).expect("order accepted");
+    };
+
+    // Orders that rest without crossing
+    let engine = MatchingEngine::new(Arc::new(InMemoryStorage::new()), orderbook());
+    report("engine/place_resting", OPS, |index| {
+        place(&engine, limit(index + 1, index % 64, OrderSide::Bid, (1 + index % 100) * TICK, 10));
+    });
+
+    // A maker then a taker filling it, beside 1,000 resting orders
+    let engine = MatchingEngine::new(Arc::new(InMemoryStorage::new()), orderbook());
+    for index in 0..1_000 {
+        place(&engine, limit(1_000_000 + index, index, OrderSide::Ask, (200 + index % 100) * TICK, 10));
+    }
+    report("engine/cross_one_maker", OPS, |index| {
+        place(&engine, limit(2 * index + 1, 1, OrderSide::Ask, 150 * TICK, 10));
+        place(&engine, limit(2 * index + 2, 2, OrderSide::Bid, 150 * TICK, 10));
+    });
+
+    // Market orders sweeping ten makers over ten levels
+    let engine = MatchingEngine::new(Arc::new(InMemoryStorage::new()), orderbook());
+    report("engine/market_sweep_10", OPS / 10, |index| {
+        for level in 0..10 {
+            place(&engine, limit(100 * index + level + 1, level, OrderSide::Ask, (100 + level) * TICK, 10));
+        }
+        let taker = Order::builder()
+            .order_id(100 * index + 99)
+            .owner(owner(99))
+            .bid()
+            .market()
+            .qty(100)
+            .ioc()
+            .timestamp(0)
+            .build()
+            .unwrap();
+        place(&engine, taker);
+    });
+}
+
+fn main() {
+    book_lookup();
+    engine_benches();
+}
@@ -6,9 +6,73 @@
 use svm_clob_types::*;
 use svm_clob_order_book::OrderBookManager;
 use svm_clob_storage::Storage;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
 use tokio::sync::RwLock;
-use tracing::{info, warn, error};
+use tracing::{info, warn, error, instrument};
+
+pub mod history;
+mod mmp;
+mod pause;
+pub mod stats;
+
+pub use history::{book_at, book_history};
+pub use stats::RollingStats;
+
+use mmp::MakerProtection;
+use pause::PauseGate;
+
+/// Order IDs reserved in the journal at a time; a crash skips at most this
+/// many
+pub const ORDER_ID_BLOCK: u64 = 1024;
+
+/// Fill produced by the match loop
+struct Fill {
+    trade: TradeExecution,
+    /// Maker order after the fill
+    maker: Order,
+}
+
+/// Outcome of matching one incoming order
+#[derive(Default)]
+struct Matched {
+    fills: Vec<Fill>,
+    /// Resting orders self-trade prevention took off the book
+    cancelled_makers: Vec<Order>,
+}
+
+/// Order IDs handed out, and reserved in the journal ahead of them
+#[derive(Default)]
+struct OrderIds {
+    /// Last ID handed out
+    last: u64,
+    /// Highest ID a journaled reservation covers
+    reserved: u64,
+}
+
+/// Order flow counted since the last metrics sample
+struct FlowWindow {
+    flow: OrderFlow,
+    /// When the window opened, in unix microseconds; unset before the first sample
+    since_us: Option<i64>,
+}
+
+/// Book state visible to pre-trade hooks
+pub struct PreTradeContext<'a> {
+    /// Best resting bid, if any
+    pub best_bid: Option<u64>,
+    /// Best resting ask, if any
+    pub best_ask: Option<u64>,
+    /// Orders the submitting user already has resting
+    pub open_orders: &'a [Order],
+}
+
+/// Check run against every incoming order before it can reach the book
+pub trait PreTradeHook: Send + Sync {
+    /// Return an error to reject the order
+    fn check(&self, order: &Order, context: &PreTradeContext<'_>) -> ClobResult<()>;
+}
 
 /// Main matching engine that processes orders and executes trades
 pub struct MatchingEngine<S: Storage> {
@@ -18,79 +82,783 @@ pub struct MatchingEngine<S: Storage> {
     storage: Arc<S>,
     /// Current orderbook configuration
     orderbook_config: OrderBook,
+    /// Market this engine matches
+    market_id: MarketId,
+    /// How fills are split between makers at one price
+    allocation: AllocationMode,
+    /// Longest an order may rest before the expiry sweep takes it off
+    resting_lifetime: Option<RestingLifetime>,
+    /// Order types and times in force new orders may use
+    order_types: OrderTypeWhitelist,
+    /// Pre-trade checks run in registration order
+    pre_trade_hooks: Vec<Arc<dyn PreTradeHook>>,
+    /// Order IDs of placements and replacements
+    order_ids: tokio::sync::Mutex<OrderIds>,
+    /// Last trade ID handed to a fill
+    last_trade_id: AtomicU64,
+    /// Whether commands are accepted; a hot standby only replays the journal
+    active: AtomicBool,
+    /// `MarketStatus` byte: which commands the market accepts
+    status: AtomicU8,
+    /// Holds or refuses order commands while matching is paused
+    pause: PauseGate,
+    /// Sequence of the last journal entry written or replayed by this engine
+    journal_position: AtomicU64,
+    /// Time source for fills and expiry checks
+    clock: Arc<dyn Clock>,
+    /// Candidate makers of the match in progress, kept to reuse its capacity
+    maker_buffer: Mutex<Vec<Order>>,
+    /// Rolling 1h and 24h trade statistics
+    stats: Mutex<RollingStats>,
+    /// Order flow since the last `sample_metrics`
+    flow: Mutex<FlowWindow>,
+    /// Market maker protection of the makers that set one
+    mmp: Mutex<MakerProtection>,
 }
 
 impl<S: Storage> MatchingEngine<S> {
     /// Create a new matching engine instance
     pub fn new(storage: Arc<S>, orderbook_config: OrderBook) -> Self {
+        let status = if orderbook_config.is_paused { MarketStatus::Paused } else { MarketStatus::Active };
         Self {
             order_book: Arc::new(RwLock::new(OrderBookManager::new(
                 orderbook_config.tick_size,
                 orderbook_config.min_order_size,
             ))),
             storage,
+            market_id: MarketId::from_mints(&orderbook_config.base_mint, &orderbook_config.quote_mint),
             orderbook_config,
+            allocation: AllocationMode::default(),
+            resting_lifetime: None,
+            order_types: OrderTypeWhitelist::ALL,
+            pre_trade_hooks: Vec::new(),
+            order_ids: tokio::sync::Mutex::new(OrderIds::default()),
+            last_trade_id: AtomicU64::new(0),
+            active: AtomicBool::new(true),
+            status: AtomicU8::new(status.into()),
+            pause: PauseGate::new(),
+            journal_position: AtomicU64::new(0),
+            clock: system_clock(),
+            maker_buffer: Mutex::new(Vec::new()),
+            stats: Mutex::new(RollingStats::new()),
+            flow: Mutex::new(FlowWindow {
+                flow: OrderFlow::default(),
+                since_us: None,
+            }),
+            mmp: Mutex::new(MakerProtection::default()),
         }
     }
 
-    /// Process a new order placement
-    pub async fn place_order(&self, mut order: Order) -> ClobResult<Vec<TradeExecution>> {
-        info!("Processing order placement: ID {}", order.order_id);
+    /// Read time from `clock` instead of the system clock
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
 
-        // Validate order parameters
-        self.validate_order(&order)?;
+    /// Time source of this engine
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
 
-        let mut trades = Vec::new();
+    /// Start or stop accepting order commands
+    ///
+    /// Takes the book lock, so a command already in flight when the engine is
+    /// deactivated has been journaled by the time this returns.
+    pub async fn set_active(&self, active: bool) {
+        let _order_book = self.order_book.write().await;
+        self.active.store(active, Ordering::SeqCst);
+        info!("Matching engine {}", if active { "activated" } else { "deactivated" });
+    }
+
+    /// Whether order commands are accepted
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    /// Trading state of the market
+    pub fn status(&self) -> MarketStatus {
+        MarketStatus::try_from(self.status.load(Ordering::SeqCst)).unwrap_or(MarketStatus::Paused)
+    }
+
+    /// Move the market to `status`, returning the previous one
+    ///
+    /// `Paused` refuses every command, `CancelOnly` only cancellations and
+    /// replacements that shrink an order, and `ReduceOnly` orders that would
+    /// grow an owner's position in this market. Replay is never gated. The
+    /// status is stored, so a restart or a promoted standby keeps it. Takes
+    /// the book lock, so no command is in flight under the old status once
+    /// this returns.
+    pub async fn set_status(&self, status: MarketStatus) -> ClobResult<MarketStatus> {
+        let _order_book = self.order_book.write().await;
+        self.storage.set_market_status(&self.market_id, status, self.clock.now()).await?;
+        let previous = MarketStatus::try_from(self.status.swap(status.into(), Ordering::SeqCst))
+            .unwrap_or(MarketStatus::Paused);
+        info!("Market {} moved from {} to {}", self.market_id, previous, status);
+        Ok(previous)
+    }
+
+    /// Stop matching until `resume`
+    ///
+    /// Unlike a `Paused` market status, which refuses commands, the pause
+    /// applies `mode` to them: buffered commands run in the order they arrived
+    /// once the engine resumes. Expiry sweeps and replay go on. The pause is
+    /// held in memory only, so a restart or a promoted standby matches again.
+    /// Takes the book lock, so no command is in flight once this returns.
+    pub async fn pause(&self, mode: PauseMode) -> EnginePause {
+        let _order_book = self.order_book.write().await;
+        if self.pause.set(Some((mode, self.clock.now()))).is_none() {
+            info!("Matching engine paused");
+        }
+        self.pause.state()
+    }
+
+    /// Match again, running buffered commands first in arrival order;
+    /// returns the pause lifted, with the commands it held
+    pub async fn resume(&self) -> EnginePause {
+        let paused = self.pause.state();
+        if self.pause.set(None).is_some() {
+            info!("Matching engine resumed with {} buffered commands", paused.buffered);
+        }
+        paused
+    }
+
+    /// Whether matching is paused, and the commands waiting for it to resume
+    pub fn pause_state(&self) -> EnginePause {
+        self.pause.state()
+    }
+
+    /// Protect the maker `user` with `config`, replacing any protection it
+    /// had and starting its window afresh
+    ///
+    /// Configurations are held in memory by this instance only, like the
+    /// pause, so each replica must be given them.
+    pub fn set_mmp(&self, user: Pubkey, config: MmpConfig) -> MmpStatus {
+        self.mmp().set(user, config, self.clock.now_millis())
+    }
+
+    /// Stop protecting `user`, returning the configuration it had
+    pub fn clear_mmp(&self, user: &Pubkey) -> Option<MmpConfig> {
+        self.mmp().clear(user)
+    }
+
+    /// Let a maker frozen by its protection quote again before the freeze ends
+    pub fn reset_mmp(&self, user: &Pubkey) -> Option<MmpStatus> {
+        self.mmp().reset(user, self.clock.now_millis())
+    }
+
+    /// Protection of `user` and its current window, if it has one
+    pub fn mmp_status(&self, user: &Pubkey) -> Option<MmpStatus> {
+        self.mmp().status(user, self.clock.now_millis())
+    }
+
+    /// Protection of every protected maker, by key
+    pub fn all_mmp(&self) -> Vec<MmpStatus> {
+        self.mmp().all(self.clock.now_millis())
+    }
+
+    fn mmp(&self) -> std::sync::MutexGuard<'_, MakerProtection> {
+        self.mmp.lock().expect("mmp lock poisoned")
+    }
+
+    /// Take up the status last stored for this market, if any
+    pub async fn restore_status(&self) -> ClobResult<MarketStatus> {
+        if let Some(status) = self.storage.get_market_status(&self.market_id).await? {
+            self.status.store(status.into(), Ordering::SeqCst);
+        }
+        Ok(self.status())
+    }
+
+    /// Sequence of the last journal entry this engine wrote or replayed
+    pub fn journal_position(&self) -> u64 {
+        self.journal_position.load(Ordering::SeqCst)
+    }
+
+    /// Apply a journal entry written by another engine instance
+    ///
+    /// Placements are matched again instead of applying their journaled fills;
+    /// matching is deterministic for a given book, so the result is the same.
+    /// Validation and pre-trade hooks are skipped because the writer already
+    /// accepted the command, and nothing is persisted or journaled. Returns the
+    /// fills a replayed placement produced, for comparison with the journaled ones.
+    pub async fn replay_journal_entry(&self, entry: &JournalEntry) -> ClobResult<Vec<TradeExecution>> {
         let mut order_book = self.order_book.write().await;
+        if entry.sequence <= self.journal_position() {
+            return Ok(Vec::new());
+        }
 
-        match order.order_type {
-            OrderType::Market => {
-                trades = self.execute_market_order(&mut order_book, &mut order).await?;
+        let mut trades = Vec::new();
+        match &entry.event {
+            JournalEvent::OrderPlaced { order } => {
+                let mut order = order.clone();
+                let matched = self.match_order(&mut order_book, &mut order).await?;
+                trades = matched.fills.into_iter().map(|fill| fill.trade).collect();
             }
-            OrderType::Limit => {
-                trades = self.execute_limit_order(&mut order_book, &mut order).await?;
+            JournalEvent::OrderCancelled { order_id } => {
+                if order_book.remove_order(*order_id).is_err() {
+                    warn!("Replayed cancel of unknown order {}", order_id);
+                }
             }
-            OrderType::PostOnly => {
-                if self.would_match_immediately(&order_book, &order).await? {
-                    return Err(ClobError::PostOnlyOrderWouldMatch);
+            JournalEvent::OrderExpired { order_id, .. } => {
+                if order_book.remove_order(*order_id).is_err() {
+                    warn!("Replayed expiry of unknown order {}", order_id);
+                }
+            }
+            JournalEvent::MmpTriggered { order_ids, .. } => {
+                for order_id in order_ids {
+                    if order_book.remove_order(*order_id).is_err() {
+                        warn!("Replayed protection pull of unknown order {}", order_id);
+                    }
+                }
+            }
+            JournalEvent::OrderReplaced { original_order_id, order } => {
+                if order_book.remove_order(*original_order_id).is_err() {
+                    warn!("Replayed replace of unknown order {}", original_order_id);
                 }
                 order_book.add_order(order.clone())?;
             }
+            JournalEvent::OrderIdsReserved { through } => {
+                let mut ids = self.order_ids.lock().await;
+                ids.last = ids.last.max(*through);
+                ids.reserved = ids.reserved.max(*through);
+            }
+            JournalEvent::TradeExecuted { trade, .. } => {
+                self.last_trade_id.fetch_max(trade.trade_id, Ordering::SeqCst);
+                self.record_stats(std::slice::from_ref(trade));
+            }
+            // The writer stored the adjustment; the book is untouched
+            JournalEvent::TradeAdjusted { .. } => {}
+        }
+
+        self.journal_position.store(entry.sequence, Ordering::SeqCst);
+        Ok(trades)
+    }
+
+    /// Rebuild the book from the open orders in storage, so a restart does not
+    /// orphan the orders resting when the previous run stopped
+    ///
+    /// Call before the engine takes commands. Orders of other markets are
+    /// skipped, and orders go back in the order they were stored, which keeps
+    /// their time priority. The journal position moves to the head, as the
+    /// book now reflects every entry up to it; an engine that rebuilds its book
+    /// by replaying the journal, such as a hot standby, should not bootstrap.
+    /// Returns the number of orders restored.
+    pub async fn bootstrap(&self) -> ClobResult<usize> {
+        let mut order_book = self.order_book.write().await;
+        let journal_head = self.storage.get_journal_head().await?;
+
+        let mut restored = 0;
+        for order in self.storage.get_open_orders().await? {
+            if order.market_id != self.market_id {
+                continue;
+            }
+            let order_id = order.order_id;
+            match order_book.add_order(order) {
+                Ok(()) => restored += 1,
+                Err(e) => warn!("Open order {} not restored to the book: {}", order_id, e),
+            }
         }
 
-        // Persist order and trades
+        self.journal_position.fetch_max(journal_head, Ordering::SeqCst);
+        drop(order_book);
+        self.seed_stats().await?;
+        let status = self.restore_status().await?;
+        info!("Restored {} open orders at journal sequence {}, market {}", restored, journal_head, status);
+        Ok(restored)
+    }
+
+    /// Record the stored trades of the last 24 hours in the rolling stats
+    async fn seed_stats(&self) -> ClobResult<()> {
+        let since = self.clock.now() - market_data::TICKER_WINDOW_SECS;
+        let mut page = Paginated::first(MAX_PAGE_LIMIT);
+        loop {
+            let trades = self.storage.get_market_trades(&self.market_id, &page).await?;
+            self.record_stats(&trades.items);
+            let reached_end = trades.items.last().map_or(true, |trade| trade.timestamp <= since);
+            match page.next(&trades) {
+                Some(next) if !reached_end => page = next,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn record_stats(&self, trades: &[TradeExecution]) {
+        let mut stats = self.stats.lock().expect("rolling stats lock poisoned");
+        for trade in trades {
+            stats.record(trade);
+        }
+    }
+
+    /// Last trade price and the rolling 1h and 24h windows ending now
+    pub fn market_stats(&self) -> MarketStats {
+        let now = self.clock.now();
+        let stats = self.stats.lock().expect("rolling stats lock poisoned");
+        MarketStats::new(
+            stats.last_price(),
+            stats.window(3_600, now),
+            stats.window(market_data::TICKER_WINDOW_SECS, now),
+        )
+    }
+
+    /// Sample the book and the order flow since the previous sample, for
+    /// dashboards; the first sample covers the flow since the engine started
+    /// with an interval of zero
+    pub async fn sample_metrics(&self) -> ClobResult<MarketMetrics> {
+        let book = self.get_order_book_snapshot().await?;
+        let now_us = self.clock.now_micros();
+        let (flow, since_us) = {
+            let mut window = self.flow.lock().expect("order flow lock poisoned");
+            let flow = std::mem::take(&mut window.flow);
+            (flow, window.since_us.replace(now_us))
+        };
+        let interval_secs = since_us.map_or(0.0, |since_us| (now_us - since_us).max(0) as f64 / 1e6);
+        Ok(MarketMetrics::new(&book, flow, interval_secs))
+    }
+
+    fn record_flow(&self, update: impl FnOnce(&mut OrderFlow)) {
+        update(&mut self.flow.lock().expect("order flow lock poisoned").flow);
+    }
+
+    /// Continue trade IDs after `last_trade_id`, the newest one persisted by
+    /// an earlier run; IDs never go backwards
+    pub fn resume_trade_ids(&self, last_trade_id: u64) {
+        self.last_trade_id.fetch_max(last_trade_id, Ordering::SeqCst);
+    }
+
+    /// Hand out order IDs past `reserved`, the highest ID reserved in the
+    /// journal by an earlier run; IDs never go backwards
+    pub fn resume_order_ids(&mut self, reserved: u64) {
+        let ids = self.order_ids.get_mut();
+        ids.last = ids.last.max(reserved);
+        ids.reserved = ids.reserved.max(reserved);
+    }
+
+    /// ID for a new order, above every ID this engine, an earlier run of it or
+    /// the leader it replayed handed out
+    ///
+    /// IDs are reserved `ORDER_ID_BLOCK` at a time by journaling
+    /// `OrderIdsReserved` before the first of them is handed out. An engine
+    /// resumed from storage, or a standby following the journal, continues
+    /// past the last reservation, so what a crash left of a block is skipped
+    /// rather than reused. IDs increase in the order they are handed out,
+    /// which is the order placements reach the engine unless callers race.
+    /// Orders placed with an ID of the caller's choosing do not move the
+    /// allocator.
+    pub async fn allocate_order_id(&self) -> ClobResult<u64> {
+        self.ensure_active()?;
+        let mut ids = self.order_ids.lock().await;
+        if ids.last >= ids.reserved {
+            // Order IDs are stored as BIGINT
+            let through = ids
+                .last
+                .checked_add(ORDER_ID_BLOCK)
+                .filter(|through| *through <= i64::MAX as u64)
+                .ok_or_else(|| ClobError::StorageError("order IDs exhausted".to_string()))?;
+            self.journal(&JournalEvent::OrderIdsReserved { through }).await?;
+            ids.reserved = through;
+        }
+        ids.last += 1;
+        Ok(ids.last)
+    }
+
+    /// Market this engine matches
+    pub fn market_id(&self) -> MarketId {
+        self.market_id
+    }
+
+    /// Override the market ID derived under the default program ID, for
+    /// deployments of the program at another address
+    pub fn set_market_id(&mut self, market_id: MarketId) {
+        self.market_id = market_id;
+    }
+
+    /// Choose how fills are split between makers at one price; engines
+    /// replaying each other's journal must use the same mode
+    pub fn set_allocation(&mut self, allocation: AllocationMode) {
+        self.allocation = allocation;
+    }
+
+    /// How fills are split between makers at one price
+    pub fn allocation(&self) -> AllocationMode {
+        self.allocation
+    }
+
+    /// Expire orders that rest longer than `lifetime`; orders rest until
+    /// cancelled, filled or their own expiry without one
+    pub fn set_resting_lifetime(&mut self, lifetime: Option<RestingLifetime>) {
+        self.resting_lifetime = lifetime;
+    }
+
+    /// Longest an order may rest, if limited
+    pub fn resting_lifetime(&self) -> Option<RestingLifetime> {
+        self.resting_lifetime
+    }
+
+    /// Accept only new orders of the order types and times in force of
+    /// `whitelist`; orders already resting, and their replacements, are
+    /// left alone
+    pub fn set_order_types(&mut self, whitelist: OrderTypeWhitelist) {
+        self.order_types = whitelist;
+    }
+
+    /// Order types and times in force new orders may use
+    pub fn order_types(&self) -> OrderTypeWhitelist {
+        self.order_types
+    }
+
+    /// Register a pre-trade check (risk limits, ...)
+    pub fn add_pre_trade_hook(&mut self, hook: Arc<dyn PreTradeHook>) {
+        self.pre_trade_hooks.push(hook);
+    }
+
+    /// Process a new order placement
+    #[instrument(name = "engine.place_order", skip_all, fields(order_id = order.order_id, side = ?order.side, order_type = ?order.order_type))]
+    pub async fn place_order(&self, mut order: Order) -> ClobResult<Vec<TradeExecution>> {
+        info!("Processing order placement: ID {}", order.order_id);
+        let started = std::time::Instant::now();
+        let received_at_us = self.clock.now_micros();
+
+        // Held or refused while paused before anything else, as cancels and
+        // modifies are
+        let intake = self.pause.admit().await?;
+
+        // Validate order parameters
+        self.assign_market(&mut order)?;
+        self.validate_order(&order)?;
+        self.check_order_type(&order)?;
+
+        let submitted = order.clone();
+        let mut order_book = self.order_book.write().await;
+        drop(intake);
+        self.ensure_active()?;
+        self.ensure_accepts_order(&order_book, &order, None).await?;
+        check_client_order_id(&order_book, &order)?;
+        self.run_pre_trade_hooks(&order_book, &order)?;
+        if order.can_rest() {
+            self.mmp().check(&order.owner, received_at_us.div_euclid(1000))?;
+        }
+        if let Some(max_slippage_bps) = order.max_slippage_bps {
+            self.check_slippage(&order_book, &order, max_slippage_bps)?;
+        }
+
+        let Matched { mut fills, cancelled_makers } = self.match_order(&mut order_book, &mut order).await?;
+        for fill in &mut fills {
+            fill.trade.received_at_us = received_at_us;
+        }
+
+        // Persist order, trades and the resting orders they changed, so the
+        // stored open orders always match the book
         self.storage.store_order(&order).await?;
-        for trade in &trades {
-            self.storage.store_trade(trade).await?;
+        for fill in &fills {
+            self.storage.store_trade(&fill.trade).await?;
+            self.storage.update_order(&fill.maker).await?;
+        }
+        for maker in &cancelled_makers {
+            self.storage.update_order(maker).await?;
         }
 
+        // Journal the accepted command followed by its fills
+        self.journal(&JournalEvent::OrderPlaced { order: submitted }).await?;
+        for fill in &fills {
+            self.journal(&JournalEvent::TradeExecuted {
+                trade: fill.trade.clone(),
+                maker: fill.maker.owner,
+                taker: order.owner,
+            }).await?;
+        }
+        self.protect_makers(&mut order_book, &fills).await?;
+
+        let trades: Vec<TradeExecution> = fills.into_iter().map(|fill| fill.trade).collect();
+        self.record_stats(&trades);
+        self.record_flow(|flow| {
+            flow.orders_placed += 1;
+            flow.trades += trades.len() as u64;
+        });
+
+        svm_clob_observability::record_order_placed(order.side, order.order_type);
+        svm_clob_observability::record_match_latency(&self.market_id, started.elapsed());
         info!("Order processed: {} trades executed", trades.len());
         Ok(trades)
     }
 
     /// Cancel an existing order
+    #[instrument(name = "engine.cancel_order", skip(self))]
     pub async fn cancel_order(&self, order_id: u64) -> ClobResult<Order> {
         info!("Canceling order: {}", order_id);
 
+        let intake = self.pause.admit().await?;
         let mut order_book = self.order_book.write().await;
+        drop(intake);
+        self.ensure_active()?;
+        if self.status() == MarketStatus::Paused {
+            return Err(ClobError::OrderbookPaused);
+        }
         let order = order_book.remove_order(order_id)?;
         
         // Update order status and persist
         let mut cancelled_order = order;
         cancelled_order.status = OrderStatus::Cancelled;
         self.storage.update_order(&cancelled_order).await?;
+        self.journal(&JournalEvent::OrderCancelled { order_id }).await?;
+        self.record_flow(|flow| flow.orders_cancelled += 1);
 
         info!("Order cancelled: {}", order_id);
         Ok(cancelled_order)
     }
 
+    /// Cancel every order `owner` has resting, oldest first, each journaled
+    /// as its own `OrderCancelled`
+    #[instrument(name = "engine.cancel_owner_orders", skip(self))]
+    pub async fn cancel_owner_orders(&self, owner: &Pubkey) -> ClobResult<Vec<Order>> {
+        let intake = self.pause.admit().await?;
+        let mut order_book = self.order_book.write().await;
+        drop(intake);
+        self.ensure_active()?;
+        if self.status() == MarketStatus::Paused {
+            return Err(ClobError::OrderbookPaused);
+        }
+
+        let mut resting = order_book.get_user_orders(owner);
+        resting.sort_by_key(|order| (order.timestamp, order.order_id));
+        let mut cancelled = Vec::with_capacity(resting.len());
+        for order in resting {
+            let mut order = order_book.remove_order(order.order_id)?;
+            order.status = OrderStatus::Cancelled;
+            self.storage.update_order(&order).await?;
+            self.journal(&JournalEvent::OrderCancelled { order_id: order.order_id }).await?;
+            self.record_flow(|flow| flow.orders_cancelled += 1);
+            cancelled.push(order);
+        }
+
+        info!("Cancelled {} orders of {}", cancelled.len(), owner);
+        Ok(cancelled)
+    }
+
+    /// Bust a trade, or correct its price when `price` is given, as an
+    /// operator, journaled as `TradeAdjusted`
+    ///
+    /// The adjustment becomes the trade's next version; the book, the orders
+    /// of the trade and their filled quantities stay as they are, and a busted
+    /// trade cannot be adjusted again. Returns the stored adjustment, whose ID
+    /// is the journal sequence, with the balances a settlement of the previous
+    /// version must move back; the settler reverses them on chain.
+    #[instrument(name = "engine.adjust_trade", skip(self, reason))]
+    pub async fn adjust_trade(
+        &self,
+        trade_id: u64,
+        price: Option<u64>,
+        reason: String,
+        actor: String,
+    ) -> ClobResult<TradeAdjustment> {
+        // The book lock serializes adjustments with the fills they follow
+        let _order_book = self.order_book.write().await;
+        self.ensure_active()?;
+
+        let Some(entry) = self.storage.get_trade_journal_entry(trade_id).await? else {
+            return Err(ClobError::TradeNotAdjustable(format!("trade {} is not in the journal", trade_id)));
+        };
+        let JournalEvent::TradeExecuted { trade: executed, maker, taker } = entry.event else {
+            return Err(ClobError::StorageError(format!("journal entry {} is not a fill", entry.sequence)));
+        };
+        if executed.market_id != self.market_id {
+            return Err(ClobError::TradeNotAdjustable(format!(
+                "trade {} belongs to market {}",
+                trade_id, executed.market_id
+            )));
+        }
+
+        let previous = match self.storage.get_trade_adjustments(trade_id).await?.last() {
+            Some(last) => TradeExecution {
+                price: last.price,
+                version: last.version,
+                status: last.status,
+                ..executed.clone()
+            },
+            None => executed,
+        };
+        if previous.status == TradeStatus::Busted {
+            return Err(ClobError::TradeNotAdjustable(format!("trade {} is already busted", trade_id)));
+        }
+        let (status, price) = match price {
+            None => (TradeStatus::Busted, previous.price),
+            Some(price) if price == previous.price => {
+                return Err(ClobError::TradeNotAdjustable(format!("trade {} is already at {}", trade_id, price)));
+            }
+            Some(0) => return Err(ClobError::InvalidPrice("a corrected price must be positive".to_string())),
+            Some(price) if !Price(price).is_aligned(self.orderbook_config.tick_size) => {
+                return Err(ClobError::PriceNotAlignedToTickSize);
+            }
+            Some(price) => (TradeStatus::Corrected, price),
+        };
+        let adjusted = TradeExecution {
+            price,
+            version: previous.version + 1,
+            status,
+            ..previous.clone()
+        };
+        let (base_to_maker, quote_to_maker) = adjusted.settlement_delta(&previous)?;
+
+        let adjustment_id = self
+            .journal(&JournalEvent::TradeAdjusted {
+                trade: adjusted.clone(),
+                maker,
+                taker,
+                trade_sequence: entry.sequence,
+                previous_price: previous.price,
+                reason: reason.clone(),
+                actor: actor.clone(),
+            })
+            .await?;
+        let now = self.clock.now();
+        let adjustment = TradeAdjustment {
+            adjustment_id,
+            trade_id,
+            market_id: self.market_id,
+            version: adjusted.version,
+            status,
+            trade_sequence: entry.sequence,
+            maker,
+            taker,
+            maker_order_id: adjusted.maker_order_id,
+            taker_order_id: adjusted.taker_order_id,
+            maker_side: adjusted.maker_side,
+            quantity: adjusted.quantity,
+            previous_price: previous.price,
+            price,
+            base_to_maker,
+            quote_to_maker,
+            reason,
+            actor,
+            reversal: ReversalStatus::Pending,
+            reversal_signature: None,
+            reversal_error: None,
+            created_at: now,
+            updated_at: now,
+        };
+        self.storage.store_trade_adjustment(&adjustment).await?;
+
+        info!(
+            "Trade {} adjusted to v{} ({:?}) at {}, was {}",
+            trade_id, adjustment.version, status, price, previous.price
+        );
+        Ok(adjustment)
+    }
+
+    /// Take the good-till-time orders whose expiry is at or before `now`
+    /// (unix seconds) off the book, and the orders that have rested for the
+    /// market's maximum lifetime by then
+    ///
+    /// Matching never reads the clock, so replay stays deterministic; an
+    /// order can therefore still fill until the sweep that expires it. Each
+    /// expired order is persisted with status `Expired` and journaled.
+    /// Returns the expired orders with the reason each expired.
+    #[instrument(name = "engine.expire_orders", skip(self))]
+    pub async fn expire_orders(&self, now: i64) -> ClobResult<Vec<(Order, ExpiryReason)>> {
+        let mut order_book = self.order_book.write().await;
+        self.ensure_active()?;
+        let due: Vec<(u64, ExpiryReason)> = order_book
+            .get_open_orders()
+            .into_iter()
+            .filter_map(|order| {
+                if order.time_in_force == TimeInForce::GoodTillTime && order.expiry_timestamp <= now {
+                    Some((order.order_id, ExpiryReason::GoodTillTime))
+                } else if self
+                    .resting_lifetime
+                    .and_then(|lifetime| lifetime.deadline(&order))
+                    .is_some_and(|deadline| deadline <= now)
+                {
+                    Some((order.order_id, ExpiryReason::MaxLifetime))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let mut expired = Vec::with_capacity(due.len());
+        for (order_id, reason) in due {
+            let mut order = order_book.remove_order(order_id)?;
+            order.status = OrderStatus::Expired;
+            self.storage.update_order(&order).await?;
+            self.journal(&JournalEvent::OrderExpired { order_id, reason }).await?;
+            info!("Order expired: {} ({:?})", order_id, reason);
+            expired.push((order, reason));
+        }
+        Ok(expired)
+    }
+
     /// Get current order book snapshot
+    ///
+    /// The snapshot is numbered by the journal position rather than the book's
+    /// own change counter, which restarts with the process: the same number
+    /// then names the same book on every engine following the journal, and
+    /// `book_at` can rebuild it later.
     pub async fn get_order_book_snapshot(&self) -> ClobResult<OrderBookSnapshot> {
         let order_book = self.order_book.read().await;
-        Ok(order_book.get_snapshot())
+        Ok(OrderBookSnapshot {
+            market_id: self.market_id,
+            sequence_number: self.journal_position(),
+            timestamp: self.clock.now(),
+            ..order_book.get_snapshot()
+        })
     }
 
-    /// Modify an existing order
+    /// Resting orders at the current journal position
+    pub async fn checkpoint(&self) -> BookCheckpoint {
+        let order_book = self.order_book.read().await;
+        BookCheckpoint {
+            market_id: self.market_id,
+            journal_sequence: self.journal_position(),
+            orders: order_book.get_open_orders_by_arrival(),
+            timestamp: self.clock.now(),
+        }
+    }
+
+    /// Load `checkpoint` into an empty book, to replay the journal after it
+    pub async fn restore_checkpoint(&self, checkpoint: &BookCheckpoint) -> ClobResult<()> {
+        let mut order_book = self.order_book.write().await;
+        for order in &checkpoint.orders {
+            order_book.add_order(order.clone())?;
+        }
+        self.journal_position.fetch_max(checkpoint.journal_sequence, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Get every order resting in the book
+    pub async fn get_open_orders(&self) -> Vec<Order> {
+        let order_book = self.order_book.read().await;
+        order_book.get_open_orders()
+    }
+
+    /// Place of a resting order in the queue of its price level
+    pub async fn queue_position(&self, order_id: u64) -> ClobResult<QueuePosition> {
+        let order_book = self.order_book.read().await;
+        let position = order_book.queue_position(order_id).ok_or(ClobError::OrderNotFound)?;
+        Ok(QueuePosition {
+            market_id: self.market_id,
+            ..position
+        })
+    }
+
+    /// Places in their levels' queues of every resting order of `owners`
+    pub async fn queue_positions(&self, owners: &[Pubkey]) -> Vec<QueuePosition> {
+        let order_book = self.order_book.read().await;
+        owners
+            .iter()
+            .flat_map(|owner| order_book.get_user_orders(owner))
+            .filter_map(|order| order_book.queue_position(order.order_id))
+            .map(|position| QueuePosition {
+                market_id: self.market_id,
+                ..position
+            })
+            .collect()
+    }
+
+    /// Orderbook configuration the engine was started with
+    pub fn orderbook_config(&self) -> &OrderBook {
+        &self.orderbook_config
+    }
+
+    /// Modify an existing order by cancel-replace: the replacement takes a new
+    /// ID at the back of its price level, journaled as `OrderReplaced`
+    #[instrument(name = "engine.modify_order", skip(self))]
     pub async fn modify_order(
         &self,
         order_id: u64,
@@ -99,17 +867,56 @@ impl<S: Storage> MatchingEngine<S> {
     ) -> ClobResult<Order> {
         info!("Modifying order: {}", order_id);
 
+        let intake = self.pause.admit().await?;
         let mut order_book = self.order_book.write().await;
-        let original_order = order_book.remove_order(order_id)?;
+        drop(intake);
+        self.ensure_active()?;
+        let original_order = order_book.get_order(order_id).ok_or(ClobError::OrderNotFound)?;
 
         let mut modified_order = original_order.clone();
-        modified_order.order_id = chrono::Utc::now().timestamp_millis() as u64; // new order ID
         modified_order.price = new_price.unwrap_or(original_order.price);
         modified_order.quantity = new_quantity.unwrap_or(original_order.quantity);
-        modified_order.remaining_quantity = modified_order.quantity; // Reset remaining quantity
 
-        // Re-validate and place the modified order
+        // The new quantity is a total; what already filled stays filled
+        let filled = original_order.filled_quantity();
+        if modified_order.quantity <= filled {
+            return Err(ClobError::InvalidQuantity(format!(
+                "new quantity {} does not exceed filled quantity {}",
+                modified_order.quantity, filled
+            )));
+        }
+        modified_order.remaining_quantity = modified_order.quantity - filled;
+
+        // Shrinking an order in place only takes risk off the book
+        let shrinks = modified_order.price == original_order.price && modified_order.quantity <= original_order.quantity;
+        if !shrinks || self.status() == MarketStatus::Paused {
+            self.ensure_accepts_order(&order_book, &modified_order, Some(order_id)).await?;
+        }
+
+        // Re-validate and place the modified order, restoring the original on rejection.
+        // Replacements rest without matching, so they must not cross the book.
         self.validate_order(&modified_order)?;
+        if self.would_match_immediately(&order_book, &modified_order).await? {
+            return Err(ClobError::InvalidPrice(format!(
+                "modified price {} would cross the book",
+                modified_order.price
+            )));
+        }
+        // Hooks see the owner's orders without the one being replaced; a
+        // rejected replacement puts it back at its arrival, keeping its priority
+        let arrival = order_book.arrival(order_id).ok_or(ClobError::OrderNotFound)?;
+        order_book.remove_order(order_id)?;
+        if let Err(e) = self.run_pre_trade_hooks(&order_book, &modified_order) {
+            order_book.restore_order(original_order, arrival);
+            return Err(e);
+        }
+        modified_order.order_id = match self.allocate_order_id().await {
+            Ok(id) => id,
+            Err(e) => {
+                order_book.restore_order(original_order, arrival);
+                return Err(e);
+            }
+        };
         order_book.add_order(modified_order.clone())?;
         
         // Update storage for both orders
@@ -117,73 +924,159 @@ impl<S: Storage> MatchingEngine<S> {
         cancelled_original.status = OrderStatus::Cancelled;
         self.storage.update_order(&cancelled_original).await?;
         self.storage.store_order(&modified_order).await?;
+        self.journal(&JournalEvent::OrderReplaced {
+            original_order_id: order_id,
+            order: modified_order.clone(),
+        }).await?;
+        self.record_flow(|flow| {
+            flow.orders_cancelled += 1;
+            flow.orders_placed += 1;
+        });
 
         info!("Order modified: original {}, new {}", order_id, modified_order.order_id);
         Ok(modified_order)
     }
 
-    /// Execute market order with immediate matching
-    async fn execute_market_order(
-        &self,
-        order_book: &mut OrderBookManager,
-        order: &mut Order,
-    ) -> ClobResult<Vec<TradeExecution>> {
-        let mut trades = Vec::new();
-        let current_time = chrono::Utc::now().timestamp();
-
-        // Get matching orders from opposite side
-        let matching_orders = match order.side {
-            OrderSide::Bid => order_book.get_asks_up_to_price(u64::MAX)?,
-            OrderSide::Ask => order_book.get_bids_down_to_price(0)?,
-        };
+    /// Match an accepted order by type, resting whatever its type lets rest
+    async fn match_order(&self, order_book: &mut OrderBookManager, order: &mut Order) -> ClobResult<Matched> {
+        match order.order_type {
+            OrderType::Market => self.execute_market_order(order_book, order).await,
+            OrderType::Limit => self.execute_limit_order(order_book, order).await,
+            OrderType::PostOnly => {
+                if self.would_match_immediately(order_book, order).await? {
+                    return Err(ClobError::PostOnlyOrderWouldMatch);
+                }
+                order_book.add_order(order.clone())?;
+                Ok(Matched::default())
+            }
+        }
+    }
 
-        for matching_order in matching_orders {
-            if order.remaining_quantity == 0 {
-                break;
+    /// Count the fills against protected makers, pulling every resting order
+    /// of a maker whose protection they trip
+    ///
+    /// Runs after the fills are journaled, so replay removes the same orders
+    /// from the `MmpTriggered` entry without counting anything itself.
+    async fn protect_makers(&self, order_book: &mut OrderBookManager, fills: &[Fill]) -> ClobResult<()> {
+        let tripped: Vec<(Pubkey, MmpLimit)> = {
+            let mut mmp = self.mmp();
+            let mut tripped: Vec<(Pubkey, MmpLimit)> = Vec::new();
+            for fill in fills {
+                let maker = &fill.maker;
+                if tripped.iter().any(|(owner, _)| *owner == maker.owner) {
+                    continue;
+                }
+                let at_ms = fill.trade.matched_at_us.div_euclid(1000);
+                if let Some(limit) = mmp.record_fill(&maker.owner, maker.side, fill.trade.quantity, at_ms) {
+                    tripped.push((maker.owner, limit));
+                }
             }
+            tripped
+        };
 
-            // Check for self-trade
-            if self.is_self_trade(order, &matching_order) {
-                self.handle_self_trade(order_book, order, &matching_order)?;
-                continue;
+        for (owner, limit) in tripped {
+            let mut order_ids = Vec::new();
+            for resting in order_book.get_user_orders(&owner) {
+                let mut pulled = order_book.remove_order(resting.order_id)?;
+                pulled.status = OrderStatus::Cancelled;
+                self.storage.update_order(&pulled).await?;
+                order_ids.push(pulled.order_id);
             }
+            warn!(
+                "Market maker protection of {} tripped on {:?}: pulled {} orders",
+                owner,
+                limit,
+                order_ids.len()
+            );
+            self.journal(&JournalEvent::MmpTriggered { owner, limit, order_ids }).await?;
+        }
+        Ok(())
+    }
 
-            // Execute trade
-            let trade_quantity = order.remaining_quantity.min(matching_order.remaining_quantity);
-            let trade_price = matching_order.price; // Market orders take maker price
-
-            let trade = TradeExecution {
-                maker_order_id: matching_order.order_id,
-                taker_order_id: order.order_id,
-                price: trade_price,
-                quantity: trade_quantity,
-                timestamp: current_time,
-                maker_side: matching_order.side,
-            };
+    /// Append an event to the journal and advance the journal position
+    async fn journal(&self, event: &JournalEvent) -> ClobResult<u64> {
+        let sequence = self.storage.append_journal_entry(event).await?;
+        self.journal_position.fetch_max(sequence, Ordering::SeqCst);
+        Ok(sequence)
+    }
 
-            trades.push(trade);
+    /// Reject commands while this instance is a standby
+    fn ensure_active(&self) -> ClobResult<()> {
+        if self.is_active() {
+            Ok(())
+        } else {
+            Err(ClobError::NotLeader)
+        }
+    }
 
-            // Update order quantities
-            order.remaining_quantity -= trade_quantity;
-            
-            // Update maker order in book
-            order_book.update_order_quantity(matching_order.order_id, 
-                                           matching_order.remaining_quantity - trade_quantity)?;
+    /// Reject an order the market's status does not admit; `replacing` is
+    /// the resting order a replacement takes the place of
+    async fn ensure_accepts_order(
+        &self,
+        order_book: &OrderBookManager,
+        order: &Order,
+        replacing: Option<u64>,
+    ) -> ClobResult<()> {
+        match self.status() {
+            MarketStatus::Active => Ok(()),
+            MarketStatus::Paused => Err(ClobError::OrderbookPaused),
+            MarketStatus::CancelOnly => Err(ClobError::MarketCancelOnly),
+            MarketStatus::ReduceOnly => self.check_reduce_only(order_book, order, replacing).await,
         }
+    }
 
-        // Update order status based on remaining quantity
-        if order.remaining_quantity == 0 {
-            order.status = OrderStatus::Filled;
-        } else if order.remaining_quantity < order.quantity {
-            order.status = OrderStatus::PartiallyFilled;
+    /// Admit an order only if it and the owner's other resting orders on its
+    /// side, all filled, would at most close the owner's position in this
+    /// market, never open or grow one
+    async fn check_reduce_only(
+        &self,
+        order_book: &OrderBookManager,
+        order: &Order,
+        replacing: Option<u64>,
+    ) -> ClobResult<()> {
+        let fills = self.storage.get_user_fills(&order.owner).await?;
+        let position = Portfolio::new(order.owner, fills, &HashMap::new(), self.clock.now())
+            .positions
+            .into_iter()
+            .find(|position| position.market_id == self.market_id)
+            .map_or(0, |position| position.net_position);
+        let closable = match order.side {
+            OrderSide::Bid => (-position).max(0),
+            OrderSide::Ask => position.max(0),
+        };
+        let resting: i128 = order_book
+            .get_user_orders(&order.owner)
+            .iter()
+            .filter(|resting| resting.side == order.side && Some(resting.order_id) != replacing)
+            .map(|resting| resting.remaining_quantity as i128)
+            .sum();
+        let requested = resting + order.remaining_quantity as i128;
+        if requested > closable {
+            return Err(ClobError::ReduceOnly(format!(
+                "{} {:?} would exceed the {} that closes the position of {}",
+                requested, order.side, closable, order.owner
+            )));
         }
+        Ok(())
+    }
 
-        // Market orders that can't be fully filled are cancelled (IOC behavior)
-        if order.remaining_quantity > 0 && order.time_in_force == TimeInForce::ImmediateOrCancel {
+    /// Execute market order with immediate matching
+    async fn execute_market_order(
+        &self,
+        order_book: &mut OrderBookManager,
+        order: &mut Order,
+    ) -> ClobResult<Matched> {
+        // Market orders take any price and never rest
+        let limit_price = match order.side {
+            OrderSide::Bid => u64::MAX,
+            OrderSide::Ask => 0,
+        };
+
+        let matched = self.sweep(order_book, order, limit_price)?;
+        if order.remaining_quantity > 0 {
             order.status = OrderStatus::Cancelled;
         }
-
-        Ok(trades)
+        Ok(matched)
     }
 
     /// Execute limit order with price-time matching
@@ -191,89 +1084,211 @@ impl<S: Storage> MatchingEngine<S> {
         &self,
         order_book: &mut OrderBookManager,
         order: &mut Order,
-    ) -> ClobResult<Vec<TradeExecution>> {
-        let mut trades = Vec::new();
-        let current_time = chrono::Utc::now().timestamp();
-
-        // Get matching orders within price range
-        let matching_orders = match order.side {
-            OrderSide::Bid => order_book.get_asks_up_to_price(order.price)?,
-            OrderSide::Ask => order_book.get_bids_down_to_price(order.price)?,
-        };
+    ) -> ClobResult<Matched> {
+        let matched = self.sweep(order_book, order, order.price)?;
+        if order.status == OrderStatus::Cancelled || order.remaining_quantity == 0 {
+            return Ok(matched);
+        }
 
-        for matching_order in matching_orders {
-            if order.remaining_quantity == 0 {
-                break;
+        // Handle time in force for unfilled portions
+        match order.time_in_force {
+            TimeInForce::ImmediateOrCancel | TimeInForce::FillOrKill => {
+                order.status = OrderStatus::Cancelled;
+            }
+            TimeInForce::GoodTillCancelled | TimeInForce::GoodTillTime => {
+                order_book.add_order(order.clone())?;
             }
+        }
 
-            // Check price compatibility
-            let can_match = match order.side {
-                OrderSide::Bid => order.price >= matching_order.price,
-                OrderSide::Ask => order.price <= matching_order.price,
-            };
+        Ok(matched)
+    }
 
-            if !can_match {
-                break;
-            }
+    /// Match an incoming order against resting orders, best price first
+    ///
+    /// Within a price level makers are filled in the order the market's
+    /// `AllocationMode` gives them, or share the fill pro rata. Leaves the taker
+    /// `Filled`, `Cancelled` (by self-trade prevention or an unfillable
+    /// fill-or-kill) or with its unfilled remainder for the caller to handle. A
+    /// fill-or-kill order that cannot fill completely leaves the book untouched.
+    fn sweep(&self, order_book: &mut OrderBookManager, order: &mut Order, limit_price: u64) -> ClobResult<Matched> {
+        // Candidate makers go through a buffer kept between matches, so a
+        // match allocates nothing for them once the buffer has grown
+        let mut makers = std::mem::take(&mut *self.maker_buffer.lock().expect("maker buffer lock poisoned"));
+        order_book.matching_orders_into(order, limit_price, &mut makers);
+        let matched = self.sweep_makers(order_book, order, &mut makers);
+        makers.clear();
+        *self.maker_buffer.lock().expect("maker buffer lock poisoned") = makers;
+        matched
+    }
 
-            // Check for self-trade
-            if self.is_self_trade(order, &matching_order) {
-                self.handle_self_trade(order_book, order, &matching_order)?;
-                continue;
+    /// `sweep` over `makers`, the resting orders the taker can reach
+    fn sweep_makers(
+        &self,
+        order_book: &mut OrderBookManager,
+        order: &mut Order,
+        makers: &mut [Order],
+    ) -> ClobResult<Matched> {
+        let mut matched = Matched::default();
+        let matched_at_us = self.clock.now_micros();
+
+        if self.allocation == AllocationMode::SizeTime {
+            size_priority(makers);
+        }
+        if order.time_in_force == TimeInForce::FillOrKill && !self.can_fill_completely(order, makers) {
+            order.status = OrderStatus::Cancelled;
+            return Ok(matched);
+        }
+
+        // Makers that share one allocation: a whole price level pro rata, one
+        // maker at a time otherwise
+        let mut start = 0;
+        while start < makers.len() && order.remaining_quantity > 0 {
+            let end = match self.allocation {
+                AllocationMode::ProRata => level_end(makers, start),
+                AllocationMode::PriceTime | AllocationMode::SizeTime => start + 1,
+            };
+            let batch = &makers[start..end];
+            start = end;
+
+            // Self-trade prevention runs before anything in the batch fills
+            for matching_order in batch {
+                if self.is_self_trade(order, matching_order) {
+                    matched.cancelled_makers.extend(self.handle_self_trade(order_book, order, matching_order)?);
+                    if order.status == OrderStatus::Cancelled {
+                        return Ok(matched);
+                    }
+                }
             }
 
-            // Execute trade at maker price
-            let trade_quantity = order.remaining_quantity.min(matching_order.remaining_quantity);
-            let trade_price = matching_order.price;
-
-            let trade = TradeExecution {
-                maker_order_id: matching_order.order_id,
-                taker_order_id: order.order_id,
-                price: trade_price,
-                quantity: trade_quantity,
-                timestamp: current_time,
-                maker_side: matching_order.side,
+            // The taker's own orders, and those of its prevention group, were
+            // dealt with above
+            let (owner, group) = (order.owner, order.prevention_group_id);
+            let fillable = || {
+                batch.iter().filter(move |maker| {
+                    maker.owner != owner && !(group.is_some() && maker.prevention_group_id == group)
+                })
             };
+            let quantities = pro_rata(order.remaining_quantity, fillable().map(|maker| maker.remaining_quantity));
+            for (matching_order, trade_quantity) in fillable().zip(quantities) {
+                if trade_quantity == 0 {
+                    continue;
+                }
+
+                // Execute trade at maker price
+                let trade = TradeExecution {
+                    trade_id: self.last_trade_id.fetch_add(1, Ordering::SeqCst) + 1,
+                    market_id: self.market_id,
+                    maker_order_id: matching_order.order_id,
+                    taker_order_id: order.order_id,
+                    price: matching_order.price,
+                    quantity: trade_quantity,
+                    timestamp: matched_at_us.div_euclid(1_000_000),
+                    maker_side: matching_order.side,
+                    // Stamped by `place_order`, which saw the order arrive
+                    received_at_us: 0,
+                    matched_at_us,
+                    version: 0,
+                    status: TradeStatus::Active,
+                };
 
-            trades.push(trade);
+                // Update order quantities
+                order.record_fill(matching_order.price, trade_quantity);
 
-            // Update order quantities
-            order.remaining_quantity -= trade_quantity;
-            
-            // Update maker order in book
-            order_book.update_order_quantity(matching_order.order_id,
-                                           matching_order.remaining_quantity - trade_quantity)?;
+                // Update maker order in book
+                order_book.update_order_quantity(matching_order.order_id,
+                                               matching_order.remaining_quantity - trade_quantity)?;
+
+                let mut maker = matching_order.clone();
+                maker.record_fill(matching_order.price, trade_quantity);
+                maker.status = if maker.remaining_quantity == 0 {
+                    OrderStatus::Filled
+                } else {
+                    OrderStatus::PartiallyFilled
+                };
+                matched.fills.push(Fill { trade, maker });
+            }
         }
 
-        // Update order status
         if order.remaining_quantity == 0 {
             order.status = OrderStatus::Filled;
         } else if order.remaining_quantity < order.quantity {
             order.status = OrderStatus::PartiallyFilled;
         }
 
-        // Handle time in force for unfilled portions
-        match order.time_in_force {
-            TimeInForce::FillOrKill => {
-                if order.remaining_quantity > 0 {
-                    order.status = OrderStatus::Cancelled;
-                    return Ok(Vec::new()); // Cancel all trades for FOK
-                }
+        Ok(matched)
+    }
+
+    /// Reject a market order whose average fill price would be more than
+    /// `max_slippage_bps` from the best opposite price
+    ///
+    /// The impact price is read off the book before anything fills, so a
+    /// rejected order leaves the book untouched and is never journaled; replay
+    /// therefore skips the check. The taker's own resting orders are left out,
+    /// as self-trade prevention never fills against them.
+    fn check_slippage(&self, order_book: &OrderBookManager, order: &Order, max_slippage_bps: u32) -> ClobResult<()> {
+        let mut matching_orders = Vec::new();
+        let limit_price = match order.side {
+            OrderSide::Bid => u64::MAX,
+            OrderSide::Ask => 0,
+        };
+        order_book.matching_orders_into(order, limit_price, &mut matching_orders);
+
+        let mut needed = order.remaining_quantity;
+        let mut best_price = None;
+        let mut filled: u128 = 0;
+        let mut notional: u128 = 0;
+        for maker in matching_orders.iter().filter(|maker| !self.is_self_trade(order, maker)) {
+            if needed == 0 {
+                break;
             }
-            TimeInForce::ImmediateOrCancel => {
-                if order.remaining_quantity > 0 {
-                    order.status = OrderStatus::Cancelled;
-                }
+            let quantity = needed.min(maker.remaining_quantity);
+            best_price.get_or_insert(maker.price);
+            filled += quantity as u128;
+            notional += maker.price as u128 * quantity as u128;
+            needed -= quantity;
+        }
+
+        // An empty book fills nothing, at no price
+        let Some(best_price) = best_price.filter(|price| *price > 0) else {
+            return Ok(());
+        };
+        let average_price = notional / filled;
+        let slippage_bps = average_price.abs_diff(best_price as u128) * 10_000 / best_price as u128;
+        if slippage_bps > max_slippage_bps as u128 {
+            return Err(ClobError::SlippageExceeded(format!(
+                "average fill price {} is {} bps from best price {}, above the {} bps tolerance",
+                average_price, slippage_bps, best_price, max_slippage_bps
+            )));
+        }
+        Ok(())
+    }
+
+    /// Whether sweeping `matching_orders` would fill the order completely,
+    /// given how self-trade prevention treats the taker's own resting orders
+    fn can_fill_completely(&self, order: &Order, matching_orders: &[Order]) -> bool {
+        let mut needed = order.remaining_quantity;
+
+        for matching_order in matching_orders {
+            if needed == 0 {
+                break;
             }
-            TimeInForce::GoodTillCancelled | TimeInForce::GoodTillTime => {
-                // Add remaining order to book if not fully filled
-                if order.remaining_quantity > 0 {
-                    order_book.add_order(order.clone())?;
+
+            if self.is_self_trade(order, matching_order) {
+                let taker_cancelled = match order.self_trade_behavior {
+                    SelfTradeBehavior::CancelProvide => false,
+                    SelfTradeBehavior::CancelTake | SelfTradeBehavior::CancelBoth => true,
+                    SelfTradeBehavior::DecrementAndCancel => needed <= matching_order.remaining_quantity,
+                };
+                if taker_cancelled {
+                    return false;
                 }
+                continue;
             }
+
+            needed -= needed.min(matching_order.remaining_quantity);
         }
 
-        Ok(trades)
+        needed == 0
     }
 
     /// Check if an order would match immediately (for PostOnly validation)
@@ -297,44 +1312,102 @@ impl<S: Storage> MatchingEngine<S> {
         }
     }
 
-    /// Check if two orders would constitute a self-trade
+    /// Check if two orders would constitute a self-trade: both belong to one
+    /// owner, or both were placed in one prevention group
     fn is_self_trade(&self, order1: &Order, order2: &Order) -> bool {
         order1.owner == order2.owner
+            || matches!(
+                (order1.prevention_group_id, order2.prevention_group_id),
+                (Some(group1), Some(group2)) if group1 == group2
+            )
     }
 
-    /// Handle self-trade prevention based on configured behavior
+    /// Handle self-trade prevention based on configured behavior, returning
+    /// the maker when it was taken off the book
     fn handle_self_trade(
         &self,
         order_book: &mut OrderBookManager,
         taker_order: &mut Order,
         maker_order: &Order,
-    ) -> ClobResult<()> {
-        match taker_order.self_trade_behavior {
+    ) -> ClobResult<Option<Order>> {
+        let cancel_maker = match taker_order.self_trade_behavior {
             SelfTradeBehavior::DecrementAndCancel => {
                 // Cancel the smaller order
                 if taker_order.remaining_quantity <= maker_order.remaining_quantity {
                     taker_order.status = OrderStatus::Cancelled;
+                    false
                 } else {
-                    order_book.remove_order(maker_order.order_id)?;
+                    true
                 }
             }
             SelfTradeBehavior::CancelProvide => {
                 // Cancel the resting (maker) order
-                order_book.remove_order(maker_order.order_id)?;
+                true
             }
             SelfTradeBehavior::CancelTake => {
                 // Cancel the incoming (taker) order
                 taker_order.status = OrderStatus::Cancelled;
+                false
             }
             SelfTradeBehavior::CancelBoth => {
                 // Cancel both orders
                 taker_order.status = OrderStatus::Cancelled;
-                order_book.remove_order(maker_order.order_id)?;
+                true
             }
-        }
+        };
 
         warn!("Self-trade prevented between orders {} and {}", 
               taker_order.order_id, maker_order.order_id);
+        if !cancel_maker {
+            return Ok(None);
+        }
+        let mut cancelled = order_book.remove_order(maker_order.order_id)?;
+        cancelled.status = OrderStatus::Cancelled;
+        Ok(Some(cancelled))
+    }
+
+    /// Run every registered pre-trade hook against an incoming order
+    fn run_pre_trade_hooks(&self, order_book: &OrderBookManager, order: &Order) -> ClobResult<()> {
+        if self.pre_trade_hooks.is_empty() {
+            return Ok(());
+        }
+
+        let open_orders = order_book.get_user_orders(&order.owner);
+        let context = PreTradeContext {
+            best_bid: order_book.get_best_bid(),
+            best_ask: order_book.get_best_ask(),
+            open_orders: &open_orders,
+        };
+        for hook in &self.pre_trade_hooks {
+            hook.check(order, &context)?;
+        }
+        Ok(())
+    }
+
+    /// Stamp the engine's market on an order without one, rejecting orders for other markets
+    fn assign_market(&self, order: &mut Order) -> ClobResult<()> {
+        if order.market_id.is_unset() {
+            order.market_id = self.market_id;
+        } else if order.market_id != self.market_id {
+            return Err(ClobError::UnknownMarket(order.market_id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Reject a new order of a type or time in force the market does not accept
+    pub fn check_order_type(&self, order: &Order) -> ClobResult<()> {
+        if !self.order_types.accepts_order_type(order.order_type) {
+            return Err(ClobError::OrderTypeDisabled(format!(
+                "{} orders are not accepted in this market",
+                order.order_type
+            )));
+        }
+        if !self.order_types.accepts_time_in_force(order.time_in_force) {
+            return Err(ClobError::OrderTypeDisabled(format!(
+                "{} orders are not accepted in this market",
+                order.time_in_force
+            )));
+        }
         Ok(())
     }
 
@@ -346,23 +1419,83 @@ impl<S: Storage> MatchingEngine<S> {
         }
 
         // Check tick size alignment
-        if order.price % self.orderbook_config.tick_size != 0 {
+        if !Price(order.price).is_aligned(self.orderbook_config.tick_size) {
             return Err(ClobError::PriceNotAlignedToTickSize);
         }
 
-        // Check if orderbook is paused
-        if self.orderbook_config.is_paused {
-            return Err(ClobError::OrderbookPaused);
+        // Only market orders take a slippage tolerance; a limit price bounds the rest
+        if order.max_slippage_bps.is_some() && order.order_type != OrderType::Market {
+            return Err(ClobError::InvalidOrderType);
         }
 
         // Check expiry for time-based orders
         if order.time_in_force == TimeInForce::GoodTillTime {
-            let current_time = chrono::Utc::now().timestamp();
-            if order.expiry_timestamp <= current_time {
+            if order.expiry_timestamp <= self.clock.now() {
                 return Err(ClobError::OrderExpired);
             }
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Reject an order reusing the client order ID of one of its owner's open
+/// orders; 0 means the client set none and is never checked
+fn check_client_order_id(order_book: &OrderBookManager, order: &Order) -> ClobResult<()> {
+    if order.client_order_id == 0 {
+        return Ok(());
+    }
+    if order_book.has_client_order_id(&order.owner, order.client_order_id) {
+        return Err(ClobError::DuplicateClientOrderId(format!(
+            "{} already has an open order with client order ID {}",
+            order.owner, order.client_order_id
+        )));
+    }
+    Ok(())
+}
+
+/// End of the price level starting at `start` of `makers`, best price first
+fn level_end(makers: &[Order], start: usize) -> usize {
+    let price = makers[start].price;
+    start + makers[start..].iter().take_while(|maker| maker.price == price).count()
+}
+
+/// Reorder each price level largest order first, keeping arrival order among
+/// equal sizes
+fn size_priority(makers: &mut [Order]) {
+    let mut start = 0;
+    while start < makers.len() {
+        let end = level_end(makers, start);
+        makers[start..end].sort_by(|a, b| b.remaining_quantity.cmp(&a.remaining_quantity));
+        start = end;
+    }
+}
+
+/// Split `quantity` between makers of remaining sizes `sizes`, in order
+///
+/// Shares are rounded down and the units left over go one each to the makers
+/// in order, so a single maker simply fills up to its size. The shares are
+/// worked out in two passes over `sizes` instead of into a buffer.
+fn pro_rata<I>(quantity: u64, sizes: I) -> impl Iterator<Item = u64>
+where
+    I: Iterator<Item = u64> + Clone,
+{
+    let total: u128 = sizes.clone().map(u128::from).sum();
+    let whole = quantity as u128 >= total;
+    let rounded_down = move |size: u64| {
+        if whole {
+            size
+        } else {
+            (quantity as u128 * size as u128 / total) as u64
+        }
+    };
+    let mut leftover = if whole { 0 } else { quantity - sizes.clone().map(rounded_down).sum::<u64>() };
+    sizes.map(move |size| {
+        let mut share = rounded_down(size);
+        if leftover > 0 && share < size {
+            share += 1;
+            leftover -= 1;
+        }
+        share
+    })
+}
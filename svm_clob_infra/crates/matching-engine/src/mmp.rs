@@ -0,0 +1,170 @@
+/// Market maker protection
+///
+/// A maker with an `MmpConfig` has its maker fills counted over a rolling
+/// window: how many there were, their base quantity, and the delta they
+/// built, bought less sold. A fill that takes any of them to its limit trips
+/// the protection; the engine then pulls every order the maker has resting
+/// and the window starts afresh. For `freeze_ms` after a trip the maker's
+/// orders that could rest are refused, while orders that only take, such as
+/// a hedge, still go through. Fills are counted in memory by the instance
+/// that matched them, so a failover or restart starts every window afresh.
+
+use svm_clob_types::{ClobError, ClobResult, MmpConfig, MmpLimit, MmpStatus, OrderSide, Pubkey};
+use std::collections::{HashMap, VecDeque};
+
+/// Maker fill counted in a window
+struct CountedFill {
+    /// Unix milliseconds
+    at_ms: i64,
+    quantity: u64,
+    /// Quantity signed by the maker's side, bought positive
+    delta: i128,
+}
+
+/// Protection of one maker
+struct Protection {
+    config: MmpConfig,
+    fills: VecDeque<CountedFill>,
+    quantity: u64,
+    delta: i128,
+    frozen_until_ms: Option<i64>,
+    trips: u64,
+    last_trip: Option<MmpLimit>,
+}
+
+impl Protection {
+    fn new(config: MmpConfig) -> Self {
+        Self {
+            config,
+            fills: VecDeque::new(),
+            quantity: 0,
+            delta: 0,
+            frozen_until_ms: None,
+            trips: 0,
+            last_trip: None,
+        }
+    }
+
+    /// Drop fills that left the window ending at `now_ms`
+    fn slide(&mut self, now_ms: i64) {
+        let start = now_ms.saturating_sub(self.config.window_ms.min(i64::MAX as u64) as i64);
+        while let Some(fill) = self.fills.front().filter(|fill| fill.at_ms <= start) {
+            self.quantity = self.quantity.saturating_sub(fill.quantity);
+            self.delta -= fill.delta;
+            self.fills.pop_front();
+        }
+    }
+
+    /// Limit the window has reached, checked in a fixed order
+    fn breached(&self) -> Option<MmpLimit> {
+        let reached = |limit: Option<u64>, value: u128| limit.is_some_and(|limit| value >= limit as u128);
+        if reached(self.config.max_fills, self.fills.len() as u128) {
+            Some(MmpLimit::Fills)
+        } else if reached(self.config.max_quantity, self.quantity as u128) {
+            Some(MmpLimit::Quantity)
+        } else if reached(self.config.max_delta, self.delta.unsigned_abs()) {
+            Some(MmpLimit::Delta)
+        } else {
+            None
+        }
+    }
+
+    fn clear_window(&mut self) {
+        self.fills.clear();
+        self.quantity = 0;
+        self.delta = 0;
+    }
+
+    fn status(&self, user: Pubkey, now_ms: i64) -> MmpStatus {
+        MmpStatus {
+            user,
+            config: self.config,
+            fills: self.fills.len() as u64,
+            quantity: self.quantity,
+            delta: self.delta,
+            frozen_until_ms: self.frozen_until_ms.filter(|until| *until > now_ms),
+            trips: self.trips,
+            last_trip: self.last_trip,
+        }
+    }
+}
+
+/// Protection of every maker that has one
+#[derive(Default)]
+pub(crate) struct MakerProtection {
+    makers: HashMap<Pubkey, Protection>,
+}
+
+impl MakerProtection {
+    /// Protect `user` with `config`, starting its window afresh
+    pub(crate) fn set(&mut self, user: Pubkey, config: MmpConfig, now_ms: i64) -> MmpStatus {
+        let protection = self.makers.entry(user).or_insert_with(|| Protection::new(config));
+        protection.config = config;
+        protection.clear_window();
+        protection.status(user, now_ms)
+    }
+
+    /// Stop protecting `user`, returning its configuration
+    pub(crate) fn clear(&mut self, user: &Pubkey) -> Option<MmpConfig> {
+        self.makers.remove(user).map(|protection| protection.config)
+    }
+
+    /// Lift the freeze of `user` and start its window afresh
+    pub(crate) fn reset(&mut self, user: &Pubkey, now_ms: i64) -> Option<MmpStatus> {
+        let protection = self.makers.get_mut(user)?;
+        protection.clear_window();
+        protection.frozen_until_ms = None;
+        Some(protection.status(*user, now_ms))
+    }
+
+    pub(crate) fn status(&mut self, user: &Pubkey, now_ms: i64) -> Option<MmpStatus> {
+        let protection = self.makers.get_mut(user)?;
+        protection.slide(now_ms);
+        Some(protection.status(*user, now_ms))
+    }
+
+    pub(crate) fn all(&mut self, now_ms: i64) -> Vec<MmpStatus> {
+        let mut statuses: Vec<MmpStatus> = self
+            .makers
+            .iter_mut()
+            .map(|(user, protection)| {
+                protection.slide(now_ms);
+                protection.status(*user, now_ms)
+            })
+            .collect();
+        statuses.sort_by_key(|status| status.user);
+        statuses
+    }
+
+    /// Refuse an order of `user` that could rest while its protection is frozen
+    pub(crate) fn check(&self, user: &Pubkey, now_ms: i64) -> ClobResult<()> {
+        match self.makers.get(user).and_then(|protection| protection.frozen_until_ms) {
+            Some(until) if until > now_ms => Err(ClobError::MmpFrozen(format!(
+                "quotes of {} are refused until {} ms",
+                user, until
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Count a fill of a resting order of `user` on `side`, returning the
+    /// limit it took the window to, which trips the protection
+    pub(crate) fn record_fill(&mut self, user: &Pubkey, side: OrderSide, quantity: u64, at_ms: i64) -> Option<MmpLimit> {
+        let protection = self.makers.get_mut(user)?;
+        protection.slide(at_ms);
+        let delta = match side {
+            OrderSide::Bid => quantity as i128,
+            OrderSide::Ask => -(quantity as i128),
+        };
+        protection.fills.push_back(CountedFill { at_ms, quantity, delta });
+        protection.quantity = protection.quantity.saturating_add(quantity);
+        protection.delta += delta;
+
+        let limit = protection.breached()?;
+        protection.clear_window();
+        protection.trips += 1;
+        protection.last_trip = Some(limit);
+        protection.frozen_until_ms = Some(at_ms.saturating_add(protection.config.freeze_ms.min(i64::MAX as u64) as i64));
+        Some(limit)
+    }
+}
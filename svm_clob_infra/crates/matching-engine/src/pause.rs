@@ -0,0 +1,88 @@
+/// Engine-level pause
+///
+/// `PauseGate` stands in front of the book lock of every order command. While
+/// the engine matches, commands pass straight through. Paused in `Buffer`
+/// mode, they wait at the gate, one behind the other in the order they
+/// arrived, and pass in that order once the engine resumes; commands arriving
+/// after the resume queue behind them, so none overtakes a buffered one.
+/// Paused in `Reject` mode, or with the buffer full, they are refused with
+/// `EnginePaused`. A command whose caller goes away while it waits is
+/// withdrawn without having run.
+
+use svm_clob_types::{ClobError, ClobResult, EnginePause, PauseMode};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::{watch, Mutex, MutexGuard};
+
+/// Gate order commands pass before taking the book lock
+pub(crate) struct PauseGate {
+    /// Pause in effect and when it began
+    state: watch::Sender<Option<(PauseMode, i64)>>,
+    /// Held by the command passing the gate until it has queued for the book
+    /// lock; Tokio's mutex is fair, so commands pass in arrival order
+    intake: Mutex<()>,
+    /// Commands between arriving at the gate and passing it
+    waiting: AtomicUsize,
+}
+
+/// Counts a command as waiting until dropped, including when its caller
+/// goes away
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl PauseGate {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: watch::channel(None).0,
+            intake: Mutex::new(()),
+            waiting: AtomicUsize::new(0),
+        }
+    }
+
+    /// Pause in `mode` from `now`, or resume when `None`; returns the pause
+    /// that was in effect
+    pub(crate) fn set(&self, pause: Option<(PauseMode, i64)>) -> Option<(PauseMode, i64)> {
+        self.state.send_replace(pause)
+    }
+
+    /// The pause in effect and the commands it holds
+    pub(crate) fn state(&self) -> EnginePause {
+        let pause = *self.state.borrow();
+        EnginePause {
+            mode: pause.map(|(mode, _)| mode),
+            buffered: if pause.is_some() { self.waiting.load(Ordering::SeqCst) } else { 0 },
+            since: pause.map(|(_, since)| since),
+        }
+    }
+
+    /// Wait for the engine to match, in arrival order; the guard must be held
+    /// until the command has asked for the book lock
+    pub(crate) async fn admit(&self) -> ClobResult<MutexGuard<'_, ()>> {
+        let ahead = self.waiting.fetch_add(1, Ordering::SeqCst);
+        let _waiting = Waiting(&self.waiting);
+        match *self.state.borrow() {
+            Some((PauseMode::Reject, _)) => return Err(ClobError::EnginePaused),
+            Some((PauseMode::Buffer { max_buffered }, _)) if ahead >= max_buffered => {
+                return Err(ClobError::EnginePaused)
+            }
+            _ => {}
+        }
+
+        let intake = self.intake.lock().await;
+        let mut state = self.state.subscribe();
+        let pause = state
+            .wait_for(|pause| !matches!(pause, Some((PauseMode::Buffer { .. }, _))))
+            .await
+            .map_err(|_| ClobError::EnginePaused)?;
+        // Switched to rejecting while this command was held
+        if pause.is_some() {
+            return Err(ClobError::EnginePaused);
+        }
+        drop(pause);
+        Ok(intake)
+    }
+}
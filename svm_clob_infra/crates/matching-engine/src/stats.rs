@@ -0,0 +1,125 @@
+/// Rolling trade statistics
+///
+/// Trades are added to one-minute buckets of a ring covering the last 24
+/// hours, so recording a trade is constant time and reading a window folds at
+/// most 1440 buckets, however busy the market. A bucket is reused once the
+/// minute it held leaves the ring. Windows are read to the minute: a window
+/// ending at `now` covers the bucket of `now` and the ones before it.
+
+use svm_clob_types::market_data::TICKER_WINDOW_SECS;
+use svm_clob_types::{Notional, Price, Quantity, TradeExecution, WindowStats};
+
+/// Width of a bucket in seconds
+pub const STATS_BUCKET_SECS: i64 = 60;
+
+/// Buckets in the ring, enough for the longest window
+const BUCKETS: usize = (TICKER_WINDOW_SECS / STATS_BUCKET_SECS) as usize;
+
+/// Trades of one minute
+#[derive(Debug, Clone, Copy, Default)]
+struct Bucket {
+    /// Minute since the unix epoch the bucket holds
+    minute: i64,
+    trades: u64,
+    volume: u64,
+    quote_volume: u128,
+    /// Time and price of the earliest trade
+    open: (i64, u64),
+    high: u64,
+    low: u64,
+}
+
+/// Volume, trade count, VWAP and range of a market over rolling windows of
+/// up to 24 hours, maintained as trades are recorded
+#[derive(Debug, Clone)]
+pub struct RollingStats {
+    buckets: Vec<Bucket>,
+    /// Newest minute recorded
+    newest: Option<i64>,
+    /// Time and price of the latest trade, however old
+    last: Option<(i64, u64)>,
+}
+
+impl Default for RollingStats {
+    fn default() -> Self {
+        Self {
+            buckets: vec![Bucket::default(); BUCKETS],
+            newest: None,
+            last: None,
+        }
+    }
+}
+
+impl RollingStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a trade; trades may arrive out of order, and one older than the
+    /// ring is only considered for the last price
+    pub fn record(&mut self, trade: &TradeExecution) {
+        if self.last.map_or(true, |(timestamp, _)| trade.timestamp >= timestamp) {
+            self.last = Some((trade.timestamp, trade.price));
+        }
+
+        let minute = trade.timestamp.div_euclid(STATS_BUCKET_SECS);
+        if self.newest.is_some_and(|newest| minute <= newest - BUCKETS as i64) {
+            return;
+        }
+        self.newest = Some(self.newest.map_or(minute, |newest| newest.max(minute)));
+
+        let bucket = &mut self.buckets[minute.rem_euclid(BUCKETS as i64) as usize];
+        if bucket.trades == 0 || bucket.minute < minute {
+            *bucket = Bucket {
+                minute,
+                open: (trade.timestamp, trade.price),
+                high: trade.price,
+                low: trade.price,
+                ..Bucket::default()
+            };
+        }
+        bucket.trades += 1;
+        bucket.volume = bucket.volume.saturating_add(trade.quantity);
+        bucket.quote_volume = bucket.quote_volume.saturating_add(trade.notional().0);
+        if trade.timestamp < bucket.open.0 {
+            bucket.open = (trade.timestamp, trade.price);
+        }
+        bucket.high = bucket.high.max(trade.price);
+        bucket.low = bucket.low.min(trade.price);
+    }
+
+    /// Price of the latest trade recorded
+    pub fn last_price(&self) -> Option<u64> {
+        self.last.map(|(_, price)| price)
+    }
+
+    /// Trades of the `window_secs` (at most 24 hours) ending at `now`
+    pub fn window(&self, window_secs: i64, now: i64) -> WindowStats {
+        let window_secs = window_secs.clamp(STATS_BUCKET_SECS, TICKER_WINDOW_SECS);
+        let until = now.div_euclid(STATS_BUCKET_SECS);
+        // Buckets that left the ring behind the newest minute may still hold
+        // their old trades when `now` lags it
+        let evicted = self.newest.map_or(i64::MIN, |newest| newest - BUCKETS as i64);
+        let from = (until - window_secs / STATS_BUCKET_SECS).max(evicted);
+
+        let mut stats = WindowStats { window_secs, ..WindowStats::default() };
+        let mut open: Option<(i64, u64)> = None;
+        for bucket in self.buckets.iter().filter(|bucket| bucket.trades > 0 && bucket.minute > from && bucket.minute <= until) {
+            stats.trades += bucket.trades;
+            stats.volume.0 = stats.volume.0.saturating_add(bucket.volume);
+            stats.quote_volume.0 = stats.quote_volume.0.saturating_add(bucket.quote_volume);
+            if open.map_or(true, |(timestamp, _)| bucket.open.0 < timestamp) {
+                open = Some(bucket.open);
+            }
+            stats.high = Some(stats.high.map_or(Price(bucket.high), |high| high.max(Price(bucket.high))));
+            stats.low = Some(stats.low.map_or(Price(bucket.low), |low| low.min(Price(bucket.low))));
+        }
+        stats.open = open.map(|(_, price)| Price(price));
+        stats.vwap = vwap(stats.quote_volume, stats.volume);
+        stats
+    }
+}
+
+fn vwap(quote_volume: Notional, volume: Quantity) -> Option<Price> {
+    (volume.0 > 0).then(|| Price((quote_volume.0 / volume.0 as u128).min(u64::MAX as u128) as u64))
+}
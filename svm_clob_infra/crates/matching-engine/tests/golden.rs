@@ -0,0 +1,151 @@
+//! Golden vector tests for `MatchingEngine`
+//!
+//! Every scenario in `crates/core/golden` lists orders in arrival order and
+//! the fills and book they must produce. The program's tests settle the same
+//! fills on chain against the same orders, signed, so the two matching paths
+//! are held to one expected result. Orders are numbered from 1 in the order
+//! they are listed, traders from 0.
+
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use std::path::Path;
+use std::sync::Arc;
+use svm_clob_matching_engine::MatchingEngine;
+use svm_clob_storage::InMemoryStorage;
+use svm_clob_types::*;
+
+#[derive(Deserialize)]
+struct Scenario {
+    description: String,
+    orders: Vec<ScenarioOrder>,
+    fills: Vec<ScenarioFill>,
+    book: ScenarioBook,
+}
+
+#[derive(Deserialize)]
+struct ScenarioOrder {
+    trader: u8,
+    side: OrderSide,
+    order_type: OrderType,
+    price: u64,
+    quantity: u64,
+    #[serde(default = "good_till_cancelled")]
+    time_in_force: TimeInForce,
+    #[serde(default = "cancel_provide")]
+    self_trade_behavior: SelfTradeBehavior,
+    /// The engine refuses the order outright
+    #[serde(default)]
+    rejected: bool,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct ScenarioFill {
+    maker_order: u64,
+    taker_order: u64,
+    price: u64,
+    quantity: u64,
+}
+
+#[derive(Deserialize)]
+struct ScenarioBook {
+    bids: Vec<(u64, u64)>,
+    asks: Vec<(u64, u64)>,
+}
+
+fn good_till_cancelled() -> TimeInForce {
+    TimeInForce::GoodTillCancelled
+}
+
+fn cancel_provide() -> SelfTradeBehavior {
+    SelfTradeBehavior::CancelProvide
+}
+
+fn trader(index: u8) -> Pubkey {
+    Pubkey::new_from_array([index + 1; 32])
+}
+
+fn orderbook() -> OrderBook {
+    OrderBook {
+        authority: Pubkey::default(),
+        base_mint: Pubkey::default(),
+        quote_mint: Pubkey::default(),
+        tick_size: 1,
+        min_order_size: 1,
+        sequence_number: 0,
+        total_orders: 0,
+        best_bid: 0,
+        best_ask: u64::MAX,
+        total_volume: 0,
+        is_initialized: true,
+        is_paused: false,
+    }
+}
+
+fn scenarios() -> Vec<(String, Scenario)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../core/golden");
+    let mut paths: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no golden scenarios in {}", dir.display());
+    paths
+        .into_iter()
+        .map(|path| {
+            let scenario = serde_json::from_str(&std::fs::read_to_string(&path).unwrap())
+                .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+            (path.file_name().unwrap().to_string_lossy().into_owned(), scenario)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn engine_reproduces_golden_vectors() {
+    for (name, scenario) in scenarios() {
+        let engine = MatchingEngine::new(Arc::new(InMemoryStorage::new()), orderbook());
+        let mut fills = Vec::new();
+        for (index, order) in scenario.orders.iter().enumerate() {
+            let order_id = index as u64 + 1;
+            let placed = engine
+                .place_order(Order {
+                    order_id,
+                    market_id: MarketId::default(),
+                    owner: trader(order.trader),
+                    price: order.price,
+                    quantity: order.quantity,
+                    remaining_quantity: order.quantity,
+                    timestamp: 0,
+                    client_order_id: order_id,
+                    expiry_timestamp: 0,
+                    side: order.side,
+                    order_type: order.order_type,
+                    status: OrderStatus::Open,
+                    self_trade_behavior: order.self_trade_behavior,
+                    prevention_group_id: None,
+                    time_in_force: order.time_in_force,
+                    max_slippage_bps: None,
+                    filled_notional: Notional::default(),
+                    avg_fill_price: None,
+                })
+                .await;
+            match placed {
+                Ok(trades) => {
+                    assert!(!order.rejected, "{}: order {} was accepted", name, order_id);
+                    fills.extend(trades.iter().map(|trade| ScenarioFill {
+                        maker_order: trade.maker_order_id,
+                        taker_order: trade.taker_order_id,
+                        price: trade.price,
+                        quantity: trade.quantity,
+                    }));
+                }
+                Err(e) => assert!(order.rejected, "{}: order {} was rejected: {}", name, order_id, e),
+            }
+        }
+
+        assert_eq!(fills, scenario.fills, "{} ({}): fills", name, scenario.description);
+        let book = engine.get_order_book_snapshot().await.unwrap();
+        assert_eq!(book.bids, scenario.book.bids, "{} ({}): bids", name, scenario.description);
+        assert_eq!(book.asks, scenario.book.asks, "{} ({}): asks", name, scenario.description);
+    }
+}
@@ -0,0 +1,1360 @@
+//! Property tests for `MatchingEngine`
+//!
+//! Random place/cancel/modify sequences run through the engine and through a
+//! naive reference matcher. Both must accept and reject the same commands,
+//! produce the same fills in the same order, leave the same resting orders, and
+//! never create or destroy quantity. Pro-rata and size-time allocation are
+//! checked separately against one price level, market order slippage
+//! protection against a ladder of asks, market statuses against a position
+//! built by one trade, the resting lifetime against orders placed at
+//! random times, the rolling stats against the trades they were fed, the
+//! metrics sample against the orders resting near the mid, placements
+//! buffered by a pause against the same placements run unpaused, market
+//! maker protection against a maker whose asks are lifted one by one,
+//! prevention groups against two accounts crossing each other, trade
+//! busts and corrections against the settlement they move back, and a
+//! modification a pre-trade hook rejects against the queue it leaves.
+
+use proptest::prelude::*;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use svm_clob_matching_engine::stats::STATS_BUCKET_SECS;
+use svm_clob_matching_engine::{book_at, MatchingEngine, PreTradeContext, PreTradeHook, RollingStats};
+use svm_clob_storage::{InMemoryStorage, Storage};
+use svm_clob_types::*;
+
+const TICK: u64 = 10;
+const MIN_SIZE: u64 = 5;
+
+#[derive(Debug, Clone)]
+enum Op {
+    Place {
+        owner: u8,
+        side: OrderSide,
+        order_type: OrderType,
+        time_in_force: TimeInForce,
+        self_trade_behavior: SelfTradeBehavior,
+        price: u64,
+        quantity: u64,
+    },
+    Cancel { index: usize },
+    Modify { index: usize, price: Option<u64>, quantity: Option<u64> },
+}
+
+/// Mostly tick-aligned prices around a narrow range so orders cross often
+fn price() -> impl Strategy<Value = u64> {
+    prop_oneof![
+        9 => (1..=12u64).prop_map(|ticks| ticks * TICK),
+        1 => 1..=120u64,
+    ]
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let side = prop_oneof![Just(OrderSide::Bid), Just(OrderSide::Ask)];
+    let order_type = prop_oneof![
+        6 => Just(OrderType::Limit),
+        2 => Just(OrderType::PostOnly),
+        1 => Just(OrderType::Market),
+    ];
+    let time_in_force = prop_oneof![
+        4 => Just(TimeInForce::GoodTillCancelled),
+        1 => Just(TimeInForce::ImmediateOrCancel),
+        1 => Just(TimeInForce::FillOrKill),
+    ];
+    let self_trade_behavior = prop_oneof![
+        Just(SelfTradeBehavior::DecrementAndCancel),
+        Just(SelfTradeBehavior::CancelProvide),
+        Just(SelfTradeBehavior::CancelTake),
+        Just(SelfTradeBehavior::CancelBoth),
+    ];
+    let place = (0..3u8, side, order_type, time_in_force, self_trade_behavior, price(), 1..=40u64).prop_map(
+        |(owner, side, order_type, time_in_force, self_trade_behavior, price, quantity)| Op::Place {
+            owner,
+            side,
+            order_type,
+            time_in_force,
+            self_trade_behavior,
+            price,
+            quantity,
+        },
+    );
+    prop_oneof![
+        6 => place,
+        1 => any::<usize>().prop_map(|index| Op::Cancel { index }),
+        1 => (any::<usize>(), prop::option::of(price()), prop::option::of(1..=40u64))
+            .prop_map(|(index, price, quantity)| Op::Modify { index, price, quantity }),
+    ]
+}
+
+/// (maker order, taker order, price, quantity)
+type Fill = (u64, u64, u64, u64);
+
+/// Naive matcher: a flat list of resting orders in arrival order
+#[derive(Default)]
+struct Reference {
+    resting: Vec<Order>,
+}
+
+impl Reference {
+    fn validate(order: &Order) -> Result<(), ()> {
+        if order.quantity < MIN_SIZE || order.price % TICK != 0 {
+            return Err(());
+        }
+        Ok(())
+    }
+
+    fn crosses(&self, order: &Order) -> bool {
+        self.resting.iter().any(|resting| match (order.side, resting.side) {
+            (OrderSide::Bid, OrderSide::Ask) => order.price >= resting.price,
+            (OrderSide::Ask, OrderSide::Bid) => order.price <= resting.price,
+            _ => false,
+        })
+    }
+
+    /// Opposite-side orders the taker may trade with, in matching priority
+    fn candidates(&self, order: &Order) -> Vec<Order> {
+        let mut candidates: Vec<(usize, Order)> = self
+            .resting
+            .iter()
+            .cloned()
+            .enumerate()
+            .filter(|(_, resting)| resting.side != order.side)
+            .filter(|(_, resting)| {
+                order.order_type == OrderType::Market
+                    || match order.side {
+                        OrderSide::Bid => resting.price <= order.price,
+                        OrderSide::Ask => resting.price >= order.price,
+                    }
+            })
+            .collect();
+        candidates.sort_by_key(|(arrival, resting)| match order.side {
+            OrderSide::Bid => (resting.price, *arrival),
+            OrderSide::Ask => (u64::MAX - resting.price, *arrival),
+        });
+        candidates.into_iter().map(|(_, resting)| resting).collect()
+    }
+
+    /// Whether self-trade prevention cancels the taker on meeting its own order
+    fn cancels_taker(taker: &Order, remaining: u64, maker: &Order) -> bool {
+        match taker.self_trade_behavior {
+            SelfTradeBehavior::CancelProvide => false,
+            SelfTradeBehavior::CancelTake | SelfTradeBehavior::CancelBoth => true,
+            SelfTradeBehavior::DecrementAndCancel => remaining <= maker.remaining_quantity,
+        }
+    }
+
+    /// Whether self-trade prevention removes the resting order
+    fn cancels_maker(taker: &Order, remaining: u64, maker: &Order) -> bool {
+        match taker.self_trade_behavior {
+            SelfTradeBehavior::CancelProvide | SelfTradeBehavior::CancelBoth => true,
+            SelfTradeBehavior::CancelTake => false,
+            SelfTradeBehavior::DecrementAndCancel => remaining > maker.remaining_quantity,
+        }
+    }
+
+    fn remove(&mut self, order_id: u64) -> Option<Order> {
+        let index = self.resting.iter().position(|resting| resting.order_id == order_id)?;
+        Some(self.resting.remove(index))
+    }
+
+    /// Returns fills and the taker's final status, or `Err` when rejected
+    fn place(&mut self, mut order: Order) -> Result<(Vec<Fill>, OrderStatus), ()> {
+        Self::validate(&order)?;
+
+        if order.order_type == OrderType::PostOnly {
+            if self.crosses(&order) {
+                return Err(());
+            }
+            self.resting.push(order);
+            return Ok((Vec::new(), OrderStatus::Open));
+        }
+
+        let candidates = self.candidates(&order);
+
+        if order.time_in_force == TimeInForce::FillOrKill {
+            let mut needed = order.quantity;
+            for maker in &candidates {
+                if needed == 0 {
+                    break;
+                }
+                if maker.owner == order.owner {
+                    if Self::cancels_taker(&order, needed, maker) {
+                        return Ok((Vec::new(), OrderStatus::Cancelled));
+                    }
+                    continue;
+                }
+                needed -= needed.min(maker.remaining_quantity);
+            }
+            if needed > 0 {
+                return Ok((Vec::new(), OrderStatus::Cancelled));
+            }
+        }
+
+        let mut fills = Vec::new();
+        for maker in candidates {
+            if order.remaining_quantity == 0 {
+                break;
+            }
+            if maker.owner == order.owner {
+                if Self::cancels_maker(&order, order.remaining_quantity, &maker) {
+                    self.remove(maker.order_id);
+                }
+                if Self::cancels_taker(&order, order.remaining_quantity, &maker) {
+                    return Ok((fills, OrderStatus::Cancelled));
+                }
+                continue;
+            }
+
+            let quantity = order.remaining_quantity.min(maker.remaining_quantity);
+            fills.push((maker.order_id, order.order_id, maker.price, quantity));
+            order.remaining_quantity -= quantity;
+
+            let resting = self.resting.iter_mut().find(|resting| resting.order_id == maker.order_id).unwrap();
+            resting.remaining_quantity -= quantity;
+            resting.status = OrderStatus::PartiallyFilled;
+            if resting.remaining_quantity == 0 {
+                self.remove(maker.order_id);
+            }
+        }
+
+        if order.remaining_quantity == 0 {
+            return Ok((fills, OrderStatus::Filled));
+        }
+        if order.order_type == OrderType::Market || order.time_in_force != TimeInForce::GoodTillCancelled {
+            return Ok((fills, OrderStatus::Cancelled));
+        }
+
+        order.status = if order.remaining_quantity < order.quantity {
+            OrderStatus::PartiallyFilled
+        } else {
+            OrderStatus::Open
+        };
+        let status = order.status;
+        self.resting.push(order);
+        Ok((fills, status))
+    }
+
+    fn modify(&mut self, order_id: u64, price: Option<u64>, quantity: Option<u64>) -> Result<Order, ()> {
+        let original = self.resting.iter().find(|resting| resting.order_id == order_id).cloned().ok_or(())?;
+
+        let mut modified = original.clone();
+        modified.price = price.unwrap_or(original.price);
+        modified.quantity = quantity.unwrap_or(original.quantity);
+        let filled = original.quantity - original.remaining_quantity;
+        if modified.quantity <= filled {
+            return Err(());
+        }
+        modified.remaining_quantity = modified.quantity - filled;
+        Self::validate(&modified)?;
+        if self.crosses(&modified) {
+            return Err(());
+        }
+
+        self.remove(order_id);
+        Ok(modified)
+    }
+}
+
+fn owner(index: u8) -> Pubkey {
+    Pubkey::new_from_array([index + 1; 32])
+}
+
+fn new_order(order_id: u64, op: &Op) -> Option<Order> {
+    let Op::Place { owner: index, side, order_type, time_in_force, self_trade_behavior, price, quantity } = *op else {
+        return None;
+    };
+    Some(Order {
+        order_id,
+        market_id: MarketId::default(),
+        owner: owner(index),
+        price: if order_type == OrderType::Market { 0 } else { price },
+        quantity,
+        remaining_quantity: quantity,
+        timestamp: 0,
+        client_order_id: order_id,
+        expiry_timestamp: 0,
+        side,
+        order_type,
+        status: OrderStatus::Open,
+        self_trade_behavior,
+        prevention_group_id: None,
+        time_in_force,
+        max_slippage_bps: None,
+        filled_notional: Notional::default(),
+        avg_fill_price: None,
+    })
+}
+
+fn orderbook() -> OrderBook {
+    OrderBook {
+        authority: Pubkey::default(),
+        base_mint: Pubkey::default(),
+        quote_mint: Pubkey::default(),
+        tick_size: TICK,
+        min_order_size: MIN_SIZE,
+        sequence_number: 0,
+        total_orders: 0,
+        best_bid: 0,
+        best_ask: u64::MAX,
+        total_volume: 0,
+        is_initialized: true,
+        is_paused: false,
+    }
+}
+
+async fn check(ops: Vec<Op>) -> Result<(), TestCaseError> {
+    let storage = Arc::new(InMemoryStorage::new());
+    let engine = MatchingEngine::new(storage.clone(), orderbook());
+    let mut reference = Reference::default();
+
+    // Every accepted order: quantity and fills carried over from the order it replaced
+    let mut accepted: HashMap<u64, (u64, u64)> = HashMap::new();
+    let mut ids: Vec<u64> = Vec::new();
+    let mut all_fills: Vec<Fill> = Vec::new();
+    let mut last_trade_id = 0;
+    let mut last_order_id = 0;
+    // Book after every command, with a checkpoint stored halfway
+    let mut history: Vec<OrderBookSnapshot> = Vec::new();
+    let checkpoint_at = ops.len() / 2;
+
+    for op in ops {
+        match &op {
+            Op::Place { .. } => {
+                let order = new_order(engine.allocate_order_id().await.unwrap(), &op).unwrap();
+                prop_assert!(order.order_id > last_order_id, "order IDs must increase");
+                last_order_id = order.order_id;
+
+                let expected = reference.place(order.clone());
+                let actual = engine.place_order(order.clone()).await;
+                match (expected, actual) {
+                    (Ok((expected_fills, expected_status)), Ok(trades)) => {
+                        let fills: Vec<Fill> = trades
+                            .iter()
+                            .map(|t| (t.maker_order_id, t.taker_order_id, t.price, t.quantity))
+                            .collect();
+                        prop_assert_eq!(&fills, &expected_fills, "fills of {:?}", op);
+                        for trade in &trades {
+                            prop_assert!(trade.trade_id > last_trade_id, "trade IDs must increase");
+                            last_trade_id = trade.trade_id;
+                        }
+                        let stored = storage.get_order(order.order_id).await.unwrap().unwrap();
+                        prop_assert_eq!(stored.status, expected_status, "status of {:?}", op);
+
+                        accepted.insert(order.order_id, (order.quantity, 0));
+                        ids.push(order.order_id);
+                        all_fills.extend(fills);
+                    }
+                    (Err(()), Err(_)) => {}
+                    (expected, actual) => {
+                        prop_assert!(false, "{:?}: reference {:?}, engine {:?}", op, expected.is_ok(), actual);
+                    }
+                }
+            }
+            Op::Cancel { index } => {
+                if ids.is_empty() {
+                    continue;
+                }
+                let order_id = ids[index % ids.len()];
+                let expected = reference.remove(order_id);
+                let actual = engine.cancel_order(order_id).await;
+                prop_assert_eq!(expected.is_some(), actual.is_ok(), "cancel {}", order_id);
+                if let (Some(expected), Ok(actual)) = (expected, actual) {
+                    prop_assert_eq!(expected.remaining_quantity, actual.remaining_quantity);
+                }
+            }
+            Op::Modify { index, price, quantity } => {
+                if ids.is_empty() {
+                    continue;
+                }
+                let order_id = ids[index % ids.len()];
+                let expected = reference.modify(order_id, *price, *quantity);
+                let actual = engine.modify_order(order_id, *price, *quantity).await;
+                match (expected, actual) {
+                    (Ok(mut expected), Ok(actual)) => {
+                        prop_assert_eq!(actual.price, expected.price);
+                        prop_assert_eq!(actual.quantity, expected.quantity);
+                        prop_assert_eq!(actual.remaining_quantity, expected.remaining_quantity);
+
+                        // The engine assigns the replacement ID
+                        prop_assert!(actual.order_id > last_order_id, "order IDs must increase");
+                        last_order_id = actual.order_id;
+                        expected.order_id = actual.order_id;
+                        reference.resting.push(expected);
+
+                        let filled = actual.quantity - actual.remaining_quantity;
+                        accepted.insert(actual.order_id, (actual.quantity, filled));
+                        ids.push(actual.order_id);
+                    }
+                    (Err(()), Err(_)) => {}
+                    (expected, actual) => {
+                        prop_assert!(false, "{:?}: reference {:?}, engine {:?}", op, expected.is_ok(), actual);
+                    }
+                }
+            }
+        }
+
+        // Resting orders agree
+        let mut open: Vec<(u64, u64)> = engine
+            .get_open_orders()
+            .await
+            .iter()
+            .map(|o| (o.order_id, o.remaining_quantity))
+            .collect();
+        open.sort_unstable();
+        let mut expected: Vec<(u64, u64)> = reference.resting.iter().map(|o| (o.order_id, o.remaining_quantity)).collect();
+        expected.sort_unstable();
+        prop_assert_eq!(&open, &expected);
+
+        // Levels aggregate the resting orders and the book is never crossed
+        let snapshot = engine.get_order_book_snapshot().await.unwrap();
+        let bid_total: u64 = snapshot.bids.iter().map(|(_, quantity)| quantity).sum();
+        let ask_total: u64 = snapshot.asks.iter().map(|(_, quantity)| quantity).sum();
+        let resting_bids: u64 = reference.resting.iter().filter(|o| o.side == OrderSide::Bid).map(|o| o.remaining_quantity).sum();
+        let resting_asks: u64 = reference.resting.iter().filter(|o| o.side == OrderSide::Ask).map(|o| o.remaining_quantity).sum();
+        prop_assert_eq!(bid_total, resting_bids);
+        prop_assert_eq!(ask_total, resting_asks);
+        if let (Some((best_bid, _)), Some((best_ask, _))) = (snapshot.bids.first(), snapshot.asks.first()) {
+            prop_assert!(best_bid < best_ask, "crossed book: {} >= {}", best_bid, best_ask);
+        }
+
+        history.push(snapshot);
+        if history.len() == checkpoint_at {
+            storage.store_book_checkpoint(&engine.checkpoint().await).await.unwrap();
+        }
+    }
+
+    // Every past book is rebuilt at its sequence, from the journal or the checkpoint
+    for book in &history {
+        let rebuilt = book_at(&*storage, &orderbook(), book.market_id, AllocationMode::default(), book.sequence_number)
+            .await
+            .unwrap();
+        prop_assert_eq!(rebuilt.sequence_number, book.sequence_number);
+        prop_assert_eq!(&rebuilt.bids, &book.bids, "bids at sequence {}", book.sequence_number);
+        prop_assert_eq!(&rebuilt.asks, &book.asks, "asks at sequence {}", book.sequence_number);
+    }
+
+    // Conservation: nothing fills or rests beyond what was ordered
+    let resting: HashMap<u64, u64> = reference.resting.iter().map(|o| (o.order_id, o.remaining_quantity)).collect();
+    for (order_id, (quantity, carried)) in &accepted {
+        let filled: u64 = all_fills
+            .iter()
+            .filter(|(maker, taker, _, _)| maker == order_id || taker == order_id)
+            .map(|(_, _, _, quantity)| quantity)
+            .sum();
+        let remaining = resting.get(order_id).copied().unwrap_or(0);
+        prop_assert!(
+            carried + filled + remaining <= *quantity,
+            "order {} over-allocated: {} carried + {} filled + {} resting > {}",
+            order_id, carried, filled, remaining, quantity
+        );
+
+        // Stored orders average their own fills; replacements also carry the
+        // fills of the order they replaced
+        if *carried == 0 {
+            let notional: u128 = all_fills
+                .iter()
+                .filter(|(maker, taker, _, _)| maker == order_id || taker == order_id)
+                .map(|(_, _, price, quantity)| *price as u128 * *quantity as u128)
+                .sum();
+            let stored = storage.get_order(*order_id).await.unwrap().unwrap();
+            prop_assert_eq!(stored.filled_quantity(), filled, "filled quantity of order {}", order_id);
+            prop_assert_eq!(stored.filled_notional, Notional(notional), "filled notional of order {}", order_id);
+            prop_assert_eq!(
+                stored.avg_fill_price,
+                (filled > 0).then(|| (notional / filled as u128) as u64),
+                "average fill price of order {}", order_id
+            );
+        }
+    }
+
+    // A restarted engine rebuilds the same book, priority included, from
+    // storage, and hands out no order ID handed out before
+    let mut restarted = MatchingEngine::new(storage.clone(), engine.orderbook_config().clone());
+    restarted.resume_order_ids(storage.get_reserved_order_ids().await.unwrap());
+    restarted.bootstrap().await.unwrap();
+    let mut restored: Vec<(u64, u64)> = restarted
+        .get_open_orders()
+        .await
+        .iter()
+        .map(|o| (o.order_id, o.remaining_quantity))
+        .collect();
+    restored.sort_unstable();
+    let mut expected: Vec<(u64, u64)> = reference.resting.iter().map(|o| (o.order_id, o.remaining_quantity)).collect();
+    expected.sort_unstable();
+    prop_assert_eq!(&restored, &expected);
+
+    for side in [OrderSide::Bid, OrderSide::Ask] {
+        let sweep = |order_id: u64| Order {
+            order_id,
+            market_id: MarketId::default(),
+            owner: owner(u8::MAX - 1),
+            price: 0,
+            quantity: u64::MAX / 2,
+            remaining_quantity: u64::MAX / 2,
+            timestamp: 0,
+            client_order_id: order_id,
+            expiry_timestamp: 0,
+            side,
+            order_type: OrderType::Market,
+            status: OrderStatus::Open,
+            self_trade_behavior: SelfTradeBehavior::CancelProvide,
+            prevention_group_id: None,
+            time_in_force: TimeInForce::ImmediateOrCancel,
+            max_slippage_bps: None,
+            filled_notional: Notional::default(),
+            avg_fill_price: None,
+        };
+        let key = |trade: &TradeExecution| (trade.maker_order_id, trade.price, trade.quantity);
+        let original_id = restarted.allocate_order_id().await.unwrap();
+        let rebuilt_id = restarted.allocate_order_id().await.unwrap();
+        prop_assert!(original_id > last_order_id, "order ID {} reused after restart", original_id);
+        last_order_id = rebuilt_id;
+        let original: Vec<_> = engine.place_order(sweep(original_id)).await.unwrap().iter().map(key).collect();
+        let rebuilt: Vec<_> = restarted.place_order(sweep(rebuilt_id)).await.unwrap().iter().map(key).collect();
+        prop_assert_eq!(original, rebuilt);
+    }
+
+    Ok(())
+}
+
+/// Asks of distinct owners resting at one price, then a bid taking part or
+/// all of the level: fills follow the allocation mode and add up to the taker
+async fn check_allocation(allocation: AllocationMode, makers: Vec<u64>, taker: u64) -> Result<(), TestCaseError> {
+    let mut engine = MatchingEngine::new(Arc::new(InMemoryStorage::new()), orderbook());
+    engine.set_allocation(allocation);
+    let place = |owner: u8, side: OrderSide, quantity: u64| Op::Place {
+        owner,
+        side,
+        order_type: OrderType::Limit,
+        time_in_force: TimeInForce::GoodTillCancelled,
+        self_trade_behavior: SelfTradeBehavior::CancelProvide,
+        price: 5 * TICK,
+        quantity,
+    };
+    for (index, &quantity) in makers.iter().enumerate() {
+        let order = new_order(index as u64 + 1, &place(index as u8, OrderSide::Ask, quantity)).unwrap();
+        engine.place_order(order).await.unwrap();
+    }
+    let order = new_order(100, &place(100, OrderSide::Bid, taker)).unwrap();
+    let trades = engine.place_order(order).await.unwrap();
+
+    let total: u64 = makers.iter().sum();
+    let executed = taker.min(total);
+    prop_assert_eq!(trades.iter().map(|t| t.quantity).sum::<u64>(), executed);
+
+    let mut filled = vec![0u64; makers.len()];
+    for trade in &trades {
+        filled[trade.maker_order_id as usize - 1] += trade.quantity;
+    }
+    match allocation {
+        AllocationMode::ProRata => {
+            for (index, (&quantity, &got)) in makers.iter().zip(&filled).enumerate() {
+                let share = executed * quantity / total;
+                prop_assert!(
+                    got == share || got == share + 1,
+                    "maker {} of {} filled {}, pro-rata share {}", index, quantity, got, share
+                );
+            }
+        }
+        AllocationMode::PriceTime | AllocationMode::SizeTime => {
+            // Makers fill completely in priority order until the taker runs out
+            let mut priority: Vec<usize> = (0..makers.len()).collect();
+            if allocation == AllocationMode::SizeTime {
+                priority.sort_by(|&a, &b| makers[b].cmp(&makers[a]));
+            }
+            let mut left = executed;
+            for index in priority {
+                let expected = left.min(makers[index]);
+                prop_assert_eq!(filled[index], expected, "maker {}", index);
+                left -= expected;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A ladder of asks, one per level from `levels`, then a market bid with a
+/// slippage tolerance: it is rejected, leaving book and journal untouched,
+/// exactly when its average fill would be further than the tolerance from the
+/// best ask
+async fn check_slippage(levels: Vec<u64>, taker: u64, max_slippage_bps: u32) -> Result<(), TestCaseError> {
+    let engine = MatchingEngine::new(Arc::new(InMemoryStorage::new()), orderbook());
+    for (index, &quantity) in levels.iter().enumerate() {
+        let order = new_order(
+            index as u64 + 1,
+            &Op::Place {
+                owner: index as u8,
+                side: OrderSide::Ask,
+                order_type: OrderType::Limit,
+                time_in_force: TimeInForce::GoodTillCancelled,
+                self_trade_behavior: SelfTradeBehavior::CancelProvide,
+                price: (index as u64 + 10) * TICK,
+                quantity,
+            },
+        )
+        .unwrap();
+        engine.place_order(order).await.unwrap();
+    }
+
+    // Reference impact price over the ladder
+    let mut left = taker;
+    let (mut filled, mut notional) = (0u128, 0u128);
+    for (index, &quantity) in levels.iter().enumerate() {
+        let quantity = left.min(quantity);
+        filled += quantity as u128;
+        notional += (index as u128 + 10) * TICK as u128 * quantity as u128;
+        left -= quantity;
+    }
+    let best = 10 * TICK as u128;
+    let slippage_bps = (notional / filled - best) * 10_000 / best;
+
+    let order = Order::builder()
+        .order_id(100)
+        .owner(owner(100))
+        .bid()
+        .market()
+        .qty(taker)
+        .ioc()
+        .max_slippage_bps(max_slippage_bps)
+        .timestamp(0)
+        .build()
+        .unwrap();
+    let position = engine.journal_position();
+    let resting = engine.get_open_orders().await.len();
+    match engine.place_order(order).await {
+        Ok(trades) => {
+            prop_assert!(slippage_bps <= max_slippage_bps as u128, "accepted at {} bps", slippage_bps);
+            prop_assert_eq!(trades.iter().map(|t| t.quantity as u128).sum::<u128>(), filled);
+        }
+        Err(ClobError::SlippageExceeded(_)) => {
+            prop_assert!(slippage_bps > max_slippage_bps as u128, "rejected at {} bps", slippage_bps);
+            prop_assert_eq!(engine.journal_position(), position);
+            prop_assert_eq!(engine.get_open_orders().await.len(), resting);
+        }
+        Err(e) => prop_assert!(false, "unexpected rejection: {}", e),
+    }
+    Ok(())
+}
+
+/// Owner 0 buys `long` from owner 1, then offers `asks` one by one under
+/// reduce-only: each is admitted exactly while the offered total still at
+/// most closes the position. Cancel-only and paused then gate the commands
+/// left, and the status outlives a restart.
+async fn check_market_status(long: u64, asks: Vec<u64>) -> Result<(), TestCaseError> {
+    let storage = Arc::new(InMemoryStorage::new());
+    let mut engine = MatchingEngine::new(storage.clone(), orderbook());
+    // Replacement IDs clear of the IDs placed below
+    engine.resume_order_ids(1_000);
+    let limit = |order_id: u64, owner_index: u8, side: OrderSide, price: u64, quantity: u64| {
+        Order::builder()
+            .order_id(order_id)
+            .owner(owner(owner_index))
+            .side(side)
+            .limit(price)
+            .qty(quantity)
+            .gtc()
+            .timestamp(0)
+            .build()
+            .unwrap()
+    };
+    engine.place_order(limit(1, 1, OrderSide::Ask, 5 * TICK, long)).await.unwrap();
+    engine.place_order(limit(2, 0, OrderSide::Bid, 5 * TICK, long)).await.unwrap();
+
+    prop_assert_eq!(engine.set_status(MarketStatus::ReduceOnly).await.unwrap(), MarketStatus::Active);
+    let mut offered = 0;
+    let mut resting = Vec::new();
+    for (index, &quantity) in asks.iter().enumerate() {
+        let order_id = 10 + index as u64;
+        match engine.place_order(limit(order_id, 0, OrderSide::Ask, 9 * TICK, quantity)).await {
+            Ok(trades) => {
+                prop_assert!(offered + quantity <= long, "{} offered past a position of {}", offered + quantity, long);
+                prop_assert!(trades.is_empty());
+                offered += quantity;
+                resting.push((order_id, quantity));
+            }
+            Err(ClobError::ReduceOnly(_)) => {
+                prop_assert!(offered + quantity > long, "{} refused within a position of {}", offered + quantity, long)
+            }
+            Err(e) => prop_assert!(false, "unexpected rejection: {}", e),
+        }
+    }
+    // Buying more would grow the position
+    let bid = engine.place_order(limit(100, 0, OrderSide::Bid, TICK, MIN_SIZE)).await;
+    prop_assert!(matches!(bid, Err(ClobError::ReduceOnly(_))), "bid admitted: {:?}", bid);
+
+    engine.set_status(MarketStatus::CancelOnly).await.unwrap();
+    let placed = engine.place_order(limit(101, 2, OrderSide::Bid, TICK, MIN_SIZE)).await;
+    prop_assert!(matches!(placed, Err(ClobError::MarketCancelOnly)), "placement admitted: {:?}", placed);
+    if let Some(&(order_id, quantity)) = resting.first() {
+        let grown = engine.modify_order(order_id, Some(8 * TICK), None).await;
+        prop_assert!(matches!(grown, Err(ClobError::MarketCancelOnly)), "reprice admitted: {:?}", grown);
+        let kept = engine.modify_order(order_id, None, Some(quantity)).await.unwrap();
+        engine.cancel_order(kept.order_id).await.unwrap();
+    }
+
+    engine.set_status(MarketStatus::Paused).await.unwrap();
+    if let Some(&(order_id, _)) = resting.get(1) {
+        prop_assert!(matches!(engine.cancel_order(order_id).await, Err(ClobError::OrderbookPaused)));
+    }
+    let restarted = MatchingEngine::new(storage, orderbook());
+    restarted.bootstrap().await.unwrap();
+    prop_assert_eq!(restarted.status(), MarketStatus::Paused);
+    Ok(())
+}
+
+/// Far enough ahead that every good-till-time expiry is still in the future
+const EPOCH: i64 = 4_000_000_000;
+
+/// Bids placed at `placed_at` seconds after `EPOCH`, good-till-time for the
+/// given seconds when set, are swept at `sweep_at`: exactly the orders past
+/// their own expiry or the market's lifetime leave, each with its reason.
+async fn check_resting_lifetime(
+    orders: Vec<(i64, Option<i64>)>,
+    max_resting_secs: i64,
+    scope: LifetimeScope,
+    sweep_at: i64,
+) -> Result<(), TestCaseError> {
+    let mut engine = MatchingEngine::new(Arc::new(InMemoryStorage::new()), orderbook());
+    let lifetime = RestingLifetime { max_resting_secs, scope };
+    engine.set_resting_lifetime(Some(lifetime));
+
+    let mut expected = HashMap::new();
+    for (index, &(placed_at, good_for)) in orders.iter().enumerate() {
+        let order_id = index as u64 + 1;
+        let builder = Order::builder()
+            .order_id(order_id)
+            .owner(owner(0))
+            .side(OrderSide::Bid)
+            .limit(TICK)
+            .qty(MIN_SIZE)
+            .timestamp(EPOCH + placed_at);
+        let order = match good_for {
+            Some(seconds) => builder.good_till(EPOCH + placed_at + seconds),
+            None => builder.gtc(),
+        }
+        .build()
+        .unwrap();
+        let reason = if order.time_in_force == TimeInForce::GoodTillTime && order.expiry_timestamp <= EPOCH + sweep_at {
+            Some(ExpiryReason::GoodTillTime)
+        } else if lifetime.deadline(&order).is_some_and(|deadline| deadline <= EPOCH + sweep_at) {
+            Some(ExpiryReason::MaxLifetime)
+        } else {
+            None
+        };
+        expected.insert(order_id, reason);
+        engine.place_order(order).await.unwrap();
+    }
+
+    let expired = engine.expire_orders(EPOCH + sweep_at).await.unwrap();
+    for (order, reason) in &expired {
+        prop_assert_eq!(order.status, OrderStatus::Expired);
+        prop_assert_eq!(expected[&order.order_id], Some(*reason), "order {}", order.order_id);
+    }
+    let remaining = engine.get_open_orders().await;
+    prop_assert_eq!(expired.len() + remaining.len(), orders.len());
+    for order in &remaining {
+        prop_assert_eq!(expected[&order.order_id], None, "order {} outlived its lifetime", order.order_id);
+    }
+    Ok(())
+}
+
+/// Trades at `(seconds after EPOCH, price, quantity)`, recorded in the given
+/// order, read back over `window_secs` ending `now` seconds after EPOCH: the
+/// window holds exactly the trades of its minutes that the ring still keeps
+fn check_rolling_stats(trades: Vec<(i64, u64, u64)>, now: i64, window_secs: i64) -> Result<(), TestCaseError> {
+    let trades: Vec<TradeExecution> = trades
+        .into_iter()
+        .enumerate()
+        .map(|(index, (at, price, quantity))| TradeExecution {
+            trade_id: index as u64 + 1,
+            market_id: MarketId::default(),
+            maker_order_id: 1,
+            taker_order_id: 2,
+            price,
+            quantity,
+            timestamp: EPOCH + at,
+            maker_side: OrderSide::Ask,
+            received_at_us: 0,
+            matched_at_us: 0,
+            version: 0,
+            status: TradeStatus::Active,
+        })
+        .collect();
+    let mut stats = RollingStats::new();
+    for trade in &trades {
+        stats.record(trade);
+    }
+
+    let minute = |timestamp: i64| timestamp.div_euclid(STATS_BUCKET_SECS);
+    let newest = trades.iter().map(|trade| minute(trade.timestamp)).max().unwrap();
+    let until = minute(EPOCH + now);
+    let window: Vec<&TradeExecution> = trades
+        .iter()
+        .filter(|trade| {
+            let minute = minute(trade.timestamp);
+            minute > newest - 24 * 60 && minute > until - window_secs / STATS_BUCKET_SECS && minute <= until
+        })
+        .collect();
+
+    let read = stats.window(window_secs, EPOCH + now);
+    let volume: u64 = window.iter().map(|trade| trade.quantity).sum();
+    let quote_volume: u128 = window.iter().map(|trade| trade.notional().0).sum();
+    prop_assert_eq!(read.trades, window.len() as u64);
+    prop_assert_eq!(read.volume, Quantity(volume));
+    prop_assert_eq!(read.quote_volume, Notional(quote_volume));
+    prop_assert_eq!(read.vwap, (volume > 0).then(|| Price((quote_volume / volume as u128) as u64)));
+    prop_assert_eq!(read.open, window.iter().min_by_key(|trade| trade.timestamp).map(|trade| Price(trade.price)));
+    prop_assert_eq!(read.high, window.iter().map(|trade| Price(trade.price)).max());
+    prop_assert_eq!(read.low, window.iter().map(|trade| Price(trade.price)).min());
+    prop_assert_eq!(
+        stats.last_price(),
+        trades.iter().max_by_key(|trade| trade.timestamp).map(|trade| trade.price)
+    );
+    Ok(())
+}
+
+/// Bids and asks `(ticks from 10_000 ticks, quantity)` rest, then the first
+/// `cancels` of them are cancelled: a metrics sample counts exactly those
+/// commands, and each band holds the resting quantity priced within it
+async fn check_market_metrics(bids: Vec<(u64, u64)>, asks: Vec<(u64, u64)>, cancels: usize) -> Result<(), TestCaseError> {
+    let engine = MatchingEngine::new(Arc::new(InMemoryStorage::new()), orderbook());
+    let orders: Vec<(OrderSide, u64, u64)> = bids
+        .iter()
+        .map(|&(ticks, quantity)| (OrderSide::Bid, (10_000 - ticks) * TICK, quantity))
+        .chain(asks.iter().map(|&(ticks, quantity)| (OrderSide::Ask, (10_000 + ticks) * TICK, quantity)))
+        .collect();
+    for (index, &(side, price, quantity)) in orders.iter().enumerate() {
+        let order = new_order(
+            index as u64 + 1,
+            &Op::Place {
+                owner: index as u8,
+                side,
+                order_type: OrderType::Limit,
+                time_in_force: TimeInForce::GoodTillCancelled,
+                self_trade_behavior: SelfTradeBehavior::CancelProvide,
+                price,
+                quantity,
+            },
+        )
+        .unwrap();
+        engine.place_order(order).await.unwrap();
+    }
+    let cancels = cancels.min(orders.len());
+    for order_id in 1..=cancels as u64 {
+        engine.cancel_order(order_id).await.unwrap();
+    }
+
+    let sample = engine.sample_metrics().await.unwrap();
+    prop_assert_eq!(
+        sample.flow,
+        OrderFlow { orders_placed: orders.len() as u64, orders_cancelled: cancels as u64, trades: 0 }
+    );
+    let resting = &orders[cancels..];
+    let best_bid = resting.iter().filter(|order| order.0 == OrderSide::Bid).map(|order| order.1).max();
+    let best_ask = resting.iter().filter(|order| order.0 == OrderSide::Ask).map(|order| order.1).min();
+    prop_assert_eq!(sample.best_bid, best_bid.map(Price));
+    prop_assert_eq!(sample.best_ask, best_ask.map(Price));
+    match (best_bid, best_ask) {
+        (Some(bid), Some(ask)) => {
+            let mid = (bid + ask) as f64 / 2.0;
+            prop_assert_eq!(sample.depth.len(), market_data::DEPTH_BANDS_BPS.len());
+            for band in &sample.depth {
+                let reach = mid * band.band_bps as f64 / 10_000.0;
+                let within = |side: OrderSide| -> u64 {
+                    resting
+                        .iter()
+                        .filter(|&&(order_side, price, _)| order_side == side && (price as f64 - mid).abs() <= reach)
+                        .map(|order| order.2)
+                        .sum()
+                };
+                prop_assert_eq!(band.bids, Quantity(within(OrderSide::Bid)));
+                prop_assert_eq!(band.asks, Quantity(within(OrderSide::Ask)));
+            }
+        }
+        _ => prop_assert!(sample.depth.is_empty()),
+    }
+
+    // The next sample starts counting afresh
+    prop_assert_eq!(engine.sample_metrics().await.unwrap().flow, OrderFlow::default());
+    Ok(())
+}
+
+/// Placements arriving while the engine is paused in buffer mode wait, up to
+/// `max_buffered` of them, and run in arrival order on resume with the fills
+/// and book the same placements give an engine never paused; the rest, and
+/// every placement in reject mode, are refused with `EnginePaused`.
+async fn check_pause(ops: Vec<Op>, max_buffered: usize) -> Result<(), TestCaseError> {
+    let orders: Vec<Order> = ops
+        .iter()
+        .enumerate()
+        .filter_map(|(index, op)| new_order(index as u64 + 1, op))
+        .collect();
+    let engine = Arc::new(MatchingEngine::new(Arc::new(InMemoryStorage::new()), orderbook()));
+
+    engine.pause(PauseMode::Reject).await;
+    if let Some(order) = orders.first() {
+        let refused = engine.place_order(order.clone()).await;
+        prop_assert!(matches!(refused, Err(ClobError::EnginePaused)), "admitted while rejecting: {:?}", refused);
+    }
+
+    engine.pause(PauseMode::Buffer { max_buffered }).await;
+    let mut placements = Vec::new();
+    for order in &orders {
+        let waiting = engine.pause_state().buffered;
+        let placement = tokio::spawn({
+            let engine = engine.clone();
+            let order = order.clone();
+            async move { engine.place_order(order).await }
+        });
+        // Let the placement reach the gate before the next one arrives
+        while engine.pause_state().buffered == waiting && !placement.is_finished() {
+            tokio::task::yield_now().await;
+        }
+        placements.push((order, waiting, placement));
+    }
+    prop_assert!(engine.pause_state().buffered <= max_buffered);
+    prop_assert_eq!(engine.resume().await.mode, Some(PauseMode::Buffer { max_buffered }));
+
+    let reference = MatchingEngine::new(Arc::new(InMemoryStorage::new()), orderbook());
+    for (order, waiting, placement) in placements {
+        let placed = placement.await.unwrap();
+        if let Err(ClobError::EnginePaused) = placed {
+            prop_assert!(waiting >= max_buffered, "order {} refused with {} buffered", order.order_id, waiting);
+            continue;
+        }
+        let expected = reference.place_order(order.clone()).await;
+        match (placed, expected) {
+            (Ok(trades), Ok(expected)) => {
+                let fills = |trades: &[TradeExecution]| -> Vec<Fill> {
+                    trades.iter().map(|t| (t.maker_order_id, t.taker_order_id, t.price, t.quantity)).collect()
+                };
+                prop_assert_eq!(fills(&trades), fills(&expected), "fills of order {}", order.order_id);
+            }
+            (Err(e), Err(expected)) => prop_assert_eq!(e.code(), expected.code()),
+            (placed, expected) => {
+                prop_assert!(false, "order {}: {:?} where unpaused gave {:?}", order.order_id, placed, expected)
+            }
+        }
+    }
+
+    let book = engine.get_order_book_snapshot().await.unwrap();
+    let expected = reference.get_order_book_snapshot().await.unwrap();
+    prop_assert_eq!(book.bids, expected.bids);
+    prop_assert_eq!(book.asks, expected.asks);
+    prop_assert_eq!(engine.pause_state(), EnginePause { mode: None, buffered: 0, since: None });
+    Ok(())
+}
+
+/// Owner 1 lifts `hits` of owner 0's `asks` one by one. The `max_fills`-th
+/// fill trips owner 0's protection, which pulls its remaining asks, journaled
+/// so that replay leaves the same book, and refuses its quotes while frozen
+/// but not its taking orders.
+async fn check_mmp(asks: Vec<u64>, max_fills: u64, hits: usize) -> Result<(), TestCaseError> {
+    let storage = Arc::new(InMemoryStorage::new());
+    let mut engine = MatchingEngine::new(storage.clone(), orderbook());
+    engine.resume_order_ids(1_000);
+    let maker = owner(0);
+    let config = MmpConfig {
+        window_ms: 3_600_000,
+        max_fills: Some(max_fills),
+        max_quantity: None,
+        max_delta: None,
+        freeze_ms: 3_600_000,
+    };
+    engine.set_mmp(maker, config);
+    let order = |order_id: u64, owner: Pubkey, side: OrderSide, price: u64, quantity: u64, tif: TimeInForce| {
+        Order::builder()
+            .order_id(order_id)
+            .owner(owner)
+            .side(side)
+            .limit(price)
+            .qty(quantity)
+            .time_in_force(tif)
+            .timestamp(0)
+            .build()
+            .unwrap()
+    };
+    for (index, &quantity) in asks.iter().enumerate() {
+        let price = (index as u64 + 1) * TICK;
+        engine.place_order(order(index as u64 + 1, maker, OrderSide::Ask, price, quantity, TimeInForce::GoodTillCancelled)).await.unwrap();
+    }
+
+    let hits = hits.min(asks.len());
+    let mut fills = 0;
+    for (index, &quantity) in asks.iter().take(hits).enumerate() {
+        let price = (index as u64 + 1) * TICK;
+        let taker = order(100 + index as u64, owner(1), OrderSide::Bid, price, quantity, TimeInForce::ImmediateOrCancel);
+        fills += engine.place_order(taker).await.unwrap().len() as u64;
+    }
+
+    let tripped = hits as u64 >= max_fills;
+    let status = engine.mmp_status(&maker).unwrap();
+    let resting = engine.get_open_orders().await;
+    if tripped {
+        prop_assert_eq!(fills, max_fills);
+        prop_assert!(resting.is_empty(), "{} asks left after the trip", resting.len());
+        prop_assert_eq!(status.trips, 1);
+        prop_assert_eq!(status.last_trip, Some(MmpLimit::Fills));
+        prop_assert!(status.frozen_until_ms.is_some());
+        for order_id in max_fills + 1..=asks.len() as u64 {
+            let pulled = storage.get_order(order_id).await.unwrap().unwrap();
+            prop_assert_eq!(pulled.status, OrderStatus::Cancelled);
+        }
+    } else {
+        prop_assert_eq!(fills, hits as u64);
+        prop_assert_eq!(resting.len(), asks.len() - hits);
+        prop_assert_eq!(status.fills, fills);
+        prop_assert_eq!(status.trips, 0);
+    }
+
+    let quote = engine.place_order(order(200, maker, OrderSide::Ask, 90 * TICK, MIN_SIZE, TimeInForce::GoodTillCancelled)).await;
+    if tripped {
+        prop_assert!(matches!(quote, Err(ClobError::MmpFrozen(_))), "quote admitted while frozen: {:?}", quote);
+    } else {
+        prop_assert!(quote.is_ok(), "quote refused: {:?}", quote);
+    }
+    let hedge = engine.place_order(order(201, maker, OrderSide::Bid, TICK, MIN_SIZE, TimeInForce::ImmediateOrCancel)).await;
+    prop_assert!(hedge.is_ok(), "hedge refused: {:?}", hedge);
+
+    let standby = MatchingEngine::new(Arc::new(InMemoryStorage::new()), orderbook());
+    for entry in storage.get_journal_entries(0, 10_000).await.unwrap() {
+        standby.replay_journal_entry(&entry).await.unwrap();
+    }
+    // Open orders come back in no particular order
+    let ids = |orders: Vec<Order>| orders.into_iter().map(|order| order.order_id).collect::<BTreeSet<_>>();
+    prop_assert_eq!(ids(standby.get_open_orders().await), ids(engine.get_open_orders().await));
+    Ok(())
+}
+
+/// Owner 0 rests an ask in `maker_group` and owner 1 lifts it with a bid in
+/// `taker_group`. The two only trade unless both name the same group, in
+/// which case the resting ask is cancelled as for one owner and the bid rests.
+async fn check_prevention_group(
+    maker_group: Option<u32>,
+    taker_group: Option<u32>,
+    quantity: u64,
+) -> Result<(), TestCaseError> {
+    let storage = Arc::new(InMemoryStorage::new());
+    let engine = MatchingEngine::new(storage.clone(), orderbook());
+    let order = |order_id: u64, owner: Pubkey, side: OrderSide, group: Option<u32>| {
+        let builder = Order::builder()
+            .order_id(order_id)
+            .owner(owner)
+            .side(side)
+            .limit(10 * TICK)
+            .qty(quantity)
+            .self_trade_behavior(SelfTradeBehavior::CancelProvide)
+            .timestamp(0);
+        match group {
+            Some(group) => builder.prevention_group(group),
+            None => builder,
+        }
+        .build()
+        .unwrap()
+    };
+    engine.place_order(order(1, owner(0), OrderSide::Ask, maker_group)).await.unwrap();
+    let fills = engine.place_order(order(2, owner(1), OrderSide::Bid, taker_group)).await.unwrap();
+
+    let grouped = matches!((maker_group, taker_group), (Some(maker), Some(taker)) if maker == taker);
+    let resting = engine.get_open_orders().await;
+    if grouped {
+        prop_assert!(fills.is_empty(), "{} fills inside one group", fills.len());
+        prop_assert_eq!(storage.get_order(1).await.unwrap().unwrap().status, OrderStatus::Cancelled);
+        prop_assert_eq!(resting.len(), 1);
+        prop_assert_eq!(resting[0].order_id, 2);
+    } else {
+        prop_assert_eq!(fills.len(), 1);
+        prop_assert_eq!(fills[0].quantity, quantity);
+        prop_assert!(resting.is_empty(), "{} orders left after a full fill", resting.len());
+    }
+    Ok(())
+}
+
+/// Owner 0 rests an ask before the market narrows to `order_types` and
+/// `time_in_force`, then owner 1 sends a bid of `order_type` and
+/// `bid_time_in_force`. The bid is refused with `OrderTypeDisabled` exactly
+/// when the whitelist leaves out its type or time in force, and the ask that
+/// rested before the change stays on the book.
+async fn check_order_types(
+    order_types: Vec<OrderType>,
+    time_in_force: Vec<TimeInForce>,
+    order_type: OrderType,
+    bid_time_in_force: TimeInForce,
+) -> Result<(), TestCaseError> {
+    let mut engine = MatchingEngine::new(Arc::new(InMemoryStorage::new()), orderbook());
+    let ask = Order::builder().order_id(1).owner(owner(0)).ask().limit(20 * TICK).qty(MIN_SIZE).timestamp(0).gtc();
+    engine.place_order(ask.build().unwrap()).await.unwrap();
+    let whitelist = OrderTypeWhitelist::new(&order_types, &time_in_force);
+    engine.set_order_types(whitelist);
+
+    let builder = Order::builder().order_id(2).owner(owner(1)).bid().qty(MIN_SIZE).timestamp(0);
+    let builder = match order_type {
+        OrderType::Limit => builder.limit(10 * TICK),
+        OrderType::PostOnly => builder.post_only(10 * TICK),
+        OrderType::Market => builder.market(),
+    };
+    let builder = match bid_time_in_force {
+        TimeInForce::GoodTillTime => builder.good_till(EPOCH),
+        time_in_force => builder.time_in_force(time_in_force),
+    };
+    let result = engine.place_order(builder.build().unwrap()).await;
+
+    let accepted = (order_types.is_empty() || order_types.contains(&order_type))
+        && (time_in_force.is_empty() || time_in_force.contains(&bid_time_in_force));
+    prop_assert_eq!(whitelist.accepts(order_type, bid_time_in_force), accepted);
+    if accepted {
+        prop_assert!(!matches!(result, Err(ClobError::OrderTypeDisabled(_))), "{:?}", result);
+    } else {
+        prop_assert!(matches!(result, Err(ClobError::OrderTypeDisabled(_))), "{:?}", result);
+        let resting = engine.get_open_orders().await;
+        prop_assert_eq!(resting.len(), 1);
+        prop_assert_eq!(resting[0].order_id, 1);
+    }
+    Ok(())
+}
+
+/// Rejects orders larger than its quantity
+struct MaxQuantity(u64);
+
+impl PreTradeHook for MaxQuantity {
+    fn check(&self, order: &Order, _context: &PreTradeContext<'_>) -> ClobResult<()> {
+        if order.remaining_quantity > self.0 {
+            return Err(ClobError::InvalidQuantity(format!("{} exceeds {}", order.remaining_quantity, self.0)));
+        }
+        Ok(())
+    }
+}
+
+/// `makers` asks of one size queue at one price, and a pre-trade hook
+/// rejects growing the one at `index`. The rejected replacement leaves it
+/// where it queued and uses up no order ID: the next replacement gets the
+/// ID an engine that never saw the rejection gives it.
+async fn check_rejected_modify(makers: u64, index: u64) -> Result<(), TestCaseError> {
+    let mut engines = Vec::new();
+    for _ in 0..2 {
+        let mut engine = MatchingEngine::new(Arc::new(InMemoryStorage::new()), orderbook());
+        engine.add_pre_trade_hook(Arc::new(MaxQuantity(MIN_SIZE)));
+        for order_id in 1..=makers {
+            let ask = Order::builder().order_id(order_id).owner(owner(order_id as u8)).ask().limit(20 * TICK).qty(MIN_SIZE).timestamp(0).gtc();
+            engine.place_order(ask.build().unwrap()).await.unwrap();
+        }
+        engines.push(engine);
+    }
+
+    let target = index % makers + 1;
+    let result = engines[0].modify_order(target, None, Some(MIN_SIZE + 1)).await;
+    prop_assert!(matches!(result, Err(ClobError::InvalidQuantity(_))), "{:?}", result);
+    for order_id in 1..=makers {
+        let position = engines[0].queue_position(order_id).await.unwrap();
+        prop_assert_eq!(position.orders_ahead as u64, order_id - 1);
+        prop_assert_eq!(position.quantity_ahead, (order_id - 1) * MIN_SIZE);
+    }
+
+    let replaced = engines[0].modify_order(target, Some(21 * TICK), None).await.unwrap();
+    let twin = engines[1].modify_order(target, Some(21 * TICK), None).await.unwrap();
+    prop_assert_eq!(replaced.order_id, twin.order_id);
+    Ok(())
+}
+
+/// Owner 0's ask is lifted by owner 1, then the trade is repriced to each
+/// of `corrections` in turn, in ticks, and busted at the end when `bust`.
+/// The balances the adjustments move back add up to the difference between
+/// settling the trade as it ends and as it was matched, the stored trade
+/// follows every version, and nothing adjusts a busted trade.
+async fn check_trade_adjustments(quantity: u64, corrections: Vec<u64>, bust: bool) -> Result<(), TestCaseError> {
+    let storage = Arc::new(InMemoryStorage::new());
+    let engine = MatchingEngine::new(storage.clone(), orderbook());
+    let order = |order_id: u64, owner: Pubkey, side: OrderSide| {
+        Order::builder()
+            .order_id(order_id)
+            .owner(owner)
+            .side(side)
+            .limit(10 * TICK)
+            .qty(quantity)
+            .timestamp(0)
+            .build()
+            .unwrap()
+    };
+    engine.place_order(order(1, owner(0), OrderSide::Ask)).await.unwrap();
+    let fills = engine.place_order(order(2, owner(1), OrderSide::Bid)).await.unwrap();
+    prop_assert_eq!(fills.len(), 1);
+    let trade_id = fills[0].trade_id;
+
+    let (mut price, mut version) = (10 * TICK, 0);
+    let (mut base_to_maker, mut quote_to_maker) = (0i64, 0i64);
+    for ticks in corrections {
+        match engine.adjust_trade(trade_id, Some(ticks * TICK), "reprice".to_string(), "ops".to_string()).await {
+            Ok(adjustment) => {
+                version += 1;
+                price = ticks * TICK;
+                prop_assert_eq!(adjustment.version, version);
+                prop_assert_eq!(adjustment.reversal, ReversalStatus::Pending);
+                base_to_maker += adjustment.base_to_maker;
+                quote_to_maker += adjustment.quote_to_maker;
+            }
+            Err(ClobError::TradeNotAdjustable(_)) => prop_assert_eq!(ticks * TICK, price),
+            Err(e) => return Err(TestCaseError::fail(format!("correction to {} ticks failed: {}", ticks, e))),
+        }
+    }
+    if bust {
+        let adjustment = engine.adjust_trade(trade_id, None, "bust".to_string(), "ops".to_string()).await.unwrap();
+        version += 1;
+        base_to_maker += adjustment.base_to_maker;
+        quote_to_maker += adjustment.quote_to_maker;
+        let again = engine.adjust_trade(trade_id, Some(TICK), "again".to_string(), "ops".to_string()).await;
+        prop_assert!(matches!(again, Err(ClobError::TradeNotAdjustable(_))), "a busted trade was adjusted");
+    }
+
+    // The maker sold: a bust hands its base back and takes the quote back
+    let settled_quote = |price: u64| (quantity * price) as i64;
+    let (final_base, final_quote) = if bust { (0, 0) } else { (quantity as i64, settled_quote(price)) };
+    prop_assert_eq!(base_to_maker, quantity as i64 - final_base);
+    prop_assert_eq!(quote_to_maker, final_quote - settled_quote(10 * TICK));
+
+    let stored = storage.get_trade(trade_id).await.unwrap().unwrap();
+    prop_assert_eq!(stored.version, version);
+    prop_assert_eq!(stored.price, price);
+    let status = match (bust, version) {
+        (true, _) => TradeStatus::Busted,
+        (false, 0) => TradeStatus::Active,
+        (false, _) => TradeStatus::Corrected,
+    };
+    prop_assert_eq!(stored.status, status);
+    prop_assert_eq!(storage.get_trade_adjustments(trade_id).await.unwrap().len() as u32, version);
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(128))]
+
+    #[test]
+    fn engine_matches_reference(ops in prop::collection::vec(op(), 1..60)) {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(check(ops))?;
+    }
+
+    #[test]
+    fn allocation_splits_one_level(
+        allocation in prop_oneof![
+            Just(AllocationMode::PriceTime),
+            Just(AllocationMode::ProRata),
+            Just(AllocationMode::SizeTime),
+        ],
+        makers in prop::collection::vec(MIN_SIZE..=40u64, 1..8),
+        taker in MIN_SIZE..=200u64,
+    ) {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(check_allocation(allocation, makers, taker))?;
+    }
+
+    #[test]
+    fn slippage_bounds_market_orders(
+        levels in prop::collection::vec(MIN_SIZE..=40u64, 1..6),
+        taker in MIN_SIZE..=200u64,
+        max_slippage_bps in 0..3_000u32,
+    ) {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(check_slippage(levels, taker, max_slippage_bps))?;
+    }
+
+    #[test]
+    fn market_status_gates_commands(
+        long in MIN_SIZE..=100u64,
+        asks in prop::collection::vec(MIN_SIZE..=40u64, 1..8),
+    ) {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(check_market_status(long, asks))?;
+    }
+
+    #[test]
+    fn resting_lifetime_expires_old_orders(
+        orders in prop::collection::vec((0..1_000i64, prop::option::of(1..1_000i64)), 1..12),
+        max_resting_secs in 1..1_000i64,
+        scope in prop_oneof![Just(LifetimeScope::GoodTillCancelled), Just(LifetimeScope::AllResting)],
+        sweep_at in 0..2_000i64,
+    ) {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(check_resting_lifetime(orders, max_resting_secs, scope, sweep_at))?;
+    }
+
+    #[test]
+    fn rolling_stats_match_their_trades(
+        trades in prop::collection::vec((0..3 * 86_400i64, 1..1_000u64, 1..1_000u64), 1..80),
+        now in 0..3 * 86_400i64,
+        window_secs in prop_oneof![Just(3_600i64), Just(86_400i64), 60..86_400i64],
+    ) {
+        check_rolling_stats(trades, now, window_secs)?;
+    }
+
+    #[test]
+    fn market_metrics_sample_the_book(
+        bids in prop::collection::vec((1..200u64, MIN_SIZE..=40u64), 0..10),
+        asks in prop::collection::vec((1..200u64, MIN_SIZE..=40u64), 0..10),
+        cancels in 0..20usize,
+    ) {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(check_market_metrics(bids, asks, cancels))?;
+    }
+
+    #[test]
+    fn pause_buffers_commands_in_arrival_order(
+        ops in prop::collection::vec(op(), 1..30),
+        max_buffered in 0..40usize,
+    ) {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(check_pause(ops, max_buffered))?;
+    }
+
+    #[test]
+    fn mmp_pulls_quotes_of_a_tripped_maker(
+        asks in prop::collection::vec(MIN_SIZE..=40u64, 1..10),
+        max_fills in 1..12u64,
+        hits in 1..12usize,
+    ) {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(check_mmp(asks, max_fills, hits))?;
+    }
+
+    #[test]
+    fn prevention_group_stops_trades_across_accounts(
+        maker_group in prop::option::of(1..4u32),
+        taker_group in prop::option::of(1..4u32),
+        quantity in MIN_SIZE..=40u64,
+    ) {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(check_prevention_group(maker_group, taker_group, quantity))?;
+    }
+
+    #[test]
+    fn order_type_whitelist_gates_new_orders(
+        order_types in prop::sample::subsequence(OrderType::ALL.to_vec(), 0..=3),
+        time_in_force in prop::sample::subsequence(TimeInForce::ALL.to_vec(), 0..=4),
+        order_type in prop::sample::select(OrderType::ALL.to_vec()),
+        bid_time_in_force in prop::sample::select(TimeInForce::ALL.to_vec()),
+    ) {
+        // A post-only order that may not rest is never built
+        prop_assume!(
+            order_type != OrderType::PostOnly
+                || matches!(bid_time_in_force, TimeInForce::GoodTillCancelled | TimeInForce::GoodTillTime)
+        );
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(check_order_types(order_types, time_in_force, order_type, bid_time_in_force))?;
+    }
+
+    #[test]
+    fn trade_adjustments_net_out_settlement(
+        quantity in MIN_SIZE..=40u64,
+        corrections in prop::collection::vec(1..20u64, 0..5),
+        bust in any::<bool>(),
+    ) {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(check_trade_adjustments(quantity, corrections, bust))?;
+    }
+
+    #[test]
+    fn rejected_modify_keeps_time_priority(makers in 1..6u64, index in 0..6u64) {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(check_rejected_modify(makers, index))?;
+    }
+}
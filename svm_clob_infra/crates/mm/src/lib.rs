@@ -0,0 +1,200 @@
+/// Market Making Framework for SVM CLOB Infrastructure
+///
+/// This module runs a quoting strategy against the RPC server through the client
+/// SDK: it tracks the bot's resting orders and inventory, asks the strategy for
+/// quotes on every refresh, and swaps stale quotes with one batch cancel-replace.
+
+use svm_clob_types::*;
+use svm_clob_sdk::{post_only_order, ClobClient};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tracing::{info, warn, debug};
+
+pub mod strategy;
+
+pub use strategy::{Quote, QuoteContext, QuotingStrategy, SymmetricParams, SymmetricStrategy};
+
+/// Market maker configuration
+#[derive(Debug, Clone)]
+pub struct MarketMakerConfig {
+    /// Wallet the bot quotes for
+    pub owner: Pubkey,
+    /// Market tick size
+    pub tick_size: u64,
+    /// Market minimum order size
+    pub min_order_size: u64,
+    /// Delay between quote refreshes
+    pub refresh_interval: Duration,
+    /// Mid price used while the book has no two-sided market
+    pub fallback_mid: Option<u64>,
+}
+
+/// Resting order placed by the bot
+#[derive(Debug, Clone)]
+struct LiveOrder {
+    quote: Quote,
+    /// Quantity filled as of the last inventory refresh
+    filled: u64,
+}
+
+/// Strategy runner that keeps the desired quotes resting on the book
+pub struct MarketMaker<Q: QuotingStrategy> {
+    client: ClobClient,
+    strategy: Q,
+    config: MarketMakerConfig,
+    live: HashMap<u64, LiveOrder>,
+    inventory: i64,
+    next_client_order_id: u64,
+}
+
+impl<Q: QuotingStrategy> MarketMaker<Q> {
+    /// Create a new market maker
+    pub fn new(client: ClobClient, strategy: Q, config: MarketMakerConfig) -> Self {
+        Self {
+            client,
+            strategy,
+            config,
+            live: HashMap::new(),
+            inventory: 0,
+            next_client_order_id: 1,
+        }
+    }
+
+    /// Net base position accumulated since start
+    pub fn inventory(&self) -> i64 {
+        self.inventory
+    }
+
+    /// Quote until the process is stopped, pulling all quotes on exit
+    pub async fn run(&mut self) -> ClobResult<()> {
+        info!("Market maker '{}' quoting for {}", self.strategy.name(), self.config.owner);
+
+        loop {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => break,
+                _ = tokio::time::sleep(self.config.refresh_interval) => {}
+            }
+
+            if let Err(e) = self.refresh().await {
+                warn!("Quote refresh failed: {}", e);
+            }
+        }
+
+        info!("Pulling {} quotes", self.live.len());
+        self.replace(Vec::new()).await
+    }
+
+    /// Update inventory, ask the strategy for quotes and replace stale ones
+    pub async fn refresh(&mut self) -> ClobResult<()> {
+        self.update_inventory().await?;
+
+        let book = self.client.get_orderbook().await?;
+        let Some(mid) = mid_price(&book).or(self.config.fallback_mid) else {
+            debug!("No mid price available, pulling quotes");
+            return self.replace(Vec::new()).await;
+        };
+
+        let context = QuoteContext {
+            book,
+            mid,
+            inventory: self.inventory,
+            tick_size: self.config.tick_size,
+            min_order_size: self.config.min_order_size,
+        };
+        let mut desired = self.strategy.quote(&context);
+
+        let mut resting: Vec<Quote> = self.live.values().map(|order| order.quote).collect();
+        resting.sort_by_key(|quote| (quote.side as u8, quote.price));
+        desired.sort_by_key(|quote| (quote.side as u8, quote.price));
+        if resting == desired {
+            return Ok(());
+        }
+
+        self.replace(desired).await
+    }
+
+    /// Fold fills of live orders into inventory and forget finished orders
+    async fn update_inventory(&mut self) -> ClobResult<()> {
+        if self.live.is_empty() {
+            return Ok(());
+        }
+
+        let orders = self.client.get_all_user_orders(&self.config.owner).await?;
+        let known: HashSet<u64> = orders.iter().map(|order| order.order_id).collect();
+        self.live.retain(|order_id, _| known.contains(order_id));
+
+        for order in orders {
+            let Some(live) = self.live.get_mut(&order.order_id) else {
+                continue;
+            };
+
+            let filled = order.quantity.saturating_sub(order.remaining_quantity);
+            let delta = filled.saturating_sub(live.filled) as i64;
+            live.filled = filled;
+            match order.side {
+                OrderSide::Bid => self.inventory += delta,
+                OrderSide::Ask => self.inventory -= delta,
+            }
+            if delta > 0 {
+                info!("Filled {} on order {}, inventory now {}", delta, order.order_id, self.inventory);
+            }
+
+            if !matches!(order.status, OrderStatus::Open | OrderStatus::PartiallyFilled) {
+                self.live.remove(&order.order_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Cancel every live order and place `quotes` in one batch
+    async fn replace(&mut self, quotes: Vec<Quote>) -> ClobResult<()> {
+        if quotes.is_empty() && self.live.is_empty() {
+            return Ok(());
+        }
+
+        let cancel: Vec<u64> = self.live.keys().copied().collect();
+        let place = quotes
+            .iter()
+            .map(|quote| {
+                let client_order_id = self.next_client_order_id;
+                self.next_client_order_id += 1;
+                post_only_order(&self.config.owner, quote.side, quote.price, quote.quantity, client_order_id)
+            })
+            .collect();
+
+        let response = self.client.cancel_replace(cancel, place).await?;
+        for error in &response.errors {
+            debug!("Batch item {:?} rejected: {}", error.item, error.message);
+        }
+
+        // Cancelled orders may have filled since the last inventory update
+        for cancelled in &response.cancelled {
+            if let Some(live) = self.live.remove(&cancelled.order_id) {
+                let filled = cancelled.quantity.saturating_sub(cancelled.remaining_quantity);
+                let delta = filled.saturating_sub(live.filled) as i64;
+                match cancelled.side {
+                    OrderSide::Bid => self.inventory += delta,
+                    OrderSide::Ask => self.inventory -= delta,
+                }
+            }
+        }
+        // Orders that could not be cancelled stay tracked until the next
+        // inventory update accounts for their fills
+
+        for order in response.placed {
+            self.live.insert(order.order_id, LiveOrder {
+                quote: Quote { side: order.side, price: order.price, quantity: order.quantity },
+                filled: 0,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Midpoint of the best bid and ask, if the book is two-sided
+pub fn mid_price(book: &OrderBookSnapshot) -> Option<u64> {
+    let best_bid = book.bids.iter().map(|(price, _)| *price).max()?;
+    let best_ask = book.asks.iter().map(|(price, _)| *price).min()?;
+    Some(best_bid / 2 + best_ask / 2 + (best_bid % 2 + best_ask % 2) / 2)
+}
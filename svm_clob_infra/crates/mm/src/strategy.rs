@@ -0,0 +1,112 @@
+/// Quoting strategies
+///
+/// A strategy turns the current book, the bot's inventory and the market's
+/// tick/lot constraints into the set of quotes the bot should be resting.
+
+use svm_clob_types::*;
+
+/// A single desired resting order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quote {
+    pub side: OrderSide,
+    pub price: u64,
+    pub quantity: u64,
+}
+
+/// Everything a strategy sees when it is asked for quotes
+#[derive(Debug, Clone)]
+pub struct QuoteContext {
+    /// Current aggregated order book
+    pub book: OrderBookSnapshot,
+    /// Reference mid price, from the book or a configured fallback
+    pub mid: u64,
+    /// Net base position accumulated by the bot (positive = long)
+    pub inventory: i64,
+    /// Market tick size
+    pub tick_size: u64,
+    /// Market minimum order size
+    pub min_order_size: u64,
+}
+
+/// Pluggable quoting logic
+pub trait QuotingStrategy: Send + Sync {
+    /// Strategy name used in logs
+    fn name(&self) -> &str;
+
+    /// Desired quotes for the given context; an empty list pulls all quotes
+    fn quote(&self, context: &QuoteContext) -> Vec<Quote>;
+}
+
+/// Parameters of the reference symmetric strategy
+#[derive(Debug, Clone)]
+pub struct SymmetricParams {
+    /// Distance of the innermost quotes from mid, in basis points
+    pub spread_bps: u64,
+    /// Price levels quoted on each side
+    pub levels: u32,
+    /// Additional ticks between consecutive levels
+    pub level_spacing_ticks: u64,
+    /// Quantity quoted per level
+    pub order_size: u64,
+    /// Mid shift per unit of `order_size` held, in basis points
+    pub skew_bps: u64,
+    /// Absolute inventory at which the side that would grow it is pulled
+    pub max_inventory: u64,
+}
+
+/// Quotes both sides around an inventory-skewed mid
+#[derive(Debug, Clone)]
+pub struct SymmetricStrategy {
+    params: SymmetricParams,
+}
+
+impl SymmetricStrategy {
+    pub fn new(params: SymmetricParams) -> Self {
+        Self { params }
+    }
+
+    /// Mid shifted against the position, so a long bot quotes lower to sell down
+    fn skewed_mid(&self, context: &QuoteContext) -> u64 {
+        if self.params.order_size == 0 {
+            return context.mid;
+        }
+        let units = context.inventory as i128 / self.params.order_size as i128;
+        let shift = context.mid as i128 * units * self.params.skew_bps as i128 / 10_000;
+        (context.mid as i128 - shift).clamp(context.tick_size as i128, u64::MAX as i128) as u64
+    }
+}
+
+impl QuotingStrategy for SymmetricStrategy {
+    fn name(&self) -> &str {
+        "symmetric"
+    }
+
+    fn quote(&self, context: &QuoteContext) -> Vec<Quote> {
+        let tick = context.tick_size.max(1);
+        if self.params.order_size < context.min_order_size {
+            return Vec::new();
+        }
+
+        let mid = self.skewed_mid(context);
+        let half_spread = (mid as u128 * self.params.spread_bps as u128 / 10_000) as u64;
+        let half_spread = half_spread.max(tick);
+        let max_inventory = self.params.max_inventory as i64;
+
+        let mut quotes = Vec::new();
+        for level in 0..self.params.levels as u64 {
+            let offset = half_spread + level * self.params.level_spacing_ticks * tick;
+
+            if context.inventory < max_inventory {
+                let bid = mid.saturating_sub(offset) / tick * tick;
+                if bid > 0 {
+                    quotes.push(Quote { side: OrderSide::Bid, price: bid, quantity: self.params.order_size });
+                }
+            }
+            if context.inventory > -max_inventory {
+                let ask = mid.saturating_add(offset).div_ceil(tick) * tick;
+                quotes.push(Quote { side: OrderSide::Ask, price: ask, quantity: self.params.order_size });
+            }
+        }
+        quotes
+    }
+}
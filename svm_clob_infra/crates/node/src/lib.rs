@@ -0,0 +1,475 @@
+/// Embeddable SVM CLOB node
+///
+/// `Node` wires storage, the matching engine, risk checks, the chain indexer
+/// and the RPC and WebSocket servers together the way `svm-clob start` does,
+/// so another binary, or a test, can run a CLOB in process instead of
+/// shelling out to the CLI. Storage and the clock are injected: hand the
+/// builder any `Storage` implementation, such as `PostgresStorage` or an
+/// in-memory one, and a `VirtualClock` to drive expiry and rate limits by hand.
+/// Only the components given an address or a configuration are run; the
+/// routers of both servers are available without listening at all. With an
+/// indexer, matching pauses while the market is paused on chain. The
+/// WebSocket server and the indexer are behind the `ws` and `indexer`
+/// features, and the storage backends behind `postgres` and `redis`.
+
+use axum::Router;
+use futures_util::future::select_all;
+#[cfg(feature = "indexer")]
+use svm_clob_indexer::{Indexer, IndexerConfig, MarketStatusChange};
+use svm_clob_matching_engine::MatchingEngine;
+use svm_clob_risk::{RiskEngine, RiskLimits};
+use svm_clob_rpc_server::idempotency::IdempotencyCache;
+use svm_clob_rpc_server::nonce::{NonceStore, DEFAULT_MAX_SIGNATURE_TTL_SECS};
+use svm_clob_rpc_server::rate_limit::{RateLimiter, RateLimits};
+use svm_clob_rpc_server::speed_bump::SpeedBumpGate;
+use svm_clob_rpc_server::suspension::SuspensionList;
+use svm_clob_rpc_server::tenant::TenantDirectory;
+use svm_clob_rpc_server::RpcServerState;
+use svm_clob_serve::ListenOptions;
+use svm_clob_storage::Storage;
+use svm_clob_types::*;
+#[cfg(feature = "ws")]
+use svm_clob_websocket_server::WebSocketServerState;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(feature = "indexer")]
+use tokio::sync::watch;
+use tokio::sync::RwLock;
+#[cfg(feature = "indexer")]
+use tracing::warn;
+use tracing::{error, info};
+
+/// How often the engine's book is published to WebSocket subscribers
+#[cfg(feature = "ws")]
+pub const BOOK_PUBLISH_INTERVAL_MS: u64 = 100;
+
+/// How often resting good-till-time orders are checked for expiry
+pub const EXPIRY_SWEEP_INTERVAL_MS: u64 = 1000;
+
+/// Builder of a `Node` over injected storage and clock
+pub struct NodeBuilder<S: Storage> {
+    storage: Arc<S>,
+    orderbook: OrderBook,
+    program_id: Pubkey,
+    clock: Arc<dyn Clock>,
+    risk_limits: RiskLimits,
+    allocation: AllocationMode,
+    resting_lifetime: Option<RestingLifetime>,
+    order_types: OrderTypeWhitelist,
+    metadata: MarketMetadata,
+    rate_limits: RateLimits,
+    speed_bump: Option<SpeedBump>,
+    admin_token: Option<String>,
+    require_signed_orders: bool,
+    require_api_key: bool,
+    rpc: Option<ListenOptions>,
+    #[cfg(feature = "ws")]
+    websocket: Option<ListenOptions>,
+    #[cfg(feature = "indexer")]
+    indexer: Option<IndexerConfig>,
+    #[cfg(feature = "indexer")]
+    chain_pause: PauseMode,
+}
+
+impl<S: Storage + 'static> NodeBuilder<S> {
+    /// Program the market lives under; the deployed program's by default
+    pub fn program_id(mut self, program_id: Pubkey) -> Self {
+        self.program_id = program_id;
+        self
+    }
+
+    /// Time source of every component; the system clock by default. Storage
+    /// keeps its own clock, so give it the same one before handing it over.
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Market-wide risk limits; no limits by default
+    pub fn risk_limits(mut self, limits: RiskLimits) -> Self {
+        self.risk_limits = limits;
+        self
+    }
+
+    /// How fills are shared between makers at one price
+    pub fn allocation(mut self, allocation: AllocationMode) -> Self {
+        self.allocation = allocation;
+        self
+    }
+
+    /// Longest time an order may rest on the book
+    pub fn resting_lifetime(mut self, lifetime: RestingLifetime) -> Self {
+        self.resting_lifetime = Some(lifetime);
+        self
+    }
+
+    /// Order types and times in force new orders may use; all by default
+    pub fn order_types(mut self, whitelist: OrderTypeWhitelist) -> Self {
+        self.order_types = whitelist;
+        self
+    }
+
+    /// Symbols and decimals of the market, for `ui_*` fields
+    pub fn metadata(mut self, metadata: MarketMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Per-owner order and per-IP request rate limits; unlimited by default
+    pub fn rate_limits(mut self, limits: RateLimits) -> Self {
+        self.rate_limits = limits;
+        self
+    }
+
+    /// Hold orders that only take for a fixed or random delay before they
+    /// reach the engine; none by default
+    pub fn speed_bump(mut self, speed_bump: SpeedBump) -> Self {
+        self.speed_bump = Some(speed_bump);
+        self
+    }
+
+    /// Bearer token enabling `/api/v1/admin`
+    pub fn admin_token(mut self, token: impl Into<String>) -> Self {
+        self.admin_token = Some(token.into());
+        self
+    }
+
+    /// Reject placements that do not carry the owner's order signature
+    pub fn require_signed_orders(mut self, required: bool) -> Self {
+        self.require_signed_orders = required;
+        self
+    }
+
+    /// Reject calls that do not carry a tenant's API key
+    pub fn require_api_key(mut self, required: bool) -> Self {
+        self.require_api_key = required;
+        self
+    }
+
+    /// Serve the RPC API when the node runs
+    pub fn rpc(mut self, options: ListenOptions) -> Self {
+        self.rpc = Some(options);
+        self
+    }
+
+    /// Serve WebSocket feeds when the node runs
+    #[cfg(feature = "ws")]
+    pub fn websocket(mut self, options: ListenOptions) -> Self {
+        self.websocket = Some(options);
+        self
+    }
+
+    /// Follow on-chain events into storage when the node runs
+    #[cfg(feature = "indexer")]
+    pub fn indexer(mut self, config: IndexerConfig) -> Self {
+        self.indexer = Some(config);
+        self
+    }
+
+    /// What matching does with commands while the indexer finds the market
+    /// paused on chain; they are refused by default
+    #[cfg(feature = "indexer")]
+    pub fn chain_pause(mut self, mode: PauseMode) -> Self {
+        self.chain_pause = mode;
+        self
+    }
+
+    /// Build the engine over the stored book and the servers' state
+    pub async fn build(self) -> InfraResult<Node<S>> {
+        if self.speed_bump.is_some_and(|speed_bump| !speed_bump.is_valid()) {
+            return Err(InfraError::config("speed bump must have min_delay_us <= max_delay_us <= one second"));
+        }
+        let market_id = MarketId::new(&self.program_id, &self.orderbook.base_mint, &self.orderbook.quote_mint);
+        let risk = Arc::new(RiskEngine::new(self.risk_limits).with_clock(self.clock.clone()));
+
+        let mut matching_engine = MatchingEngine::new(self.storage.clone(), self.orderbook);
+        matching_engine.set_market_id(market_id);
+        matching_engine.set_allocation(self.allocation);
+        matching_engine.set_resting_lifetime(self.resting_lifetime);
+        matching_engine.set_order_types(self.order_types);
+        matching_engine.set_clock(self.clock.clone());
+        matching_engine.resume_trade_ids(self.storage.get_last_trade_id().await?);
+        matching_engine.resume_order_ids(self.storage.get_reserved_order_ids().await?);
+        matching_engine.add_pre_trade_hook(risk.clone());
+        matching_engine.bootstrap().await?;
+        let matching_engine = Arc::new(RwLock::new(matching_engine));
+
+        let rpc_state = Arc::new(RpcServerState {
+            matching_engine: matching_engine.clone(),
+            storage: self.storage.clone(),
+            risk,
+            admin_token: self.admin_token,
+            require_signed_orders: self.require_signed_orders,
+            nonces: NonceStore::new(DEFAULT_MAX_SIGNATURE_TTL_SECS),
+            paper: None,
+            market_metadata: self.metadata,
+            program_id: self.program_id,
+            rate_limiter: RateLimiter::new(self.rate_limits),
+            tenants: TenantDirectory::new(self.require_api_key),
+            speed_bump: SpeedBumpGate::new(self.speed_bump),
+            suspensions: SuspensionList::new(),
+            idempotency: IdempotencyCache::new(),
+            clock: self.clock.clone(),
+        });
+
+        Ok(Node {
+            matching_engine,
+            rpc_state,
+            #[cfg(feature = "ws")]
+            ws_state: Arc::new(WebSocketServerState::with_clock(self.clock).with_entitlements(self.storage.clone())),
+            storage: self.storage,
+            rpc: self.rpc,
+            #[cfg(feature = "ws")]
+            websocket: self.websocket,
+            #[cfg(feature = "indexer")]
+            indexer: self.indexer,
+            #[cfg(feature = "indexer")]
+            chain_pause: self.chain_pause,
+        })
+    }
+}
+
+/// A CLOB node: one market's engine with its servers and indexer
+pub struct Node<S: Storage> {
+    storage: Arc<S>,
+    matching_engine: Arc<RwLock<MatchingEngine<S>>>,
+    rpc_state: Arc<RpcServerState<S>>,
+    #[cfg(feature = "ws")]
+    ws_state: Arc<WebSocketServerState>,
+    rpc: Option<ListenOptions>,
+    #[cfg(feature = "ws")]
+    websocket: Option<ListenOptions>,
+    #[cfg(feature = "indexer")]
+    indexer: Option<IndexerConfig>,
+    #[cfg(feature = "indexer")]
+    chain_pause: PauseMode,
+}
+
+type Service = Pin<Box<dyn Future<Output = InfraResult<()>> + Send>>;
+
+impl<S: Storage + 'static> Node<S> {
+    /// Builder of a node trading `orderbook`'s market over `storage`
+    pub fn builder(storage: Arc<S>, orderbook: OrderBook) -> NodeBuilder<S> {
+        NodeBuilder {
+            storage,
+            orderbook,
+            program_id: program::program_id(),
+            clock: system_clock(),
+            risk_limits: RiskLimits::default(),
+            allocation: AllocationMode::default(),
+            resting_lifetime: None,
+            order_types: OrderTypeWhitelist::ALL,
+            metadata: MarketMetadata::default(),
+            rate_limits: RateLimits::default(),
+            speed_bump: None,
+            admin_token: None,
+            require_signed_orders: false,
+            require_api_key: false,
+            rpc: None,
+            #[cfg(feature = "ws")]
+            websocket: None,
+            #[cfg(feature = "indexer")]
+            indexer: None,
+            #[cfg(feature = "indexer")]
+            chain_pause: PauseMode::Reject,
+        }
+    }
+
+    pub fn storage(&self) -> &Arc<S> {
+        &self.storage
+    }
+
+    pub fn engine(&self) -> &Arc<RwLock<MatchingEngine<S>>> {
+        &self.matching_engine
+    }
+
+    pub fn rpc_state(&self) -> &Arc<RpcServerState<S>> {
+        &self.rpc_state
+    }
+
+    #[cfg(feature = "ws")]
+    pub fn ws_state(&self) -> &Arc<WebSocketServerState> {
+        &self.ws_state
+    }
+
+    /// RPC routes with every layer they are served with, to call in process
+    pub fn rpc_router(&self) -> Router {
+        svm_clob_rpc_server::app(self.rpc_state.clone())
+    }
+
+    /// WebSocket routes, to serve on a listener of the caller's own
+    #[cfg(feature = "ws")]
+    pub fn ws_router(&self) -> Router {
+        svm_clob_websocket_server::create_router().with_state(self.ws_state.clone())
+    }
+
+    /// Run the configured servers and indexer, publishing the book and
+    /// expiring orders meanwhile, until one of them fails
+    pub async fn run(self) -> InfraResult<()> {
+        let mut services: Vec<Service> = Vec::new();
+        if let Some(options) = self.rpc {
+            services.push(Box::pin(svm_clob_rpc_server::start_server(self.rpc_state, options)));
+        }
+        #[cfg(feature = "ws")]
+        if let Some(options) = self.websocket {
+            services.push(Box::pin(svm_clob_websocket_server::start_server(self.ws_state.clone(), options)));
+        }
+        #[cfg(feature = "indexer")]
+        if let Some(config) = self.indexer {
+            let (address, _) = {
+                let engine = self.matching_engine.read().await;
+                let orderbook = engine.orderbook_config();
+                program::orderbook_address(&config.program_id, &orderbook.base_mint, &orderbook.quote_mint)
+            };
+            let indexer = Indexer::new(self.storage, config);
+            spawn_chain_pause(self.matching_engine.clone(), indexer.subscribe_market_status(), address, self.chain_pause);
+            services.push(Box::pin(async move { indexer.run().await.map_err(InfraError::from) }));
+        }
+        if services.is_empty() {
+            return Err(InfraError::config("The node has no RPC server, WebSocket server or indexer to run"));
+        }
+
+        #[cfg(feature = "ws")]
+        {
+            spawn_book_publisher(self.matching_engine.clone(), self.ws_state.clone());
+            spawn_expiry_sweeper(self.matching_engine, Some(self.ws_state));
+        }
+        #[cfg(not(feature = "ws"))]
+        spawn_sweeper(self.matching_engine, |_, _| async {});
+        info!("Node started {} services", services.len());
+
+        let handles: Vec<_> = services.into_iter().map(tokio::spawn).collect();
+        let (served, _, others) = select_all(handles).await;
+        for other in others {
+            other.abort();
+        }
+        served.map_err(InfraError::task)?
+    }
+}
+
+/// Publish the engine's book to WebSocket subscribers as it changes, and
+/// to each owner followed through `UserOrders` the queue places of its
+/// resting orders: once when first seen, then whenever they change
+#[cfg(feature = "ws")]
+pub fn spawn_book_publisher<S: Storage + 'static>(
+    matching_engine: Arc<RwLock<MatchingEngine<S>>>,
+    ws_state: Arc<WebSocketServerState>,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_millis(BOOK_PUBLISH_INTERVAL_MS));
+        let mut published: std::collections::HashMap<u64, QueuePosition> = std::collections::HashMap::new();
+        loop {
+            interval.tick().await;
+            let engine = matching_engine.read().await;
+            let snapshot = engine.get_order_book_snapshot().await;
+            match snapshot {
+                Ok(snapshot) => ws_state.publish_order_book(snapshot).await,
+                Err(e) => error!("Failed to snapshot the book for WebSocket subscribers: {}", e),
+            }
+
+            let users = ws_state.subscribed_users().await;
+            let positions = engine.queue_positions(&users).await;
+            drop(engine);
+            let mut current = std::collections::HashMap::with_capacity(positions.len());
+            for position in positions {
+                if published.get(&position.order_id) != Some(&position) {
+                    ws_state.publish_queue_position(position.clone()).await;
+                }
+                current.insert(position.order_id, position);
+            }
+            published = current;
+        }
+    });
+}
+
+/// Pause matching in `mode` while the market at `orderbook` is paused on
+/// chain, where its trades could not settle, and resume once it is not; a
+/// pause the operator set is left alone
+#[cfg(feature = "indexer")]
+pub fn spawn_chain_pause<S: Storage + 'static>(
+    matching_engine: Arc<RwLock<MatchingEngine<S>>>,
+    mut changes: watch::Receiver<Option<MarketStatusChange>>,
+    orderbook: Pubkey,
+    mode: PauseMode,
+) {
+    tokio::spawn(async move {
+        let mut paused_by_chain = false;
+        while changes.changed().await.is_ok() {
+            let Some(change) = *changes.borrow_and_update() else {
+                continue;
+            };
+            if change.orderbook != orderbook {
+                continue;
+            }
+            let engine = matching_engine.read().await;
+            let paused = engine.pause_state().mode.is_some();
+            match change.status {
+                MarketStatus::Paused if !paused => {
+                    warn!("Market {} was paused on chain at slot {}, pausing matching", orderbook, change.slot);
+                    engine.pause(mode).await;
+                    paused_by_chain = true;
+                }
+                MarketStatus::Paused => {}
+                status if paused_by_chain => {
+                    let lifted = engine.resume().await;
+                    info!(
+                        "Market {} is {} on chain at slot {}, resuming matching with {} buffered commands",
+                        orderbook, status, change.slot, lifted.buffered
+                    );
+                    paused_by_chain = false;
+                }
+                _ => {}
+            }
+        }
+    });
+}
+
+/// Expire good-till-time orders as their time passes, telling each owner
+/// over `ws_state` when given; a standby engine is skipped until it leads
+#[cfg(feature = "ws")]
+pub fn spawn_expiry_sweeper<S: Storage + 'static>(
+    matching_engine: Arc<RwLock<MatchingEngine<S>>>,
+    ws_state: Option<Arc<WebSocketServerState>>,
+) {
+    spawn_sweeper(matching_engine, move |order, reason| {
+        let ws_state = ws_state.clone();
+        async move {
+            if let Some(ws_state) = ws_state {
+                ws_state.publish_order_update(order, Some(reason)).await;
+            }
+        }
+    });
+}
+
+/// Expire good-till-time orders as their time passes, handing each to
+/// `expired`; a standby engine is skipped until it leads
+fn spawn_sweeper<S, F, Fut>(matching_engine: Arc<RwLock<MatchingEngine<S>>>, expired: F)
+where
+    S: Storage + 'static,
+    F: Fn(Order, ExpiryReason) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_millis(EXPIRY_SWEEP_INTERVAL_MS));
+        loop {
+            interval.tick().await;
+            let swept = {
+                let engine = matching_engine.read().await;
+                if !engine.is_active() {
+                    continue;
+                }
+                engine.expire_orders(engine.clock().now()).await
+            };
+            match swept {
+                Ok(swept) => {
+                    for (order, reason) in swept {
+                        expired(order, reason).await;
+                    }
+                }
+                Err(e) => error!("Failed to expire orders: {}", e),
+            }
+        }
+    });
+}
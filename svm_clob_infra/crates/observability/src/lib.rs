@@ -0,0 +1,188 @@
+/// Observability for SVM CLOB Infrastructure
+///
+/// This module gives every service the same tracing subscriber, the same
+/// Prometheus exporter and the same metric names, so dashboards and alerts do
+/// not depend on which binary emitted a series. The subscriber and exporter
+/// are behind the `exporter` feature and the Redis time series behind `redis`;
+/// without either, only the `record_*` functions are built, which write to
+/// whatever `metrics` recorder the embedding process installed.
+
+use svm_clob_types::market_data::DEPTH_BANDS_BPS;
+use svm_clob_types::{MarketId, MarketMetrics, OrderSide, OrderType};
+#[cfg(feature = "exporter")]
+use metrics::{describe_counter, describe_gauge, describe_histogram, Unit};
+#[cfg(feature = "exporter")]
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
+#[cfg(feature = "exporter")]
+use std::net::SocketAddr;
+use std::time::Duration;
+use thiserror::Error;
+#[cfg(feature = "exporter")]
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+#[cfg(feature = "redis")]
+pub mod time_series;
+
+#[cfg(feature = "exporter")]
+pub use svm_clob_telemetry::TelemetryConfig;
+#[cfg(feature = "redis")]
+pub use time_series::TimeSeriesSink;
+
+/// Orders accepted by the matching engine, labelled by `side` and `order_type`
+pub const ORDERS_PLACED_TOTAL: &str = "orders_placed_total";
+/// Time spent matching and persisting one order, labelled by `market`
+pub const MATCH_LATENCY_SECONDS: &str = "match_latency_seconds";
+/// Best ask less best bid in native price units, labelled by `market`
+pub const MARKET_SPREAD: &str = "market_spread";
+/// Spread relative to the mid in basis points, labelled by `market`
+pub const MARKET_SPREAD_BPS: &str = "market_spread_bps";
+/// Resting base quantity near the mid, labelled by `market`, `side` and `band_bps`
+pub const MARKET_DEPTH: &str = "market_depth";
+/// Orders accepted per second over the last sample, labelled by `market`
+pub const MARKET_ORDER_ARRIVAL_RATE: &str = "market_order_arrival_rate";
+/// Cancellations per trade over the last sample, labelled by `market`
+pub const MARKET_CANCEL_TO_TRADE_RATIO: &str = "market_cancel_to_trade_ratio";
+/// Currently connected WebSocket clients
+pub const WS_CLIENTS: &str = "ws_clients";
+/// 1 while a feature runs on its fallback because a dependency is
+/// unavailable, else 0, labelled by `dependency` and `feature`
+pub const DEPENDENCY_DEGRADED: &str = "dependency_degraded";
+/// Calls a feature served from its fallback, labelled by `dependency` and `feature`
+pub const DEPENDENCY_FALLBACKS_TOTAL: &str = "dependency_fallbacks_total";
+
+/// Histogram buckets for `match_latency_seconds`, 50us to 1s
+#[cfg(feature = "exporter")]
+const MATCH_LATENCY_BUCKETS: &[f64] = &[
+    0.00005, 0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0,
+];
+
+/// Observability setup errors
+#[derive(Error, Debug)]
+pub enum ObservabilityError {
+    #[error("Invalid log filter: {0}")]
+    InvalidFilter(String),
+    #[error("Tracing setup failed: {0}")]
+    Tracing(String),
+    #[error("Metrics setup failed: {0}")]
+    Metrics(String),
+}
+
+/// Install the global tracing subscriber
+///
+/// `level` is an `EnvFilter` directive such as `info` or `svm_clob=debug,info`.
+/// Spans are also exported over OTLP when `telemetry` is set, which requires a
+/// running Tokio runtime.
+#[cfg(feature = "exporter")]
+pub fn init_tracing(
+    level: &str,
+    json_format: bool,
+    telemetry: Option<&TelemetryConfig>,
+) -> Result<(), ObservabilityError> {
+    let filter = EnvFilter::try_new(level).map_err(|e| ObservabilityError::InvalidFilter(e.to_string()))?;
+
+    let otel = telemetry
+        .map(svm_clob_telemetry::otel_layer)
+        .transpose()
+        .map_err(|e| ObservabilityError::Tracing(e.to_string()))?;
+
+    let registry = tracing_subscriber::registry().with(filter).with(otel);
+
+    let result = if json_format {
+        registry.with(tracing_subscriber::fmt::layer().json()).try_init()
+    } else {
+        registry.with(tracing_subscriber::fmt::layer()).try_init()
+    };
+    result.map_err(|e| ObservabilityError::Tracing(e.to_string()))
+}
+
+/// Flush telemetry buffered by exporters; call before the process exits
+#[cfg(feature = "exporter")]
+pub fn shutdown() {
+    svm_clob_telemetry::shutdown();
+}
+
+/// Install the global Prometheus recorder, serving `/metrics` on `listen`
+///
+/// Must be called from within a Tokio runtime, which runs the scrape endpoint.
+#[cfg(feature = "exporter")]
+pub fn init_metrics(listen: SocketAddr) -> Result<(), ObservabilityError> {
+    PrometheusBuilder::new()
+        .with_http_listener(listen)
+        .set_buckets_for_metric(Matcher::Full(MATCH_LATENCY_SECONDS.to_string()), MATCH_LATENCY_BUCKETS)
+        .map_err(|e| ObservabilityError::Metrics(e.to_string()))?
+        .install()
+        .map_err(|e| ObservabilityError::Metrics(e.to_string()))?;
+
+    describe_metrics();
+    Ok(())
+}
+
+/// Register help text and units for the shared metrics
+#[cfg(feature = "exporter")]
+fn describe_metrics() {
+    describe_counter!(ORDERS_PLACED_TOTAL, Unit::Count, "Orders accepted by the matching engine");
+    describe_histogram!(MATCH_LATENCY_SECONDS, Unit::Seconds, "Time spent matching and persisting one order");
+    describe_gauge!(WS_CLIENTS, Unit::Count, "Connected WebSocket clients");
+    describe_gauge!(MARKET_SPREAD, "Best ask less best bid in native price units");
+    describe_gauge!(MARKET_SPREAD_BPS, "Spread relative to the mid in basis points");
+    describe_gauge!(MARKET_DEPTH, "Resting base quantity within band_bps of the mid");
+    describe_gauge!(MARKET_ORDER_ARRIVAL_RATE, "Orders accepted per second over the last sample");
+    describe_gauge!(MARKET_CANCEL_TO_TRADE_RATIO, "Cancellations per trade over the last sample");
+    describe_gauge!(DEPENDENCY_DEGRADED, "Whether a feature runs on its fallback because a dependency is unavailable");
+    describe_counter!(DEPENDENCY_FALLBACKS_TOTAL, Unit::Count, "Calls a feature served from its fallback");
+}
+
+/// Count an accepted order
+pub fn record_order_placed(side: OrderSide, order_type: OrderType) {
+    metrics::counter!(ORDERS_PLACED_TOTAL, "side" => side.to_string(), "order_type" => order_type.to_string()).increment(1);
+}
+
+/// Record how long the engine of `market` took to process an order
+pub fn record_match_latency(market: &MarketId, elapsed: Duration) {
+    metrics::histogram!(MATCH_LATENCY_SECONDS, "market" => market.to_string()).record(elapsed.as_secs_f64());
+}
+
+/// Set the market gauges from a sample; a gauge that does not apply, such
+/// as the spread of a one-sided book, is set to NaN so dashboards show a gap
+pub fn record_market_metrics(sample: &MarketMetrics) {
+    let market = sample.market_id.to_string();
+    let or_nan = |value: Option<f64>| value.unwrap_or(f64::NAN);
+    metrics::gauge!(MARKET_SPREAD, "market" => market.clone()).set(or_nan(sample.spread().map(|spread| spread as f64)));
+    metrics::gauge!(MARKET_SPREAD_BPS, "market" => market.clone()).set(or_nan(sample.spread_bps()));
+    metrics::gauge!(MARKET_ORDER_ARRIVAL_RATE, "market" => market.clone()).set(sample.order_arrival_rate());
+    metrics::gauge!(MARKET_CANCEL_TO_TRADE_RATIO, "market" => market.clone()).set(or_nan(sample.cancel_to_trade_ratio()));
+    for band_bps in DEPTH_BANDS_BPS {
+        let band = sample.depth.iter().find(|band| band.band_bps == band_bps);
+        let sides = [("bid", band.map(|band| band.bids)), ("ask", band.map(|band| band.asks))];
+        for (side, quantity) in sides {
+            metrics::gauge!(
+                MARKET_DEPTH,
+                "market" => market.clone(),
+                "side" => side,
+                "band_bps" => band_bps.to_string()
+            )
+            .set(or_nan(quantity.map(|quantity| quantity.0 as f64)));
+        }
+    }
+}
+
+/// Record whether `feature` runs on its fallback because `dependency` is unavailable
+pub fn record_dependency_degraded(dependency: &'static str, feature: &'static str, degraded: bool) {
+    metrics::gauge!(DEPENDENCY_DEGRADED, "dependency" => dependency, "feature" => feature)
+        .set(if degraded { 1.0 } else { 0.0 });
+}
+
+/// Count a call of `feature` served from its fallback while `dependency` is unavailable
+pub fn record_dependency_fallback(dependency: &'static str, feature: &'static str) {
+    metrics::counter!(DEPENDENCY_FALLBACKS_TOTAL, "dependency" => dependency, "feature" => feature).increment(1);
+}
+
+/// Count a WebSocket client connecting
+pub fn ws_client_connected() {
+    metrics::gauge!(WS_CLIENTS).increment(1.0);
+}
+
+/// Count a WebSocket client disconnecting
+pub fn ws_client_disconnected() {
+    metrics::gauge!(WS_CLIENTS).decrement(1.0);
+}
@@ -0,0 +1,80 @@
+/// Redis time series of market metrics
+///
+/// Writes each `MarketMetrics` sample to RedisTimeSeries, one series per
+/// market and metric under `<prefix>:<market>:<metric>`, labelled with
+/// `market` and `metric` so a Grafana Redis data source can query them with
+/// `TS.MRANGE ... FILTER metric=spread_bps` straight away, without waiting
+/// for a Prometheus scrape. Series are created on first write with the sink's
+/// retention. Requires a Redis server with the time series module loaded, as
+/// in Redis Stack. The connection reconnects by itself after Redis drops it.
+
+use crate::ObservabilityError;
+use svm_clob_types::MarketMetrics;
+use redis::aio::ConnectionManager;
+
+/// Sink of market metrics samples into Redis time series
+pub struct TimeSeriesSink {
+    connection: ConnectionManager,
+    prefix: String,
+    retention_ms: u64,
+}
+
+impl TimeSeriesSink {
+    /// Sink writing under `prefix`, keeping points for `retention_ms`
+    pub async fn connect(url: &str, prefix: &str, retention_ms: u64) -> Result<Self, ObservabilityError> {
+        let client = redis::Client::open(url).map_err(time_series_error)?;
+        let connection = ConnectionManager::new(client).await.map_err(time_series_error)?;
+        Ok(Self {
+            connection,
+            prefix: prefix.to_string(),
+            retention_ms,
+        })
+    }
+
+    /// Add a sample's values at its timestamp; values that do not apply,
+    /// such as the spread of a one-sided book, are left out
+    pub async fn add(&self, sample: &MarketMetrics) -> Result<(), ObservabilityError> {
+        let market = sample.market_id.to_string();
+        let mut points = vec![
+            ("spread".to_string(), sample.spread().map(|spread| spread as f64)),
+            ("spread_bps".to_string(), sample.spread_bps()),
+            ("order_arrival_rate".to_string(), Some(sample.order_arrival_rate())),
+            ("cancel_to_trade_ratio".to_string(), sample.cancel_to_trade_ratio()),
+        ];
+        for band in &sample.depth {
+            points.push((format!("depth_bid_{}bps", band.band_bps), Some(band.bids.0 as f64)));
+            points.push((format!("depth_ask_{}bps", band.band_bps), Some(band.asks.0 as f64)));
+        }
+
+        let timestamp_ms = sample.timestamp.saturating_mul(1000);
+        let mut pipeline = redis::pipe();
+        for (metric, value) in points {
+            let Some(value) = value else {
+                continue;
+            };
+            pipeline
+                .cmd("TS.ADD")
+                .arg(format!("{}:{}:{}", self.prefix, market, metric))
+                .arg(timestamp_ms)
+                .arg(value)
+                .arg("RETENTION")
+                .arg(self.retention_ms)
+                .arg("ON_DUPLICATE")
+                .arg("LAST")
+                .arg("LABELS")
+                .arg("market")
+                .arg(&market)
+                .arg("metric")
+                .arg(&metric)
+                .ignore();
+        }
+        pipeline
+            .query_async::<_, ()>(&mut self.connection.clone())
+            .await
+            .map_err(time_series_error)
+    }
+}
+
+fn time_series_error(e: redis::RedisError) -> ObservabilityError {
+    ObservabilityError::Metrics(e.to_string())
+}
@@ -5,18 +5,35 @@
 
 use svm_clob_types::*;
 use dashmap::DashMap;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::sync::Arc;
-use tracing::{info, warn, debug};
+use tracing::debug;
+
+/// Resting orders of one price level
+#[derive(Debug, Default)]
+struct Level {
+    /// Remaining quantity of the level's orders
+    quantity: u64,
+    /// Order IDs keyed by arrival stamp, so iteration follows time priority
+    queue: BTreeMap<u64, u64>,
+}
 
 /// Order book manager for efficient price-level operations
+///
+/// Each level keeps its orders in arrival order and each owner the IDs of its
+/// resting orders, so matching and per-owner checks read only the orders they
+/// need: nothing on the matching path scans the whole book or sorts by time.
 pub struct OrderBookManager {
     /// Bid orders organized by price level (descending)
-    bid_levels: BTreeMap<u64, PriceLevel>,
+    bid_levels: BTreeMap<u64, Level>,
     /// Ask orders organized by price level (ascending)  
-    ask_levels: BTreeMap<u64, PriceLevel>,
+    ask_levels: BTreeMap<u64, Level>,
     /// Fast order lookup by order ID
     orders: DashMap<u64, Order>,
+    /// Arrival stamp of each resting order, for time priority within a level
+    arrivals: HashMap<u64, u64>,
+    /// Resting order IDs of each owner
+    owners: HashMap<Pubkey, BTreeSet<u64>>,
     /// Configuration parameters
     tick_size: u64,
     min_order_size: u64,
@@ -31,12 +48,21 @@ impl OrderBookManager {
             bid_levels: BTreeMap::new(),
             ask_levels: BTreeMap::new(),
             orders: DashMap::new(),
+            arrivals: HashMap::new(),
+            owners: HashMap::new(),
             tick_size,
             min_order_size,
             sequence_number: 0,
         }
     }
 
+    fn levels_mut(&mut self, side: OrderSide) -> &mut BTreeMap<u64, Level> {
+        match side {
+            OrderSide::Bid => &mut self.bid_levels,
+            OrderSide::Ask => &mut self.ask_levels,
+        }
+    }
+
     /// Add a new order to the book
     pub fn add_order(&mut self, order: Order) -> ClobResult<()> {
         debug!("Adding order {} to book at price {}", order.order_id, order.price);
@@ -46,34 +72,36 @@ impl OrderBookManager {
             return Err(ClobError::PriceNotAlignedToTickSize);
         }
 
-        // Add to appropriate side
-        match order.side {
-            OrderSide::Bid => {
-                let level = self.bid_levels.entry(order.price).or_insert(PriceLevel {
-                    price: order.price,
-                    quantity: 0,
-                    order_count: 0,
-                });
-                level.quantity += order.remaining_quantity;
-                level.order_count += 1;
-            }
-            OrderSide::Ask => {
-                let level = self.ask_levels.entry(order.price).or_insert(PriceLevel {
-                    price: order.price,
-                    quantity: 0,
-                    order_count: 0,
-                });
-                level.quantity += order.remaining_quantity;
-                level.order_count += 1;
-            }
-        }
+        // Queue at the back of its level
+        self.sequence_number += 1;
+        self.insert_order(order, self.sequence_number);
+        Ok(())
+    }
 
-        // Store order for fast lookup
-        self.orders.insert(order.order_id, order);
+    /// Put an order taken off with `remove_order` back at `arrival`, the
+    /// stamp it rested with, so it keeps its time priority
+    pub fn restore_order(&mut self, order: Order, arrival: u64) {
+        debug!("Restoring order {} to book at arrival {}", order.order_id, arrival);
         self.sequence_number += 1;
+        self.insert_order(order, arrival);
+    }
 
-        info!("Order {} added to book", order.order_id);
-        Ok(())
+    /// Arrival stamp of a resting order; earlier stamps fill first within a level
+    pub fn arrival(&self, order_id: u64) -> Option<u64> {
+        self.arrivals.get(&order_id).copied()
+    }
+
+    fn insert_order(&mut self, order: Order, arrival: u64) {
+        let order_id = order.order_id;
+        let level = self.levels_mut(order.side).entry(order.price).or_default();
+        level.quantity += order.remaining_quantity;
+        level.queue.insert(arrival, order_id);
+
+        self.owners.entry(order.owner).or_default().insert(order_id);
+        self.arrivals.insert(order_id, arrival);
+        self.orders.insert(order_id, order);
+
+        debug!("Order {} added to book", order_id);
     }
 
     /// Remove an order from the book
@@ -81,80 +109,68 @@ impl OrderBookManager {
         let order = self.orders.remove(&order_id)
             .ok_or(ClobError::OrderNotFound)?
             .1;
+        let arrival = self.arrivals.remove(&order_id);
 
         debug!("Removing order {} from book", order_id);
 
-        // Remove from appropriate side
-        match order.side {
-            OrderSide::Bid => {
-                if let Some(level) = self.bid_levels.get_mut(&order.price) {
-                    level.quantity -= order.remaining_quantity;
-                    level.order_count -= 1;
-                    
-                    // Remove empty levels
-                    if level.order_count == 0 {
-                        self.bid_levels.remove(&order.price);
-                    }
-                }
+        if let Some(owned) = self.owners.get_mut(&order.owner) {
+            owned.remove(&order_id);
+            if owned.is_empty() {
+                self.owners.remove(&order.owner);
             }
-            OrderSide::Ask => {
-                if let Some(level) = self.ask_levels.get_mut(&order.price) {
-                    level.quantity -= order.remaining_quantity;
-                    level.order_count -= 1;
-                    
-                    // Remove empty levels
-                    if level.order_count == 0 {
-                        self.ask_levels.remove(&order.price);
-                    }
-                }
+        }
+
+        // Remove from its level, dropping the level once empty
+        let levels = self.levels_mut(order.side);
+        if let Some(level) = levels.get_mut(&order.price) {
+            level.quantity -= order.remaining_quantity;
+            if let Some(arrival) = arrival {
+                level.queue.remove(&arrival);
+            }
+            if level.queue.is_empty() {
+                levels.remove(&order.price);
             }
         }
 
         self.sequence_number += 1;
-        info!("Order {} removed from book", order_id);
+        debug!("Order {} removed from book", order_id);
         Ok(order)
     }
 
     /// Update order quantity (for partial fills)
     pub fn update_order_quantity(&mut self, order_id: u64, new_remaining_quantity: u64) -> ClobResult<()> {
-        let mut order_ref = self.orders.get_mut(&order_id)
-            .ok_or(ClobError::OrderNotFound)?;
-        
-        let old_quantity = order_ref.remaining_quantity;
-        let quantity_change = old_quantity - new_remaining_quantity;
-        
-        order_ref.remaining_quantity = new_remaining_quantity;
+        // Release the map guard before touching levels or removing the order
+        let (side, price, quantity_change) = {
+            let mut order_ref = self.orders.get_mut(&order_id)
+                .ok_or(ClobError::OrderNotFound)?;
 
-        // Update price level
-        match order_ref.side {
-            OrderSide::Bid => {
-                if let Some(level) = self.bid_levels.get_mut(&order_ref.price) {
-                    level.quantity -= quantity_change;
-                    
-                    // Remove empty levels
-                    if level.quantity == 0 {
-                        self.bid_levels.remove(&order_ref.price);
-                    }
-                }
+            if new_remaining_quantity > order_ref.remaining_quantity {
+                return Err(ClobError::InvalidQuantity(format!(
+                    "remaining quantity of order {} cannot grow from {} to {}",
+                    order_id, order_ref.remaining_quantity, new_remaining_quantity
+                )));
             }
-            OrderSide::Ask => {
-                if let Some(level) = self.ask_levels.get_mut(&order_ref.price) {
-                    level.quantity -= quantity_change;
-                    
-                    // Remove empty levels
-                    if level.quantity == 0 {
-                        self.ask_levels.remove(&order_ref.price);
-                    }
-                }
+
+            // Resting orders fill at their own price
+            let quantity_change = order_ref.remaining_quantity - new_remaining_quantity;
+            let price = order_ref.price;
+            order_ref.record_fill(price, quantity_change);
+            if new_remaining_quantity == 0 {
+                order_ref.status = OrderStatus::Filled;
+            } else if new_remaining_quantity < order_ref.quantity {
+                order_ref.status = OrderStatus::PartiallyFilled;
             }
+            (order_ref.side, order_ref.price, quantity_change)
+        };
+
+        // Update price level; empty levels are dropped when their last order leaves
+        if let Some(level) = self.levels_mut(side).get_mut(&price) {
+            level.quantity -= quantity_change;
         }
 
-        // Update order status if fully filled
+        // Fully filled orders leave the book
         if new_remaining_quantity == 0 {
-            order_ref.status = OrderStatus::Filled;
             self.remove_order(order_id)?;
-        } else if new_remaining_quantity < order_ref.quantity {
-            order_ref.status = OrderStatus::PartiallyFilled;
         }
 
         self.sequence_number += 1;
@@ -171,42 +187,80 @@ impl OrderBookManager {
         self.ask_levels.keys().next().copied()
     }
 
-    /// Get bid orders down to a specific price (for matching)
+    /// Get bid orders down to a specific price (for matching), best first
     pub fn get_bids_down_to_price(&self, min_price: u64) -> ClobResult<Vec<Order>> {
         let mut orders = Vec::new();
-        
-        for (&price, _level) in self.bid_levels.range(min_price..).rev() {
-            for order_entry in self.orders.iter() {
-                let order = order_entry.value();
-                if order.side == OrderSide::Bid && order.price == price && order.remaining_quantity > 0 {
-                    orders.push(order.clone());
-                }
-            }
-            
-            // Sort by timestamp for price-time priority
-            orders.sort_by_key(|o| o.timestamp);
-        }
-
+        self.extend_from_levels(self.bid_levels.range(min_price..).rev().map(|(_, level)| level), None, &mut orders);
         Ok(orders)
     }
 
-    /// Get ask orders up to a specific price (for matching)
+    /// Get ask orders up to a specific price (for matching), best first
     pub fn get_asks_up_to_price(&self, max_price: u64) -> ClobResult<Vec<Order>> {
         let mut orders = Vec::new();
-        
-        for (&price, _level) in self.ask_levels.range(..=max_price) {
-            for order_entry in self.orders.iter() {
-                let order = order_entry.value();
-                if order.side == OrderSide::Ask && order.price == price && order.remaining_quantity > 0 {
-                    orders.push(order.clone());
+        self.extend_from_levels(self.ask_levels.range(..=max_price).map(|(_, level)| level), None, &mut orders);
+        Ok(orders)
+    }
+
+    /// Fill `makers` with the resting orders `taker` can trade with up to
+    /// `limit_price`, best first and in arrival order within a level
+    ///
+    /// Only whole levels are taken, and only as many as it takes for the
+    /// orders of other owners to cover the taker's remaining quantity; the
+    /// taker's own orders are included for self-trade prevention but do not
+    /// count. `makers` is cleared first, so a caller can reuse its buffer.
+    pub fn matching_orders_into(&self, taker: &Order, limit_price: u64, makers: &mut Vec<Order>) {
+        makers.clear();
+        let needed = Some((taker.owner, taker.remaining_quantity));
+        match taker.side {
+            OrderSide::Bid => {
+                self.extend_from_levels(self.ask_levels.range(..=limit_price).map(|(_, level)| level), needed, makers)
+            }
+            OrderSide::Ask => {
+                self.extend_from_levels(self.bid_levels.range(limit_price..).rev().map(|(_, level)| level), needed, makers)
+            }
+        }
+    }
+
+    /// Append the orders of `levels` to `orders` in priority order; with
+    /// `needed` set, stop after the level where orders of owners other than
+    /// the given one reach the given quantity
+    fn extend_from_levels<'a>(
+        &self,
+        levels: impl Iterator<Item = &'a Level>,
+        needed: Option<(Pubkey, u64)>,
+        orders: &mut Vec<Order>,
+    ) {
+        let mut covered = 0u64;
+        for level in levels {
+            if let Some((_, quantity)) = needed {
+                if covered >= quantity {
+                    break;
+                }
+            }
+            for order_id in level.queue.values() {
+                let Some(order) = self.orders.get(order_id) else {
+                    continue;
+                };
+                if order.remaining_quantity == 0 {
+                    continue;
+                }
+                if needed.is_some_and(|(owner, _)| owner != order.owner) {
+                    covered = covered.saturating_add(order.remaining_quantity);
                 }
+                orders.push(order.value().clone());
             }
-            
-            // Sort by timestamp for price-time priority
-            orders.sort_by_key(|o| o.timestamp);
         }
+    }
 
-        Ok(orders)
+    /// Whether `owner` has a resting order with `client_order_id`
+    pub fn has_client_order_id(&self, owner: &Pubkey, client_order_id: u64) -> bool {
+        self.owners.get(owner).is_some_and(|owned| {
+            owned.iter().any(|order_id| {
+                self.orders
+                    .get(order_id)
+                    .is_some_and(|order| order.client_order_id == client_order_id)
+            })
+        })
     }
 
     /// Get current order book snapshot
@@ -227,6 +281,7 @@ impl OrderBookManager {
             .collect();
 
         OrderBookSnapshot {
+            market_id: MarketId::default(),
             bids,
             asks,
             sequence_number: self.sequence_number,
@@ -239,19 +294,59 @@ impl OrderBookManager {
         self.orders.get(&order_id).map(|entry| entry.value().clone())
     }
 
+    /// Place of a resting order in the queue of its price level, `None` when
+    /// it is not on the book
+    pub fn queue_position(&self, order_id: u64) -> Option<QueuePosition> {
+        let order = self.get_order(order_id)?;
+        let arrival = *self.arrivals.get(&order_id)?;
+        let level = match order.side {
+            OrderSide::Bid => self.bid_levels.get(&order.price)?,
+            OrderSide::Ask => self.ask_levels.get(&order.price)?,
+        };
+        let (orders_ahead, quantity_ahead) = level
+            .queue
+            .range(..arrival)
+            .filter_map(|(_, ahead)| self.orders.get(ahead).map(|ahead| ahead.remaining_quantity))
+            .filter(|&remaining| remaining > 0)
+            .fold((0u32, 0u64), |(orders, quantity), remaining| (orders + 1, quantity.saturating_add(remaining)));
+        Some(QueuePosition {
+            order_id,
+            market_id: order.market_id,
+            owner: order.owner,
+            side: order.side,
+            price: order.price,
+            remaining_quantity: order.remaining_quantity,
+            orders_ahead,
+            quantity_ahead,
+            level_quantity: level.quantity,
+        })
+    }
+
     /// Get all orders for a specific user
-    pub fn get_user_orders(&self, user: &solana_sdk::pubkey::Pubkey) -> Vec<Order> {
-        self.orders
-            .iter()
-            .filter(|entry| &entry.value().owner == user)
-            .map(|entry| entry.value().clone())
-            .collect()
+    pub fn get_user_orders(&self, user: &Pubkey) -> Vec<Order> {
+        self.owners
+            .get(user)
+            .map(|owned| owned.iter().filter_map(|order_id| self.get_order(*order_id)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Get every resting order
+    pub fn get_open_orders(&self) -> Vec<Order> {
+        self.orders.iter().map(|entry| entry.value().clone()).collect()
+    }
+
+    /// Get every resting order, earliest arrival first; adding them to an
+    /// empty book in this order keeps their time priority
+    pub fn get_open_orders_by_arrival(&self) -> Vec<Order> {
+        let mut orders = self.get_open_orders();
+        orders.sort_by_key(|order| self.arrivals.get(&order.order_id).copied().unwrap_or(u64::MAX));
+        orders
     }
 
     /// Get market statistics
     pub fn get_market_stats(&self) -> MarketStats {
-        let total_bid_orders = self.bid_levels.values().map(|l| l.order_count as u64).sum();
-        let total_ask_orders = self.ask_levels.values().map(|l| l.order_count as u64).sum();
+        let total_bid_orders = self.bid_levels.values().map(|l| l.queue.len() as u64).sum();
+        let total_ask_orders = self.ask_levels.values().map(|l| l.queue.len() as u64).sum();
         let total_bid_quantity = self.bid_levels.values().map(|l| l.quantity).sum();
         let total_ask_quantity = self.ask_levels.values().map(|l| l.quantity).sum();
 
@@ -259,7 +354,7 @@ impl OrderBookManager {
             best_bid: self.get_best_bid(),
             best_ask: self.get_best_ask(),
             spread: match (self.get_best_bid(), self.get_best_ask()) {
-                (Some(bid), Some(ask)) => Some(ask - bid),
+                (Some(bid), Some(ask)) => ask.checked_sub(bid),
                 _ => None,
             },
             total_bid_orders,
@@ -276,6 +371,7 @@ impl OrderBookManager {
 pub struct MarketStats {
     pub best_bid: Option<u64>,
     pub best_ask: Option<u64>,
+    /// Best ask less best bid; none for a one-sided or crossed book
     pub spread: Option<u64>,
     pub total_bid_orders: u64,
     pub total_ask_orders: u64,
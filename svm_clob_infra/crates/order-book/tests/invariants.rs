@@ -0,0 +1,173 @@
+//! Property tests for `OrderBookManager`
+//!
+//! Random add/remove/partial-fill sequences are applied to the book and to a
+//! naive list of resting orders; levels, best prices, matching priority,
+//! queue positions and the makers a taker reaches must agree after every step.
+
+use proptest::prelude::*;
+use solana_sdk::pubkey::Pubkey;
+use svm_clob_order_book::OrderBookManager;
+use svm_clob_types::*;
+
+const TICK: u64 = 10;
+
+#[derive(Debug, Clone)]
+enum Op {
+    Add { side: OrderSide, price_ticks: u64, quantity: u64 },
+    Remove { index: usize },
+    Fill { index: usize, quantity: u64 },
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let side = prop_oneof![Just(OrderSide::Bid), Just(OrderSide::Ask)];
+    prop_oneof![
+        3 => (side, 1..=12u64, 1..=40u64).prop_map(|(side, price_ticks, quantity)| Op::Add { side, price_ticks, quantity }),
+        1 => any::<usize>().prop_map(|index| Op::Remove { index }),
+        2 => (any::<usize>(), 1..=40u64).prop_map(|(index, quantity)| Op::Fill { index, quantity }),
+    ]
+}
+
+fn order(order_id: u64, side: OrderSide, price: u64, quantity: u64) -> Order {
+    Order {
+        order_id,
+        market_id: MarketId::default(),
+        owner: Pubkey::new_from_array([1; 32]),
+        price,
+        quantity,
+        remaining_quantity: quantity,
+        timestamp: 0,
+        client_order_id: order_id,
+        expiry_timestamp: 0,
+        side,
+        order_type: OrderType::Limit,
+        status: OrderStatus::Open,
+        self_trade_behavior: SelfTradeBehavior::CancelProvide,
+        prevention_group_id: None,
+        time_in_force: TimeInForce::GoodTillCancelled,
+        max_slippage_bps: None,
+        filled_notional: Notional::default(),
+        avg_fill_price: None,
+    }
+}
+
+/// Aggregate resting orders into (price, quantity) levels, best first
+fn levels(resting: &[Order], side: OrderSide) -> Vec<(u64, u64)> {
+    let mut levels: Vec<(u64, u64)> = Vec::new();
+    let mut prices: Vec<u64> = resting.iter().filter(|o| o.side == side).map(|o| o.price).collect();
+    prices.sort_unstable();
+    prices.dedup();
+    if side == OrderSide::Bid {
+        prices.reverse();
+    }
+    for price in prices {
+        let quantity = resting
+            .iter()
+            .filter(|o| o.side == side && o.price == price)
+            .map(|o| o.remaining_quantity)
+            .sum();
+        levels.push((price, quantity));
+    }
+    levels
+}
+
+/// Orders of one side in matching priority: best price first, then arrival
+fn priority(resting: &[Order], side: OrderSide) -> Vec<u64> {
+    let mut orders: Vec<(usize, &Order)> = resting.iter().enumerate().filter(|(_, o)| o.side == side).collect();
+    orders.sort_by_key(|(arrival, o)| match side {
+        OrderSide::Bid => (u64::MAX - o.price, *arrival),
+        OrderSide::Ask => (o.price, *arrival),
+    });
+    orders.into_iter().map(|(_, o)| o.order_id).collect()
+}
+
+proptest! {
+    #[test]
+    fn book_matches_reference(ops in prop::collection::vec(op(), 1..80), reach in 1..=120u64) {
+        let mut book = OrderBookManager::new(TICK, 1);
+        // Resting orders in arrival order
+        let mut resting: Vec<Order> = Vec::new();
+        let mut next_id = 1;
+
+        for op in ops {
+            match op {
+                Op::Add { side, price_ticks, quantity } => {
+                    let order = order(next_id, side, price_ticks * TICK, quantity);
+                    next_id += 1;
+                    book.add_order(order.clone()).unwrap();
+                    resting.push(order);
+                }
+                Op::Remove { index } => {
+                    if resting.is_empty() {
+                        prop_assert!(book.remove_order(next_id).is_err());
+                        continue;
+                    }
+                    let expected = resting.remove(index % resting.len());
+                    let removed = book.remove_order(expected.order_id).unwrap();
+                    prop_assert_eq!(removed.remaining_quantity, expected.remaining_quantity);
+                }
+                Op::Fill { index, quantity } => {
+                    if resting.is_empty() {
+                        continue;
+                    }
+                    let index = index % resting.len();
+                    let fill = quantity.min(resting[index].remaining_quantity);
+                    let remaining = resting[index].remaining_quantity - fill;
+                    book.update_order_quantity(resting[index].order_id, remaining).unwrap();
+                    if remaining == 0 {
+                        let filled = resting.remove(index);
+                        prop_assert!(book.get_order(filled.order_id).is_none());
+                    } else {
+                        resting[index].remaining_quantity = remaining;
+                        let order = book.get_order(resting[index].order_id).unwrap();
+                        prop_assert_eq!(order.remaining_quantity, remaining);
+                        prop_assert_eq!(order.status, OrderStatus::PartiallyFilled);
+                    }
+                }
+            }
+
+            let snapshot = book.get_snapshot();
+            prop_assert_eq!(&snapshot.bids, &levels(&resting, OrderSide::Bid));
+            prop_assert_eq!(&snapshot.asks, &levels(&resting, OrderSide::Ask));
+            prop_assert_eq!(book.get_best_bid(), snapshot.bids.first().map(|(price, _)| *price));
+            prop_assert_eq!(book.get_best_ask(), snapshot.asks.first().map(|(price, _)| *price));
+
+            let bids: Vec<u64> = book.get_bids_down_to_price(0).unwrap().iter().map(|o| o.order_id).collect();
+            let asks: Vec<u64> = book.get_asks_up_to_price(u64::MAX).unwrap().iter().map(|o| o.order_id).collect();
+            prop_assert_eq!(bids, priority(&resting, OrderSide::Bid));
+            prop_assert_eq!(&asks, &priority(&resting, OrderSide::Ask));
+
+            // A bid taker reaches a priority-ordered prefix of the asks, in
+            // whole levels, covering its quantity unless the side runs out
+            let taker = Order {
+                owner: Pubkey::new_from_array([2; 32]),
+                ..order(0, OrderSide::Bid, TICK, reach)
+            };
+            let mut makers = Vec::new();
+            book.matching_orders_into(&taker, u64::MAX, &mut makers);
+            let reached: Vec<u64> = makers.iter().map(|o| o.order_id).collect();
+            prop_assert_eq!(&reached[..], &asks[..reached.len()]);
+            let covered: u64 = makers.iter().map(|o| o.remaining_quantity).sum();
+            prop_assert!(covered >= reach || reached.len() == asks.len());
+            if let (Some(last), Some(next)) = (makers.last(), asks.get(reached.len())) {
+                prop_assert_ne!(book.get_order(*next).unwrap().price, last.price);
+            }
+
+            // Each order queues behind the earlier arrivals at its price
+            for (arrival, order) in resting.iter().enumerate() {
+                let ahead: Vec<&Order> = resting[..arrival]
+                    .iter()
+                    .filter(|o| o.side == order.side && o.price == order.price)
+                    .collect();
+                let position = book.queue_position(order.order_id).unwrap();
+                prop_assert_eq!(position.orders_ahead as usize, ahead.len());
+                prop_assert_eq!(position.quantity_ahead, ahead.iter().map(|o| o.remaining_quantity).sum::<u64>());
+                prop_assert_eq!(position.remaining_quantity, order.remaining_quantity);
+                let level = levels(&resting, order.side).into_iter().find(|(price, _)| *price == order.price);
+                prop_assert_eq!(Some(position.level_quantity), level.map(|(_, quantity)| quantity));
+            }
+
+            let stats = book.get_market_stats();
+            prop_assert_eq!(stats.total_bid_orders + stats.total_ask_orders, resting.len() as u64);
+        }
+    }
+}
@@ -0,0 +1,440 @@
+/// State Reconciliation for SVM CLOB Infrastructure
+///
+/// This module periodically loads the on-chain `OrderBook` and `UserAccount` state
+/// and diffs it against the matching engine and PostgreSQL, logging an alert for
+/// every divergence and writing a machine-readable JSON report.
+///
+/// The program keeps no per-order accounts or sequence numbers on-chain, so open
+/// orders are reconciled between the engine and storage, and sequencing is checked
+/// across the engine journal, the settler and the indexer.
+
+use svm_clob_types::*;
+use svm_clob_types::program::{orderbook_address, user_account_address};
+use svm_clob_types::{OrderBookView, UserAccountView};
+use svm_clob_storage::Storage;
+use svm_clob_matching_engine::MatchingEngine;
+use svm_clob_settler::CHECKPOINT_SERVICE as SETTLER_CHECKPOINT;
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{info, warn, error};
+
+/// Maximum accounts per `getMultipleAccounts` request
+const MAX_ACCOUNTS_PER_REQUEST: usize = 100;
+
+/// Reconciler configuration
+#[derive(Debug, Clone)]
+pub struct ReconcilerConfig {
+    /// Solana JSON-RPC endpoint
+    pub rpc_url: String,
+    /// SVM CLOB program ID
+    pub program_id: Pubkey,
+    /// Commitment used when reading accounts
+    pub commitment: CommitmentConfig,
+    /// Market parameters the on-chain orderbook is expected to have
+    pub orderbook: OrderBook,
+    /// Delay between reconciliation runs
+    pub interval: Duration,
+    /// Where to write the latest JSON report, if anywhere
+    pub report_path: Option<PathBuf>,
+    /// Journal entries the settler may lag behind before it is reported
+    pub max_settlement_lag: u64,
+    /// Order types the market is expected to accept when there is no engine
+    /// to ask
+    pub order_types: OrderTypeWhitelist,
+}
+
+/// Area of state a divergence was found in
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DivergenceCategory {
+    OrderBook,
+    Balance,
+    OpenOrder,
+    Sequence,
+}
+
+/// How urgently a divergence needs attention
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Expected to resolve once in-flight work lands
+    Warning,
+    /// Funds or book state disagree
+    Critical,
+}
+
+/// A single disagreement between sources of truth
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Divergence {
+    pub category: DivergenceCategory,
+    pub severity: Severity,
+    /// Account, order or service the divergence concerns
+    pub subject: String,
+    /// Field that disagrees
+    pub field: String,
+    /// Value held by the matching engine, when it has one
+    pub engine: Option<String>,
+    /// Value held in PostgreSQL, when it has one
+    pub storage: Option<String>,
+    /// Value held on-chain, when it has one
+    pub chain: Option<String>,
+}
+
+/// Result of one reconciliation run
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReconciliationReport {
+    /// Unix timestamp of the run
+    pub generated_at: i64,
+    /// Slot the on-chain state was read at
+    pub slot: u64,
+    /// Orderbook PDA that was reconciled
+    pub orderbook: Pubkey,
+    pub users_checked: usize,
+    pub orders_checked: usize,
+    pub divergences: Vec<Divergence>,
+}
+
+impl ReconciliationReport {
+    /// Whether any divergence is critical
+    pub fn has_critical(&self) -> bool {
+        self.divergences.iter().any(|d| d.severity == Severity::Critical)
+    }
+}
+
+/// Service that diffs on-chain, engine and storage state
+pub struct Reconciler<S: Storage> {
+    config: ReconcilerConfig,
+    storage: Arc<S>,
+    engine: Option<Arc<RwLock<MatchingEngine<S>>>>,
+    rpc: RpcClient,
+}
+
+impl<S: Storage> Reconciler<S> {
+    /// Create a new reconciler; without an engine only chain and storage are compared
+    pub fn new(
+        storage: Arc<S>,
+        engine: Option<Arc<RwLock<MatchingEngine<S>>>>,
+        config: ReconcilerConfig,
+    ) -> Self {
+        let rpc = RpcClient::new_with_commitment(config.rpc_url.clone(), config.commitment);
+        Self { config, storage, engine, rpc }
+    }
+
+    /// Reconcile on every interval until storage fails
+    pub async fn run(&self) -> ClobResult<()> {
+        info!("Reconciler running every {:?}", self.config.interval);
+
+        loop {
+            match self.reconcile().await {
+                Ok(report) => self.publish(&report)?,
+                Err(ClobError::NetworkError(e)) => error!("Reconciliation skipped: {}", e),
+                Err(e) => return Err(e),
+            }
+            tokio::time::sleep(self.config.interval).await;
+        }
+    }
+
+    /// Run a single reconciliation pass
+    pub async fn reconcile(&self) -> ClobResult<ReconciliationReport> {
+        let (orderbook, _) = orderbook_address(
+            &self.config.program_id,
+            &self.config.orderbook.base_mint,
+            &self.config.orderbook.quote_mint,
+        );
+        let slot = self.rpc.get_slot().await.map_err(network_error)?;
+
+        let mut divergences = Vec::new();
+        self.check_orderbook(&orderbook, &mut divergences).await?;
+        let users_checked = self.check_balances(&mut divergences).await?;
+        let orders_checked = self.check_open_orders(&mut divergences).await?;
+        self.check_sequences(&mut divergences).await?;
+
+        Ok(ReconciliationReport {
+            generated_at: chrono::Utc::now().timestamp(),
+            slot,
+            orderbook,
+            users_checked,
+            orders_checked,
+            divergences,
+        })
+    }
+
+    /// Compare the on-chain orderbook with the expected market and indexed volume
+    async fn check_orderbook(&self, orderbook: &Pubkey, divergences: &mut Vec<Divergence>) -> ClobResult<()> {
+        let subject = orderbook.to_string();
+        let account = self
+            .rpc
+            .get_account_with_commitment(orderbook, self.config.commitment)
+            .await
+            .map_err(network_error)?
+            .value;
+
+        let Some(account) = account else {
+            divergences.push(Divergence {
+                category: DivergenceCategory::OrderBook,
+                severity: Severity::Critical,
+                subject,
+                field: "account".to_string(),
+                engine: Some("initialized".to_string()),
+                storage: None,
+                chain: None,
+            });
+            return Ok(());
+        };
+        let on_chain = OrderBookView::new(&account.data)?;
+        let (expected, status, order_types) = match &self.engine {
+            Some(engine) => {
+                let engine = engine.read().await;
+                (engine.orderbook_config().clone(), engine.status(), engine.order_types())
+            }
+            None => {
+                let configured = if self.config.orderbook.is_paused { MarketStatus::Paused } else { MarketStatus::Active };
+                let stored = self.storage.get_market_status(&MarketId(*orderbook)).await?;
+                (self.config.orderbook.clone(), stored.unwrap_or(configured), self.config.order_types)
+            }
+        };
+
+        let mut compare = |field: &str, engine: String, chain: String| {
+            if engine != chain {
+                divergences.push(Divergence {
+                    category: DivergenceCategory::OrderBook,
+                    severity: Severity::Critical,
+                    subject: subject.clone(),
+                    field: field.to_string(),
+                    engine: Some(engine),
+                    storage: None,
+                    chain: Some(chain),
+                });
+            }
+        };
+        compare("tick_size", expected.tick_size.to_string(), on_chain.tick_size().to_string());
+        compare("min_order_size", expected.min_order_size.to_string(), on_chain.min_order_size().to_string());
+        compare("status", status.to_string(), on_chain.status().to_string());
+        compare(
+            "order_types",
+            format!("{:?}", AcceptedOrderTypes::from(order_types)),
+            format!("{:?}", AcceptedOrderTypes::from(on_chain.order_types())),
+        );
+        if expected.authority != Pubkey::default() {
+            compare("authority", expected.authority.to_string(), on_chain.authority().to_string());
+        }
+
+        let settled_volume = self.storage.get_settled_volume().await?;
+        if settled_volume != on_chain.total_volume() {
+            divergences.push(Divergence {
+                category: DivergenceCategory::OrderBook,
+                severity: Severity::Warning,
+                subject,
+                field: "total_volume".to_string(),
+                engine: None,
+                storage: Some(settled_volume.to_string()),
+                chain: Some(on_chain.total_volume().to_string()),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Compare on-chain user balances with those derived from indexed events
+    async fn check_balances(&self, divergences: &mut Vec<Divergence>) -> ClobResult<usize> {
+        let ledger = self
+            .storage
+            .get_ledger_balances(&self.config.orderbook.base_mint, &self.config.orderbook.quote_mint, None, None)
+            .await?;
+
+        for chunk in ledger.chunks(MAX_ACCOUNTS_PER_REQUEST) {
+            let addresses: Vec<Pubkey> = chunk
+                .iter()
+                .map(|balance| user_account_address(&self.config.program_id, &balance.user).0)
+                .collect();
+            let accounts = self
+                .rpc
+                .get_multiple_accounts_with_commitment(&addresses, self.config.commitment)
+                .await
+                .map_err(network_error)?
+                .value;
+
+            for (expected, account) in chunk.iter().zip(accounts) {
+                let subject = expected.user.to_string();
+                let Some(account) = account else {
+                    divergences.push(Divergence {
+                        category: DivergenceCategory::Balance,
+                        severity: Severity::Critical,
+                        subject,
+                        field: "account".to_string(),
+                        engine: None,
+                        storage: Some("has activity".to_string()),
+                        chain: None,
+                    });
+                    continue;
+                };
+                let on_chain = UserAccountView::new(&account.data)?;
+
+                for (field, storage, chain) in [
+                    ("base_token_balance", expected.base_balance.to_string(), on_chain.base_token_balance().to_string()),
+                    ("quote_token_balance", expected.quote_balance.to_string(), on_chain.quote_token_balance().to_string()),
+                    ("total_volume_traded", expected.total_volume_traded.to_string(), on_chain.total_volume_traded().to_string()),
+                ] {
+                    if storage != chain {
+                        divergences.push(Divergence {
+                            category: DivergenceCategory::Balance,
+                            severity: Severity::Critical,
+                            subject: subject.clone(),
+                            field: field.to_string(),
+                            engine: None,
+                            storage: Some(storage),
+                            chain: Some(chain),
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(ledger.len())
+    }
+
+    /// Compare resting orders in the engine with open orders in storage
+    async fn check_open_orders(&self, divergences: &mut Vec<Divergence>) -> ClobResult<usize> {
+        let Some(engine) = &self.engine else {
+            return Ok(0);
+        };
+
+        let in_engine: HashMap<u64, Order> = engine
+            .read()
+            .await
+            .get_open_orders()
+            .await
+            .into_iter()
+            .map(|order| (order.order_id, order))
+            .collect();
+        let in_storage: HashMap<u64, Order> = self
+            .storage
+            .get_open_orders()
+            .await?
+            .into_iter()
+            .map(|order| (order.order_id, order))
+            .collect();
+
+        let mut push = |order_id: u64, field: &str, engine: Option<String>, storage: Option<String>| {
+            divergences.push(Divergence {
+                category: DivergenceCategory::OpenOrder,
+                severity: Severity::Critical,
+                subject: order_id.to_string(),
+                field: field.to_string(),
+                engine,
+                storage,
+                chain: None,
+            });
+        };
+
+        for (order_id, order) in &in_engine {
+            match in_storage.get(order_id) {
+                None => push(*order_id, "status", Some(format!("{:?}", order.status)), None),
+                Some(stored) if stored.remaining_quantity != order.remaining_quantity => push(
+                    *order_id,
+                    "remaining_quantity",
+                    Some(order.remaining_quantity.to_string()),
+                    Some(stored.remaining_quantity.to_string()),
+                ),
+                Some(_) => {}
+            }
+        }
+        for (order_id, stored) in &in_storage {
+            if !in_engine.contains_key(order_id) {
+                push(*order_id, "status", None, Some(format!("{:?}", stored.status)));
+            }
+        }
+
+        Ok(in_engine.len().max(in_storage.len()))
+    }
+
+    /// Check the journal, settler and indexer agree on how far trades have progressed
+    async fn check_sequences(&self, divergences: &mut Vec<Divergence>) -> ClobResult<()> {
+        let journal_head = self.storage.get_journal_head().await?;
+        let settled_through = self
+            .storage
+            .get_checkpoint(SETTLER_CHECKPOINT)
+            .await?
+            .map(|checkpoint| checkpoint.position)
+            .unwrap_or(0);
+
+        if journal_head.saturating_sub(settled_through) > self.config.max_settlement_lag {
+            divergences.push(Divergence {
+                category: DivergenceCategory::Sequence,
+                severity: Severity::Warning,
+                subject: "settler".to_string(),
+                field: "journal_sequence".to_string(),
+                engine: Some(journal_head.to_string()),
+                storage: Some(settled_through.to_string()),
+                chain: None,
+            });
+        }
+
+        for settlement in self.storage.get_unindexed_settlements(MAX_ACCOUNTS_PER_REQUEST as u32).await? {
+            divergences.push(Divergence {
+                category: DivergenceCategory::Sequence,
+                severity: Severity::Warning,
+                subject: format!("journal:{}", settlement.journal_sequence),
+                field: "signature".to_string(),
+                engine: None,
+                storage: settlement.signature,
+                chain: None,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Alert on divergences and write the report
+    fn publish(&self, report: &ReconciliationReport) -> ClobResult<()> {
+        for divergence in &report.divergences {
+            let values = format!(
+                "engine={:?} storage={:?} chain={:?}",
+                divergence.engine, divergence.storage, divergence.chain
+            );
+            match divergence.severity {
+                Severity::Critical => error!(
+                    "Reconciliation divergence {:?} {}.{}: {}",
+                    divergence.category, divergence.subject, divergence.field, values
+                ),
+                Severity::Warning => warn!(
+                    "Reconciliation divergence {:?} {}.{}: {}",
+                    divergence.category, divergence.subject, divergence.field, values
+                ),
+            }
+        }
+        info!(
+            "Reconciled {} users and {} orders at slot {}: {} divergences",
+            report.users_checked,
+            report.orders_checked,
+            report.slot,
+            report.divergences.len()
+        );
+
+        if let Some(path) = &self.config.report_path {
+            write_report(path, report)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write a report as pretty JSON, replacing the previous one atomically
+pub fn write_report(path: &Path, report: &ReconciliationReport) -> ClobResult<()> {
+    let json = serde_json::to_string_pretty(report)
+        .map_err(|e| ClobError::SerializationError(e.to_string()))?;
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, json).map_err(|e| ClobError::StorageError(e.to_string()))?;
+    std::fs::rename(&tmp, path).map_err(|e| ClobError::StorageError(e.to_string()))?;
+    Ok(())
+}
+
+fn network_error<E: std::fmt::Display>(e: E) -> ClobError {
+    ClobError::NetworkError(e.to_string())
+}
@@ -0,0 +1,233 @@
+/// Parquet layout of recordings
+///
+/// A book file holds one row per sample, with each side as two parallel
+/// lists of prices and quantities, best level first; an empty side is an
+/// empty list. A trade file holds one row per fill. Files are written with
+/// ZSTD compression, in a single row group per segment.
+
+use crate::{BookSample, RecordedTrade};
+use svm_clob_types::*;
+use arrow_array::builder::{ListBuilder, UInt64Builder};
+use arrow_array::{Array, ArrayRef, Int64Array, ListArray, RecordBatch, StringArray, UInt64Array, UInt8Array};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use solana_sdk::pubkey::Pubkey;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+fn level_list(name: &str) -> Field {
+    Field::new_list(name, Field::new("item", DataType::UInt64, true), false)
+}
+
+fn book_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("timestamp_ms", DataType::Int64, false),
+        Field::new("sequence", DataType::UInt64, false),
+        Field::new("book_timestamp", DataType::Int64, false),
+        level_list("bid_prices"),
+        level_list("bid_quantities"),
+        level_list("ask_prices"),
+        level_list("ask_quantities"),
+    ]))
+}
+
+fn trade_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("timestamp", DataType::Int64, false),
+        Field::new("journal_sequence", DataType::UInt64, false),
+        Field::new("trade_id", DataType::UInt64, false),
+        Field::new("maker_order_id", DataType::UInt64, false),
+        Field::new("taker_order_id", DataType::UInt64, false),
+        Field::new("maker", DataType::Utf8, false),
+        Field::new("taker", DataType::Utf8, false),
+        Field::new("price", DataType::UInt64, false),
+        Field::new("quantity", DataType::UInt64, false),
+        Field::new("maker_side", DataType::UInt8, false),
+        Field::new("received_at_us", DataType::Int64, false),
+        Field::new("matched_at_us", DataType::Int64, false),
+    ]))
+}
+
+/// Prices or quantities of one side of every sample
+fn levels<F>(samples: &[BookSample], field: F) -> ArrayRef
+where
+    F: Fn(&BookSample) -> Vec<u64>,
+{
+    let mut builder = ListBuilder::new(UInt64Builder::new());
+    for sample in samples {
+        builder.values().append_slice(&field(sample));
+        builder.append(true);
+    }
+    Arc::new(builder.finish())
+}
+
+pub fn encode_books(samples: &[BookSample]) -> ClobResult<RecordBatch> {
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(Int64Array::from_iter_values(samples.iter().map(|sample| sample.timestamp_ms))),
+        Arc::new(UInt64Array::from_iter_values(samples.iter().map(|sample| sample.book.sequence_number))),
+        Arc::new(Int64Array::from_iter_values(samples.iter().map(|sample| sample.book.timestamp))),
+        levels(samples, |sample| sample.book.bids.iter().map(|(price, _)| *price).collect()),
+        levels(samples, |sample| sample.book.bids.iter().map(|(_, quantity)| *quantity).collect()),
+        levels(samples, |sample| sample.book.asks.iter().map(|(price, _)| *price).collect()),
+        levels(samples, |sample| sample.book.asks.iter().map(|(_, quantity)| *quantity).collect()),
+    ];
+    RecordBatch::try_new(book_schema(), columns).map_err(format_error)
+}
+
+pub fn encode_trades(trades: &[RecordedTrade]) -> ClobResult<RecordBatch> {
+    let u64s = |field: fn(&RecordedTrade) -> u64| -> ArrayRef { Arc::new(UInt64Array::from_iter_values(trades.iter().map(field))) };
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(Int64Array::from_iter_values(trades.iter().map(|fill| fill.trade.timestamp))),
+        u64s(|fill| fill.journal_sequence),
+        u64s(|fill| fill.trade.trade_id),
+        u64s(|fill| fill.trade.maker_order_id),
+        u64s(|fill| fill.trade.taker_order_id),
+        Arc::new(StringArray::from_iter_values(trades.iter().map(|fill| fill.maker.to_string()))),
+        Arc::new(StringArray::from_iter_values(trades.iter().map(|fill| fill.taker.to_string()))),
+        u64s(|fill| fill.trade.price),
+        u64s(|fill| fill.trade.quantity),
+        Arc::new(UInt8Array::from_iter_values(trades.iter().map(|fill| fill.trade.maker_side as u8))),
+        Arc::new(Int64Array::from_iter_values(trades.iter().map(|fill| fill.trade.received_at_us))),
+        Arc::new(Int64Array::from_iter_values(trades.iter().map(|fill| fill.trade.matched_at_us))),
+    ];
+    RecordBatch::try_new(trade_schema(), columns).map_err(format_error)
+}
+
+pub fn decode_books(batch: &RecordBatch, market_id: MarketId) -> ClobResult<Vec<BookSample>> {
+    let timestamps = column::<Int64Array>(batch, "timestamp_ms")?;
+    let sequences = column::<UInt64Array>(batch, "sequence")?;
+    let book_timestamps = column::<Int64Array>(batch, "book_timestamp")?;
+    let sides = [
+        (column::<ListArray>(batch, "bid_prices")?, column::<ListArray>(batch, "bid_quantities")?),
+        (column::<ListArray>(batch, "ask_prices")?, column::<ListArray>(batch, "ask_quantities")?),
+    ];
+
+    let mut samples = Vec::with_capacity(batch.num_rows());
+    for row in 0..batch.num_rows() {
+        let mut books = Vec::with_capacity(2);
+        for (prices, quantities) in &sides {
+            let prices = list_values(prices, row)?;
+            let quantities = list_values(quantities, row)?;
+            if prices.len() != quantities.len() {
+                return Err(ClobError::SerializationError(format!(
+                    "Book sample {} has {} prices but {} quantities",
+                    row,
+                    prices.len(),
+                    quantities.len()
+                )));
+            }
+            books.push(prices.into_iter().zip(quantities).collect::<Vec<(u64, u64)>>());
+        }
+        let asks = books.pop().unwrap_or_default();
+        let bids = books.pop().unwrap_or_default();
+        samples.push(BookSample {
+            timestamp_ms: timestamps.value(row),
+            book: OrderBookSnapshot {
+                market_id,
+                bids,
+                asks,
+                sequence_number: sequences.value(row),
+                timestamp: book_timestamps.value(row),
+            },
+        });
+    }
+    Ok(samples)
+}
+
+pub fn decode_trades(batch: &RecordBatch, market_id: MarketId) -> ClobResult<Vec<RecordedTrade>> {
+    let timestamps = column::<Int64Array>(batch, "timestamp")?;
+    let sequences = column::<UInt64Array>(batch, "journal_sequence")?;
+    let trade_ids = column::<UInt64Array>(batch, "trade_id")?;
+    let maker_order_ids = column::<UInt64Array>(batch, "maker_order_id")?;
+    let taker_order_ids = column::<UInt64Array>(batch, "taker_order_id")?;
+    let makers = column::<StringArray>(batch, "maker")?;
+    let takers = column::<StringArray>(batch, "taker")?;
+    let prices = column::<UInt64Array>(batch, "price")?;
+    let quantities = column::<UInt64Array>(batch, "quantity")?;
+    let maker_sides = column::<UInt8Array>(batch, "maker_side")?;
+    // Files recorded before fills carried engine times have neither column
+    let received = optional_column::<Int64Array>(batch, "received_at_us");
+    let matched = optional_column::<Int64Array>(batch, "matched_at_us");
+
+    (0..batch.num_rows())
+        .map(|row| {
+            Ok(RecordedTrade {
+                journal_sequence: sequences.value(row),
+                trade: TradeExecution {
+                    trade_id: trade_ids.value(row),
+                    market_id,
+                    maker_order_id: maker_order_ids.value(row),
+                    taker_order_id: taker_order_ids.value(row),
+                    price: prices.value(row),
+                    quantity: quantities.value(row),
+                    timestamp: timestamps.value(row),
+                    maker_side: OrderSide::try_from(maker_sides.value(row)).map_err(|_| ClobError::InvalidOrderSide)?,
+                    received_at_us: received.map_or(0, |times| times.value(row)),
+                    matched_at_us: matched.map_or(0, |times| times.value(row)),
+                    version: 0,
+                    status: TradeStatus::Active,
+                },
+                maker: parse_pubkey(makers.value(row))?,
+                taker: parse_pubkey(takers.value(row))?,
+            })
+        })
+        .collect()
+}
+
+/// Write `batch` to `path`, through a temporary file so readers never see a partial file
+pub fn write_file(path: &Path, batch: &RecordBatch) -> ClobResult<()> {
+    let partial = path.with_extension("parquet.partial");
+    let file = File::create(&partial).map_err(io_error)?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .build();
+    let mut writer = ArrowWriter::try_new(file, batch.schema(), Some(properties)).map_err(format_error)?;
+    writer.write(batch).map_err(format_error)?;
+    writer.close().map_err(format_error)?;
+    std::fs::rename(&partial, path).map_err(io_error)
+}
+
+/// Every record batch in the file at `path`
+pub fn read_file(path: &Path) -> ClobResult<Vec<RecordBatch>> {
+    let file = File::open(path).map_err(io_error)?;
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+        .and_then(|builder| builder.build())
+        .map_err(format_error)?;
+    reader
+        .map(|batch| batch.map_err(|e| ClobError::SerializationError(format!("{}: {}", path.display(), e))))
+        .collect()
+}
+
+fn column<'a, T: Array + 'static>(batch: &'a RecordBatch, name: &str) -> ClobResult<&'a T> {
+    optional_column(batch, name)
+        .ok_or_else(|| ClobError::SerializationError(format!("Recording has no {} column of the expected type", name)))
+}
+
+fn optional_column<'a, T: Array + 'static>(batch: &'a RecordBatch, name: &str) -> Option<&'a T> {
+    batch.column_by_name(name).and_then(|column| column.as_any().downcast_ref::<T>())
+}
+
+fn list_values(list: &ListArray, row: usize) -> ClobResult<Vec<u64>> {
+    let values = list.value(row);
+    let values = values
+        .as_any()
+        .downcast_ref::<UInt64Array>()
+        .ok_or_else(|| ClobError::SerializationError("Book levels are not u64 lists".to_string()))?;
+    Ok(values.values().to_vec())
+}
+
+fn parse_pubkey(value: &str) -> ClobResult<Pubkey> {
+    value.parse().map_err(|_| ClobError::SerializationError(format!("Invalid pubkey {}", value)))
+}
+
+fn format_error<E: std::fmt::Display>(e: E) -> ClobError {
+    ClobError::SerializationError(e.to_string())
+}
+
+pub fn io_error(e: std::io::Error) -> ClobError {
+    ClobError::StorageError(e.to_string())
+}
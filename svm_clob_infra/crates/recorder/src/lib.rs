@@ -0,0 +1,226 @@
+/// Depth-of-Market Recorder for SVM CLOB Infrastructure
+///
+/// `DomRecorder` samples the engine's L2 book at a fixed cadence, reads every
+/// fill of the market from the engine journal and writes both to
+/// ZSTD-compressed Parquet files for research. Each segment of
+/// `segment_interval` becomes one file per kind, named after the first and
+/// last timestamp it holds, in milliseconds:
+///
+/// ```text
+/// <dir>/<market>/books/<first>-<last>.parquet
+/// <dir>/<market>/trades/<first>-<last>.parquet
+/// ```
+///
+/// The journal checkpoint only advances once a segment's files are written,
+/// so a restart reads the fills of an unwritten segment again; its book
+/// samples are lost. `Recording` reads the files back: the book as of any
+/// instant, the samples of a range and the fills of a range.
+
+mod columns;
+pub mod reader;
+
+pub use reader::Recording;
+
+use svm_clob_types::*;
+use svm_clob_matching_engine::MatchingEngine;
+use svm_clob_storage::Storage;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, info};
+
+/// Checkpoint service name used by the recorder
+pub const CHECKPOINT_SERVICE: &str = "dom_recorder";
+
+const BOOKS_DIR: &str = "books";
+const TRADES_DIR: &str = "trades";
+
+/// Recorder configuration
+#[derive(Debug, Clone)]
+pub struct RecorderConfig {
+    /// Root directory of recordings; each market records under its own subdirectory
+    pub dir: PathBuf,
+    pub market_id: MarketId,
+    /// Time between book samples
+    pub snapshot_interval: Duration,
+    /// Price levels per side kept in a sample
+    pub depth: usize,
+    /// Time covered by one file
+    pub segment_interval: Duration,
+    /// Journal entries read per query
+    pub batch_size: u32,
+}
+
+impl RecorderConfig {
+    pub fn new(dir: impl Into<PathBuf>, market_id: MarketId) -> Self {
+        Self {
+            dir: dir.into(),
+            market_id,
+            snapshot_interval: Duration::from_secs(1),
+            depth: 50,
+            segment_interval: Duration::from_secs(3600),
+            batch_size: 1000,
+        }
+    }
+}
+
+/// L2 book as sampled
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BookSample {
+    /// Wall clock time of the sample
+    pub timestamp_ms: i64,
+    pub book: OrderBookSnapshot,
+}
+
+/// Fill read from the journal
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedTrade {
+    pub journal_sequence: u64,
+    pub trade: TradeExecution,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+}
+
+impl RecordedTrade {
+    pub fn timestamp_ms(&self) -> i64 {
+        self.trade.timestamp.saturating_mul(1000)
+    }
+}
+
+/// Samples and fills of the segment being recorded
+#[derive(Default)]
+struct Segment {
+    books: Vec<BookSample>,
+    trades: Vec<RecordedTrade>,
+}
+
+/// Records one market's book and fills to Parquet
+pub struct DomRecorder<S: Storage> {
+    config: RecorderConfig,
+    storage: Arc<S>,
+    engine: Arc<RwLock<MatchingEngine<S>>>,
+}
+
+impl<S: Storage> DomRecorder<S> {
+    /// Create a new recorder
+    pub fn new(storage: Arc<S>, engine: Arc<RwLock<MatchingEngine<S>>>, config: RecorderConfig) -> Self {
+        Self { config, storage, engine }
+    }
+
+    /// Run until storage or the file system fails
+    pub async fn run(&self) -> ClobResult<()> {
+        let market_dir = market_dir(&self.config.dir, &self.config.market_id);
+        for kind in [BOOKS_DIR, TRADES_DIR] {
+            tokio::fs::create_dir_all(market_dir.join(kind)).await.map_err(columns::io_error)?;
+        }
+
+        let mut position = self
+            .storage
+            .get_checkpoint(CHECKPOINT_SERVICE)
+            .await?
+            .map(|checkpoint| checkpoint.position)
+            .unwrap_or(0);
+        info!(
+            "Recording the book of {} to {} from journal sequence {}",
+            self.config.market_id,
+            market_dir.display(),
+            position
+        );
+
+        let mut segment = Segment::default();
+        let mut samples = tokio::time::interval(self.config.snapshot_interval.max(Duration::from_millis(1)));
+        let mut segment_end = tokio::time::Instant::now() + self.config.segment_interval;
+        loop {
+            samples.tick().await;
+            segment.books.push(self.sample().await?);
+            position = self.read_trades(position, &mut segment.trades).await?;
+
+            if tokio::time::Instant::now() >= segment_end {
+                self.write_segment(&market_dir, std::mem::take(&mut segment)).await?;
+                self.save_checkpoint(position).await?;
+                segment_end += self.config.segment_interval;
+            }
+        }
+    }
+
+    async fn sample(&self) -> ClobResult<BookSample> {
+        let mut book = self.engine.read().await.get_order_book_snapshot().await?;
+        book.bids.truncate(self.config.depth);
+        book.asks.truncate(self.config.depth);
+        Ok(BookSample {
+            timestamp_ms: chrono::Utc::now().timestamp_millis(),
+            book,
+        })
+    }
+
+    /// Append the market's fills journaled after `position`, returning the new position
+    async fn read_trades(&self, mut position: u64, trades: &mut Vec<RecordedTrade>) -> ClobResult<u64> {
+        loop {
+            let entries = self.storage.get_journal_entries(position, self.config.batch_size).await?;
+            for entry in &entries {
+                if let JournalEvent::TradeExecuted { trade, maker, taker } = &entry.event {
+                    if trade.market_id == self.config.market_id {
+                        trades.push(RecordedTrade {
+                            journal_sequence: entry.sequence,
+                            trade: trade.clone(),
+                            maker: *maker,
+                            taker: *taker,
+                        });
+                    }
+                }
+                position = entry.sequence;
+            }
+            if entries.len() < self.config.batch_size as usize {
+                return Ok(position);
+            }
+        }
+    }
+
+    async fn write_segment(&self, market_dir: &Path, segment: Segment) -> ClobResult<()> {
+        let market_dir = market_dir.to_path_buf();
+        let (samples, fills) = (segment.books.len(), segment.trades.len());
+        tokio::task::spawn_blocking(move || -> ClobResult<()> {
+            if let (Some(first), Some(last)) = (segment.books.first(), segment.books.last()) {
+                let path = segment_path(&market_dir.join(BOOKS_DIR), first.timestamp_ms, last.timestamp_ms);
+                columns::write_file(&path, &columns::encode_books(&segment.books)?)?;
+            }
+            // Fills read again after a restart can be older than the segment
+            let times = segment.trades.iter().map(RecordedTrade::timestamp_ms);
+            if let (Some(first), Some(last)) = (times.clone().min(), times.max()) {
+                let path = segment_path(&market_dir.join(TRADES_DIR), first, last);
+                columns::write_file(&path, &columns::encode_trades(&segment.trades)?)?;
+            }
+            Ok(())
+        })
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))??;
+        debug!("Recorded {} book samples and {} fills", samples, fills);
+        Ok(())
+    }
+
+    async fn save_checkpoint(&self, sequence: u64) -> ClobResult<()> {
+        self.storage.save_checkpoint(&Checkpoint {
+            service: CHECKPOINT_SERVICE.to_string(),
+            position: sequence,
+            cursor: None,
+        }).await
+    }
+}
+
+fn market_dir(dir: &Path, market_id: &MarketId) -> PathBuf {
+    dir.join(market_id.to_string())
+}
+
+fn segment_path(dir: &Path, first_ms: i64, last_ms: i64) -> PathBuf {
+    dir.join(format!("{:020}-{:020}.parquet", first_ms, last_ms))
+}
+
+/// First and last timestamp of a segment file, from its name
+fn segment_range(path: &Path) -> Option<(i64, i64)> {
+    let name = path.file_name()?.to_str()?.strip_suffix(".parquet")?;
+    let (first, last) = name.split_once('-')?;
+    Some((first.parse().ok()?, last.parse().ok()?))
+}
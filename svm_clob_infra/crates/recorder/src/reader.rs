@@ -0,0 +1,104 @@
+/// Reading recordings back
+///
+/// `Recording` only opens the files whose name range overlaps a query. Fills
+/// come back in journal order without duplicates, since a segment cut short
+/// by a restart may be recorded again by the next one.
+
+use crate::{columns, market_dir, segment_range, BookSample, RecordedTrade, BOOKS_DIR, TRADES_DIR};
+use svm_clob_types::*;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Recorded books and fills of one market
+pub struct Recording {
+    dir: PathBuf,
+    market_id: MarketId,
+}
+
+impl Recording {
+    /// Recording of `market_id` under the recorder directory `dir`
+    pub fn open(dir: impl AsRef<Path>, market_id: MarketId) -> Self {
+        Self {
+            dir: market_dir(dir.as_ref(), &market_id),
+            market_id,
+        }
+    }
+
+    /// Latest sample at or before `timestamp_ms`
+    pub fn book_at(&self, timestamp_ms: i64) -> ClobResult<Option<BookSample>> {
+        // Samples are taken in order, so the latest one is in the last file starting by then
+        let Some((path, _)) = self
+            .files(BOOKS_DIR)?
+            .into_iter()
+            .filter(|(_, (first, _))| *first <= timestamp_ms)
+            .max_by_key(|(_, (first, _))| *first)
+        else {
+            return Ok(None);
+        };
+        let samples = self.read_books(&path)?;
+        Ok(samples.into_iter().rev().find(|sample| sample.timestamp_ms <= timestamp_ms))
+    }
+
+    /// Samples taken from `from_ms` through `to_ms`, oldest first
+    pub fn books(&self, from_ms: i64, to_ms: i64) -> ClobResult<Vec<BookSample>> {
+        let mut samples = Vec::new();
+        for (path, _) in self.overlapping(BOOKS_DIR, from_ms, to_ms)? {
+            samples.extend(
+                self.read_books(&path)?
+                    .into_iter()
+                    .filter(|sample| (from_ms..=to_ms).contains(&sample.timestamp_ms)),
+            );
+        }
+        samples.sort_by_key(|sample| sample.timestamp_ms);
+        Ok(samples)
+    }
+
+    /// Fills executed from `from_ms` through `to_ms`, in journal order
+    pub fn trades(&self, from_ms: i64, to_ms: i64) -> ClobResult<Vec<RecordedTrade>> {
+        let mut trades = BTreeMap::new();
+        for (path, _) in self.overlapping(TRADES_DIR, from_ms, to_ms)? {
+            for batch in columns::read_file(&path)? {
+                for fill in columns::decode_trades(&batch, self.market_id)? {
+                    if (from_ms..=to_ms).contains(&fill.timestamp_ms()) {
+                        trades.insert(fill.journal_sequence, fill);
+                    }
+                }
+            }
+        }
+        Ok(trades.into_values().collect())
+    }
+
+    fn read_books(&self, path: &Path) -> ClobResult<Vec<BookSample>> {
+        let mut samples = Vec::new();
+        for batch in columns::read_file(path)? {
+            samples.extend(columns::decode_books(&batch, self.market_id)?);
+        }
+        Ok(samples)
+    }
+
+    fn overlapping(&self, kind: &str, from_ms: i64, to_ms: i64) -> ClobResult<Vec<(PathBuf, (i64, i64))>> {
+        Ok(self
+            .files(kind)?
+            .into_iter()
+            .filter(|(_, (first, last))| *first <= to_ms && *last >= from_ms)
+            .collect())
+    }
+
+    /// Segment files of one kind with their time ranges; none before the first segment is written
+    fn files(&self, kind: &str) -> ClobResult<Vec<(PathBuf, (i64, i64))>> {
+        let dir = self.dir.join(kind);
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(columns::io_error(e)),
+        };
+        let mut files = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(columns::io_error)?.path();
+            if let Some(range) = segment_range(&path) {
+                files.push((path, range));
+            }
+        }
+        Ok(files)
+    }
+}
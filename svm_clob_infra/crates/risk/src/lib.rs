@@ -0,0 +1,188 @@
+/// Risk Engine for SVM CLOB Infrastructure
+///
+/// This module enforces per-market and per-user limits (maximum order size,
+/// maximum open notional and price collars around the mid, or around an
+/// external oracle's price when one is fed in) as a pre-trade hook in the
+/// matching engine. Limits can be changed at runtime through the admin API.
+
+use svm_clob_types::*;
+use svm_clob_matching_engine::{PreTradeContext, PreTradeHook};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::sync::{Arc, RwLock};
+use tracing::{info, warn};
+
+pub mod oracle;
+
+pub use oracle::{HermesClient, ReferencePrice};
+
+/// Risk limits; `None` leaves a dimension unchecked
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RiskLimits {
+    /// Largest accepted order quantity
+    pub max_order_size: Option<u64>,
+    /// Largest price * remaining quantity across a user's resting and incoming orders
+    pub max_open_notional: Option<u128>,
+    /// Largest distance of a limit price from the oracle's price, or from mid
+    /// without a fresh one, in basis points
+    pub price_collar_bps: Option<u64>,
+}
+
+impl RiskLimits {
+    /// Fill unset dimensions from `fallback`
+    pub fn or(&self, fallback: &RiskLimits) -> RiskLimits {
+        RiskLimits {
+            max_order_size: self.max_order_size.or(fallback.max_order_size),
+            max_open_notional: self.max_open_notional.or(fallback.max_open_notional),
+            price_collar_bps: self.price_collar_bps.or(fallback.price_collar_bps),
+        }
+    }
+}
+
+impl WirePayload for RiskLimits {
+    const TYPE: &'static str = "RiskLimits";
+}
+
+/// Market-wide limits with per-user overrides
+pub struct RiskEngine {
+    market_limits: RwLock<RiskLimits>,
+    user_limits: DashMap<Pubkey, RiskLimits>,
+    /// Latest oracle price and the time (unix seconds) after which it is stale
+    reference: RwLock<Option<(ReferencePrice, i64)>>,
+    /// Time source deciding whether the oracle price is stale
+    clock: Arc<dyn Clock>,
+}
+
+impl RiskEngine {
+    /// Create a risk engine with the given market-wide limits
+    pub fn new(market_limits: RiskLimits) -> Self {
+        Self {
+            market_limits: RwLock::new(market_limits),
+            user_limits: DashMap::new(),
+            reference: RwLock::new(None),
+            clock: system_clock(),
+        }
+    }
+
+    /// Read time from `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Take `reference` as the market's price until `max_age_secs` after it
+    /// was published
+    pub fn set_reference_price(&self, reference: ReferencePrice, max_age_secs: i64) {
+        let stale_after = reference.publish_time.saturating_add(max_age_secs);
+        *self.reference.write().expect("reference price lock poisoned") = Some((reference, stale_after));
+    }
+
+    /// Oracle price of the market, unless none was fed in or it went stale
+    pub fn reference_price(&self) -> Option<ReferencePrice> {
+        let now = self.clock.now();
+        self.reference
+            .read()
+            .expect("reference price lock poisoned")
+            .and_then(|(reference, stale_after)| (now <= stale_after).then_some(reference))
+    }
+
+    /// Current market-wide limits
+    pub fn market_limits(&self) -> RiskLimits {
+        self.market_limits.read().expect("risk limits lock poisoned").clone()
+    }
+
+    /// Replace the market-wide limits
+    pub fn set_market_limits(&self, limits: RiskLimits) {
+        info!("Market risk limits set to {:?}", limits);
+        *self.market_limits.write().expect("risk limits lock poisoned") = limits;
+    }
+
+    /// Per-user override, if any
+    pub fn user_limits(&self, user: &Pubkey) -> Option<RiskLimits> {
+        self.user_limits.get(user).map(|entry| entry.value().clone())
+    }
+
+    /// Every per-user override
+    pub fn all_user_limits(&self) -> Vec<(Pubkey, RiskLimits)> {
+        self.user_limits
+            .iter()
+            .map(|entry| (*entry.key(), entry.value().clone()))
+            .collect()
+    }
+
+    /// Set a per-user override; unset dimensions fall back to the market limits
+    pub fn set_user_limits(&self, user: Pubkey, limits: RiskLimits) {
+        info!("Risk limits for {} set to {:?}", user, limits);
+        self.user_limits.insert(user, limits);
+    }
+
+    /// Remove a per-user override
+    pub fn clear_user_limits(&self, user: &Pubkey) -> Option<RiskLimits> {
+        self.user_limits.remove(user).map(|(_, limits)| limits)
+    }
+
+    /// Limits that apply to a user
+    pub fn effective_limits(&self, user: &Pubkey) -> RiskLimits {
+        let market = self.market_limits();
+        match self.user_limits(user) {
+            Some(user_limits) => user_limits.or(&market),
+            None => market,
+        }
+    }
+}
+
+impl PreTradeHook for RiskEngine {
+    fn check(&self, order: &Order, context: &PreTradeContext<'_>) -> ClobResult<()> {
+        let limits = self.effective_limits(&order.owner);
+
+        if let Some(max_order_size) = limits.max_order_size {
+            if order.quantity > max_order_size {
+                return reject(order, format!("quantity {} exceeds max order size {}", order.quantity, max_order_size));
+            }
+        }
+
+        // Collars anchor on the oracle's price while it is fresh, else on mid
+        let anchor = match self.reference_price() {
+            Some(reference) => Some(("oracle price", reference.price.0)),
+            None => match (context.best_bid, context.best_ask) {
+                (Some(bid), Some(ask)) => Some(("mid", bid / 2 + ask / 2)),
+                (Some(price), None) | (None, Some(price)) => Some(("mid", price)),
+                (None, None) => None,
+            },
+        };
+
+        if let (Some(collar_bps), Some((name, anchor)), false) =
+            (limits.price_collar_bps, anchor, order.order_type == OrderType::Market)
+        {
+            let distance = order.price.abs_diff(anchor) as u128 * 10_000;
+            if anchor > 0 && distance > collar_bps as u128 * anchor as u128 {
+                return reject(
+                    order,
+                    format!("price {} is more than {} bps from {} {}", order.price, collar_bps, name, anchor),
+                );
+            }
+        }
+
+        if let Some(max_open_notional) = limits.max_open_notional {
+            // Market orders carry no price; value them at the far touch
+            let price = match (order.order_type, order.side) {
+                (OrderType::Market, OrderSide::Bid) => context.best_ask.unwrap_or(0),
+                (OrderType::Market, OrderSide::Ask) => context.best_bid.unwrap_or(0),
+                _ => order.price,
+            };
+            let resting: Notional = context.open_orders.iter().map(Order::remaining_notional).sum();
+            let notional = resting + Price(price).notional(Quantity(order.quantity));
+            if notional > Notional(max_open_notional) {
+                return reject(order, format!("open notional {} exceeds {}", notional, max_open_notional));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn reject(order: &Order, reason: String) -> ClobResult<()> {
+    warn!("Rejected order {} from {}: {}", order.order_id, order.owner, reason);
+    Err(ClobError::RiskLimitExceeded(reason))
+}
@@ -0,0 +1,124 @@
+/// External reference prices
+///
+/// `HermesClient` polls a Pyth Hermes endpoint for the latest price of one
+/// feed and converts it into the market's native price units. Handed to
+/// `RiskEngine::set_reference_price`, the reference replaces the book's mid as
+/// the anchor of price collars and as the mark of portfolios, and lets the
+/// node halt matching when the book strays from it (see `in_band`). A
+/// reference is only used until `max_age_secs` after its publish time, so a
+/// stalled feed falls back to the mid rather than pinning the market to a
+/// stale price.
+
+use svm_clob_types::{ClobError, ClobResult, MarketUnits, Price};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Price of the market from an external oracle, in native units
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReferencePrice {
+    pub price: Price,
+    /// Half-width of the oracle's confidence interval
+    pub confidence: Price,
+    /// When the oracle published the price (unix seconds)
+    pub publish_time: i64,
+}
+
+/// Whether `mid` lies within `band_bps` basis points of `reference`
+pub fn in_band(mid: Price, reference: &ReferencePrice, band_bps: u64) -> bool {
+    mid.distance_bps(reference.price) <= band_bps as u128
+}
+
+/// Latest update of one feed, as Hermes serves it
+#[derive(Deserialize)]
+struct HermesResponse {
+    parsed: Vec<HermesUpdate>,
+}
+
+#[derive(Deserialize)]
+struct HermesUpdate {
+    price: HermesPrice,
+}
+
+/// Fixed-point price: `price * 10^expo` whole quote tokens per whole base token
+#[derive(Deserialize)]
+struct HermesPrice {
+    price: String,
+    conf: String,
+    expo: i32,
+    publish_time: i64,
+}
+
+/// Client of a Pyth Hermes price service for one market's feed
+#[derive(Debug, Clone)]
+pub struct HermesClient {
+    http: reqwest::Client,
+    base_url: String,
+    /// Hex ID of the price feed, e.g. SOL/USD's
+    feed_id: String,
+    /// Decimals of the market the feed prices
+    units: MarketUnits,
+}
+
+impl HermesClient {
+    /// Client of the Hermes service at `base_url` (e.g.
+    /// `https://hermes.pyth.network`) reading `feed_id` for a market of `units`
+    pub fn new(base_url: &str, feed_id: &str, units: MarketUnits) -> ClobResult<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .map_err(|e| ClobError::NetworkError(e.to_string()))?;
+        Ok(Self {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            feed_id: feed_id.trim_start_matches("0x").to_string(),
+            units,
+        })
+    }
+
+    /// Latest price of the feed
+    pub async fn latest(&self) -> ClobResult<ReferencePrice> {
+        let response = self
+            .http
+            .get(format!("{}/v2/updates/price/latest", self.base_url))
+            .query(&[("ids[]", self.feed_id.as_str()), ("parsed", "true")])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| ClobError::NetworkError(e.to_string()))?;
+        let body: HermesResponse = response
+            .json()
+            .await
+            .map_err(|e| ClobError::SerializationError(e.to_string()))?;
+        let update = body
+            .parsed
+            .into_iter()
+            .next()
+            .ok_or_else(|| ClobError::NetworkError(format!("Hermes returned no price for feed {}", self.feed_id)))?;
+        self.reference(&update.price)
+    }
+
+    fn reference(&self, price: &HermesPrice) -> ClobResult<ReferencePrice> {
+        let invalid = || ClobError::InvalidPrice(format!("Oracle price {}e{} is out of range", price.price, price.expo));
+        let mantissa: u64 = price.price.parse().map_err(|_| invalid())?;
+        let confidence: u64 = price.conf.parse().map_err(|_| invalid())?;
+        Ok(ReferencePrice {
+            price: native_price(mantissa, price.expo, &self.units).ok_or_else(invalid)?,
+            confidence: native_price(confidence, price.expo, &self.units).ok_or_else(invalid)?,
+            publish_time: price.publish_time,
+        })
+    }
+}
+
+/// Native price of `mantissa * 10^expo` whole quote tokens per whole base
+/// token, rounded half up to a native unit
+fn native_price(mantissa: u64, expo: i32, units: &MarketUnits) -> Option<Price> {
+    // native = ui * 10^quote / 10^base
+    let scale = expo + units.quote_decimals as i32 - units.base_decimals as i32;
+    let native = if scale >= 0 {
+        (mantissa as u128).checked_mul(10u128.checked_pow(scale as u32)?)?
+    } else {
+        let divisor = 10u128.checked_pow(scale.unsigned_abs())?;
+        (mantissa as u128 + divisor / 2) / divisor
+    };
+    u64::try_from(native).ok().map(Price)
+}
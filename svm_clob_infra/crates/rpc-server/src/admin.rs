@@ -0,0 +1,1470 @@
+/// Admin API
+///
+/// Operator endpoints under `/api/v1/admin`, authenticated with the bearer token
+/// from the `[admin]` configuration section. Without a token every admin route
+/// answers 403. Every change to a runtime setting is recorded in the admin
+/// audit log before it is applied; a change that cannot be recorded is refused.
+
+use crate::rate_limit::RateLimits;
+use crate::tenant::{issue_key, UsageQuery};
+use crate::{check_page, fee_report_response, ApiError, FeeReportQuery, JsonRpcResponse, RpcServerState};
+use svm_clob_risk::RiskLimits;
+use svm_clob_storage::Storage;
+use svm_clob_types::{
+    AdminAuditEntry, AlertKind, ApiAuditEntry, ApiAuditQuery, DeadLetter, DeadLetterStatus, DeadLetterTrade,
+    EnginePause, Envelope, FeedEntitlement, MarketMakerObligation, MarketMakerScorecard, MarketStatus, MmpConfig, MmpStatus, Page, Paginated, PauseMode, SpeedBump, SurveillanceAlert,
+    ReversalStatus, ScorecardQuery, Suspension, SuspensionReason, SuspensionSubject, Tenant, TenantApiKey, TenantUsage,
+    TradeAdjustment, WirePayload,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::{Json, Response},
+    routing::{delete, get, post, put},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+/// Header naming the operator behind a change, for the audit logs
+pub(crate) const OPERATOR_HEADER: &str = "x-admin-operator";
+
+/// Per-user risk override as returned by the admin API
+#[derive(Serialize, Deserialize)]
+pub struct UserRiskLimits {
+    pub user: String,
+    /// Override as configured
+    pub limits: RiskLimits,
+    /// Override merged with the market limits
+    pub effective: RiskLimits,
+}
+
+impl WirePayload for UserRiskLimits {
+    const TYPE: &'static str = "UserRiskLimits";
+}
+
+/// Settings of a tenant as set through the admin API
+#[derive(Serialize, Deserialize)]
+pub struct TenantSettings {
+    pub name: String,
+    #[serde(default)]
+    pub max_requests_per_second: Option<u32>,
+    #[serde(default)]
+    pub max_orders_per_second: Option<u32>,
+    #[serde(default)]
+    pub fee_share_bps: u64,
+    #[serde(default)]
+    pub referral_code: Option<String>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+/// API key as issued; `api_key` is not shown again
+#[derive(Serialize, Deserialize)]
+pub struct IssuedApiKey {
+    pub api_key: String,
+    pub key: TenantApiKey,
+}
+
+impl WirePayload for IssuedApiKey {
+    const TYPE: &'static str = "IssuedApiKey";
+}
+
+/// Trading state of the engine's market, as read and set through the admin API
+#[derive(Serialize, Deserialize)]
+pub struct MarketStatusSetting {
+    pub status: MarketStatus,
+}
+
+impl WirePayload for MarketStatusSetting {
+    const TYPE: &'static str = "MarketStatusSetting";
+}
+
+/// Speed bump of the engine's market, `None` when orders are not delayed
+#[derive(Serialize, Deserialize)]
+pub struct SpeedBumpSetting {
+    pub speed_bump: Option<SpeedBump>,
+}
+
+impl WirePayload for SpeedBumpSetting {
+    const TYPE: &'static str = "SpeedBumpSetting";
+}
+
+/// Suspension as set, with the orders it cancelled
+#[derive(Serialize, Deserialize)]
+pub struct SuspensionOutcome {
+    pub suspension: Suspension,
+    /// IDs of the resting orders cancelled with it, oldest first
+    pub cancelled_order_ids: Vec<u64>,
+}
+
+impl WirePayload for SuspensionOutcome {
+    const TYPE: &'static str = "SuspensionOutcome";
+}
+
+/// Body of a suspension
+#[derive(Deserialize)]
+struct SuspendRequest {
+    reason: SuspensionReason,
+    #[serde(default)]
+    note: Option<String>,
+    /// Cancel the owner's resting orders too
+    #[serde(default)]
+    cancel_orders: bool,
+}
+
+/// Body of an API key request
+#[derive(Deserialize)]
+struct IssueKeyRequest {
+    label: Option<String>,
+    /// Issue a paper trading key
+    #[serde(default)]
+    paper: bool,
+    /// WebSocket feeds the key serves; public L2 when absent
+    #[serde(default)]
+    entitlement: FeedEntitlement,
+}
+
+/// Body of an API key entitlement change
+#[derive(Serialize, Deserialize)]
+struct EntitlementSetting {
+    entitlement: FeedEntitlement,
+}
+
+/// Query parameters for the surveillance alert list
+#[derive(Deserialize)]
+struct AlertQuery {
+    kind: Option<AlertKind>,
+    min_score: Option<f64>,
+}
+
+/// Query parameters for the dead letter list
+#[derive(Deserialize)]
+struct DeadLetterQuery {
+    status: Option<DeadLetterStatus>,
+}
+
+/// Query parameters for the trade adjustment list
+#[derive(Deserialize)]
+struct AdjustmentQuery {
+    reversal: Option<ReversalStatus>,
+}
+
+/// Body of a trade bust
+#[derive(Deserialize)]
+struct BustRequest {
+    reason: String,
+}
+
+/// Body of a trade price correction
+#[derive(Deserialize)]
+struct CorrectRequest {
+    price: u64,
+    reason: String,
+}
+
+/// Body of a dead letter edit: the trades to settle instead
+#[derive(Serialize, Deserialize)]
+struct DeadLetterEdit {
+    trades: Vec<DeadLetterTrade>,
+}
+
+/// Body of a market maker registration: its quoting obligations
+#[derive(Deserialize)]
+struct ObligationSetting {
+    #[serde(default)]
+    label: Option<String>,
+    max_spread_bps: u64,
+    min_depth: u64,
+    min_uptime_bps: u64,
+}
+
+/// Query parameters for the scorecard lists
+#[derive(Deserialize)]
+struct ScorecardListQuery {
+    period_start: Option<i64>,
+}
+
+/// Admin routes, merged into the main router
+pub(crate) fn admin_router<S: Storage + 'static>() -> Router<Arc<RpcServerState<S>>> {
+    Router::new()
+        .route(
+            "/api/v1/admin/risk/limits",
+            get(get_market_limits_handler).put(set_market_limits_handler),
+        )
+        .route("/api/v1/admin/risk/users", get(list_user_limits_handler))
+        .route(
+            "/api/v1/admin/risk/users/:user",
+            get(get_user_limits_handler)
+                .put(set_user_limits_handler)
+                .delete(clear_user_limits_handler),
+        )
+        .route("/api/v1/admin/surveillance/alerts", get(list_alerts_handler))
+        .route("/api/v1/admin/surveillance/alerts/:id", get(get_alert_handler))
+        .route("/api/v1/admin/fees/reports", get(list_fee_reports_handler))
+        .route("/api/v1/admin/settlement/dead-letters", get(list_dead_letters_handler))
+        .route(
+            "/api/v1/admin/settlement/dead-letters/:id",
+            get(get_dead_letter_handler).put(edit_dead_letter_handler),
+        )
+        .route(
+            "/api/v1/admin/settlement/dead-letters/:id/resubmit",
+            post(resubmit_dead_letter_handler),
+        )
+        .route(
+            "/api/v1/admin/settlement/dead-letters/:id/discard",
+            post(discard_dead_letter_handler),
+        )
+        .route("/api/v1/admin/trades/adjustments", get(list_trade_adjustments_handler))
+        .route("/api/v1/admin/trades/:trade_id/bust", post(bust_trade_handler))
+        .route("/api/v1/admin/trades/:trade_id/correct", post(correct_trade_handler))
+        .route(
+            "/api/v1/admin/market/status",
+            get(get_market_status_handler).put(set_market_status_handler),
+        )
+        .route(
+            "/api/v1/admin/market/speed-bump",
+            get(get_speed_bump_handler).put(set_speed_bump_handler).delete(clear_speed_bump_handler),
+        )
+        .route(
+            "/api/v1/admin/engine/pause",
+            get(get_engine_pause_handler).put(pause_engine_handler).delete(resume_engine_handler),
+        )
+        .route("/api/v1/admin/mmp/users", get(list_mmp_handler))
+        .route(
+            "/api/v1/admin/mmp/users/:user",
+            get(get_mmp_handler).put(set_mmp_handler).delete(clear_mmp_handler),
+        )
+        .route("/api/v1/admin/mmp/users/:user/reset", post(reset_mmp_handler))
+        .route("/api/v1/admin/market-makers", get(list_market_makers_handler))
+        .route("/api/v1/admin/market-makers/scorecards", get(list_scorecards_handler))
+        .route(
+            "/api/v1/admin/market-makers/:maker",
+            get(get_market_maker_handler).put(set_market_maker_handler).delete(remove_market_maker_handler),
+        )
+        .route("/api/v1/admin/market-makers/:maker/scorecards", get(list_maker_scorecards_handler))
+        .route("/api/v1/admin/suspensions", get(list_suspensions_handler))
+        .route(
+            "/api/v1/admin/suspensions/owners/:owner",
+            put(suspend_owner_handler).delete(lift_owner_suspension_handler),
+        )
+        .route(
+            "/api/v1/admin/suspensions/api-keys/:key_id",
+            put(suspend_api_key_handler).delete(lift_api_key_suspension_handler),
+        )
+        .route(
+            "/api/v1/admin/rate-limits",
+            get(get_rate_limits_handler).put(set_rate_limits_handler),
+        )
+        .route("/api/v1/admin/tenants", get(list_tenants_handler))
+        .route(
+            "/api/v1/admin/tenants/:tenant",
+            get(get_tenant_handler).put(set_tenant_handler),
+        )
+        .route(
+            "/api/v1/admin/tenants/:tenant/keys",
+            get(list_tenant_keys_handler).post(issue_tenant_key_handler),
+        )
+        .route("/api/v1/admin/tenants/:tenant/keys/:key_id", delete(revoke_tenant_key_handler))
+        .route(
+            "/api/v1/admin/tenants/:tenant/keys/:key_id/entitlement",
+            put(set_tenant_key_entitlement_handler),
+        )
+        .route("/api/v1/admin/tenants/:tenant/usage", get(get_tenant_usage_handler))
+        .route("/api/v1/admin/audit", get(list_audit_entries_handler))
+        .route("/api/v1/admin/api-audit", get(list_api_audit_entries_handler))
+}
+
+/// Check the bearer token against the configured admin token
+fn authorize<S: Storage>(state: &RpcServerState<S>, headers: &HeaderMap) -> Result<(), StatusCode> {
+    let Some(expected) = state.admin_token.as_deref() else {
+        return Err(StatusCode::FORBIDDEN);
+    };
+
+    let provided = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if !constant_time_eq(provided.as_bytes(), expected.as_bytes()) {
+        warn!("Rejected admin request with invalid token");
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn ok<T: WirePayload>(result: T) -> Json<JsonRpcResponse<T>> {
+    Json(JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: Some(1),
+        result: Some(Envelope::new(result)),
+        error: None,
+    })
+}
+
+fn parse_user(user: &str) -> Result<Pubkey, StatusCode> {
+    user.parse().map_err(|_| StatusCode::BAD_REQUEST)
+}
+
+/// Operator the request names, `admin` when it names none
+fn operator(headers: &HeaderMap) -> &str {
+    headers
+        .get(OPERATOR_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("admin")
+}
+
+/// Record a change about to be applied
+async fn audit<S: Storage>(
+    state: &RpcServerState<S>,
+    headers: &HeaderMap,
+    action: &str,
+    target: Option<&str>,
+    before: impl Serialize,
+    after: impl Serialize,
+) -> Result<(), StatusCode> {
+    let entry = AdminAuditEntry {
+        id: 0,
+        actor: operator(headers).to_string(),
+        action: action.to_string(),
+        target: target.map(str::to_string),
+        before: serde_json::to_value(before).unwrap_or_default(),
+        after: serde_json::to_value(after).unwrap_or_default(),
+        timestamp: state.clock.now(),
+    };
+    match state.storage.store_admin_audit_entry(&entry).await {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            error!("Failed to record admin change {}: {}", action, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get market-wide risk limits
+async fn get_market_limits_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+) -> Result<Json<JsonRpcResponse<RiskLimits>>, StatusCode> {
+    authorize(&state, &headers)?;
+    Ok(ok(state.risk.market_limits()))
+}
+
+/// Replace market-wide risk limits
+async fn set_market_limits_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Json(limits): Json<RiskLimits>,
+) -> Result<Json<JsonRpcResponse<RiskLimits>>, StatusCode> {
+    authorize(&state, &headers)?;
+    audit(&state, &headers, "risk.market_limits.set", None, state.risk.market_limits(), &limits).await?;
+    info!("Admin updated market risk limits");
+    state.risk.set_market_limits(limits);
+    Ok(ok(state.risk.market_limits()))
+}
+
+/// List every per-user override
+async fn list_user_limits_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<UserRiskLimits>>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let mut overrides = state.risk.all_user_limits();
+    overrides.sort_by_key(|(user, _)| *user);
+    let users = Page::slice(overrides, &page).map_err(|_| StatusCode::BAD_REQUEST)?;
+    Ok(ok(users.map(|(user, limits)| UserRiskLimits {
+        user: user.to_string(),
+        effective: state.risk.effective_limits(&user),
+        limits,
+    })))
+}
+
+/// Get the override and effective limits of one user
+async fn get_user_limits_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(user): Path<String>,
+) -> Result<Json<JsonRpcResponse<UserRiskLimits>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let pubkey = parse_user(&user)?;
+    Ok(ok(UserRiskLimits {
+        user,
+        limits: state.risk.user_limits(&pubkey).unwrap_or_default(),
+        effective: state.risk.effective_limits(&pubkey),
+    }))
+}
+
+/// Set the override of one user
+async fn set_user_limits_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(user): Path<String>,
+    Json(limits): Json<RiskLimits>,
+) -> Result<Json<JsonRpcResponse<UserRiskLimits>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let pubkey = parse_user(&user)?;
+    let before = state.risk.user_limits(&pubkey);
+    audit(&state, &headers, "risk.user_limits.set", Some(user.as_str()), before, &limits).await?;
+    info!("Admin updated risk limits for {}", user);
+    state.risk.set_user_limits(pubkey, limits.clone());
+    Ok(ok(UserRiskLimits {
+        user,
+        limits,
+        effective: state.risk.effective_limits(&pubkey),
+    }))
+}
+
+/// Remove the override of one user
+async fn clear_user_limits_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(user): Path<String>,
+) -> Result<Json<JsonRpcResponse<RiskLimits>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let pubkey = parse_user(&user)?;
+    let before = state.risk.user_limits(&pubkey).ok_or(StatusCode::NOT_FOUND)?;
+    audit(&state, &headers, "risk.user_limits.clear", Some(user.as_str()), before, ()).await?;
+    info!("Admin cleared risk limits for {}", user);
+    match state.risk.clear_user_limits(&pubkey) {
+        Some(limits) => Ok(ok(limits)),
+        None => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+/// List surveillance alerts, newest first
+async fn list_alerts_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Query(params): Query<AlertQuery>,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<SurveillanceAlert>>>, StatusCode> {
+    authorize(&state, &headers)?;
+    check_page(&page)?;
+    match state
+        .storage
+        .get_surveillance_alerts(params.kind, params.min_score.unwrap_or(0.0), &page)
+        .await
+    {
+        Ok(alerts) => Ok(ok(alerts)),
+        Err(e) => {
+            error!("Failed to get surveillance alerts: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get one surveillance alert
+async fn get_alert_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+) -> Result<Json<JsonRpcResponse<SurveillanceAlert>>, StatusCode> {
+    authorize(&state, &headers)?;
+    match state.storage.get_surveillance_alert(id).await {
+        Ok(Some(alert)) => Ok(ok(alert)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to get surveillance alert {}: {}", id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Fee reports of every user for one month
+async fn list_fee_reports_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Query(params): Query<FeeReportQuery>,
+) -> Result<Response, StatusCode> {
+    authorize(&state, &headers)?;
+    let (from, until) = params.period(state.clock.now())?;
+    match state.storage.get_fee_reports(from, until, None).await {
+        Ok(reports) => Ok(fee_report_response(reports, params.format.as_deref())),
+        Err(e) => {
+            error!("Failed to get fee reports: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// List settlement batches the settler gave up on, oldest first
+async fn list_dead_letters_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Query(query): Query<DeadLetterQuery>,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<DeadLetter>>>, StatusCode> {
+    authorize(&state, &headers)?;
+    check_page(&page)?;
+    match state.storage.get_dead_letters(query.status, &page).await {
+        Ok(letters) => Ok(ok(letters)),
+        Err(e) => {
+            error!("Failed to get dead letters: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn load_dead_letter<S: Storage>(state: &RpcServerState<S>, id: u64) -> Result<DeadLetter, StatusCode> {
+    match state.storage.get_dead_letter(id).await {
+        Ok(Some(letter)) => Ok(letter),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to get dead letter {}: {}", id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Store `letter`, changed from an open one; 409 when the settler or another
+/// operator changed it first
+async fn update_open_dead_letter<S: Storage>(
+    state: &RpcServerState<S>,
+    letter: DeadLetter,
+) -> Result<Json<JsonRpcResponse<DeadLetter>>, StatusCode> {
+    match state.storage.update_dead_letter(&letter, DeadLetterStatus::Open).await {
+        Ok(true) => Ok(ok(letter)),
+        Ok(false) => Err(StatusCode::CONFLICT),
+        Err(e) => {
+            error!("Failed to update dead letter {}: {}", letter.id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get one dead letter
+async fn get_dead_letter_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+) -> Result<Json<JsonRpcResponse<DeadLetter>>, StatusCode> {
+    authorize(&state, &headers)?;
+    Ok(ok(load_dead_letter(&state, id).await?))
+}
+
+/// Replace the trades of an open dead letter, e.g. to drop a trade that can
+/// never settle; each trade must be from the journal range of the batch, once
+async fn edit_dead_letter_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+    Json(edit): Json<DeadLetterEdit>,
+) -> Result<Json<JsonRpcResponse<DeadLetter>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let before = load_dead_letter(&state, id).await?;
+    if before.status != DeadLetterStatus::Open {
+        return Err(StatusCode::CONFLICT);
+    }
+    let mut sequences: Vec<u64> = edit.trades.iter().map(|trade| trade.journal_sequence).collect();
+    sequences.sort_unstable();
+    sequences.dedup();
+    let in_batch = |sequence: &u64| (before.first_sequence..=before.last_sequence).contains(sequence);
+    if edit.trades.is_empty() || sequences.len() != edit.trades.len() || !sequences.iter().all(in_batch) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let letter = DeadLetter {
+        trades: edit.trades,
+        updated_at: state.clock.now(),
+        ..before.clone()
+    };
+    let target = id.to_string();
+    audit(&state, &headers, "settlement.dead_letters.edit", Some(target.as_str()), &before.trades, &letter.trades).await?;
+    info!("Admin edited dead letter {}", id);
+    update_open_dead_letter(&state, letter).await
+}
+
+/// Queue an open dead letter for the settler to submit again
+async fn resubmit_dead_letter_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+) -> Result<Json<JsonRpcResponse<DeadLetter>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let before = load_dead_letter(&state, id).await?;
+    if before.status != DeadLetterStatus::Open {
+        return Err(StatusCode::CONFLICT);
+    }
+    let target = id.to_string();
+    audit(&state, &headers, "settlement.dead_letters.resubmit", Some(target.as_str()), before.status, DeadLetterStatus::Resubmitting).await?;
+    info!("Admin queued dead letter {} for resubmission", id);
+    update_open_dead_letter(&state, DeadLetter {
+        status: DeadLetterStatus::Resubmitting,
+        updated_at: state.clock.now(),
+        ..before
+    })
+    .await
+}
+
+/// Close an open dead letter without settling it
+async fn discard_dead_letter_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+) -> Result<Json<JsonRpcResponse<DeadLetter>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let before = load_dead_letter(&state, id).await?;
+    if before.status != DeadLetterStatus::Open {
+        return Err(StatusCode::CONFLICT);
+    }
+    let target = id.to_string();
+    audit(&state, &headers, "settlement.dead_letters.discard", Some(target.as_str()), &before, ()).await?;
+    warn!("Admin discarded dead letter {}; its fills stay off chain", id);
+    update_open_dead_letter(&state, DeadLetter {
+        status: DeadLetterStatus::Discarded,
+        updated_at: state.clock.now(),
+        ..before
+    })
+    .await
+}
+
+/// List trade busts and corrections, newest first
+async fn list_trade_adjustments_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Query(query): Query<AdjustmentQuery>,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<TradeAdjustment>>>, StatusCode> {
+    authorize(&state, &headers)?;
+    check_page(&page)?;
+    match state.storage.get_recent_trade_adjustments(query.reversal, &page).await {
+        Ok(adjustments) => Ok(ok(adjustments)),
+        Err(e) => {
+            error!("Failed to get trade adjustments: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Bust a trade; a settlement on chain is reversed by the settler
+async fn bust_trade_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(trade_id): Path<u64>,
+    Json(request): Json<BustRequest>,
+) -> Result<Json<JsonRpcResponse<TradeAdjustment>>, ApiError> {
+    authorize(&state, &headers)?;
+    adjust_trade(&state, &headers, trade_id, None, request.reason).await.map(ok)
+}
+
+/// Correct the price of a trade; a settlement on chain is moved to the new
+/// price by the settler
+async fn correct_trade_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(trade_id): Path<u64>,
+    Json(request): Json<CorrectRequest>,
+) -> Result<Json<JsonRpcResponse<TradeAdjustment>>, ApiError> {
+    authorize(&state, &headers)?;
+    adjust_trade(&state, &headers, trade_id, Some(request.price), request.reason).await.map(ok)
+}
+
+/// Audit and make an adjustment of a known trade; engine refusals answer
+/// with their error code
+async fn adjust_trade<S: Storage>(
+    state: &RpcServerState<S>,
+    headers: &HeaderMap,
+    trade_id: u64,
+    price: Option<u64>,
+    reason: String,
+) -> Result<TradeAdjustment, ApiError> {
+    if reason.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST.into());
+    }
+    let before = match state.storage.get_trade(trade_id).await {
+        Ok(Some(trade)) => trade,
+        Ok(None) => return Err(StatusCode::NOT_FOUND.into()),
+        Err(e) => {
+            error!("Failed to get trade {}: {}", trade_id, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+        }
+    };
+    let engine = state.matching_engine.read().await;
+    // A standby cannot journal; the active matcher must
+    if !engine.is_active() {
+        return Err(StatusCode::SERVICE_UNAVAILABLE.into());
+    }
+    let (action, after) = match price {
+        Some(price) => ("trade.correct", serde_json::json!({ "price": price, "reason": &reason })),
+        None => ("trade.bust", serde_json::json!({ "reason": &reason })),
+    };
+    let target = trade_id.to_string();
+    audit(state, headers, action, Some(target.as_str()), &before, after).await?;
+    let adjustment = engine.adjust_trade(trade_id, price, reason, operator(headers).to_string()).await?;
+    warn!(
+        "Admin adjusted trade {} to version {} ({:?} at {})",
+        trade_id, adjustment.version, adjustment.status, adjustment.price
+    );
+    Ok(adjustment)
+}
+
+/// Get the market's trading state
+async fn get_market_status_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+) -> Result<Json<JsonRpcResponse<MarketStatusSetting>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let status = state.matching_engine.read().await.status();
+    Ok(ok(MarketStatusSetting { status }))
+}
+
+/// Move the market to another trading state; the on-chain status is set
+/// separately with the program's `set_market_status`
+async fn set_market_status_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Json(setting): Json<MarketStatusSetting>,
+) -> Result<Json<JsonRpcResponse<MarketStatusSetting>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let engine = state.matching_engine.read().await;
+    // A standby would only change its own copy; the active matcher must
+    if !engine.is_active() {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    let market = engine.market_id().to_string();
+    let before = MarketStatusSetting { status: engine.status() };
+    audit(&state, &headers, "market.status.set", Some(market.as_str()), before, &setting).await?;
+    info!("Admin set market {} to {}", market, setting.status);
+    match engine.set_status(setting.status).await {
+        Ok(_) => Ok(ok(MarketStatusSetting { status: engine.status() })),
+        Err(e) => {
+            error!("Failed to set market status: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// List every suspension in force, oldest first
+async fn list_suspensions_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<Suspension>>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let suspensions = state.storage.get_suspensions().await.map_err(|e| {
+        error!("Failed to get suspensions: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(ok(Page::slice(suspensions, &page).map_err(|_| StatusCode::BAD_REQUEST)?))
+}
+
+/// Refuse new orders of an owner, optionally cancelling its resting orders
+async fn suspend_owner_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(owner): Path<String>,
+    Json(request): Json<SuspendRequest>,
+) -> Result<Json<JsonRpcResponse<SuspensionOutcome>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let pubkey = parse_user(&owner)?;
+    let cancel_orders = request.cancel_orders;
+    // Only the active matcher can cancel; refuse before anything is stored
+    if cancel_orders && !state.matching_engine.read().await.is_active() {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    let suspension = suspend(&state, &headers, SuspensionSubject::Owner(pubkey), request).await?;
+    let cancelled_order_ids = if cancel_orders {
+        let engine = state.matching_engine.read().await;
+        match engine.cancel_owner_orders(&pubkey).await {
+            Ok(cancelled) => cancelled.into_iter().map(|order| order.order_id).collect(),
+            Err(e) => {
+                error!("Suspended {} but failed to cancel its orders: {}", owner, e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        }
+    } else {
+        Vec::new()
+    };
+    Ok(ok(SuspensionOutcome { suspension, cancelled_order_ids }))
+}
+
+/// Refuse new orders placed with an API key; orders are not attributed to
+/// keys, so none can be cancelled with it
+async fn suspend_api_key_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(key_id): Path<String>,
+    Json(request): Json<SuspendRequest>,
+) -> Result<Json<JsonRpcResponse<SuspensionOutcome>>, StatusCode> {
+    authorize(&state, &headers)?;
+    if request.cancel_orders {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let suspension = suspend(&state, &headers, SuspensionSubject::ApiKey(key_id), request).await?;
+    Ok(ok(SuspensionOutcome { suspension, cancelled_order_ids: Vec::new() }))
+}
+
+/// Audit and store the suspension of `subject`, applying it on this replica at once
+async fn suspend<S: Storage>(
+    state: &RpcServerState<S>,
+    headers: &HeaderMap,
+    subject: SuspensionSubject,
+    request: SuspendRequest,
+) -> Result<Suspension, StatusCode> {
+    let before = current_suspension(state, &subject).await?;
+    let suspension = Suspension {
+        subject,
+        reason: request.reason,
+        note: request.note,
+        actor: operator(headers).to_string(),
+        suspended_at: state.clock.now(),
+    };
+    let target = suspension.subject.to_string();
+    audit(state, headers, "suspension.set", Some(target.as_str()), before, &suspension).await?;
+    warn!("Admin suspended {} ({:?})", target, suspension.reason);
+    if let Err(e) = state.storage.store_suspension(&suspension).await {
+        error!("Failed to store the suspension of {}: {}", target, e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    state.suspensions.invalidate();
+    Ok(suspension)
+}
+
+/// Accept new orders of an owner again; answers with the suspension lifted
+async fn lift_owner_suspension_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(owner): Path<String>,
+) -> Result<Json<JsonRpcResponse<Suspension>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let subject = SuspensionSubject::Owner(parse_user(&owner)?);
+    lift(&state, &headers, subject).await.map(ok)
+}
+
+/// Accept new orders placed with an API key again; answers with the
+/// suspension lifted
+async fn lift_api_key_suspension_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(key_id): Path<String>,
+) -> Result<Json<JsonRpcResponse<Suspension>>, StatusCode> {
+    authorize(&state, &headers)?;
+    lift(&state, &headers, SuspensionSubject::ApiKey(key_id)).await.map(ok)
+}
+
+async fn lift<S: Storage>(
+    state: &RpcServerState<S>,
+    headers: &HeaderMap,
+    subject: SuspensionSubject,
+) -> Result<Suspension, StatusCode> {
+    let before = current_suspension(state, &subject).await?.ok_or(StatusCode::NOT_FOUND)?;
+    let target = subject.to_string();
+    audit(state, headers, "suspension.lift", Some(target.as_str()), &before, ()).await?;
+    info!("Admin lifted the suspension of {}", target);
+    match state.storage.lift_suspension(&subject).await {
+        Ok(true) => {}
+        Ok(false) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to lift the suspension of {}: {}", target, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+    state.suspensions.invalidate();
+    Ok(before)
+}
+
+async fn current_suspension<S: Storage>(
+    state: &RpcServerState<S>,
+    subject: &SuspensionSubject,
+) -> Result<Option<Suspension>, StatusCode> {
+    match state.storage.get_suspensions().await {
+        Ok(suspensions) => Ok(suspensions.into_iter().find(|suspension| suspension.subject == *subject)),
+        Err(e) => {
+            error!("Failed to get suspensions: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get the delay orders that only take are held for
+async fn get_speed_bump_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+) -> Result<Json<JsonRpcResponse<SpeedBumpSetting>>, StatusCode> {
+    authorize(&state, &headers)?;
+    Ok(ok(SpeedBumpSetting { speed_bump: state.speed_bump.get() }))
+}
+
+/// Hold orders that only take for a fixed or random delay on this replica
+async fn set_speed_bump_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Json(speed_bump): Json<SpeedBump>,
+) -> Result<Json<JsonRpcResponse<SpeedBumpSetting>>, StatusCode> {
+    authorize(&state, &headers)?;
+    if !speed_bump.is_valid() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let market = state.matching_engine.read().await.market_id().to_string();
+    audit(&state, &headers, "speed_bump.set", Some(market.as_str()), state.speed_bump.get(), speed_bump).await?;
+    info!(
+        "Admin set the speed bump of market {} to {}-{} us",
+        market, speed_bump.min_delay_us, speed_bump.max_delay_us
+    );
+    state.speed_bump.set(Some(speed_bump));
+    Ok(ok(SpeedBumpSetting { speed_bump: Some(speed_bump) }))
+}
+
+/// Stop delaying orders; answers with the speed bump that was lifted
+async fn clear_speed_bump_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+) -> Result<Json<JsonRpcResponse<SpeedBumpSetting>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let market = state.matching_engine.read().await.market_id().to_string();
+    audit(&state, &headers, "speed_bump.clear", Some(market.as_str()), state.speed_bump.get(), None::<SpeedBump>).await?;
+    info!("Admin lifted the speed bump of market {}", market);
+    Ok(ok(SpeedBumpSetting { speed_bump: state.speed_bump.set(None) }))
+}
+
+/// Get whether matching is paused and how many commands wait for it
+async fn get_engine_pause_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+) -> Result<Json<JsonRpcResponse<EnginePause>>, StatusCode> {
+    authorize(&state, &headers)?;
+    Ok(ok(state.matching_engine.read().await.pause_state()))
+}
+
+/// Stop matching, buffering or refusing commands until resumed
+async fn pause_engine_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Json(mode): Json<PauseMode>,
+) -> Result<Json<JsonRpcResponse<EnginePause>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let engine = state.matching_engine.read().await;
+    let market = engine.market_id().to_string();
+    audit(&state, &headers, "engine.pause", Some(market.as_str()), engine.pause_state(), mode).await?;
+    info!("Admin paused matching in market {}", market);
+    Ok(ok(engine.pause(mode).await))
+}
+
+/// Match again, running buffered commands in arrival order; answers with
+/// the pause that was lifted
+async fn resume_engine_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+) -> Result<Json<JsonRpcResponse<EnginePause>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let engine = state.matching_engine.read().await;
+    let market = engine.market_id().to_string();
+    audit(&state, &headers, "engine.resume", Some(market.as_str()), engine.pause_state(), None::<PauseMode>).await?;
+    info!("Admin resumed matching in market {}", market);
+    Ok(ok(engine.resume().await))
+}
+
+/// List every maker with market maker protection
+async fn list_mmp_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<MmpStatus>>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let protected = state.matching_engine.read().await.all_mmp();
+    Ok(ok(Page::slice(protected, &page).map_err(|_| StatusCode::BAD_REQUEST)?))
+}
+
+/// Get the protection of one maker and its fills in the current window
+async fn get_mmp_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(user): Path<String>,
+) -> Result<Json<JsonRpcResponse<MmpStatus>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let pubkey = parse_user(&user)?;
+    let status = state.matching_engine.read().await.mmp_status(&pubkey);
+    status.map(ok).ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Protect one maker, starting its window afresh
+async fn set_mmp_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(user): Path<String>,
+    Json(config): Json<MmpConfig>,
+) -> Result<Json<JsonRpcResponse<MmpStatus>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let pubkey = parse_user(&user)?;
+    if !config.is_valid() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let engine = state.matching_engine.read().await;
+    let before = engine.mmp_status(&pubkey).map(|status| status.config);
+    audit(&state, &headers, "mmp.set", Some(user.as_str()), before, config).await?;
+    info!("Admin set market maker protection for {}", user);
+    Ok(ok(engine.set_mmp(pubkey, config)))
+}
+
+/// Stop protecting one maker; answers with its protection as it was
+async fn clear_mmp_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(user): Path<String>,
+) -> Result<Json<JsonRpcResponse<MmpStatus>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let pubkey = parse_user(&user)?;
+    let engine = state.matching_engine.read().await;
+    let before = engine.mmp_status(&pubkey).ok_or(StatusCode::NOT_FOUND)?;
+    audit(&state, &headers, "mmp.clear", Some(user.as_str()), before.config, ()).await?;
+    info!("Admin cleared market maker protection for {}", user);
+    engine.clear_mmp(&pubkey);
+    Ok(ok(before))
+}
+
+/// List designated market makers by key
+async fn list_market_makers_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<MarketMakerObligation>>>, StatusCode> {
+    authorize(&state, &headers)?;
+    check_page(&page)?;
+    match state.storage.get_market_makers(&page).await {
+        Ok(makers) => Ok(ok(makers)),
+        Err(e) => {
+            error!("Failed to get market makers: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn load_market_maker<S: Storage>(
+    state: &RpcServerState<S>,
+    maker: &Pubkey,
+) -> Result<Option<MarketMakerObligation>, StatusCode> {
+    state.storage.get_market_maker(maker).await.map_err(|e| {
+        error!("Failed to get market maker {}: {}", maker, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Get the obligations of one designated market maker
+async fn get_market_maker_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(maker): Path<String>,
+) -> Result<Json<JsonRpcResponse<MarketMakerObligation>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let pubkey = parse_user(&maker)?;
+    load_market_maker(&state, &pubkey).await?.map(ok).ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Designate a market maker or replace its obligations; it is scored from
+/// its first registration on
+async fn set_market_maker_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(maker): Path<String>,
+    Json(setting): Json<ObligationSetting>,
+) -> Result<Json<JsonRpcResponse<MarketMakerObligation>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let pubkey = parse_user(&maker)?;
+    if setting.max_spread_bps == 0 || setting.max_spread_bps > 10_000 || setting.min_uptime_bps > 10_000 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let before = load_market_maker(&state, &pubkey).await?;
+
+    let now = state.clock.now();
+    let obligation = MarketMakerObligation {
+        maker: pubkey,
+        label: setting.label,
+        max_spread_bps: setting.max_spread_bps,
+        min_depth: setting.min_depth,
+        min_uptime_bps: setting.min_uptime_bps,
+        registered_at: before.as_ref().map_or(now, |before| before.registered_at),
+        updated_at: now,
+    };
+    audit(&state, &headers, "market_makers.set", Some(maker.as_str()), &before, &obligation).await?;
+    info!("Admin set the obligations of market maker {}", maker);
+    if let Err(e) = state.storage.store_market_maker(&obligation).await {
+        error!("Failed to store market maker {}: {}", maker, e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    Ok(ok(obligation))
+}
+
+/// Withdraw a maker's designation; answers with its obligations as they
+/// were. Its past scorecards are kept.
+async fn remove_market_maker_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(maker): Path<String>,
+) -> Result<Json<JsonRpcResponse<MarketMakerObligation>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let pubkey = parse_user(&maker)?;
+    let before = load_market_maker(&state, &pubkey).await?.ok_or(StatusCode::NOT_FOUND)?;
+    audit(&state, &headers, "market_makers.remove", Some(maker.as_str()), &before, ()).await?;
+    info!("Admin removed market maker {}", maker);
+    match state.storage.remove_market_maker(&pubkey).await {
+        Ok(true) => Ok(ok(before)),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to remove market maker {}: {}", maker, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// List scorecards of every maker, newest period first
+async fn list_scorecards_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Query(query): Query<ScorecardListQuery>,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<MarketMakerScorecard>>>, StatusCode> {
+    authorize(&state, &headers)?;
+    check_page(&page)?;
+    let query = ScorecardQuery {
+        period_start: query.period_start,
+        maker: None,
+    };
+    scorecards(&state, &query, &page).await
+}
+
+/// List the scorecards of one maker, newest period first
+async fn list_maker_scorecards_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(maker): Path<String>,
+    Query(query): Query<ScorecardListQuery>,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<MarketMakerScorecard>>>, StatusCode> {
+    authorize(&state, &headers)?;
+    check_page(&page)?;
+    let query = ScorecardQuery {
+        period_start: query.period_start,
+        maker: Some(parse_user(&maker)?),
+    };
+    scorecards(&state, &query, &page).await
+}
+
+async fn scorecards<S: Storage>(
+    state: &RpcServerState<S>,
+    query: &ScorecardQuery,
+    page: &Paginated,
+) -> Result<Json<JsonRpcResponse<Page<MarketMakerScorecard>>>, StatusCode> {
+    match state.storage.get_market_maker_scorecards(query, page).await {
+        Ok(scorecards) => Ok(ok(scorecards)),
+        Err(e) => {
+            error!("Failed to get market maker scorecards: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Let a maker frozen by its protection quote again
+async fn reset_mmp_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(user): Path<String>,
+) -> Result<Json<JsonRpcResponse<MmpStatus>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let pubkey = parse_user(&user)?;
+    let engine = state.matching_engine.read().await;
+    let before = engine.mmp_status(&pubkey).ok_or(StatusCode::NOT_FOUND)?;
+    audit(&state, &headers, "mmp.reset", Some(user.as_str()), before.frozen_until_ms, None::<i64>).await?;
+    info!("Admin reset market maker protection for {}", user);
+    engine.reset_mmp(&pubkey).map(ok).ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Get the rate limits
+async fn get_rate_limits_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+) -> Result<Json<JsonRpcResponse<RateLimits>>, StatusCode> {
+    authorize(&state, &headers)?;
+    Ok(ok(current_rate_limits(&state).await?))
+}
+
+/// Replace the rate limits, on every replica when they are shared
+async fn set_rate_limits_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Json(limits): Json<RateLimits>,
+) -> Result<Json<JsonRpcResponse<RateLimits>>, StatusCode> {
+    authorize(&state, &headers)?;
+    audit(&state, &headers, "rate_limits.set", None, current_rate_limits(&state).await?, &limits).await?;
+    info!("Admin updated rate limits");
+    if let Err(e) = state.rate_limiter.set_limits(limits).await {
+        error!("Failed to set rate limits: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    Ok(ok(current_rate_limits(&state).await?))
+}
+
+async fn current_rate_limits<S: Storage>(state: &RpcServerState<S>) -> Result<RateLimits, StatusCode> {
+    state.rate_limiter.limits().await.map_err(|e| {
+        error!("Failed to read rate limits: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Tenant IDs name the tenant in URLs and audit logs, so they are kept to
+/// lowercase letters, digits, `-` and `_`
+fn valid_tenant_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_')
+}
+
+async fn load_tenant<S: Storage>(state: &RpcServerState<S>, id: &str) -> Result<Tenant, StatusCode> {
+    match state.storage.get_tenant(id).await {
+        Ok(Some(tenant)) => Ok(tenant),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to get tenant {}: {}", id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// List tenants by ID
+async fn list_tenants_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<Tenant>>>, StatusCode> {
+    authorize(&state, &headers)?;
+    check_page(&page)?;
+    match state.storage.get_tenants(&page).await {
+        Ok(tenants) => Ok(ok(tenants)),
+        Err(e) => {
+            error!("Failed to get tenants: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get one tenant
+async fn get_tenant_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(tenant): Path<String>,
+) -> Result<Json<JsonRpcResponse<Tenant>>, StatusCode> {
+    authorize(&state, &headers)?;
+    Ok(ok(load_tenant(&state, &tenant).await?))
+}
+
+/// Create a tenant or replace its settings
+async fn set_tenant_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(settings): Json<TenantSettings>,
+) -> Result<Json<JsonRpcResponse<Tenant>>, StatusCode> {
+    authorize(&state, &headers)?;
+    if !valid_tenant_id(&id) || settings.fee_share_bps > 10_000 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let before = match state.storage.get_tenant(&id).await {
+        Ok(before) => before,
+        Err(e) => {
+            error!("Failed to get tenant {}: {}", id, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    if let Some(code) = &settings.referral_code {
+        match state.storage.get_tenant_by_referral_code(code).await {
+            Ok(Some(holder)) if holder.id != id => return Err(StatusCode::CONFLICT),
+            Ok(_) => {}
+            Err(e) => {
+                error!("Failed to look up referral code {}: {}", code, e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        }
+    }
+
+    let now = state.clock.now();
+    let tenant = Tenant {
+        id: id.clone(),
+        name: settings.name,
+        max_requests_per_second: settings.max_requests_per_second,
+        max_orders_per_second: settings.max_orders_per_second,
+        fee_share_bps: settings.fee_share_bps,
+        referral_code: settings.referral_code,
+        disabled: settings.disabled,
+        created_at: before.as_ref().map_or(now, |before| before.created_at),
+        updated_at: now,
+    };
+    audit(&state, &headers, "tenants.set", Some(id.as_str()), &before, &tenant).await?;
+    info!("Admin updated tenant {}", id);
+    if let Err(e) = state.storage.store_tenant(&tenant).await {
+        error!("Failed to store tenant {}: {}", id, e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    state.tenants.invalidate();
+    Ok(ok(tenant))
+}
+
+/// List the API keys of a tenant, revoked ones included
+async fn list_tenant_keys_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(tenant): Path<String>,
+) -> Result<Json<JsonRpcResponse<Vec<TenantApiKey>>>, StatusCode> {
+    authorize(&state, &headers)?;
+    load_tenant(&state, &tenant).await?;
+    match state.storage.get_tenant_api_keys(&tenant).await {
+        Ok(keys) => Ok(ok(keys)),
+        Err(e) => {
+            error!("Failed to get API keys of tenant {}: {}", tenant, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Issue an API key to a tenant
+async fn issue_tenant_key_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(tenant): Path<String>,
+    Json(request): Json<IssueKeyRequest>,
+) -> Result<Json<JsonRpcResponse<IssuedApiKey>>, StatusCode> {
+    authorize(&state, &headers)?;
+    load_tenant(&state, &tenant).await?;
+    let (api_key, key) = issue_key(&tenant, request.label, request.paper, request.entitlement, state.clock.now());
+    audit(&state, &headers, "tenants.keys.issue", Some(tenant.as_str()), (), &key).await?;
+    info!("Admin issued API key {} to tenant {}", key.key_id, tenant);
+    if let Err(e) = state.storage.store_tenant_api_key(&key).await {
+        error!("Failed to store API key of tenant {}: {}", tenant, e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    Ok(ok(IssuedApiKey { api_key, key }))
+}
+
+/// Change the WebSocket feeds an API key of a tenant serves; connections
+/// already open keep the entitlement they were opened with
+async fn set_tenant_key_entitlement_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path((tenant, key_id)): Path<(String, String)>,
+    Json(setting): Json<EntitlementSetting>,
+) -> Result<Json<JsonRpcResponse<Vec<TenantApiKey>>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let keys = state.storage.get_tenant_api_keys(&tenant).await.map_err(|e| {
+        error!("Failed to get API keys of tenant {}: {}", tenant, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let before = keys
+        .iter()
+        .find(|key| key.key_id == key_id && key.revoked_at.is_none())
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let target = format!("{}/{}", tenant, key_id);
+    let previous = EntitlementSetting { entitlement: before.entitlement };
+    audit(&state, &headers, "tenants.keys.entitlement", Some(target.as_str()), previous, &setting).await?;
+    info!("Admin set the feed entitlement of API key {} of tenant {} to {:?}", key_id, tenant, setting.entitlement);
+    match state.storage.set_tenant_api_key_entitlement(&tenant, &key_id, setting.entitlement).await {
+        Ok(true) => {}
+        Ok(false) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to set the entitlement of API key {} of tenant {}: {}", key_id, tenant, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+    match state.storage.get_tenant_api_keys(&tenant).await {
+        Ok(keys) => Ok(ok(keys)),
+        Err(e) => {
+            error!("Failed to get API keys of tenant {}: {}", tenant, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Revoke an API key of a tenant
+async fn revoke_tenant_key_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path((tenant, key_id)): Path<(String, String)>,
+) -> Result<Json<JsonRpcResponse<Vec<TenantApiKey>>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let keys = state.storage.get_tenant_api_keys(&tenant).await.map_err(|e| {
+        error!("Failed to get API keys of tenant {}: {}", tenant, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let before = keys
+        .iter()
+        .find(|key| key.key_id == key_id && key.revoked_at.is_none())
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let target = format!("{}/{}", tenant, key_id);
+    audit(&state, &headers, "tenants.keys.revoke", Some(target.as_str()), before, ()).await?;
+    info!("Admin revoked API key {} of tenant {}", key_id, tenant);
+    match state.storage.revoke_tenant_api_key(&tenant, &key_id, state.clock.now()).await {
+        Ok(true) => {}
+        Ok(false) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to revoke API key {} of tenant {}: {}", key_id, tenant, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+    state.tenants.invalidate();
+    match state.storage.get_tenant_api_keys(&tenant).await {
+        Ok(keys) => Ok(ok(keys)),
+        Err(e) => {
+            error!("Failed to get API keys of tenant {}: {}", tenant, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Stored usage of a tenant, hour by hour
+async fn get_tenant_usage_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Path(tenant): Path<String>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<JsonRpcResponse<Vec<TenantUsage>>>, StatusCode> {
+    authorize(&state, &headers)?;
+    let (from, until) = query.range(state.clock.now())?;
+    load_tenant(&state, &tenant).await?;
+    match state.storage.get_tenant_usage(&tenant, from, until).await {
+        Ok(usage) => Ok(ok(usage)),
+        Err(e) => {
+            error!("Failed to get usage of tenant {}: {}", tenant, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// List admin changes, newest first
+async fn list_audit_entries_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<AdminAuditEntry>>>, StatusCode> {
+    authorize(&state, &headers)?;
+    check_page(&page)?;
+    match state.storage.get_admin_audit_entries(&page).await {
+        Ok(entries) => Ok(ok(entries)),
+        Err(e) => {
+            error!("Failed to get admin audit log: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// List mutating API calls, newest first
+async fn list_api_audit_entries_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    headers: HeaderMap,
+    Query(query): Query<ApiAuditQuery>,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<ApiAuditEntry>>>, StatusCode> {
+    authorize(&state, &headers)?;
+    check_page(&page)?;
+    match state.storage.get_api_audit_entries(&query, &page).await {
+        Ok(entries) => Ok(ok(entries)),
+        Err(e) => {
+            error!("Failed to get API audit log: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
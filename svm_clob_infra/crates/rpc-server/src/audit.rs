@@ -0,0 +1,145 @@
+/// API audit log
+///
+/// Every mutating call (POST, PUT, DELETE) is written to the API audit log
+/// once it is answered, with its actor, request body, status, response body
+/// and latency, under the request ID it was answered with. Calls refused as
+/// unauthenticated (401, 403) are not recorded.
+/// The record is written off the request path, so the log never slows or
+/// refuses a call; a record that cannot be written is logged as an error.
+/// Issued API keys are redacted from recorded responses.
+
+use crate::admin::OPERATOR_HEADER;
+use crate::RpcServerState;
+use svm_clob_storage::Storage;
+use svm_clob_types::ApiAuditEntry;
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::{MatchedPath, Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::error;
+
+/// Largest request body read for the log, the limit axum's `Json` applies anyway
+const MAX_REQUEST_BYTES: usize = 2 * 1024 * 1024;
+
+/// Record mutating calls in the API audit log
+pub(crate) async fn audit_request<S: Storage + 'static>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !matches!(*request.method(), Method::POST | Method::PUT | Method::DELETE) {
+        return next.run(request).await;
+    }
+
+    let started = Instant::now();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let operator = request
+        .headers()
+        .get(OPERATOR_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    let (parts, body) = request.into_parts();
+    let Ok(body) = to_bytes(body, MAX_REQUEST_BYTES).await else {
+        return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+    };
+    let params = json(&body);
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    let latency_us = started.elapsed().as_micros() as u64;
+
+    let status = response.status();
+    if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            error!("Failed to read the response to {} {} for the audit log: {}", method, path, e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let entry = ApiAuditEntry {
+        id: 0,
+        actor: actor(&route, operator, &params),
+        method,
+        route,
+        path,
+        params,
+        status: status.as_u16(),
+        result: redact(json(&body)),
+        latency_us,
+        timestamp: state.clock.now(),
+        request_id: svm_clob_telemetry::current_request_id(),
+    };
+    let storage = state.storage.clone();
+    tokio::spawn(async move {
+        if let Err(e) = storage.store_api_audit_entry(&entry).await {
+            error!("Failed to record {} {} in the API audit log: {}", entry.method, entry.path, e);
+        }
+    });
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Who made a call: the operator of an admin call, otherwise the owners the
+/// request names
+fn actor(route: &str, operator: Option<String>, params: &Value) -> String {
+    if route.starts_with("/api/v1/admin/") {
+        return operator.unwrap_or_else(|| "admin".to_string());
+    }
+    // A placement names its owner; a batch names one per placement
+    let mut owners = BTreeSet::new();
+    if let Some(owner) = params.get("owner").and_then(Value::as_str) {
+        owners.insert(owner);
+    }
+    for place in params.get("place").and_then(Value::as_array).into_iter().flatten() {
+        if let Some(owner) = place.get("owner").and_then(Value::as_str) {
+            owners.insert(owner);
+        }
+    }
+    if owners.is_empty() {
+        return "anonymous".to_string();
+    }
+    owners.into_iter().collect::<Vec<_>>().join(",")
+}
+
+/// Replace every `api_key` field, which only an issued key carries
+fn redact(mut value: Value) -> Value {
+    match &mut value {
+        Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                *field = if name == "api_key" {
+                    Value::String("[redacted]".to_string())
+                } else {
+                    redact(field.take())
+                };
+            }
+        }
+        Value::Array(items) => {
+            for item in items.iter_mut() {
+                *item = redact(item.take());
+            }
+        }
+        _ => {}
+    }
+    value
+}
+
+fn json(body: &Bytes) -> Value {
+    serde_json::from_slice(body).unwrap_or(Value::Null)
+}
@@ -0,0 +1,178 @@
+/// Circuit breakers around optional dependencies
+///
+/// Redis backs shared rate limits and nonce claims, but a request should not
+/// fail because the cache tier blips. Each feature calls Redis through a
+/// `CircuitBreaker`, which gives every call a short deadline and, after a few
+/// failures in a row, opens: calls are then answered by the feature's
+/// fallback straight away instead of waiting on a dependency known to be down.
+/// Once open for a while, the next call is let through as a probe, and the
+/// breaker closes again when it succeeds. While a breaker is open its feature
+/// is reported as degraded by `/health` and the `dependency_degraded` gauge,
+/// and each call served from the fallback counts in
+/// `dependency_fallbacks_total`.
+
+use redis::aio::ConnectionManager;
+use redis::RedisResult;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use svm_clob_observability::{record_dependency_degraded, record_dependency_fallback};
+use svm_clob_types::{ClobError, ClobResult};
+use tokio::sync::OnceCell;
+use tracing::{info, warn};
+
+/// Failures in a row that open a breaker
+const FAILURE_THRESHOLD: u32 = 3;
+
+/// How long an open breaker answers from the fallback before probing again
+const OPEN_FOR: Duration = Duration::from_secs(5);
+
+/// Deadline of one call through a breaker
+const CALL_TIMEOUT: Duration = Duration::from_millis(250);
+
+#[derive(Default)]
+struct BreakerState {
+    /// Failures since the last success
+    failures: u32,
+    /// While open, when the next probe is let through
+    open_until: Option<Instant>,
+}
+
+/// Breaker guarding one feature's calls to one dependency
+pub struct CircuitBreaker {
+    dependency: &'static str,
+    feature: &'static str,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    /// Closed breaker for `feature`'s calls to `dependency`, both as named in
+    /// logs, metrics and `/health`
+    pub fn new(dependency: &'static str, feature: &'static str) -> Self {
+        record_dependency_degraded(dependency, feature, false);
+        Self {
+            dependency,
+            feature,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    pub fn feature(&self) -> &'static str {
+        self.feature
+    }
+
+    /// Whether calls are currently answered by the fallback
+    pub fn is_open(&self) -> bool {
+        self.state().open_until.is_some()
+    }
+
+    /// Run `call` unless the breaker is open; `None` when it was not run,
+    /// failed or missed its deadline, and the caller should fall back
+    pub async fn call<T, E: Display>(&self, call: impl Future<Output = Result<T, E>>) -> Option<T> {
+        if !self.admit() {
+            record_dependency_fallback(self.dependency, self.feature);
+            return None;
+        }
+        let failure = match tokio::time::timeout(CALL_TIMEOUT, call).await {
+            Ok(Ok(value)) => {
+                self.succeeded();
+                return Some(value);
+            }
+            Ok(Err(e)) => e.to_string(),
+            Err(_) => format!("no answer within {:?}", CALL_TIMEOUT),
+        };
+        self.failed(&failure);
+        record_dependency_fallback(self.dependency, self.feature);
+        None
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        self.state.lock().expect("circuit breaker lock poisoned")
+    }
+
+    /// Whether a call may go through: always while closed, and once per
+    /// `OPEN_FOR` as a probe while open
+    fn admit(&self) -> bool {
+        let mut state = self.state();
+        match state.open_until {
+            Some(open_until) if Instant::now() < open_until => false,
+            Some(_) => {
+                state.open_until = Some(Instant::now() + OPEN_FOR);
+                true
+            }
+            None => true,
+        }
+    }
+
+    fn succeeded(&self) {
+        let mut state = self.state();
+        state.failures = 0;
+        if state.open_until.take().is_some() {
+            info!("{} is back for {}; leaving its fallback", self.dependency, self.feature);
+            record_dependency_degraded(self.dependency, self.feature, false);
+        }
+    }
+
+    fn failed(&self, failure: &str) {
+        let mut state = self.state();
+        state.failures = state.failures.saturating_add(1);
+        match state.open_until {
+            // A failed probe keeps the breaker open for another period
+            Some(_) => state.open_until = Some(Instant::now() + OPEN_FOR),
+            None if state.failures >= FAILURE_THRESHOLD => {
+                warn!(
+                    "{} failed {} times in a row for {} ({}); falling back for {:?}",
+                    self.dependency, state.failures, self.feature, failure, OPEN_FOR
+                );
+                state.open_until = Some(Instant::now() + OPEN_FOR);
+                record_dependency_degraded(self.dependency, self.feature, true);
+            }
+            None => warn!("{} failed for {}: {}; falling back", self.dependency, self.feature, failure),
+        }
+    }
+}
+
+/// Redis connection of one feature behind its breaker. The connection is
+/// made on first use and reconnects by itself, so a server starts while
+/// Redis is down and picks it up once it is back.
+pub(crate) struct RedisLink {
+    client: redis::Client,
+    connection: OnceCell<ConnectionManager>,
+    breaker: CircuitBreaker,
+}
+
+impl RedisLink {
+    pub(crate) fn new(url: &str, feature: &'static str) -> ClobResult<Self> {
+        Ok(Self {
+            client: redis::Client::open(url).map_err(|e| ClobError::StorageError(e.to_string()))?,
+            connection: OnceCell::new(),
+            breaker: CircuitBreaker::new("redis", feature),
+        })
+    }
+
+    /// Run `command` on the connection; `None` when Redis is unavailable
+    pub(crate) async fn run<T, F, Fut>(&self, command: F) -> Option<T>
+    where
+        F: FnOnce(ConnectionManager) -> Fut,
+        Fut: Future<Output = RedisResult<T>>,
+    {
+        self.breaker
+            .call(async {
+                let connection = self
+                    .connection
+                    .get_or_try_init(|| ConnectionManager::new(self.client.clone()))
+                    .await?;
+                command(connection.clone()).await
+            })
+            .await
+    }
+
+    pub(crate) fn is_degraded(&self) -> bool {
+        self.breaker.is_open()
+    }
+
+    pub(crate) fn feature(&self) -> &'static str {
+        self.breaker.feature()
+    }
+}
@@ -0,0 +1,84 @@
+/// HTTP caching of market data
+///
+/// Order book and depth responses carry a weak ETag built from the book's
+/// sequence number, the journal position, which moves with every command that
+/// can change the book. A client polling with `If-None-Match` gets an empty
+/// `304 Not Modified` until the book changes. The tag also names the server
+/// process, since a point-in-time restore reuses sequence numbers for a
+/// different history. Trades and candles instead carry a short
+/// `Cache-Control: max-age`, letting browsers and CDNs absorb polling.
+
+use axum::{
+    http::{
+        header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds trades may be served from a cache
+pub(crate) const TRADES_MAX_AGE: u32 = 1;
+
+/// Seconds candles may be served from a cache
+pub(crate) const CANDLES_MAX_AGE: u32 = 5;
+
+/// Identifies this server process in ETags
+fn instance() -> u64 {
+    static INSTANCE: OnceLock<u64> = OnceLock::new();
+    *INSTANCE.get_or_init(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or_default()
+    })
+}
+
+/// Weak ETag of a view of the book at `sequence`; `variant` tells apart
+/// views of the same book, such as depth at different level counts
+pub(crate) fn book_etag(sequence: u64, variant: &str) -> String {
+    format!("W/\"{:x}-{}-{}\"", instance(), sequence, variant)
+}
+
+/// Whether the request's `If-None-Match` names `etag`
+pub(crate) fn is_fresh(headers: &HeaderMap, etag: &str) -> bool {
+    let Some(candidates) = headers.get(IF_NONE_MATCH).and_then(|value| value.to_str().ok()) else {
+        return false;
+    };
+    // If-None-Match compares weakly, so a `W/` prefix on either side is ignored
+    let opaque = etag.trim_start_matches("W/");
+    candidates
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == opaque)
+}
+
+/// `304 Not Modified` for a client already holding `etag`
+pub(crate) fn not_modified(etag: &str) -> Response {
+    let mut response = StatusCode::NOT_MODIFIED.into_response();
+    insert(&mut response, ETAG, etag);
+    insert(&mut response, CACHE_CONTROL, "no-cache");
+    response
+}
+
+/// `body` tagged with `etag`; clients must revalidate before reusing it
+pub(crate) fn tagged(etag: &str, body: impl IntoResponse) -> Response {
+    let mut response = body.into_response();
+    insert(&mut response, ETAG, etag);
+    insert(&mut response, CACHE_CONTROL, "no-cache");
+    response
+}
+
+/// `body` cacheable by anyone for `seconds`
+pub(crate) fn max_age(seconds: u32, body: impl IntoResponse) -> Response {
+    let mut response = body.into_response();
+    insert(&mut response, CACHE_CONTROL, &format!("public, max-age={}", seconds));
+    response
+}
+
+fn insert(response: &mut Response, name: axum::http::HeaderName, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        response.headers_mut().insert(name, value);
+    }
+}
@@ -0,0 +1,284 @@
+/// Idempotent order commands
+///
+/// A placement, batch, modification or cancellation sent with an
+/// `Idempotency-Key` header runs once per key. The answer is kept for
+/// `IDEMPOTENCY_TTL` and a retry carrying the same key is answered with it,
+/// marked `Idempotent-Replayed: true`, without reaching the engine again; a
+/// retry arriving while the first call is still running waits for its answer.
+/// A key sent again with another route or body is refused with
+/// `ClobError::IdempotencyKeyReused`. Keys are scoped to the tenant API key
+/// the call was made with, so tenants cannot replay each other's answers.
+///
+/// Answers that did not settle the command are not kept, so a retry runs it
+/// again: rate limiting (429), server errors (5xx) and calls refused before
+/// they were authenticated (401, 403). Keys live in the replica that answered
+/// them; a retry sent to another replica runs again, and only the engine's
+/// client order ID check stops a second copy of an order that rests.
+
+use crate::suspension::ApiKeyId;
+use crate::RpcServerState;
+use svm_clob_storage::Storage;
+use svm_clob_types::ClobError;
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::{Request, State},
+    http::{header::CONTENT_TYPE, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// Header naming the key of a command
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Header set on an answer replayed for a retry
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// How long an answer is kept for retries
+pub const IDEMPOTENCY_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// Longest key accepted
+const MAX_KEY_LENGTH: usize = 255;
+
+/// Answers kept before the oldest are forgotten early
+const MAX_ENTRIES: usize = 100_000;
+
+/// Largest request body read, the limit axum's `Json` applies anyway
+const MAX_REQUEST_BYTES: usize = 2 * 1024 * 1024;
+
+/// Answer of a command, as replayed
+#[derive(Clone)]
+struct Answer {
+    status: StatusCode,
+    content_type: Option<HeaderValue>,
+    body: Bytes,
+}
+
+enum Entry {
+    /// The first call is running; its answer is sent when it is kept, and
+    /// the sender dropped when it is not
+    Running { fingerprint: u64, answer: watch::Receiver<Option<Answer>> },
+    Done { fingerprint: u64, answer: Answer },
+}
+
+impl Entry {
+    fn fingerprint(&self) -> u64 {
+        match self {
+            Entry::Running { fingerprint, .. } | Entry::Done { fingerprint, .. } => *fingerprint,
+        }
+    }
+}
+
+/// Tenant API key ID, or empty for calls made without one, and the key
+type Scope = (String, String);
+
+#[derive(Default)]
+struct Entries {
+    /// Each key's entry, with the time it was claimed
+    by_key: HashMap<Scope, (Instant, Entry)>,
+    /// Keys in the order they were first used, with that time
+    order: VecDeque<(Instant, Scope)>,
+}
+
+impl Entries {
+    /// Forget answers past the TTL, and the oldest over `MAX_ENTRIES`
+    fn prune(&mut self, now: Instant) {
+        while let Some((used_at, _)) = self.order.front() {
+            let expired = now.duration_since(*used_at) >= IDEMPOTENCY_TTL;
+            if !expired && self.order.len() < MAX_ENTRIES {
+                break;
+            }
+            // A key forgotten and claimed again is listed again, later
+            if let Some((used_at, scope)) = self.order.pop_front() {
+                if self.by_key.get(&scope).is_some_and(|(claimed_at, _)| *claimed_at == used_at) {
+                    self.by_key.remove(&scope);
+                }
+            }
+        }
+    }
+}
+
+/// What to do with a call carrying a key
+enum Claim<'a> {
+    /// Run it, and keep its answer with the claim
+    Run(Running<'a>),
+    Replay(Answer),
+    Wait(watch::Receiver<Option<Answer>>),
+    Mismatch,
+}
+
+/// Answers of the commands this replica ran under a key
+#[derive(Default)]
+pub struct IdempotencyCache {
+    entries: Mutex<Entries>,
+}
+
+impl IdempotencyCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn claim(&self, scope: Scope, fingerprint: u64) -> Claim<'_> {
+        let mut entries = self.entries.lock().expect("idempotency cache lock poisoned");
+        let now = Instant::now();
+        entries.prune(now);
+        match entries.by_key.get(&scope).map(|(_, entry)| entry) {
+            Some(entry) if entry.fingerprint() != fingerprint => Claim::Mismatch,
+            Some(Entry::Done { answer, .. }) => Claim::Replay(answer.clone()),
+            Some(Entry::Running { answer, .. }) => Claim::Wait(answer.clone()),
+            None => {
+                let (sender, answer) = watch::channel(None);
+                entries.by_key.insert(scope.clone(), (now, Entry::Running { fingerprint, answer }));
+                entries.order.push_back((now, scope.clone()));
+                Claim::Run(Running {
+                    cache: self,
+                    scope,
+                    fingerprint,
+                    sender: Some(sender),
+                })
+            }
+        }
+    }
+}
+
+/// Claim of a running call on its key; a claim dropped without an answer,
+/// as when the client goes away mid-call, is forgotten
+struct Running<'a> {
+    cache: &'a IdempotencyCache,
+    scope: Scope,
+    fingerprint: u64,
+    sender: Option<watch::Sender<Option<Answer>>>,
+}
+
+impl Running<'_> {
+    /// Keep `answer` for retries, or forget the claim when it is `None`
+    fn finish(mut self, answer: Option<Answer>) {
+        let sender = self.sender.take();
+        let mut entries = self.cache.entries.lock().expect("idempotency cache lock poisoned");
+        match (answer, sender) {
+            (Some(answer), Some(sender)) => {
+                let fingerprint = self.fingerprint;
+                if let Some((_, entry)) = entries.by_key.get_mut(&self.scope) {
+                    *entry = Entry::Done { fingerprint, answer: answer.clone() };
+                }
+                sender.send_replace(Some(answer));
+            }
+            _ => {
+                entries.by_key.remove(&self.scope);
+            }
+        }
+    }
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        if self.sender.is_some() {
+            let mut entries = self.cache.entries.lock().expect("idempotency cache lock poisoned");
+            entries.by_key.remove(&self.scope);
+        }
+    }
+}
+
+/// Whether an answer with `status` settled the command
+fn is_kept(status: StatusCode) -> bool {
+    !(status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::UNAUTHORIZED
+        || status == StatusCode::FORBIDDEN)
+}
+
+fn fingerprint(method: &Method, path: &str, body: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    method.as_str().hash(&mut hasher);
+    path.hash(&mut hasher);
+    body.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn replay(answer: Answer) -> Response {
+    let mut response = (answer.status, answer.body).into_response();
+    if let Some(content_type) = answer.content_type {
+        response.headers_mut().insert(CONTENT_TYPE, content_type);
+    }
+    response
+        .headers_mut()
+        .insert(HeaderName::from_static(IDEMPOTENT_REPLAYED_HEADER), HeaderValue::from_static("true"));
+    response
+}
+
+fn refuse(e: ClobError) -> Response {
+    crate::ApiError::Clob(e).into_response()
+}
+
+/// Run order commands carrying an `Idempotency-Key` once per key
+pub(crate) async fn replay_idempotent<S: Storage + 'static>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let is_command = matches!(*request.method(), Method::POST | Method::PUT | Method::DELETE)
+        && request.uri().path().starts_with("/api/v1/orders");
+    let key = request.headers().get(IDEMPOTENCY_KEY_HEADER).map(|value| value.to_str().map(str::to_string));
+    let key = match (is_command, key) {
+        (true, Some(Ok(key))) if !key.is_empty() && key.len() <= MAX_KEY_LENGTH => key,
+        (true, Some(_)) => return StatusCode::BAD_REQUEST.into_response(),
+        _ => return next.run(request).await,
+    };
+    let api_key = request.extensions().get::<ApiKeyId>().map(|ApiKeyId(id)| id.clone()).unwrap_or_default();
+    let scope = (api_key, key);
+
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let (parts, body) = request.into_parts();
+    let Ok(body) = to_bytes(body, MAX_REQUEST_BYTES).await else {
+        return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+    };
+    let fingerprint = fingerprint(&method, &path, &body);
+
+    let key = scope.1.clone();
+    let running = match state.idempotency.claim(scope, fingerprint) {
+        Claim::Run(running) => running,
+        Claim::Replay(answer) => return replay(answer),
+        Claim::Wait(mut answer) => {
+            // The first call's claim is dropped without an answer when it is
+            // not kept, and the retry is answered as unavailable to try again
+            return match answer.wait_for(Option::is_some).await {
+                Ok(answer) => replay((*answer).clone().expect("waited for an answer")),
+                Err(_) => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+            };
+        }
+        Claim::Mismatch => {
+            return refuse(ClobError::IdempotencyKeyReused(format!(
+                "key '{}' was used for another request",
+                key
+            )))
+        }
+    };
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    let status = response.status();
+    if !is_kept(status) {
+        running.finish(None);
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(_) => {
+            running.finish(None);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let answer = Answer {
+        status,
+        content_type: parts.headers.get(CONTENT_TYPE).cloned(),
+        body: body.clone(),
+    };
+    running.finish(Some(answer));
+    Response::from_parts(parts, Body::from(body))
+}
@@ -4,32 +4,84 @@
 /// for order placement, cancellation, and market data retrieval.
 
 use svm_clob_types::*;
+use svm_clob_types::receipts::ReceiptProof;
+use svm_clob_types::statements::{statement_date, statement_period_start};
 use svm_clob_matching_engine::MatchingEngine;
 use svm_clob_storage::Storage;
+use svm_clob_risk::RiskEngine;
+use svm_clob_serve::ListenOptions;
 use axum::{
-    extract::{State, Query, Path},
-    http::StatusCode,
-    response::Json,
+    extract::{State, Query, Path, MatchedPath, Request},
+    http::{header::{CONTENT_DISPOSITION, CONTENT_TYPE}, HeaderMap, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Json, Response},
     routing::{get, post, delete, put},
-    Router,
+    Extension, Router,
 };
+use opentelemetry::propagation::Extractor;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tracing::{info, warn, error};
+use tracing::{info, warn, error, info_span, Instrument};
+
+pub mod admin;
+mod audit;
+pub mod breaker;
+mod cache;
+pub mod idempotency;
+pub mod nonce;
+pub mod paper;
+pub mod rate_limit;
+pub mod speed_bump;
+pub mod suspension;
+pub mod tenant;
+
+use idempotency::IdempotencyCache;
+use nonce::NonceStore;
+use paper::{PaperExchange, PaperMode};
+use rate_limit::{RateKey, RateLimiter};
+use speed_bump::SpeedBumpGate;
+use suspension::{ApiKeyId, SuspensionList};
+use tenant::{TenantContext, TenantDirectory};
 
 /// RPC server state
 pub struct RpcServerState<S: Storage> {
     pub matching_engine: Arc<RwLock<MatchingEngine<S>>>,
     pub storage: Arc<S>,
+    /// Risk engine registered as a pre-trade hook on `matching_engine`
+    pub risk: Arc<RiskEngine>,
+    /// Bearer token for `/api/v1/admin`; admin routes are disabled without one
+    pub admin_token: Option<String>,
+    /// Reject placements that do not carry the owner's order signature
+    pub require_signed_orders: bool,
+    /// Nonces of signed placements already accepted
+    pub nonces: NonceStore,
+    /// Sandbox of paper trading keys; calls made with one are refused without it
+    pub paper: Option<PaperExchange>,
+    /// Symbols and decimals of the engine's market, for `ui_*` fields
+    pub market_metadata: MarketMetadata,
+    /// SVM CLOB program the engine's market lives in, for deposit instructions
+    pub program_id: solana_sdk::pubkey::Pubkey,
+    /// Per-owner order and per-IP request rate limits, tunable through the admin API
+    pub rate_limiter: RateLimiter,
+    /// Tenants' API keys and usage metering
+    pub tenants: TenantDirectory,
+    /// Delay of orders that only take, tunable through the admin API
+    pub speed_bump: SpeedBumpGate,
+    /// Owners and API keys whose new orders are refused
+    pub suspensions: SuspensionList,
+    /// Answers of order commands sent with an `Idempotency-Key`, for retries
+    pub idempotency: IdempotencyCache,
+    /// Time source for order timestamps, signature expiry and rate limits
+    pub clock: Arc<dyn Clock>,
 }
 
-/// JSON-RPC response wrapper
+/// JSON-RPC response wrapper; the result travels in a versioned envelope
 #[derive(Serialize)]
 pub struct JsonRpcResponse<T> {
     pub jsonrpc: String,
     pub id: Option<u64>,
-    pub result: Option<T>,
+    pub result: Option<Envelope<T>>,
     pub error: Option<JsonRpcError>,
 }
 
@@ -46,94 +98,360 @@ pub fn create_router<S: Storage + 'static>() -> Router<Arc<RpcServerState<S>>> {
     Router::new()
         // Order management endpoints
         .route("/api/v1/orders", post(place_order_handler))
+        .route("/api/v1/orders/batch", post(batch_orders_handler))
         .route("/api/v1/orders/:order_id", delete(cancel_order_handler))
         .route("/api/v1/orders/:order_id", put(modify_order_handler))
         .route("/api/v1/orders/:order_id", get(get_order_handler))
+        .route("/api/v1/orders/:order_id/queue-position", get(get_queue_position_handler))
         
         // Market data endpoints
         .route("/api/v1/orderbook", get(get_orderbook_handler))
+        .route("/api/v1/orderbook/history", get(get_orderbook_history_handler))
         .route("/api/v1/trades", get(get_trades_handler))
+        .route("/api/v1/trades/:trade_id", get(get_trade_handler))
         .route("/api/v1/market/stats", get(get_market_stats_handler))
+        .route("/api/v1/markets", get(get_markets_handler))
+        .route("/api/v1/markets/:market_id/orderbook", get(get_market_orderbook_handler))
+        .route("/api/v1/markets/:market_id/trades", get(get_market_trades_handler))
+        .route("/api/v1/markets/:market_id/stats", get(get_market_stats_by_id_handler))
+        .route("/api/v1/markets/:market_id/ticker", get(get_market_ticker_handler))
+        .route("/api/v1/markets/:market_id/depth", get(get_market_depth_handler))
+        .route("/api/v1/markets/:market_id/candles", get(get_market_candles_handler))
         
         // User endpoints
         .route("/api/v1/users/:user_id/orders", get(get_user_orders_handler))
+        .route("/api/v1/users/:user_id/fees", get(get_user_fees_handler))
+        .route("/api/v1/users/:user_id/fee-tier", get(get_user_fee_tier_handler))
+        .route("/api/v1/users/:user_id/sub-accounts", get(get_user_sub_accounts_handler))
+        .route("/api/v1/users/:user_id/portfolio", get(get_user_portfolio_handler))
+        .route("/api/v1/users/:user_id/balances", get(get_user_balances_handler))
+        .route("/api/v1/users/:user_id/incentives", get(get_user_incentives_handler))
+        .route("/api/v1/users/:user_id/deposit-instructions", get(get_user_deposit_instructions_handler))
+        .route("/api/v1/users/:user_id/vault-transfers", get(get_user_vault_transfers_handler))
+        .route("/api/v1/users/:user_id/receipts", get(get_user_receipts_handler))
+        .route("/api/v1/receipts/:merkle_tree/:leaf_index/proof", get(get_receipt_proof_handler))
+        .route("/api/v1/users/:user_id/statements", get(get_user_statements_handler))
+        .route("/api/v1/users/:user_id/statements/:date", get(get_user_statement_handler))
+        
+        // Liquidity incentive endpoints
+        .route("/api/v1/incentives/epochs", get(get_incentive_epochs_handler))
+        .route("/api/v1/incentives/epochs/:epoch_start", get(get_incentive_epoch_handler))
+        
+        // Insurance fund endpoints
+        .route("/api/v1/insurance-fund", get(get_insurance_fund_handler))
+        .route("/api/v1/insurance-fund/events", get(get_insurance_fund_events_handler))
+        
+        // Sequence map endpoints
+        .route("/api/v1/sequence/orders/:order_id", get(get_order_sequence_handler))
+        .route("/api/v1/sequence/journal/:sequence", get(get_journal_sequence_handler))
+        .route("/api/v1/sequence/signatures/:signature", get(get_signature_sequence_handler))
+        .route("/api/v1/sequence/accounts/:user_account", get(get_account_sequence_handler))
+        
+        // Chain status endpoints
+        .route("/api/v1/chain/transactions/:signature", get(get_chain_transaction_handler))
+        
+        // Tenant endpoints, for calls made with an API key
+        .route("/api/v1/tenant", get(tenant::get_tenant_handler))
+        .route("/api/v1/tenant/usage", get(tenant::get_tenant_usage_handler))
         
         // Health check
         .route("/health", get(health_check_handler))
+        
+        // Operator endpoints
+        .merge(admin::admin_router())
+
+        // Request tracing
+        .route_layer(middleware::from_fn(trace_request))
+}
+
+/// Give every call a request ID, the caller's `X-Request-Id` when usable, and
+/// answer with it; storage writes made for the call record it
+async fn assign_request_id(request: Request, next: Next) -> Response {
+    let request_id = svm_clob_telemetry::request_id_or_new(
+        request
+            .headers()
+            .get(svm_clob_telemetry::REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok()),
+    );
+    let header = HeaderValue::from_str(&request_id).ok();
+    let mut response = svm_clob_telemetry::with_request_id(request_id, next.run(request)).await;
+    if let Some(header) = header {
+        response.headers_mut().insert(svm_clob_telemetry::REQUEST_ID_HEADER, header);
+    }
+    response
+}
+
+/// Open a span per request, continuing the caller's trace from its `traceparent` header
+async fn trace_request(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let span = info_span!(
+        "http.request",
+        http.method = %request.method(),
+        http.route = %route,
+        http.status_code = tracing::field::Empty,
+        request_id = %svm_clob_telemetry::current_request_id().unwrap_or_default(),
+    );
+    let parent = svm_clob_telemetry::extract_context(&HeaderExtractor(request.headers()));
+    svm_clob_telemetry::set_remote_parent(&span, parent);
+
+    let response = next.run(request).instrument(span.clone()).await;
+    span.record("http.status_code", response.status().as_u16());
+    response
+}
+
+/// Read W3C trace context from request headers
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+/// Failure of an order command: a bare status for requests that never reached
+/// the engine, or an engine error answered with its canonical HTTP status and
+/// a JSON-RPC error body
+pub enum ApiError {
+    Status(StatusCode),
+    Clob(ClobError),
+}
+
+impl From<StatusCode> for ApiError {
+    fn from(status: StatusCode) -> Self {
+        ApiError::Status(status)
+    }
+}
+
+impl From<ClobError> for ApiError {
+    fn from(e: ClobError) -> Self {
+        ApiError::Clob(e)
+    }
+}
+
+impl From<&ClobError> for JsonRpcError {
+    fn from(e: &ClobError) -> Self {
+        JsonRpcError {
+            code: e.code() as i32,
+            message: e.to_string(),
+            data: Some(match e.rate_limit() {
+                Some(status) => serde_json::json!({ "detail": e.detail(), "grpc_code": e.grpc_code(), "rate_limit": status }),
+                None => serde_json::json!({ "detail": e.detail(), "grpc_code": e.grpc_code() }),
+            }),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        match self {
+            ApiError::Status(status) => status.into_response(),
+            ApiError::Clob(e) => {
+                let status = StatusCode::from_u16(e.http_status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+                let body = JsonRpcResponse::<()> {
+                    jsonrpc: "2.0".to_string(),
+                    id: Some(1),
+                    result: None,
+                    error: Some(JsonRpcError::from(&e)),
+                };
+                let mut response = (status, Json(body)).into_response();
+                if let Some(status) = e.rate_limit() {
+                    rate_limit::insert_headers(response.headers_mut(), status);
+                }
+                response
+            }
+        }
+    }
 }
 
 /// Place order handler
 async fn place_order_handler<S: Storage>(
     State(state): State<Arc<RpcServerState<S>>>,
+    tenant: Option<Extension<TenantContext>>,
+    api_key: Option<Extension<ApiKeyId>>,
+    paper: Option<Extension<PaperMode>>,
     Json(request): Json<PlaceOrderRequest>,
-) -> Result<Json<JsonRpcResponse<Order>>, StatusCode> {
+) -> Result<Json<JsonRpcResponse<Order>>, ApiError> {
     info!("Received place order request");
     
-    let current_time = chrono::Utc::now().timestamp();
-    let order_id = generate_order_id().await;
-    
-    // Create order from request
-    let owner_pubkey = match request.owner.parse::<solana_sdk::pubkey::Pubkey>() {
-        Ok(pubkey) => pubkey,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    let paper = paper_exchange(&state, paper)?;
+    let tenant = tenant.map(|Extension(TenantContext(tenant))| tenant);
+    let api_key = api_key.map(|Extension(api_key)| api_key);
+    expiry::check_expiry(&request, state.clock.now())?;
+    check_order_rate(&state, tenant.as_deref(), &request.owner).await?;
+    check_suspension(&state, &request.owner, api_key.as_ref()).await?;
+    state.speed_bump.hold([&request]).await;
+    let matching_engine = state.matching_engine.read().await;
+    authorize_order(&state, &request, matching_engine.market_id()).await?;
+    let order_id = allocate_order_id(&matching_engine, paper).await?;
+    let order = match order_from_request(request, order_id, state.clock.now()) {
+        Some(order) => order,
+        None => return Err(StatusCode::BAD_REQUEST.into()),
     };
     
-    let order = Order {
-        order_id,
-        owner: owner_pubkey,
-        price: request.price,
-        quantity: request.quantity,
-        remaining_quantity: request.quantity,
-        timestamp: current_time,
-        client_order_id: request.client_order_id,
-        expiry_timestamp: request.expiry_timestamp.unwrap_or(0),
-        side: request.side,
-        order_type: request.order_type,
-        status: OrderStatus::Open,
-        self_trade_behavior: request.self_trade_behavior,
-        time_in_force: request.time_in_force,
+    // Process order through matching engine, or the sandbox for a paper key
+    let placed = match paper {
+        Some(paper) => paper.place_order(&matching_engine, order.clone()).await,
+        None => matching_engine.place_order(order.clone()).await,
     };
-    
-    // Process order through matching engine
-    let matching_engine = state.matching_engine.read().await;
-    match matching_engine.place_order(order.clone()).await {
+    match placed {
         Ok(_trades) => {
+            if let (Some(tenant), None) = (&tenant, paper) {
+                state.tenants.record_order(tenant, &order, state.clock.now());
+            }
             let response = JsonRpcResponse {
                 jsonrpc: "2.0".to_string(),
                 id: Some(1),
-                result: Some(order),
+                result: Some(Envelope::new(order)),
                 error: None,
             };
             Ok(Json(response))
         }
         Err(e) => {
             error!("Failed to place order: {}", e);
-            Err(StatusCode::BAD_REQUEST)
+            Err(e.into())
         }
     }
 }
 
+/// Batch cancel-replace handler
+async fn batch_orders_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    tenant: Option<Extension<TenantContext>>,
+    api_key: Option<Extension<ApiKeyId>>,
+    paper: Option<Extension<PaperMode>>,
+    Json(request): Json<BatchOrderRequest>,
+) -> Result<Json<JsonRpcResponse<BatchOrderResponse>>, StatusCode> {
+    info!(
+        "Received batch request: {} cancels, {} placements",
+        request.cancel.len(),
+        request.place.len()
+    );
+    
+    let paper = paper_exchange(&state, paper)?;
+    let tenant = tenant.map(|Extension(TenantContext(tenant))| tenant);
+    let api_key = api_key.map(|Extension(api_key)| api_key);
+    let mut result = BatchOrderResponse::default();
+    state.speed_bump.hold(&request.place).await;
+    let matching_engine = state.matching_engine.read().await;
+    
+    for order_id in request.cancel {
+        let cancelled = match paper {
+            Some(paper) => paper.cancel_order(order_id).await,
+            None => matching_engine.cancel_order(order_id).await,
+        };
+        match cancelled {
+            Ok(cancelled_order) => result.cancelled.push(cancelled_order),
+            Err(e) => result.errors.push(BatchOrderError {
+                item: BatchOrderItem::Cancel(order_id),
+                message: e.to_string(),
+                code: e.code(),
+                rate_limit: None,
+            }),
+        }
+    }
+    
+    for (index, place) in request.place.into_iter().enumerate() {
+        let checked = match expiry::check_expiry(&place, state.clock.now()) {
+            Ok(()) => check_order_rate(&state, tenant.as_deref(), &place.owner).await,
+            Err(e) => Err(e),
+        };
+        let checked = match checked {
+            Ok(()) => check_suspension(&state, &place.owner, api_key.as_ref()).await,
+            Err(e) => Err(e),
+        };
+        let checked = match checked {
+            Ok(()) => authorize_order(&state, &place, matching_engine.market_id()).await,
+            Err(e) => Err(e),
+        };
+        let allocated = match checked {
+            Ok(()) => allocate_order_id(&matching_engine, paper).await,
+            Err(e) => Err(e),
+        };
+        let order_id = match allocated {
+            Ok(order_id) => order_id,
+            Err(e) => {
+                if let (Some(tenant), ClobError::RateLimited(_)) = (&tenant, &e) {
+                    state.tenants.record_rate_limited(tenant, state.clock.now());
+                }
+                result.errors.push(BatchOrderError {
+                    item: BatchOrderItem::Place(index),
+                    message: e.to_string(),
+                    code: e.code(),
+                    rate_limit: e.rate_limit().copied(),
+                });
+                continue;
+            }
+        };
+        let Some(order) = order_from_request(place, order_id, state.clock.now()) else {
+            result.errors.push(BatchOrderError {
+                item: BatchOrderItem::Place(index),
+                message: "Invalid owner".to_string(),
+                code: error_codes::INVALID_REQUEST,
+                rate_limit: None,
+            });
+            continue;
+        };
+        let placed = match paper {
+            Some(paper) => paper.place_order(&matching_engine, order.clone()).await,
+            None => matching_engine.place_order(order.clone()).await,
+        };
+        match placed {
+            Ok(_trades) => {
+                if let (Some(tenant), None) = (&tenant, paper) {
+                    state.tenants.record_order(tenant, &order, state.clock.now());
+                }
+                result.placed.push(order)
+            }
+            Err(e) => result.errors.push(BatchOrderError {
+                item: BatchOrderItem::Place(index),
+                message: e.to_string(),
+                code: e.code(),
+                rate_limit: None,
+            }),
+        }
+    }
+    
+    Ok(Json(JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: Some(1),
+        result: Some(Envelope::new(result)),
+        error: None,
+    }))
+}
+
 /// Cancel order handler
 async fn cancel_order_handler<S: Storage>(
     State(state): State<Arc<RpcServerState<S>>>,
+    paper: Option<Extension<PaperMode>>,
     Path(order_id): Path<u64>,
-) -> Result<Json<JsonRpcResponse<Order>>, StatusCode> {
+) -> Result<Json<JsonRpcResponse<Order>>, ApiError> {
     info!("Received cancel order request for ID: {}", order_id);
     
-    let matching_engine = state.matching_engine.read().await;
-    match matching_engine.cancel_order(order_id).await {
+    let cancelled = match paper_exchange(&state, paper)? {
+        Some(paper) => paper.cancel_order(order_id).await,
+        None => state.matching_engine.read().await.cancel_order(order_id).await,
+    };
+    match cancelled {
         Ok(cancelled_order) => {
             let response = JsonRpcResponse {
                 jsonrpc: "2.0".to_string(),
                 id: Some(1),
-                result: Some(cancelled_order),
+                result: Some(Envelope::new(cancelled_order)),
                 error: None,
             };
             Ok(Json(response))
         }
         Err(e) => {
             error!("Failed to cancel order: {}", e);
-            Err(StatusCode::NOT_FOUND)
+            Err(e.into())
         }
     }
 }
@@ -141,25 +459,41 @@ async fn cancel_order_handler<S: Storage>(
 /// Modify order handler
 async fn modify_order_handler<S: Storage>(
     State(state): State<Arc<RpcServerState<S>>>,
+    api_key: Option<Extension<ApiKeyId>>,
+    paper: Option<Extension<PaperMode>>,
     Path(order_id): Path<u64>,
     Json(request): Json<ModifyOrderRequest>,
-) -> Result<Json<JsonRpcResponse<Order>>, StatusCode> {
+) -> Result<Json<JsonRpcResponse<Order>>, ApiError> {
     info!("Received modify order request for ID: {}", order_id);
     
+    let paper = paper_exchange(&state, paper)?;
+    // The replacement is a new order of the same owner
+    let original = match paper {
+        Some(paper) => paper.storage().get_order(order_id).await?,
+        None => state.storage.get_order(order_id).await?,
+    };
+    if let Some(original) = original {
+        let api_key = api_key.map(|Extension(api_key)| api_key);
+        state.suspensions.check(state.storage.as_ref(), &original.owner, api_key.as_ref()).await?;
+    }
     let matching_engine = state.matching_engine.read().await;
-    match matching_engine.modify_order(order_id, request.new_price, request.new_quantity).await {
+    let modified = match paper {
+        Some(paper) => paper.modify_order(&matching_engine, order_id, request.new_price, request.new_quantity).await,
+        None => matching_engine.modify_order(order_id, request.new_price, request.new_quantity).await,
+    };
+    match modified {
         Ok(modified_order) => {
             let response = JsonRpcResponse {
                 jsonrpc: "2.0".to_string(),
                 id: Some(1),
-                result: Some(modified_order),
+                result: Some(Envelope::new(modified_order)),
                 error: None,
             };
             Ok(Json(response))
         }
         Err(e) => {
             error!("Failed to modify order: {}", e);
-            Err(StatusCode::BAD_REQUEST)
+            Err(e.into())
         }
     }
 }
@@ -167,14 +501,20 @@ async fn modify_order_handler<S: Storage>(
 /// Get order handler
 async fn get_order_handler<S: Storage>(
     State(state): State<Arc<RpcServerState<S>>>,
+    paper: Option<Extension<PaperMode>>,
     Path(order_id): Path<u64>,
-) -> Result<Json<JsonRpcResponse<Order>>, StatusCode> {
-    match state.storage.get_order(order_id).await {
+) -> Result<Json<JsonRpcResponse<Displayed<Order>>>, StatusCode> {
+    let units = market_units(&state).await;
+    let order = match paper_exchange(&state, paper)? {
+        Some(paper) => paper.storage().get_order(order_id).await,
+        None => state.storage.get_order(order_id).await,
+    };
+    match order {
         Ok(Some(order)) => {
             let response = JsonRpcResponse {
                 jsonrpc: "2.0".to_string(),
                 id: Some(1),
-                result: Some(order),
+                result: Some(Envelope::new(Displayed::new(order, units.as_ref()))),
                 error: None,
             };
             Ok(Json(response))
@@ -187,44 +527,340 @@ async fn get_order_handler<S: Storage>(
     }
 }
 
-/// Get orderbook handler
-async fn get_orderbook_handler<S: Storage>(
+/// Get how much rests ahead of an order at its price; a paper order queues
+/// behind the live orders of the sandbox as well
+async fn get_queue_position_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    paper: Option<Extension<PaperMode>>,
+    Path(order_id): Path<u64>,
+) -> Result<Json<JsonRpcResponse<QueuePosition>>, ApiError> {
+    let paper = paper_exchange(&state, paper)?;
+    let matching_engine = state.matching_engine.read().await;
+    let position = match paper {
+        Some(paper) => paper.queue_position(&matching_engine, order_id).await?,
+        None => matching_engine.queue_position(order_id).await?,
+    };
+    let response = JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: Some(1),
+        result: Some(Envelope::new(position)),
+        error: None,
+    };
+    Ok(Json(response))
+}
+
+/// Sandbox to serve a call made with a paper trading key from, `None` for
+/// other calls; paper calls are refused when the server has no sandbox
+fn paper_exchange<S: Storage>(
+    state: &RpcServerState<S>,
+    paper: Option<Extension<PaperMode>>,
+) -> Result<Option<&PaperExchange>, StatusCode> {
+    match (paper, &state.paper) {
+        (None, _) => Ok(None),
+        (Some(_), Some(exchange)) => Ok(Some(exchange)),
+        (Some(_), None) => Err(StatusCode::FORBIDDEN),
+    }
+}
+
+/// Units of the engine's market, when its decimals are configured
+async fn market_units<S: Storage>(state: &RpcServerState<S>) -> Option<MarketUnits> {
+    let tick_size = state.matching_engine.read().await.orderbook_config().tick_size;
+    state.market_metadata.units(tick_size)
+}
+
+/// Resolve a market path segment to the engine's market, or 404
+async fn resolve_market<S: Storage>(state: &RpcServerState<S>, market_id: &str) -> Result<MarketId, StatusCode> {
+    let market_id: MarketId = market_id.parse().map_err(|_| StatusCode::NOT_FOUND)?;
+    if market_id != state.matching_engine.read().await.market_id() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(market_id)
+}
+
+/// List markets handler
+async fn get_markets_handler<S: Storage>(
     State(state): State<Arc<RpcServerState<S>>>,
-) -> Result<Json<JsonRpcResponse<OrderBookSnapshot>>, StatusCode> {
+) -> Json<JsonRpcResponse<Vec<MarketInfo>>> {
+    let matching_engine = state.matching_engine.read().await;
+    let config = matching_engine.orderbook_config();
+    let status = matching_engine.status();
+    let market = MarketInfo {
+        market_id: matching_engine.market_id(),
+        base_mint: config.base_mint,
+        quote_mint: config.quote_mint,
+        tick_size: config.tick_size,
+        min_order_size: config.min_order_size,
+        is_paused: status == MarketStatus::Paused,
+        status,
+        resting_lifetime: matching_engine.resting_lifetime(),
+        accepted: matching_engine.order_types().into(),
+        metadata: state.market_metadata.clone(),
+    };
+
+    Json(JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: Some(1),
+        result: Some(Envelope::new(vec![market])),
+        error: None,
+    })
+}
+
+/// Get one market's orderbook handler
+async fn get_market_orderbook_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path(market_id): Path<String>,
+    query: Query<OrderBookQuery>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    resolve_market(&state, &market_id).await?;
+    get_orderbook_handler(State(state), query, headers).await
+}
+
+/// Get one market's recent trades handler
+async fn get_market_trades_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path(market_id): Path<String>,
+    Query(page): Query<Paginated>,
+) -> Result<Response, StatusCode> {
+    let market_id = resolve_market(&state, &market_id).await?;
+    check_page(&page)?;
+    let units = market_units(&state).await;
+
+    match state.storage.get_market_trades(&market_id, &page).await {
+        Ok(trades) => {
+            let response = JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: Some(1),
+                result: Some(Envelope::new(trades.map(|trade| Displayed::new(trade, units.as_ref())))),
+                error: None,
+            };
+            Ok(cache::max_age(cache::TRADES_MAX_AGE, Json(response)))
+        }
+        Err(e) => {
+            error!("Failed to get market trades: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get one market's stats handler
+async fn get_market_stats_by_id_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path(market_id): Path<String>,
+) -> Result<Json<JsonRpcResponse<MarketStats>>, StatusCode> {
+    resolve_market(&state, &market_id).await?;
+    let stats = state.matching_engine.read().await.market_stats();
+    Ok(Json(JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: Some(1),
+        result: Some(Envelope::new(stats)),
+        error: None,
+    }))
+}
+
+/// Get one market's ticker handler
+async fn get_market_ticker_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path(market_id): Path<String>,
+) -> Result<Json<JsonRpcResponse<Ticker>>, StatusCode> {
+    let market_id = resolve_market(&state, &market_id).await?;
+    let (book, stats) = {
+        let matching_engine = state.matching_engine.read().await;
+        let book = matching_engine.get_order_book_snapshot().await.map_err(|e| {
+            error!("Failed to get orderbook snapshot: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        (book, matching_engine.market_stats())
+    };
+
+    let ticker = Ticker::from_window(
+        market_id,
+        &book,
+        stats.last_price.map(Price),
+        &stats.window_24h,
+        state.clock.now(),
+    );
+    Ok(Json(JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: Some(1),
+        result: Some(Envelope::new(ticker)),
+        error: None,
+    }))
+}
+
+/// Get one market's depth handler
+async fn get_market_depth_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path(market_id): Path<String>,
+    Query(params): Query<DepthQuery>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    resolve_market(&state, &market_id).await?;
+    let levels = params.levels.unwrap_or(20).min(500);
+    let units = market_units(&state).await;
+
     let matching_engine = state.matching_engine.read().await;
     match matching_engine.get_order_book_snapshot().await {
         Ok(snapshot) => {
+            let etag = cache::book_etag(snapshot.sequence_number, &format!("depth{}", levels));
+            if cache::is_fresh(&headers, &etag) {
+                return Ok(cache::not_modified(&etag));
+            }
             let response = JsonRpcResponse {
                 jsonrpc: "2.0".to_string(),
                 id: Some(1),
-                result: Some(snapshot),
+                result: Some(Envelope::new(
+                    Depth::from_snapshot(&snapshot, levels as usize).with_units(units.as_ref()),
+                )),
                 error: None,
             };
-            Ok(Json(response))
+            Ok(cache::tagged(&etag, Json(response)))
         }
         Err(e) => {
-            error!("Failed to get orderbook snapshot: {}", e);
+            error!("Failed to get depth: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
 }
 
+/// Get one market's candles handler
+async fn get_market_candles_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path(market_id): Path<String>,
+    Query(params): Query<CandleQuery>,
+) -> Result<Response, StatusCode> {
+    let market_id = resolve_market(&state, &market_id).await?;
+    let interval = match params.interval.as_deref() {
+        Some(interval) => interval.parse().map_err(|_| StatusCode::BAD_REQUEST)?,
+        None => CandleInterval::OneMinute,
+    };
+    let limit = params.limit.unwrap_or(100).min(1000);
+
+    match state.storage.get_market_candles(&market_id, interval, limit).await {
+        Ok(candles) => {
+            let response = JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: Some(1),
+                result: Some(Envelope::new(candles)),
+                error: None,
+            };
+            Ok(cache::max_age(cache::CANDLES_MAX_AGE, Json(response)))
+        }
+        Err(e) => {
+            error!("Failed to get candles: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get orderbook handler
+async fn get_orderbook_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Query(query): Query<OrderBookQuery>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let (snapshot, orderbook, market_id, allocation) = {
+        let matching_engine = state.matching_engine.read().await;
+        let snapshot = matching_engine.get_order_book_snapshot().await.map_err(|e| {
+            error!("Failed to get orderbook snapshot: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        (
+            snapshot,
+            matching_engine.orderbook_config().clone(),
+            matching_engine.market_id(),
+            matching_engine.allocation(),
+        )
+    };
+
+    let sequence = query.sequence.unwrap_or(snapshot.sequence_number);
+    let etag = cache::book_etag(sequence, "book");
+    if cache::is_fresh(&headers, &etag) {
+        return Ok(cache::not_modified(&etag));
+    }
+
+    let snapshot = if sequence == snapshot.sequence_number {
+        snapshot
+    } else {
+        let head = state.storage.get_journal_head().await.map_err(|e| {
+            error!("Failed to get journal head: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        if sequence > head {
+            return Err(StatusCode::NOT_FOUND);
+        }
+        svm_clob_matching_engine::book_at(&*state.storage, &orderbook, market_id, allocation, sequence)
+            .await
+            .map_err(|e| {
+                error!("Failed to rebuild the orderbook at sequence {}: {}", sequence, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+    };
+
+    let response = JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: Some(1),
+        result: Some(Envelope::new(snapshot)),
+        error: None,
+    };
+    Ok(cache::tagged(&etag, Json(response)))
+}
+
+/// Books sampled over a past time range, rebuilt from checkpoints and the journal
+async fn get_orderbook_history_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Query(query): Query<OrderBookHistoryQuery>,
+) -> Result<Json<JsonRpcResponse<Vec<OrderBookSnapshot>>>, StatusCode> {
+    let (from, to, granularity) = query.range(state.clock.now())?;
+    let (orderbook, market_id, allocation) = {
+        let matching_engine = state.matching_engine.read().await;
+        (
+            matching_engine.orderbook_config().clone(),
+            matching_engine.market_id(),
+            matching_engine.allocation(),
+        )
+    };
+
+    let books = svm_clob_matching_engine::book_history(
+        &*state.storage,
+        &orderbook,
+        market_id,
+        allocation,
+        from,
+        to,
+        granularity,
+    )
+    .await
+    .map_err(|e| {
+        error!("Failed to rebuild the orderbook from {} to {}: {}", from, to, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: Some(1),
+        result: Some(Envelope::new(books)),
+        error: None,
+    }))
+}
+
 /// Get recent trades handler
 async fn get_trades_handler<S: Storage>(
     State(state): State<Arc<RpcServerState<S>>>,
-    Query(params): Query<TradeQuery>,
-) -> Result<Json<JsonRpcResponse<Vec<TradeExecution>>>, StatusCode> {
-    let limit = params.limit.unwrap_or(100).min(1000);
-    
-    match state.storage.get_recent_trades(limit).await {
+    Query(page): Query<Paginated>,
+) -> Result<Response, StatusCode> {
+    check_page(&page)?;
+    let units = market_units(&state).await;
+
+    match state.storage.get_recent_trades(&page).await {
         Ok(trades) => {
             let response = JsonRpcResponse {
                 jsonrpc: "2.0".to_string(),
                 id: Some(1),
-                result: Some(trades),
+                result: Some(Envelope::new(trades.map(|trade| Displayed::new(trade, units.as_ref())))),
                 error: None,
             };
-            Ok(Json(response))
+            Ok(cache::max_age(cache::TRADES_MAX_AGE, Json(response)))
         }
         Err(e) => {
             error!("Failed to get trades: {}", e);
@@ -233,98 +869,912 @@ async fn get_trades_handler<S: Storage>(
     }
 }
 
+/// Get one trade as it stands, with the busts and corrections that made it so
+async fn get_trade_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path(trade_id): Path<u64>,
+) -> Result<Json<JsonRpcResponse<TradeHistory>>, StatusCode> {
+    let trade = match state.storage.get_trade(trade_id).await {
+        Ok(Some(trade)) => trade,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to get trade {}: {}", trade_id, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    match state.storage.get_trade_adjustments(trade_id).await {
+        Ok(adjustments) => Ok(Json(JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(1),
+            result: Some(Envelope::new(TradeHistory { trade, adjustments })),
+            error: None,
+        })),
+        Err(e) => {
+            error!("Failed to get adjustments of trade {}: {}", trade_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
 /// Get market stats handler
 async fn get_market_stats_handler<S: Storage>(
     State(state): State<Arc<RpcServerState<S>>>,
 ) -> Result<Json<JsonRpcResponse<MarketStats>>, StatusCode> {
-    match state.storage.get_recent_trades(1000).await {
-        Ok(trades) => {
-            let last_price = trades.first().map(|t| t.price);
-            let volume_24h = trades.iter().map(|t| t.quantity).sum();
-            let high_24h = trades.iter().map(|t| t.price).max();
-            let low_24h = trades.iter().map(|t| t.price).min();
-
-            let stats = MarketStats {
-                last_price,
-                volume_24h,
-                high_24h,
-                low_24h,
+    let stats = state.matching_engine.read().await.market_stats();
+    Ok(Json(JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: Some(1),
+        result: Some(Envelope::new(stats)),
+        error: None,
+    }))
+}
+
+/// Get user orders handler
+async fn get_user_orders_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    paper: Option<Extension<PaperMode>>,
+    Path(user_id): Path<String>,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<Displayed<Order>>>>, StatusCode> {
+    check_page(&page)?;
+    let units = market_units(&state).await;
+
+    let orders = match paper_exchange(&state, paper)? {
+        Some(paper) => paper.storage().get_user_orders(&user_id, &page).await,
+        None => state.storage.get_user_orders(&user_id, &page).await,
+    };
+    match orders {
+        Ok(orders) => {
+            let response = JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: Some(1),
+                result: Some(Envelope::new(orders.map(|order| Displayed::new(order, units.as_ref())))),
+                error: None,
             };
+            Ok(Json(response))
+        }
+        Err(e) => {
+            error!("Failed to get user orders: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get a user's fee report for one month
+async fn get_user_fees_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path(user_id): Path<String>,
+    Query(params): Query<FeeReportQuery>,
+) -> Result<Response, StatusCode> {
+    let user: solana_sdk::pubkey::Pubkey = user_id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let (from, until) = params.period(state.clock.now())?;
+
+    match state.storage.get_fee_reports(from, until, Some(&user)).await {
+        Ok(reports) => Ok(fee_report_response(reports, params.format.as_deref())),
+        Err(e) => {
+            error!("Failed to get fee report for {}: {}", user_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get the fee tier a user was assigned from their trailing 30-day volume
+async fn get_user_fee_tier_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<JsonRpcResponse<UserFeeTier>>, StatusCode> {
+    let user: solana_sdk::pubkey::Pubkey = user_id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    match state.storage.get_fee_tier(&user).await {
+        Ok(Some(tier)) => Ok(Json(JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(1),
+            result: Some(Envelope::new(tier)),
+            error: None,
+        })),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to get fee tier of {}: {}", user_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// List a wallet's sub-accounts; each is queried, and trades, as its own user
+async fn get_user_sub_accounts_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<JsonRpcResponse<Vec<SubAccount>>>, StatusCode> {
+    let wallet: solana_sdk::pubkey::Pubkey = user_id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    match state.storage.get_sub_accounts(&wallet).await {
+        Ok(sub_accounts) => Ok(Json(JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(1),
+            result: Some(Envelope::new(sub_accounts)),
+            error: None,
+        })),
+        Err(e) => {
+            error!("Failed to get sub-accounts of {}: {}", user_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get a user's positions and P&L per market, marked to the oracle price or the current mid
+async fn get_user_portfolio_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    paper: Option<Extension<PaperMode>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<JsonRpcResponse<Portfolio>>, StatusCode> {
+    let user: solana_sdk::pubkey::Pubkey = user_id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let fills = match paper_exchange(&state, paper)? {
+        Some(paper) => paper.storage().get_user_fills(&user).await,
+        None => state.storage.get_user_fills(&user).await,
+    };
+    let fills = fills.map_err(|e| {
+        error!("Failed to get fills of {}: {}", user_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
 
+    // Only the engine's own market has a live book to mark against; the
+    // oracle's price, while fresh, marks it instead of the mid
+    let mut mids = std::collections::HashMap::new();
+    {
+        let matching_engine = state.matching_engine.read().await;
+        let mark = match state.risk.reference_price() {
+            Some(reference) => Some(reference.price),
+            None => {
+                let book = matching_engine.get_order_book_snapshot().await.map_err(|e| {
+                    error!("Failed to get orderbook snapshot: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+                portfolio::mid_price(&book)
+            }
+        };
+        if let Some(mark) = mark {
+            mids.insert(matching_engine.market_id(), mark);
+        }
+    }
+
+    let response = JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: Some(1),
+        result: Some(Envelope::new(Portfolio::new(user, fills, &mids, state.clock.now()))),
+        error: None,
+    };
+    Ok(Json(response))
+}
+
+/// Get user collateral per market handler
+async fn get_user_balances_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    paper: Option<Extension<PaperMode>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<JsonRpcResponse<Balances>>, StatusCode> {
+    let user: solana_sdk::pubkey::Pubkey = user_id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    if let Some(paper) = paper_exchange(&state, paper)? {
+        return Ok(Json(JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(1),
+            result: Some(Envelope::new(paper.balances(user, state.clock.now()).await)),
+            error: None,
+        }));
+    }
+
+    let (market_id, config, open_orders) = {
+        let matching_engine = state.matching_engine.read().await;
+        let open_orders: Vec<Order> = matching_engine
+            .get_open_orders()
+            .await
+            .into_iter()
+            .filter(|order| order.owner == user)
+            .collect();
+        (matching_engine.market_id(), matching_engine.orderbook_config().clone(), open_orders)
+    };
+    let ledger = state
+        .storage
+        .get_ledger_balances(&config.base_mint, &config.quote_mint, Some(&user), None)
+        .await
+        .map_err(|e| {
+            error!("Failed to get ledger balance of {}: {}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let market = MarketBalance::new(market_id, config.base_mint, config.quote_mint, ledger.first(), &open_orders);
+    let response = JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: Some(1),
+        result: Some(Envelope::new(Balances {
+            user,
+            markets: vec![market],
+            timestamp: state.clock.now(),
+        })),
+        error: None,
+    };
+    Ok(Json(response))
+}
+
+/// Query parameters of deposit instructions
+#[derive(Deserialize)]
+struct DepositInstructionsQuery {
+    /// Base or quote mint of the engine's market
+    mint: String,
+    /// Amount in the mint's base units
+    amount: u64,
+    /// Sub-account to credit, defaulting to the wallet itself
+    owner: Option<String>,
+}
+
+/// Accounts and instructions for a wallet to deposit into the engine's
+/// market through the program, so a deposit never needs crediting by hand
+async fn get_user_deposit_instructions_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path(user_id): Path<String>,
+    Query(params): Query<DepositInstructionsQuery>,
+) -> Result<Json<JsonRpcResponse<program::DepositInstructions>>, StatusCode> {
+    let wallet: solana_sdk::pubkey::Pubkey = user_id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let mint: solana_sdk::pubkey::Pubkey = params.mint.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let owner: solana_sdk::pubkey::Pubkey = match &params.owner {
+        Some(owner) => owner.parse().map_err(|_| StatusCode::BAD_REQUEST)?,
+        None => wallet,
+    };
+    if params.amount == 0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let (market_id, config) = {
+        let matching_engine = state.matching_engine.read().await;
+        (matching_engine.market_id(), matching_engine.orderbook_config().clone())
+    };
+    if mint != config.base_mint && mint != config.quote_mint {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    // A sub-account is only credited through the wallet that owns it
+    if owner != wallet {
+        match state.storage.get_sub_account(&owner).await {
+            Ok(Some(sub_account)) if sub_account.wallet == wallet => {}
+            Ok(_) => return Err(StatusCode::BAD_REQUEST),
+            Err(e) => {
+                error!("Failed to get sub-account {}: {}", owner, e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        }
+    }
+
+    Ok(Json(JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: Some(1),
+        result: Some(Envelope::new(program::deposit_instructions(
+            &state.program_id,
+            &market_id.0,
+            &wallet,
+            &owner,
+            &mint,
+            params.amount,
+        ))),
+        error: None,
+    }))
+}
+
+/// List the plain transfers a wallet made into the vaults, newest first,
+/// with how far each is from being credited
+async fn get_user_vault_transfers_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path(user_id): Path<String>,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<VaultTransfer>>>, StatusCode> {
+    check_page(&page)?;
+    let sender: solana_sdk::pubkey::Pubkey = user_id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    match state.storage.get_user_vault_transfers(&sender, &page).await {
+        Ok(transfers) => Ok(Json(JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(1),
+            result: Some(Envelope::new(transfers)),
+            error: None,
+        })),
+        Err(e) => {
+            error!("Failed to get vault transfers of {}: {}", user_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// List a user's compressed fill receipts, newest first
+async fn get_user_receipts_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path(user_id): Path<String>,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<CompressedReceipt>>>, StatusCode> {
+    check_page(&page)?;
+    let owner: solana_sdk::pubkey::Pubkey = user_id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    match state.storage.get_compressed_receipts(&owner, &page).await {
+        Ok(receipts) => Ok(Json(JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(1),
+            result: Some(Envelope::new(receipts)),
+            error: None,
+        })),
+        Err(e) => {
+            error!("Failed to get receipts of {}: {}", user_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// List a user's daily statements, newest first
+async fn get_user_statements_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path(user_id): Path<String>,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<StatementSummary>>>, StatusCode> {
+    check_page(&page)?;
+    let owner: solana_sdk::pubkey::Pubkey = user_id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    match state.storage.get_statements(&owner, &page).await {
+        Ok(statements) => Ok(Json(JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(1),
+            result: Some(Envelope::new(statements)),
+            error: None,
+        })),
+        Err(e) => {
+            error!("Failed to get statements of {}: {}", user_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Query parameters of a statement download
+#[derive(Deserialize)]
+struct StatementQuery {
+    /// `json` (default), `csv` or `pdf`
+    format: Option<StatementFormat>,
+}
+
+/// Download a user's statement of one UTC day, `YYYY-MM-DD`, as an attachment
+async fn get_user_statement_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path((user_id, date)): Path<(String, String)>,
+    Query(query): Query<StatementQuery>,
+) -> Result<Response, StatusCode> {
+    let owner: solana_sdk::pubkey::Pubkey = user_id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let period_start = statement_period_start(&date).ok_or(StatusCode::BAD_REQUEST)?;
+    let format = query.format.unwrap_or(StatementFormat::Json);
+
+    match state.storage.get_statement_artifact(&owner, period_start, format).await {
+        Ok(Some(artifact)) => {
+            let disposition = format!(
+                "attachment; filename=\"{}\"",
+                StatementArtifacts::file_name(&owner, &statement_date(period_start), format)
+            );
+            Ok((
+                [(CONTENT_TYPE, format.content_type().to_string()), (CONTENT_DISPOSITION, disposition)],
+                artifact,
+            )
+                .into_response())
+        }
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to get the statement of {} for {}: {}", user_id, date, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Prove a compressed receipt: its leaf, the sibling hashes up to the root
+/// of every receipt indexed in its tree, and that root. The tree is rebuilt
+/// from its indexed leaves on each call.
+async fn get_receipt_proof_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path((merkle_tree, leaf_index)): Path<(String, u64)>,
+) -> Result<Json<JsonRpcResponse<ReceiptProof>>, StatusCode> {
+    let merkle_tree: solana_sdk::pubkey::Pubkey = merkle_tree.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let (tree, receipt) = match tokio::try_join!(
+        state.storage.get_receipt_tree(&merkle_tree),
+        state.storage.get_compressed_receipt(&merkle_tree, leaf_index),
+    ) {
+        Ok((Some(tree), Some(receipt))) => (tree, receipt),
+        Ok(_) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to get receipt {} of {}: {}", leaf_index, merkle_tree, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let leaves = state.storage.get_receipt_leaves(&merkle_tree).await.map_err(|e| {
+        error!("Failed to get the leaves of {}: {}", merkle_tree, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    // A receipt past a leaf not indexed yet cannot be proven until it is
+    if leaf_index >= leaves.len() as u64 {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    match ReceiptProof::new(receipt, &leaves, tree.max_depth) {
+        Ok(proof) => Ok(Json(JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(1),
+            result: Some(Envelope::new(proof)),
+            error: None,
+        })),
+        Err(e) => {
+            error!("Failed to prove receipt {} of {}: {}", leaf_index, merkle_tree, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Query parameters for fee report endpoints
+#[derive(Deserialize)]
+pub(crate) struct FeeReportQuery {
+    /// `YYYY-MM`, defaulting to the current month
+    month: Option<String>,
+    /// `json` (default) or `csv`
+    format: Option<String>,
+}
+
+impl FeeReportQuery {
+    /// Bounds of the requested month, or of the month containing `now`
+    pub(crate) fn period(&self, now: i64) -> Result<(i64, i64), StatusCode> {
+        match &self.month {
+            Some(month) => svm_clob_fees::parse_month(month).ok_or(StatusCode::BAD_REQUEST),
+            None => Ok(svm_clob_fees::month_bounds(now)),
+        }
+    }
+}
+
+/// Fee reports as a JSON-RPC response or a CSV attachment
+pub(crate) fn fee_report_response(reports: Vec<FeeReport>, format: Option<&str>) -> Response {
+    if format == Some("csv") {
+        return ([(CONTENT_TYPE, "text/csv")], svm_clob_fees::reports_to_csv(&reports)).into_response();
+    }
+    Json(JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: Some(1),
+        result: Some(Envelope::new(reports)),
+        error: None,
+    })
+    .into_response()
+}
+
+/// Query parameters of incentive epoch reports
+#[derive(Deserialize)]
+struct IncentiveExportQuery {
+    /// `json` (default) for a page of reports, or `csv` for every report of the epoch
+    format: Option<String>,
+}
+
+/// List closed incentive epochs, newest first
+async fn get_incentive_epochs_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<IncentiveEpoch>>>, StatusCode> {
+    check_page(&page)?;
+    match state.storage.get_incentive_epochs(&page).await {
+        Ok(epochs) => {
             let response = JsonRpcResponse {
                 jsonrpc: "2.0".to_string(),
                 id: Some(1),
-                result: Some(stats),
+                result: Some(Envelope::new(epochs)),
                 error: None,
             };
             Ok(Json(response))
         }
         Err(e) => {
-            error!("Failed to get market stats: {}", e);
+            error!("Failed to get incentive epochs: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
 }
 
-/// Get user orders handler
-async fn get_user_orders_handler<S: Storage>(
+/// Get the maker reports of one epoch, largest reward first; `format=csv`
+/// exports the whole epoch for distribution
+async fn get_incentive_epoch_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path(epoch_start): Path<i64>,
+    Query(page): Query<Paginated>,
+    Query(export): Query<IncentiveExportQuery>,
+) -> Result<Response, StatusCode> {
+    check_page(&page)?;
+    let query = IncentiveQuery {
+        epoch_start: Some(epoch_start),
+        maker: None,
+    };
+    if export.format.as_deref() != Some("csv") {
+        return incentive_reports(&state, &query, &page).await.map(IntoResponse::into_response);
+    }
+
+    let mut reports = Vec::new();
+    let mut next = Some(Paginated::first(MAX_PAGE_LIMIT));
+    while let Some(page) = next {
+        let fetched = state.storage.get_incentive_reports(&query, &page).await.map_err(|e| {
+            error!("Failed to export incentive epoch {}: {}", epoch_start, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        next = page.next(&fetched);
+        reports.extend(fetched.items);
+    }
+    Ok(([(CONTENT_TYPE, "text/csv")], svm_clob_incentives::reports_to_csv(&reports)).into_response())
+}
+
+/// Get a maker's incentive reports, newest epoch first
+async fn get_user_incentives_handler<S: Storage>(
     State(state): State<Arc<RpcServerState<S>>>,
     Path(user_id): Path<String>,
-) -> Result<Json<JsonRpcResponse<Vec<Order>>>, StatusCode> {
-    match state.storage.get_user_orders(&user_id).await {
-        Ok(orders) => {
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<IncentiveReport>>>, StatusCode> {
+    let user: solana_sdk::pubkey::Pubkey = user_id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    check_page(&page)?;
+    let query = IncentiveQuery {
+        epoch_start: None,
+        maker: Some(user),
+    };
+    incentive_reports(&state, &query, &page).await
+}
+
+async fn incentive_reports<S: Storage>(
+    state: &RpcServerState<S>,
+    query: &IncentiveQuery,
+    page: &Paginated,
+) -> Result<Json<JsonRpcResponse<Page<IncentiveReport>>>, StatusCode> {
+    match state.storage.get_incentive_reports(query, page).await {
+        Ok(reports) => {
             let response = JsonRpcResponse {
                 jsonrpc: "2.0".to_string(),
                 id: Some(1),
-                result: Some(orders),
+                result: Some(Envelope::new(reports)),
                 error: None,
             };
             Ok(Json(response))
         }
         Err(e) => {
-            error!("Failed to get user orders: {}", e);
+            error!("Failed to get incentive reports: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Query parameters of insurance fund events
+#[derive(Deserialize)]
+struct InsuranceFundEventsQuery {
+    /// Only `funded` or `drawn` events
+    kind: Option<InsuranceFundEventKind>,
+}
+
+/// Get the market's insurance fund: fee contributions accrued for it and
+/// its balance on chain
+async fn get_insurance_fund_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+) -> Result<Json<JsonRpcResponse<InsuranceFund>>, StatusCode> {
+    let orderbook = state.matching_engine.read().await.market_id().0;
+    match state.storage.get_insurance_fund(&orderbook).await {
+        Ok(fund) => {
+            let response = JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: Some(1),
+                result: Some(Envelope::new(fund)),
+                error: None,
+            };
+            Ok(Json(response))
+        }
+        Err(e) => {
+            error!("Failed to get the insurance fund: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// List payments into and out of the market's insurance fund, newest first
+async fn get_insurance_fund_events_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Query(page): Query<Paginated>,
+    Query(query): Query<InsuranceFundEventsQuery>,
+) -> Result<Json<JsonRpcResponse<Page<InsuranceFundEvent>>>, StatusCode> {
+    check_page(&page)?;
+    let orderbook = state.matching_engine.read().await.market_id().0;
+    match state.storage.get_insurance_fund_events(&orderbook, query.kind, &page).await {
+        Ok(events) => {
+            let response = JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: Some(1),
+                result: Some(Envelope::new(events)),
+                error: None,
+            };
+            Ok(Json(response))
+        }
+        Err(e) => {
+            error!("Failed to get insurance fund events: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get the journal entries and on-chain accounts of an order
+async fn get_order_sequence_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path(order_id): Path<u64>,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<SequenceMapping>>>, StatusCode> {
+    sequence_mappings(&state, SequenceLookup::Order(order_id), page).await
+}
+
+/// Get the orders a journal entry placed, replaced or filled
+async fn get_journal_sequence_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path(sequence): Path<u64>,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<SequenceMapping>>>, StatusCode> {
+    sequence_mappings(&state, SequenceLookup::JournalSequence(sequence), page).await
+}
+
+/// Get the fills a settlement transaction settled
+async fn get_signature_sequence_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path(signature): Path<String>,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<SequenceMapping>>>, StatusCode> {
+    sequence_mappings(&state, SequenceLookup::Signature(signature), page).await
+}
+
+/// Get the orders settled against a user account PDA
+async fn get_account_sequence_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path(user_account): Path<String>,
+    Query(page): Query<Paginated>,
+) -> Result<Json<JsonRpcResponse<Page<SequenceMapping>>>, StatusCode> {
+    let user_account = user_account.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    sequence_mappings(&state, SequenceLookup::UserAccount(user_account), page).await
+}
+
+async fn sequence_mappings<S: Storage>(
+    state: &RpcServerState<S>,
+    lookup: SequenceLookup,
+    page: Paginated,
+) -> Result<Json<JsonRpcResponse<Page<SequenceMapping>>>, StatusCode> {
+    check_page(&page)?;
+    match state.storage.get_sequence_mappings(&lookup, &page).await {
+        Ok(mappings) => {
+            let response = JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: Some(1),
+                result: Some(Envelope::new(mappings)),
+                error: None,
+            };
+            Ok(Json(response))
+        }
+        Err(e) => {
+            error!("Failed to get sequence mappings for {:?}: {}", lookup, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get the commitment of a program transaction the indexer stored records of
+async fn get_chain_transaction_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    Path(signature): Path<String>,
+) -> Result<Json<JsonRpcResponse<ChainTransaction>>, StatusCode> {
+    match state.storage.get_chain_transaction(&signature).await {
+        Ok(Some(transaction)) => {
+            let response = JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: Some(1),
+                result: Some(Envelope::new(transaction)),
+                error: None,
+            };
+            Ok(Json(response))
+        }
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to get chain transaction {}: {}", signature, e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
 }
 
 /// Health check handler
-async fn health_check_handler<S: Storage>() -> Json<serde_json::Value> {
+async fn health_check_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+) -> Json<serde_json::Value> {
+    let matching_engine = state.matching_engine.read().await;
+    // Features serving from their fallback; the server still takes requests
+    let degraded: Vec<&str> = [state.rate_limiter.degraded(), state.nonces.degraded()]
+        .into_iter()
+        .flatten()
+        .collect();
     Json(serde_json::json!({
-        "status": "healthy",
-        "timestamp": chrono::Utc::now().timestamp(),
-        "service": "svm-clob-rpc-server"
+        "status": if degraded.is_empty() { "healthy" } else { "degraded" },
+        "timestamp": state.clock.now(),
+        "service": "svm-clob-rpc-server",
+        "active": matching_engine.is_active(),
+        "journal_position": matching_engine.journal_position(),
+        "degraded": degraded
     }))
 }
 
-/// Query parameters for trades endpoint
+/// Reject a page request whose cursor was not handed out by this API
+pub(crate) fn check_page(page: &Paginated) -> Result<(), StatusCode> {
+    page.offset().map(|_| ()).map_err(|_| StatusCode::BAD_REQUEST)
+}
+
+/// Query parameters for orderbook endpoints
 #[derive(Deserialize)]
-struct TradeQuery {
+struct OrderBookQuery {
+    /// Journal sequence to serve the book as of; the live book when absent
+    sequence: Option<u64>,
+}
+
+/// Most books one history query returns
+const MAX_HISTORY_SAMPLES: i64 = 500;
+
+/// Seconds between books of a history query that names no granularity
+const DEFAULT_HISTORY_GRANULARITY: i64 = 60;
+
+/// Query parameters for the orderbook history endpoint
+#[derive(Deserialize)]
+struct OrderBookHistoryQuery {
+    /// Time of the first book (unix seconds)
+    from: i64,
+    /// Latest time to sample (unix seconds), defaulting to and capped at now
+    to: Option<i64>,
+    /// Seconds between books
+    granularity: Option<i64>,
+}
+
+impl OrderBookHistoryQuery {
+    /// Bounds and step of the requested samples, refusing empty ranges and
+    /// ranges of more than `MAX_HISTORY_SAMPLES` books
+    fn range(&self, now: i64) -> Result<(i64, i64, i64), StatusCode> {
+        let to = self.to.unwrap_or(now).min(now);
+        let granularity = self.granularity.unwrap_or(DEFAULT_HISTORY_GRANULARITY);
+        if granularity <= 0 || self.from > to || (to - self.from) / granularity >= MAX_HISTORY_SAMPLES {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        Ok((self.from, to, granularity))
+    }
+}
+
+/// Query parameters for depth endpoint
+#[derive(Deserialize)]
+struct DepthQuery {
+    levels: Option<u32>,
+}
+
+/// Query parameters for candles endpoint
+#[derive(Deserialize)]
+struct CandleQuery {
+    interval: Option<String>,
     limit: Option<u32>,
 }
 
-use uuid::Uuid;
+/// ID for a new order from the engine's allocator, or the sandbox's for a
+/// paper key
+async fn allocate_order_id<S: Storage>(
+    matching_engine: &MatchingEngine<S>,
+    paper: Option<&PaperExchange>,
+) -> ClobResult<u64> {
+    match paper {
+        Some(paper) => paper.allocate_order_id().await,
+        None => matching_engine.allocate_order_id().await,
+    }
+}
+
+/// Count a placement against its owner's rate limit and, when made with a
+/// tenant's key, the tenant's
+async fn check_order_rate<S: Storage>(state: &RpcServerState<S>, tenant: Option<&Tenant>, owner: &str) -> ClobResult<()> {
+    let now = state.clock.now_micros();
+    if let Some(tenant) = tenant {
+        state.rate_limiter.check(RateKey::TenantOrders(tenant), now).await?;
+    }
+    state.rate_limiter.check_order(owner, now).await
+}
 
-/// Generate unique order ID
-async fn generate_order_id() -> u64 {
-    // Using the current timestamp and a random number to ensure uniqueness
-    let timestamp = chrono::Utc::now().timestamp_millis() as u64;
-    let uuid_hash = Uuid::new_v4().as_u128() as u64;
-    timestamp.wrapping_add(uuid_hash)
+/// Refuse a placement of a suspended owner, or made with a suspended key
+async fn check_suspension<S: Storage>(state: &RpcServerState<S>, owner: &str, api_key: Option<&ApiKeyId>) -> ClobResult<()> {
+    match owner.parse::<solana_sdk::pubkey::Pubkey>() {
+        Ok(owner) => state.suspensions.check(state.storage.as_ref(), &owner, api_key).await,
+        // Refused as a bad request when the order is built
+        Err(_) => Ok(()),
+    }
+}
+
+/// Verify the owner's signature on a placement, or its wallet's for a
+/// sub-account, and claim its nonce; unsigned placements pass unless the
+/// server requires signatures. `engine_market` stands in for a request
+/// without a market.
+async fn authorize_order<S: Storage>(
+    state: &RpcServerState<S>,
+    request: &PlaceOrderRequest,
+    engine_market: MarketId,
+) -> ClobResult<()> {
+    let market = request.market_id.unwrap_or(engine_market);
+    if let Some(group) = request.prevention_group_id {
+        // A group only counts for accounts the market's authority put in it,
+        // or anyone could cancel another firm's quotes by naming its group
+        let owner = request
+            .owner
+            .parse::<solana_sdk::pubkey::Pubkey>()
+            .map_err(|_| ClobError::Unauthorized)?;
+        if state.storage.get_prevention_group(&market, &owner).await? != Some(group) {
+            return Err(ClobError::Unauthorized);
+        }
+    }
+    match signing::signed_request(request, &market)? {
+        Some(signed) => {
+            let now = state.clock.now();
+            let owner = solana_sdk::pubkey::Pubkey::new_from_array(signed.order_intent.owner);
+            let signer = match state.storage.get_sub_account(&owner).await? {
+                Some(sub_account) => sub_account.wallet,
+                None => owner,
+            };
+            signing::verify_signed_order(&signed, &signer, now)?;
+            state.nonces.claim(state.storage.as_ref(), &signed, now).await
+        }
+        None if state.require_signed_orders => Err(ClobError::Unauthorized),
+        None => Ok(()),
+    }
+}
+
+/// Build a new order from a placement request, `None` if the owner is not a valid pubkey
+fn order_from_request(request: PlaceOrderRequest, order_id: u64, now: i64) -> Option<Order> {
+    let owner = request.owner.parse::<solana_sdk::pubkey::Pubkey>().ok()?;
+    
+    Some(Order {
+        order_id,
+        market_id: request.market_id.unwrap_or_default(),
+        owner,
+        price: request.price,
+        quantity: request.quantity,
+        remaining_quantity: request.quantity,
+        timestamp: now,
+        client_order_id: request.client_order_id,
+        expiry_timestamp: request.expiry_timestamp.unwrap_or(0),
+        side: request.side,
+        order_type: request.order_type,
+        status: OrderStatus::Open,
+        self_trade_behavior: request.self_trade_behavior,
+        prevention_group_id: request.prevention_group_id,
+        time_in_force: request.time_in_force,
+        max_slippage_bps: request.max_slippage_bps,
+        filled_notional: Notional::default(),
+        avg_fill_price: None,
+    })
+}
+
+/// Routes of the server over `state`, with every layer `start_server` serves
+/// them with; call them in process, e.g. with `tower::ServiceExt::oneshot`
+pub fn app<S: Storage + 'static>(state: Arc<RpcServerState<S>>) -> Router {
+    // The audit, rate limit and tenant layers need the state up front, so
+    // they are added here rather than in `create_router`; a call is given its
+    // request ID before anything else, so refusals carry one too, then its
+    // tenant is resolved, and requests over the per-IP or tenant limit are
+    // refused before they are audited. Replays of idempotent commands are
+    // audited and rate limited like the call they repeat
+    create_router()
+        .route_layer(middleware::from_fn_with_state(state.clone(), idempotency::replay_idempotent))
+        .route_layer(middleware::from_fn_with_state(state.clone(), audit::audit_request))
+        .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit::limit_requests))
+        .route_layer(middleware::from_fn_with_state(state.clone(), tenant::resolve_tenant))
+        .route_layer(middleware::from_fn(assign_request_id))
+        .with_state(state)
 }
 
 /// Start the RPC server
 pub async fn start_server<S: Storage + 'static>(
     state: Arc<RpcServerState<S>>,
-    port: u16,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let app = create_router().with_state(state);
-    
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port))
-        .await?;
+    options: ListenOptions,
+) -> InfraResult<()> {
+    let app = app(state.clone());
+    tokio::spawn(tenant::flush_usage(state));
     
-    info!("RPC server starting on port {}", port);
-    axum::serve(listener, app).await?;
+    info!("RPC server starting on {}", options.describe());
+    svm_clob_serve::serve(app, &options).await.map_err(|source| InfraError::Bind {
+        address: options.describe(),
+        source,
+    })?;
     
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,169 @@
+/// Replay protection of signed orders
+///
+/// A signed order is accepted once. After its signature verifies, its owner
+/// and nonce are claimed until the signature expires, and a later placement
+/// carrying the same pair is refused with `ClobError::NonceReused`, whether it
+/// repeats a captured payload or signs a new order under the used nonce. So
+/// that claims stay bounded, a signature may run at most `max_ttl_secs` past
+/// the time it is received; one expiring later is refused, like one already
+/// expired. Clients should give each signed order a fresh nonce, e.g. a
+/// counter or the current time in microseconds.
+///
+/// A single server keeps claims in process. Replicas behind one load balancer
+/// share them in Redis instead, so a payload refused by one replica is not
+/// accepted by another. On chain, `settle_match` tracks each signed order's
+/// fills under the same owner and nonce, so a captured signature cannot be
+/// settled beyond its quantity either. A server attaching to a market that
+/// already settled signed orders reserves their nonces from those records
+/// (`reserve`), so its fresh claims do not accept them again.
+///
+/// Replay protection does not lapse when Redis does. Redis is reached through
+/// a circuit breaker, and while it is unavailable claims are made in storage
+/// instead, which every replica shares too. Those claims stay binding once
+/// Redis is back, so a shared store also checks storage for a nonce it just
+/// claimed in Redis, at the cost of one indexed read per signed order.
+
+use crate::breaker::RedisLink;
+use svm_clob_storage::Storage;
+use svm_clob_types::{ClobError, ClobResult, SignedOrder};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Longest signature lifetime accepted by default: one day
+pub const DEFAULT_MAX_SIGNATURE_TTL_SECS: i64 = 86_400;
+
+/// Local claims kept before expired ones are dropped
+const MAX_LOCAL_CLAIMS: usize = 1_000_000;
+
+enum Backend {
+    /// Expiry of each claimed owner and nonce
+    Local { claims: Mutex<HashMap<(Pubkey, u64), i64>> },
+    /// Claims under `prefix` in Redis, each living until its signature
+    /// expires, and in storage while Redis is unavailable
+    Redis { link: RedisLink, prefix: String },
+}
+
+/// Owners' used nonces
+pub struct NonceStore {
+    backend: Backend,
+    /// Longest time a signature may run past the time it is received
+    max_ttl_secs: i64,
+}
+
+impl NonceStore {
+    /// Store keeping claims in this process
+    pub fn new(max_ttl_secs: i64) -> Self {
+        Self {
+            backend: Backend::Local {
+                claims: Mutex::new(HashMap::new()),
+            },
+            max_ttl_secs,
+        }
+    }
+
+    /// Store shared through Redis by every server using the same `prefix`;
+    /// Redis is connected on first use
+    pub async fn redis(url: &str, prefix: &str, max_ttl_secs: i64) -> ClobResult<Self> {
+        Ok(Self {
+            backend: Backend::Redis {
+                link: RedisLink::new(url, "nonces")?,
+                prefix: prefix.to_string(),
+            },
+            max_ttl_secs,
+        })
+    }
+
+    /// The feature, `nonces`, when it runs on its fallback because Redis is
+    /// unavailable
+    pub fn degraded(&self) -> Option<&'static str> {
+        match &self.backend {
+            Backend::Redis { link, .. } if link.is_degraded() => Some(link.feature()),
+            _ => None,
+        }
+    }
+
+    /// Claim a verified signed order's nonce at `now` (unix seconds),
+    /// refusing an expired or too long-lived signature and a used nonce
+    pub async fn claim<S: Storage + ?Sized>(&self, storage: &S, signed: &SignedOrder, now: i64) -> ClobResult<()> {
+        if signed.is_expired(now) {
+            return Err(ClobError::SignatureExpired);
+        }
+        if signed.expiry.saturating_sub(now) > self.max_ttl_secs {
+            return Err(ClobError::InvalidSignature(format!(
+                "Expiry is more than {} seconds away",
+                self.max_ttl_secs
+            )));
+        }
+
+        let owner = Pubkey::new_from_array(signed.order_intent.owner);
+        if self.try_claim(storage, owner, signed.nonce, signed.expiry, now).await? {
+            Ok(())
+        } else {
+            Err(ClobError::NonceReused(format!("nonce {} of {}", signed.nonce, owner)))
+        }
+    }
+
+    /// Mark `owner`'s `nonce` used until `expiry`, as for an order settled
+    /// before this store existed; a nonce already claimed or expired by
+    /// `now` is left as it is
+    pub async fn reserve<S: Storage + ?Sized>(
+        &self,
+        storage: &S,
+        owner: Pubkey,
+        nonce: u64,
+        expiry: i64,
+        now: i64,
+    ) -> ClobResult<()> {
+        if expiry >= now {
+            self.try_claim(storage, owner, nonce, expiry, now).await?;
+        }
+        Ok(())
+    }
+
+    /// Claim `owner`'s `nonce` until `expiry`; false when it is claimed already
+    async fn try_claim<S: Storage + ?Sized>(
+        &self,
+        storage: &S,
+        owner: Pubkey,
+        nonce: u64,
+        expiry: i64,
+        now: i64,
+    ) -> ClobResult<bool> {
+        match &self.backend {
+            Backend::Local { claims } => {
+                let mut claims = claims.lock().expect("nonce claims lock poisoned");
+                if claims.len() >= MAX_LOCAL_CLAIMS {
+                    claims.retain(|_, expiry| *expiry >= now);
+                }
+                match claims.get(&(owner, nonce)) {
+                    Some(&claimed_until) if claimed_until >= now => Ok(false),
+                    _ => {
+                        claims.insert((owner, nonce), expiry);
+                        Ok(true)
+                    }
+                }
+            }
+            Backend::Redis { link, prefix } => {
+                let key = format!("{}:{}:{}", prefix, owner, nonce);
+                let claimed: Option<Option<String>> = link
+                    .run(|mut connection| async move {
+                        redis::cmd("SET")
+                            .arg(key)
+                            .arg(expiry)
+                            .arg("NX")
+                            .arg("EX")
+                            .arg(expiry - now + 1)
+                            .query_async(&mut connection)
+                            .await
+                    })
+                    .await;
+                match claimed {
+                    Some(Some(_)) => Ok(!storage.signed_nonce_claimed(&owner, nonce, now).await?),
+                    Some(None) => Ok(false),
+                    None => storage.claim_signed_nonce(&owner, nonce, expiry, now).await,
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,351 @@
+/// Paper trading
+///
+/// Calls made with an API key issued with `paper` set trade in a sandbox
+/// rather than the market. Their orders go to a second engine, kept in memory,
+/// whose book mirrors the live one: before each paper placement or
+/// modification the sandbox copies in the live orders placed since the last
+/// one and takes out those filled, cancelled or expired since, so paper orders
+/// fill against real resting liquidity, and against each other, without
+/// touching it. Liquidity a paper order takes stays taken in the sandbox
+/// until its live order fills further or leaves the book. A live order copied
+/// in at a resting paper order's price fills it, as the live order would have.
+///
+/// Each owner trading on paper starts from a virtual balance of both mints
+/// that its fills move, and an order its free balance does not cover is
+/// refused with `InsufficientBalance`. Paper orders never reach the live book,
+/// storage, journal or chain, are not published over WebSocket and pay no
+/// fees. The sandbox starts empty with the server, so paper orders and
+/// balances do not survive a restart, and each replica has its own.
+
+use svm_clob_matching_engine::{MatchingEngine, PreTradeContext, PreTradeHook};
+use svm_clob_storage::{InMemoryStorage, Storage};
+use svm_clob_types::{
+    Balances, ClobError, ClobResult, LedgerBalance, MarketBalance, MarketId, MarketStatus, Order, OrderSide,
+    OrderStatus, OrderType, Price, Quantity, QueuePosition, TimeInForce, TradeExecution,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// Order IDs of the sandbox start here to stay clear of live order IDs, which
+/// copies of live orders keep
+const PAPER_ORDER_ID_BASE: u64 = 1 << 62;
+
+/// Virtual balance a paper owner starts with by default, in native units
+pub const DEFAULT_PAPER_BALANCE: u64 = 1_000_000_000_000;
+
+/// Marks a call made with a paper trading key, set by `resolve_tenant`
+#[derive(Debug, Clone, Copy)]
+pub struct PaperMode;
+
+/// Virtual balances of paper owners, and the live orders copied into the sandbox
+struct PaperAccounts {
+    market_id: MarketId,
+    base_mint: Pubkey,
+    quote_mint: Pubkey,
+    /// Base and quote balance of an owner's first paper order
+    starting_balance: (u64, u64),
+    ledgers: Mutex<HashMap<Pubkey, LedgerBalance>>,
+    /// Remaining quantity of each copied live order when it was last copied
+    mirrored: Mutex<HashMap<u64, u64>>,
+}
+
+impl PaperAccounts {
+    fn is_mirrored(&self, order_id: u64) -> bool {
+        self.mirrored.lock().expect("paper mirror lock poisoned").contains_key(&order_id)
+    }
+
+    fn ledger(&self, owner: &Pubkey) -> LedgerBalance {
+        self.ledgers
+            .lock()
+            .expect("paper ledger lock poisoned")
+            .get(owner)
+            .cloned()
+            .unwrap_or_else(|| self.opening_ledger(owner))
+    }
+
+    fn opening_ledger(&self, owner: &Pubkey) -> LedgerBalance {
+        let (base, quote) = self.starting_balance;
+        LedgerBalance {
+            user: *owner,
+            base_balance: base.min(i64::MAX as u64) as i64,
+            quote_balance: quote.min(i64::MAX as u64) as i64,
+            total_volume_traded: 0,
+            fees: 0,
+            base_held: 0,
+            quote_held: 0,
+        }
+    }
+
+    /// Balance of `owner` with its resting paper orders holding their share
+    fn balance(&self, owner: &Pubkey, open_orders: &[Order]) -> MarketBalance {
+        let ledger = self.ledger(owner);
+        MarketBalance::new(self.market_id, self.base_mint, self.quote_mint, Some(&ledger), open_orders)
+    }
+
+    /// Move `owner`'s balance by a fill on `side`
+    fn fill(&self, owner: &Pubkey, side: OrderSide, trade: &TradeExecution) {
+        let quantity = trade.quantity.min(i64::MAX as u64) as i64;
+        let notional = trade.notional().0.min(i64::MAX as u128) as i64;
+        let mut ledgers = self.ledgers.lock().expect("paper ledger lock poisoned");
+        let ledger = ledgers.entry(*owner).or_insert_with(|| self.opening_ledger(owner));
+        let (base, quote) = match side {
+            OrderSide::Bid => (quantity, -notional),
+            OrderSide::Ask => (-quantity, notional),
+        };
+        ledger.base_balance = ledger.base_balance.saturating_add(base);
+        ledger.quote_balance = ledger.quote_balance.saturating_add(quote);
+        ledger.total_volume_traded = ledger.total_volume_traded.saturating_add(trade.quantity);
+    }
+}
+
+impl PreTradeHook for PaperAccounts {
+    fn check(&self, order: &Order, context: &PreTradeContext<'_>) -> ClobResult<()> {
+        if self.is_mirrored(order.order_id) {
+            return Ok(());
+        }
+        let balance = self.balance(&order.owner, context.open_orders);
+        let covered = match order.side {
+            OrderSide::Ask => balance.base_free >= order.remaining_quantity.min(i64::MAX as u64) as i64,
+            OrderSide::Bid => {
+                // Market orders carry no price; value them at the far touch
+                let price = match order.order_type {
+                    OrderType::Market => context.best_ask.unwrap_or(0),
+                    _ => order.price,
+                };
+                let notional = Price(price).notional(Quantity(order.remaining_quantity)).0;
+                balance.quote_free as i128 >= notional.min(i128::MAX as u128) as i128
+            }
+        };
+        if covered {
+            Ok(())
+        } else {
+            Err(ClobError::InsufficientBalance)
+        }
+    }
+}
+
+/// Sandbox engine and virtual balances of paper trading keys
+pub struct PaperExchange {
+    engine: MatchingEngine<InMemoryStorage>,
+    storage: Arc<InMemoryStorage>,
+    accounts: Arc<PaperAccounts>,
+    /// Held across a sync and the command after it, so each live order is
+    /// copied once and fills are applied in order
+    sequencer: tokio::sync::Mutex<()>,
+}
+
+impl PaperExchange {
+    /// Sandbox of `live`'s market, giving each paper owner `starting_balance`
+    /// of base and quote
+    pub fn new<S: Storage>(live: &MatchingEngine<S>, starting_balance: (u64, u64)) -> Self {
+        let config = live.orderbook_config().clone();
+        let accounts = Arc::new(PaperAccounts {
+            market_id: live.market_id(),
+            base_mint: config.base_mint,
+            quote_mint: config.quote_mint,
+            starting_balance,
+            ledgers: Mutex::new(HashMap::new()),
+            mirrored: Mutex::new(HashMap::new()),
+        });
+        let storage = Arc::new(InMemoryStorage::new());
+        let mut engine = MatchingEngine::new(storage.clone(), config);
+        engine.set_market_id(live.market_id());
+        engine.set_clock(live.clock().clone());
+        engine.set_allocation(live.allocation());
+        engine.add_pre_trade_hook(accounts.clone());
+        engine.resume_order_ids(PAPER_ORDER_ID_BASE);
+        Self {
+            engine,
+            storage,
+            accounts,
+            sequencer: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Storage of the sandbox, holding paper orders and fills
+    pub fn storage(&self) -> &InMemoryStorage {
+        &self.storage
+    }
+
+    /// ID for a new paper order
+    pub async fn allocate_order_id(&self) -> ClobResult<u64> {
+        self.engine.allocate_order_id().await
+    }
+
+    /// Place a paper order against the sandbox, synced with `live` first
+    pub async fn place_order<S: Storage>(&self, live: &MatchingEngine<S>, order: Order) -> ClobResult<Vec<TradeExecution>> {
+        // The sandbox engine accepts every type, for the copies of live
+        // orders; paper orders follow the live market's whitelist
+        live.check_order_type(&order)?;
+        let _sequence = self.sequencer.lock().await;
+        self.sync(live).await?;
+        let trades = self.engine.place_order(order.clone()).await?;
+        self.record_fills(&order, &trades).await?;
+        Ok(trades)
+    }
+
+    /// Cancel a resting paper order
+    pub async fn cancel_order(&self, order_id: u64) -> ClobResult<Order> {
+        let _sequence = self.sequencer.lock().await;
+        if self.accounts.is_mirrored(order_id) {
+            return Err(ClobError::OrderNotFound);
+        }
+        self.engine.cancel_order(order_id).await
+    }
+
+    /// Modify a resting paper order, with the sandbox synced with `live` first
+    pub async fn modify_order<S: Storage>(
+        &self,
+        live: &MatchingEngine<S>,
+        order_id: u64,
+        new_price: Option<u64>,
+        new_quantity: Option<u64>,
+    ) -> ClobResult<Order> {
+        let _sequence = self.sequencer.lock().await;
+        if self.accounts.is_mirrored(order_id) {
+            return Err(ClobError::OrderNotFound);
+        }
+        self.sync(live).await?;
+        self.engine.modify_order(order_id, new_price, new_quantity).await
+    }
+
+    /// Place of a resting paper order in its level's queue, behind the live
+    /// orders copied into the sandbox as well as paper ones
+    pub async fn queue_position<S: Storage>(&self, live: &MatchingEngine<S>, order_id: u64) -> ClobResult<QueuePosition> {
+        let _sequence = self.sequencer.lock().await;
+        if self.accounts.is_mirrored(order_id) {
+            return Err(ClobError::OrderNotFound);
+        }
+        self.sync(live).await?;
+        self.engine.queue_position(order_id).await
+    }
+
+    /// Virtual balance of `user`, with its resting paper orders holding their share
+    pub async fn balances(&self, user: Pubkey, now: i64) -> Balances {
+        let open_orders: Vec<Order> = self
+            .engine
+            .get_open_orders()
+            .await
+            .into_iter()
+            .filter(|order| order.owner == user && !self.accounts.is_mirrored(order.order_id))
+            .collect();
+        Balances {
+            user,
+            markets: vec![self.accounts.balance(&user, &open_orders)],
+            timestamp: now,
+        }
+    }
+
+    /// Bring the sandbox book in line with `live`: take out or shrink the
+    /// copies of live orders that left the book or filled, copy in the live
+    /// orders that arrived, oldest first, then take the market's status
+    async fn sync<S: Storage>(&self, live: &MatchingEngine<S>) -> ClobResult<()> {
+        // Copies move whatever the market's status; paper commands follow it
+        if self.engine.status() != MarketStatus::Active {
+            self.engine.set_status(MarketStatus::Active).await?;
+        }
+
+        let mut live_orders: HashMap<u64, Order> =
+            live.get_open_orders().await.into_iter().map(|order| (order.order_id, order)).collect();
+        let mirrored: Vec<(u64, u64)> = self
+            .accounts
+            .mirrored
+            .lock()
+            .expect("paper mirror lock poisoned")
+            .iter()
+            .map(|(&order_id, &remaining)| (order_id, remaining))
+            .collect();
+
+        for (order_id, copied_remaining) in mirrored {
+            match live_orders.remove(&order_id) {
+                None => {
+                    self.remove_copy(order_id).await?;
+                    self.accounts.mirrored.lock().expect("paper mirror lock poisoned").remove(&order_id);
+                }
+                // The live order filled since it was copied: take the same
+                // quantity off what paper orders left of the copy
+                Some(order) if order.remaining_quantity < copied_remaining => {
+                    let left = match self.remove_copy(order_id).await? {
+                        Some(copy) => copy.remaining_quantity.saturating_sub(copied_remaining - order.remaining_quantity),
+                        None => 0,
+                    };
+                    self.accounts
+                        .mirrored
+                        .lock()
+                        .expect("paper mirror lock poisoned")
+                        .insert(order_id, order.remaining_quantity);
+                    if left > 0 {
+                        self.copy_in(&order, left).await?;
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+
+        let mut arrived: Vec<Order> = live_orders.into_values().collect();
+        arrived.sort_by_key(|order| (order.timestamp, order.order_id));
+        for order in arrived {
+            self.accounts
+                .mirrored
+                .lock()
+                .expect("paper mirror lock poisoned")
+                .insert(order.order_id, order.remaining_quantity);
+            self.copy_in(&order, order.remaining_quantity).await?;
+        }
+
+        if live.status() != MarketStatus::Active {
+            self.engine.set_status(live.status()).await?;
+        }
+        Ok(())
+    }
+
+    /// Take a copy off the sandbox book, returning it if it was still resting
+    async fn remove_copy(&self, order_id: u64) -> ClobResult<Option<Order>> {
+        match self.engine.cancel_order(order_id).await {
+            Ok(copy) => Ok(Some(copy)),
+            Err(ClobError::OrderNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Place a resting copy of a live order with `remaining` left, filling
+    /// any paper orders it crosses
+    async fn copy_in(&self, live: &Order, remaining: u64) -> ClobResult<()> {
+        let copy = Order {
+            remaining_quantity: remaining,
+            status: OrderStatus::Open,
+            order_type: OrderType::Limit,
+            time_in_force: TimeInForce::GoodTillCancelled,
+            expiry_timestamp: 0,
+            client_order_id: 0,
+            max_slippage_bps: None,
+            ..live.clone()
+        };
+        match self.engine.place_order(copy.clone()).await {
+            Ok(trades) => self.record_fills(&copy, &trades).await,
+            Err(e) => {
+                warn!("Failed to copy live order {} into the paper sandbox: {}", live.order_id, e);
+                Ok(())
+            }
+        }
+    }
+
+    /// Move the balances of the paper owners on either side of `trades`
+    async fn record_fills(&self, taker: &Order, trades: &[TradeExecution]) -> ClobResult<()> {
+        let taker_is_paper = !self.accounts.is_mirrored(taker.order_id);
+        for trade in trades {
+            if taker_is_paper {
+                self.accounts.fill(&taker.owner, taker.side, trade);
+            }
+            if self.accounts.is_mirrored(trade.maker_order_id) {
+                continue;
+            }
+            if let Some(maker) = self.storage.get_order(trade.maker_order_id).await? {
+                self.accounts.fill(&maker.owner, trade.maker_side, trade);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,470 @@
+/// Rate limiting
+///
+/// Caps how many orders each owner may place per second, and how many
+/// requests each client IP may make per second across the API. Calls made
+/// with a tenant's API key also count against the tenant's own limits, and
+/// its request limit replaces the per-IP one. All are
+/// enforced with GCRA: a key may spend its whole second's allowance in one
+/// burst, after which calls are admitted at the limit's even pace. Calls over
+/// a limit are rejected with `ClobError::RateLimited` before they reach the
+/// engine. The rejection carries the caller's standing against the limit:
+/// calls used, and how long until one is admitted again and until the whole
+/// allowance has refilled. HTTP responses repeat it in `Retry-After` and
+/// `X-RateLimit-*` headers.
+///
+/// A single server keeps its counters in process. Replicas behind one load
+/// balancer share them in Redis instead, so a limit holds across the
+/// deployment rather than per replica: the limits themselves are stored there
+/// too, seeded from the first replica's `[rpc_server]` configuration, and a
+/// change made through the admin API of any replica applies to all of them.
+/// Replicas time calls by their own clocks, which should be kept in sync.
+///
+/// A shared limiter does not fail calls when Redis does. Redis is reached
+/// through a circuit breaker, and while it is unavailable each replica counts
+/// calls in process against the limits it last read, so a limit then holds
+/// per replica rather than across the deployment. Limits cannot be changed
+/// until Redis is back; ones lost with a Redis restart are stored again from
+/// the replica's copy.
+
+use crate::breaker::RedisLink;
+use crate::tenant::TenantContext;
+use crate::{ApiError, RpcServerState};
+use svm_clob_storage::Storage;
+use svm_clob_types::{ClobError, ClobResult, RateLimitScope, RateLimitStatus, Tenant, WirePayload};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, RwLock};
+use tracing::{info, warn};
+
+const SECOND_US: i64 = 1_000_000;
+
+/// Local keys kept before ones whose allowance has fully refilled are dropped
+const MAX_LOCAL_KEYS: usize = 100_000;
+
+/// GCRA for the key at KEYS[2], against the limit in ARGV[3] or, when that is
+/// empty, the field ARGV[1] of the limits stored at KEYS[1]. Returns {-1} when
+/// the call is admitted, {-2} when no limits are stored, otherwise the limit
+/// it exceeded and the key's backlog, its theoretical arrival time less now,
+/// in microseconds.
+const GCRA_SCRIPT: &str = r#"
+local limit = tonumber(ARGV[3])
+if not limit then
+  local limits = redis.call('GET', KEYS[1])
+  if not limits then return {-2} end
+  limit = cjson.decode(limits)[ARGV[1]]
+  if type(limit) ~= 'number' then return {-1} end
+end
+if limit <= 0 then return {0, 0} end
+local now = tonumber(ARGV[2])
+local interval = math.max(math.floor(1000000 / limit), 1)
+local tat = math.max(tonumber(redis.call('GET', KEYS[2]) or now), now)
+if tat + interval - now > 1000000 then return {limit, tat - now} end
+redis.call('SET', KEYS[2], string.format('%.0f', tat + interval), 'PX', math.ceil((tat + interval - now) / 1000))
+return {-1}
+"#;
+
+/// Rate limits; `None` leaves a dimension unlimited
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateLimits {
+    /// Orders accepted per owner per second
+    pub max_orders_per_second: Option<u32>,
+    /// Requests accepted per client IP per second, across all routes but `/health`
+    #[serde(default)]
+    pub max_requests_per_ip_per_second: Option<u32>,
+}
+
+impl WirePayload for RateLimits {
+    const TYPE: &'static str = "RateLimits";
+}
+
+/// What a call is counted against
+#[derive(Debug, Clone, Copy)]
+pub enum RateKey<'a> {
+    /// Order placements of one owner
+    Owner(&'a str),
+    /// Requests from one client IP
+    Ip(IpAddr),
+    /// Order placements made with one tenant's keys
+    TenantOrders(&'a Tenant),
+    /// Requests made with one tenant's keys
+    TenantRequests(&'a Tenant),
+}
+
+impl RateKey<'_> {
+    fn name(&self) -> String {
+        match self {
+            RateKey::Owner(owner) => format!("owner:{}", owner),
+            RateKey::Ip(ip) => format!("ip:{}", ip),
+            RateKey::TenantOrders(tenant) => format!("tenant-orders:{}", tenant.id),
+            RateKey::TenantRequests(tenant) => format!("tenant-requests:{}", tenant.id),
+        }
+    }
+
+    fn limit(&self, limits: &RateLimits) -> Option<u32> {
+        match self {
+            RateKey::Owner(_) => limits.max_orders_per_second,
+            RateKey::Ip(_) => limits.max_requests_per_ip_per_second,
+            RateKey::TenantOrders(_) | RateKey::TenantRequests(_) => self.carried_limit().flatten(),
+        }
+    }
+
+    /// Limit of a tenant key, which the tenant carries rather than `RateLimits`
+    fn carried_limit(&self) -> Option<Option<u32>> {
+        match self {
+            RateKey::Owner(_) | RateKey::Ip(_) => None,
+            RateKey::TenantOrders(tenant) => Some(tenant.max_orders_per_second),
+            RateKey::TenantRequests(tenant) => Some(tenant.max_requests_per_second),
+        }
+    }
+
+    /// Field of `RateLimits` holding this key's limit; tenant limits are
+    /// carried by the tenant instead
+    fn field(&self) -> &'static str {
+        match self {
+            RateKey::Owner(_) => "max_orders_per_second",
+            RateKey::Ip(_) => "max_requests_per_ip_per_second",
+            RateKey::TenantOrders(_) | RateKey::TenantRequests(_) => "",
+        }
+    }
+
+    /// Rejection of a call over `limit`, made when the key's theoretical
+    /// arrival time was `backlog_us` ahead of now
+    fn rejection(&self, limit: u32, backlog_us: i64) -> ClobError {
+        let scope = match self {
+            RateKey::Owner(_) => RateLimitScope::Orders,
+            RateKey::Ip(_) => RateLimitScope::Requests,
+            RateKey::TenantOrders(_) => RateLimitScope::TenantOrders,
+            RateKey::TenantRequests(_) => RateLimitScope::TenantRequests,
+        };
+        ClobError::RateLimited(status(scope, limit, backlog_us))
+    }
+}
+
+/// Standing of a key `backlog_us` behind its theoretical arrival time. Each
+/// admitted call pushes that time one interval further, and a call is admitted
+/// once it is at most a second ahead, so the backlog counts the calls still
+/// within the current second's allowance.
+fn status(scope: RateLimitScope, limit: u32, backlog_us: i64) -> RateLimitStatus {
+    // A zero limit refuses everything; look again once the limits may have changed
+    if limit == 0 {
+        return RateLimitStatus {
+            scope,
+            limit,
+            used: 0,
+            retry_after_ms: 1000,
+            reset_after_ms: 1000,
+        };
+    }
+    let backlog_us = backlog_us.max(0);
+    let interval = (SECOND_US / i64::from(limit)).max(1);
+    let used = (backlog_us + interval - 1) / interval;
+    RateLimitStatus {
+        scope,
+        limit,
+        used: u32::try_from(used).unwrap_or(u32::MAX).min(limit),
+        retry_after_ms: millis(backlog_us + interval - SECOND_US),
+        reset_after_ms: millis(backlog_us),
+    }
+}
+
+/// Microseconds rounded up to milliseconds, so waiting that long is enough
+fn millis(us: i64) -> u64 {
+    (us.max(0) as u64).div_ceil(1000)
+}
+
+/// Repeat a rejection's standing in `Retry-After`, in whole seconds, and in
+/// `X-RateLimit-*` headers, whose times are in milliseconds
+pub(crate) fn insert_headers(headers: &mut HeaderMap, status: &RateLimitStatus) {
+    let values = [
+        ("retry-after", status.retry_after_ms.div_ceil(1000).max(1)),
+        ("x-ratelimit-limit", u64::from(status.limit)),
+        ("x-ratelimit-remaining", u64::from(status.remaining())),
+        ("x-ratelimit-retry-after-ms", status.retry_after_ms),
+        ("x-ratelimit-reset-ms", status.reset_after_ms),
+    ];
+    for (name, value) in values {
+        headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+    }
+}
+
+/// Limits and each key's theoretical arrival time, in unix microseconds
+struct LocalLimiter {
+    limits: RwLock<RateLimits>,
+    arrivals: Mutex<HashMap<String, i64>>,
+}
+
+impl LocalLimiter {
+    fn new(limits: RateLimits) -> Self {
+        Self {
+            limits: RwLock::new(limits),
+            arrivals: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn limits(&self) -> RateLimits {
+        self.limits.read().expect("rate limits lock poisoned").clone()
+    }
+
+    fn set_limits(&self, limits: RateLimits) {
+        *self.limits.write().expect("rate limits lock poisoned") = limits;
+    }
+
+    fn check(&self, key: RateKey<'_>, now_us: i64) -> ClobResult<()> {
+        let Some(limit) = key.limit(&self.limits.read().expect("rate limits lock poisoned")) else {
+            return Ok(());
+        };
+        if limit == 0 {
+            return Err(key.rejection(limit, 0));
+        }
+        let interval = (SECOND_US / i64::from(limit)).max(1);
+        let mut arrivals = self.arrivals.lock().expect("rate limiter lock poisoned");
+        if arrivals.len() >= MAX_LOCAL_KEYS {
+            arrivals.retain(|_, arrival| *arrival > now_us);
+        }
+        let arrival = arrivals.entry(key.name()).or_insert(now_us);
+        let tat = (*arrival).max(now_us);
+        if tat + interval - now_us > SECOND_US {
+            return Err(key.rejection(limit, tat - now_us));
+        }
+        *arrival = tat + interval;
+        Ok(())
+    }
+}
+
+enum Backend {
+    Local(LocalLimiter),
+    /// Limits and arrival times under `prefix` in Redis, with the limits last
+    /// read kept in `fallback`, which counts calls while Redis is unavailable
+    Redis {
+        link: RedisLink,
+        prefix: String,
+        script: redis::Script,
+        fallback: LocalLimiter,
+    },
+}
+
+/// Per-owner and per-IP rate limiter
+pub struct RateLimiter {
+    backend: Backend,
+    /// Take the client IP from `X-Forwarded-For`, set by a trusted load balancer
+    trust_forwarded_for: bool,
+}
+
+impl RateLimiter {
+    /// Limiter counting calls in this process
+    pub fn new(limits: RateLimits) -> Self {
+        Self {
+            backend: Backend::Local(LocalLimiter::new(limits)),
+            trust_forwarded_for: false,
+        }
+    }
+
+    /// Limiter shared through Redis by every server using the same `prefix`.
+    /// `limits` are stored unless another server stored limits first; when
+    /// Redis cannot be reached, the limiter starts on them in process.
+    pub async fn redis(url: &str, prefix: &str, limits: RateLimits) -> ClobResult<Self> {
+        let limiter = Self {
+            backend: Backend::Redis {
+                link: RedisLink::new(url, "rate_limits")?,
+                prefix: prefix.to_string(),
+                script: redis::Script::new(GCRA_SCRIPT),
+                fallback: LocalLimiter::new(limits),
+            },
+            trust_forwarded_for: false,
+        };
+        if let Backend::Redis { link, prefix, fallback, .. } = &limiter.backend {
+            if !seed(link, prefix, fallback).await {
+                warn!("Redis is unavailable; counting rate limits in process until it is back");
+            }
+        }
+        // Adopt the limits another server stored
+        limiter.limits().await?;
+        Ok(limiter)
+    }
+
+    /// Take the client IP from the first `X-Forwarded-For` entry instead of
+    /// the peer address; only safe behind a proxy that overwrites the header
+    pub fn trust_forwarded_for(mut self, trust: bool) -> Self {
+        self.trust_forwarded_for = trust;
+        self
+    }
+
+    /// Current limits; while Redis is unavailable, the ones last read from it
+    pub async fn limits(&self) -> ClobResult<RateLimits> {
+        match &self.backend {
+            Backend::Local(local) => Ok(local.limits()),
+            Backend::Redis { link, prefix, fallback, .. } => {
+                let key = limits_key(prefix);
+                let stored: Option<Option<String>> = link
+                    .run(|mut connection| async move { redis::cmd("GET").arg(key).query_async(&mut connection).await })
+                    .await;
+                match stored.flatten() {
+                    Some(stored) => {
+                        let limits: RateLimits =
+                            serde_json::from_str(&stored).map_err(|e| ClobError::SerializationError(e.to_string()))?;
+                        fallback.set_limits(limits.clone());
+                        Ok(limits)
+                    }
+                    None => Ok(fallback.limits()),
+                }
+            }
+        }
+    }
+
+    /// Replace the limits; allowance already spent is kept. A shared limiter
+    /// refuses while Redis is unavailable, as the change could not reach the
+    /// other replicas.
+    pub async fn set_limits(&self, new_limits: RateLimits) -> ClobResult<()> {
+        match &self.backend {
+            Backend::Local(local) => local.set_limits(new_limits.clone()),
+            Backend::Redis { link, prefix, fallback, .. } => {
+                let key = limits_key(prefix);
+                let encoded = encode(&new_limits)?;
+                link.run(|mut connection| async move {
+                    redis::cmd("SET").arg(key).arg(encoded).query_async::<_, ()>(&mut connection).await
+                })
+                .await
+                .ok_or_else(|| ClobError::StorageError("Redis is unavailable; rate limits are unchanged".to_string()))?;
+                fallback.set_limits(new_limits.clone());
+            }
+        }
+        info!("Rate limits set to {:?}", new_limits);
+        Ok(())
+    }
+
+    /// The feature, `rate_limits`, when it runs on its fallback because
+    /// Redis is unavailable
+    pub fn degraded(&self) -> Option<&'static str> {
+        match &self.backend {
+            Backend::Redis { link, .. } if link.is_degraded() => Some(link.feature()),
+            _ => None,
+        }
+    }
+
+    /// Count one order of `owner` at `now_us` (unix microseconds)
+    pub async fn check_order(&self, owner: &str, now_us: i64) -> ClobResult<()> {
+        self.check(RateKey::Owner(owner), now_us).await
+    }
+
+    /// Count one call against `key` at `now_us` (unix microseconds), rejecting
+    /// it when the key has used up its allowance
+    pub async fn check(&self, key: RateKey<'_>, now_us: i64) -> ClobResult<()> {
+        match &self.backend {
+            Backend::Local(local) => local.check(key, now_us),
+            Backend::Redis { link, prefix, script, fallback } => {
+                let carried = match key.carried_limit() {
+                    // An unlimited tenant has no limit to pass and none stored
+                    Some(None) => return Ok(()),
+                    Some(Some(limit)) => limit.to_string(),
+                    None => String::new(),
+                };
+                let mut invocation = script.key(limits_key(prefix));
+                invocation
+                    .key(format!("{}:{}", prefix, key.name()))
+                    .arg(key.field())
+                    .arg(now_us)
+                    .arg(carried);
+                let outcome: Option<Vec<i64>> = link
+                    .run(|mut connection| async move { invocation.invoke_async(&mut connection).await })
+                    .await;
+                match outcome.as_deref() {
+                    None => fallback.check(key, now_us),
+                    // Redis lost the limits, e.g. in a restart: store this
+                    // replica's copy again and decide this call by it
+                    Some([-2, ..]) => {
+                        seed(link, prefix, fallback).await;
+                        fallback.check(key, now_us)
+                    }
+                    Some([exceeded, backlog, ..]) => match u32::try_from(*exceeded) {
+                        Ok(limit) => Err(key.rejection(limit, *backlog)),
+                        Err(_) => Ok(()),
+                    },
+                    Some(_) => Ok(()),
+                }
+            }
+        }
+    }
+
+    /// Client IP of a request: the peer, or the first forwarded address when trusted
+    fn client_ip(&self, request: &Request) -> Option<IpAddr> {
+        if self.trust_forwarded_for {
+            let forwarded = request
+                .headers()
+                .get("x-forwarded-for")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.split(',').next())
+                .and_then(|first| first.trim().parse().ok());
+            if forwarded.is_some() {
+                return forwarded;
+            }
+        }
+        request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(peer)| peer.ip())
+    }
+}
+
+/// Apply the per-IP limit, or the tenant's request limit in its place, to
+/// every route but the health check
+pub(crate) async fn limit_requests<S: Storage + 'static>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.uri().path() != "/health" {
+        let now_us = state.clock.now_micros();
+        let tenant = request.extensions().get::<TenantContext>().map(|TenantContext(tenant)| tenant.clone());
+        let checked = match tenant {
+            Some(tenant) if tenant.max_requests_per_second.is_some() => {
+                state.rate_limiter.check(RateKey::TenantRequests(&tenant), now_us).await
+            }
+            _ => match state.rate_limiter.client_ip(&request) {
+                Some(ip) => state.rate_limiter.check(RateKey::Ip(ip), now_us).await,
+                None => Ok(()),
+            },
+        };
+        if let Err(e) = checked {
+            return ApiError::from(e).into_response();
+        }
+    }
+    next.run(request).await
+}
+
+fn limits_key(prefix: &str) -> String {
+    format!("{}:limits", prefix)
+}
+
+fn encode(limits: &RateLimits) -> ClobResult<String> {
+    serde_json::to_string(limits).map_err(|e| ClobError::SerializationError(e.to_string()))
+}
+
+/// Store `fallback`'s limits under `prefix` unless limits are stored there;
+/// false when Redis is unavailable
+async fn seed(link: &RedisLink, prefix: &str, fallback: &LocalLimiter) -> bool {
+    let limits = fallback.limits();
+    let Ok(encoded) = encode(&limits) else {
+        return false;
+    };
+    let key = limits_key(prefix);
+    let seeded: Option<Option<String>> = link
+        .run(|mut connection| async move {
+            redis::cmd("SET").arg(key).arg(encoded).arg("NX").query_async(&mut connection).await
+        })
+        .await;
+    match seeded {
+        Some(Some(_)) => {
+            info!("Seeded shared rate limits under {} with {:?}", prefix, limits);
+            true
+        }
+        Some(None) => true,
+        None => false,
+    }
+}
@@ -0,0 +1,53 @@
+/// Speed bump
+///
+/// Orders that only take, market orders and immediate-or-cancel or
+/// fill-or-kill limits, are held at the gateway for a short delay before they
+/// reach the engine, fixed or drawn at random from the configured bounds.
+/// Orders that can rest, cancellations and replacements pass straight
+/// through, so a maker pulling a stale quote is not overtaken by a taker that
+/// saw the same price move a few microseconds sooner. The delay runs before
+/// the order is given an ID or asks for the book lock, so nothing waits
+/// behind it. Each replica holds its own setting, taken from `[orderbook]
+/// speed_bump` at startup and changed through the admin API.
+
+use svm_clob_types::{PlaceOrderRequest, SpeedBump};
+use rand::Rng;
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// Speed bump of the engine's market, off when `None`
+pub struct SpeedBumpGate {
+    setting: RwLock<Option<SpeedBump>>,
+}
+
+impl SpeedBumpGate {
+    pub fn new(speed_bump: Option<SpeedBump>) -> Self {
+        Self {
+            setting: RwLock::new(speed_bump),
+        }
+    }
+
+    pub fn get(&self) -> Option<SpeedBump> {
+        *self.setting.read().expect("speed bump lock poisoned")
+    }
+
+    /// Replace the speed bump, returning the one it replaces
+    pub fn set(&self, speed_bump: Option<SpeedBump>) -> Option<SpeedBump> {
+        std::mem::replace(&mut *self.setting.write().expect("speed bump lock poisoned"), speed_bump)
+    }
+
+    /// Wait out the speed bump when any of `requests` only takes; the
+    /// requests of one batch share one delay
+    pub(crate) async fn hold<'a>(&self, requests: impl IntoIterator<Item = &'a PlaceOrderRequest>) {
+        let Some(speed_bump) = self.get() else {
+            return;
+        };
+        if requests.into_iter().all(PlaceOrderRequest::can_rest) {
+            return;
+        }
+        let delay_us = rand::thread_rng().gen_range(speed_bump.min_delay_us..=speed_bump.max_delay_us);
+        if delay_us > 0 {
+            tokio::time::sleep(Duration::from_micros(delay_us)).await;
+        }
+    }
+}
@@ -0,0 +1,94 @@
+/// Suspensions
+///
+/// Operators suspend an owner or a tenant API key through the admin API when
+/// an account is abusive or its key or wallet is believed compromised. New
+/// orders and replacements of a suspended owner, of the sub-accounts of a
+/// suspended wallet, and any placed with a suspended key are refused with
+/// `ClobError::Suspended`; cancellations still go through, so the owner can
+/// leave the book. Suspending an owner can cancel its resting orders at the
+/// same time. Suspensions live in storage with their reason code, and every
+/// change is in the admin audit log. Each replica reads them through a
+/// short-lived cache, so a change made on another replica applies within
+/// `SUSPENSION_CACHE_TTL`.
+
+use svm_clob_storage::Storage;
+use svm_clob_types::{ClobError, ClobResult, Suspension, SuspensionReason, SuspensionSubject};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long the suspensions read from storage are trusted
+const SUSPENSION_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// ID of the tenant API key a call was made with, set by `resolve_tenant`
+#[derive(Debug, Clone)]
+pub struct ApiKeyId(pub String);
+
+struct Cached {
+    reasons: HashMap<SuspensionSubject, SuspensionReason>,
+    fetched_at: Instant,
+}
+
+/// Suspensions in force, as this replica last read them
+#[derive(Default)]
+pub struct SuspensionList {
+    cached: Mutex<Option<Cached>>,
+}
+
+impl SuspensionList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget the cached suspensions, after one changed on this replica
+    pub(crate) fn invalidate(&self) {
+        *self.cached.lock().expect("suspension cache lock poisoned") = None;
+    }
+
+    /// Reason each subject in force is suspended for
+    async fn reasons<S: Storage>(&self, storage: &S) -> ClobResult<HashMap<SuspensionSubject, SuspensionReason>> {
+        if let Some(cached) = &*self.cached.lock().expect("suspension cache lock poisoned") {
+            if cached.fetched_at.elapsed() < SUSPENSION_CACHE_TTL {
+                return Ok(cached.reasons.clone());
+            }
+        }
+
+        let reasons: HashMap<SuspensionSubject, SuspensionReason> = storage
+            .get_suspensions()
+            .await?
+            .into_iter()
+            .map(|Suspension { subject, reason, .. }| (subject, reason))
+            .collect();
+        *self.cached.lock().expect("suspension cache lock poisoned") = Some(Cached {
+            reasons: reasons.clone(),
+            fetched_at: Instant::now(),
+        });
+        Ok(reasons)
+    }
+
+    /// Refuse an order of `owner`, or one made with `api_key`, while either
+    /// is suspended; an owner that is a sub-account is refused while its
+    /// wallet is
+    pub(crate) async fn check<S: Storage>(&self, storage: &S, owner: &Pubkey, api_key: Option<&ApiKeyId>) -> ClobResult<()> {
+        let reasons = self.reasons(storage).await?;
+        if reasons.is_empty() {
+            return Ok(());
+        }
+
+        let refuse = |subject: SuspensionSubject| match reasons.get(&subject) {
+            Some(reason) => Err(ClobError::Suspended(format!("{} is suspended ({:?})", subject, reason))),
+            None => Ok(()),
+        };
+        if let Some(ApiKeyId(key_id)) = api_key {
+            refuse(SuspensionSubject::ApiKey(key_id.clone()))?;
+        }
+        refuse(SuspensionSubject::Owner(*owner))?;
+        if reasons.keys().any(|subject| matches!(subject, SuspensionSubject::Owner(_))) {
+            if let Some(sub_account) = storage.get_sub_account(owner).await? {
+                refuse(SuspensionSubject::Owner(sub_account.wallet))?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,324 @@
+/// Tenants
+///
+/// Several frontends can integrate against one deployment as tenants, each
+/// calling with its own API keys in an `X-Api-Key` header. A call with a key
+/// is attributed to the key's tenant: it counts against the tenant's request
+/// and order limits, and is metered into hourly usage counts that each replica
+/// adds to storage every few seconds. Calls without a key are served as before
+/// unless `require_api_key` is set. Keys are stored as their SHA-256 only and
+/// resolved through a short-lived cache, so a revocation or tenant change made
+/// on another replica applies within `KEY_CACHE_TTL`. A key issued with
+/// `paper` set trades in the paper sandbox, see `paper`.
+
+use crate::paper::PaperMode;
+use crate::suspension::ApiKeyId;
+use crate::{JsonRpcResponse, RpcServerState};
+use svm_clob_storage::Storage;
+use svm_clob_types::{
+    ClobResult, Envelope, FeedEntitlement, Order, Tenant, TenantApiKey, TenantUsage, WirePayload, TENANT_USAGE_PERIOD_SECS,
+};
+use axum::{
+    extract::{Query, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+    Extension,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{error, warn};
+use uuid::Uuid;
+
+/// Header carrying a tenant's API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// How long a resolved key is trusted before storage is asked again
+const KEY_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Resolved keys kept before stale ones are dropped
+const MAX_CACHED_KEYS: usize = 10_000;
+
+/// How often metered usage is added to storage
+pub(crate) const USAGE_FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Widest usage range served at once, in seconds
+const MAX_USAGE_RANGE_SECS: i64 = 31 * 86_400;
+
+/// Tenant a request was made for, set by `resolve_tenant`
+#[derive(Debug, Clone)]
+pub struct TenantContext(pub Arc<Tenant>);
+
+struct CachedKey {
+    /// `None` for a key that is unknown or revoked
+    tenant: Option<Arc<Tenant>>,
+    /// Whether the key trades on paper
+    paper: bool,
+    fetched_at: Instant,
+}
+
+/// API key resolution and usage metering of the RPC server
+pub struct TenantDirectory {
+    /// Refuse calls that carry no API key
+    require_api_key: bool,
+    /// Resolved keys by hash
+    keys: Mutex<HashMap<String, CachedKey>>,
+    /// Usage not yet added to storage, by tenant and period
+    usage: Mutex<HashMap<(String, i64), TenantUsage>>,
+}
+
+impl TenantDirectory {
+    pub fn new(require_api_key: bool) -> Self {
+        Self {
+            require_api_key,
+            keys: Mutex::new(HashMap::new()),
+            usage: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Tenant of a live API key, and whether the key trades on paper
+    async fn resolve<S: Storage>(&self, storage: &S, api_key: &str) -> ClobResult<Option<(Arc<Tenant>, bool)>> {
+        let hash = hash_key(api_key);
+        if let Some(cached) = self.keys.lock().expect("tenant key cache lock poisoned").get(&hash) {
+            if cached.fetched_at.elapsed() < KEY_CACHE_TTL {
+                return Ok(cached.tenant.clone().map(|tenant| (tenant, cached.paper)));
+            }
+        }
+
+        let (tenant, paper) = match storage.get_tenant_api_key(&hash).await? {
+            Some(key) if key.revoked_at.is_none() => {
+                (storage.get_tenant(&key.tenant_id).await?.map(Arc::new), key.paper)
+            }
+            _ => (None, false),
+        };
+        let mut keys = self.keys.lock().expect("tenant key cache lock poisoned");
+        if keys.len() >= MAX_CACHED_KEYS {
+            keys.retain(|_, cached| cached.fetched_at.elapsed() < KEY_CACHE_TTL);
+            if keys.len() >= MAX_CACHED_KEYS {
+                keys.clear();
+            }
+        }
+        keys.insert(
+            hash,
+            CachedKey {
+                tenant: tenant.clone(),
+                paper,
+                fetched_at: Instant::now(),
+            },
+        );
+        Ok(tenant.map(|tenant| (tenant, paper)))
+    }
+
+    /// Forget resolved keys, after a tenant or key changed on this replica
+    pub(crate) fn invalidate(&self) {
+        self.keys.lock().expect("tenant key cache lock poisoned").clear();
+    }
+
+    fn meter(&self, tenant_id: &str, now: i64, update: impl FnOnce(&mut TenantUsage)) {
+        let usage = TenantUsage::new(tenant_id, now);
+        let mut pending = self.usage.lock().expect("tenant usage lock poisoned");
+        update(
+            pending
+                .entry((usage.tenant_id.clone(), usage.period_start))
+                .or_insert(usage),
+        );
+    }
+
+    /// Count an order the engine accepted for `tenant`
+    pub(crate) fn record_order(&self, tenant: &Tenant, order: &Order, now: i64) {
+        self.meter(&tenant.id, now, |usage| {
+            usage.orders_placed += 1;
+            usage.notional_placed = usage
+                .notional_placed
+                .saturating_add(order.price.saturating_mul(order.quantity));
+        });
+    }
+
+    /// Count a batch item of `tenant` refused by a rate limit
+    pub(crate) fn record_rate_limited(&self, tenant: &Tenant, now: i64) {
+        self.meter(&tenant.id, now, |usage| usage.rate_limited += 1);
+    }
+
+    fn record_response(&self, tenant: &Tenant, status: StatusCode, now: i64) {
+        self.meter(&tenant.id, now, |usage| {
+            usage.requests += 1;
+            if status.is_client_error() || status.is_server_error() {
+                usage.errors += 1;
+            }
+            if status == StatusCode::TOO_MANY_REQUESTS {
+                usage.rate_limited += 1;
+            }
+        });
+    }
+
+    /// Take the usage metered since the last call
+    fn take_usage(&self) -> Vec<TenantUsage> {
+        let mut pending = self.usage.lock().expect("tenant usage lock poisoned");
+        pending.drain().map(|(_, usage)| usage).collect()
+    }
+
+    /// Put back usage that could not be stored, to be added with the next flush
+    fn restore_usage(&self, usage: Vec<TenantUsage>) {
+        let mut pending = self.usage.lock().expect("tenant usage lock poisoned");
+        for counts in usage {
+            pending
+                .entry((counts.tenant_id.clone(), counts.period_start))
+                .or_insert_with(|| TenantUsage::new(&counts.tenant_id, counts.period_start))
+                .merge(&counts);
+        }
+    }
+}
+
+/// SHA-256 of an API key, as stored
+pub(crate) fn hash_key(api_key: &str) -> String {
+    TenantApiKey::hash(api_key)
+}
+
+/// New API key of `tenant_id`, trading on paper when `paper` is set and
+/// serving the WebSocket feeds of `entitlement`: the key to hand to the
+/// tenant, and its record. Keys read `<key_id>.<secret>`.
+pub(crate) fn issue_key(
+    tenant_id: &str,
+    label: Option<String>,
+    paper: bool,
+    entitlement: FeedEntitlement,
+    now: i64,
+) -> (String, TenantApiKey) {
+    let key_id = Uuid::new_v4().simple().to_string()[..12].to_string();
+    let api_key = format!("{}.{}", key_id, Uuid::new_v4().simple());
+    let record = TenantApiKey {
+        key_id,
+        tenant_id: tenant_id.to_string(),
+        key_hash: hash_key(&api_key),
+        label,
+        paper,
+        entitlement,
+        created_at: now,
+        revoked_at: None,
+    };
+    (api_key, record)
+}
+
+/// Attribute calls carrying an API key to its tenant, and meter them
+pub(crate) async fn resolve_tenant<S: Storage + 'static>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    // Admin routes authenticate with the operator token instead
+    let path = request.uri().path();
+    if path == "/health" || path.starts_with("/api/v1/admin/") {
+        return next.run(request).await;
+    }
+
+    let api_key = request
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let (tenant, paper) = match api_key.as_deref() {
+        Some(api_key) => match state.tenants.resolve(state.storage.as_ref(), api_key).await {
+            Ok(Some((tenant, paper))) if !tenant.disabled => (tenant, paper),
+            Ok(Some((tenant, _))) => {
+                warn!("Refused a call for disabled tenant {}", tenant.id);
+                return StatusCode::FORBIDDEN.into_response();
+            }
+            Ok(None) => return StatusCode::UNAUTHORIZED.into_response(),
+            Err(e) => {
+                error!("Failed to resolve an API key: {}", e);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        },
+        None if state.tenants.require_api_key => return StatusCode::UNAUTHORIZED.into_response(),
+        None => return next.run(request).await,
+    };
+
+    request.extensions_mut().insert(TenantContext(tenant.clone()));
+    // Keys read `<key_id>.<secret>`, and this one resolved
+    if let Some((key_id, _)) = api_key.as_deref().and_then(|api_key| api_key.split_once('.')) {
+        request.extensions_mut().insert(ApiKeyId(key_id.to_string()));
+    }
+    if paper {
+        request.extensions_mut().insert(PaperMode);
+    }
+    let response = next.run(request).await;
+    state.tenants.record_response(&tenant, response.status(), state.clock.now());
+    response
+}
+
+/// Add metered usage to storage every `USAGE_FLUSH_INTERVAL`; usage that
+/// cannot be stored is kept for the next attempt
+pub(crate) async fn flush_usage<S: Storage + 'static>(state: Arc<RpcServerState<S>>) {
+    let mut interval = tokio::time::interval(USAGE_FLUSH_INTERVAL);
+    loop {
+        interval.tick().await;
+        let usage = state.tenants.take_usage();
+        if usage.is_empty() {
+            continue;
+        }
+        if let Err(e) = state.storage.add_tenant_usage(&usage).await {
+            warn!("Failed to store tenant usage, retrying with the next flush: {}", e);
+            state.tenants.restore_usage(usage);
+        }
+    }
+}
+
+/// Query parameters of a usage report
+#[derive(Deserialize)]
+pub(crate) struct UsageQuery {
+    /// Start of the range (unix seconds), defaulting to a day before `until`
+    from: Option<i64>,
+    /// End of the range (unix seconds), defaulting to now
+    until: Option<i64>,
+}
+
+impl UsageQuery {
+    /// Bounds of the requested range, refusing ranges wider than a month
+    pub(crate) fn range(&self, now: i64) -> Result<(i64, i64), StatusCode> {
+        let until = self.until.unwrap_or(now + TENANT_USAGE_PERIOD_SECS);
+        let from = self.from.unwrap_or(until - 86_400);
+        if from > until || until - from > MAX_USAGE_RANGE_SECS {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        Ok((from, until))
+    }
+}
+
+fn ok<T: WirePayload>(result: T) -> Json<JsonRpcResponse<T>> {
+    Json(JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: Some(1),
+        result: Some(Envelope::new(result)),
+        error: None,
+    })
+}
+
+/// The caller's tenant
+pub(crate) async fn get_tenant_handler(
+    tenant: Option<Extension<TenantContext>>,
+) -> Result<Json<JsonRpcResponse<Tenant>>, StatusCode> {
+    let Some(Extension(TenantContext(tenant))) = tenant else {
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+    Ok(ok(tenant.as_ref().clone()))
+}
+
+/// Stored usage of the caller's tenant; the last few seconds are still being metered
+pub(crate) async fn get_tenant_usage_handler<S: Storage>(
+    State(state): State<Arc<RpcServerState<S>>>,
+    tenant: Option<Extension<TenantContext>>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<JsonRpcResponse<Vec<TenantUsage>>>, StatusCode> {
+    let Some(Extension(TenantContext(tenant))) = tenant else {
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+    let (from, until) = query.range(state.clock.now())?;
+    match state.storage.get_tenant_usage(&tenant.id, from, until).await {
+        Ok(usage) => Ok(ok(usage)),
+        Err(e) => {
+            error!("Failed to get usage of tenant {}: {}", tenant.id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
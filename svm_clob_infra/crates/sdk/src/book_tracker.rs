@@ -0,0 +1,394 @@
+/// Local order book maintenance
+///
+/// `BookTracker` keeps a copy of one market's L2 book from the WebSocket feed.
+/// It subscribes to the market's book, starts from the snapshot the server
+/// answers with and applies each `BookDelta` that follows it in sequence and
+/// matches its checksum. A gap, a checksum mismatch or a dropped connection
+/// discards the copy and resubscribes for a fresh snapshot. A connection that
+/// cannot be made, or goes quiet past the server's pings, is retried with a
+/// growing backoff, moving through the backup servers in turn. Reconnecting to
+/// the same server resumes the session of the dropped connection, which
+/// replays the deltas missed instead of sending a fresh snapshot. `LocalBook`
+/// is the same state machine without the connection, for feeds read some
+/// other way.
+
+use crate::retry::{Endpoints, RetryPolicy};
+use svm_clob_types::*;
+use svm_clob_types::ws::{Subscription, WebSocketMessage, WebSocketSession};
+use futures_util::stream::{SplitSink, StreamExt};
+use futures_util::SinkExt;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tracing::{debug, info, warn};
+
+/// Deltas held while waiting for a snapshot; older ones are dropped first
+const MAX_PENDING_DELTAS: usize = 1024;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// What a feed message did to a `LocalBook`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BookUpdate {
+    /// The book changed
+    Applied,
+    /// Nothing changed: another market, a delta the book already covers, or
+    /// one held until the snapshot arrives
+    Skipped,
+    /// The book was discarded and needs a fresh snapshot
+    Gap(String),
+}
+
+/// One market's book rebuilt from a snapshot and the deltas after it
+#[derive(Debug, Clone)]
+pub struct LocalBook {
+    market_id: MarketId,
+    book: Option<OrderBookSnapshot>,
+    /// Deltas received before the snapshot they follow
+    pending: Vec<BookDelta>,
+}
+
+impl LocalBook {
+    pub fn new(market_id: MarketId) -> Self {
+        Self {
+            market_id,
+            book: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Current book; `None` until a snapshot arrives and after a gap
+    pub fn book(&self) -> Option<&OrderBookSnapshot> {
+        self.book.as_ref()
+    }
+
+    /// Discard the book until the next snapshot
+    pub fn reset(&mut self) {
+        self.book = None;
+        self.pending.clear();
+    }
+
+    /// Apply a market data update
+    pub fn on_update(&mut self, update: &MarketDataUpdate) -> BookUpdate {
+        if update.market_id != self.market_id {
+            return BookUpdate::Skipped;
+        }
+        match (update.update_type, &update.order_book, &update.book_delta) {
+            (MarketDataUpdateType::OrderBookUpdate, Some(book), _) => self.on_snapshot(book.clone()),
+            (MarketDataUpdateType::OrderBookDelta, _, Some(delta)) => self.on_delta(delta),
+            _ => BookUpdate::Skipped,
+        }
+    }
+
+    /// Replace the book with `snapshot`, then apply the held deltas that follow it
+    pub fn on_snapshot(&mut self, snapshot: OrderBookSnapshot) -> BookUpdate {
+        let pending = std::mem::take(&mut self.pending);
+        self.book = Some(snapshot);
+        for delta in &pending {
+            if let BookUpdate::Gap(reason) = self.on_delta(delta) {
+                return BookUpdate::Gap(reason);
+            }
+        }
+        BookUpdate::Applied
+    }
+
+    /// Apply `delta` when it follows the book; a delta that does not, or whose
+    /// checksum does not match, discards the book
+    pub fn on_delta(&mut self, delta: &BookDelta) -> BookUpdate {
+        let Some(book) = self.book.as_mut() else {
+            if self.pending.len() >= MAX_PENDING_DELTAS {
+                self.pending.remove(0);
+            }
+            self.pending.push(delta.clone());
+            return BookUpdate::Skipped;
+        };
+        if delta.sequence_number <= book.sequence_number {
+            return BookUpdate::Skipped;
+        }
+        match delta.apply(book) {
+            Ok(()) => BookUpdate::Applied,
+            Err(e) => {
+                self.reset();
+                BookUpdate::Gap(e.to_string())
+            }
+        }
+    }
+}
+
+/// Book tracker configuration
+#[derive(Debug, Clone)]
+pub struct BookTrackerConfig {
+    /// WebSocket server URL, e.g. `ws://localhost:8081/ws`
+    pub ws_url: String,
+    pub market_id: MarketId,
+    /// Ask the server for at most this many book frames a second, each
+    /// carrying the latest book; `None` receives every delta
+    pub max_frames_per_second: Option<u32>,
+    /// Levels a side to track; `None` tracks as many as the connection is
+    /// entitled to
+    pub depth: Option<u32>,
+    /// Tenant API key to connect with, whose entitlement may serve deeper books
+    pub api_key: Option<String>,
+    /// Wait before reconnecting after the connection drops, doubled after
+    /// each attempt that fails to get a book, up to `max_reconnect_delay`
+    pub reconnect_delay: Duration,
+    pub max_reconnect_delay: Duration,
+    /// Servers to move to, in order, when one cannot be reached
+    pub backup_urls: Vec<String>,
+    /// Longest the WebSocket handshake may take
+    pub connect_timeout: Duration,
+    /// Longest the connection may stay silent before it is taken as lost; the
+    /// server pings an idle connection every 30 seconds
+    pub idle_timeout: Duration,
+}
+
+impl BookTrackerConfig {
+    pub fn new(ws_url: impl Into<String>, market_id: MarketId) -> Self {
+        Self {
+            ws_url: ws_url.into(),
+            market_id,
+            max_frames_per_second: None,
+            depth: None,
+            api_key: None,
+            reconnect_delay: Duration::from_secs(1),
+            max_reconnect_delay: Duration::from_secs(30),
+            backup_urls: Vec::new(),
+            connect_timeout: Duration::from_secs(10),
+            idle_timeout: Duration::from_secs(90),
+        }
+    }
+}
+
+/// Background task keeping a `LocalBook` in sync with a WebSocket server
+///
+/// The book is `None` until the first snapshot, and again from a gap or a
+/// disconnect until the snapshot that follows it or the session resumes. Dropping the tracker stops
+/// the task.
+pub struct BookTracker {
+    books: watch::Receiver<Option<OrderBookSnapshot>>,
+    task: JoinHandle<()>,
+}
+
+impl BookTracker {
+    /// Connect and start tracking; must be called within a tokio runtime
+    pub fn start(config: BookTrackerConfig) -> Self {
+        let (sender, books) = watch::channel(None);
+        let task = tokio::spawn(run(config, sender));
+        Self { books, task }
+    }
+
+    /// Latest book
+    pub fn book(&self) -> Option<OrderBookSnapshot> {
+        self.books.borrow().clone()
+    }
+
+    /// Receiver notified whenever the book changes
+    pub fn subscribe(&self) -> watch::Receiver<Option<OrderBookSnapshot>> {
+        self.books.clone()
+    }
+
+    /// Call `callback` with every new book until the tracker is dropped
+    pub fn on_update<F>(&self, mut callback: F) -> JoinHandle<()>
+    where
+        F: FnMut(&OrderBookSnapshot) + Send + 'static,
+    {
+        let mut books = self.subscribe();
+        tokio::spawn(async move {
+            while books.changed().await.is_ok() {
+                let book = books.borrow_and_update().clone();
+                if let Some(book) = book {
+                    callback(&book);
+                }
+            }
+        })
+    }
+}
+
+impl Drop for BookTracker {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Session of the last connection, for the next one to the same server to resume
+struct Resumable {
+    host: usize,
+    token: String,
+    /// Sequence of the last update received in the session
+    last_sequence: Option<u64>,
+}
+
+impl Resumable {
+    /// `url` resuming the session
+    fn url(&self, url: &str) -> String {
+        let separator = if url.contains('?') { '&' } else { '?' };
+        let mut url = format!("{}{}session={}", url, separator, self.token);
+        if let Some(last_sequence) = self.last_sequence {
+            url.push_str(&format!("&last_sequence={}", last_sequence));
+        }
+        url
+    }
+}
+
+async fn run(config: BookTrackerConfig, books: watch::Sender<Option<OrderBookSnapshot>>) {
+    let mut local = LocalBook::new(config.market_id);
+    let endpoints = Endpoints::new(config.ws_url.clone(), &config.backup_urls);
+    let backoff = RetryPolicy {
+        max_attempts: u32::MAX,
+        initial_backoff: config.reconnect_delay,
+        max_backoff: config.max_reconnect_delay,
+    };
+    // Reconnects since the book was last served
+    let mut failures = 0;
+    let mut session = None;
+    loop {
+        let (host, url) = endpoints.current();
+        let mut served = false;
+        if let Err(e) = track(&config, host, url, &mut local, &books, &mut served, &mut session).await {
+            warn!("Book feed of {} from {} lost: {}", config.market_id, url, e);
+        }
+        // The book is kept for the session to resume, and discarded if it is not
+        books.send_replace(None);
+        if served {
+            failures = 0;
+        } else {
+            // The server never got as far as a book: try the next one
+            endpoints.fail_over(host);
+        }
+        failures += 1;
+        tokio::time::sleep(backoff.backoff(failures, None)).await;
+    }
+}
+
+/// Follow the feed from `url`, server `host`, over one connection until it
+/// drops, resuming `session` when it was opened with the same server; `served`
+/// is set once a book has been applied or the session resumed
+async fn track(
+    config: &BookTrackerConfig,
+    host: usize,
+    url: &str,
+    local: &mut LocalBook,
+    books: &watch::Sender<Option<OrderBookSnapshot>>,
+    served: &mut bool,
+    session: &mut Option<Resumable>,
+) -> ClobResult<()> {
+    let resuming = session.as_ref().is_some_and(|session| session.host == host);
+    let url = match session.as_ref().filter(|_| resuming) {
+        Some(session) => session.url(url),
+        None => url.to_string(),
+    };
+    let mut request = url.as_str().into_client_request().map_err(network_error)?;
+    if let Some(api_key) = &config.api_key {
+        let value = HeaderValue::from_str(api_key).map_err(network_error)?;
+        request.headers_mut().insert("x-api-key", value);
+    }
+    let (socket, _) = tokio::time::timeout(config.connect_timeout, connect_async(request))
+        .await
+        .map_err(|_| ClobError::NetworkError(format!("Connecting to {} timed out", url)))?
+        .map_err(network_error)?;
+    let (mut sink, mut stream) = socket.split();
+
+    let subscription = Subscription::OrderBook {
+        market: config.market_id,
+        max_frames_per_second: config.max_frames_per_second,
+        depth: config.depth,
+    };
+    // A resumed session is subscribed already
+    if !resuming {
+        local.reset();
+        send(&mut sink, &WebSocketMessage::Subscribe(subscription.clone())).await?;
+    }
+    info!("Tracking the book of {} from {}", config.market_id, url);
+
+    loop {
+        let message = match tokio::time::timeout(config.idle_timeout, stream.next()).await {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(_) => {
+                return Err(ClobError::NetworkError(format!(
+                    "No frame for {:?}",
+                    config.idle_timeout
+                )))
+            }
+        };
+        let text = match message.map_err(network_error)? {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        let update = match WebSocketMessage::from_frame(&text) {
+            Ok(WebSocketMessage::MarketData(update)) => update,
+            Ok(WebSocketMessage::Session(opened)) => {
+                let resumed = resuming && restores(&opened, config.market_id);
+                if resumed {
+                    info!("Resumed the book feed of {} from {}", config.market_id, url);
+                    *served = true;
+                    books.send_replace(local.book().cloned());
+                } else if resuming {
+                    // The server no longer had the session: start over
+                    local.reset();
+                    send(&mut sink, &WebSocketMessage::Subscribe(subscription.clone())).await?;
+                }
+                let last_sequence = session.as_ref().filter(|_| resumed).and_then(|session| session.last_sequence);
+                *session = Some(Resumable {
+                    host,
+                    token: opened.token,
+                    last_sequence,
+                });
+                continue;
+            }
+            Ok(WebSocketMessage::Ping) => {
+                send(&mut sink, &WebSocketMessage::Pong).await?;
+                continue;
+            }
+            Ok(WebSocketMessage::Error(error)) => {
+                warn!("Book feed of {} reported error {}: {}", config.market_id, error.code, error.message);
+                continue;
+            }
+            Ok(_) => continue,
+            Err(e) => {
+                debug!("Ignoring undecodable frame: {}", e);
+                continue;
+            }
+        };
+
+        if let (Some(session), Some(sequence)) = (session.as_mut(), update.sequence) {
+            session.last_sequence = Some(sequence);
+        }
+        match local.on_update(&update) {
+            BookUpdate::Applied => {
+                *served = true;
+                books.send_replace(local.book().cloned());
+            }
+            BookUpdate::Skipped => {}
+            BookUpdate::Gap(reason) => {
+                warn!("Resubscribing to the book of {}: {}", config.market_id, reason);
+                books.send_replace(None);
+                send(&mut sink, &WebSocketMessage::Unsubscribe(subscription.clone())).await?;
+                send(&mut sink, &WebSocketMessage::Subscribe(subscription.clone())).await?;
+            }
+        }
+    }
+    Err(ClobError::NetworkError("Connection closed".to_string()))
+}
+
+/// Whether `session` resumed a subscription to the book of `market`
+fn restores(session: &WebSocketSession, market: MarketId) -> bool {
+    session.resumed
+        && session.subscriptions.iter().any(|subscription| {
+            matches!(subscription, Subscription::OrderBook { market: subscribed, .. } if *subscribed == market)
+        })
+}
+
+async fn send(sink: &mut SplitSink<Socket, Message>, message: &WebSocketMessage) -> ClobResult<()> {
+    sink.send(Message::Text(message.to_frame()?)).await.map_err(network_error)
+}
+
+fn network_error<E: std::fmt::Display>(e: E) -> ClobError {
+    ClobError::NetworkError(e.to_string())
+}
@@ -0,0 +1,573 @@
+/// Client SDK for SVM CLOB Infrastructure
+///
+/// This module provides a typed client for the REST API (order management, batch
+/// cancel-replace and market data), a WebSocket `BookTracker` that keeps a
+/// local copy of a market's book, and re-exports the on-chain program interface
+/// for building deposit, withdrawal and settlement transactions. Orders have
+/// no on-chain instructions; they are placed, cancelled and matched through
+/// the REST API only. Both transports retry transient failures and fail over
+/// to backup hosts (see `retry`).
+
+use svm_clob_types::*;
+use reqwest::header::HeaderValue;
+use reqwest::{Method, StatusCode};
+use serde::Deserialize;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+pub mod book_tracker;
+pub mod retry;
+
+pub use book_tracker::{BookTracker, BookTrackerConfig, BookUpdate, LocalBook};
+pub use retry::RetryPolicy;
+pub use svm_clob_types::{program, signing};
+
+use retry::Endpoints;
+
+/// Header carrying the key an order command runs once under
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Response wrapper returned by every REST endpoint; `result` is a versioned
+/// envelope, or a bare payload from servers that predate envelopes
+#[derive(Deserialize, Debug)]
+struct ApiResponse {
+    result: Option<serde_json::Value>,
+    error: Option<ApiError>,
+}
+
+/// Error body returned by the REST API
+#[derive(Deserialize, Debug)]
+struct ApiError {
+    code: i32,
+    message: String,
+    data: Option<serde_json::Value>,
+}
+
+impl ApiError {
+    /// Typed error for a canonical code, falling back to a network error for
+    /// codes this build does not know
+    fn into_clob_error(self) -> ClobError {
+        let detail = self
+            .data
+            .as_ref()
+            .and_then(|data| data.get("detail"))
+            .and_then(|detail| detail.as_str())
+            .unwrap_or(&self.message);
+        u32::try_from(self.code)
+            .ok()
+            .and_then(|code| ClobError::from_code(code, detail))
+            .unwrap_or_else(|| ClobError::NetworkError(format!("{} ({})", self.message, self.code)))
+    }
+}
+
+/// REST client configuration
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// Longest one attempt of a call may take, connecting included
+    pub timeout: Duration,
+    /// Longest connecting to a host may take before the attempt fails
+    pub connect_timeout: Duration,
+    pub retry: RetryPolicy,
+    /// Hosts calls move to, in order, when the primary cannot be reached
+    pub backup_urls: Vec<String>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            connect_timeout: Duration::from_secs(3),
+            retry: RetryPolicy::default(),
+            backup_urls: Vec::new(),
+        }
+    }
+}
+
+/// Why an attempt failed, and what the next one may do about it
+struct Failure {
+    error: ClobError,
+    retry: bool,
+    /// Whether the next attempt may go to another host
+    fail_over: bool,
+    retry_after: Option<Duration>,
+}
+
+impl Failure {
+    fn fatal(error: ClobError) -> Self {
+        Self {
+            error,
+            retry: false,
+            fail_over: false,
+            retry_after: None,
+        }
+    }
+}
+
+/// REST client for an SVM CLOB RPC server and its backups
+#[derive(Debug, Clone)]
+pub struct ClobClient {
+    http: reqwest::Client,
+    /// Shared by clones, so they all follow a failover
+    endpoints: Arc<Endpoints>,
+    retry: RetryPolicy,
+    /// Tenant API key sent with every request
+    api_key: Option<String>,
+}
+
+impl ClobClient {
+    /// Create a client for the server at `base_url` (e.g. `http://localhost:8080`)
+    pub fn new(base_url: impl Into<String>) -> ClobResult<Self> {
+        Self::with_config(base_url, ClientConfig::default())
+    }
+
+    /// Create a client with a custom request timeout
+    pub fn with_timeout(base_url: impl Into<String>, timeout: Duration) -> ClobResult<Self> {
+        Self::with_config(base_url, ClientConfig { timeout, ..ClientConfig::default() })
+    }
+
+    /// Create a client for the server at `base_url` and the backups of `config`
+    pub fn with_config(base_url: impl Into<String>, config: ClientConfig) -> ClobResult<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .connect_timeout(config.connect_timeout)
+            .build()
+            .map_err(network_error)?;
+
+        Ok(Self {
+            http,
+            endpoints: Arc::new(Endpoints::new(base_url, &config.backup_urls)),
+            retry: config.retry,
+            api_key: None,
+        })
+    }
+
+    /// Call as the tenant owning `api_key`
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Place a new order
+    pub async fn place_order(&self, request: &PlaceOrderRequest) -> ClobResult<Order> {
+        self.send(self.http.post(self.url("/api/v1/orders")).json(request)).await
+    }
+
+    /// Place a new order under `idempotency_key`; sending the same request
+    /// under the same key again, e.g. after a restart, answers with the first
+    /// placement instead of placing another
+    pub async fn place_order_with_key(&self, request: &PlaceOrderRequest, idempotency_key: &str) -> ClobResult<Order> {
+        let request = self.http.post(self.url("/api/v1/orders")).json(request);
+        self.send(request.header(IDEMPOTENCY_KEY_HEADER, idempotency_key)).await
+    }
+
+    /// Cancel an order by ID
+    pub async fn cancel_order(&self, order_id: u64) -> ClobResult<Order> {
+        self.send(self.http.delete(self.url(&format!("/api/v1/orders/{}", order_id)))).await
+    }
+
+    /// Change the price and/or quantity of an order
+    pub async fn modify_order(
+        &self,
+        order_id: u64,
+        new_price: Option<u64>,
+        new_quantity: Option<u64>,
+    ) -> ClobResult<Order> {
+        let request = ModifyOrderRequest {
+            order_id: Some(order_id),
+            client_order_id: None,
+            new_price,
+            new_quantity,
+        };
+        self.send(self.http.put(self.url(&format!("/api/v1/orders/{}", order_id))).json(&request)).await
+    }
+
+    /// Cancel and place orders in one request
+    pub async fn batch(&self, request: &BatchOrderRequest) -> ClobResult<BatchOrderResponse> {
+        self.send(self.http.post(self.url("/api/v1/orders/batch")).json(request)).await
+    }
+
+    /// Cancel and place orders in one request under `idempotency_key`
+    pub async fn batch_with_key(&self, request: &BatchOrderRequest, idempotency_key: &str) -> ClobResult<BatchOrderResponse> {
+        let request = self.http.post(self.url("/api/v1/orders/batch")).json(request);
+        self.send(request.header(IDEMPOTENCY_KEY_HEADER, idempotency_key)).await
+    }
+
+    /// Cancel every given order and place the replacements in one request
+    pub async fn cancel_replace(
+        &self,
+        cancel: Vec<u64>,
+        place: Vec<PlaceOrderRequest>,
+    ) -> ClobResult<BatchOrderResponse> {
+        self.batch(&BatchOrderRequest { cancel, place }).await
+    }
+
+    /// Get an order by ID
+    pub async fn get_order(&self, order_id: u64) -> ClobResult<Order> {
+        self.send(self.http.get(self.url(&format!("/api/v1/orders/{}", order_id)))).await
+    }
+
+    /// Get a page of a user's orders, newest first
+    pub async fn get_user_orders(&self, user: &Pubkey, page: &Paginated) -> ClobResult<Page<Order>> {
+        self.send(self.http.get(self.url(&format!("/api/v1/users/{}/orders", user))).query(page)).await
+    }
+
+    /// Get every order of a user, following cursors to the last page
+    pub async fn get_all_user_orders(&self, user: &Pubkey) -> ClobResult<Vec<Order>> {
+        let mut request = Paginated::first(MAX_PAGE_LIMIT);
+        let mut orders = Vec::new();
+        loop {
+            let page = self.get_user_orders(user, &request).await?;
+            let next = request.next(&page);
+            orders.extend(page.items);
+            match next {
+                Some(next) => request = next,
+                None => return Ok(orders),
+            }
+        }
+    }
+
+    /// Get a page of the sequence map entries matching `lookup`, in journal order
+    pub async fn get_sequence_mappings(&self, lookup: &SequenceLookup, page: &Paginated) -> ClobResult<Page<SequenceMapping>> {
+        let path = match lookup {
+            SequenceLookup::Order(order_id) => format!("/api/v1/sequence/orders/{}", order_id),
+            SequenceLookup::JournalSequence(sequence) => format!("/api/v1/sequence/journal/{}", sequence),
+            SequenceLookup::Signature(signature) => format!("/api/v1/sequence/signatures/{}", signature),
+            SequenceLookup::UserAccount(account) => format!("/api/v1/sequence/accounts/{}", account),
+        };
+        self.send(self.http.get(self.url(&path)).query(page)).await
+    }
+
+    /// Get a user's positions and P&L per market
+    pub async fn get_portfolio(&self, user: &Pubkey) -> ClobResult<Portfolio> {
+        self.send(self.http.get(self.url(&format!("/api/v1/users/{}/portfolio", user)))).await
+    }
+
+    /// Get a user's collateral per market, locked and free
+    pub async fn get_balances(&self, user: &Pubkey) -> ClobResult<Balances> {
+        self.send(self.http.get(self.url(&format!("/api/v1/users/{}/balances", user)))).await
+    }
+
+    /// Build the `withdraw` instruction for `amount` of `mint` out of the
+    /// user account of `owner` (see `program::withdraw_instruction`) once its
+    /// balances show that much withdrawable. Credits from settlements not yet
+    /// finalized are held, and an amount reaching into them is refused with
+    /// `InsufficientBalance`; the program cannot observe finality, so this is
+    /// where the hold is enforced
+    #[allow(clippy::too_many_arguments)]
+    pub async fn checked_withdraw_instruction(
+        &self,
+        program_id: &Pubkey,
+        orderbook: &Pubkey,
+        user: &Pubkey,
+        owner: &Pubkey,
+        user_token_account: &Pubkey,
+        mint: &Pubkey,
+        amount: u64,
+    ) -> ClobResult<Instruction> {
+        let balances = self.get_balances(owner).await?;
+        // Collateral in a mint is shared by every market trading it, so the
+        // least any of them leaves withdrawable is what can go
+        let withdrawable = balances
+            .markets
+            .iter()
+            .filter_map(|market| {
+                if market.base_mint == *mint {
+                    Some(market.base_withdrawable)
+                } else if market.quote_mint == *mint {
+                    Some(market.quote_withdrawable)
+                } else {
+                    None
+                }
+            })
+            .min()
+            .ok_or_else(|| ClobError::UnknownMarket(format!("no market trades mint {}", mint)))?;
+        if i64::try_from(amount).map_or(true, |amount| amount > withdrawable) {
+            return Err(ClobError::InsufficientBalance);
+        }
+        Ok(program::withdraw_instruction(program_id, orderbook, user, owner, user_token_account, mint, amount))
+    }
+
+    /// Get the current aggregated order book
+    pub async fn get_orderbook(&self) -> ClobResult<OrderBookSnapshot> {
+        self.send(self.http.get(self.url("/api/v1/orderbook"))).await
+    }
+
+    /// Get the aggregated order book as of journal entry `sequence`
+    pub async fn get_orderbook_at(&self, sequence: u64) -> ClobResult<OrderBookSnapshot> {
+        self.send(self.http.get(self.url(&format!("/api/v1/orderbook?sequence={}", sequence)))).await
+    }
+
+    /// Get a page of trades, newest first
+    pub async fn get_trades(&self, page: &Paginated) -> ClobResult<Page<TradeExecution>> {
+        self.send(self.http.get(self.url("/api/v1/trades")).query(page)).await
+    }
+
+    /// Get 24h market statistics
+    pub async fn get_market_stats(&self) -> ClobResult<MarketStats> {
+        self.send(self.http.get(self.url("/api/v1/market/stats"))).await
+    }
+
+    /// List the markets served by the node
+    pub async fn get_markets(&self) -> ClobResult<Vec<MarketInfo>> {
+        self.send(self.http.get(self.url("/api/v1/markets"))).await
+    }
+
+    /// Get the current orderbook of one market
+    pub async fn get_market_orderbook(&self, market_id: &MarketId) -> ClobResult<OrderBookSnapshot> {
+        let path = format!("/api/v1/markets/{}/orderbook", market_id);
+        self.send(self.http.get(self.url(&path))).await.map_err(|e| unknown_market(e, market_id))
+    }
+
+    /// Get a page of one market's trades, newest first
+    pub async fn get_market_trades(&self, market_id: &MarketId, page: &Paginated) -> ClobResult<Page<TradeExecution>> {
+        let path = format!("/api/v1/markets/{}/trades", market_id);
+        self.send(self.http.get(self.url(&path)).query(page))
+            .await
+            .map_err(|e| unknown_market(e, market_id))
+    }
+
+    /// Get the ticker of one market
+    pub async fn get_ticker(&self, market_id: &MarketId) -> ClobResult<Ticker> {
+        let path = format!("/api/v1/markets/{}/ticker", market_id);
+        self.send(self.http.get(self.url(&path))).await.map_err(|e| unknown_market(e, market_id))
+    }
+
+    /// Get up to `levels` levels per side of one market's book
+    pub async fn get_depth(&self, market_id: &MarketId, levels: u32) -> ClobResult<Depth> {
+        let path = format!("/api/v1/markets/{}/depth", market_id);
+        self.send(self.http.get(self.url(&path)).query(&[("levels", levels)]))
+            .await
+            .map_err(|e| unknown_market(e, market_id))
+    }
+
+    /// Get the most recent candles of one market, oldest first
+    pub async fn get_candles(&self, market_id: &MarketId, interval: CandleInterval, limit: u32) -> ClobResult<Vec<Candle>> {
+        let path = format!("/api/v1/markets/{}/candles", market_id);
+        let query = [("interval", interval.to_string()), ("limit", limit.to_string())];
+        self.send(self.http.get(self.url(&path)).query(&query))
+            .await
+            .map_err(|e| unknown_market(e, market_id))
+    }
+
+    /// Get the market's insurance fund
+    pub async fn get_insurance_fund(&self) -> ClobResult<InsuranceFund> {
+        self.send(self.http.get(self.url("/api/v1/insurance-fund"))).await
+    }
+
+    /// Get a page of payments into and out of the insurance fund, optionally
+    /// of one kind, newest first
+    pub async fn get_insurance_fund_events(
+        &self,
+        kind: Option<InsuranceFundEventKind>,
+        page: &Paginated,
+    ) -> ClobResult<Page<InsuranceFundEvent>> {
+        let mut request = self.http.get(self.url("/api/v1/insurance-fund/events")).query(page);
+        if let Some(kind) = kind {
+            request = request.query(&[("kind", kind)]);
+        }
+        self.send(request).await
+    }
+
+    /// Get the tenant of this client's API key
+    pub async fn get_tenant(&self) -> ClobResult<Tenant> {
+        self.send(self.http.get(self.url("/api/v1/tenant"))).await
+    }
+
+    /// Get the hourly usage of this client's tenant over periods starting in
+    /// `[from, until)` (unix seconds); the server defaults to the last day
+    pub async fn get_tenant_usage(&self, from: Option<i64>, until: Option<i64>) -> ClobResult<Vec<TenantUsage>> {
+        let mut request = self.http.get(self.url("/api/v1/tenant/usage"));
+        if let Some(from) = from {
+            request = request.query(&[("from", from)]);
+        }
+        if let Some(until) = until {
+            request = request.query(&[("until", until)]);
+        }
+        self.send(request).await
+    }
+
+    /// URL of `path` on the primary host; `send` moves it to the host the
+    /// call goes to
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.endpoints.primary(), path)
+    }
+
+    /// Send `request`, retrying as the policy allows. An order command is
+    /// given an idempotency key unless it has one, and keeps it across retries.
+    async fn send<T: WirePayload>(&self, request: reqwest::RequestBuilder) -> ClobResult<T> {
+        let request = match &self.api_key {
+            Some(api_key) => request.header("x-api-key", api_key),
+            None => request,
+        };
+        let mut request = request.build().map_err(network_error)?;
+        let command = matches!(*request.method(), Method::POST | Method::PUT | Method::DELETE);
+        if command && !request.headers().contains_key(IDEMPOTENCY_KEY_HEADER) {
+            let key = HeaderValue::from_str(&uuid::Uuid::new_v4().to_string()).map_err(network_error)?;
+            request.headers_mut().insert(IDEMPOTENCY_KEY_HEADER, key);
+        }
+        let path = self.endpoints.relative(request.url());
+
+        let mut attempt = 1;
+        loop {
+            let (host, base_url) = self.endpoints.current();
+            let mut attempt_request = request
+                .try_clone()
+                .ok_or_else(|| ClobError::NetworkError("Request body cannot be retried".to_string()))?;
+            *attempt_request.url_mut() = format!("{}{}", base_url, path).parse().map_err(network_error)?;
+
+            let failure = match self.attempt(attempt_request, command).await {
+                Ok(result) => return Ok(result),
+                Err(failure) => failure,
+            };
+            if !failure.retry || attempt >= self.retry.max_attempts {
+                return Err(failure.error);
+            }
+            if failure.fail_over && self.endpoints.hosts() > 1 {
+                self.endpoints.fail_over(host);
+            }
+            let backoff = self.retry.backoff(attempt, failure.retry_after);
+            warn!("{} {} failed, retrying in {:?}: {}", request.method(), path, backoff, failure.error);
+            tokio::time::sleep(backoff).await;
+            attempt += 1;
+        }
+    }
+
+    /// One attempt of a call. A command moves to another host only when it
+    /// cannot have run on this one, since hosts do not share idempotency keys.
+    async fn attempt<T: WirePayload>(&self, request: reqwest::Request, command: bool) -> Result<T, Failure> {
+        let response = match self.http.execute(request).await {
+            Ok(response) => response,
+            Err(e) => {
+                return Err(Failure {
+                    fail_over: e.is_connect() || !command,
+                    retry: true,
+                    retry_after: None,
+                    error: network_error(e),
+                })
+            }
+        };
+        let status = response.status();
+        debug!("{} {}", status, response.url());
+
+        if !status.is_success() {
+            let retry_after = retry::retry_after(response.headers());
+            // Engine rejections carry a JSON-RPC error with a canonical code;
+            // anything else is mapped from the status alone
+            let body = response.text().await.unwrap_or_default();
+            let error = match serde_json::from_str::<ApiResponse>(&body) {
+                Ok(ApiResponse { error: Some(error), .. }) => error.into_clob_error(),
+                _ => match status {
+                    StatusCode::NOT_FOUND => ClobError::OrderNotFound,
+                    StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ClobError::Unauthorized,
+                    status => ClobError::NetworkError(format!("{}: {}", status, body)),
+                },
+            };
+            // A standby or paused engine answers 503 without running the
+            // call; a gateway error may follow a call that ran
+            return Err(match status {
+                StatusCode::SERVICE_UNAVAILABLE => Failure {
+                    error,
+                    retry: true,
+                    fail_over: true,
+                    retry_after,
+                },
+                StatusCode::TOO_MANY_REQUESTS => Failure {
+                    error,
+                    retry: true,
+                    fail_over: false,
+                    retry_after,
+                },
+                StatusCode::BAD_GATEWAY | StatusCode::GATEWAY_TIMEOUT => Failure {
+                    error,
+                    retry: true,
+                    fail_over: !command,
+                    retry_after,
+                },
+                _ => Failure::fatal(error),
+            });
+        }
+
+        let body: ApiResponse = match response.json().await {
+            Ok(body) => body,
+            // The answer was lost on the way; a command's retry is answered
+            // with it again under the same key
+            Err(e) if e.is_timeout() || e.is_body() => {
+                return Err(Failure {
+                    error: network_error(e),
+                    retry: true,
+                    fail_over: !command,
+                    retry_after: None,
+                })
+            }
+            Err(e) => return Err(Failure::fatal(ClobError::SerializationError(e.to_string()))),
+        };
+
+        match (body.result, body.error) {
+            (Some(result), _) => Envelope::from_value(result)
+                .and_then(|envelope| envelope.open())
+                .map_err(Failure::fatal),
+            (None, Some(error)) => Err(Failure::fatal(error.into_clob_error())),
+            (None, None) => Err(Failure::fatal(ClobError::SerializationError("Empty response".to_string()))),
+        }
+    }
+}
+
+/// Convenience constructor for a limit order request
+pub fn limit_order(owner: &Pubkey, side: OrderSide, price: u64, quantity: u64, client_order_id: u64) -> PlaceOrderRequest {
+    PlaceOrderRequest {
+        market_id: None,
+        owner: owner.to_string(),
+        client_order_id,
+        side,
+        order_type: OrderType::Limit,
+        price,
+        quantity,
+        time_in_force: TimeInForce::GoodTillCancelled,
+        expiry_timestamp: None,
+        self_trade_behavior: SelfTradeBehavior::CancelProvide,
+        max_slippage_bps: None,
+        prevention_group_id: None,
+        signature: None,
+    }
+}
+
+/// Convenience constructor for a post-only order request
+pub fn post_only_order(owner: &Pubkey, side: OrderSide, price: u64, quantity: u64, client_order_id: u64) -> PlaceOrderRequest {
+    PlaceOrderRequest {
+        order_type: OrderType::PostOnly,
+        ..limit_order(owner, side, price, quantity, client_order_id)
+    }
+}
+
+/// Convenience constructor for a market order request, rejected with
+/// `SlippageExceeded` when its average fill price would be more than
+/// `max_slippage_bps` from the best opposite price
+pub fn market_order(
+    owner: &Pubkey,
+    side: OrderSide,
+    quantity: u64,
+    max_slippage_bps: Option<u32>,
+    client_order_id: u64,
+) -> PlaceOrderRequest {
+    PlaceOrderRequest {
+        order_type: OrderType::Market,
+        time_in_force: TimeInForce::ImmediateOrCancel,
+        max_slippage_bps,
+        ..limit_order(owner, side, 0, quantity, client_order_id)
+    }
+}
+
+/// Market routes answer 404 for a market the node does not serve
+fn unknown_market(e: ClobError, market_id: &MarketId) -> ClobError {
+    match e {
+        ClobError::OrderNotFound => ClobError::UnknownMarket(market_id.to_string()),
+        e => e,
+    }
+}
+
+fn network_error<E: std::fmt::Display>(e: E) -> ClobError {
+    ClobError::NetworkError(e.to_string())
+}
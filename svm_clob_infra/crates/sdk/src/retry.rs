@@ -0,0 +1,127 @@
+/// Retries and failover
+///
+/// `ClobClient` and `BookTracker` retry a failed call or connection after an
+/// exponential backoff with full jitter, and move through an ordered list of
+/// hosts, the primary first, when one cannot be reached. The host that last
+/// answered is remembered, so calls keep going to a backup until it fails in
+/// turn and the list wraps around to the primary.
+///
+/// Order commands are retried under the same `Idempotency-Key`, which the
+/// server answers once per key, so a placement whose answer was lost is not
+/// placed twice. The server keeps keys per replica, so a command is only
+/// moved to another host when it cannot have run on the first: the
+/// connection was refused, or the host answered `503` as a standby or paused
+/// engine. A command that timed out is retried on the same host.
+
+use rand::Rng;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// When and how often to retry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts of a call in all, the first included; 1 never retries
+    pub max_attempts: u32,
+    /// Backoff before the first retry, doubled for each one after it
+    pub initial_backoff: Duration,
+    /// Longest backoff between two attempts
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Try each call once
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Wait before retry `retry`, counted from 1: a uniform draw up to the
+    /// exponential backoff, or `retry_after` when the server asked for longer
+    pub fn backoff(&self, retry: u32, retry_after: Option<Duration>) -> Duration {
+        let ceiling = self
+            .initial_backoff
+            .saturating_mul(1u32 << retry.saturating_sub(1).min(16))
+            .min(self.max_backoff);
+        let jittered = rand::thread_rng().gen_range(Duration::ZERO..=ceiling);
+        retry_after.map_or(jittered, |retry_after| retry_after.max(jittered))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Hosts of one service in failover order, with the one calls go to
+#[derive(Debug)]
+pub(crate) struct Endpoints {
+    urls: Vec<String>,
+    current: AtomicUsize,
+}
+
+impl Endpoints {
+    /// `primary`, then each of `backups`; trailing slashes are dropped
+    pub(crate) fn new(primary: impl Into<String>, backups: &[String]) -> Self {
+        let urls = std::iter::once(primary.into())
+            .chain(backups.iter().cloned())
+            .map(|url| url.trim_end_matches('/').to_string())
+            .collect();
+        Self {
+            urls,
+            current: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn primary(&self) -> &str {
+        &self.urls[0]
+    }
+
+    /// Path and query of `url`, a URL on the primary host, below the
+    /// primary's own path, for the same call on another host
+    pub(crate) fn relative(&self, url: &reqwest::Url) -> String {
+        let prefix = reqwest::Url::parse(self.primary())
+            .map(|primary| primary.path().trim_end_matches('/').len())
+            .unwrap_or(0);
+        let path = url.path().get(prefix..).unwrap_or(url.path());
+        match url.query() {
+            Some(query) => format!("{}?{}", path, query),
+            None => path.to_string(),
+        }
+    }
+
+    /// Index and URL of the host calls go to
+    pub(crate) fn current(&self) -> (usize, &str) {
+        let index = self.current.load(Ordering::Relaxed) % self.urls.len();
+        (index, &self.urls[index])
+    }
+
+    /// Move calls on from host `failed` to the next one, unless another call
+    /// already has
+    pub(crate) fn fail_over(&self, failed: usize) {
+        let next = (failed + 1) % self.urls.len();
+        let _ = self.current.compare_exchange(failed, next, Ordering::Relaxed, Ordering::Relaxed);
+    }
+
+    pub(crate) fn hosts(&self) -> usize {
+        self.urls.len()
+    }
+}
+
+/// Seconds a `Retry-After` header asks to wait
+pub(crate) fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
@@ -0,0 +1,238 @@
+/// Listeners of the SVM CLOB HTTP servers
+///
+/// The RPC and WebSocket servers serve their routers through `serve`, which
+/// binds where `ListenOptions` says: a TCP address, optionally with TLS, or a
+/// Unix domain socket for a gateway running as a sidecar. It applies the
+/// socket options to every accepted connection, caps how many connections are
+/// open at once and how large a request body may be, and serves HTTP/1 with
+/// upgrades, so WebSockets work, and HTTP/2. TCP connections carry their peer
+/// address as `ConnectInfo<SocketAddr>`; Unix socket connections carry none,
+/// so a gateway in front should forward the client's address in
+/// `X-Forwarded-For`.
+
+use axum::extract::{ConnectInfo, DefaultBodyLimit, Request};
+use axum::Router;
+use hyper::body::Incoming;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::service::TowerToHyperService;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio::sync::Semaphore;
+use tokio_rustls::rustls;
+use tokio_rustls::TlsAcceptor;
+use tower::ServiceExt;
+use tracing::{info, warn};
+
+/// Where and how a server listens
+#[derive(Debug, Clone)]
+pub struct ListenOptions {
+    /// Address to bind on TCP, e.g. `0.0.0.0` or `127.0.0.1`
+    pub host: String,
+    pub port: u16,
+    /// Listen on this Unix domain socket instead of TCP
+    pub unix_socket: Option<PathBuf>,
+    /// Certificate chain and private key files (PEM) to serve TCP over TLS
+    pub tls: Option<TlsFiles>,
+    /// Disable Nagle's algorithm on accepted TCP connections
+    pub tcp_nodelay: bool,
+    /// Idle time before TCP keepalive probes start; no probes when unset
+    pub tcp_keepalive: Option<Duration>,
+    /// Keep HTTP/1 connections open between requests
+    pub http_keepalive: bool,
+    /// Connections served at once; further ones wait to be accepted
+    pub max_connections: Option<usize>,
+    /// Largest request body accepted, in bytes; axum's 2 MB when unset
+    pub max_body_bytes: Option<usize>,
+}
+
+/// PEM files of a TLS listener
+#[derive(Debug, Clone)]
+pub struct TlsFiles {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+impl ListenOptions {
+    /// Plain TCP on every interface at `port`, as the servers have always listened
+    pub fn tcp(port: u16) -> Self {
+        Self {
+            host: "0.0.0.0".to_string(),
+            port,
+            unix_socket: None,
+            tls: None,
+            tcp_nodelay: false,
+            tcp_keepalive: None,
+            http_keepalive: true,
+            max_connections: None,
+            max_body_bytes: None,
+        }
+    }
+
+    /// Where the server listens, for logs
+    pub fn describe(&self) -> String {
+        match (&self.unix_socket, &self.tls) {
+            (Some(path), _) => format!("unix:{}", path.display()),
+            (None, Some(_)) => format!("https://{}:{}", self.host, self.port),
+            (None, None) => format!("http://{}:{}", self.host, self.port),
+        }
+    }
+}
+
+/// Serve `router` as `options` say until the listener fails
+pub async fn serve(router: Router, options: &ListenOptions) -> io::Result<()> {
+    let router = match options.max_body_bytes {
+        Some(limit) => router.layer(DefaultBodyLimit::max(limit)),
+        None => router,
+    };
+    let mut builder = Builder::new(TokioExecutor::new());
+    builder.http1().keep_alive(options.http_keepalive);
+    let slots = options.max_connections.map(|max| Arc::new(Semaphore::new(max.max(1))));
+
+    match &options.unix_socket {
+        Some(path) => serve_unix(router, builder, slots, path).await,
+        None => serve_tcp(router, builder, slots, options).await,
+    }
+}
+
+async fn serve_tcp(
+    router: Router,
+    builder: Builder<TokioExecutor>,
+    slots: Option<Arc<Semaphore>>,
+    options: &ListenOptions,
+) -> io::Result<()> {
+    let tls = options.tls.as_ref().map(tls_acceptor).transpose()?;
+    let listener = tokio::net::TcpListener::bind((options.host.as_str(), options.port)).await?;
+    loop {
+        let permit = match &slots {
+            Some(slots) => Some(slots.clone().acquire_owned().await.expect("connection slots closed")),
+            None => None,
+        };
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                warn!("Failed to accept a connection on {}: {}", options.describe(), e);
+                continue;
+            }
+        };
+        if let Err(e) = configure(&stream, options) {
+            warn!("Failed to set socket options for {}: {}", peer, e);
+        }
+
+        let service = router.clone().map_request(move |mut request: Request<Incoming>| {
+            request.extensions_mut().insert(ConnectInfo(peer));
+            request
+        });
+        let builder = builder.clone();
+        let tls = tls.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            match tls {
+                Some(tls) => match tls.accept(stream).await {
+                    Ok(stream) => serve_connection(builder, stream, service).await,
+                    Err(e) => warn!("TLS handshake with {} failed: {}", peer, e),
+                },
+                None => serve_connection(builder, stream, service).await,
+            }
+        });
+    }
+}
+
+#[cfg(unix)]
+async fn serve_unix(
+    router: Router,
+    builder: Builder<TokioExecutor>,
+    slots: Option<Arc<Semaphore>>,
+    path: &Path,
+) -> io::Result<()> {
+    remove_stale_socket(path)?;
+    let listener = tokio::net::UnixListener::bind(path)?;
+    loop {
+        let permit = match &slots {
+            Some(slots) => Some(slots.clone().acquire_owned().await.expect("connection slots closed")),
+            None => None,
+        };
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                warn!("Failed to accept a connection on unix:{}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let service = router.clone();
+        let builder = builder.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            serve_connection(builder, stream, service).await;
+        });
+    }
+}
+
+#[cfg(not(unix))]
+async fn serve_unix(_: Router, _: Builder<TokioExecutor>, _: Option<Arc<Semaphore>>, path: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("Unix domain sockets are not supported here: {}", path.display()),
+    ))
+}
+
+/// Remove a socket file a previous run left behind; anything else at `path`
+/// is left alone and fails the bind
+#[cfg(unix)]
+fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            info!("Removing stale socket {}", path.display());
+            std::fs::remove_file(path)
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn serve_connection<I, S>(builder: Builder<TokioExecutor>, io: I, service: S)
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: tower::Service<Request<Incoming>, Response = axum::response::Response, Error = std::convert::Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    let service = TowerToHyperService::new(service);
+    if let Err(e) = builder.serve_connection_with_upgrades(TokioIo::new(io), service).await {
+        // Clients dropping connections mid-request are routine
+        tracing::debug!("Connection closed with an error: {}", e);
+    }
+}
+
+fn configure(stream: &TcpStream, options: &ListenOptions) -> io::Result<()> {
+    stream.set_nodelay(options.tcp_nodelay)?;
+    if let Some(idle) = options.tcp_keepalive {
+        socket2::SockRef::from(stream).set_tcp_keepalive(&socket2::TcpKeepalive::new().with_time(idle))?;
+    }
+    Ok(())
+}
+
+/// TLS acceptor of a certificate chain and key, offering HTTP/2 and HTTP/1.1
+fn tls_acceptor(files: &TlsFiles) -> io::Result<TlsAcceptor> {
+    let certs = rustls_pemfile::certs(&mut io::BufReader::new(std::fs::File::open(&files.cert_path)?))
+        .collect::<Result<Vec<_>, _>>()?;
+    let key = rustls_pemfile::private_key(&mut io::BufReader::new(std::fs::File::open(&files.key_path)?))?
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("No private key in {}", files.key_path.display()),
+            )
+        })?;
+    let mut config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
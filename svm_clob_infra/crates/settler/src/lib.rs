@@ -0,0 +1,880 @@
+/// Settlement Crank for SVM CLOB Infrastructure
+///
+/// This module consumes matched trades from the engine journal, batches them into
+/// `execute_trade` transactions signed by the orderbook authority, and records the
+/// resulting on-chain signatures next to each trade. Every journal entry it reads
+/// is also written to the sequence map, which links order IDs and journal
+/// sequences to the PDAs and settlement signatures they correspond to on chain.
+/// A batch that still fails after every retry is stored as a dead letter, which
+/// an operator can edit and queue for resubmission through the admin API.
+/// Settlement transactions end with a memo naming the request IDs of the calls
+/// that matched their trades, so a client action can be found on chain too.
+/// Plain transfers into the market's vaults that the indexer found finalized
+/// are credited to their senders with `credit_transfer`. Busted trades are not
+/// settled and corrected ones settle at their corrected price; when an operator
+/// adjusts a trade already settled, `adjust_settlement` moves the difference
+/// back between its maker and taker. Once the market keeps a compressed receipt
+/// tree, every `execute_trade` passes it so the program can append the fill's
+/// receipts.
+
+use svm_clob_types::*;
+use svm_clob_types::program::{
+    adjust_settlement_instruction, credit_transfer_instruction, credited_transfer_address, execute_trade_instruction,
+    memo_instruction, receipt_tree_accounts, receipt_tree_address, settlement_adjustment_address, TradeArgs,
+};
+use svm_clob_types::accounts::ReceiptTreeView;
+use svm_clob_storage::Storage;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn, error, info_span, instrument, Instrument, Span};
+
+/// Checkpoint service name used by the settler
+pub const CHECKPOINT_SERVICE: &str = "settler";
+
+/// Dead letters resubmitted per poll
+const RESUBMIT_BATCH: u32 = 16;
+
+/// Longest settlement memo; request IDs that do not fit are left out
+const MAX_MEMO_LEN: usize = 128;
+
+/// Vault transfers credited per poll
+const CREDIT_BATCH: u32 = 16;
+
+/// Seconds after which a credit or a reversal that has not been read back
+/// from chain is checked, past the lifetime of its blockhash
+const CREDIT_TIMEOUT_SECS: i64 = 120;
+
+/// Trade adjustments looked at per poll
+const REVERSAL_BATCH: u32 = 16;
+
+/// Settler configuration
+#[derive(Debug, Clone)]
+pub struct SettlerConfig {
+    /// Solana JSON-RPC endpoint
+    pub rpc_url: String,
+    /// SVM CLOB program ID
+    pub program_id: Pubkey,
+    /// Orderbook PDA the trades settle against
+    pub orderbook: Pubkey,
+    /// Base and quote mints of the orderbook, whose vault transfers it credits
+    pub mints: [Pubkey; 2],
+    /// Commitment used for blockhashes and confirmation
+    pub commitment: CommitmentConfig,
+    /// Maximum `execute_trade` instructions per transaction
+    pub max_trades_per_transaction: usize,
+    /// Journal entries read per poll
+    pub journal_batch_size: u32,
+    /// Delay between polls when the journal is drained
+    pub poll_interval: Duration,
+    /// Submission retries per batch before giving up
+    pub max_retries: u32,
+    /// Base delay between retries, doubled on every attempt
+    pub retry_backoff: Duration,
+}
+
+/// Journaled trade awaiting settlement
+#[derive(Debug, Clone)]
+struct PendingTrade {
+    journal_sequence: u64,
+    trade: TradeExecution,
+    maker: Pubkey,
+    taker: Pubkey,
+    /// Trace of the request that matched the trade
+    trace_context: Option<String>,
+    /// ID of the request that matched the trade
+    request_id: Option<String>,
+}
+
+/// Outcome of submitting one batch
+struct Submission {
+    result: Result<Signature, String>,
+    attempts: u32,
+}
+
+/// Settlement crank that moves matched trades on-chain
+pub struct Settler<S: Storage> {
+    config: SettlerConfig,
+    storage: Arc<S>,
+    rpc: RpcClient,
+    authority: Keypair,
+    clock: Arc<dyn Clock>,
+}
+
+impl<S: Storage> Settler<S> {
+    /// Create a new settler signing with the orderbook authority
+    pub fn new(storage: Arc<S>, authority: Keypair, config: SettlerConfig) -> Self {
+        let rpc = RpcClient::new_with_commitment(config.rpc_url.clone(), config.commitment);
+        Self {
+            config,
+            storage,
+            rpc,
+            authority,
+            clock: system_clock(),
+        }
+    }
+
+    /// Timestamp dead letters from `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Run the crank until storage fails
+    pub async fn run(&self) -> ClobResult<()> {
+        info!("Settler signing as {}", self.authority.pubkey());
+
+        loop {
+            let consumed = self.settle_pending().await?;
+            let resubmitted = self.resubmit_dead_letters().await?;
+            let credited = self.credit_vault_transfers().await?;
+            let reversed = self.reverse_adjustments().await?;
+            if consumed == 0 && resubmitted == 0 && credited == 0 && reversed == 0 {
+                tokio::time::sleep(self.config.poll_interval).await;
+            }
+        }
+    }
+
+    /// Settle the next page of the journal, returning the number of entries consumed
+    pub async fn settle_pending(&self) -> ClobResult<usize> {
+        let position = self
+            .storage
+            .get_checkpoint(CHECKPOINT_SERVICE)
+            .await?
+            .map(|checkpoint| checkpoint.position)
+            .unwrap_or(0);
+
+        let entries = self
+            .storage
+            .get_journal_entries(position, self.config.journal_batch_size)
+            .await?;
+        let Some(last_sequence) = entries.last().map(|entry| entry.sequence) else {
+            return Ok(0);
+        };
+        let consumed = entries.len();
+
+        let mappings: Vec<SequenceMapping> = entries
+            .iter()
+            .flat_map(|entry| SequenceMapping::from_journal_entry(&self.config.program_id, entry))
+            .collect();
+        self.storage.store_sequence_mappings(&mappings).await?;
+
+        let pending: Vec<PendingTrade> = entries
+            .into_iter()
+            .filter_map(|entry| match entry.event {
+                JournalEvent::TradeExecuted { trade, maker, taker } => Some(PendingTrade {
+                    journal_sequence: entry.sequence,
+                    trade,
+                    maker,
+                    taker,
+                    trace_context: entry.trace_context,
+                    request_id: entry.request_id,
+                }),
+                _ => None,
+            })
+            .collect();
+
+        for batch in pending.chunks(self.config.max_trades_per_transaction.max(1)) {
+            self.settle_batch(batch).instrument(batch_span(batch)).await?;
+            self.save_checkpoint(batch[batch.len() - 1].journal_sequence).await?;
+        }
+
+        self.save_checkpoint(last_sequence).await?;
+        Ok(consumed)
+    }
+
+    /// Settle one batch, skipping trades already settled by a previous run
+    async fn settle_batch(&self, batch: &[PendingTrade]) -> ClobResult<()> {
+        let mut unsettled = Vec::with_capacity(batch.len());
+        for pending in batch {
+            if !self.already_settled(pending).await? {
+                unsettled.extend(self.adjusted(pending.clone()).await?);
+            }
+        }
+        if unsettled.is_empty() {
+            return Ok(());
+        }
+
+        let submission = self.submit(&unsettled).await?;
+        match submission.result {
+            Ok(signature) => {
+                info!("Settled {} trades in {}", unsettled.len(), signature);
+                self.record(&unsettled, Some(signature.to_string()), SettlementStatus::Confirmed, submission.attempts, None).await
+            }
+            Err(reason) => {
+                self.record(&unsettled, None, SettlementStatus::Failed, submission.attempts, Some(reason.clone())).await?;
+                let now = self.clock.now();
+                let id = self.storage.store_dead_letter(&DeadLetter {
+                    id: 0,
+                    first_sequence: unsettled[0].journal_sequence,
+                    last_sequence: unsettled[unsettled.len() - 1].journal_sequence,
+                    trades: unsettled.iter().map(dead_letter_trade).collect(),
+                    status: DeadLetterStatus::Open,
+                    reason: reason.clone(),
+                    attempts: submission.attempts,
+                    signature: None,
+                    created_at: now,
+                    updated_at: now,
+                }).await?;
+                error!(
+                    "Giving up on settlement of journal entries {}..={}, dead letter {}: {}",
+                    unsettled[0].journal_sequence,
+                    unsettled[unsettled.len() - 1].journal_sequence,
+                    id,
+                    reason
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Submit the dead letters an operator queued for resubmission, returning
+    /// how many were tried; one that fails again goes back to the operator
+    pub async fn resubmit_dead_letters(&self) -> ClobResult<usize> {
+        let queued = self
+            .storage
+            .get_dead_letters(Some(DeadLetterStatus::Resubmitting), &Paginated::first(RESUBMIT_BATCH))
+            .await?;
+        let tried = queued.items.len();
+
+        for mut letter in queued.items {
+            let batch: Vec<PendingTrade> = letter.trades.iter().cloned().map(PendingTrade::from).collect();
+            let mut unsettled = Vec::with_capacity(batch.len());
+            for pending in batch {
+                if !self.already_settled(&pending).await? {
+                    unsettled.extend(self.adjusted(pending).await?);
+                }
+            }
+
+            if unsettled.is_empty() {
+                letter.status = DeadLetterStatus::Settled;
+            } else {
+                let submission = self.submit(&unsettled).await?;
+                letter.attempts += submission.attempts;
+                match submission.result {
+                    Ok(signature) => {
+                        info!("Settled dead letter {} in {}", letter.id, signature);
+                        self.record(&unsettled, Some(signature.to_string()), SettlementStatus::Confirmed, submission.attempts, None).await?;
+                        letter.status = DeadLetterStatus::Settled;
+                        letter.signature = Some(signature.to_string());
+                    }
+                    Err(reason) => {
+                        error!("Resubmission of dead letter {} failed: {}", letter.id, reason);
+                        self.record(&unsettled, None, SettlementStatus::Failed, submission.attempts, Some(reason.clone())).await?;
+                        letter.status = DeadLetterStatus::Open;
+                        letter.reason = reason;
+                    }
+                }
+            }
+
+            letter.updated_at = self.clock.now();
+            if !self.storage.update_dead_letter(&letter, DeadLetterStatus::Resubmitting).await? {
+                warn!("Dead letter {} changed while it was resubmitted", letter.id);
+            }
+        }
+        Ok(tried)
+    }
+
+    /// Credit the plain transfers into the market's vaults the indexer found
+    /// ready, returning how many were sent; credits sent before that were
+    /// not read back from chain in time are checked and sent again if they
+    /// never landed
+    pub async fn credit_vault_transfers(&self) -> ClobResult<usize> {
+        let now = self.clock.now();
+        let submitted = self
+            .storage
+            .get_vault_transfers_by_status(VaultTransferStatus::Submitted, CREDIT_BATCH)
+            .await?;
+        for transfer in submitted {
+            if now - transfer.updated_at < CREDIT_TIMEOUT_SECS {
+                continue;
+            }
+            let status = match transfer.credit_signature.as_deref().map(Signature::from_str) {
+                Some(Ok(signature)) => match self.rpc.get_signature_status(&signature).await {
+                    Ok(status) => status,
+                    Err(e) => {
+                        warn!("Failed to check the credit of {}: {}", transfer.signature, e);
+                        continue;
+                    }
+                },
+                _ => None,
+            };
+            // A credit that landed is marked once the indexer reads it
+            let updated = match status {
+                Some(Ok(())) => continue,
+                Some(Err(e)) => VaultTransfer {
+                    status: VaultTransferStatus::Rejected,
+                    reason: Some(e.to_string()),
+                    updated_at: now,
+                    ..transfer
+                },
+                None => VaultTransfer {
+                    status: VaultTransferStatus::Ready,
+                    credit_signature: None,
+                    updated_at: now,
+                    ..transfer
+                },
+            };
+            self.storage.update_vault_transfer(&updated).await?;
+        }
+
+        let ready = self
+            .storage
+            .get_vault_transfers_by_status(VaultTransferStatus::Ready, CREDIT_BATCH)
+            .await?;
+        let mut sent = 0;
+        for transfer in ready {
+            // Another market's settler credits the vaults of its own mints
+            if self.config.mints.contains(&transfer.mint) && self.credit(transfer).await? {
+                sent += 1;
+            }
+        }
+        Ok(sent)
+    }
+
+    /// Send the `credit_transfer` of one ready vault transfer, returning
+    /// whether it was sent
+    async fn credit(&self, transfer: VaultTransfer) -> ClobResult<bool> {
+        let transfer_signature: [u8; 64] = match Signature::from_str(&transfer.signature) {
+            Ok(signature) => signature.as_ref().try_into().expect("signatures are 64 bytes"),
+            Err(e) => {
+                error!("Vault transfer {} has an invalid signature: {}", transfer.signature, e);
+                return Ok(false);
+            }
+        };
+        let instruction = credit_transfer_instruction(
+            &self.config.program_id,
+            &self.config.orderbook,
+            &self.authority.pubkey(),
+            &transfer.sender,
+            &transfer.mint,
+            &transfer_signature,
+            transfer.instruction_index,
+            transfer.amount,
+        );
+        let blockhash = match self.rpc.get_latest_blockhash().await {
+            Ok(blockhash) => blockhash,
+            Err(e) => {
+                warn!("Failed to credit {}: {}", transfer.signature, e);
+                return Ok(false);
+            }
+        };
+        let tx = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.authority.pubkey()),
+            &[&self.authority],
+            blockhash,
+        );
+        // Recorded before sending, so a restart checks it rather than
+        // crediting twice; the record on chain refuses a second credit anyway
+        let submitted = VaultTransfer {
+            status: VaultTransferStatus::Submitted,
+            credit_signature: Some(tx.signatures[0].to_string()),
+            updated_at: self.clock.now(),
+            ..transfer
+        };
+        self.storage.update_vault_transfer(&submitted).await?;
+
+        match self.rpc.send_and_confirm_transaction(&tx).await {
+            Ok(signature) => {
+                info!(
+                    "Credited {} of {} to {} for transfer {} in {}",
+                    submitted.amount, submitted.mint, submitted.sender, submitted.signature, signature
+                );
+                Ok(true)
+            }
+            Err(e) => {
+                let Some(tx_error) = e.get_transaction_error() else {
+                    // Checked again once its blockhash has expired
+                    warn!("Credit of {} not confirmed: {}", submitted.signature, e);
+                    return Ok(true);
+                };
+                let (record, _) = credited_transfer_address(
+                    &self.config.program_id,
+                    &transfer_signature,
+                    submitted.instruction_index,
+                );
+                if matches!(self.rpc.get_account(&record).await, Ok(_)) {
+                    info!("Transfer {} was already credited", submitted.signature);
+                    return Ok(true);
+                }
+                error!("Failed to credit transfer {}: {}", submitted.signature, tx_error);
+                self.storage.update_vault_transfer(&VaultTransfer {
+                    status: VaultTransferStatus::Rejected,
+                    reason: Some(tx_error.to_string()),
+                    updated_at: self.clock.now(),
+                    ..submitted
+                }).await?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Reverse on chain what adjustments of settled trades move back,
+    /// returning how many were sent; adjustments of trades that settle as
+    /// adjusted, or never settle, need nothing, and reversals sent before
+    /// that were not read back from chain in time are checked and sent again
+    /// if they never landed
+    pub async fn reverse_adjustments(&self) -> ClobResult<usize> {
+        let now = self.clock.now();
+        let submitted = self
+            .storage
+            .get_trade_adjustments_by_reversal(ReversalStatus::Submitted, REVERSAL_BATCH)
+            .await?;
+        for adjustment in submitted {
+            if now - adjustment.updated_at < CREDIT_TIMEOUT_SECS {
+                continue;
+            }
+            let status = match adjustment.reversal_signature.as_deref().map(Signature::from_str) {
+                Some(Ok(signature)) => match self.rpc.get_signature_status(&signature).await {
+                    Ok(status) => status,
+                    Err(e) => {
+                        warn!("Failed to check the reversal of adjustment {}: {}", adjustment.adjustment_id, e);
+                        continue;
+                    }
+                },
+                _ => None,
+            };
+            // A reversal that landed is marked once the indexer reads it
+            let updated = match status {
+                Some(Ok(())) => continue,
+                Some(Err(e)) => TradeAdjustment {
+                    reversal: ReversalStatus::Failed,
+                    reversal_error: Some(e.to_string()),
+                    updated_at: now,
+                    ..adjustment
+                },
+                None => TradeAdjustment {
+                    reversal: ReversalStatus::Pending,
+                    reversal_signature: None,
+                    updated_at: now,
+                    ..adjustment
+                },
+            };
+            self.storage.update_trade_adjustment(&updated).await?;
+        }
+
+        let position = self
+            .storage
+            .get_checkpoint(CHECKPOINT_SERVICE)
+            .await?
+            .map(|checkpoint| checkpoint.position)
+            .unwrap_or(0);
+        let pending = self
+            .storage
+            .get_trade_adjustments_by_reversal(ReversalStatus::Pending, REVERSAL_BATCH)
+            .await?;
+        let mut sent = 0;
+        for adjustment in pending {
+            let settlement = self.storage.get_trade_settlement(adjustment.trade_sequence).await?;
+            let reversal = match settlement {
+                // Settled before the adjustment: its balances move back
+                Some(settlement) if settlement.status == SettlementStatus::Confirmed => {
+                    if adjustment.version <= settlement.version {
+                        Some(ReversalStatus::NotRequired)
+                    } else {
+                        self.reversal_blocked(&adjustment, settlement.version).await?
+                    }
+                }
+                // Still in flight, at a version only known once it lands
+                Some(settlement) if settlement.status == SettlementStatus::Submitted => continue,
+                // A dead letter settles the trade as it stands when resubmitted
+                Some(_) => Some(ReversalStatus::NotRequired),
+                // Not settled yet, or skipped by the settler as busted
+                None if position >= adjustment.trade_sequence => Some(ReversalStatus::NotRequired),
+                None => continue,
+            };
+            match reversal {
+                // Waits for an earlier adjustment of the trade
+                Some(ReversalStatus::Pending) => continue,
+                Some(reversal) => {
+                    let failed = reversal == ReversalStatus::Failed;
+                    if failed {
+                        error!(
+                            "Adjustment {} of trade {} cannot be reversed: an earlier adjustment failed",
+                            adjustment.adjustment_id, adjustment.trade_id
+                        );
+                    }
+                    self.storage.update_trade_adjustment(&TradeAdjustment {
+                        reversal,
+                        reversal_error: failed.then(|| "an earlier adjustment of the trade failed to reverse".to_string()),
+                        updated_at: self.clock.now(),
+                        ..adjustment
+                    }).await?;
+                }
+                None => {
+                    if self.reverse(adjustment).await? {
+                        sent += 1;
+                    }
+                }
+            }
+        }
+        Ok(sent)
+    }
+
+    /// Whether an adjustment of a trade settled at `settled_version` must
+    /// wait for (`Pending`), or fail with (`Failed`), an earlier adjustment;
+    /// `None` when it can be sent
+    async fn reversal_blocked(&self, adjustment: &TradeAdjustment, settled_version: u32) -> ClobResult<Option<ReversalStatus>> {
+        let earlier = self.storage.get_trade_adjustments(adjustment.trade_id).await?;
+        for previous in earlier
+            .iter()
+            .filter(|previous| previous.version > settled_version && previous.version < adjustment.version)
+        {
+            match previous.reversal {
+                ReversalStatus::Reversed | ReversalStatus::NotRequired => {}
+                ReversalStatus::Failed => return Ok(Some(ReversalStatus::Failed)),
+                // Sent in order, so the balances it moves are there first
+                ReversalStatus::Pending | ReversalStatus::Submitted => return Ok(Some(ReversalStatus::Pending)),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Send the `adjust_settlement` of one adjustment, returning whether it
+    /// was sent
+    async fn reverse(&self, adjustment: TradeAdjustment) -> ClobResult<bool> {
+        let instruction = adjust_settlement_instruction(
+            &self.config.program_id,
+            &self.config.orderbook,
+            &self.authority.pubkey(),
+            &adjustment.maker,
+            &adjustment.taker,
+            adjustment.adjustment_id,
+            adjustment.trade_id,
+            adjustment.base_to_maker,
+            adjustment.quote_to_maker,
+        );
+        let blockhash = match self.rpc.get_latest_blockhash().await {
+            Ok(blockhash) => blockhash,
+            Err(e) => {
+                warn!("Failed to reverse adjustment {}: {}", adjustment.adjustment_id, e);
+                return Ok(false);
+            }
+        };
+        let tx = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.authority.pubkey()),
+            &[&self.authority],
+            blockhash,
+        );
+        // Recorded before sending, so a restart checks it rather than
+        // reversing twice; the record on chain refuses a second reversal anyway
+        let submitted = TradeAdjustment {
+            reversal: ReversalStatus::Submitted,
+            reversal_signature: Some(tx.signatures[0].to_string()),
+            updated_at: self.clock.now(),
+            ..adjustment
+        };
+        self.storage.update_trade_adjustment(&submitted).await?;
+
+        match self.rpc.send_and_confirm_transaction(&tx).await {
+            Ok(signature) => {
+                info!(
+                    "Reversed adjustment {} of trade {}: {} base and {} quote to the maker in {}",
+                    submitted.adjustment_id, submitted.trade_id, submitted.base_to_maker, submitted.quote_to_maker, signature
+                );
+                Ok(true)
+            }
+            Err(e) => {
+                let Some(tx_error) = e.get_transaction_error() else {
+                    // Checked again once its blockhash has expired
+                    warn!("Reversal of adjustment {} not confirmed: {}", submitted.adjustment_id, e);
+                    return Ok(true);
+                };
+                let (record, _) = settlement_adjustment_address(
+                    &self.config.program_id,
+                    &self.config.orderbook,
+                    submitted.adjustment_id,
+                );
+                if matches!(self.rpc.get_account(&record).await, Ok(_)) {
+                    info!("Adjustment {} was already reversed", submitted.adjustment_id);
+                    return Ok(true);
+                }
+                // Typically a party no longer holds what it gives back; an
+                // operator settles it with them
+                error!("Failed to reverse adjustment {}: {}", submitted.adjustment_id, tx_error);
+                self.storage.update_trade_adjustment(&TradeAdjustment {
+                    reversal: ReversalStatus::Failed,
+                    reversal_error: Some(tx_error.to_string()),
+                    updated_at: self.clock.now(),
+                    ..submitted
+                }).await?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Bring a journaled trade up to its latest adjustment; `None` when it
+    /// was busted and settles nothing
+    async fn adjusted(&self, mut pending: PendingTrade) -> ClobResult<Option<PendingTrade>> {
+        let adjustments = self.storage.get_trade_adjustments(pending.trade.trade_id).await?;
+        let Some(latest) = adjustments.last() else {
+            return Ok(Some(pending));
+        };
+        if latest.status == TradeStatus::Busted {
+            info!("Trade {} was busted, not settling it", pending.trade.trade_id);
+            return Ok(None);
+        }
+        pending.trade.price = latest.price;
+        pending.trade.version = latest.version;
+        pending.trade.status = latest.status;
+        Ok(Some(pending))
+    }
+
+    /// Check whether a trade was settled before a restart
+    async fn already_settled(&self, pending: &PendingTrade) -> ClobResult<bool> {
+        let Some(settlement) = self.storage.get_trade_settlement(pending.journal_sequence).await? else {
+            return Ok(false);
+        };
+
+        match (settlement.status, settlement.signature.as_deref()) {
+            (SettlementStatus::Confirmed, _) => Ok(true),
+            (SettlementStatus::Submitted, Some(signature)) => {
+                let landed = match Signature::from_str(signature) {
+                    Ok(signature) => matches!(self.rpc.get_signature_status(&signature).await, Ok(Some(Ok(())))),
+                    Err(_) => false,
+                };
+                if landed {
+                    let mut confirmed = settlement;
+                    confirmed.status = SettlementStatus::Confirmed;
+                    self.storage.store_trade_settlement(&confirmed).await?;
+                }
+                Ok(landed)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Sign and send a batch, handling blockhash expiry and transient failures
+    #[instrument(name = "settler.submit", skip_all, fields(trades = batch.len()))]
+    async fn submit(&self, batch: &[PendingTrade]) -> ClobResult<Submission> {
+        let receipt_tree = self.receipt_tree_accounts(batch.len()).await?;
+        let mut instructions = batch
+            .iter()
+            .map(|pending| {
+                let mut instruction = execute_trade_instruction(
+                    &self.config.program_id,
+                    &self.config.orderbook,
+                    &self.authority.pubkey(),
+                    &trade_args(pending),
+                )?;
+                instruction.accounts.extend(receipt_tree.iter().cloned());
+                Ok(instruction)
+            })
+            .collect::<ClobResult<Vec<_>>>()?;
+        if let Some(memo) = request_memo(batch) {
+            instructions.push(memo_instruction(&memo));
+        }
+
+        let mut transaction: Option<Transaction> = None;
+        let mut last_error = String::new();
+        let mut attempts = 0;
+
+        while attempts <= self.config.max_retries {
+            attempts += 1;
+
+            // Resending the same signed transaction is idempotent; re-signing is
+            // only safe once its blockhash has expired and it provably did not land.
+            let expired = match &transaction {
+                Some(tx) => !self
+                    .rpc
+                    .is_blockhash_valid(&tx.message.recent_blockhash, self.config.commitment)
+                    .await
+                    .unwrap_or(true),
+                None => true,
+            };
+
+            if expired {
+                if let Some(tx) = &transaction {
+                    match self.rpc.get_signature_status(&tx.signatures[0]).await {
+                        Ok(Some(Ok(()))) => return Ok(Submission { result: Ok(tx.signatures[0]), attempts }),
+                        Ok(Some(Err(e))) => return Ok(Submission { result: Err(e.to_string()), attempts }),
+                        Ok(None) => warn!("Blockhash expired before {} landed, re-signing", tx.signatures[0]),
+                        Err(e) => {
+                            last_error = e.to_string();
+                            self.backoff(attempts).await;
+                            continue;
+                        }
+                    }
+                }
+
+                let blockhash = match self.rpc.get_latest_blockhash().await {
+                    Ok(blockhash) => blockhash,
+                    Err(e) => {
+                        last_error = e.to_string();
+                        self.backoff(attempts).await;
+                        continue;
+                    }
+                };
+                let tx = Transaction::new_signed_with_payer(
+                    &instructions,
+                    Some(&self.authority.pubkey()),
+                    &[&self.authority],
+                    blockhash,
+                );
+                self.record(batch, Some(tx.signatures[0].to_string()), SettlementStatus::Submitted, attempts, None).await?;
+                transaction = Some(tx);
+            }
+
+            let tx = transaction.as_ref().expect("transaction signed above");
+            match self.rpc.send_and_confirm_transaction(tx).await {
+                Ok(signature) => return Ok(Submission { result: Ok(signature), attempts }),
+                Err(e) => {
+                    // Program errors will fail identically on every retry
+                    if let Some(tx_error) = e.get_transaction_error() {
+                        return Ok(Submission { result: Err(tx_error.to_string()), attempts });
+                    }
+                    warn!("Settlement attempt {} failed: {}", attempts, e);
+                    last_error = e.to_string();
+                    self.backoff(attempts).await;
+                }
+            }
+        }
+
+        Ok(Submission { result: Err(last_error), attempts })
+    }
+
+    /// Accounts of the market's compressed receipt tree for a batch of
+    /// `trades`; none while the market keeps no tree. A full tree fails the
+    /// batch on chain until an operator starts a new one.
+    async fn receipt_tree_accounts(&self, trades: usize) -> ClobResult<Vec<AccountMeta>> {
+        let (address, _) = receipt_tree_address(&self.config.program_id, &self.config.orderbook);
+        let account = self
+            .rpc
+            .get_account_with_commitment(&address, self.config.commitment)
+            .await
+            .map_err(|e| ClobError::NetworkError(e.to_string()))?
+            .value;
+        let Some(account) = account else {
+            return Ok(Vec::new());
+        };
+        let tree = ReceiptTreeView::new(&account.data)?;
+        if tree.leaves() + 2 * trades as u64 > tree.capacity() {
+            warn!(
+                "Receipt tree {} holds {} of {} receipts; start a new one with initialize_receipt_tree",
+                tree.merkle_tree(),
+                tree.leaves(),
+                tree.capacity()
+            );
+        }
+        Ok(receipt_tree_accounts(&self.config.program_id, &self.config.orderbook, &tree.merkle_tree()))
+    }
+
+    /// Record the settlement state of every trade in a batch
+    async fn record(
+        &self,
+        batch: &[PendingTrade],
+        signature: Option<String>,
+        status: SettlementStatus,
+        attempts: u32,
+        error: Option<String>,
+    ) -> ClobResult<()> {
+        for pending in batch {
+            self.storage.store_trade_settlement(&TradeSettlement {
+                journal_sequence: pending.journal_sequence,
+                maker_order_id: pending.trade.maker_order_id,
+                taker_order_id: pending.trade.taker_order_id,
+                signature: signature.clone(),
+                status,
+                attempts,
+                error: error.clone(),
+                version: pending.trade.version,
+            }).await?;
+        }
+        Ok(())
+    }
+
+    async fn save_checkpoint(&self, sequence: u64) -> ClobResult<()> {
+        self.storage.save_checkpoint(&Checkpoint {
+            service: CHECKPOINT_SERVICE.to_string(),
+            position: sequence,
+            cursor: None,
+        }).await
+    }
+
+    async fn backoff(&self, attempt: u32) {
+        let delay = self.config.retry_backoff * 2u32.saturating_pow(attempt.saturating_sub(1));
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Span for settling a batch, continuing the originating trace of a single trade
+/// and linking to every trace when several trades share a transaction
+fn batch_span(batch: &[PendingTrade]) -> Span {
+    let span = info_span!("settler.settle_batch", trades = batch.len());
+    match batch {
+        [PendingTrade { trace_context: Some(traceparent), .. }] => {
+            svm_clob_telemetry::set_remote_parent(&span, svm_clob_telemetry::context_from_traceparent(traceparent));
+        }
+        _ => {
+            for traceparent in batch.iter().filter_map(|pending| pending.trace_context.as_deref()) {
+                svm_clob_telemetry::link_traceparent(&span, traceparent);
+            }
+        }
+    }
+    span
+}
+
+impl From<DeadLetterTrade> for PendingTrade {
+    fn from(trade: DeadLetterTrade) -> Self {
+        Self {
+            journal_sequence: trade.journal_sequence,
+            trade: trade.trade,
+            maker: trade.maker,
+            taker: trade.taker,
+            trace_context: None,
+            request_id: trade.request_id,
+        }
+    }
+}
+
+fn dead_letter_trade(pending: &PendingTrade) -> DeadLetterTrade {
+    DeadLetterTrade {
+        journal_sequence: pending.journal_sequence,
+        trade: pending.trade.clone(),
+        maker: pending.maker,
+        taker: pending.taker,
+        request_id: pending.request_id.clone(),
+    }
+}
+
+/// Memo of a settlement transaction, `req:<id>,<id>...`, naming each request
+/// that matched a trade of the batch once, as many as fit in `MAX_MEMO_LEN`
+fn request_memo(batch: &[PendingTrade]) -> Option<String> {
+    let mut memo = String::from("req:");
+    let mut named: Vec<&str> = Vec::new();
+    for request_id in batch.iter().filter_map(|pending| pending.request_id.as_deref()) {
+        if named.contains(&request_id) {
+            continue;
+        }
+        let separator = if named.is_empty() { "" } else { "," };
+        if memo.len() + separator.len() + request_id.len() > MAX_MEMO_LEN {
+            break;
+        }
+        memo.push_str(separator);
+        memo.push_str(request_id);
+        named.push(request_id);
+    }
+    (!named.is_empty()).then_some(memo)
+}
+
+/// Convert a journaled trade into the on-chain `Trade` argument
+fn trade_args(pending: &PendingTrade) -> TradeArgs {
+    TradeArgs {
+        taker_order_id: pending.trade.taker_order_id,
+        maker_order_id: pending.trade.maker_order_id,
+        taker: pending.taker.to_bytes(),
+        maker: pending.maker.to_bytes(),
+        price: pending.trade.price,
+        quantity: pending.trade.quantity,
+        taker_side: pending.trade.maker_side.opposite(),
+        timestamp: pending.trade.timestamp,
+    }
+}
@@ -0,0 +1,255 @@
+/// Order flow sources
+///
+/// Turns engine journals, historical trades or a seeded random process into a
+/// time-ordered stream of commands for the simulator. All timestamps are
+/// virtual milliseconds.
+
+use svm_clob_types::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use solana_sdk::pubkey::Pubkey;
+
+/// Command applied to the engine
+#[derive(Debug, Clone)]
+pub enum SimCommand {
+    /// Submit an order
+    Place(Order),
+    /// Cancel a resting order
+    Cancel(u64),
+}
+
+/// Command scheduled at a virtual time
+#[derive(Debug, Clone)]
+pub struct SimEvent {
+    /// Virtual time in milliseconds
+    pub timestamp: i64,
+    pub command: SimCommand,
+}
+
+/// Re-issue the commands recorded in an engine journal
+///
+/// Fills are not replayed; they are re-derived by matching the replayed orders.
+pub fn events_from_journal(entries: &[JournalEntry]) -> Vec<SimEvent> {
+    let mut events = Vec::new();
+    for entry in entries {
+        let timestamp = entry.timestamp * 1000;
+        match &entry.event {
+            JournalEvent::OrderPlaced { order } => events.push(SimEvent {
+                timestamp,
+                command: SimCommand::Place(order.clone()),
+            }),
+            JournalEvent::OrderCancelled { order_id } | JournalEvent::OrderExpired { order_id, .. } => events.push(SimEvent {
+                timestamp,
+                command: SimCommand::Cancel(*order_id),
+            }),
+            JournalEvent::OrderReplaced { original_order_id, order } => {
+                events.push(SimEvent { timestamp, command: SimCommand::Cancel(*original_order_id) });
+                events.push(SimEvent { timestamp, command: SimCommand::Place(order.clone()) });
+            }
+            // The protection is not configured in the simulation, so its
+            // pulls are replayed as cancels
+            JournalEvent::MmpTriggered { order_ids, .. } => events.extend(
+                order_ids.iter().map(|order_id| SimEvent { timestamp, command: SimCommand::Cancel(*order_id) }),
+            ),
+            JournalEvent::TradeExecuted { .. }
+            | JournalEvent::OrderIdsReserved { .. }
+            | JournalEvent::TradeAdjusted { .. } => {}
+        }
+    }
+    events
+}
+
+/// Recreate historical trades as a resting maker order hit by an IOC taker
+///
+/// `maker` and `taker` own every synthesized order; they must differ so
+/// self-trade prevention does not interfere. Order IDs start at `first_order_id`.
+pub fn events_from_trades(
+    trades: &[TradeExecution],
+    maker: Pubkey,
+    taker: Pubkey,
+    first_order_id: u64,
+) -> Vec<SimEvent> {
+    let mut order_id = first_order_id;
+    let mut events = Vec::with_capacity(trades.len() * 2);
+    for trade in trades {
+        let timestamp = trade.timestamp * 1000;
+        let taker_side = match trade.maker_side {
+            OrderSide::Bid => OrderSide::Ask,
+            OrderSide::Ask => OrderSide::Bid,
+        };
+        for (owner, side, time_in_force) in [
+            (maker, trade.maker_side, TimeInForce::GoodTillCancelled),
+            (taker, taker_side, TimeInForce::ImmediateOrCancel),
+        ] {
+            events.push(SimEvent {
+                timestamp,
+                command: SimCommand::Place(limit_order(order_id, owner, side, trade.price, trade.quantity, trade.timestamp, time_in_force)),
+            });
+            order_id += 1;
+        }
+    }
+    events
+}
+
+/// Parameters of the synthetic order flow
+#[derive(Debug, Clone)]
+pub struct SyntheticFlowConfig {
+    /// RNG seed; equal seeds produce identical flows
+    pub seed: u64,
+    /// Number of events to generate
+    pub events: usize,
+    /// Virtual start time in milliseconds
+    pub start_time: i64,
+    /// Initial fair price
+    pub start_mid: u64,
+    /// Market tick size
+    pub tick_size: u64,
+    /// Market minimum order size; quantities are multiples of it
+    pub min_order_size: u64,
+    /// Standard deviation of the fair price move per event, in basis points
+    pub volatility_bps: u64,
+    /// Mean time between events in milliseconds
+    pub mean_interarrival_ms: u64,
+    /// Share of events that are market orders
+    pub market_order_ratio: f64,
+    /// Share of events that cancel a random resting order
+    pub cancel_ratio: f64,
+    /// Largest limit distance from the fair price, in ticks
+    pub max_depth_ticks: u64,
+    /// Largest order quantity in multiples of `min_order_size`
+    pub max_lots: u64,
+    /// Number of distinct trader wallets
+    pub traders: usize,
+}
+
+/// Seeded random order flow around a random-walk fair price
+pub struct SyntheticFlow {
+    config: SyntheticFlowConfig,
+    rng: StdRng,
+    traders: Vec<Pubkey>,
+    fair_price: f64,
+    clock: i64,
+    next_order_id: u64,
+    /// Limit orders that may still be resting, candidates for cancels
+    resting: Vec<u64>,
+    generated: usize,
+}
+
+impl SyntheticFlow {
+    /// Create a flow; order IDs start at 1
+    pub fn new(config: SyntheticFlowConfig) -> Self {
+        let mut rng = StdRng::seed_from_u64(config.seed);
+        let traders = (0..config.traders.max(2))
+            .map(|_| Pubkey::new_from_array(rng.gen()))
+            .collect();
+        Self {
+            fair_price: config.start_mid as f64,
+            clock: config.start_time,
+            rng,
+            traders,
+            next_order_id: 1,
+            resting: Vec::new(),
+            generated: 0,
+            config,
+        }
+    }
+
+    /// Wallets submitting the flow
+    pub fn traders(&self) -> &[Pubkey] {
+        &self.traders
+    }
+
+    /// Standard normal sample (Box-Muller)
+    fn gaussian(&mut self) -> f64 {
+        let u1: f64 = self.rng.gen_range(f64::EPSILON..1.0);
+        let u2: f64 = self.rng.gen();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+
+    fn next_order(&mut self) -> Order {
+        let order_id = self.next_order_id;
+        self.next_order_id += 1;
+
+        let owner = self.traders[self.rng.gen_range(0..self.traders.len())];
+        let side = if self.rng.gen_bool(0.5) { OrderSide::Bid } else { OrderSide::Ask };
+        let quantity = self.rng.gen_range(1..=self.config.max_lots.max(1)) * self.config.min_order_size.max(1);
+        let tick = self.config.tick_size.max(1);
+
+        if self.rng.gen_bool(self.config.market_order_ratio.clamp(0.0, 1.0)) {
+            let mut order = limit_order(order_id, owner, side, 0, quantity, self.clock / 1000, TimeInForce::ImmediateOrCancel);
+            order.order_type = OrderType::Market;
+            return order;
+        }
+
+        // Passive orders rest behind the fair price, aggressive ones cross it
+        let depth = self.rng.gen_range(0..=self.config.max_depth_ticks) as f64 * tick as f64;
+        let aggressive = self.rng.gen_bool(0.2);
+        let offset = if aggressive { -depth } else { depth };
+        let raw = match side {
+            OrderSide::Bid => self.fair_price - offset,
+            OrderSide::Ask => self.fair_price + offset,
+        };
+        let price = ((raw.max(tick as f64) / tick as f64).round() as u64).max(1) * tick;
+
+        self.resting.push(order_id);
+        limit_order(order_id, owner, side, price, quantity, self.clock / 1000, TimeInForce::GoodTillCancelled)
+    }
+}
+
+impl Iterator for SyntheticFlow {
+    type Item = SimEvent;
+
+    fn next(&mut self) -> Option<SimEvent> {
+        if self.generated >= self.config.events {
+            return None;
+        }
+        self.generated += 1;
+
+        // Exponential inter-arrival times give a Poisson event process
+        let u: f64 = self.rng.gen_range(f64::EPSILON..1.0);
+        self.clock += (-u.ln() * self.config.mean_interarrival_ms as f64) as i64;
+
+        let shock = self.gaussian() * self.config.volatility_bps as f64 / 10_000.0;
+        self.fair_price = (self.fair_price * (1.0 + shock)).max(self.config.tick_size.max(1) as f64);
+
+        let command = if !self.resting.is_empty() && self.rng.gen_bool(self.config.cancel_ratio.clamp(0.0, 1.0)) {
+            let index = self.rng.gen_range(0..self.resting.len());
+            SimCommand::Cancel(self.resting.swap_remove(index))
+        } else {
+            SimCommand::Place(self.next_order())
+        };
+
+        Some(SimEvent { timestamp: self.clock, command })
+    }
+}
+
+fn limit_order(
+    order_id: u64,
+    owner: Pubkey,
+    side: OrderSide,
+    price: u64,
+    quantity: u64,
+    timestamp: i64,
+    time_in_force: TimeInForce,
+) -> Order {
+    Order {
+        order_id,
+        market_id: MarketId::default(),
+        owner,
+        price,
+        quantity,
+        remaining_quantity: quantity,
+        timestamp,
+        client_order_id: order_id,
+        expiry_timestamp: 0,
+        side,
+        order_type: OrderType::Limit,
+        status: OrderStatus::Open,
+        self_trade_behavior: SelfTradeBehavior::CancelTake,
+        prevention_group_id: None,
+        time_in_force,
+        max_slippage_bps: None,
+        filled_notional: Notional::default(),
+        avg_fill_price: None,
+    }
+}
@@ -0,0 +1,351 @@
+/// Market Simulator for SVM CLOB Infrastructure
+///
+/// This module replays journaled or historical order flow, or seeded synthetic
+/// flow, through the real matching engine on a virtual clock, optionally with a
+/// quoting strategy participating, and reports fills and P&L per wallet. Equal
+/// inputs produce equal reports, so a report diff doubles as a regression check
+/// for engine changes.
+
+use svm_clob_types::*;
+use svm_clob_matching_engine::MatchingEngine;
+use svm_clob_mm::{mid_price, QuoteContext, QuotingStrategy};
+use svm_clob_storage::InMemoryStorage;
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tracing::{debug, info};
+
+pub mod flow;
+pub mod replay;
+
+pub use flow::{events_from_journal, events_from_trades, SimCommand, SimEvent, SyntheticFlow, SyntheticFlowConfig};
+pub use replay::{diff_fills, load_journal, FillDivergence, ReplayReport, ReplayStep, Replayer};
+
+/// Order IDs handed to the strategy start here to stay clear of replayed IDs
+const STRATEGY_ORDER_ID_BASE: u64 = 1 << 62;
+
+/// Quoting strategy taking part in a simulation
+pub struct SimStrategy {
+    /// Strategy deciding the quotes
+    pub strategy: Box<dyn QuotingStrategy>,
+    /// Wallet the strategy trades for
+    pub owner: Pubkey,
+    /// Virtual milliseconds between quote refreshes
+    pub refresh_interval_ms: i64,
+    /// Mid price used while the book has no two-sided market and nothing has traded
+    pub fallback_mid: Option<u64>,
+}
+
+/// Fill observed during a simulation
+#[derive(Serialize, Debug, Clone)]
+pub struct SimFill {
+    /// Virtual time in milliseconds
+    pub timestamp: i64,
+    pub maker_order_id: u64,
+    pub taker_order_id: u64,
+    pub maker: String,
+    pub taker: String,
+    pub maker_side: OrderSide,
+    pub price: u64,
+    pub quantity: u64,
+}
+
+/// Per-wallet outcome
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct AccountReport {
+    pub owner: String,
+    /// Net base position (positive = long)
+    pub position: i64,
+    /// Net quote flow from fills
+    pub cash: i128,
+    /// `cash + position * mark_price`
+    pub pnl: i128,
+    /// Base quantity traded
+    pub volume: u64,
+    pub fills: u64,
+    /// Fills where the wallet provided liquidity
+    pub maker_fills: u64,
+}
+
+/// Result of a simulation run
+#[derive(Serialize, Debug, Clone)]
+pub struct SimReport {
+    /// Virtual time of the first and last event, in milliseconds
+    pub start_time: i64,
+    pub end_time: i64,
+    pub events_processed: u64,
+    pub orders_placed: u64,
+    pub orders_cancelled: u64,
+    /// Rejected commands by engine error
+    pub rejections: BTreeMap<String, u64>,
+    /// Price positions are marked at: the last trade, else the final mid
+    pub mark_price: Option<u64>,
+    pub total_volume: u64,
+    pub accounts: Vec<AccountReport>,
+    pub fills: Vec<SimFill>,
+    pub final_book: OrderBookSnapshot,
+}
+
+/// Strategy state between refreshes
+struct StrategyRunner {
+    config: SimStrategy,
+    next_refresh: i64,
+    next_order_id: u64,
+    live: Vec<u64>,
+}
+
+/// Deterministic replay of order flow through the matching engine
+pub struct Simulator {
+    engine: MatchingEngine<InMemoryStorage>,
+    orderbook: OrderBook,
+    strategy: Option<StrategyRunner>,
+    /// Virtual time in milliseconds
+    clock: i64,
+    /// The engine's and storage's clock, kept at `clock`
+    engine_clock: Arc<VirtualClock>,
+    start_time: Option<i64>,
+    owners: HashMap<u64, Pubkey>,
+    accounts: BTreeMap<String, AccountReport>,
+    fills: Vec<SimFill>,
+    rejections: BTreeMap<String, u64>,
+    events_processed: u64,
+    orders_placed: u64,
+    orders_cancelled: u64,
+    last_price: Option<u64>,
+}
+
+impl Simulator {
+    /// Create a simulator for a market with a fresh in-memory engine
+    pub fn new(orderbook: OrderBook) -> Self {
+        let engine_clock = Arc::new(VirtualClock::default());
+        let storage = InMemoryStorage::with_clock(engine_clock.clone());
+        let mut engine = MatchingEngine::new(Arc::new(storage), orderbook.clone());
+        engine.set_clock(engine_clock.clone());
+        Self {
+            engine,
+            orderbook,
+            strategy: None,
+            clock: 0,
+            engine_clock,
+            start_time: None,
+            owners: HashMap::new(),
+            accounts: BTreeMap::new(),
+            fills: Vec::new(),
+            rejections: BTreeMap::new(),
+            events_processed: 0,
+            orders_placed: 0,
+            orders_cancelled: 0,
+            last_price: None,
+        }
+    }
+
+    /// Split fills between makers at one price with `allocation` instead of
+    /// price-time priority
+    pub fn with_allocation(mut self, allocation: AllocationMode) -> Self {
+        self.engine.set_allocation(allocation);
+        self
+    }
+
+    /// Let a quoting strategy trade alongside the replayed flow
+    pub fn with_strategy(mut self, strategy: SimStrategy) -> Self {
+        self.strategy = Some(StrategyRunner {
+            config: strategy,
+            next_refresh: i64::MIN,
+            next_order_id: STRATEGY_ORDER_ID_BASE,
+            live: Vec::new(),
+        });
+        self
+    }
+
+    /// Process every event in time order and report the outcome
+    pub async fn run(mut self, events: impl IntoIterator<Item = SimEvent>) -> ClobResult<SimReport> {
+        for event in events {
+            // The clock never runs backwards, even for out-of-order input
+            self.clock = self.clock.max(event.timestamp);
+            self.engine_clock.set_millis(self.clock);
+            self.start_time.get_or_insert(self.clock);
+
+            self.refresh_strategy().await?;
+            self.apply(event.command).await;
+            self.events_processed += 1;
+        }
+
+        info!(
+            "Simulation finished: {} events, {} fills",
+            self.events_processed,
+            self.fills.len()
+        );
+        self.report().await
+    }
+
+    async fn apply(&mut self, command: SimCommand) {
+        match command {
+            SimCommand::Place(order) => {
+                self.place(order).await;
+            }
+            SimCommand::Cancel(order_id) => {
+                self.cancel(order_id).await;
+            }
+        }
+    }
+
+    /// Submit an order, returning whether the engine accepted it
+    async fn place(&mut self, order: Order) -> bool {
+        let order_id = order.order_id;
+        let owner = order.owner;
+        self.owners.insert(order_id, owner);
+
+        match self.engine.place_order(order).await {
+            Ok(trades) => {
+                self.orders_placed += 1;
+                for trade in trades {
+                    self.record_fill(&trade);
+                }
+                true
+            }
+            Err(e) => {
+                debug!("Order {} rejected: {}", order_id, e);
+                *self.rejections.entry(e.to_string()).or_default() += 1;
+                false
+            }
+        }
+    }
+
+    async fn cancel(&mut self, order_id: u64) {
+        match self.engine.cancel_order(order_id).await {
+            Ok(_) => self.orders_cancelled += 1,
+            Err(e) => *self.rejections.entry(e.to_string()).or_default() += 1,
+        }
+    }
+
+    fn record_fill(&mut self, trade: &TradeExecution) {
+        let (Some(maker), Some(taker)) = (
+            self.owners.get(&trade.maker_order_id).copied(),
+            self.owners.get(&trade.taker_order_id).copied(),
+        ) else {
+            return;
+        };
+
+        let notional = trade.notional().0 as i128;
+        let quantity = trade.quantity as i64;
+        let (buyer, seller) = match trade.maker_side {
+            OrderSide::Bid => (maker, taker),
+            OrderSide::Ask => (taker, maker),
+        };
+
+        let buyer = self.account(buyer);
+        buyer.position += quantity;
+        buyer.cash -= notional;
+        let seller = self.account(seller);
+        seller.position -= quantity;
+        seller.cash += notional;
+
+        for owner in [maker, taker] {
+            let account = self.account(owner);
+            account.volume += trade.quantity;
+            account.fills += 1;
+        }
+        self.account(maker).maker_fills += 1;
+
+        self.last_price = Some(trade.price);
+        self.fills.push(SimFill {
+            timestamp: self.clock,
+            maker_order_id: trade.maker_order_id,
+            taker_order_id: trade.taker_order_id,
+            maker: maker.to_string(),
+            taker: taker.to_string(),
+            maker_side: trade.maker_side,
+            price: trade.price,
+            quantity: trade.quantity,
+        });
+    }
+
+    fn account(&mut self, owner: Pubkey) -> &mut AccountReport {
+        self.accounts.entry(owner.to_string()).or_insert_with(|| AccountReport {
+            owner: owner.to_string(),
+            ..Default::default()
+        })
+    }
+
+    /// Cancel the strategy's quotes and place fresh ones when a refresh is due
+    async fn refresh_strategy(&mut self) -> ClobResult<()> {
+        let Some(mut runner) = self.strategy.take() else {
+            return Ok(());
+        };
+        if runner.next_refresh > self.clock {
+            self.strategy = Some(runner);
+            return Ok(());
+        }
+        runner.next_refresh = self.clock + runner.config.refresh_interval_ms.max(1);
+
+        // Quotes that filled completely are no longer in the book
+        for order_id in std::mem::take(&mut runner.live) {
+            let _ = self.engine.cancel_order(order_id).await;
+        }
+
+        let book = self.engine.get_order_book_snapshot().await?;
+        let mid = mid_price(&book).or(self.last_price).or(runner.config.fallback_mid);
+        if let Some(mid) = mid {
+            let inventory = self
+                .accounts
+                .get(&runner.config.owner.to_string())
+                .map_or(0, |account| account.position);
+            let context = QuoteContext {
+                book,
+                mid,
+                inventory,
+                tick_size: self.orderbook.tick_size,
+                min_order_size: self.orderbook.min_order_size,
+            };
+
+            for quote in runner.config.strategy.quote(&context) {
+                let order_id = runner.next_order_id;
+                runner.next_order_id += 1;
+                let order = Order::builder()
+                    .order_id(order_id)
+                    .client_order_id(order_id)
+                    .owner(runner.config.owner)
+                    .side(quote.side)
+                    .post_only(quote.price)
+                    .qty(quote.quantity)
+                    .timestamp(self.clock / 1000)
+                    .build();
+                // A zero-sized or unpriced quote would be rejected by the engine anyway
+                let Ok(order) = order else {
+                    continue;
+                };
+                if self.place(order).await {
+                    runner.live.push(order_id);
+                }
+            }
+        }
+
+        self.strategy = Some(runner);
+        Ok(())
+    }
+
+    async fn report(mut self) -> ClobResult<SimReport> {
+        let final_book = self.engine.get_order_book_snapshot().await?;
+
+        let mark_price = self.last_price.or_else(|| mid_price(&final_book));
+        let mark = mark_price.unwrap_or(0) as i128;
+        for account in self.accounts.values_mut() {
+            account.pnl = account.cash + account.position as i128 * mark;
+        }
+
+        Ok(SimReport {
+            start_time: self.start_time.unwrap_or(self.clock),
+            end_time: self.clock,
+            events_processed: self.events_processed,
+            orders_placed: self.orders_placed,
+            orders_cancelled: self.orders_cancelled,
+            rejections: self.rejections,
+            mark_price,
+            total_volume: self.fills.iter().map(|fill| fill.quantity).sum(),
+            accounts: self.accounts.into_values().collect(),
+            fills: self.fills,
+            final_book,
+        })
+    }
+}
@@ -0,0 +1,255 @@
+/// Journal replay debugger
+///
+/// `Replayer` re-runs a range of the engine journal through an offline engine
+/// one command at a time. Each step shows the book before and after the
+/// command and compares the fills the engine produces now with the
+/// `TradeExecuted` entries journaled after it, so the first command whose
+/// outcome differs from production can be found and inspected. Trade IDs and
+/// timestamps are not compared; everything else about a fill is.
+
+use svm_clob_types::*;
+use svm_clob_matching_engine::MatchingEngine;
+use svm_clob_storage::{InMemoryStorage, Storage};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Journal entries fetched per query while loading a range
+const LOAD_BATCH_SIZE: u32 = 1000;
+
+/// Difference between a journaled fill and the replayed one at the same position
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum FillDivergence {
+    /// Journaled, but not produced on replay
+    Missing { recorded: TradeExecution },
+    /// Produced on replay, but not journaled
+    Unexpected { replayed: TradeExecution },
+    /// Both exist but disagree
+    Mismatch {
+        recorded: TradeExecution,
+        replayed: TradeExecution,
+    },
+}
+
+/// One replayed command
+#[derive(Serialize, Debug, Clone)]
+pub struct ReplayStep {
+    /// Journal entry of the command
+    pub sequence: u64,
+    pub timestamp: i64,
+    pub event: JournalEvent,
+    pub book_before: OrderBookSnapshot,
+    pub book_after: OrderBookSnapshot,
+    /// Fills journaled after the command
+    pub recorded: Vec<TradeExecution>,
+    /// Fills the engine produced on replay
+    pub replayed: Vec<TradeExecution>,
+    pub divergences: Vec<FillDivergence>,
+    /// Error the engine raised on replay, though the command was accepted when journaled
+    pub error: Option<String>,
+}
+
+/// Outcome of replaying a range without stopping
+#[derive(Serialize, Debug, Clone)]
+pub struct ReplayReport {
+    /// Position before the first and after the last replayed command
+    pub start_position: u64,
+    pub end_position: u64,
+    pub commands_replayed: u64,
+    /// Steps whose outcome differed, in journal order
+    pub diverged: Vec<ReplayStep>,
+}
+
+impl ReplayStep {
+    /// Whether the replayed outcome differs from the journaled one
+    pub fn diverged(&self) -> bool {
+        !self.divergences.is_empty() || self.error.is_some()
+    }
+}
+
+/// Steps through journal commands on an offline engine
+pub struct Replayer {
+    engine: MatchingEngine<InMemoryStorage>,
+    /// Entries not replayed yet
+    pending: VecDeque<JournalEntry>,
+    /// Price levels per side kept in step books
+    depth: usize,
+}
+
+impl Replayer {
+    /// Replayer positioned at the first command from `first` on; earlier
+    /// entries are applied without stepping, to rebuild the book they left
+    pub async fn new(
+        orderbook: OrderBook,
+        market_id: MarketId,
+        allocation: AllocationMode,
+        entries: Vec<JournalEntry>,
+        first: u64,
+        depth: usize,
+    ) -> ClobResult<Self> {
+        let mut engine = MatchingEngine::new(Arc::new(InMemoryStorage::new()), orderbook);
+        engine.set_market_id(market_id);
+        engine.set_allocation(allocation);
+
+        let mut pending = VecDeque::from(entries);
+        while pending.front().is_some_and(|entry| entry.sequence < first) {
+            if let Some(entry) = pending.pop_front() {
+                engine.replay_journal_entry(&entry).await?;
+            }
+        }
+        Ok(Self { engine, pending, depth })
+    }
+
+    /// Sequence of the last entry applied
+    pub fn position(&self) -> u64 {
+        self.engine.journal_position()
+    }
+
+    /// Whether commands are left to step through
+    pub fn is_done(&self) -> bool {
+        self.pending.iter().all(|entry| !is_command(&entry.event))
+    }
+
+    /// Book at the current position, up to the configured depth
+    pub async fn book(&self) -> ClobResult<OrderBookSnapshot> {
+        let mut book = self.engine.get_order_book_snapshot().await?;
+        book.bids.truncate(self.depth);
+        book.asks.truncate(self.depth);
+        Ok(book)
+    }
+
+    /// Resting order at the current position
+    pub async fn order(&self, order_id: u64) -> Option<Order> {
+        self.engine
+            .get_open_orders()
+            .await
+            .into_iter()
+            .find(|order| order.order_id == order_id)
+    }
+
+    /// Replay every remaining command, keeping the steps that diverged
+    pub async fn run_to_end(&mut self) -> ClobResult<ReplayReport> {
+        let start_position = self.position();
+        let mut commands_replayed = 0;
+        let mut diverged = Vec::new();
+        while let Some(step) = self.step().await? {
+            commands_replayed += 1;
+            if step.diverged() {
+                diverged.push(step);
+            }
+        }
+        Ok(ReplayReport {
+            start_position,
+            end_position: self.position(),
+            commands_replayed,
+            diverged,
+        })
+    }
+
+    /// Replay the next command and the fills journaled after it; `None` at the end
+    pub async fn step(&mut self) -> ClobResult<Option<ReplayStep>> {
+        // Fills whose command lies before the range only advance trade IDs,
+        // and order ID reservations are no command of their own
+        while let Some(entry) = self.pending.front() {
+            if is_command(&entry.event) {
+                break;
+            }
+            if let Some(entry) = self.pending.pop_front() {
+                self.engine.replay_journal_entry(&entry).await?;
+            }
+        }
+        let Some(command) = self.pending.pop_front() else {
+            return Ok(None);
+        };
+
+        let mut recorded_entries = Vec::new();
+        while let Some(entry) = self.pending.front() {
+            if is_command(&entry.event) {
+                break;
+            }
+            recorded_entries.extend(self.pending.pop_front());
+        }
+        let recorded: Vec<TradeExecution> = recorded_entries
+            .iter()
+            .filter_map(|entry| match &entry.event {
+                JournalEvent::TradeExecuted { trade, .. } => Some(trade.clone()),
+                _ => None,
+            })
+            .collect();
+
+        let book_before = self.book().await?;
+        let (replayed, error) = match self.engine.replay_journal_entry(&command).await {
+            Ok(replayed) => (replayed, None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+        for entry in &recorded_entries {
+            self.engine.replay_journal_entry(entry).await?;
+        }
+        let book_after = self.book().await?;
+
+        Ok(Some(ReplayStep {
+            sequence: command.sequence,
+            timestamp: command.timestamp,
+            event: command.event,
+            book_before,
+            book_after,
+            divergences: diff_fills(&recorded, &replayed),
+            recorded,
+            replayed,
+            error,
+        }))
+    }
+}
+
+/// Whether `event` is a command the engine accepted, rather than a fill or
+/// an order ID reservation journaled around one
+fn is_command(event: &JournalEvent) -> bool {
+    !matches!(event, JournalEvent::TradeExecuted { .. } | JournalEvent::OrderIdsReserved { .. })
+}
+
+/// Compare fills position by position, ignoring trade IDs and timestamps
+pub fn diff_fills(recorded: &[TradeExecution], replayed: &[TradeExecution]) -> Vec<FillDivergence> {
+    let same = |a: &TradeExecution, b: &TradeExecution| {
+        (a.market_id, a.maker_order_id, a.taker_order_id, a.price, a.quantity, a.maker_side)
+            == (b.market_id, b.maker_order_id, b.taker_order_id, b.price, b.quantity, b.maker_side)
+    };
+
+    let mut divergences = Vec::new();
+    for index in 0..recorded.len().max(replayed.len()) {
+        match (recorded.get(index), replayed.get(index)) {
+            (Some(recorded), Some(replayed)) if !same(recorded, replayed) => divergences.push(FillDivergence::Mismatch {
+                recorded: recorded.clone(),
+                replayed: replayed.clone(),
+            }),
+            (Some(recorded), None) => divergences.push(FillDivergence::Missing {
+                recorded: recorded.clone(),
+            }),
+            (None, Some(replayed)) => divergences.push(FillDivergence::Unexpected {
+                replayed: replayed.clone(),
+            }),
+            _ => {}
+        }
+    }
+    divergences
+}
+
+/// Journal from the start through the command at `last` (the head when
+/// `None`), including the fills journaled after that command
+pub async fn load_journal<S: Storage>(storage: &S, last: Option<u64>) -> ClobResult<Vec<JournalEntry>> {
+    let last = last.unwrap_or(u64::MAX);
+    let mut entries: Vec<JournalEntry> = Vec::new();
+    loop {
+        let after = entries.last().map_or(0, |entry| entry.sequence);
+        let batch = storage.get_journal_entries(after, LOAD_BATCH_SIZE).await?;
+        if batch.is_empty() {
+            return Ok(entries);
+        }
+        for entry in batch {
+            if entry.sequence > last && is_command(&entry.event) {
+                return Ok(entries);
+            }
+            entries.push(entry);
+        }
+    }
+}
@@ -0,0 +1,207 @@
+/// End-of-Day Statements for SVM CLOB Infrastructure
+///
+/// This module generates a statement per user for every UTC day once it has
+/// closed: the day's fills in the market, the fees they accrued, deposits,
+/// withdrawals and transfers between a wallet's accounts, and the user's
+/// ledger balance at the start and end of the day. Each statement is rendered
+/// as JSON, CSV and PDF and stored with the day, for the RPC server to serve
+/// to back offices. Days are generated in order; a restart resumes after the
+/// newest stored day.
+
+use svm_clob_types::statements::{statement_date, STATEMENT_DAY_SECS};
+use svm_clob_types::*;
+use svm_clob_storage::Storage;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+mod pdf;
+mod render;
+
+pub use render::{render_csv, render_pdf};
+
+/// Statement configuration
+#[derive(Debug, Clone)]
+pub struct StatementConfig {
+    /// Time to wait after a day closes before generating it, so that
+    /// settlements and chain events of its last minutes are indexed
+    pub delay: Duration,
+    /// Delay between checks for a closed day
+    pub poll_interval: Duration,
+    /// Symbols and decimals printed on statements
+    pub metadata: MarketMetadata,
+}
+
+impl Default for StatementConfig {
+    fn default() -> Self {
+        Self {
+            delay: Duration::from_secs(3600),
+            poll_interval: Duration::from_secs(60),
+            metadata: MarketMetadata::default(),
+        }
+    }
+}
+
+/// Render a statement in every format
+pub fn render(statement: &Statement) -> ClobResult<StatementArtifacts> {
+    let json = serde_json::to_vec_pretty(statement).map_err(|e| ClobError::SerializationError(e.to_string()))?;
+    Ok(StatementArtifacts {
+        summary: statement.summary(),
+        json,
+        csv: render_csv(statement).into_bytes(),
+        pdf: render_pdf(statement),
+    })
+}
+
+/// Scheduled job that generates each day's statements
+pub struct StatementService<S: Storage> {
+    config: StatementConfig,
+    storage: Arc<S>,
+    orderbook: OrderBook,
+    market_id: MarketId,
+    clock: Arc<dyn Clock>,
+}
+
+impl<S: Storage> StatementService<S> {
+    /// Create a new statement service for the market of `orderbook`
+    pub fn new(storage: Arc<S>, orderbook: OrderBook, market_id: MarketId, config: StatementConfig) -> Self {
+        Self {
+            config,
+            storage,
+            orderbook,
+            market_id,
+            clock: system_clock(),
+        }
+    }
+
+    /// Read the time days close at from `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Run until storage fails
+    ///
+    /// Without a stored day, generation starts with the day before the one
+    /// the service started in; earlier days are not backfilled.
+    pub async fn run(&self) -> ClobResult<()> {
+        let mut next = match self.storage.get_last_statement_day().await? {
+            Some(day) => day.period_end,
+            None => (self.clock.now().div_euclid(STATEMENT_DAY_SECS) - 1) * STATEMENT_DAY_SECS,
+        };
+        info!("Statements generated daily from {}", statement_date(next));
+
+        loop {
+            let ready_at = next + STATEMENT_DAY_SECS + self.config.delay.as_secs() as i64;
+            if self.clock.now() < ready_at {
+                tokio::time::sleep(self.config.poll_interval).await;
+                continue;
+            }
+            let day = self.generate(next).await?;
+            info!("Generated {} statements of {}", day.statements, statement_date(day.period_start));
+            next = day.period_end;
+        }
+    }
+
+    /// Generate and store the statements of the day starting at
+    /// `period_start`, for every user with activity in the day or a balance
+    /// at its end
+    pub async fn generate(&self, period_start: i64) -> ClobResult<StatementDay> {
+        let period_end = period_start + STATEMENT_DAY_SECS;
+        let (base_mint, quote_mint) = (&self.orderbook.base_mint, &self.orderbook.quote_mint);
+
+        let opening: HashMap<Pubkey, LedgerBalance> = self
+            .storage
+            .get_ledger_balances(base_mint, quote_mint, None, Some(period_start))
+            .await?
+            .into_iter()
+            .map(|balance| (balance.user, balance))
+            .collect();
+        let ending: BTreeMap<Pubkey, LedgerBalance> = self
+            .storage
+            .get_ledger_balances(base_mint, quote_mint, None, Some(period_end))
+            .await?
+            .into_iter()
+            .map(|balance| (balance.user, balance))
+            .collect();
+        let fees: HashMap<Pubkey, FeeReport> = self
+            .storage
+            .get_fee_reports(period_start, period_end, None)
+            .await?
+            .into_iter()
+            .map(|report| (report.user, report))
+            .collect();
+
+        let mut users: BTreeSet<Pubkey> = ending.keys().copied().collect();
+        users.extend(
+            self.storage
+                .get_traded_volumes(period_start, period_end)
+                .await?
+                .into_iter()
+                .map(|(user, _)| user),
+        );
+
+        let generated_at = self.clock.now();
+        let mut statements = Vec::new();
+        for user in users {
+            let mut fills = self.storage.get_user_fills_between(&user, period_start, period_end).await?;
+            fills.retain(|fill| fill.trade.market_id == self.market_id);
+            let statement = Statement {
+                owner: user,
+                date: statement_date(period_start),
+                period_start,
+                period_end,
+                market_id: self.market_id,
+                base_mint: *base_mint,
+                quote_mint: *quote_mint,
+                metadata: self.config.metadata.clone(),
+                opening_balance: opening.get(&user).cloned().unwrap_or_else(|| LedgerBalance::empty(user)),
+                ending_balance: ending.get(&user).cloned().unwrap_or_else(|| LedgerBalance::empty(user)),
+                fills,
+                fees: fees.get(&user).cloned().unwrap_or(FeeReport {
+                    user,
+                    period_start,
+                    period_end,
+                    fills: 0,
+                    maker_volume: 0,
+                    taker_volume: 0,
+                    maker_fees: 0,
+                    taker_fees: 0,
+                    total_fees: 0,
+                }),
+                deposits: self.storage.get_user_deposits(&user, period_start, period_end).await?,
+                withdrawals: self.storage.get_user_withdrawals(&user, period_start, period_end).await?,
+                transfers: self.storage.get_user_sub_account_transfers(&user, period_start, period_end).await?,
+                generated_at,
+            };
+            if is_dormant(&statement) {
+                continue;
+            }
+            statements.push(render(&statement)?);
+        }
+
+        let day = StatementDay {
+            period_start,
+            period_end,
+            statements: statements.len() as u32,
+            generated_at,
+        };
+        self.storage.store_statement_day(&day, &statements).await?;
+        Ok(day)
+    }
+}
+
+/// Whether a statement has nothing to report: no activity in the day and
+/// nothing left at its end
+fn is_dormant(statement: &Statement) -> bool {
+    let ending = &statement.ending_balance;
+    statement.fills.is_empty()
+        && statement.deposits.is_empty()
+        && statement.withdrawals.is_empty()
+        && statement.transfers.is_empty()
+        && statement.fees.fills == 0
+        && ending.base_balance == 0
+        && ending.quote_balance == 0
+        && ending.fees == 0
+}
@@ -0,0 +1,125 @@
+/// Plain text PDF
+///
+/// Statements are monospaced text, so a PDF 1.4 document of Courier lines
+/// on A4 pages is all they need: a catalog, a page tree, the font, and a page
+/// with one content stream per page of lines. Output depends only on the
+/// lines written, so the same statement always renders to the same bytes.
+
+const PAGE_WIDTH: u32 = 595;
+const PAGE_HEIGHT: u32 = 842;
+const MARGIN: u32 = 40;
+const FONT_SIZE: u32 = 9;
+const LEADING: u32 = 11;
+const LINES_PER_PAGE: usize = ((PAGE_HEIGHT - 2 * MARGIN) / LEADING) as usize;
+
+/// Document being written, a line at a time
+#[derive(Default)]
+pub struct PdfDocument {
+    lines: Vec<String>,
+}
+
+impl PdfDocument {
+    pub fn new() -> Self {
+        Self { lines: Vec::new() }
+    }
+
+    pub fn line(&mut self, text: String) {
+        self.lines.push(text);
+    }
+
+    /// Start a section: a blank line, then its title
+    pub fn section(&mut self, title: String) {
+        self.lines.push(String::new());
+        self.lines.push(title);
+    }
+
+    /// The document's bytes
+    pub fn finish(self) -> Vec<u8> {
+        let pages: Vec<&[String]> = if self.lines.is_empty() {
+            vec![&self.lines[..]]
+        } else {
+            self.lines.chunks(LINES_PER_PAGE).collect()
+        };
+
+        // Objects 1 to 3 are the catalog, page tree and font; each page is
+        // then a page object followed by its content stream
+        let page_ids: Vec<usize> = (0..pages.len()).map(|page| 4 + 2 * page).collect();
+        let mut objects = vec![
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                page_ids.iter().map(|id| format!("{} 0 R", id)).collect::<Vec<_>>().join(" "),
+                pages.len()
+            )
+            .into_bytes(),
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>".to_vec(),
+        ];
+        for (lines, id) in pages.iter().zip(&page_ids) {
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                     /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+                    PAGE_WIDTH,
+                    PAGE_HEIGHT,
+                    id + 1
+                )
+                .into_bytes(),
+            );
+            let mut content = format!(
+                "BT\n/F1 {} Tf\n{} TL\n{} {} Td\n",
+                FONT_SIZE,
+                LEADING,
+                MARGIN,
+                PAGE_HEIGHT - MARGIN - FONT_SIZE
+            );
+            for line in lines.iter() {
+                content.push_str(&format!("({}) Tj T*\n", escape(line)));
+            }
+            content.push_str("ET");
+            let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+            stream.extend_from_slice(content.as_bytes());
+            stream.extend_from_slice(b"\nendstream");
+            objects.push(stream);
+        }
+
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            pdf.extend_from_slice(object);
+            pdf.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = pdf.len();
+        pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+        for offset in offsets {
+            pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        pdf.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                objects.len() + 1,
+                xref
+            )
+            .as_bytes(),
+        );
+        pdf
+    }
+}
+
+/// A line as a PDF string literal's contents: delimiters escaped, and
+/// anything outside printable ASCII replaced
+fn escape(line: &str) -> String {
+    let mut escaped = String::with_capacity(line.len());
+    for c in line.chars() {
+        match c {
+            '\\' | '(' | ')' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' '..='~' => escaped.push(c),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,226 @@
+/// Statement rendering
+///
+/// The CSV has one row per line of the statement, so a back office can load
+/// it into a single table: the opening balance, every fill, deposit,
+/// withdrawal and transfer, the day's fees and the ending balance. Amounts
+/// are signed from the user's side, in native units: what a fill, deposit or
+/// transfer adds to the balance is positive and what it takes is negative.
+/// The PDF prints the same lines for people to read.
+
+use crate::pdf::PdfDocument;
+use chrono::{TimeZone, Utc};
+use svm_clob_types::*;
+
+const CSV_HEADER: &str = "record,time,reference,side,role,status,price,quantity,base_amount,quote_amount,fee\n";
+
+fn rfc3339(timestamp: i64) -> String {
+    Utc.timestamp_opt(timestamp, 0)
+        .single()
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+fn side(side: OrderSide) -> &'static str {
+    match side {
+        OrderSide::Bid => "buy",
+        OrderSide::Ask => "sell",
+    }
+}
+
+fn role(role: LiquidityRole) -> &'static str {
+    match role {
+        LiquidityRole::Maker => "maker",
+        LiquidityRole::Taker => "taker",
+    }
+}
+
+fn status(status: TradeStatus) -> &'static str {
+    match status {
+        TradeStatus::Active => "active",
+        TradeStatus::Corrected => "corrected",
+        TradeStatus::Busted => "busted",
+    }
+}
+
+/// Base and quote a fill moved into the user's balance; nothing for a
+/// busted fill
+fn fill_amounts(fill: &UserFill) -> (i128, i128) {
+    if fill.trade.status == TradeStatus::Busted {
+        return (0, 0);
+    }
+    let base = fill.trade.quantity as i128;
+    let quote = fill.trade.notional().0 as i128;
+    match fill.side() {
+        OrderSide::Bid => (base, -quote),
+        OrderSide::Ask => (-base, quote),
+    }
+}
+
+/// Base and quote a deposit or withdrawal of `amount` of `mint` moved
+fn mint_amounts(statement: &Statement, mint: &Pubkey, amount: i128) -> (i128, i128) {
+    if *mint == statement.base_mint {
+        (amount, 0)
+    } else if *mint == statement.quote_mint {
+        (0, amount)
+    } else {
+        (0, 0)
+    }
+}
+
+/// Base and quote a transfer moved into the user's account
+fn transfer_amounts(statement: &Statement, transfer: &SubAccountTransferRecord) -> (i128, i128) {
+    let (base, quote) = (transfer.base_amount as i128, transfer.quote_amount as i128);
+    if transfer.to == statement.owner {
+        (base, quote)
+    } else {
+        (-base, -quote)
+    }
+}
+
+/// Render a statement as CSV with a header row
+pub fn render_csv(statement: &Statement) -> String {
+    let mut csv = String::from(CSV_HEADER);
+    let mut row = |record: &str, time: i64, reference: &str, fill: Option<&UserFill>, base: i128, quote: i128, fee: i64| {
+        let (fill_side, fill_role, fill_status, price, quantity) = match fill {
+            Some(fill) => (
+                side(fill.side()),
+                role(fill.role),
+                status(fill.trade.status),
+                fill.trade.price.to_string(),
+                fill.trade.quantity.to_string(),
+            ),
+            None => ("", "", "", String::new(), String::new()),
+        };
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},{}\n",
+            record,
+            rfc3339(time),
+            reference,
+            fill_side,
+            fill_role,
+            fill_status,
+            price,
+            quantity,
+            base,
+            quote,
+            fee
+        ));
+    };
+
+    let opening = &statement.opening_balance;
+    let (base, quote) = (opening.base_balance.into(), opening.quote_balance.into());
+    row("opening_balance", statement.period_start, "", None, base, quote, opening.fees);
+    for fill in &statement.fills {
+        let (base, quote) = fill_amounts(fill);
+        row("fill", fill.trade.timestamp, &fill.trade.trade_id.to_string(), Some(fill), base, quote, 0);
+    }
+    for deposit in &statement.deposits {
+        let (base, quote) = mint_amounts(statement, &deposit.mint, deposit.amount as i128);
+        row("deposit", deposit.timestamp, &deposit.signature, None, base, quote, 0);
+    }
+    for withdrawal in &statement.withdrawals {
+        let (base, quote) = mint_amounts(statement, &withdrawal.mint, -(withdrawal.amount as i128));
+        row("withdrawal", withdrawal.timestamp, &withdrawal.signature, None, base, quote, 0);
+    }
+    for transfer in &statement.transfers {
+        let (base, quote) = transfer_amounts(statement, transfer);
+        row("transfer", transfer.timestamp, &transfer.signature, None, base, quote, 0);
+    }
+    row("fees", statement.period_end, "", None, 0, 0, statement.fees.total_fees);
+    let ending = &statement.ending_balance;
+    let (base, quote) = (ending.base_balance.into(), ending.quote_balance.into());
+    row("ending_balance", statement.period_end, "", None, base, quote, ending.fees);
+    csv
+}
+
+/// Render a statement as a PDF of plain text pages
+pub fn render_pdf(statement: &Statement) -> Vec<u8> {
+    let market = statement.metadata.symbol().unwrap_or_else(|| statement.market_id.to_string());
+    let base = statement.metadata.base_symbol.clone().unwrap_or_else(|| "base".to_string());
+    let quote = statement.metadata.quote_symbol.clone().unwrap_or_else(|| "quote".to_string());
+    let balance_line = |label: &str, balance: &LedgerBalance| {
+        format!("{:<16}{:>24} {:<6}{:>24} {}", label, balance.base_balance, base, balance.quote_balance, quote)
+    };
+
+    let mut document = PdfDocument::new();
+    document.line(format!("Daily statement {}", statement.date));
+    document.line(String::new());
+    document.line(format!("Account   {}", statement.owner));
+    document.line(format!("Market    {}", market));
+    document.line(format!("Period    {} to {}", rfc3339(statement.period_start), rfc3339(statement.period_end)));
+    document.line(format!("Generated {}", rfc3339(statement.generated_at)));
+    document.line("Amounts are in native token units.".to_string());
+    document.line(String::new());
+    document.line(balance_line("Opening balance", &statement.opening_balance));
+
+    document.section(format!("Fills ({})", statement.fills.len()));
+    if !statement.fills.is_empty() {
+        document.line(format!(
+            "{:<10}{:>12} {:<5}{:<6}{:<10}{:>16}{:>16}",
+            "Time", "Trade", "Side", "Role", "Status", "Price", "Quantity"
+        ));
+    }
+    for fill in &statement.fills {
+        let time = Utc
+            .timestamp_opt(fill.trade.timestamp, 0)
+            .single()
+            .map(|time| time.format("%H:%M:%S").to_string())
+            .unwrap_or_default();
+        document.line(format!(
+            "{:<10}{:>12} {:<5}{:<6}{:<10}{:>16}{:>16}",
+            time,
+            fill.trade.trade_id,
+            side(fill.side()),
+            role(fill.role),
+            status(fill.trade.status),
+            fill.trade.price,
+            fill.trade.quantity
+        ));
+    }
+
+    let fees = &statement.fees;
+    document.section("Fees".to_string());
+    document.line(format!("Fills charged {:>12}", fees.fills));
+    document.line(format!("Maker volume  {:>24}   fees {:>20} {}", fees.maker_volume, fees.maker_fees, quote));
+    document.line(format!("Taker volume  {:>24}   fees {:>20} {}", fees.taker_volume, fees.taker_fees, quote));
+    document.line(format!("Total fees    {:>55} {}", fees.total_fees, quote));
+
+    document.section(format!("Deposits ({})", statement.deposits.len()));
+    for deposit in &statement.deposits {
+        document.line(format!("{}  {:>20} {}", rfc3339(deposit.timestamp), deposit.amount, deposit.mint));
+        document.line(format!("  {}", deposit.signature));
+    }
+    document.section(format!("Withdrawals ({})", statement.withdrawals.len()));
+    for withdrawal in &statement.withdrawals {
+        document.line(format!("{}  {:>20} {}", rfc3339(withdrawal.timestamp), withdrawal.amount, withdrawal.mint));
+        document.line(format!("  {}", withdrawal.signature));
+    }
+    document.section(format!("Transfers ({})", statement.transfers.len()));
+    for transfer in &statement.transfers {
+        let (base_amount, quote_amount) = transfer_amounts(statement, transfer);
+        let (direction, counterparty) = if transfer.to == statement.owner {
+            ("from", transfer.from)
+        } else {
+            ("to", transfer.to)
+        };
+        document.line(format!(
+            "{}  {:>20} {} {:>20} {}",
+            rfc3339(transfer.timestamp),
+            base_amount,
+            base,
+            quote_amount,
+            quote
+        ));
+        document.line(format!("  {} {}", direction, counterparty));
+    }
+
+    let ending = &statement.ending_balance;
+    document.section("Ending balance".to_string());
+    document.line(balance_line("Balance", ending));
+    document.line(format!("{:<16}{:>24} {:<6}{:>24} {}", "Held", ending.base_held, base, ending.quote_held, quote));
+    document.line(format!("{:<16}{:>55} {}", "Fees accrued", ending.fees, quote));
+    document.line(String::new());
+    document.line("Balances count fills settled on chain; fills of the day not yet settled".to_string());
+    document.line("are listed above but not included. Held amounts await finality.".to_string());
+    document.finish()
+}
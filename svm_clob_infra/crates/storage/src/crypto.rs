@@ -0,0 +1,122 @@
+/// Column encryption
+///
+/// With a `FieldCipher`, `PostgresStorage` encrypts the columns that can hold
+/// personal or key-related data before writing them: tenant names, API key
+/// labels, and the bodies and settings kept by the API and admin audit logs.
+/// Values are sealed with AES-256-GCM under one of several numbered keys and
+/// stored as `enc:<key id>:<base64 of nonce and ciphertext>`, bound to their
+/// column so a sealed value copied into another column does not open. The keys
+/// come from the deployment's key management, e.g. a data key a KMS decrypts
+/// into the environment at startup. Reads open a value with whichever key it
+/// names and pass unsealed values through, so encryption can be turned on for
+/// a populated database. A key is rotated by adding a new one, making it
+/// active and re-encrypting, after which the old key can be dropped.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::collections::HashMap;
+use svm_clob_types::{ClobError, ClobResult};
+
+/// Prefix of a sealed value
+const SEALED_PREFIX: &str = "enc:";
+
+/// Length of an AES-GCM nonce in bytes
+const NONCE_LEN: usize = 12;
+
+/// Keys sealing and opening encrypted columns
+pub struct FieldCipher {
+    /// Key new values are sealed with
+    active: u32,
+    keys: HashMap<u32, Aes256Gcm>,
+}
+
+impl std::fmt::Debug for FieldCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut key_ids: Vec<&u32> = self.keys.keys().collect();
+        key_ids.sort();
+        f.debug_struct("FieldCipher").field("active", &self.active).field("key_ids", &key_ids).finish()
+    }
+}
+
+impl FieldCipher {
+    /// Cipher sealing with key `active` and opening with any of `keys`, each
+    /// given as the base64 of 32 bytes
+    pub fn new(active: u32, keys: &HashMap<u32, String>) -> ClobResult<Self> {
+        let keys = keys
+            .iter()
+            .map(|(&key_id, encoded)| {
+                let bytes = BASE64
+                    .decode(encoded.trim())
+                    .map_err(|e| ClobError::StorageError(format!("Encryption key {} is not base64: {}", key_id, e)))?;
+                if bytes.len() != 32 {
+                    return Err(ClobError::StorageError(format!(
+                        "Encryption key {} has {} bytes, expected 32",
+                        key_id,
+                        bytes.len()
+                    )));
+                }
+                Ok((key_id, Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&bytes))))
+            })
+            .collect::<ClobResult<HashMap<_, _>>>()?;
+        if !keys.contains_key(&active) {
+            return Err(ClobError::StorageError(format!("Active encryption key {} is not configured", active)));
+        }
+        Ok(Self { active, keys })
+    }
+
+    /// Key new values are sealed with
+    pub fn active_key_id(&self) -> u32 {
+        self.active
+    }
+
+    /// Seal `plaintext` for `column` under the active key
+    pub fn seal(&self, column: &str, plaintext: &str) -> ClobResult<String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self.keys[&self.active]
+            .encrypt(&nonce, Payload { msg: plaintext.as_bytes(), aad: column.as_bytes() })
+            .map_err(|_| ClobError::StorageError(format!("Failed to encrypt {}", column)))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}:{}", SEALED_PREFIX, self.active, BASE64.encode(sealed)))
+    }
+
+    /// Plaintext of a value of `column`, sealed or not
+    pub fn open(&self, column: &str, stored: &str) -> ClobResult<String> {
+        let Some((key_id, sealed)) = parse_sealed(stored) else {
+            return Ok(stored.to_string());
+        };
+        let invalid = || ClobError::StorageError(format!("Failed to decrypt {} with key {}", column, key_id));
+
+        let key = self
+            .keys
+            .get(&key_id)
+            .ok_or_else(|| ClobError::StorageError(format!("Encryption key {} of {} is not configured", key_id, column)))?;
+        let sealed = BASE64.decode(sealed).map_err(|_| invalid())?;
+        if sealed.len() < NONCE_LEN {
+            return Err(invalid());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = key
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: column.as_bytes() })
+            .map_err(|_| invalid())?;
+        String::from_utf8(plaintext).map_err(|_| invalid())
+    }
+
+    /// Whether `stored` is already sealed under the active key
+    pub fn is_current(&self, stored: &str) -> bool {
+        parse_sealed(stored).is_some_and(|(key_id, _)| key_id == self.active)
+    }
+}
+
+/// Whether `stored` is a sealed value
+pub fn is_sealed(stored: &str) -> bool {
+    parse_sealed(stored).is_some()
+}
+
+/// Key ID and payload of a sealed value
+fn parse_sealed(stored: &str) -> Option<(u32, &str)> {
+    let (key_id, sealed) = stored.strip_prefix(SEALED_PREFIX)?.split_once(':')?;
+    Some((key_id.parse().ok()?, sealed))
+}
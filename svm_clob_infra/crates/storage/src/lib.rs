@@ -1,15 +1,31 @@
 /// Storage Layer for SVM CLOB Infrastructure
 /// 
 /// This module provides persistent storage for orders, trades, and market state
-/// with support for both PostgreSQL and Redis backends.
+/// with support for both PostgreSQL and Redis backends. Each backend sits behind
+/// a cargo feature of its name, both on by default; without them only the
+/// `Storage` trait and `InMemoryStorage` are built, with no sqlx or redis.
 
 use svm_clob_types::*;
 use async_trait::async_trait;
+#[cfg(feature = "postgres")]
 use sqlx::{PgPool, Row};
+#[cfg(feature = "redis")]
 use redis::AsyncCommands;
+#[cfg(feature = "postgres")]
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::{info, warn, error};
+use std::time::Duration;
+#[cfg(feature = "postgres")]
+use tracing::{info, warn, error, instrument};
+
+#[cfg(feature = "postgres")]
+pub mod crypto;
+pub mod memory;
+#[cfg(feature = "postgres")]
+pub mod snapshot_codec;
+
+#[cfg(feature = "postgres")]
+pub use crypto::FieldCipher;
+pub use memory::InMemoryStorage;
 
 /// Storage trait for abstracting persistence operations
 #[async_trait]
@@ -23,27 +39,497 @@ pub trait Storage: Send + Sync {
     /// Get order by ID
     async fn get_order(&self, order_id: u64) -> ClobResult<Option<Order>>;
 
-    /// Get all orders for a user
-    async fn get_user_orders(&self, user_id: &str) -> ClobResult<Vec<Order>>;
+    /// Get a page of a user's orders, newest first
+    async fn get_user_orders(&self, user_id: &str, page: &Paginated) -> ClobResult<Page<Order>>;
     
     /// Store a trade execution
     async fn store_trade(&self, trade: &TradeExecution) -> ClobResult<()>;
     
-    /// Get recent trades
-    async fn get_recent_trades(&self, limit: u32) -> ClobResult<Vec<TradeExecution>>;
+    /// Get a page of trades, newest first
+    async fn get_recent_trades(&self, page: &Paginated) -> ClobResult<Page<TradeExecution>>;
+    
+    /// Get a page of one market's trades, newest first
+    async fn get_market_trades(&self, market_id: &MarketId, page: &Paginated) -> ClobResult<Page<TradeExecution>>;
+
+    /// Get every fill a user took part in, across markets, oldest first
+    async fn get_user_fills(&self, user: &Pubkey) -> ClobResult<Vec<UserFill>>;
+
+    /// Get the fills a user took part in at `[from, until)`, across markets,
+    /// oldest first
+    async fn get_user_fills_between(&self, user: &Pubkey, from: i64, until: i64) -> ClobResult<Vec<UserFill>> {
+        let mut fills = self.get_user_fills(user).await?;
+        fills.retain(|fill| (from..until).contains(&fill.trade.timestamp));
+        Ok(fills)
+    }
+
+    /// Get candles of one market over its most recent trades, oldest first
+    async fn get_market_candles(
+        &self,
+        market_id: &MarketId,
+        interval: CandleInterval,
+        limit: u32,
+    ) -> ClobResult<Vec<Candle>> {
+        let trades = self.get_market_trades(market_id, &Paginated::first(MAX_PAGE_LIMIT)).await?.items;
+        let mut candles = Candle::from_trades(*market_id, interval, &trades);
+        let skip = candles.len().saturating_sub(limit as usize);
+        Ok(candles.split_off(skip))
+    }
     
     /// Store orderbook snapshot
     async fn store_orderbook_snapshot(&self, snapshot: &OrderBookSnapshot) -> ClobResult<()>;
     
     /// Get latest orderbook snapshot
     async fn get_latest_orderbook_snapshot(&self) -> ClobResult<Option<OrderBookSnapshot>>;
+
+    /// Store a book checkpoint (idempotent per journal sequence)
+    async fn store_book_checkpoint(&self, checkpoint: &BookCheckpoint) -> ClobResult<()>;
+
+    /// Get the newest book checkpoint at or before journal entry `sequence`
+    async fn get_book_checkpoint_before(&self, sequence: u64) -> ClobResult<Option<BookCheckpoint>>;
+
+    /// Get the newest book checkpoint taken at or before `timestamp` (unix seconds)
+    async fn get_book_checkpoint_at_time(&self, timestamp: i64) -> ClobResult<Option<BookCheckpoint>>;
+
+    /// Store an on-chain deposit (idempotent per signature and event index)
+    async fn store_deposit(&self, deposit: &DepositRecord) -> ClobResult<()>;
+
+    /// Store an on-chain withdrawal (idempotent per signature and event index)
+    async fn store_withdrawal(&self, withdrawal: &WithdrawalRecord) -> ClobResult<()>;
+
+    /// Store an on-chain trade settlement (idempotent per signature and event index)
+    async fn store_settled_trade(&self, trade: &SettledTradeRecord) -> ClobResult<()>;
+
+    /// Store an on-chain insurance fund payment (idempotent per signature and event index)
+    async fn store_insurance_fund_event(&self, event: &InsuranceFundEvent) -> ClobResult<()>;
+
+    /// Store a sub-account opened on chain (idempotent per owner)
+    async fn store_sub_account(&self, sub_account: &SubAccount) -> ClobResult<()>;
+
+    /// Get the sub-account trading as `owner`, if `owner` is one
+    async fn get_sub_account(&self, owner: &Pubkey) -> ClobResult<Option<SubAccount>>;
+
+    /// Get the sub-accounts of `wallet` by index
+    async fn get_sub_accounts(&self, wallet: &Pubkey) -> ClobResult<Vec<SubAccount>>;
+
+    /// Store an on-chain transfer between user accounts of one wallet
+    /// (idempotent per signature and event index)
+    async fn store_sub_account_transfer(&self, transfer: &SubAccountTransferRecord) -> ClobResult<()>;
+
+    /// Store an on-chain change of a self-trade prevention group (idempotent
+    /// per signature and event index)
+    async fn store_prevention_group(&self, record: &PreventionGroupRecord) -> ClobResult<()>;
+
+    /// Get the prevention group `owner` is in on `market_id`, if any
+    async fn get_prevention_group(&self, market_id: &MarketId, owner: &Pubkey) -> ClobResult<Option<u32>>;
+
+    /// Record the commitment of a program transaction; a live status only
+    /// moves forward, and a rolled-back transaction seen again is live again
+    async fn store_chain_transaction(&self, transaction: &ChainTransaction) -> ClobResult<()>;
+
+    /// Get a program transaction seen by the indexer
+    async fn get_chain_transaction(&self, signature: &str) -> ClobResult<Option<ChainTransaction>>;
+
+    /// Get pending and confirmed program transactions, oldest slot first
+    async fn get_unfinalized_chain_transactions(&self, limit: u32) -> ClobResult<Vec<ChainTransaction>>;
+
+    /// Delete the deposits, withdrawals, settlements and insurance fund
+    /// payments of a transaction that failed or was dropped, and record it
+    /// with `status`, atomically
+    async fn roll_back_chain_transaction(
+        &self,
+        signature: &str,
+        status: ChainStatus,
+        error: Option<&str>,
+    ) -> ClobResult<()>;
+
+    /// Get the checkpoint of a background service
+    async fn get_checkpoint(&self, service: &str) -> ClobResult<Option<Checkpoint>>;
+
+    /// Save the checkpoint of a background service
+    async fn save_checkpoint(&self, checkpoint: &Checkpoint) -> ClobResult<()>;
+
+    /// Append an event to the engine journal, returning its sequence number
+    async fn append_journal_entry(&self, event: &JournalEvent) -> ClobResult<u64>;
+
+    /// Get journal entries with a sequence greater than `after`, oldest first
+    async fn get_journal_entries(&self, after: u64, limit: u32) -> ClobResult<Vec<JournalEntry>>;
+
+    /// Record the on-chain settlement outcome of a journaled trade
+    async fn store_trade_settlement(&self, settlement: &TradeSettlement) -> ClobResult<()>;
+
+    /// Get the settlement record of a journaled trade
+    async fn get_trade_settlement(&self, journal_sequence: u64) -> ClobResult<Option<TradeSettlement>>;
+
+    /// Get orders that are still open or partially filled, in the order they
+    /// were first stored
+    async fn get_open_orders(&self) -> ClobResult<Vec<Order>>;
+
+    /// Derive expected per-user balances from indexed deposits, withdrawals,
+    /// sub-account transfers and settlements, for every user or only `owner`,
+    /// holding what settlements credit until their transaction is finalized;
+    /// with `until`, only from what happened before it
+    async fn get_ledger_balances(
+        &self,
+        base_mint: &Pubkey,
+        quote_mint: &Pubkey,
+        owner: Option<&Pubkey>,
+        until: Option<i64>,
+    ) -> ClobResult<Vec<LedgerBalance>>;
+
+    /// Get the deposits credited to `owner` at `[from, until)`, oldest first
+    async fn get_user_deposits(&self, owner: &Pubkey, from: i64, until: i64) -> ClobResult<Vec<DepositRecord>>;
+
+    /// Get the withdrawals debited from `owner` at `[from, until)`, oldest first
+    async fn get_user_withdrawals(&self, owner: &Pubkey, from: i64, until: i64) -> ClobResult<Vec<WithdrawalRecord>>;
+
+    /// Get the transfers into or out of `owner`'s account between accounts of
+    /// one wallet at `[from, until)`, oldest first
+    async fn get_user_sub_account_transfers(
+        &self,
+        owner: &Pubkey,
+        from: i64,
+        until: i64,
+    ) -> ClobResult<Vec<SubAccountTransferRecord>>;
+
+    /// Get the total quantity of indexed settled trades
+    async fn get_settled_volume(&self) -> ClobResult<u64>;
+
+    /// Get the sequence of the newest journal entry (0 when empty)
+    async fn get_journal_head(&self) -> ClobResult<u64>;
+
+    /// Get the ID of the newest stored trade (0 when none)
+    async fn get_last_trade_id(&self) -> ClobResult<u64>;
+
+    /// Get the highest order ID reserved in the journal (0 when none)
+    async fn get_reserved_order_ids(&self) -> ClobResult<u64>;
+
+    /// Get confirmed settlements whose signature the indexer has not seen
+    async fn get_unindexed_settlements(&self, limit: u32) -> ClobResult<Vec<TradeSettlement>>;
+
+    /// Store a settlement batch the settler gave up on, returning its ID; a
+    /// batch already dead-lettered with the same first sequence keeps its letter
+    async fn store_dead_letter(&self, letter: &DeadLetter) -> ClobResult<u64>;
+
+    /// Get one dead letter by ID
+    async fn get_dead_letter(&self, id: u64) -> ClobResult<Option<DeadLetter>>;
+
+    /// Get a page of dead letters, oldest first, optionally in one status
+    async fn get_dead_letters(&self, status: Option<DeadLetterStatus>, page: &Paginated) -> ClobResult<Page<DeadLetter>>;
+
+    /// Replace a dead letter's trades, status and outcome if it is still in
+    /// `expected`; returns whether it was
+    async fn update_dead_letter(&self, letter: &DeadLetter, expected: DeadLetterStatus) -> ClobResult<bool>;
+
+    /// Acquire or renew the named lease for `holder` until `ttl` from now
+    ///
+    /// Returns the lease epoch, which increases whenever the lease changes hands
+    /// or lapses, or `None` while another holder's lease is live.
+    async fn try_acquire_lease(&self, name: &str, holder: &str, ttl: Duration) -> ClobResult<Option<u64>>;
+
+    /// Expire a lease held by `holder` so another instance can take it at once
+    async fn release_lease(&self, name: &str, holder: &str) -> ClobResult<()>;
+
+    /// Store a surveillance alert, returning its ID; storing the same finding
+    /// (kind, window and accounts) again returns the existing ID
+    async fn store_surveillance_alert(&self, alert: &SurveillanceAlert) -> ClobResult<u64>;
+
+    /// Get a page of alerts, newest first, optionally of one kind and at or above a score
+    async fn get_surveillance_alerts(
+        &self,
+        kind: Option<AlertKind>,
+        min_score: f64,
+        page: &Paginated,
+    ) -> ClobResult<Page<SurveillanceAlert>>;
+
+    /// Get one alert by ID
+    async fn get_surveillance_alert(&self, id: u64) -> ClobResult<Option<SurveillanceAlert>>;
+
+    /// Store fee accruals; an accrual already stored for the same fill and role is kept
+    async fn store_fee_accruals(&self, accruals: &[FeeAccrual]) -> ClobResult<()>;
+
+    /// Per-user fee totals of fills in `[from, until)`, optionally for one user
+    async fn get_fee_reports(&self, from: i64, until: i64, user: Option<&Pubkey>) -> ClobResult<Vec<FeeReport>>;
+
+    /// Base quantity each user traded, as maker or taker, in `[from, until)`,
+    /// of users who traded at all
+    async fn get_traded_volumes(&self, from: i64, until: i64) -> ClobResult<Vec<(Pubkey, u64)>>;
+
+    /// Replace every user's fee tier with `tiers`
+    async fn replace_fee_tiers(&self, tiers: &[UserFeeTier]) -> ClobResult<()>;
+
+    /// Fee tier of one user, if one was assigned
+    async fn get_fee_tier(&self, user: &Pubkey) -> ClobResult<Option<UserFeeTier>>;
+
+    /// Insurance fund of the market at `orderbook`: fee contributions accrued
+    /// and payments into and out of it on chain
+    async fn get_insurance_fund(&self, orderbook: &Pubkey) -> ClobResult<InsuranceFund>;
+
+    /// Get a page of the payments into and out of the insurance fund of the
+    /// market at `orderbook`, optionally of one kind, newest first
+    async fn get_insurance_fund_events(
+        &self,
+        orderbook: &Pubkey,
+        kind: Option<InsuranceFundEventKind>,
+        page: &Paginated,
+    ) -> ClobResult<Page<InsuranceFundEvent>>;
+
+    /// Record an admin change, returning its ID
+    async fn store_admin_audit_entry(&self, entry: &AdminAuditEntry) -> ClobResult<u64>;
+
+    /// Get a page of admin changes, newest first
+    async fn get_admin_audit_entries(&self, page: &Paginated) -> ClobResult<Page<AdminAuditEntry>>;
+
+    /// Record a mutating API call, returning its ID
+    async fn store_api_audit_entry(&self, entry: &ApiAuditEntry) -> ClobResult<u64>;
+
+    /// Get a page of the API calls matching `query`, newest first
+    async fn get_api_audit_entries(&self, query: &ApiAuditQuery, page: &Paginated) -> ClobResult<Page<ApiAuditEntry>>;
+
+    /// Create a tenant or replace its settings; `created_at` of an existing
+    /// tenant is kept
+    async fn store_tenant(&self, tenant: &Tenant) -> ClobResult<()>;
+
+    /// Get one tenant by ID
+    async fn get_tenant(&self, id: &str) -> ClobResult<Option<Tenant>>;
+
+    /// Get the tenant holding a referral code
+    async fn get_tenant_by_referral_code(&self, code: &str) -> ClobResult<Option<Tenant>>;
+
+    /// Get a page of tenants by ID
+    async fn get_tenants(&self, page: &Paginated) -> ClobResult<Page<Tenant>>;
+
+    /// Store a newly issued API key
+    async fn store_tenant_api_key(&self, key: &TenantApiKey) -> ClobResult<()>;
+
+    /// Get the API key with hash `key_hash`, revoked or not
+    async fn get_tenant_api_key(&self, key_hash: &str) -> ClobResult<Option<TenantApiKey>>;
+
+    /// Get every API key of a tenant, oldest first
+    async fn get_tenant_api_keys(&self, tenant_id: &str) -> ClobResult<Vec<TenantApiKey>>;
+
+    /// Revoke an API key of a tenant; returns whether a live key was revoked
+    async fn revoke_tenant_api_key(&self, tenant_id: &str, key_id: &str, revoked_at: i64) -> ClobResult<bool>;
+
+    /// Set the feed entitlement of a live API key of a tenant; returns
+    /// whether the key was found
+    async fn set_tenant_api_key_entitlement(
+        &self,
+        tenant_id: &str,
+        key_id: &str,
+        entitlement: FeedEntitlement,
+    ) -> ClobResult<bool>;
+
+    /// Add metered usage to the stored counts of each tenant and period
+    async fn add_tenant_usage(&self, usage: &[TenantUsage]) -> ClobResult<()>;
+
+    /// Get a tenant's usage over the periods starting in `[from, until)`, oldest first
+    async fn get_tenant_usage(&self, tenant_id: &str, from: i64, until: i64) -> ClobResult<Vec<TenantUsage>>;
+
+    /// Store a closed incentive epoch with its reports; an epoch already stored is kept
+    async fn store_incentive_epoch(&self, epoch: &IncentiveEpoch, reports: &[IncentiveReport]) -> ClobResult<()>;
+
+    /// Get the newest closed incentive epoch
+    async fn get_last_incentive_epoch(&self) -> ClobResult<Option<IncentiveEpoch>>;
+
+    /// Get a page of closed incentive epochs, newest first
+    async fn get_incentive_epochs(&self, page: &Paginated) -> ClobResult<Page<IncentiveEpoch>>;
+
+    /// Get a page of the incentive reports matching `query`, newest epoch
+    /// first and largest reward first within an epoch
+    async fn get_incentive_reports(&self, query: &IncentiveQuery, page: &Paginated) -> ClobResult<Page<IncentiveReport>>;
+
+    /// Register a designated market maker or replace its obligations;
+    /// `registered_at` of a registered maker is kept
+    async fn store_market_maker(&self, obligation: &MarketMakerObligation) -> ClobResult<()>;
+
+    /// Get the obligations of a designated market maker
+    async fn get_market_maker(&self, maker: &Pubkey) -> ClobResult<Option<MarketMakerObligation>>;
+
+    /// Get a page of designated market makers by key
+    async fn get_market_makers(&self, page: &Paginated) -> ClobResult<Page<MarketMakerObligation>>;
+
+    /// Deregister a designated market maker, keeping its scorecards; returns
+    /// whether it was registered
+    async fn remove_market_maker(&self, maker: &Pubkey) -> ClobResult<bool>;
+
+    /// Store a closed obligation period with its scorecards; a period already
+    /// stored is kept
+    async fn store_obligation_period(
+        &self,
+        period: &ObligationPeriod,
+        scorecards: &[MarketMakerScorecard],
+    ) -> ClobResult<()>;
+
+    /// Get the newest closed obligation period
+    async fn get_last_obligation_period(&self) -> ClobResult<Option<ObligationPeriod>>;
+
+    /// Get a page of the scorecards matching `query`, newest period first and
+    /// by maker within a period
+    async fn get_market_maker_scorecards(
+        &self,
+        query: &ScorecardQuery,
+        page: &Paginated,
+    ) -> ClobResult<Page<MarketMakerScorecard>>;
+
+    /// Store a generated statement day with its users' statements; a day
+    /// already stored is kept
+    async fn store_statement_day(&self, day: &StatementDay, statements: &[StatementArtifacts]) -> ClobResult<()>;
+
+    /// Get the newest generated statement day
+    async fn get_last_statement_day(&self) -> ClobResult<Option<StatementDay>>;
+
+    /// Get a page of a user's statements, newest first
+    async fn get_statements(&self, owner: &Pubkey, page: &Paginated) -> ClobResult<Page<StatementSummary>>;
+
+    /// Get a user's statement of the day starting at `period_start`, rendered
+    /// in `format`
+    async fn get_statement_artifact(
+        &self,
+        owner: &Pubkey,
+        period_start: i64,
+        format: StatementFormat,
+    ) -> ClobResult<Option<Vec<u8>>>;
+
+    /// Record sequence mappings; mappings already recorded are kept
+    async fn store_sequence_mappings(&self, mappings: &[SequenceMapping]) -> ClobResult<()>;
+
+    /// Get a page of the sequence mappings matching `lookup` in journal order,
+    /// with the settlement of fills
+    async fn get_sequence_mappings(&self, lookup: &SequenceLookup, page: &Paginated) -> ClobResult<Page<SequenceMapping>>;
+
+    /// Get the status an operator last set for a market, if any
+    async fn get_market_status(&self, market_id: &MarketId) -> ClobResult<Option<MarketStatus>>;
+
+    /// Record the status of a market, replacing the previous one
+    async fn set_market_status(&self, market_id: &MarketId, status: MarketStatus, updated_at: i64) -> ClobResult<()>;
+
+    /// Store a suspension, replacing the one its subject had
+    async fn store_suspension(&self, suspension: &Suspension) -> ClobResult<()>;
+
+    /// Lift the suspension of `subject`; returns whether there was one
+    async fn lift_suspension(&self, subject: &SuspensionSubject) -> ClobResult<bool>;
+
+    /// Get every suspension in force, oldest first
+    async fn get_suspensions(&self) -> ClobResult<Vec<Suspension>>;
+
+    /// Claim `owner`'s signed order `nonce` until `expiry`, for replay
+    /// protection while its shared cache is unavailable; false when it is
+    /// claimed already and unexpired at `now`
+    async fn claim_signed_nonce(&self, owner: &Pubkey, nonce: u64, expiry: i64, now: i64) -> ClobResult<bool>;
+
+    /// Whether `owner`'s signed order `nonce` holds a claim unexpired at `now`
+    async fn signed_nonce_claimed(&self, owner: &Pubkey, nonce: u64, now: i64) -> ClobResult<bool>;
+
+    /// Store a plain transfer into a vault as first seen (idempotent per
+    /// signature and instruction index; a stored transfer is left as it is)
+    async fn store_vault_transfer(&self, transfer: &VaultTransfer) -> ClobResult<()>;
+
+    /// Record the progress of a stored vault transfer: its status,
+    /// confirmations, reason, credit signature and update time
+    async fn update_vault_transfer(&self, transfer: &VaultTransfer) -> ClobResult<()>;
+
+    async fn get_vault_transfer(&self, signature: &str, instruction_index: u8) -> ClobResult<Option<VaultTransfer>>;
+
+    /// Get up to `limit` vault transfers in `status`, oldest first
+    async fn get_vault_transfers_by_status(&self, status: VaultTransferStatus, limit: u32) -> ClobResult<Vec<VaultTransfer>>;
+
+    /// Get a page of the vault transfers sent by `sender`, newest first
+    async fn get_user_vault_transfers(&self, sender: &Pubkey, page: &Paginated) -> ClobResult<Page<VaultTransfer>>;
+
+    /// Get a trade as it currently stands
+    async fn get_trade(&self, trade_id: u64) -> ClobResult<Option<TradeExecution>>;
+
+    /// Get the `TradeExecuted` journal entry of a trade
+    async fn get_trade_journal_entry(&self, trade_id: u64) -> ClobResult<Option<JournalEntry>>;
+
+    /// Store an adjustment of a trade and move the stored trade to the
+    /// version, status and price it produced
+    async fn store_trade_adjustment(&self, adjustment: &TradeAdjustment) -> ClobResult<()>;
+
+    /// Record the progress of a stored trade adjustment's reversal: its
+    /// status, signature, error and update time
+    async fn update_trade_adjustment(&self, adjustment: &TradeAdjustment) -> ClobResult<()>;
+
+    /// Get every adjustment of a trade, oldest first
+    async fn get_trade_adjustments(&self, trade_id: u64) -> ClobResult<Vec<TradeAdjustment>>;
+
+    /// Get up to `limit` trade adjustments whose reversal is in `reversal`,
+    /// oldest first
+    async fn get_trade_adjustments_by_reversal(&self, reversal: ReversalStatus, limit: u32) -> ClobResult<Vec<TradeAdjustment>>;
+
+    /// Get a page of trade adjustments, optionally filtered by reversal
+    /// status, newest first
+    async fn get_recent_trade_adjustments(&self, reversal: Option<ReversalStatus>, page: &Paginated) -> ClobResult<Page<TradeAdjustment>>;
+
+    /// Store a settlement adjustment observed on chain and mark its trade
+    /// adjustment reversed (idempotent per signature and event index)
+    async fn store_settlement_adjustment(&self, record: &SettlementAdjustmentRecord) -> ClobResult<()>;
+
+    /// Store a compressed receipt tree observed on chain (idempotent per tree)
+    async fn store_receipt_tree(&self, record: &ReceiptTreeRecord) -> ClobResult<()>;
+
+    /// Get a compressed receipt tree by its Merkle tree account
+    async fn get_receipt_tree(&self, merkle_tree: &Pubkey) -> ClobResult<Option<ReceiptTreeRecord>>;
+
+    /// Store compressed receipts observed on chain (idempotent per tree and
+    /// leaf index)
+    async fn store_compressed_receipts(&self, receipts: &[CompressedReceipt]) -> ClobResult<()>;
+
+    /// Get a page of an owner's compressed receipts, newest first
+    async fn get_compressed_receipts(&self, owner: &Pubkey, page: &Paginated) -> ClobResult<Page<CompressedReceipt>>;
+
+    /// Get the compressed receipt at a leaf of a tree
+    async fn get_compressed_receipt(&self, merkle_tree: &Pubkey, leaf_index: u64) -> ClobResult<Option<CompressedReceipt>>;
+
+    /// Get the leaf hashes of a tree from the first up to the first one not
+    /// indexed yet
+    async fn get_receipt_leaves(&self, merkle_tree: &Pubkey) -> ClobResult<Vec<[u8; 32]>>;
+
+    /// Get the fee accruals of the fill journaled at `journal_sequence`
+    async fn get_fee_accruals(&self, journal_sequence: u64) -> ClobResult<Vec<FeeAccrual>>;
+
+    /// Roll engine state back for a point-in-time restore
+    ///
+    /// Removes journal entries after `journal_sequence` together with the
+    /// settlements, fee accruals, sequence mappings, surveillance alerts,
+    /// book checkpoints and trade adjustments derived from them, returning
+    /// adjusted trades to the version before the first adjustment removed, and
+    /// orders, trades and book snapshots after `timestamp`, as well as the
+    /// incentive epochs, obligation periods and statement days ending after it. The checkpoints of
+    /// `journal_consumers`, which hold journal sequences, are clamped to
+    /// `journal_sequence`; the next journal entry gets `journal_sequence + 1`.
+    /// On-chain records and other checkpoints are kept.
+    async fn rewind(&self, journal_sequence: u64, timestamp: i64, journal_consumers: &[String]) -> ClobResult<()>;
 }
 
+/// Unique index over the client order IDs of open orders, per market and owner
+#[cfg(feature = "postgres")]
+const OPEN_CLIENT_ORDER_ID_INDEX: &str = "idx_orders_open_client_order_id";
+
 /// PostgreSQL storage implementation
+#[cfg(feature = "postgres")]
 pub struct PostgresStorage {
     pool: PgPool,
+    /// Time source for journal timestamps
+    clock: Arc<dyn Clock>,
+    /// Keys of the encrypted columns; written in plaintext when unset
+    cipher: Option<Arc<FieldCipher>>,
 }
 
+/// Columns sealed by a `FieldCipher`, by table and key column
+#[cfg(feature = "postgres")]
+const SEALED_COLUMNS: &[(&str, &str, &[&str])] = &[
+    ("tenants", "id", &["name"]),
+    ("tenant_api_keys", "key_id", &["label"]),
+    ("api_audit", "id", &["params", "result"]),
+    ("admin_audit_log", "id", &["before", "after"]),
+];
+
+/// Rows read per batch while re-encrypting
+#[cfg(feature = "postgres")]
+const REENCRYPT_BATCH: i64 = 500;
+
+#[cfg(feature = "postgres")]
 impl PostgresStorage {
     /// Create new PostgreSQL storage
     pub async fn new(database_url: &str) -> ClobResult<Self> {
@@ -57,20 +543,175 @@ impl PostgresStorage {
             .await
             .map_err(|e| ClobError::StorageError(e.to_string()))?;
         
-        Ok(Self { pool })
+        Ok(Self { pool, clock: system_clock(), cipher: None })
+    }
+
+    /// Stamp journal entries from `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Encrypt the columns listed in `SEALED_COLUMNS` with `cipher`
+    pub fn with_cipher(mut self, cipher: Arc<FieldCipher>) -> Self {
+        self.cipher = Some(cipher);
+        self
+    }
+
+    /// `value` as written to `column`
+    fn seal(&self, column: &str, value: &str) -> ClobResult<String> {
+        match &self.cipher {
+            Some(cipher) => cipher.seal(column, value),
+            None => Ok(value.to_string()),
+        }
+    }
+
+    /// Plaintext of `stored`, read from `column`
+    fn open(&self, column: &str, stored: String) -> ClobResult<String> {
+        match &self.cipher {
+            Some(cipher) => cipher.open(column, &stored),
+            None if crypto::is_sealed(&stored) => Err(ClobError::StorageError(format!(
+                "{} is encrypted but no encryption keys are configured",
+                column
+            ))),
+            None => Ok(stored),
+        }
+    }
+
+    /// Seal every value of the encrypted columns that is not sealed under the
+    /// active key, after enabling encryption or rotating keys; returns the
+    /// number of rows rewritten
+    pub async fn reencrypt(&self) -> ClobResult<u64> {
+        let Some(cipher) = &self.cipher else {
+            return Err(ClobError::StorageError("No encryption keys are configured".to_string()));
+        };
+
+        let mut rewritten = 0;
+        for &(table, key, columns) in SEALED_COLUMNS {
+            let mut after = String::new();
+            loop {
+                // Keys are compared as text, which orders numeric IDs oddly
+                // but still visits every row once
+                let rows = sqlx::query(&format!(
+                    "SELECT {key}::TEXT AS key, {columns} FROM {table} WHERE {key}::TEXT > $1 ORDER BY {key}::TEXT LIMIT $2",
+                    columns = columns.join(", "),
+                ))
+                .bind(&after)
+                .bind(REENCRYPT_BATCH)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| ClobError::StorageError(e.to_string()))?;
+                let Some(last) = rows.last() else {
+                    break;
+                };
+                after = last.try_get("key").map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+                for row in &rows {
+                    let mut values = Vec::new();
+                    for &column in columns {
+                        let stored: Option<String> =
+                            row.try_get(column).map_err(|e| ClobError::StorageError(e.to_string()))?;
+                        values.push(stored);
+                    }
+                    if values.iter().flatten().all(|stored| cipher.is_current(stored)) {
+                        continue;
+                    }
+
+                    let mut update = sqlx::query_builder::QueryBuilder::new(format!("UPDATE {} SET ", table));
+                    for (index, (&column, stored)) in columns.iter().zip(&values).enumerate() {
+                        let qualified = format!("{}.{}", table, column);
+                        let resealed = match stored {
+                            Some(stored) => Some(cipher.seal(&qualified, &cipher.open(&qualified, stored)?)?),
+                            None => None,
+                        };
+                        if index > 0 {
+                            update.push(", ");
+                        }
+                        update.push(format!("{} = ", column)).push_bind(resealed);
+                    }
+                    let id: String = row.try_get("key").map_err(|e| ClobError::StorageError(e.to_string()))?;
+                    update.push(format!(" WHERE {}::TEXT = ", key)).push_bind(id);
+                    // A row changed since it was read was written under the active key
+                    for (&column, stored) in columns.iter().zip(values) {
+                        update.push(format!(" AND {} IS NOT DISTINCT FROM ", column)).push_bind(stored);
+                    }
+                    rewritten += update
+                        .build()
+                        .execute(&self.pool)
+                        .await
+                        .map_err(|e| ClobError::StorageError(e.to_string()))?
+                        .rows_affected();
+                }
+            }
+        }
+
+        info!("Re-encrypted {} rows under key {}", rewritten, cipher.active_key_id());
+        Ok(rewritten)
+    }
+
+    /// Fills of `user` at `[from, until)`, unbounded where `None`
+    async fn user_fills(&self, user: &Pubkey, from: Option<i64>, until: Option<i64>) -> ClobResult<Vec<UserFill>> {
+        // A self-trade joins both of its orders and yields one fill per side
+        let rows = sqlx::query!(
+            r#"
+            SELECT t.trade_id, t.market_id, t.maker_order_id, t.taker_order_id,
+                   t.price, t.quantity, t.timestamp, t.maker_side,
+                   t.received_at_us, t.matched_at_us, t.version, t.status,
+                   (o.order_id = t.maker_order_id) AS "is_maker!"
+            FROM trades t
+            JOIN orders o ON o.order_id = t.maker_order_id OR o.order_id = t.taker_order_id
+            WHERE o.owner = $1
+              AND ($2::BIGINT IS NULL OR t.timestamp >= $2)
+              AND ($3::BIGINT IS NULL OR t.timestamp < $3)
+            ORDER BY t.timestamp ASC, t.trade_id ASC
+            "#,
+            user.to_string(),
+            from,
+            until
+        )
+
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let mut fills = Vec::new();
+        for row in rows {
+            fills.push(UserFill {
+                trade: TradeExecution {
+                    trade_id: row.trade_id as u64,
+                    market_id: parse_market_id(&row.market_id)?,
+                    maker_order_id: row.maker_order_id as u64,
+                    taker_order_id: row.taker_order_id as u64,
+                    price: row.price as u64,
+                    quantity: row.quantity as u64,
+                    timestamp: row.timestamp,
+                    maker_side: OrderSide::try_from(row.maker_side as u8).map_err(|_| ClobError::InvalidOrderSide)?,
+                    received_at_us: row.received_at_us,
+                    matched_at_us: row.matched_at_us,
+                    version: row.version as u32,
+                    status: trade_status(row.status)?,
+                },
+                role: if row.is_maker { LiquidityRole::Maker } else { LiquidityRole::Taker },
+            });
+        }
+        Ok(fills)
     }
 }
 
+#[cfg(feature = "postgres")]
 #[async_trait]
 impl Storage for PostgresStorage {
+    #[instrument(name = "storage.store_order", skip_all, fields(order_id = order.order_id))]
     async fn store_order(&self, order: &Order) -> ClobResult<()> {
         sqlx::query!(
             r#"
             INSERT INTO orders (
                 order_id, owner, price, quantity, remaining_quantity, 
                 timestamp, client_order_id, expiry_timestamp, side, 
-                order_type, status, self_trade_behavior, time_in_force
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                order_type, status, self_trade_behavior, time_in_force, market_id,
+                max_slippage_bps, request_id, filled_notional, avg_fill_price,
+                prevention_group_id
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19)
             "#,
             order.order_id as i64,
             order.owner.to_string(),
@@ -84,26 +725,45 @@ impl Storage for PostgresStorage {
             order.order_type as i16,
             order.status as i16,
             order.self_trade_behavior as i16,
-            order.time_in_force as i16
+            order.time_in_force as i16,
+            order.market_id.to_string(),
+            order.max_slippage_bps.map(|bps| bps as i32),
+            svm_clob_telemetry::current_request_id(),
+            order.filled_notional.0.to_string(),
+            order.avg_fill_price.map(|price| price as i64),
+            order.prevention_group_id.map(|group| group as i64)
         )
         .execute(&self.pool)
         .await
-        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        .map_err(|e| match &e {
+            sqlx::Error::Database(db) if db.constraint() == Some(OPEN_CLIENT_ORDER_ID_INDEX) => {
+                ClobError::DuplicateClientOrderId(format!(
+                    "{} already has an open order with client order ID {}",
+                    order.owner, order.client_order_id
+                ))
+            }
+            _ => ClobError::StorageError(e.to_string()),
+        })?;
         
         info!("Stored order {}", order.order_id);
         Ok(())
     }
     
+    #[instrument(name = "storage.update_order", skip_all, fields(order_id = order.order_id))]
     async fn update_order(&self, order: &Order) -> ClobResult<()> {
         sqlx::query!(
             r#"
             UPDATE orders SET 
                 remaining_quantity = $1, 
-                status = $2
-            WHERE order_id = $3
+                status = $2,
+                filled_notional = $3,
+                avg_fill_price = $4
+            WHERE order_id = $5
             "#,
             order.remaining_quantity as i64,
             order.status as i16,
+            order.filled_notional.0.to_string(),
+            order.avg_fill_price.map(|price| price as i64),
             order.order_id as i64
         )
         .execute(&self.pool)
@@ -125,6 +785,7 @@ impl Storage for PostgresStorage {
         if let Some(row) = row {
             Ok(Some(Order {
                 order_id: row.order_id as u64,
+                market_id: parse_market_id(&row.market_id)?,
                 owner: row.owner.parse().map_err(|e| ClobError::StorageError(format!("Invalid pubkey: {}", e)))?,
                 price: row.price as u64,
                 quantity: row.quantity as u64,
@@ -137,16 +798,22 @@ impl Storage for PostgresStorage {
                 status: OrderStatus::try_from(row.status as u8).map_err(|_| ClobError::StorageError("Invalid status".to_string()))?,
                 self_trade_behavior: SelfTradeBehavior::try_from(row.self_trade_behavior as u8).map_err(|_| ClobError::StorageError("Invalid self trade behavior".to_string()))?,
                 time_in_force: TimeInForce::try_from(row.time_in_force as u8).map_err(|_| ClobError::StorageError("Invalid time in force".to_string()))?,
+                max_slippage_bps: row.max_slippage_bps.map(|bps| bps as u32),
+                filled_notional: parse_notional(&row.filled_notional)?,
+                avg_fill_price: row.avg_fill_price.map(|price| price as u64),
+                prevention_group_id: row.prevention_group_id.map(|group| group as u32),
             }))
         } else {
             Ok(None)
         }
     }
 
-    async fn get_user_orders(&self, user_id: &str) -> ClobResult<Vec<Order>> {
+    async fn get_user_orders(&self, user_id: &str, page: &Paginated) -> ClobResult<Page<Order>> {
         let rows = sqlx::query!(
-            "SELECT * FROM orders WHERE owner = $1 ORDER BY timestamp DESC",
-            user_id
+            "SELECT * FROM orders WHERE owner = $1 ORDER BY timestamp DESC, order_id DESC LIMIT $2 OFFSET $3",
+            user_id,
+            page.limit() as i64 + 1,
+            page.offset()? as i64
         )
         .fetch_all(&self.pool)
         .await
@@ -156,6 +823,7 @@ impl Storage for PostgresStorage {
         for row in rows {
             orders.push(Order {
                 order_id: row.order_id as u64,
+                market_id: parse_market_id(&row.market_id)?,
                 owner: row.owner.parse().map_err(|e| ClobError::StorageError(format!("Invalid pubkey: {}", e)))?,
                 price: row.price as u64,
                 quantity: row.quantity as u64,
@@ -168,38 +836,53 @@ impl Storage for PostgresStorage {
                 status: OrderStatus::try_from(row.status as u8).map_err(|_| ClobError::StorageError("Invalid status".to_string()))?,
                 self_trade_behavior: SelfTradeBehavior::try_from(row.self_trade_behavior as u8).map_err(|_| ClobError::StorageError("Invalid self trade behavior".to_string()))?,
                 time_in_force: TimeInForce::try_from(row.time_in_force as u8).map_err(|_| ClobError::StorageError("Invalid time in force".to_string()))?,
+                max_slippage_bps: row.max_slippage_bps.map(|bps| bps as u32),
+                filled_notional: parse_notional(&row.filled_notional)?,
+                avg_fill_price: row.avg_fill_price.map(|price| price as u64),
+                prevention_group_id: row.prevention_group_id.map(|group| group as u32),
             });
         }
-        Ok(orders)
+        Page::from_fetched(orders, page)
     }
     
+    #[instrument(name = "storage.store_trade", skip_all, fields(trade_id = trade.trade_id))]
     async fn store_trade(&self, trade: &TradeExecution) -> ClobResult<()> {
         sqlx::query!(
             r#"
             INSERT INTO trades (
                 maker_order_id, taker_order_id, price, quantity, 
-                timestamp, maker_side
-            ) VALUES ($1, $2, $3, $4, $5, $6)
+                timestamp, maker_side, market_id, trade_id,
+                received_at_us, matched_at_us, request_id, version, status
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            ON CONFLICT (trade_id) DO NOTHING
             "#,
             trade.maker_order_id as i64,
             trade.taker_order_id as i64,
             trade.price as i64,
             trade.quantity as i64,
             trade.timestamp,
-            trade.maker_side as i16
+            trade.maker_side as i16,
+            trade.market_id.to_string(),
+            trade.trade_id as i64,
+            trade.received_at_us,
+            trade.matched_at_us,
+            svm_clob_telemetry::current_request_id(),
+            trade.version as i32,
+            trade.status as i16
         )
         .execute(&self.pool)
         .await
         .map_err(|e| ClobError::StorageError(e.to_string()))?;
         
-        info!("Stored trade: maker {} taker {}", trade.maker_order_id, trade.taker_order_id);
+        info!("Stored trade {}: maker {} taker {}", trade.trade_id, trade.maker_order_id, trade.taker_order_id);
         Ok(())
     }
     
-    async fn get_recent_trades(&self, limit: u32) -> ClobResult<Vec<TradeExecution>> {
+    async fn get_recent_trades(&self, page: &Paginated) -> ClobResult<Page<TradeExecution>> {
         let rows = sqlx::query!(
-            "SELECT * FROM trades ORDER BY timestamp DESC LIMIT $1",
-            limit as i64
+            "SELECT * FROM trades ORDER BY trade_id DESC LIMIT $1 OFFSET $2",
+            page.limit() as i64 + 1,
+            page.offset()? as i64
         )
         .fetch_all(&self.pool)
         .await
@@ -208,34 +891,77 @@ impl Storage for PostgresStorage {
         let mut trades = Vec::new();
         for row in rows {
             trades.push(TradeExecution {
+                trade_id: row.trade_id as u64,
+                market_id: parse_market_id(&row.market_id)?,
                 maker_order_id: row.maker_order_id as u64,
                 taker_order_id: row.taker_order_id as u64,
                 price: row.price as u64,
                 quantity: row.quantity as u64,
                 timestamp: row.timestamp,
                 maker_side: OrderSide::try_from(row.maker_side as u8).map_err(|_| ClobError::InvalidOrderSide)?,
+                received_at_us: row.received_at_us,
+                matched_at_us: row.matched_at_us,
+                version: row.version as u32,
+                status: trade_status(row.status)?,
             });
         }
         
-        Ok(trades)
+        Page::from_fetched(trades, page)
+    }
+    
+    async fn get_market_trades(&self, market_id: &MarketId, page: &Paginated) -> ClobResult<Page<TradeExecution>> {
+        let rows = sqlx::query!(
+            "SELECT * FROM trades WHERE market_id = $1 ORDER BY trade_id DESC LIMIT $2 OFFSET $3",
+            market_id.to_string(),
+            page.limit() as i64 + 1,
+            page.offset()? as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let mut trades = Vec::new();
+        for row in rows {
+            trades.push(TradeExecution {
+                trade_id: row.trade_id as u64,
+                market_id: parse_market_id(&row.market_id)?,
+                maker_order_id: row.maker_order_id as u64,
+                taker_order_id: row.taker_order_id as u64,
+                price: row.price as u64,
+                quantity: row.quantity as u64,
+                timestamp: row.timestamp,
+                maker_side: OrderSide::try_from(row.maker_side as u8).map_err(|_| ClobError::InvalidOrderSide)?,
+                received_at_us: row.received_at_us,
+                matched_at_us: row.matched_at_us,
+                version: row.version as u32,
+                status: trade_status(row.status)?,
+            });
+        }
+
+        Page::from_fetched(trades, page)
+    }
+
+    async fn get_user_fills(&self, user: &Pubkey) -> ClobResult<Vec<UserFill>> {
+        self.user_fills(user, None, None).await
+    }
+
+    async fn get_user_fills_between(&self, user: &Pubkey, from: i64, until: i64) -> ClobResult<Vec<UserFill>> {
+        self.user_fills(user, Some(from), Some(until)).await
     }
     
     async fn store_orderbook_snapshot(&self, snapshot: &OrderBookSnapshot) -> ClobResult<()> {
-        let bids_json = serde_json::to_string(&snapshot.bids)
-            .map_err(|e| ClobError::SerializationError(e.to_string()))?;
-        let asks_json = serde_json::to_string(&snapshot.asks)
-            .map_err(|e| ClobError::SerializationError(e.to_string()))?;
-        
+        let levels = snapshot_codec::encode_levels(&snapshot.bids, &snapshot.asks)?;
+
         sqlx::query!(
             r#"
             INSERT INTO orderbook_snapshots (
-                sequence_number, timestamp, bids, asks
+                sequence_number, timestamp, levels, market_id
             ) VALUES ($1, $2, $3, $4)
             "#,
             snapshot.sequence_number as i64,
             snapshot.timestamp,
-            bids_json,
-            asks_json
+            levels,
+            snapshot.market_id.to_string()
         )
         .execute(&self.pool)
         .await
@@ -246,19 +972,30 @@ impl Storage for PostgresStorage {
     
     async fn get_latest_orderbook_snapshot(&self) -> ClobResult<Option<OrderBookSnapshot>> {
         let row = sqlx::query!(
-            "SELECT * FROM orderbook_snapshots ORDER BY sequence_number DESC LIMIT 1"
+            r#"
+            SELECT sequence_number, timestamp, levels, bids, asks, market_id
+            FROM orderbook_snapshots
+            ORDER BY sequence_number DESC
+            LIMIT 1
+            "#
         )
         .fetch_optional(&self.pool)
         .await
         .map_err(|e| ClobError::StorageError(e.to_string()))?;
         
         if let Some(row) = row {
-            let bids: Vec<(u64, u64)> = serde_json::from_str(&row.bids)
-                .map_err(|e| ClobError::SerializationError(e.to_string()))?;
-            let asks: Vec<(u64, u64)> = serde_json::from_str(&row.asks)
-                .map_err(|e| ClobError::SerializationError(e.to_string()))?;
+            // Snapshots written before the binary encoding keep JSON arrays
+            let (bids, asks) = match (row.levels, row.bids, row.asks) {
+                (Some(levels), _, _) => snapshot_codec::decode_levels(&levels)?,
+                (None, Some(bids), Some(asks)) => (
+                    serde_json::from_str(&bids).map_err(|e| ClobError::SerializationError(e.to_string()))?,
+                    serde_json::from_str(&asks).map_err(|e| ClobError::SerializationError(e.to_string()))?,
+                ),
+                _ => return Err(ClobError::StorageError("Order book snapshot without levels".to_string())),
+            };
             
             Ok(Some(OrderBookSnapshot {
+                market_id: parse_market_id(&row.market_id)?,
                 bids,
                 asks,
                 sequence_number: row.sequence_number as u64,
@@ -268,128 +1005,3573 @@ impl Storage for PostgresStorage {
             Ok(None)
         }
     }
-}
-
-/// Redis storage for fast caching and real-time data
-pub struct RedisStorage {
-    client: redis::Client,
-}
 
-impl RedisStorage {
-    /// Create new Redis storage
-    pub fn new(redis_url: &str) -> ClobResult<Self> {
-        let client = redis::Client::open(redis_url)
-            .map_err(|e| ClobError::StorageError(e.to_string()))?;
-        
-        Ok(Self { client })
-    }
-    
-    /// Cache order book snapshot in Redis
-    pub async fn cache_orderbook_snapshot(&self, snapshot: &OrderBookSnapshot) -> ClobResult<()> {
-        let mut conn = self.client.get_async_connection()
-            .await
-            .map_err(|e| ClobError::StorageError(e.to_string()))?;
-        
-        let snapshot_json = serde_json::to_string(snapshot)
+    async fn store_book_checkpoint(&self, checkpoint: &BookCheckpoint) -> ClobResult<()> {
+        let orders_json = serde_json::to_string(&checkpoint.orders)
             .map_err(|e| ClobError::SerializationError(e.to_string()))?;
-        
-        conn.set("orderbook:latest", &snapshot_json)
-            .await
-            .map_err(|e| ClobError::StorageError(e.to_string()))?;
-        
-        // Set expiry for cache
-        conn.expire("orderbook:latest", 300)
-            .await
-            .map_err(|e| ClobError::StorageError(e.to_string()))?;
-        
-        Ok(())
-    }
-    
-    /// Get cached order book snapshot
-    pub async fn get_cached_orderbook_snapshot(&self) -> ClobResult<Option<OrderBookSnapshot>> {
-        let mut conn = self.client.get_async_connection()
-            .await
-            .map_err(|e| ClobError::StorageError(e.to_string()))?;
-        
-        let snapshot_json: Option<String> = conn.get("orderbook:latest")
-            .await
-            .map_err(|e| ClobError::StorageError(e.to_string()))?;
-        
-        if let Some(json) = snapshot_json {
-            let snapshot = serde_json::from_str(&json)
-                .map_err(|e| ClobError::SerializationError(e.to_string()))?;
-            Ok(Some(snapshot))
-        } else {
-            Ok(None)
-        }
-    }
-}
 
-// Add trait implementations for common conversions
-impl TryFrom<u8> for OrderSide {
-    type Error = ();
-    
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
-        match value {
-            0 => Ok(OrderSide::Bid),
-            1 => Ok(OrderSide::Ask),
-            _ => Err(()),
-        }
-    }
-}
+        sqlx::query!(
+            r#"
+            INSERT INTO book_checkpoints (journal_sequence, market_id, orders, timestamp)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (journal_sequence) DO NOTHING
+            "#,
+            checkpoint.journal_sequence as i64,
+            checkpoint.market_id.to_string(),
+            orders_json,
+            checkpoint.timestamp
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
 
-impl TryFrom<u8> for OrderType {
-    type Error = ();
-    
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
-        match value {
-            0 => Ok(OrderType::Limit),
-            1 => Ok(OrderType::Market),
-            2 => Ok(OrderType::PostOnly),
-            _ => Err(()),
-        }
+        Ok(())
     }
-}
 
-impl TryFrom<u8> for OrderStatus {
-    type Error = ();
-    
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
-        match value {
-            0 => Ok(OrderStatus::Open),
-            1 => Ok(OrderStatus::PartiallyFilled),
-            2 => Ok(OrderStatus::Filled),
-            3 => Ok(OrderStatus::Cancelled),
-            4 => Ok(OrderStatus::Expired),
-            _ => Err(()),
+    async fn get_book_checkpoint_before(&self, sequence: u64) -> ClobResult<Option<BookCheckpoint>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT journal_sequence, market_id, orders, timestamp
+            FROM book_checkpoints
+            WHERE journal_sequence <= $1
+            ORDER BY journal_sequence DESC
+            LIMIT 1
+            "#,
+            sequence as i64
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        Ok(Some(BookCheckpoint {
+            market_id: parse_market_id(&row.market_id)?,
+            journal_sequence: row.journal_sequence as u64,
+            orders: serde_json::from_str(&row.orders).map_err(|e| ClobError::SerializationError(e.to_string()))?,
+            timestamp: row.timestamp,
+        }))
+    }
+
+    async fn get_book_checkpoint_at_time(&self, timestamp: i64) -> ClobResult<Option<BookCheckpoint>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT journal_sequence, market_id, orders, timestamp
+            FROM book_checkpoints
+            WHERE timestamp <= $1
+            ORDER BY timestamp DESC, journal_sequence DESC
+            LIMIT 1
+            "#,
+            timestamp
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        Ok(Some(BookCheckpoint {
+            market_id: parse_market_id(&row.market_id)?,
+            journal_sequence: row.journal_sequence as u64,
+            orders: serde_json::from_str(&row.orders).map_err(|e| ClobError::SerializationError(e.to_string()))?,
+            timestamp: row.timestamp,
+        }))
+    }
+
+    async fn store_deposit(&self, deposit: &DepositRecord) -> ClobResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO deposits (
+                signature, event_index, slot, owner, mint, amount, timestamp
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (signature, event_index) DO NOTHING
+            "#,
+            deposit.signature,
+            deposit.event_index as i32,
+            deposit.slot as i64,
+            deposit.user.to_string(),
+            deposit.mint.to_string(),
+            deposit.amount as i64,
+            deposit.timestamp
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        info!("Stored deposit {}#{}", deposit.signature, deposit.event_index);
+        Ok(())
+    }
+
+    async fn store_withdrawal(&self, withdrawal: &WithdrawalRecord) -> ClobResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO withdrawals (
+                signature, event_index, slot, owner, mint, amount, timestamp
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (signature, event_index) DO NOTHING
+            "#,
+            withdrawal.signature,
+            withdrawal.event_index as i32,
+            withdrawal.slot as i64,
+            withdrawal.user.to_string(),
+            withdrawal.mint.to_string(),
+            withdrawal.amount as i64,
+            withdrawal.timestamp
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        info!("Stored withdrawal {}#{}", withdrawal.signature, withdrawal.event_index);
+        Ok(())
+    }
+
+    async fn store_settled_trade(&self, trade: &SettledTradeRecord) -> ClobResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO settled_trades (
+                signature, event_index, slot, taker_order_id, maker_order_id,
+                taker, maker, price, quantity, timestamp
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (signature, event_index) DO NOTHING
+            "#,
+            trade.signature,
+            trade.event_index as i32,
+            trade.slot as i64,
+            trade.taker_order_id as i64,
+            trade.maker_order_id as i64,
+            trade.taker.to_string(),
+            trade.maker.to_string(),
+            trade.price as i64,
+            trade.quantity as i64,
+            trade.timestamp
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        info!("Stored settled trade {}#{}", trade.signature, trade.event_index);
+        Ok(())
+    }
+
+    async fn store_insurance_fund_event(&self, event: &InsuranceFundEvent) -> ClobResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO insurance_fund_events (
+                signature, event_index, slot, orderbook, kind, account, amount, timestamp
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (signature, event_index) DO NOTHING
+            "#,
+            event.signature,
+            event.event_index as i32,
+            event.slot as i64,
+            event.orderbook.to_string(),
+            event.kind as i16,
+            event.account.to_string(),
+            event.amount as i64,
+            event.timestamp
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        info!("Stored insurance fund event {}#{}", event.signature, event.event_index);
+        Ok(())
+    }
+
+    async fn store_sub_account(&self, sub_account: &SubAccount) -> ClobResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO sub_accounts (owner, wallet, sub_account_index, name, signature, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (owner) DO NOTHING
+            "#,
+            sub_account.owner.to_string(),
+            sub_account.wallet.to_string(),
+            sub_account.index as i32,
+            sub_account.name,
+            sub_account.signature,
+            sub_account.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        info!("Stored sub-account {} of {}", sub_account.index, sub_account.wallet);
+        Ok(())
+    }
+
+    async fn get_sub_account(&self, owner: &Pubkey) -> ClobResult<Option<SubAccount>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT wallet, sub_account_index, name, signature, created_at
+            FROM sub_accounts
+            WHERE owner = $1
+            "#,
+            owner.to_string()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        row.map(|row| {
+            Ok(SubAccount {
+                owner: *owner,
+                wallet: parse_pubkey(&row.wallet)?,
+                index: row.sub_account_index as u16,
+                name: row.name,
+                signature: row.signature,
+                created_at: row.created_at,
+            })
+        })
+        .transpose()
+    }
+
+    async fn get_sub_accounts(&self, wallet: &Pubkey) -> ClobResult<Vec<SubAccount>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT owner, sub_account_index, name, signature, created_at
+            FROM sub_accounts
+            WHERE wallet = $1
+            ORDER BY sub_account_index
+            "#,
+            wallet.to_string()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(SubAccount {
+                    owner: parse_pubkey(&row.owner)?,
+                    wallet: *wallet,
+                    index: row.sub_account_index as u16,
+                    name: row.name,
+                    signature: row.signature,
+                    created_at: row.created_at,
+                })
+            })
+            .collect()
+    }
+
+    async fn store_sub_account_transfer(&self, transfer: &SubAccountTransferRecord) -> ClobResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO sub_account_transfers (
+                signature, event_index, slot, wallet, from_owner, to_owner,
+                base_amount, quote_amount, timestamp
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (signature, event_index) DO NOTHING
+            "#,
+            transfer.signature,
+            transfer.event_index as i32,
+            transfer.slot as i64,
+            transfer.wallet.to_string(),
+            transfer.from.to_string(),
+            transfer.to.to_string(),
+            transfer.base_amount as i64,
+            transfer.quote_amount as i64,
+            transfer.timestamp
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        info!("Stored sub-account transfer {}#{}", transfer.signature, transfer.event_index);
+        Ok(())
+    }
+
+    async fn store_prevention_group(&self, record: &PreventionGroupRecord) -> ClobResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO prevention_groups (
+                signature, event_index, slot, market_id, owner, prevention_group, timestamp
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (signature, event_index) DO NOTHING
+            "#,
+            record.signature,
+            record.event_index as i32,
+            record.slot as i64,
+            record.market_id.to_string(),
+            record.owner.to_string(),
+            record.group as i64,
+            record.timestamp
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        info!("Stored prevention group {} of {}", record.group, record.owner);
+        Ok(())
+    }
+
+    async fn get_prevention_group(&self, market_id: &MarketId, owner: &Pubkey) -> ClobResult<Option<u32>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT prevention_group FROM prevention_groups
+            WHERE market_id = $1 AND owner = $2
+            ORDER BY slot DESC, event_index DESC
+            LIMIT 1
+            "#,
+            market_id.to_string(),
+            owner.to_string()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(row.map(|row| row.prevention_group as u32).filter(|group| *group != 0))
+    }
+
+    async fn store_chain_transaction(&self, transaction: &ChainTransaction) -> ClobResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO chain_transactions (signature, slot, status, error)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (signature) DO UPDATE SET
+                slot = EXCLUDED.slot,
+                status = CASE
+                    WHEN chain_transactions.status >= 3 OR EXCLUDED.status >= 3 THEN EXCLUDED.status
+                    ELSE GREATEST(chain_transactions.status, EXCLUDED.status)
+                END,
+                error = EXCLUDED.error
+            "#,
+            transaction.signature,
+            transaction.slot as i64,
+            transaction.status as i16,
+            transaction.error
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_chain_transaction(&self, signature: &str) -> ClobResult<Option<ChainTransaction>> {
+        let row = sqlx::query!(
+            "SELECT signature, slot, status, error FROM chain_transactions WHERE signature = $1",
+            signature
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        row.map(|row| {
+            Ok(ChainTransaction {
+                signature: row.signature,
+                slot: row.slot as u64,
+                status: chain_status(row.status)?,
+                error: row.error,
+            })
+        })
+        .transpose()
+    }
+
+    async fn get_unfinalized_chain_transactions(&self, limit: u32) -> ClobResult<Vec<ChainTransaction>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT signature, slot, status, error FROM chain_transactions
+            WHERE status IN (0, 1)
+            ORDER BY slot ASC, signature ASC
+            LIMIT $1
+            "#,
+            limit as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(ChainTransaction {
+                    signature: row.signature,
+                    slot: row.slot as u64,
+                    status: chain_status(row.status)?,
+                    error: row.error,
+                })
+            })
+            .collect()
+    }
+
+    async fn roll_back_chain_transaction(
+        &self,
+        signature: &str,
+        status: ChainStatus,
+        error: Option<&str>,
+    ) -> ClobResult<()> {
+        let mut tx = self.pool.begin().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        sqlx::query!("DELETE FROM deposits WHERE signature = $1", signature)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM withdrawals WHERE signature = $1", signature)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM settled_trades WHERE signature = $1", signature)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM insurance_fund_events WHERE signature = $1", signature)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM sub_accounts WHERE signature = $1", signature)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM sub_account_transfers WHERE signature = $1", signature)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM prevention_groups WHERE signature = $1", signature)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM settlement_adjustments WHERE signature = $1", signature)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM receipt_trees WHERE signature = $1", signature)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM compressed_receipts WHERE signature = $1", signature)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        // Likewise an undone reversal is sent again
+        sqlx::query!(
+            "UPDATE trade_adjustments SET reversal = $2, reversal_signature = NULL WHERE reversal_signature = $1",
+            signature,
+            ReversalStatus::Pending as i16
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        // The credit is undone with the transaction, so the transfer is
+        // credited again
+        sqlx::query!(
+            "UPDATE vault_transfers SET status = $2, credit_signature = NULL WHERE credit_signature = $1",
+            signature,
+            VaultTransferStatus::Ready as i16
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!(
+            "UPDATE chain_transactions SET status = $2, error = $3 WHERE signature = $1",
+            signature,
+            status as i16,
+            error
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        tx.commit().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+        warn!("Rolled back the records of transaction {} ({:?})", signature, status);
+        Ok(())
+    }
+
+    async fn get_checkpoint(&self, service: &str) -> ClobResult<Option<Checkpoint>> {
+        let row = sqlx::query!(
+            "SELECT service, position, cursor FROM service_checkpoints WHERE service = $1",
+            service
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(row.map(|row| Checkpoint {
+            service: row.service,
+            position: row.position as u64,
+            cursor: row.cursor,
+        }))
+    }
+
+    async fn save_checkpoint(&self, checkpoint: &Checkpoint) -> ClobResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO service_checkpoints (service, position, cursor)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (service) DO UPDATE SET
+                position = EXCLUDED.position,
+                cursor = EXCLUDED.cursor
+            "#,
+            checkpoint.service,
+            checkpoint.position as i64,
+            checkpoint.cursor
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    #[instrument(name = "storage.append_journal_entry", skip_all)]
+    async fn append_journal_entry(&self, event: &JournalEvent) -> ClobResult<u64> {
+        let payload = serde_json::to_value(event)
+            .map_err(|e| ClobError::SerializationError(e.to_string()))?;
+
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO engine_journal (timestamp, payload, trace_context, request_id)
+            VALUES ($1, $2, $3, $4)
+            RETURNING sequence
+            "#,
+            self.clock.now(),
+            payload,
+            svm_clob_telemetry::current_traceparent(),
+            svm_clob_telemetry::current_request_id()
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(row.sequence as u64)
+    }
+
+    async fn get_journal_entries(&self, after: u64, limit: u32) -> ClobResult<Vec<JournalEntry>> {
+        let rows = sqlx::query!(
+            "SELECT sequence, timestamp, payload, trace_context, request_id FROM engine_journal WHERE sequence > $1 ORDER BY sequence ASC LIMIT $2",
+            after as i64,
+            limit as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            entries.push(JournalEntry {
+                sequence: row.sequence as u64,
+                timestamp: row.timestamp,
+                event: serde_json::from_value(row.payload)
+                    .map_err(|e| ClobError::SerializationError(e.to_string()))?,
+                trace_context: row.trace_context,
+                request_id: row.request_id,
+            });
+        }
+        Ok(entries)
+    }
+
+    #[instrument(name = "storage.store_trade_settlement", skip_all, fields(journal_sequence = settlement.journal_sequence))]
+    async fn store_trade_settlement(&self, settlement: &TradeSettlement) -> ClobResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO trade_settlements (
+                journal_sequence, maker_order_id, taker_order_id,
+                signature, status, attempts, error, version
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (journal_sequence) DO UPDATE SET
+                signature = EXCLUDED.signature,
+                status = EXCLUDED.status,
+                attempts = EXCLUDED.attempts,
+                error = EXCLUDED.error,
+                version = EXCLUDED.version
+            "#,
+            settlement.journal_sequence as i64,
+            settlement.maker_order_id as i64,
+            settlement.taker_order_id as i64,
+            settlement.signature,
+            settlement.status as i16,
+            settlement.attempts as i32,
+            settlement.error,
+            settlement.version as i32
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        info!(
+            "Recorded settlement of journal entry {}: {:?}",
+            settlement.journal_sequence, settlement.status
+        );
+        Ok(())
+    }
+
+    async fn get_trade_settlement(&self, journal_sequence: u64) -> ClobResult<Option<TradeSettlement>> {
+        let row = sqlx::query!(
+            "SELECT * FROM trade_settlements WHERE journal_sequence = $1",
+            journal_sequence as i64
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        if let Some(row) = row {
+            Ok(Some(TradeSettlement {
+                journal_sequence: row.journal_sequence as u64,
+                maker_order_id: row.maker_order_id as u64,
+                taker_order_id: row.taker_order_id as u64,
+                signature: row.signature,
+                status: SettlementStatus::try_from(row.status as u8).map_err(|_| ClobError::StorageError("Invalid settlement status".to_string()))?,
+                attempts: row.attempts as u32,
+                error: row.error,
+                version: row.version as u32,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn get_open_orders(&self) -> ClobResult<Vec<Order>> {
+        let rows = sqlx::query!(
+            "SELECT * FROM orders WHERE status IN (0, 1) ORDER BY created_at ASC, order_id ASC"
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let mut orders = Vec::new();
+        for row in rows {
+            orders.push(Order {
+                order_id: row.order_id as u64,
+                market_id: parse_market_id(&row.market_id)?,
+                owner: row.owner.parse().map_err(|e| ClobError::StorageError(format!("Invalid pubkey: {}", e)))?,
+                price: row.price as u64,
+                quantity: row.quantity as u64,
+                remaining_quantity: row.remaining_quantity as u64,
+                timestamp: row.timestamp,
+                client_order_id: row.client_order_id as u64,
+                expiry_timestamp: row.expiry_timestamp,
+                side: OrderSide::try_from(row.side as u8).map_err(|_| ClobError::InvalidOrderSide)?,
+                order_type: OrderType::try_from(row.order_type as u8).map_err(|_| ClobError::InvalidOrderType)?,
+                status: OrderStatus::try_from(row.status as u8).map_err(|_| ClobError::StorageError("Invalid status".to_string()))?,
+                self_trade_behavior: SelfTradeBehavior::try_from(row.self_trade_behavior as u8).map_err(|_| ClobError::StorageError("Invalid self trade behavior".to_string()))?,
+                time_in_force: TimeInForce::try_from(row.time_in_force as u8).map_err(|_| ClobError::StorageError("Invalid time in force".to_string()))?,
+                max_slippage_bps: row.max_slippage_bps.map(|bps| bps as u32),
+                filled_notional: parse_notional(&row.filled_notional)?,
+                avg_fill_price: row.avg_fill_price.map(|price| price as u64),
+                prevention_group_id: row.prevention_group_id.map(|group| group as u32),
+            });
+        }
+        Ok(orders)
+    }
+
+    async fn get_ledger_balances(
+        &self,
+        base_mint: &Pubkey,
+        quote_mint: &Pubkey,
+        owner: Option<&Pubkey>,
+        until: Option<i64>,
+    ) -> ClobResult<Vec<LedgerBalance>> {
+        // Settled trades carry no side, so it is recovered from the engine's
+        // trade record; adjustments of settled trades move balances between
+        // the parties without adding volume. What a fill or an adjustment
+        // credits is held until its transaction is finalized; transactions
+        // indexed before their status was tracked count as finalized.
+        let rows = sqlx::query!(
+            r#"
+            WITH settled AS (
+                SELECT s.taker,
+                       s.maker,
+                       CASE WHEN t.maker_side = 1 THEN s.quantity ELSE -s.quantity END::NUMERIC AS taker_base,
+                       CASE WHEN t.maker_side = 1 THEN -(s.quantity::NUMERIC * s.price) ELSE s.quantity::NUMERIC * s.price END AS taker_quote,
+                       s.quantity,
+                       COALESCE(c.status IN (0, 1), FALSE) AS unfinalized
+                FROM settled_trades s
+                JOIN trades t ON t.maker_order_id = s.maker_order_id AND t.taker_order_id = s.taker_order_id
+                LEFT JOIN chain_transactions c ON c.signature = s.signature
+                WHERE $4::BIGINT IS NULL OR s.timestamp < $4
+            ),
+            adjusted AS (
+                SELECT a.maker,
+                       a.taker,
+                       a.base_to_maker::NUMERIC AS maker_base,
+                       a.quote_to_maker::NUMERIC AS maker_quote,
+                       COALESCE(c.status IN (0, 1), FALSE) AS unfinalized
+                FROM settlement_adjustments a
+                LEFT JOIN chain_transactions c ON c.signature = a.signature
+                WHERE $4::BIGINT IS NULL OR a.timestamp < $4
+            ),
+            movements AS (
+                SELECT owner,
+                       CASE WHEN mint = $1 THEN amount ELSE 0 END::NUMERIC AS base,
+                       CASE WHEN mint = $2 THEN amount ELSE 0 END::NUMERIC AS quote,
+                       0::NUMERIC AS volume,
+                       0::NUMERIC AS fees,
+                       0::NUMERIC AS base_held,
+                       0::NUMERIC AS quote_held
+                FROM deposits
+                WHERE $4::BIGINT IS NULL OR timestamp < $4
+                UNION ALL
+                SELECT owner,
+                       CASE WHEN mint = $1 THEN -amount ELSE 0 END,
+                       CASE WHEN mint = $2 THEN -amount ELSE 0 END,
+                       0,
+                       0,
+                       0,
+                       0
+                FROM withdrawals
+                WHERE $4::BIGINT IS NULL OR timestamp < $4
+                UNION ALL
+                SELECT from_owner, -base_amount, -quote_amount, 0, 0, 0, 0
+                FROM sub_account_transfers
+                WHERE $4::BIGINT IS NULL OR timestamp < $4
+                UNION ALL
+                SELECT to_owner, base_amount, quote_amount, 0, 0, 0, 0
+                FROM sub_account_transfers
+                WHERE $4::BIGINT IS NULL OR timestamp < $4
+                UNION ALL
+                SELECT taker,
+                       taker_base,
+                       taker_quote,
+                       quantity,
+                       0,
+                       CASE WHEN unfinalized THEN GREATEST(taker_base, 0) ELSE 0 END,
+                       CASE WHEN unfinalized THEN GREATEST(taker_quote, 0) ELSE 0 END
+                FROM settled
+                UNION ALL
+                SELECT maker,
+                       -taker_base,
+                       -taker_quote,
+                       quantity,
+                       0,
+                       CASE WHEN unfinalized THEN GREATEST(-taker_base, 0) ELSE 0 END,
+                       CASE WHEN unfinalized THEN GREATEST(-taker_quote, 0) ELSE 0 END
+                FROM settled
+                UNION ALL
+                SELECT maker,
+                       maker_base,
+                       maker_quote,
+                       0,
+                       0,
+                       CASE WHEN unfinalized THEN GREATEST(maker_base, 0) ELSE 0 END,
+                       CASE WHEN unfinalized THEN GREATEST(maker_quote, 0) ELSE 0 END
+                FROM adjusted
+                UNION ALL
+                SELECT taker,
+                       -maker_base,
+                       -maker_quote,
+                       0,
+                       0,
+                       CASE WHEN unfinalized THEN GREATEST(-maker_base, 0) ELSE 0 END,
+                       CASE WHEN unfinalized THEN GREATEST(-maker_quote, 0) ELSE 0 END
+                FROM adjusted
+                UNION ALL
+                SELECT owner, 0, 0, 0, fee, 0, 0
+                FROM fee_accruals
+                WHERE $4::BIGINT IS NULL OR timestamp < $4
+            )
+            SELECT owner AS "owner!",
+                   SUM(base)::BIGINT AS "base!",
+                   SUM(quote)::BIGINT AS "quote!",
+                   SUM(volume)::BIGINT AS "volume!",
+                   SUM(fees)::BIGINT AS "fees!",
+                   SUM(base_held)::BIGINT AS "base_held!",
+                   SUM(quote_held)::BIGINT AS "quote_held!"
+            FROM movements
+            WHERE $3::TEXT IS NULL OR owner = $3
+            GROUP BY owner
+            ORDER BY owner
+            "#,
+            base_mint.to_string(),
+            quote_mint.to_string(),
+            owner.map(|owner| owner.to_string()),
+            until
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let mut balances = Vec::new();
+        for row in rows {
+            balances.push(LedgerBalance {
+                user: row.owner.parse().map_err(|e| ClobError::StorageError(format!("Invalid pubkey: {}", e)))?,
+                base_balance: row.base,
+                quote_balance: row.quote,
+                total_volume_traded: row.volume as u64,
+                fees: row.fees,
+                base_held: row.base_held as u64,
+                quote_held: row.quote_held as u64,
+            });
+        }
+        Ok(balances)
+    }
+
+    async fn get_user_deposits(&self, owner: &Pubkey, from: i64, until: i64) -> ClobResult<Vec<DepositRecord>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT signature, event_index, slot, owner, mint, amount, timestamp
+            FROM deposits
+            WHERE owner = $1 AND timestamp >= $2 AND timestamp < $3
+            ORDER BY slot ASC, event_index ASC
+            "#,
+            owner.to_string(),
+            from,
+            until
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let mut deposits = Vec::new();
+        for row in rows {
+            deposits.push(DepositRecord {
+                signature: row.signature,
+                event_index: row.event_index as u32,
+                slot: row.slot as u64,
+                user: row.owner.parse().map_err(|e| ClobError::StorageError(format!("Invalid pubkey: {}", e)))?,
+                mint: row.mint.parse().map_err(|e| ClobError::StorageError(format!("Invalid pubkey: {}", e)))?,
+                amount: row.amount as u64,
+                timestamp: row.timestamp,
+            });
+        }
+        Ok(deposits)
+    }
+
+    async fn get_user_withdrawals(&self, owner: &Pubkey, from: i64, until: i64) -> ClobResult<Vec<WithdrawalRecord>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT signature, event_index, slot, owner, mint, amount, timestamp
+            FROM withdrawals
+            WHERE owner = $1 AND timestamp >= $2 AND timestamp < $3
+            ORDER BY slot ASC, event_index ASC
+            "#,
+            owner.to_string(),
+            from,
+            until
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let mut withdrawals = Vec::new();
+        for row in rows {
+            withdrawals.push(WithdrawalRecord {
+                signature: row.signature,
+                event_index: row.event_index as u32,
+                slot: row.slot as u64,
+                user: row.owner.parse().map_err(|e| ClobError::StorageError(format!("Invalid pubkey: {}", e)))?,
+                mint: row.mint.parse().map_err(|e| ClobError::StorageError(format!("Invalid pubkey: {}", e)))?,
+                amount: row.amount as u64,
+                timestamp: row.timestamp,
+            });
+        }
+        Ok(withdrawals)
+    }
+
+    async fn get_user_sub_account_transfers(
+        &self,
+        owner: &Pubkey,
+        from: i64,
+        until: i64,
+    ) -> ClobResult<Vec<SubAccountTransferRecord>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT signature, event_index, slot, wallet, from_owner, to_owner, base_amount, quote_amount, timestamp
+            FROM sub_account_transfers
+            WHERE (from_owner = $1 OR to_owner = $1) AND timestamp >= $2 AND timestamp < $3
+            ORDER BY slot ASC, event_index ASC
+            "#,
+            owner.to_string(),
+            from,
+            until
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let parse = |key: &str| -> ClobResult<Pubkey> {
+            key.parse().map_err(|e| ClobError::StorageError(format!("Invalid pubkey: {}", e)))
+        };
+        let mut transfers = Vec::new();
+        for row in rows {
+            transfers.push(SubAccountTransferRecord {
+                signature: row.signature,
+                event_index: row.event_index as u32,
+                slot: row.slot as u64,
+                wallet: parse(&row.wallet)?,
+                from: parse(&row.from_owner)?,
+                to: parse(&row.to_owner)?,
+                base_amount: row.base_amount as u64,
+                quote_amount: row.quote_amount as u64,
+                timestamp: row.timestamp,
+            });
+        }
+        Ok(transfers)
+    }
+
+    async fn get_settled_volume(&self) -> ClobResult<u64> {
+        let row = sqlx::query!(
+            r#"SELECT COALESCE(SUM(quantity), 0)::BIGINT AS "volume!" FROM settled_trades"#
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(row.volume as u64)
+    }
+
+    async fn get_last_trade_id(&self) -> ClobResult<u64> {
+        let row = sqlx::query!(r#"SELECT COALESCE(MAX(trade_id), 0) AS "trade_id!" FROM trades"#)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(row.trade_id as u64)
+    }
+
+    async fn get_reserved_order_ids(&self) -> ClobResult<u64> {
+        let row = sqlx::query!(
+            r#"
+            SELECT COALESCE(MAX((payload->'data'->>'through')::BIGINT), 0) AS "through!"
+            FROM engine_journal
+            WHERE payload->>'type' = 'OrderIdsReserved'
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(row.through as u64)
+    }
+
+    async fn get_journal_head(&self) -> ClobResult<u64> {
+        let row = sqlx::query!(
+            r#"SELECT COALESCE(MAX(sequence), 0) AS "sequence!" FROM engine_journal"#
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(row.sequence as u64)
+    }
+
+    async fn get_unindexed_settlements(&self, limit: u32) -> ClobResult<Vec<TradeSettlement>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT ts.* FROM trade_settlements ts
+            WHERE ts.status = 1
+              AND NOT EXISTS (SELECT 1 FROM settled_trades s WHERE s.signature = ts.signature)
+            ORDER BY ts.journal_sequence ASC
+            LIMIT $1
+            "#,
+            limit as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let mut settlements = Vec::new();
+        for row in rows {
+            settlements.push(TradeSettlement {
+                journal_sequence: row.journal_sequence as u64,
+                maker_order_id: row.maker_order_id as u64,
+                taker_order_id: row.taker_order_id as u64,
+                signature: row.signature,
+                status: SettlementStatus::try_from(row.status as u8).map_err(|_| ClobError::StorageError("Invalid settlement status".to_string()))?,
+                attempts: row.attempts as u32,
+                error: row.error,
+                version: row.version as u32,
+            });
+        }
+        Ok(settlements)
+    }
+
+    async fn store_dead_letter(&self, letter: &DeadLetter) -> ClobResult<u64> {
+        let trades = serde_json::to_value(&letter.trades)
+            .map_err(|e| ClobError::SerializationError(e.to_string()))?;
+
+        let row = sqlx::query!(
+            r#"
+            WITH inserted AS (
+                INSERT INTO settlement_dead_letters (
+                    first_sequence, last_sequence, trades, status, reason,
+                    attempts, signature, created_at, updated_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                ON CONFLICT (first_sequence) DO NOTHING
+                RETURNING id
+            )
+            SELECT id AS "id!" FROM inserted
+            UNION ALL
+            SELECT id FROM settlement_dead_letters WHERE first_sequence = $1
+            LIMIT 1
+            "#,
+            letter.first_sequence as i64,
+            letter.last_sequence as i64,
+            trades,
+            letter.status as i16,
+            letter.reason,
+            letter.attempts as i32,
+            letter.signature,
+            letter.created_at,
+            letter.updated_at
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(row.id as u64)
+    }
+
+    async fn get_dead_letter(&self, id: u64) -> ClobResult<Option<DeadLetter>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id, first_sequence, last_sequence, trades, status, reason,
+                   attempts, signature, created_at, updated_at
+            FROM settlement_dead_letters
+            WHERE id = $1
+            "#,
+            id as i64
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        row.map(|row| {
+            Ok(DeadLetter {
+                id: row.id as u64,
+                first_sequence: row.first_sequence as u64,
+                last_sequence: row.last_sequence as u64,
+                trades: serde_json::from_value(row.trades).map_err(|e| ClobError::SerializationError(e.to_string()))?,
+                status: dead_letter_status(row.status)?,
+                reason: row.reason,
+                attempts: row.attempts as u32,
+                signature: row.signature,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+            })
+        })
+        .transpose()
+    }
+
+    async fn get_dead_letters(&self, status: Option<DeadLetterStatus>, page: &Paginated) -> ClobResult<Page<DeadLetter>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, first_sequence, last_sequence, trades, status, reason,
+                   attempts, signature, created_at, updated_at
+            FROM settlement_dead_letters
+            WHERE ($1::SMALLINT IS NULL OR status = $1)
+            ORDER BY id ASC
+            LIMIT $2 OFFSET $3
+            "#,
+            status.map(|status| status as i16),
+            page.limit() as i64 + 1,
+            page.offset()? as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let mut letters = Vec::new();
+        for row in rows {
+            letters.push(DeadLetter {
+                id: row.id as u64,
+                first_sequence: row.first_sequence as u64,
+                last_sequence: row.last_sequence as u64,
+                trades: serde_json::from_value(row.trades).map_err(|e| ClobError::SerializationError(e.to_string()))?,
+                status: dead_letter_status(row.status)?,
+                reason: row.reason,
+                attempts: row.attempts as u32,
+                signature: row.signature,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+            });
+        }
+        Page::from_fetched(letters, page)
+    }
+
+    async fn update_dead_letter(&self, letter: &DeadLetter, expected: DeadLetterStatus) -> ClobResult<bool> {
+        let trades = serde_json::to_value(&letter.trades)
+            .map_err(|e| ClobError::SerializationError(e.to_string()))?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE settlement_dead_letters
+            SET trades = $3, status = $4, reason = $5, attempts = $6, signature = $7, updated_at = $8
+            WHERE id = $1 AND status = $2
+            "#,
+            letter.id as i64,
+            expected as i16,
+            trades,
+            letter.status as i16,
+            letter.reason,
+            letter.attempts as i32,
+            letter.signature,
+            letter.updated_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn try_acquire_lease(&self, name: &str, holder: &str, ttl: Duration) -> ClobResult<Option<u64>> {
+        // Expiry is judged by the database clock, so instances need not agree on time
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO leader_leases (name, holder, epoch, expires_at)
+            VALUES ($1, $2, 1, now() + make_interval(secs => $3))
+            ON CONFLICT (name) DO UPDATE SET
+                holder = EXCLUDED.holder,
+                epoch = CASE
+                    WHEN leader_leases.holder = EXCLUDED.holder AND leader_leases.expires_at >= now()
+                    THEN leader_leases.epoch
+                    ELSE leader_leases.epoch + 1
+                END,
+                expires_at = EXCLUDED.expires_at
+            WHERE leader_leases.holder = EXCLUDED.holder OR leader_leases.expires_at < now()
+            RETURNING epoch
+            "#,
+            name,
+            holder,
+            ttl.as_secs_f64()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(row.map(|row| row.epoch as u64))
+    }
+
+    async fn release_lease(&self, name: &str, holder: &str) -> ClobResult<()> {
+        sqlx::query!(
+            "UPDATE leader_leases SET expires_at = now() - interval '1 millisecond' WHERE name = $1 AND holder = $2",
+            name,
+            holder
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn store_surveillance_alert(&self, alert: &SurveillanceAlert) -> ClobResult<u64> {
+        let accounts: Vec<String> = alert.accounts.iter().map(|account| account.to_string()).collect();
+        let row = sqlx::query!(
+            r#"
+            WITH inserted AS (
+                INSERT INTO surveillance_alerts (
+                    kind, accounts, score, window_start, window_end,
+                    first_sequence, last_sequence, details
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT (kind, window_start, accounts) DO NOTHING
+                RETURNING id
+            )
+            SELECT id AS "id!" FROM inserted
+            UNION ALL
+            SELECT id FROM surveillance_alerts
+            WHERE kind = $1 AND window_start = $4 AND accounts = $2
+            LIMIT 1
+            "#,
+            alert.kind as i16,
+            &accounts,
+            alert.score,
+            alert.window_start,
+            alert.window_end,
+            alert.first_sequence as i64,
+            alert.last_sequence as i64,
+            alert.details
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(row.id as u64)
+    }
+
+    async fn get_surveillance_alerts(
+        &self,
+        kind: Option<AlertKind>,
+        min_score: f64,
+        page: &Paginated,
+    ) -> ClobResult<Page<SurveillanceAlert>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, kind, accounts, score, window_start, window_end,
+                   first_sequence, last_sequence, details
+            FROM surveillance_alerts
+            WHERE ($1::SMALLINT IS NULL OR kind = $1) AND score >= $2
+            ORDER BY id DESC
+            LIMIT $3 OFFSET $4
+            "#,
+            kind.map(|kind| kind as i16),
+            min_score,
+            page.limit() as i64 + 1,
+            page.offset()? as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let mut alerts = Vec::new();
+        for row in rows {
+            alerts.push(SurveillanceAlert {
+                id: row.id as u64,
+                kind: alert_kind(row.kind)?,
+                accounts: parse_pubkeys(&row.accounts)?,
+                score: row.score,
+                window_start: row.window_start,
+                window_end: row.window_end,
+                first_sequence: row.first_sequence as u64,
+                last_sequence: row.last_sequence as u64,
+                details: row.details,
+            });
+        }
+        Page::from_fetched(alerts, page)
+    }
+
+    async fn get_surveillance_alert(&self, id: u64) -> ClobResult<Option<SurveillanceAlert>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id, kind, accounts, score, window_start, window_end,
+                   first_sequence, last_sequence, details
+            FROM surveillance_alerts
+            WHERE id = $1
+            "#,
+            id as i64
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        row.map(|row| {
+            Ok(SurveillanceAlert {
+                id: row.id as u64,
+                kind: alert_kind(row.kind)?,
+                accounts: parse_pubkeys(&row.accounts)?,
+                score: row.score,
+                window_start: row.window_start,
+                window_end: row.window_end,
+                first_sequence: row.first_sequence as u64,
+                last_sequence: row.last_sequence as u64,
+                details: row.details,
+            })
+        })
+        .transpose()
+    }
+
+    async fn store_fee_accruals(&self, accruals: &[FeeAccrual]) -> ClobResult<()> {
+        let mut tx = self.pool.begin().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+        for accrual in accruals {
+            sqlx::query!(
+                r#"
+                INSERT INTO fee_accruals (
+                    journal_sequence, role, owner, price, quantity, fee_bps, fee, insurance_contribution, timestamp,
+                    adjusted_sequence
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                ON CONFLICT (journal_sequence, role) DO NOTHING
+                "#,
+                accrual.journal_sequence as i64,
+                accrual.role as i16,
+                accrual.user.to_string(),
+                accrual.price as i64,
+                accrual.quantity as i64,
+                accrual.fee_bps,
+                accrual.fee,
+                accrual.insurance_contribution,
+                accrual.timestamp,
+                accrual.adjusted_sequence.map(|sequence| sequence as i64)
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        }
+        tx.commit().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_fee_reports(&self, from: i64, until: i64, user: Option<&Pubkey>) -> ClobResult<Vec<FeeReport>> {
+        // Accruals compensating a bust take their fill and its volume back out
+        let rows = sqlx::query!(
+            r#"
+            SELECT owner AS "owner!",
+                   (COUNT(*) FILTER (WHERE adjusted_sequence IS NULL)
+                    - COUNT(*) FILTER (WHERE adjusted_sequence IS NOT NULL AND quantity > 0)) AS "fills!",
+                   COALESCE(SUM(CASE WHEN adjusted_sequence IS NULL THEN quantity ELSE -quantity END) FILTER (WHERE role = 0), 0)::BIGINT AS "maker_volume!",
+                   COALESCE(SUM(CASE WHEN adjusted_sequence IS NULL THEN quantity ELSE -quantity END) FILTER (WHERE role = 1), 0)::BIGINT AS "taker_volume!",
+                   COALESCE(SUM(fee) FILTER (WHERE role = 0), 0)::BIGINT AS "maker_fees!",
+                   COALESCE(SUM(fee) FILTER (WHERE role = 1), 0)::BIGINT AS "taker_fees!"
+            FROM fee_accruals
+            WHERE timestamp >= $1 AND timestamp < $2 AND ($3::TEXT IS NULL OR owner = $3)
+            GROUP BY owner
+            ORDER BY owner
+            "#,
+            from,
+            until,
+            user.map(|user| user.to_string())
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let mut reports = Vec::new();
+        for row in rows {
+            reports.push(FeeReport {
+                user: row.owner.parse().map_err(|e| ClobError::StorageError(format!("Invalid pubkey: {}", e)))?,
+                period_start: from,
+                period_end: until,
+                fills: row.fills as u64,
+                maker_volume: row.maker_volume as u64,
+                taker_volume: row.taker_volume as u64,
+                maker_fees: row.maker_fees,
+                taker_fees: row.taker_fees,
+                total_fees: row.maker_fees + row.taker_fees,
+            });
+        }
+        Ok(reports)
+    }
+
+    async fn get_traded_volumes(&self, from: i64, until: i64) -> ClobResult<Vec<(Pubkey, u64)>> {
+        // A self-trade joins both of its orders and counts once per side; a
+        // busted trade counts as never having taken place
+        let rows = sqlx::query!(
+            r#"
+            SELECT o.owner AS "owner!", SUM(t.quantity)::BIGINT AS "volume!"
+            FROM trades t
+            JOIN orders o ON o.order_id = t.maker_order_id OR o.order_id = t.taker_order_id
+            WHERE t.timestamp >= $1 AND t.timestamp < $2 AND t.status <> $3
+            GROUP BY o.owner
+            ORDER BY o.owner
+            "#,
+            from,
+            until,
+            TradeStatus::Busted as i16
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let mut volumes = Vec::new();
+        for row in rows {
+            let owner = row.owner.parse().map_err(|e| ClobError::StorageError(format!("Invalid pubkey: {}", e)))?;
+            volumes.push((owner, row.volume as u64));
+        }
+        Ok(volumes)
+    }
+
+    async fn replace_fee_tiers(&self, tiers: &[UserFeeTier]) -> ClobResult<()> {
+        let mut tx = self.pool.begin().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM fee_tiers")
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        for tier in tiers {
+            sqlx::query!(
+                r#"
+                INSERT INTO fee_tiers (owner, volume_30d, tier, maker_fee_bps, taker_fee_bps, computed_at)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+                tier.user.to_string(),
+                tier.volume_30d as i64,
+                tier.tier.map(|tier| tier as i32),
+                tier.maker_fee_bps,
+                tier.taker_fee_bps,
+                tier.computed_at
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        }
+        tx.commit().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_fee_tier(&self, user: &Pubkey) -> ClobResult<Option<UserFeeTier>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT volume_30d, tier, maker_fee_bps, taker_fee_bps, computed_at
+            FROM fee_tiers
+            WHERE owner = $1
+            "#,
+            user.to_string()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(row.map(|row| UserFeeTier {
+            user: *user,
+            volume_30d: row.volume_30d as u64,
+            tier: row.tier.map(|tier| tier as u32),
+            maker_fee_bps: row.maker_fee_bps,
+            taker_fee_bps: row.taker_fee_bps,
+            computed_at: row.computed_at,
+        }))
+    }
+
+    async fn get_insurance_fund(&self, orderbook: &Pubkey) -> ClobResult<InsuranceFund> {
+        let accrued = sqlx::query_scalar!(
+            r#"SELECT COALESCE(SUM(insurance_contribution), 0)::BIGINT AS "accrued!" FROM fee_accruals"#
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let row = sqlx::query!(
+            r#"
+            SELECT COALESCE(SUM(amount) FILTER (WHERE kind = 0), 0)::BIGINT AS "funded!",
+                   COALESCE(SUM(amount) FILTER (WHERE kind = 1), 0)::BIGINT AS "drawn!"
+            FROM insurance_fund_events
+            WHERE orderbook = $1
+            "#,
+            orderbook.to_string()
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(InsuranceFund::new(*orderbook, accrued, row.funded as u64, row.drawn as u64))
+    }
+
+    async fn get_insurance_fund_events(
+        &self,
+        orderbook: &Pubkey,
+        kind: Option<InsuranceFundEventKind>,
+        page: &Paginated,
+    ) -> ClobResult<Page<InsuranceFundEvent>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT signature, event_index, slot, orderbook, kind, account, amount, timestamp
+            FROM insurance_fund_events
+            WHERE orderbook = $1 AND ($2::SMALLINT IS NULL OR kind = $2)
+            ORDER BY slot DESC, signature DESC, event_index DESC
+            LIMIT $3 OFFSET $4
+            "#,
+            orderbook.to_string(),
+            kind.map(|kind| kind as i16),
+            page.limit() as i64 + 1,
+            page.offset()? as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let events = rows
+            .into_iter()
+            .map(|row| {
+                Ok(InsuranceFundEvent {
+                    signature: row.signature,
+                    event_index: row.event_index as u32,
+                    slot: row.slot as u64,
+                    orderbook: parse_pubkey(&row.orderbook)?,
+                    kind: insurance_fund_event_kind(row.kind)?,
+                    account: parse_pubkey(&row.account)?,
+                    amount: row.amount as u64,
+                    timestamp: row.timestamp,
+                })
+            })
+            .collect::<ClobResult<Vec<_>>>()?;
+        Page::from_fetched(events, page)
+    }
+
+    async fn store_admin_audit_entry(&self, entry: &AdminAuditEntry) -> ClobResult<u64> {
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO admin_audit_log (actor, action, target, before, after, timestamp)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id
+            "#,
+            entry.actor,
+            entry.action,
+            entry.target,
+            self.seal("admin_audit_log.before", &entry.before.to_string())?,
+            self.seal("admin_audit_log.after", &entry.after.to_string())?,
+            entry.timestamp
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(row.id as u64)
+    }
+
+    async fn get_admin_audit_entries(&self, page: &Paginated) -> ClobResult<Page<AdminAuditEntry>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, actor, action, target, before, after, timestamp
+            FROM admin_audit_log
+            ORDER BY id DESC
+            LIMIT $1 OFFSET $2
+            "#,
+            page.limit() as i64 + 1,
+            page.offset()? as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            entries.push(AdminAuditEntry {
+                id: row.id as u64,
+                actor: row.actor,
+                action: row.action,
+                target: row.target,
+                before: serde_json::from_str(&self.open("admin_audit_log.before", row.before)?)
+                    .map_err(|e| ClobError::StorageError(e.to_string()))?,
+                after: serde_json::from_str(&self.open("admin_audit_log.after", row.after)?)
+                    .map_err(|e| ClobError::StorageError(e.to_string()))?,
+                timestamp: row.timestamp,
+            });
+        }
+        Page::from_fetched(entries, page)
+    }
+
+    async fn store_api_audit_entry(&self, entry: &ApiAuditEntry) -> ClobResult<u64> {
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO api_audit (actor, method, route, path, params, status, result, latency_us, timestamp, request_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING id
+            "#,
+            entry.actor,
+            entry.method,
+            entry.route,
+            entry.path,
+            self.seal("api_audit.params", &entry.params.to_string())?,
+            entry.status as i32,
+            self.seal("api_audit.result", &entry.result.to_string())?,
+            entry.latency_us as i64,
+            entry.timestamp,
+            entry.request_id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(row.id as u64)
+    }
+
+    async fn get_api_audit_entries(&self, query: &ApiAuditQuery, page: &Paginated) -> ClobResult<Page<ApiAuditEntry>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, actor, method, route, path, params, status, result, latency_us, timestamp, request_id
+            FROM api_audit
+            WHERE ($1::TEXT IS NULL OR actor = $1)
+              AND ($2::BIGINT IS NULL OR timestamp >= $2)
+              AND ($3::BIGINT IS NULL OR timestamp < $3)
+              AND ($6::TEXT IS NULL OR request_id = $6)
+            ORDER BY id DESC
+            LIMIT $4 OFFSET $5
+            "#,
+            query.actor,
+            query.from,
+            query.until,
+            page.limit() as i64 + 1,
+            page.offset()? as i64,
+            query.request_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            entries.push(ApiAuditEntry {
+                id: row.id as u64,
+                actor: row.actor,
+                method: row.method,
+                route: row.route,
+                path: row.path,
+                params: serde_json::from_str(&self.open("api_audit.params", row.params)?)
+                    .map_err(|e| ClobError::StorageError(e.to_string()))?,
+                status: row.status as u16,
+                result: serde_json::from_str(&self.open("api_audit.result", row.result)?)
+                    .map_err(|e| ClobError::StorageError(e.to_string()))?,
+                latency_us: row.latency_us as u64,
+                timestamp: row.timestamp,
+                request_id: row.request_id,
+            });
+        }
+        Page::from_fetched(entries, page)
+    }
+
+    async fn store_tenant(&self, tenant: &Tenant) -> ClobResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO tenants (
+                id, name, max_requests_per_second, max_orders_per_second, fee_share_bps,
+                referral_code, disabled, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (id) DO UPDATE SET
+                name = EXCLUDED.name,
+                max_requests_per_second = EXCLUDED.max_requests_per_second,
+                max_orders_per_second = EXCLUDED.max_orders_per_second,
+                fee_share_bps = EXCLUDED.fee_share_bps,
+                referral_code = EXCLUDED.referral_code,
+                disabled = EXCLUDED.disabled,
+                updated_at = EXCLUDED.updated_at
+            "#,
+            tenant.id,
+            self.seal("tenants.name", &tenant.name)?,
+            tenant.max_requests_per_second.map(|limit| limit as i32),
+            tenant.max_orders_per_second.map(|limit| limit as i32),
+            tenant.fee_share_bps as i64,
+            tenant.referral_code,
+            tenant.disabled,
+            tenant.created_at,
+            tenant.updated_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_tenant(&self, id: &str) -> ClobResult<Option<Tenant>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id, name, max_requests_per_second, max_orders_per_second, fee_share_bps,
+                referral_code, disabled, created_at, updated_at
+            FROM tenants
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        row.map(|row| {
+            Ok(Tenant {
+                id: row.id,
+                name: self.open("tenants.name", row.name)?,
+                max_requests_per_second: row.max_requests_per_second.map(|limit| limit as u32),
+                max_orders_per_second: row.max_orders_per_second.map(|limit| limit as u32),
+                fee_share_bps: row.fee_share_bps as u64,
+                referral_code: row.referral_code,
+                disabled: row.disabled,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+            })
+        })
+        .transpose()
+    }
+
+    async fn get_tenant_by_referral_code(&self, code: &str) -> ClobResult<Option<Tenant>> {
+        let row = sqlx::query!(r#"SELECT id FROM tenants WHERE referral_code = $1"#, code)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        match row {
+            Some(row) => self.get_tenant(&row.id).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn get_tenants(&self, page: &Paginated) -> ClobResult<Page<Tenant>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, name, max_requests_per_second, max_orders_per_second, fee_share_bps,
+                referral_code, disabled, created_at, updated_at
+            FROM tenants
+            ORDER BY id
+            LIMIT $1 OFFSET $2
+            "#,
+            page.limit() as i64 + 1,
+            page.offset()? as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let tenants = rows
+            .into_iter()
+            .map(|row| {
+                Ok(Tenant {
+                    id: row.id,
+                    name: self.open("tenants.name", row.name)?,
+                    max_requests_per_second: row.max_requests_per_second.map(|limit| limit as u32),
+                    max_orders_per_second: row.max_orders_per_second.map(|limit| limit as u32),
+                    fee_share_bps: row.fee_share_bps as u64,
+                    referral_code: row.referral_code,
+                    disabled: row.disabled,
+                    created_at: row.created_at,
+                    updated_at: row.updated_at,
+                })
+            })
+            .collect::<ClobResult<Vec<_>>>()?;
+        Page::from_fetched(tenants, page)
+    }
+
+    async fn store_tenant_api_key(&self, key: &TenantApiKey) -> ClobResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO tenant_api_keys (key_id, tenant_id, key_hash, label, paper, entitlement, created_at, revoked_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            key.key_id,
+            key.tenant_id,
+            key.key_hash,
+            key.label.as_deref().map(|label| self.seal("tenant_api_keys.label", label)).transpose()?,
+            key.paper,
+            key.entitlement as i16,
+            key.created_at,
+            key.revoked_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_tenant_api_key(&self, key_hash: &str) -> ClobResult<Option<TenantApiKey>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT key_id, tenant_id, key_hash, label, paper, entitlement, created_at, revoked_at
+            FROM tenant_api_keys
+            WHERE key_hash = $1
+            "#,
+            key_hash
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        row.map(|row| {
+            Ok(TenantApiKey {
+                key_id: row.key_id,
+                tenant_id: row.tenant_id,
+                key_hash: row.key_hash,
+                label: row.label.map(|label| self.open("tenant_api_keys.label", label)).transpose()?,
+                paper: row.paper,
+                entitlement: feed_entitlement(row.entitlement)?,
+                created_at: row.created_at,
+                revoked_at: row.revoked_at,
+            })
+        })
+        .transpose()
+    }
+
+    async fn get_tenant_api_keys(&self, tenant_id: &str) -> ClobResult<Vec<TenantApiKey>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT key_id, tenant_id, key_hash, label, paper, entitlement, created_at, revoked_at
+            FROM tenant_api_keys
+            WHERE tenant_id = $1
+            ORDER BY created_at, key_id
+            "#,
+            tenant_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(TenantApiKey {
+                    key_id: row.key_id,
+                    tenant_id: row.tenant_id,
+                    key_hash: row.key_hash,
+                    label: row.label.map(|label| self.open("tenant_api_keys.label", label)).transpose()?,
+                    paper: row.paper,
+                    entitlement: feed_entitlement(row.entitlement)?,
+                    created_at: row.created_at,
+                    revoked_at: row.revoked_at,
+                })
+            })
+            .collect()
+    }
+
+    async fn revoke_tenant_api_key(&self, tenant_id: &str, key_id: &str, revoked_at: i64) -> ClobResult<bool> {
+        let revoked = sqlx::query!(
+            r#"
+            UPDATE tenant_api_keys
+            SET revoked_at = $3
+            WHERE tenant_id = $1 AND key_id = $2 AND revoked_at IS NULL
+            "#,
+            tenant_id,
+            key_id,
+            revoked_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?
+        .rows_affected();
+
+        Ok(revoked > 0)
+    }
+
+    async fn set_tenant_api_key_entitlement(
+        &self,
+        tenant_id: &str,
+        key_id: &str,
+        entitlement: FeedEntitlement,
+    ) -> ClobResult<bool> {
+        let updated = sqlx::query!(
+            r#"
+            UPDATE tenant_api_keys
+            SET entitlement = $3
+            WHERE tenant_id = $1 AND key_id = $2 AND revoked_at IS NULL
+            "#,
+            tenant_id,
+            key_id,
+            entitlement as i16
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?
+        .rows_affected();
+
+        Ok(updated > 0)
+    }
+
+    async fn add_tenant_usage(&self, usage: &[TenantUsage]) -> ClobResult<()> {
+        let mut tx = self.pool.begin().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+        for counts in usage {
+            sqlx::query!(
+                r#"
+                INSERT INTO tenant_usage (
+                    tenant_id, period_start, requests, errors, rate_limited, orders_placed, notional_placed
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                ON CONFLICT (tenant_id, period_start) DO UPDATE SET
+                    requests = tenant_usage.requests + EXCLUDED.requests,
+                    errors = tenant_usage.errors + EXCLUDED.errors,
+                    rate_limited = tenant_usage.rate_limited + EXCLUDED.rate_limited,
+                    orders_placed = tenant_usage.orders_placed + EXCLUDED.orders_placed,
+                    notional_placed = tenant_usage.notional_placed + EXCLUDED.notional_placed
+                "#,
+                counts.tenant_id,
+                counts.period_start,
+                counts.requests as i64,
+                counts.errors as i64,
+                counts.rate_limited as i64,
+                counts.orders_placed as i64,
+                counts.notional_placed.min(i64::MAX as u64) as i64
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        }
+        tx.commit().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_tenant_usage(&self, tenant_id: &str, from: i64, until: i64) -> ClobResult<Vec<TenantUsage>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT tenant_id, period_start, requests, errors, rate_limited, orders_placed, notional_placed
+            FROM tenant_usage
+            WHERE tenant_id = $1 AND period_start >= $2 AND period_start < $3
+            ORDER BY period_start
+            "#,
+            tenant_id,
+            from,
+            until
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| TenantUsage {
+                tenant_id: row.tenant_id,
+                period_start: row.period_start,
+                requests: row.requests as u64,
+                errors: row.errors as u64,
+                rate_limited: row.rate_limited as u64,
+                orders_placed: row.orders_placed as u64,
+                notional_placed: row.notional_placed as u64,
+            })
+            .collect())
+    }
+
+    async fn store_incentive_epoch(&self, epoch: &IncentiveEpoch, reports: &[IncentiveReport]) -> ClobResult<()> {
+        let mut tx = self.pool.begin().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+        let inserted = sqlx::query!(
+            r#"
+            INSERT INTO incentive_epochs (
+                epoch_start, epoch_end, band_bps, reward_pool, makers, total_depth_seconds, last_sequence
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (epoch_start) DO NOTHING
+            "#,
+            epoch.epoch_start,
+            epoch.epoch_end,
+            epoch.band_bps as i64,
+            epoch.reward_pool as i64,
+            epoch.makers as i32,
+            epoch.total_depth_seconds as i64,
+            epoch.last_sequence as i64
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?
+        .rows_affected();
+        if inserted == 0 {
+            return Ok(());
+        }
+
+        for report in reports {
+            sqlx::query!(
+                r#"
+                INSERT INTO incentive_reports (
+                    epoch_start, epoch_end, maker, bid_depth_seconds, ask_depth_seconds, average_depth, share, reward
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                "#,
+                report.epoch_start,
+                report.epoch_end,
+                report.maker.to_string(),
+                report.bid_depth_seconds as i64,
+                report.ask_depth_seconds as i64,
+                report.average_depth as i64,
+                report.share,
+                report.reward as i64
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        }
+        tx.commit().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_last_incentive_epoch(&self) -> ClobResult<Option<IncentiveEpoch>> {
+        let page = self.get_incentive_epochs(&Paginated::first(1)).await?;
+        Ok(page.items.into_iter().next())
+    }
+
+    async fn get_incentive_epochs(&self, page: &Paginated) -> ClobResult<Page<IncentiveEpoch>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT epoch_start, epoch_end, band_bps, reward_pool, makers, total_depth_seconds, last_sequence
+            FROM incentive_epochs
+            ORDER BY epoch_start DESC
+            LIMIT $1 OFFSET $2
+            "#,
+            page.limit() as i64 + 1,
+            page.offset()? as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let epochs = rows
+            .into_iter()
+            .map(|row| IncentiveEpoch {
+                epoch_start: row.epoch_start,
+                epoch_end: row.epoch_end,
+                band_bps: row.band_bps as u64,
+                reward_pool: row.reward_pool as u64,
+                makers: row.makers as u32,
+                total_depth_seconds: row.total_depth_seconds as u64,
+                last_sequence: row.last_sequence as u64,
+            })
+            .collect();
+        Page::from_fetched(epochs, page)
+    }
+
+    async fn get_incentive_reports(&self, query: &IncentiveQuery, page: &Paginated) -> ClobResult<Page<IncentiveReport>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT epoch_start, epoch_end, maker, bid_depth_seconds, ask_depth_seconds, average_depth, share, reward
+            FROM incentive_reports
+            WHERE ($1::BIGINT IS NULL OR epoch_start = $1)
+              AND ($2::TEXT IS NULL OR maker = $2)
+            ORDER BY epoch_start DESC, reward DESC, maker
+            LIMIT $3 OFFSET $4
+            "#,
+            query.epoch_start,
+            query.maker.map(|maker| maker.to_string()),
+            page.limit() as i64 + 1,
+            page.offset()? as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let mut reports = Vec::new();
+        for row in rows {
+            reports.push(IncentiveReport {
+                epoch_start: row.epoch_start,
+                epoch_end: row.epoch_end,
+                maker: row.maker.parse().map_err(|e| ClobError::StorageError(format!("Invalid pubkey: {}", e)))?,
+                bid_depth_seconds: row.bid_depth_seconds as u64,
+                ask_depth_seconds: row.ask_depth_seconds as u64,
+                average_depth: row.average_depth as u64,
+                share: row.share,
+                reward: row.reward as u64,
+            });
+        }
+        Page::from_fetched(reports, page)
+    }
+
+    async fn store_market_maker(&self, obligation: &MarketMakerObligation) -> ClobResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO market_makers (
+                maker, label, max_spread_bps, min_depth, min_uptime_bps, registered_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (maker) DO UPDATE SET
+                label = EXCLUDED.label,
+                max_spread_bps = EXCLUDED.max_spread_bps,
+                min_depth = EXCLUDED.min_depth,
+                min_uptime_bps = EXCLUDED.min_uptime_bps,
+                updated_at = EXCLUDED.updated_at
+            "#,
+            obligation.maker.to_string(),
+            obligation.label,
+            obligation.max_spread_bps as i64,
+            obligation.min_depth as i64,
+            obligation.min_uptime_bps as i64,
+            obligation.registered_at,
+            obligation.updated_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_market_maker(&self, maker: &Pubkey) -> ClobResult<Option<MarketMakerObligation>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT maker, label, max_spread_bps, min_depth, min_uptime_bps, registered_at, updated_at
+            FROM market_makers
+            WHERE maker = $1
+            "#,
+            maker.to_string()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        row.map(|row| {
+            Ok(MarketMakerObligation {
+                maker: row.maker.parse().map_err(|e| ClobError::StorageError(format!("Invalid pubkey: {}", e)))?,
+                label: row.label,
+                max_spread_bps: row.max_spread_bps as u64,
+                min_depth: row.min_depth as u64,
+                min_uptime_bps: row.min_uptime_bps as u64,
+                registered_at: row.registered_at,
+                updated_at: row.updated_at,
+            })
+        })
+        .transpose()
+    }
+
+    async fn get_market_makers(&self, page: &Paginated) -> ClobResult<Page<MarketMakerObligation>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT maker, label, max_spread_bps, min_depth, min_uptime_bps, registered_at, updated_at
+            FROM market_makers
+            ORDER BY maker
+            LIMIT $1 OFFSET $2
+            "#,
+            page.limit() as i64 + 1,
+            page.offset()? as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let makers = rows
+            .into_iter()
+            .map(|row| {
+                Ok(MarketMakerObligation {
+                    maker: row.maker.parse().map_err(|e| ClobError::StorageError(format!("Invalid pubkey: {}", e)))?,
+                    label: row.label,
+                    max_spread_bps: row.max_spread_bps as u64,
+                    min_depth: row.min_depth as u64,
+                    min_uptime_bps: row.min_uptime_bps as u64,
+                    registered_at: row.registered_at,
+                    updated_at: row.updated_at,
+                })
+            })
+            .collect::<ClobResult<Vec<_>>>()?;
+        Page::from_fetched(makers, page)
+    }
+
+    async fn remove_market_maker(&self, maker: &Pubkey) -> ClobResult<bool> {
+        let removed = sqlx::query!("DELETE FROM market_makers WHERE maker = $1", maker.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?
+            .rows_affected();
+        Ok(removed > 0)
+    }
+
+    async fn store_obligation_period(
+        &self,
+        period: &ObligationPeriod,
+        scorecards: &[MarketMakerScorecard],
+    ) -> ClobResult<()> {
+        let mut tx = self.pool.begin().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+        let inserted = sqlx::query!(
+            r#"
+            INSERT INTO obligation_periods (period_start, period_end, sample_secs, makers, last_sequence)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (period_start) DO NOTHING
+            "#,
+            period.period_start,
+            period.period_end,
+            period.sample_secs,
+            period.makers as i32,
+            period.last_sequence as i64
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?
+        .rows_affected();
+        if inserted == 0 {
+            return Ok(());
+        }
+
+        for scorecard in scorecards {
+            sqlx::query!(
+                r#"
+                INSERT INTO market_maker_scorecards (
+                    period_start, period_end, maker, max_spread_bps, min_depth, min_uptime_bps,
+                    samples, two_sided_samples, compliant_samples, uptime_bps, average_spread_bps,
+                    average_bid_depth, average_ask_depth, meets_obligations
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+                "#,
+                scorecard.period_start,
+                scorecard.period_end,
+                scorecard.maker.to_string(),
+                scorecard.max_spread_bps as i64,
+                scorecard.min_depth as i64,
+                scorecard.min_uptime_bps as i64,
+                scorecard.samples as i32,
+                scorecard.two_sided_samples as i32,
+                scorecard.compliant_samples as i32,
+                scorecard.uptime_bps as i64,
+                scorecard.average_spread_bps.map(|spread| spread as i64),
+                scorecard.average_bid_depth as i64,
+                scorecard.average_ask_depth as i64,
+                scorecard.meets_obligations
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        }
+        tx.commit().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_last_obligation_period(&self) -> ClobResult<Option<ObligationPeriod>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT period_start, period_end, sample_secs, makers, last_sequence
+            FROM obligation_periods
+            ORDER BY period_start DESC
+            LIMIT 1
+            "#
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(row.map(|row| ObligationPeriod {
+            period_start: row.period_start,
+            period_end: row.period_end,
+            sample_secs: row.sample_secs,
+            makers: row.makers as u32,
+            last_sequence: row.last_sequence as u64,
+        }))
+    }
+
+    async fn get_market_maker_scorecards(
+        &self,
+        query: &ScorecardQuery,
+        page: &Paginated,
+    ) -> ClobResult<Page<MarketMakerScorecard>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT period_start, period_end, maker, max_spread_bps, min_depth, min_uptime_bps,
+                samples, two_sided_samples, compliant_samples, uptime_bps, average_spread_bps,
+                average_bid_depth, average_ask_depth, meets_obligations
+            FROM market_maker_scorecards
+            WHERE ($1::BIGINT IS NULL OR period_start = $1)
+              AND ($2::TEXT IS NULL OR maker = $2)
+            ORDER BY period_start DESC, maker
+            LIMIT $3 OFFSET $4
+            "#,
+            query.period_start,
+            query.maker.map(|maker| maker.to_string()),
+            page.limit() as i64 + 1,
+            page.offset()? as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let mut scorecards = Vec::new();
+        for row in rows {
+            scorecards.push(MarketMakerScorecard {
+                period_start: row.period_start,
+                period_end: row.period_end,
+                maker: row.maker.parse().map_err(|e| ClobError::StorageError(format!("Invalid pubkey: {}", e)))?,
+                max_spread_bps: row.max_spread_bps as u64,
+                min_depth: row.min_depth as u64,
+                min_uptime_bps: row.min_uptime_bps as u64,
+                samples: row.samples as u32,
+                two_sided_samples: row.two_sided_samples as u32,
+                compliant_samples: row.compliant_samples as u32,
+                uptime_bps: row.uptime_bps as u64,
+                average_spread_bps: row.average_spread_bps.map(|spread| spread as u64),
+                average_bid_depth: row.average_bid_depth as u64,
+                average_ask_depth: row.average_ask_depth as u64,
+                meets_obligations: row.meets_obligations,
+            });
+        }
+        Page::from_fetched(scorecards, page)
+    }
+
+    async fn store_statement_day(&self, day: &StatementDay, statements: &[StatementArtifacts]) -> ClobResult<()> {
+        let mut tx = self.pool.begin().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+        let inserted = sqlx::query!(
+            r#"
+            INSERT INTO statement_days (period_start, period_end, statements, generated_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (period_start) DO NOTHING
+            "#,
+            day.period_start,
+            day.period_end,
+            day.statements as i32,
+            day.generated_at
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?
+        .rows_affected();
+        if inserted == 0 {
+            return Ok(());
+        }
+
+        for statement in statements {
+            let summary = &statement.summary;
+            sqlx::query!(
+                r#"
+                INSERT INTO statements (
+                    period_start, period_end, owner, statement_date, fills, deposits, withdrawals,
+                    transfers, total_fees, ending_base_balance, ending_quote_balance, generated_at,
+                    json, csv, pdf
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+                "#,
+                summary.period_start,
+                summary.period_end,
+                summary.owner.to_string(),
+                summary.date,
+                summary.fills as i32,
+                summary.deposits as i32,
+                summary.withdrawals as i32,
+                summary.transfers as i32,
+                summary.total_fees,
+                summary.ending_base_balance,
+                summary.ending_quote_balance,
+                summary.generated_at,
+                statement.json,
+                statement.csv,
+                statement.pdf
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        }
+        tx.commit().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_last_statement_day(&self) -> ClobResult<Option<StatementDay>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT period_start, period_end, statements, generated_at
+            FROM statement_days
+            ORDER BY period_start DESC
+            LIMIT 1
+            "#
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(row.map(|row| StatementDay {
+            period_start: row.period_start,
+            period_end: row.period_end,
+            statements: row.statements as u32,
+            generated_at: row.generated_at,
+        }))
+    }
+
+    async fn get_statements(&self, owner: &Pubkey, page: &Paginated) -> ClobResult<Page<StatementSummary>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT period_start, period_end, statement_date, fills, deposits, withdrawals,
+                   transfers, total_fees, ending_base_balance, ending_quote_balance, generated_at
+            FROM statements
+            WHERE owner = $1
+            ORDER BY period_start DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            owner.to_string(),
+            page.limit() as i64 + 1,
+            page.offset()? as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let statements = rows
+            .into_iter()
+            .map(|row| StatementSummary {
+                owner: *owner,
+                date: row.statement_date,
+                period_start: row.period_start,
+                period_end: row.period_end,
+                fills: row.fills as u32,
+                deposits: row.deposits as u32,
+                withdrawals: row.withdrawals as u32,
+                transfers: row.transfers as u32,
+                total_fees: row.total_fees,
+                ending_base_balance: row.ending_base_balance,
+                ending_quote_balance: row.ending_quote_balance,
+                generated_at: row.generated_at,
+            })
+            .collect();
+        Page::from_fetched(statements, page)
+    }
+
+    async fn get_statement_artifact(
+        &self,
+        owner: &Pubkey,
+        period_start: i64,
+        format: StatementFormat,
+    ) -> ClobResult<Option<Vec<u8>>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT CASE $3::SMALLINT WHEN 0 THEN json WHEN 1 THEN csv ELSE pdf END AS "artifact!"
+            FROM statements
+            WHERE owner = $1 AND period_start = $2
+            "#,
+            owner.to_string(),
+            period_start,
+            format as i16
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(row.map(|row| row.artifact))
+    }
+
+    async fn store_sequence_mappings(&self, mappings: &[SequenceMapping]) -> ClobResult<()> {
+        let mut tx = self.pool.begin().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+        for mapping in mappings {
+            sqlx::query!(
+                r#"
+                INSERT INTO sequence_map (
+                    journal_sequence, order_id, event, trade_id, role,
+                    owner, market_id, user_account, timestamp
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                ON CONFLICT (journal_sequence, order_id) DO NOTHING
+                "#,
+                mapping.journal_sequence as i64,
+                mapping.order_id as i64,
+                mapping.event as i16,
+                mapping.trade_id.map(|trade_id| trade_id as i64),
+                mapping.role.map(|role| role as i16),
+                mapping.owner.to_string(),
+                mapping.market_id.to_string(),
+                mapping.user_account.to_string(),
+                mapping.timestamp
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        }
+        tx.commit().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_sequence_mappings(&self, lookup: &SequenceLookup, page: &Paginated) -> ClobResult<Page<SequenceMapping>> {
+        let (order_id, journal_sequence, signature, user_account) = match lookup {
+            SequenceLookup::Order(order_id) => (Some(*order_id as i64), None, None, None),
+            SequenceLookup::JournalSequence(sequence) => (None, Some(*sequence as i64), None, None),
+            SequenceLookup::Signature(signature) => (None, None, Some(signature.clone()), None),
+            SequenceLookup::UserAccount(account) => (None, None, None, Some(account.to_string())),
+        };
+        let rows = sqlx::query!(
+            r#"
+            SELECT m.journal_sequence, m.order_id, m.event, m.trade_id, m.role,
+                   m.owner, m.market_id, m.user_account, m.timestamp,
+                   s.signature AS "signature?", s.status AS "settlement_status?",
+                   c.status AS "chain_status?"
+            FROM sequence_map m
+            LEFT JOIN trade_settlements s ON s.journal_sequence = m.journal_sequence AND m.event = 2
+            LEFT JOIN chain_transactions c ON c.signature = s.signature
+            WHERE ($1::BIGINT IS NULL OR m.order_id = $1)
+              AND ($2::BIGINT IS NULL OR m.journal_sequence = $2)
+              AND ($3::TEXT IS NULL OR s.signature = $3)
+              AND ($4::TEXT IS NULL OR m.user_account = $4)
+            ORDER BY m.journal_sequence ASC, m.order_id ASC
+            LIMIT $5 OFFSET $6
+            "#,
+            order_id,
+            journal_sequence,
+            signature,
+            user_account,
+            page.limit() as i64 + 1,
+            page.offset()? as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let mut mappings = Vec::new();
+        for row in rows {
+            mappings.push(SequenceMapping {
+                journal_sequence: row.journal_sequence as u64,
+                order_id: row.order_id as u64,
+                event: SequenceEvent::try_from(row.event as u8)
+                    .map_err(|_| ClobError::StorageError("Invalid sequence event".to_string()))?,
+                trade_id: row.trade_id.map(|trade_id| trade_id as u64),
+                role: row
+                    .role
+                    .map(|role| LiquidityRole::try_from(role as u8))
+                    .transpose()
+                    .map_err(|_| ClobError::StorageError("Invalid liquidity role".to_string()))?,
+                owner: row.owner.parse().map_err(|e| ClobError::StorageError(format!("Invalid pubkey: {}", e)))?,
+                market_id: parse_market_id(&row.market_id)?,
+                user_account: row.user_account.parse().map_err(|e| ClobError::StorageError(format!("Invalid pubkey: {}", e)))?,
+                signature: row.signature,
+                settlement_status: row
+                    .settlement_status
+                    .map(|status| SettlementStatus::try_from(status as u8))
+                    .transpose()
+                    .map_err(|_| ClobError::StorageError("Invalid settlement status".to_string()))?,
+                chain_status: row.chain_status.map(chain_status).transpose()?,
+                timestamp: row.timestamp,
+            });
+        }
+        Page::from_fetched(mappings, page)
+    }
+
+    async fn get_market_status(&self, market_id: &MarketId) -> ClobResult<Option<MarketStatus>> {
+        let row = sqlx::query!("SELECT status FROM market_status WHERE market_id = $1", market_id.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        row.map(|row| {
+            MarketStatus::try_from(row.status as u8).map_err(|_| ClobError::StorageError("Invalid market status".to_string()))
+        })
+        .transpose()
+    }
+
+    async fn set_market_status(&self, market_id: &MarketId, status: MarketStatus, updated_at: i64) -> ClobResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO market_status (market_id, status, updated_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (market_id) DO UPDATE SET
+                status = EXCLUDED.status,
+                updated_at = EXCLUDED.updated_at
+            "#,
+            market_id.to_string(),
+            status as i16,
+            updated_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn store_suspension(&self, suspension: &Suspension) -> ClobResult<()> {
+        let (kind, subject) = suspension_subject_columns(&suspension.subject);
+        sqlx::query!(
+            r#"
+            INSERT INTO suspensions (subject_kind, subject, reason, note, actor, suspended_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (subject_kind, subject) DO UPDATE SET
+                reason = EXCLUDED.reason,
+                note = EXCLUDED.note,
+                actor = EXCLUDED.actor,
+                suspended_at = EXCLUDED.suspended_at
+            "#,
+            kind,
+            subject,
+            suspension.reason as i16,
+            suspension.note,
+            suspension.actor,
+            suspension.suspended_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        info!("Stored suspension of {}", suspension.subject);
+        Ok(())
+    }
+
+    async fn lift_suspension(&self, subject: &SuspensionSubject) -> ClobResult<bool> {
+        let (kind, subject) = suspension_subject_columns(subject);
+        let result = sqlx::query!(
+            "DELETE FROM suspensions WHERE subject_kind = $1 AND subject = $2",
+            kind,
+            subject
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_suspensions(&self) -> ClobResult<Vec<Suspension>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT subject_kind, subject, reason, note, actor, suspended_at
+            FROM suspensions
+            ORDER BY suspended_at, subject_kind, subject
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(Suspension {
+                    subject: match row.subject_kind {
+                        0 => SuspensionSubject::Owner(
+                            row.subject
+                                .parse()
+                                .map_err(|e| ClobError::StorageError(format!("Invalid pubkey: {}", e)))?,
+                        ),
+                        1 => SuspensionSubject::ApiKey(row.subject),
+                        _ => return Err(ClobError::StorageError("Invalid suspension subject".to_string())),
+                    },
+                    reason: SuspensionReason::try_from(row.reason as u8)
+                        .map_err(|_| ClobError::StorageError("Invalid suspension reason".to_string()))?,
+                    note: row.note,
+                    actor: row.actor,
+                    suspended_at: row.suspended_at,
+                })
+            })
+            .collect()
+    }
+
+    async fn claim_signed_nonce(&self, owner: &Pubkey, nonce: u64, expiry: i64, now: i64) -> ClobResult<bool> {
+        // An expired claim is taken over; an unexpired one leaves no row returned
+        let claimed = sqlx::query!(
+            r#"
+            INSERT INTO nonce_claims (owner, nonce, expiry, claimed_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (owner, nonce) DO UPDATE SET
+                expiry = EXCLUDED.expiry,
+                claimed_at = EXCLUDED.claimed_at
+            WHERE nonce_claims.expiry < $4
+            RETURNING nonce
+            "#,
+            owner.to_string(),
+            nonce as i64,
+            expiry,
+            now
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(claimed.is_some())
+    }
+
+    async fn signed_nonce_claimed(&self, owner: &Pubkey, nonce: u64, now: i64) -> ClobResult<bool> {
+        let row = sqlx::query!(
+            "SELECT expiry FROM nonce_claims WHERE owner = $1 AND nonce = $2 AND expiry >= $3",
+            owner.to_string(),
+            nonce as i64,
+            now
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(row.is_some())
+    }
+
+    async fn store_vault_transfer(&self, transfer: &VaultTransfer) -> ClobResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO vault_transfers (
+                signature, instruction_index, slot, mint, source, sender, amount, status,
+                confirmations, reason, credit_signature, detected_at, updated_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            ON CONFLICT (signature, instruction_index) DO NOTHING
+            "#,
+            transfer.signature,
+            transfer.instruction_index as i16,
+            transfer.slot as i64,
+            transfer.mint.to_string(),
+            transfer.source.to_string(),
+            transfer.sender.to_string(),
+            transfer.amount as i64,
+            transfer.status as i16,
+            transfer.confirmations.map(|confirmations| confirmations as i64),
+            transfer.reason,
+            transfer.credit_signature,
+            transfer.detected_at,
+            transfer.updated_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        info!("Stored vault transfer {}#{}", transfer.signature, transfer.instruction_index);
+        Ok(())
+    }
+
+    async fn update_vault_transfer(&self, transfer: &VaultTransfer) -> ClobResult<()> {
+        sqlx::query!(
+            r#"
+            UPDATE vault_transfers
+            SET status = $3, confirmations = $4, reason = $5, credit_signature = $6, updated_at = $7
+            WHERE signature = $1 AND instruction_index = $2
+            "#,
+            transfer.signature,
+            transfer.instruction_index as i16,
+            transfer.status as i16,
+            transfer.confirmations.map(|confirmations| confirmations as i64),
+            transfer.reason,
+            transfer.credit_signature,
+            transfer.updated_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_vault_transfer(&self, signature: &str, instruction_index: u8) -> ClobResult<Option<VaultTransfer>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT signature, instruction_index, slot, mint, source, sender, amount, status,
+                   confirmations, reason, credit_signature, detected_at, updated_at
+            FROM vault_transfers
+            WHERE signature = $1 AND instruction_index = $2
+            "#,
+            signature,
+            instruction_index as i16
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        row.map(|row| {
+            Ok(VaultTransfer {
+                signature: row.signature,
+                instruction_index: row.instruction_index as u8,
+                slot: row.slot as u64,
+                mint: parse_pubkey(&row.mint)?,
+                source: parse_pubkey(&row.source)?,
+                sender: parse_pubkey(&row.sender)?,
+                amount: row.amount as u64,
+                status: vault_transfer_status(row.status)?,
+                confirmations: row.confirmations.map(|confirmations| confirmations as u64),
+                reason: row.reason,
+                credit_signature: row.credit_signature,
+                detected_at: row.detected_at,
+                updated_at: row.updated_at,
+            })
+        })
+        .transpose()
+    }
+
+    async fn get_vault_transfers_by_status(&self, status: VaultTransferStatus, limit: u32) -> ClobResult<Vec<VaultTransfer>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT signature, instruction_index, slot, mint, source, sender, amount, status,
+                   confirmations, reason, credit_signature, detected_at, updated_at
+            FROM vault_transfers
+            WHERE status = $1
+            ORDER BY slot, signature, instruction_index
+            LIMIT $2
+            "#,
+            status as i16,
+            limit as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(VaultTransfer {
+                    signature: row.signature,
+                    instruction_index: row.instruction_index as u8,
+                    slot: row.slot as u64,
+                    mint: parse_pubkey(&row.mint)?,
+                    source: parse_pubkey(&row.source)?,
+                    sender: parse_pubkey(&row.sender)?,
+                    amount: row.amount as u64,
+                    status: vault_transfer_status(row.status)?,
+                    confirmations: row.confirmations.map(|confirmations| confirmations as u64),
+                    reason: row.reason,
+                    credit_signature: row.credit_signature,
+                    detected_at: row.detected_at,
+                    updated_at: row.updated_at,
+                })
+            })
+            .collect()
+    }
+
+    async fn get_user_vault_transfers(&self, sender: &Pubkey, page: &Paginated) -> ClobResult<Page<VaultTransfer>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT signature, instruction_index, slot, mint, source, sender, amount, status,
+                   confirmations, reason, credit_signature, detected_at, updated_at
+            FROM vault_transfers
+            WHERE sender = $1
+            ORDER BY slot DESC, signature DESC, instruction_index DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            sender.to_string(),
+            page.limit() as i64 + 1,
+            page.offset()? as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let transfers = rows
+            .into_iter()
+            .map(|row| {
+                Ok(VaultTransfer {
+                    signature: row.signature,
+                    instruction_index: row.instruction_index as u8,
+                    slot: row.slot as u64,
+                    mint: parse_pubkey(&row.mint)?,
+                    source: parse_pubkey(&row.source)?,
+                    sender: parse_pubkey(&row.sender)?,
+                    amount: row.amount as u64,
+                    status: vault_transfer_status(row.status)?,
+                    confirmations: row.confirmations.map(|confirmations| confirmations as u64),
+                    reason: row.reason,
+                    credit_signature: row.credit_signature,
+                    detected_at: row.detected_at,
+                    updated_at: row.updated_at,
+                })
+            })
+            .collect::<ClobResult<Vec<_>>>()?;
+        Page::from_fetched(transfers, page)
+    }
+
+    async fn get_trade(&self, trade_id: u64) -> ClobResult<Option<TradeExecution>> {
+        let row = sqlx::query!("SELECT * FROM trades WHERE trade_id = $1", trade_id as i64)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        row.map(|row| {
+            Ok(TradeExecution {
+                trade_id: row.trade_id as u64,
+                market_id: parse_market_id(&row.market_id)?,
+                maker_order_id: row.maker_order_id as u64,
+                taker_order_id: row.taker_order_id as u64,
+                price: row.price as u64,
+                quantity: row.quantity as u64,
+                timestamp: row.timestamp,
+                maker_side: OrderSide::try_from(row.maker_side as u8).map_err(|_| ClobError::InvalidOrderSide)?,
+                received_at_us: row.received_at_us,
+                matched_at_us: row.matched_at_us,
+                version: row.version as u32,
+                status: trade_status(row.status)?,
+            })
+        })
+        .transpose()
+    }
+
+    async fn get_trade_journal_entry(&self, trade_id: u64) -> ClobResult<Option<JournalEntry>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT sequence, timestamp, payload, trace_context, request_id
+            FROM engine_journal
+            WHERE payload->>'type' = 'TradeExecuted'
+              AND (payload->'data'->'trade'->>'trade_id')::BIGINT = $1
+            ORDER BY sequence
+            LIMIT 1
+            "#,
+            trade_id as i64
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        row.map(|row| {
+            Ok(JournalEntry {
+                sequence: row.sequence as u64,
+                timestamp: row.timestamp,
+                event: serde_json::from_value(row.payload)
+                    .map_err(|e| ClobError::SerializationError(e.to_string()))?,
+                trace_context: row.trace_context,
+                request_id: row.request_id,
+            })
+        })
+        .transpose()
+    }
+
+    #[instrument(name = "storage.store_trade_adjustment", skip_all, fields(adjustment_id = adjustment.adjustment_id))]
+    async fn store_trade_adjustment(&self, adjustment: &TradeAdjustment) -> ClobResult<()> {
+        let mut tx = self.pool.begin().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO trade_adjustments (
+                adjustment_id, trade_id, market_id, version, status, trade_sequence,
+                maker, taker, maker_order_id, taker_order_id, maker_side, quantity,
+                previous_price, price, base_to_maker, quote_to_maker, reason, actor,
+                reversal, reversal_signature, reversal_error, created_at, updated_at
+            ) VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12,
+                $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23
+            )
+            "#,
+            adjustment.adjustment_id as i64,
+            adjustment.trade_id as i64,
+            adjustment.market_id.to_string(),
+            adjustment.version as i32,
+            adjustment.status as i16,
+            adjustment.trade_sequence as i64,
+            adjustment.maker.to_string(),
+            adjustment.taker.to_string(),
+            adjustment.maker_order_id as i64,
+            adjustment.taker_order_id as i64,
+            adjustment.maker_side as i16,
+            adjustment.quantity as i64,
+            adjustment.previous_price as i64,
+            adjustment.price as i64,
+            adjustment.base_to_maker,
+            adjustment.quote_to_maker,
+            adjustment.reason,
+            adjustment.actor,
+            adjustment.reversal as i16,
+            adjustment.reversal_signature,
+            adjustment.reversal_error,
+            adjustment.created_at,
+            adjustment.updated_at
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!(
+            "UPDATE trades SET price = $2, version = $3, status = $4 WHERE trade_id = $1",
+            adjustment.trade_id as i64,
+            adjustment.price as i64,
+            adjustment.version as i32,
+            adjustment.status as i16
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        tx.commit().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+        info!(
+            "Stored adjustment {} of trade {}: v{} {:?}",
+            adjustment.adjustment_id, adjustment.trade_id, adjustment.version, adjustment.status
+        );
+        Ok(())
+    }
+
+    async fn update_trade_adjustment(&self, adjustment: &TradeAdjustment) -> ClobResult<()> {
+        sqlx::query!(
+            r#"
+            UPDATE trade_adjustments
+            SET reversal = $2, reversal_signature = $3, reversal_error = $4, updated_at = $5
+            WHERE adjustment_id = $1
+            "#,
+            adjustment.adjustment_id as i64,
+            adjustment.reversal as i16,
+            adjustment.reversal_signature,
+            adjustment.reversal_error,
+            adjustment.updated_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_trade_adjustments(&self, trade_id: u64) -> ClobResult<Vec<TradeAdjustment>> {
+        let rows = sqlx::query!(
+            "SELECT * FROM trade_adjustments WHERE trade_id = $1 ORDER BY version",
+            trade_id as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(TradeAdjustment {
+                    adjustment_id: row.adjustment_id as u64,
+                    trade_id: row.trade_id as u64,
+                    market_id: parse_market_id(&row.market_id)?,
+                    version: row.version as u32,
+                    status: trade_status(row.status)?,
+                    trade_sequence: row.trade_sequence as u64,
+                    maker: parse_pubkey(&row.maker)?,
+                    taker: parse_pubkey(&row.taker)?,
+                    maker_order_id: row.maker_order_id as u64,
+                    taker_order_id: row.taker_order_id as u64,
+                    maker_side: OrderSide::try_from(row.maker_side as u8).map_err(|_| ClobError::InvalidOrderSide)?,
+                    quantity: row.quantity as u64,
+                    previous_price: row.previous_price as u64,
+                    price: row.price as u64,
+                    base_to_maker: row.base_to_maker,
+                    quote_to_maker: row.quote_to_maker,
+                    reason: row.reason,
+                    actor: row.actor,
+                    reversal: reversal_status(row.reversal)?,
+                    reversal_signature: row.reversal_signature,
+                    reversal_error: row.reversal_error,
+                    created_at: row.created_at,
+                    updated_at: row.updated_at,
+                })
+            })
+            .collect()
+    }
+
+    async fn get_trade_adjustments_by_reversal(&self, reversal: ReversalStatus, limit: u32) -> ClobResult<Vec<TradeAdjustment>> {
+        let rows = sqlx::query!(
+            "SELECT * FROM trade_adjustments WHERE reversal = $1 ORDER BY adjustment_id LIMIT $2",
+            reversal as i16,
+            limit as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(TradeAdjustment {
+                    adjustment_id: row.adjustment_id as u64,
+                    trade_id: row.trade_id as u64,
+                    market_id: parse_market_id(&row.market_id)?,
+                    version: row.version as u32,
+                    status: trade_status(row.status)?,
+                    trade_sequence: row.trade_sequence as u64,
+                    maker: parse_pubkey(&row.maker)?,
+                    taker: parse_pubkey(&row.taker)?,
+                    maker_order_id: row.maker_order_id as u64,
+                    taker_order_id: row.taker_order_id as u64,
+                    maker_side: OrderSide::try_from(row.maker_side as u8).map_err(|_| ClobError::InvalidOrderSide)?,
+                    quantity: row.quantity as u64,
+                    previous_price: row.previous_price as u64,
+                    price: row.price as u64,
+                    base_to_maker: row.base_to_maker,
+                    quote_to_maker: row.quote_to_maker,
+                    reason: row.reason,
+                    actor: row.actor,
+                    reversal: reversal_status(row.reversal)?,
+                    reversal_signature: row.reversal_signature,
+                    reversal_error: row.reversal_error,
+                    created_at: row.created_at,
+                    updated_at: row.updated_at,
+                })
+            })
+            .collect()
+    }
+
+    async fn get_recent_trade_adjustments(&self, reversal: Option<ReversalStatus>, page: &Paginated) -> ClobResult<Page<TradeAdjustment>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT * FROM trade_adjustments
+            WHERE $1::SMALLINT IS NULL OR reversal = $1
+            ORDER BY adjustment_id DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            reversal.map(|reversal| reversal as i16),
+            page.limit() as i64 + 1,
+            page.offset()? as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let adjustments = rows
+            .into_iter()
+            .map(|row| {
+                Ok(TradeAdjustment {
+                    adjustment_id: row.adjustment_id as u64,
+                    trade_id: row.trade_id as u64,
+                    market_id: parse_market_id(&row.market_id)?,
+                    version: row.version as u32,
+                    status: trade_status(row.status)?,
+                    trade_sequence: row.trade_sequence as u64,
+                    maker: parse_pubkey(&row.maker)?,
+                    taker: parse_pubkey(&row.taker)?,
+                    maker_order_id: row.maker_order_id as u64,
+                    taker_order_id: row.taker_order_id as u64,
+                    maker_side: OrderSide::try_from(row.maker_side as u8).map_err(|_| ClobError::InvalidOrderSide)?,
+                    quantity: row.quantity as u64,
+                    previous_price: row.previous_price as u64,
+                    price: row.price as u64,
+                    base_to_maker: row.base_to_maker,
+                    quote_to_maker: row.quote_to_maker,
+                    reason: row.reason,
+                    actor: row.actor,
+                    reversal: reversal_status(row.reversal)?,
+                    reversal_signature: row.reversal_signature,
+                    reversal_error: row.reversal_error,
+                    created_at: row.created_at,
+                    updated_at: row.updated_at,
+                })
+            })
+            .collect::<ClobResult<Vec<_>>>()?;
+        Page::from_fetched(adjustments, page)
+    }
+
+    async fn store_settlement_adjustment(&self, record: &SettlementAdjustmentRecord) -> ClobResult<()> {
+        let mut tx = self.pool.begin().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO settlement_adjustments (
+                signature, event_index, slot, adjustment_id, trade_id,
+                maker, taker, base_to_maker, quote_to_maker, timestamp
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (signature, event_index) DO NOTHING
+            "#,
+            record.signature,
+            record.event_index as i32,
+            record.slot as i64,
+            record.adjustment_id as i64,
+            record.trade_id as i64,
+            record.maker.to_string(),
+            record.taker.to_string(),
+            record.base_to_maker,
+            record.quote_to_maker,
+            record.timestamp
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!(
+            r#"
+            UPDATE trade_adjustments
+            SET reversal = $2, reversal_signature = $3, reversal_error = NULL, updated_at = $4
+            WHERE adjustment_id = $1
+            "#,
+            record.adjustment_id as i64,
+            ReversalStatus::Reversed as i16,
+            record.signature,
+            record.timestamp
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        tx.commit().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+        info!(
+            "Stored settlement adjustment {} of trade {} from {}",
+            record.adjustment_id, record.trade_id, record.signature
+        );
+        Ok(())
+    }
+
+    async fn store_receipt_tree(&self, record: &ReceiptTreeRecord) -> ClobResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO receipt_trees (
+                merkle_tree, orderbook, max_depth, max_buffer_size, generation, signature, slot, timestamp
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (merkle_tree) DO NOTHING
+            "#,
+            record.merkle_tree.to_string(),
+            record.orderbook.to_string(),
+            record.max_depth as i32,
+            record.max_buffer_size as i32,
+            record.generation as i32,
+            record.signature,
+            record.slot as i64,
+            record.timestamp
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        info!("Stored receipt tree {} of {}", record.merkle_tree, record.orderbook);
+        Ok(())
+    }
+
+    async fn get_receipt_tree(&self, merkle_tree: &Pubkey) -> ClobResult<Option<ReceiptTreeRecord>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT merkle_tree, orderbook, max_depth, max_buffer_size, generation, signature, slot, timestamp
+            FROM receipt_trees
+            WHERE merkle_tree = $1
+            "#,
+            merkle_tree.to_string()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        row.map(|row| {
+            Ok(ReceiptTreeRecord {
+                merkle_tree: parse_pubkey(&row.merkle_tree)?,
+                orderbook: parse_pubkey(&row.orderbook)?,
+                max_depth: row.max_depth as u32,
+                max_buffer_size: row.max_buffer_size as u32,
+                generation: row.generation as u32,
+                signature: row.signature,
+                slot: row.slot as u64,
+                timestamp: row.timestamp,
+            })
+        })
+        .transpose()
+    }
+
+    async fn store_compressed_receipts(&self, receipts: &[CompressedReceipt]) -> ClobResult<()> {
+        let mut tx = self.pool.begin().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+        for receipt in receipts {
+            let leaf = svm_clob_types::receipts::leaf_hash(&receipt.leaf());
+            sqlx::query!(
+                r#"
+                INSERT INTO compressed_receipts (
+                    merkle_tree, leaf_index, signature, slot, orderbook, owner, order_id,
+                    counterparty_order_id, price, quantity, timestamp, side, role, leaf
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+                ON CONFLICT (merkle_tree, leaf_index) DO NOTHING
+                "#,
+                receipt.merkle_tree.to_string(),
+                receipt.leaf_index as i64,
+                receipt.signature,
+                receipt.slot as i64,
+                receipt.orderbook.to_string(),
+                receipt.owner.to_string(),
+                receipt.order_id as i64,
+                receipt.counterparty_order_id as i64,
+                receipt.price as i64,
+                receipt.quantity as i64,
+                receipt.timestamp,
+                receipt.side as i16,
+                receipt.role as i16,
+                &leaf[..]
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
         }
+        tx.commit().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+        Ok(())
     }
-}
 
-impl TryFrom<u8> for SelfTradeBehavior {
-    type Error = ();
-    
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
-        match value {
-            0 => Ok(SelfTradeBehavior::DecrementAndCancel),
-            1 => Ok(SelfTradeBehavior::CancelProvide),
-            2 => Ok(SelfTradeBehavior::CancelTake),
-            3 => Ok(SelfTradeBehavior::CancelBoth),
-            _ => Err(()),
+    async fn get_compressed_receipts(&self, owner: &Pubkey, page: &Paginated) -> ClobResult<Page<CompressedReceipt>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT merkle_tree, leaf_index, signature, slot, orderbook, owner, order_id,
+                   counterparty_order_id, price, quantity, timestamp, side, role
+            FROM compressed_receipts
+            WHERE owner = $1
+            ORDER BY timestamp DESC, leaf_index DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            owner.to_string(),
+            page.limit() as i64 + 1,
+            page.offset()? as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let receipts = rows
+            .into_iter()
+            .map(|row| {
+                Ok(CompressedReceipt {
+                    merkle_tree: parse_pubkey(&row.merkle_tree)?,
+                    leaf_index: row.leaf_index as u64,
+                    signature: row.signature,
+                    slot: row.slot as u64,
+                    orderbook: parse_pubkey(&row.orderbook)?,
+                    owner: parse_pubkey(&row.owner)?,
+                    order_id: row.order_id as u64,
+                    counterparty_order_id: row.counterparty_order_id as u64,
+                    price: row.price as u64,
+                    quantity: row.quantity as u64,
+                    timestamp: row.timestamp,
+                    side: OrderSide::try_from(row.side as u8).map_err(|_| ClobError::InvalidOrderSide)?,
+                    role: LiquidityRole::try_from(row.role as u8)
+                        .map_err(|_| ClobError::StorageError("Invalid liquidity role".to_string()))?,
+                })
+            })
+            .collect::<ClobResult<Vec<_>>>()?;
+        Page::from_fetched(receipts, page)
+    }
+
+    async fn get_compressed_receipt(&self, merkle_tree: &Pubkey, leaf_index: u64) -> ClobResult<Option<CompressedReceipt>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT merkle_tree, leaf_index, signature, slot, orderbook, owner, order_id,
+                   counterparty_order_id, price, quantity, timestamp, side, role
+            FROM compressed_receipts
+            WHERE merkle_tree = $1 AND leaf_index = $2
+            "#,
+            merkle_tree.to_string(),
+            leaf_index as i64
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        row.map(|row| {
+            Ok(CompressedReceipt {
+                merkle_tree: parse_pubkey(&row.merkle_tree)?,
+                leaf_index: row.leaf_index as u64,
+                signature: row.signature,
+                slot: row.slot as u64,
+                orderbook: parse_pubkey(&row.orderbook)?,
+                owner: parse_pubkey(&row.owner)?,
+                order_id: row.order_id as u64,
+                counterparty_order_id: row.counterparty_order_id as u64,
+                price: row.price as u64,
+                quantity: row.quantity as u64,
+                timestamp: row.timestamp,
+                side: OrderSide::try_from(row.side as u8).map_err(|_| ClobError::InvalidOrderSide)?,
+                role: LiquidityRole::try_from(row.role as u8)
+                    .map_err(|_| ClobError::StorageError("Invalid liquidity role".to_string()))?,
+            })
+        })
+        .transpose()
+    }
+
+    async fn get_receipt_leaves(&self, merkle_tree: &Pubkey) -> ClobResult<Vec<[u8; 32]>> {
+        let rows = sqlx::query!(
+            "SELECT leaf_index, leaf FROM compressed_receipts WHERE merkle_tree = $1 ORDER BY leaf_index",
+            merkle_tree.to_string()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        let mut leaves = Vec::with_capacity(rows.len());
+        for row in rows {
+            if row.leaf_index as usize != leaves.len() {
+                break;
+            }
+            let leaf = row
+                .leaf
+                .try_into()
+                .map_err(|_| ClobError::StorageError(format!("Invalid leaf {} of {}", row.leaf_index, merkle_tree)))?;
+            leaves.push(leaf);
         }
+        Ok(leaves)
+    }
+
+    async fn get_fee_accruals(&self, journal_sequence: u64) -> ClobResult<Vec<FeeAccrual>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT journal_sequence, role, owner, price, quantity, fee_bps, fee,
+                   insurance_contribution, timestamp, adjusted_sequence
+            FROM fee_accruals
+            WHERE journal_sequence = $1
+            ORDER BY role
+            "#,
+            journal_sequence as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(FeeAccrual {
+                    journal_sequence: row.journal_sequence as u64,
+                    user: parse_pubkey(&row.owner)?,
+                    role: LiquidityRole::try_from(row.role as u8)
+                        .map_err(|_| ClobError::StorageError("Invalid liquidity role".to_string()))?,
+                    price: row.price as u64,
+                    quantity: row.quantity as u64,
+                    fee_bps: row.fee_bps,
+                    fee: row.fee,
+                    insurance_contribution: row.insurance_contribution,
+                    timestamp: row.timestamp,
+                    adjusted_sequence: row.adjusted_sequence.map(|sequence| sequence as u64),
+                })
+            })
+            .collect()
+    }
+
+    async fn rewind(&self, journal_sequence: u64, timestamp: i64, journal_consumers: &[String]) -> ClobResult<()> {
+        let sequence = journal_sequence as i64;
+        let mut tx = self.pool.begin().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        sqlx::query!("DELETE FROM trade_settlements WHERE journal_sequence > $1", sequence)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM settlement_dead_letters WHERE first_sequence > $1", sequence)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM fee_accruals WHERE journal_sequence > $1", sequence)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM sequence_map WHERE journal_sequence > $1", sequence)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM surveillance_alerts WHERE last_sequence > $1", sequence)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM book_checkpoints WHERE journal_sequence > $1", sequence)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        // A trade goes back to the version the first removed adjustment
+        // replaced; only corrections come before another adjustment
+        sqlx::query!(
+            r#"
+            UPDATE trades t
+            SET price = a.previous_price,
+                version = a.version - 1,
+                status = CASE WHEN a.version > 1 THEN $2 ELSE $3 END
+            FROM (
+                SELECT DISTINCT ON (trade_id) trade_id, version, previous_price
+                FROM trade_adjustments
+                WHERE adjustment_id > $1
+                ORDER BY trade_id, version
+            ) a
+            WHERE t.trade_id = a.trade_id
+            "#,
+            sequence,
+            TradeStatus::Corrected as i16,
+            TradeStatus::Active as i16
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM trade_adjustments WHERE adjustment_id > $1", sequence)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM engine_journal WHERE sequence > $1", sequence)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM trades WHERE timestamp > $1", timestamp)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM orders WHERE timestamp > $1", timestamp)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM orderbook_snapshots WHERE timestamp > $1", timestamp)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM incentive_epochs WHERE epoch_end > $1", timestamp)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM obligation_periods WHERE period_end > $1", timestamp)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        sqlx::query!("DELETE FROM statement_days WHERE period_end > $1", timestamp)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        // Journal sequences stay gap-free after the cut
+        sqlx::query(
+            "SELECT setval(pg_get_serial_sequence('engine_journal', 'sequence'), GREATEST($1, 1), $1 > 0)",
+        )
+        .bind(sequence)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        sqlx::query!(
+            "UPDATE service_checkpoints SET position = LEAST(position, $1) WHERE service = ANY($2)",
+            sequence,
+            journal_consumers
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ClobError::StorageError(e.to_string()))?;
+
+        tx.commit().await.map_err(|e| ClobError::StorageError(e.to_string()))?;
+        info!("Rewound storage to journal sequence {} at {}", journal_sequence, timestamp);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "postgres")]
+fn parse_market_id(market_id: &str) -> ClobResult<MarketId> {
+    market_id.parse().map_err(|_| ClobError::StorageError(format!("Invalid market ID {}", market_id)))
+}
+
+/// Notionals are stored as decimal text, since they overflow BIGINT
+#[cfg(feature = "postgres")]
+fn parse_notional(notional: &str) -> ClobResult<Notional> {
+    notional
+        .parse()
+        .map(Notional)
+        .map_err(|_| ClobError::StorageError(format!("Invalid notional {}", notional)))
+}
+
+/// `subject_kind` and `subject` columns of a suspension
+#[cfg(feature = "postgres")]
+fn suspension_subject_columns(subject: &SuspensionSubject) -> (i16, String) {
+    match subject {
+        SuspensionSubject::Owner(owner) => (0, owner.to_string()),
+        SuspensionSubject::ApiKey(key_id) => (1, key_id.clone()),
+    }
+}
+
+#[cfg(feature = "postgres")]
+fn alert_kind(kind: i16) -> ClobResult<AlertKind> {
+    AlertKind::try_from(kind as u8).map_err(|_| ClobError::StorageError("Invalid alert kind".to_string()))
+}
+
+#[cfg(feature = "postgres")]
+fn insurance_fund_event_kind(kind: i16) -> ClobResult<InsuranceFundEventKind> {
+    match kind {
+        0 => Ok(InsuranceFundEventKind::Funded),
+        1 => Ok(InsuranceFundEventKind::Drawn),
+        _ => Err(ClobError::StorageError("Invalid insurance fund event kind".to_string())),
+    }
+}
+
+#[cfg(feature = "postgres")]
+fn dead_letter_status(status: i16) -> ClobResult<DeadLetterStatus> {
+    match status {
+        0 => Ok(DeadLetterStatus::Open),
+        1 => Ok(DeadLetterStatus::Resubmitting),
+        2 => Ok(DeadLetterStatus::Settled),
+        3 => Ok(DeadLetterStatus::Discarded),
+        _ => Err(ClobError::StorageError("Invalid dead letter status".to_string())),
+    }
+}
+
+#[cfg(feature = "postgres")]
+fn chain_status(status: i16) -> ClobResult<ChainStatus> {
+    match status {
+        0 => Ok(ChainStatus::Pending),
+        1 => Ok(ChainStatus::Confirmed),
+        2 => Ok(ChainStatus::Finalized),
+        3 => Ok(ChainStatus::Failed),
+        4 => Ok(ChainStatus::Dropped),
+        _ => Err(ClobError::StorageError("Invalid chain status".to_string())),
     }
 }
 
-impl TryFrom<u8> for TimeInForce {
-    type Error = ();
+#[cfg(feature = "postgres")]
+fn vault_transfer_status(status: i16) -> ClobResult<VaultTransferStatus> {
+    VaultTransferStatus::try_from(status as u8)
+        .map_err(|_| ClobError::StorageError("Invalid vault transfer status".to_string()))
+}
+
+#[cfg(feature = "postgres")]
+fn trade_status(status: i16) -> ClobResult<TradeStatus> {
+    TradeStatus::try_from(status as u8).map_err(|_| ClobError::StorageError("Invalid trade status".to_string()))
+}
+
+#[cfg(feature = "postgres")]
+fn feed_entitlement(entitlement: i16) -> ClobResult<FeedEntitlement> {
+    FeedEntitlement::try_from(entitlement as u8)
+        .map_err(|_| ClobError::StorageError("Invalid feed entitlement".to_string()))
+}
+
+#[cfg(feature = "postgres")]
+fn reversal_status(status: i16) -> ClobResult<ReversalStatus> {
+    ReversalStatus::try_from(status as u8).map_err(|_| ClobError::StorageError("Invalid reversal status".to_string()))
+}
+
+#[cfg(feature = "postgres")]
+fn parse_pubkey(account: &str) -> ClobResult<Pubkey> {
+    account.parse().map_err(|_| ClobError::StorageError(format!("Invalid pubkey {}", account)))
+}
+
+#[cfg(feature = "postgres")]
+fn parse_pubkeys(accounts: &[String]) -> ClobResult<Vec<Pubkey>> {
+    accounts.iter().map(|account| parse_pubkey(account)).collect()
+}
+
+/// Redis storage for fast caching and real-time data
+#[cfg(feature = "redis")]
+pub struct RedisStorage {
+    client: redis::Client,
+}
+
+#[cfg(feature = "redis")]
+impl RedisStorage {
+    /// Create new Redis storage
+    pub fn new(redis_url: &str) -> ClobResult<Self> {
+        let client = redis::Client::open(redis_url)
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        
+        Ok(Self { client })
+    }
+    
+    /// Cache order book snapshot in Redis
+    pub async fn cache_orderbook_snapshot(&self, snapshot: &OrderBookSnapshot) -> ClobResult<()> {
+        let mut conn = self.client.get_async_connection()
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        
+        let snapshot_json = serde_json::to_string(snapshot)
+            .map_err(|e| ClobError::SerializationError(e.to_string()))?;
+        
+        conn.set("orderbook:latest", &snapshot_json)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        
+        // Set expiry for cache
+        conn.expire("orderbook:latest", 300)
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        
+        Ok(())
+    }
     
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
-        match value {
-            0 => Ok(TimeInForce::GoodTillCancelled),
-            1 => Ok(TimeInForce::ImmediateOrCancel),
-            2 => Ok(TimeInForce::FillOrKill),
-            3 => Ok(TimeInForce::GoodTillTime),
-            _ => Err(()),
+    /// Get cached order book snapshot
+    pub async fn get_cached_orderbook_snapshot(&self) -> ClobResult<Option<OrderBookSnapshot>> {
+        let mut conn = self.client.get_async_connection()
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        
+        let snapshot_json: Option<String> = conn.get("orderbook:latest")
+            .await
+            .map_err(|e| ClobError::StorageError(e.to_string()))?;
+        
+        if let Some(json) = snapshot_json {
+            let snapshot = serde_json::from_str(&json)
+                .map_err(|e| ClobError::SerializationError(e.to_string()))?;
+            Ok(Some(snapshot))
+        } else {
+            Ok(None)
         }
     }
-}
\ No newline at end of file
+}
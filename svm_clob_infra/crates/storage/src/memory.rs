@@ -0,0 +1,1529 @@
+/// In-memory storage
+///
+/// `Storage` implementation backed by process memory, for simulations and tests
+/// that drive the real engine without PostgreSQL. Nothing survives a restart.
+
+use crate::Storage;
+use svm_clob_types::*;
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+struct Lease {
+    holder: String,
+    epoch: u64,
+    expires_at: Instant,
+}
+
+#[derive(Default)]
+struct State {
+    orders: BTreeMap<u64, Order>,
+    /// Order IDs in the order they were first stored
+    arrivals: Vec<u64>,
+    trades: Vec<TradeExecution>,
+    snapshots: Vec<OrderBookSnapshot>,
+    book_checkpoints: BTreeMap<u64, BookCheckpoint>,
+    deposits: BTreeMap<(String, u32), DepositRecord>,
+    withdrawals: BTreeMap<(String, u32), WithdrawalRecord>,
+    settled_trades: BTreeMap<(String, u32), SettledTradeRecord>,
+    insurance_fund_events: BTreeMap<(String, u32), InsuranceFundEvent>,
+    sub_accounts: HashMap<Pubkey, SubAccount>,
+    sub_account_transfers: BTreeMap<(String, u32), SubAccountTransferRecord>,
+    prevention_groups: BTreeMap<(String, u32), PreventionGroupRecord>,
+    chain_transactions: HashMap<String, ChainTransaction>,
+    checkpoints: HashMap<String, Checkpoint>,
+    journal: Vec<JournalEntry>,
+    settlements: BTreeMap<u64, TradeSettlement>,
+    dead_letters: BTreeMap<u64, DeadLetter>,
+    leases: HashMap<String, Lease>,
+    alerts: BTreeMap<u64, SurveillanceAlert>,
+    fee_accruals: BTreeMap<(u64, u8), FeeAccrual>,
+    fee_tiers: HashMap<Pubkey, UserFeeTier>,
+    admin_audit: Vec<AdminAuditEntry>,
+    api_audit: Vec<ApiAuditEntry>,
+    tenants: BTreeMap<String, Tenant>,
+    /// API keys by hash
+    tenant_api_keys: HashMap<String, TenantApiKey>,
+    tenant_usage: BTreeMap<(String, i64), TenantUsage>,
+    incentive_epochs: BTreeMap<i64, IncentiveEpoch>,
+    /// Reports by epoch start and maker
+    incentive_reports: BTreeMap<(i64, String), IncentiveReport>,
+    /// Designated market makers by key
+    market_makers: BTreeMap<String, MarketMakerObligation>,
+    obligation_periods: BTreeMap<i64, ObligationPeriod>,
+    /// Scorecards by period start and maker
+    scorecards: BTreeMap<(i64, String), MarketMakerScorecard>,
+    statement_days: BTreeMap<i64, StatementDay>,
+    /// Statements by owner and day
+    statements: BTreeMap<(Pubkey, i64), StatementArtifacts>,
+    sequence_map: BTreeMap<(u64, u64), SequenceMapping>,
+    market_status: HashMap<MarketId, MarketStatus>,
+    suspensions: HashMap<SuspensionSubject, Suspension>,
+    /// Expiry of each claimed owner and signed order nonce
+    nonce_claims: HashMap<(Pubkey, u64), i64>,
+    vault_transfers: BTreeMap<(String, u8), VaultTransfer>,
+    trade_adjustments: BTreeMap<u64, TradeAdjustment>,
+    settlement_adjustments: BTreeMap<(String, u32), SettlementAdjustmentRecord>,
+    receipt_trees: HashMap<Pubkey, ReceiptTreeRecord>,
+    compressed_receipts: BTreeMap<(Pubkey, u64), CompressedReceipt>,
+}
+
+/// Storage kept entirely in memory
+pub struct InMemoryStorage {
+    state: Mutex<State>,
+    /// Time source for journal timestamps
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for InMemoryStorage {
+    fn default() -> Self {
+        Self::with_clock(system_clock())
+    }
+}
+
+impl InMemoryStorage {
+    /// Create empty storage
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create empty storage stamping journal entries from `clock`
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            state: Mutex::new(State::default()),
+            clock,
+        }
+    }
+
+    /// Every stored trade, oldest first
+    pub fn trades(&self) -> Vec<TradeExecution> {
+        self.state().trades.clone()
+    }
+
+    /// Every journal entry, oldest first
+    pub fn journal(&self) -> Vec<JournalEntry> {
+        self.state().journal.clone()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("in-memory storage lock poisoned")
+    }
+}
+
+#[async_trait]
+impl Storage for InMemoryStorage {
+    async fn store_order(&self, order: &Order) -> ClobResult<()> {
+        let mut state = self.state();
+        let is_open = |order: &Order| matches!(order.status, OrderStatus::Open | OrderStatus::PartiallyFilled);
+        if order.client_order_id != 0 && is_open(order) {
+            let duplicate = state.orders.values().any(|other| {
+                other.order_id != order.order_id
+                    && is_open(other)
+                    && other.market_id == order.market_id
+                    && other.owner == order.owner
+                    && other.client_order_id == order.client_order_id
+            });
+            if duplicate {
+                return Err(ClobError::DuplicateClientOrderId(format!(
+                    "{} already has an open order with client order ID {}",
+                    order.owner, order.client_order_id
+                )));
+            }
+        }
+        if state.orders.insert(order.order_id, order.clone()).is_none() {
+            state.arrivals.push(order.order_id);
+        }
+        Ok(())
+    }
+
+    async fn update_order(&self, order: &Order) -> ClobResult<()> {
+        let mut state = self.state();
+        match state.orders.get_mut(&order.order_id) {
+            Some(existing) => {
+                existing.remaining_quantity = order.remaining_quantity;
+                existing.status = order.status;
+                existing.filled_notional = order.filled_notional;
+                existing.avg_fill_price = order.avg_fill_price;
+                Ok(())
+            }
+            None => Err(ClobError::OrderNotFound),
+        }
+    }
+
+    async fn get_order(&self, order_id: u64) -> ClobResult<Option<Order>> {
+        Ok(self.state().orders.get(&order_id).cloned())
+    }
+
+    async fn get_user_orders(&self, user_id: &str, page: &Paginated) -> ClobResult<Page<Order>> {
+        let mut orders: Vec<Order> = self
+            .state()
+            .orders
+            .values()
+            .filter(|order| order.owner.to_string() == user_id)
+            .cloned()
+            .collect();
+        orders.sort_by(|a, b| (b.timestamp, b.order_id).cmp(&(a.timestamp, a.order_id)));
+        Page::slice(orders, page)
+    }
+
+    async fn store_trade(&self, trade: &TradeExecution) -> ClobResult<()> {
+        let mut state = self.state();
+        if !state.trades.iter().any(|stored| stored.trade_id == trade.trade_id) {
+            state.trades.push(trade.clone());
+        }
+        Ok(())
+    }
+
+    async fn get_recent_trades(&self, page: &Paginated) -> ClobResult<Page<TradeExecution>> {
+        let mut trades = self.state().trades.clone();
+        trades.sort_by(|a, b| b.trade_id.cmp(&a.trade_id));
+        Page::slice(trades, page)
+    }
+
+    async fn get_market_trades(&self, market_id: &MarketId, page: &Paginated) -> ClobResult<Page<TradeExecution>> {
+        let mut trades: Vec<_> = self
+            .state()
+            .trades
+            .iter()
+            .filter(|trade| trade.market_id == *market_id)
+            .cloned()
+            .collect();
+        trades.sort_by(|a, b| b.trade_id.cmp(&a.trade_id));
+        Page::slice(trades, page)
+    }
+
+    async fn get_user_fills(&self, user: &Pubkey) -> ClobResult<Vec<UserFill>> {
+        let state = self.state();
+        let owns = |order_id: u64| state.orders.get(&order_id).is_some_and(|order| order.owner == *user);
+        let mut fills = Vec::new();
+        for trade in &state.trades {
+            for (order_id, role) in [(trade.maker_order_id, LiquidityRole::Maker), (trade.taker_order_id, LiquidityRole::Taker)] {
+                if owns(order_id) {
+                    fills.push(UserFill { trade: trade.clone(), role });
+                }
+            }
+        }
+        fills.sort_by_key(|fill| (fill.trade.timestamp, fill.trade.trade_id));
+        Ok(fills)
+    }
+
+    async fn store_orderbook_snapshot(&self, snapshot: &OrderBookSnapshot) -> ClobResult<()> {
+        self.state().snapshots.push(snapshot.clone());
+        Ok(())
+    }
+
+    async fn get_latest_orderbook_snapshot(&self) -> ClobResult<Option<OrderBookSnapshot>> {
+        Ok(self
+            .state()
+            .snapshots
+            .iter()
+            .max_by_key(|snapshot| snapshot.sequence_number)
+            .cloned())
+    }
+
+    async fn store_book_checkpoint(&self, checkpoint: &BookCheckpoint) -> ClobResult<()> {
+        self.state()
+            .book_checkpoints
+            .entry(checkpoint.journal_sequence)
+            .or_insert_with(|| checkpoint.clone());
+        Ok(())
+    }
+
+    async fn get_book_checkpoint_before(&self, sequence: u64) -> ClobResult<Option<BookCheckpoint>> {
+        Ok(self
+            .state()
+            .book_checkpoints
+            .range(..=sequence)
+            .next_back()
+            .map(|(_, checkpoint)| checkpoint.clone()))
+    }
+
+    async fn get_book_checkpoint_at_time(&self, timestamp: i64) -> ClobResult<Option<BookCheckpoint>> {
+        Ok(self
+            .state()
+            .book_checkpoints
+            .values()
+            .rev()
+            .find(|checkpoint| checkpoint.timestamp <= timestamp)
+            .cloned())
+    }
+
+    async fn store_deposit(&self, deposit: &DepositRecord) -> ClobResult<()> {
+        self.state()
+            .deposits
+            .entry((deposit.signature.clone(), deposit.event_index))
+            .or_insert_with(|| deposit.clone());
+        Ok(())
+    }
+
+    async fn store_withdrawal(&self, withdrawal: &WithdrawalRecord) -> ClobResult<()> {
+        self.state()
+            .withdrawals
+            .entry((withdrawal.signature.clone(), withdrawal.event_index))
+            .or_insert_with(|| withdrawal.clone());
+        Ok(())
+    }
+
+    async fn store_settled_trade(&self, trade: &SettledTradeRecord) -> ClobResult<()> {
+        self.state()
+            .settled_trades
+            .entry((trade.signature.clone(), trade.event_index))
+            .or_insert_with(|| trade.clone());
+        Ok(())
+    }
+
+    async fn store_insurance_fund_event(&self, event: &InsuranceFundEvent) -> ClobResult<()> {
+        self.state()
+            .insurance_fund_events
+            .entry((event.signature.clone(), event.event_index))
+            .or_insert_with(|| event.clone());
+        Ok(())
+    }
+
+    async fn store_sub_account(&self, sub_account: &SubAccount) -> ClobResult<()> {
+        self.state()
+            .sub_accounts
+            .entry(sub_account.owner)
+            .or_insert_with(|| sub_account.clone());
+        Ok(())
+    }
+
+    async fn get_sub_account(&self, owner: &Pubkey) -> ClobResult<Option<SubAccount>> {
+        Ok(self.state().sub_accounts.get(owner).cloned())
+    }
+
+    async fn get_sub_accounts(&self, wallet: &Pubkey) -> ClobResult<Vec<SubAccount>> {
+        let mut sub_accounts: Vec<SubAccount> = self
+            .state()
+            .sub_accounts
+            .values()
+            .filter(|sub_account| sub_account.wallet == *wallet)
+            .cloned()
+            .collect();
+        sub_accounts.sort_by_key(|sub_account| sub_account.index);
+        Ok(sub_accounts)
+    }
+
+    async fn store_sub_account_transfer(&self, transfer: &SubAccountTransferRecord) -> ClobResult<()> {
+        self.state()
+            .sub_account_transfers
+            .entry((transfer.signature.clone(), transfer.event_index))
+            .or_insert_with(|| transfer.clone());
+        Ok(())
+    }
+
+    async fn store_prevention_group(&self, record: &PreventionGroupRecord) -> ClobResult<()> {
+        self.state()
+            .prevention_groups
+            .entry((record.signature.clone(), record.event_index))
+            .or_insert_with(|| record.clone());
+        Ok(())
+    }
+
+    async fn get_prevention_group(&self, market_id: &MarketId, owner: &Pubkey) -> ClobResult<Option<u32>> {
+        Ok(self
+            .state()
+            .prevention_groups
+            .values()
+            .filter(|record| record.market_id == *market_id && record.owner == *owner)
+            .max_by_key(|record| (record.slot, record.event_index))
+            .map(|record| record.group)
+            .filter(|group| *group != 0))
+    }
+
+    async fn store_chain_transaction(&self, transaction: &ChainTransaction) -> ClobResult<()> {
+        let mut state = self.state();
+        let status = match state.chain_transactions.get(&transaction.signature) {
+            Some(stored)
+                if stored.status.is_live()
+                    && transaction.status.is_live()
+                    && stored.status as u8 > transaction.status as u8 =>
+            {
+                stored.status
+            }
+            _ => transaction.status,
+        };
+        state.chain_transactions.insert(
+            transaction.signature.clone(),
+            ChainTransaction {
+                status,
+                ..transaction.clone()
+            },
+        );
+        Ok(())
+    }
+
+    async fn get_chain_transaction(&self, signature: &str) -> ClobResult<Option<ChainTransaction>> {
+        Ok(self.state().chain_transactions.get(signature).cloned())
+    }
+
+    async fn get_unfinalized_chain_transactions(&self, limit: u32) -> ClobResult<Vec<ChainTransaction>> {
+        let mut transactions: Vec<ChainTransaction> = self
+            .state()
+            .chain_transactions
+            .values()
+            .filter(|transaction| matches!(transaction.status, ChainStatus::Pending | ChainStatus::Confirmed))
+            .cloned()
+            .collect();
+        transactions.sort_by(|a, b| a.slot.cmp(&b.slot).then_with(|| a.signature.cmp(&b.signature)));
+        transactions.truncate(limit as usize);
+        Ok(transactions)
+    }
+
+    async fn roll_back_chain_transaction(
+        &self,
+        signature: &str,
+        status: ChainStatus,
+        error: Option<&str>,
+    ) -> ClobResult<()> {
+        let mut state = self.state();
+        state.deposits.retain(|(record, _), _| record != signature);
+        state.withdrawals.retain(|(record, _), _| record != signature);
+        state.settled_trades.retain(|(record, _), _| record != signature);
+        state.insurance_fund_events.retain(|(record, _), _| record != signature);
+        state.sub_accounts.retain(|_, sub_account| sub_account.signature != signature);
+        state.sub_account_transfers.retain(|(record, _), _| record != signature);
+        state.prevention_groups.retain(|(record, _), _| record != signature);
+        state.settlement_adjustments.retain(|(record, _), _| record != signature);
+        state.receipt_trees.retain(|_, tree| tree.signature != signature);
+        state.compressed_receipts.retain(|_, receipt| receipt.signature != signature);
+        for adjustment in state.trade_adjustments.values_mut() {
+            if adjustment.reversal_signature.as_deref() == Some(signature) {
+                adjustment.reversal = ReversalStatus::Pending;
+                adjustment.reversal_signature = None;
+            }
+        }
+        for transfer in state.vault_transfers.values_mut() {
+            if transfer.credit_signature.as_deref() == Some(signature) {
+                transfer.status = VaultTransferStatus::Ready;
+                transfer.credit_signature = None;
+            }
+        }
+        if let Some(transaction) = state.chain_transactions.get_mut(signature) {
+            transaction.status = status;
+            transaction.error = error.map(str::to_string);
+        }
+        Ok(())
+    }
+
+    async fn get_checkpoint(&self, service: &str) -> ClobResult<Option<Checkpoint>> {
+        Ok(self.state().checkpoints.get(service).cloned())
+    }
+
+    async fn save_checkpoint(&self, checkpoint: &Checkpoint) -> ClobResult<()> {
+        self.state()
+            .checkpoints
+            .insert(checkpoint.service.clone(), checkpoint.clone());
+        Ok(())
+    }
+
+    async fn append_journal_entry(&self, event: &JournalEvent) -> ClobResult<u64> {
+        let mut state = self.state();
+        let sequence = state.journal.len() as u64 + 1;
+        state.journal.push(JournalEntry {
+            sequence,
+            timestamp: self.clock.now(),
+            event: event.clone(),
+            trace_context: None,
+            #[cfg(feature = "telemetry")]
+            request_id: svm_clob_telemetry::current_request_id(),
+            #[cfg(not(feature = "telemetry"))]
+            request_id: None,
+        });
+        Ok(sequence)
+    }
+
+    async fn get_journal_entries(&self, after: u64, limit: u32) -> ClobResult<Vec<JournalEntry>> {
+        Ok(self
+            .state()
+            .journal
+            .iter()
+            .filter(|entry| entry.sequence > after)
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn store_trade_settlement(&self, settlement: &TradeSettlement) -> ClobResult<()> {
+        self.state()
+            .settlements
+            .insert(settlement.journal_sequence, settlement.clone());
+        Ok(())
+    }
+
+    async fn get_trade_settlement(&self, journal_sequence: u64) -> ClobResult<Option<TradeSettlement>> {
+        Ok(self.state().settlements.get(&journal_sequence).cloned())
+    }
+
+    async fn get_open_orders(&self) -> ClobResult<Vec<Order>> {
+        let state = self.state();
+        Ok(state
+            .arrivals
+            .iter()
+            .filter_map(|order_id| state.orders.get(order_id))
+            .filter(|order| matches!(order.status, OrderStatus::Open | OrderStatus::PartiallyFilled))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_ledger_balances(
+        &self,
+        base_mint: &Pubkey,
+        quote_mint: &Pubkey,
+        owner: Option<&Pubkey>,
+        until: Option<i64>,
+    ) -> ClobResult<Vec<LedgerBalance>> {
+        let state = self.state();
+        let mut balances: BTreeMap<String, LedgerBalance> = BTreeMap::new();
+        let before = |timestamp: i64| until.map_or(true, |until| timestamp < until);
+
+        for deposit in state.deposits.values().filter(|deposit| before(deposit.timestamp)) {
+            let balance = ledger_entry(&mut balances, deposit.user);
+            if deposit.mint == *base_mint {
+                balance.base_balance += deposit.amount as i64;
+            } else if deposit.mint == *quote_mint {
+                balance.quote_balance += deposit.amount as i64;
+            }
+        }
+        for withdrawal in state.withdrawals.values().filter(|withdrawal| before(withdrawal.timestamp)) {
+            let balance = ledger_entry(&mut balances, withdrawal.user);
+            if withdrawal.mint == *base_mint {
+                balance.base_balance -= withdrawal.amount as i64;
+            } else if withdrawal.mint == *quote_mint {
+                balance.quote_balance -= withdrawal.amount as i64;
+            }
+        }
+        for transfer in state.sub_account_transfers.values().filter(|transfer| before(transfer.timestamp)) {
+            let from = ledger_entry(&mut balances, transfer.from);
+            from.base_balance -= transfer.base_amount as i64;
+            from.quote_balance -= transfer.quote_amount as i64;
+            let to = ledger_entry(&mut balances, transfer.to);
+            to.base_balance += transfer.base_amount as i64;
+            to.quote_balance += transfer.quote_amount as i64;
+        }
+
+        // Settled trades carry no side, so it is recovered from the engine's trade record
+        for settled in state.settled_trades.values().filter(|settled| before(settled.timestamp)) {
+            let Some(trade) = state.trades.iter().find(|trade| {
+                trade.maker_order_id == settled.maker_order_id && trade.taker_order_id == settled.taker_order_id
+            }) else {
+                continue;
+            };
+            let base = settled.quantity as i64;
+            let quote = Price(settled.price).notional(Quantity(settled.quantity)).0 as i64;
+            // The maker buys base when it rested on the bid side
+            let maker_sign = match trade.maker_side {
+                OrderSide::Bid => 1,
+                OrderSide::Ask => -1,
+            };
+            // Transactions indexed before their status was tracked count as finalized
+            let unfinalized = state
+                .chain_transactions
+                .get(&settled.signature)
+                .is_some_and(|transaction| matches!(transaction.status, ChainStatus::Pending | ChainStatus::Confirmed));
+
+            for (user, sign) in [(settled.maker, maker_sign), (settled.taker, -maker_sign)] {
+                let balance = ledger_entry(&mut balances, user);
+                balance.base_balance += sign * base;
+                balance.quote_balance -= sign * quote;
+                balance.total_volume_traded += settled.quantity;
+                if unfinalized {
+                    // Credits wait for finality; debits apply at once
+                    let (base_credit, quote_credit) = if sign > 0 { (base, 0) } else { (0, quote) };
+                    balance.base_held += base_credit as u64;
+                    balance.quote_held += quote_credit as u64;
+                }
+            }
+        }
+
+        // Adjustments of settled trades move balances without adding volume
+        for adjusted in state.settlement_adjustments.values().filter(|adjusted| before(adjusted.timestamp)) {
+            let unfinalized = state
+                .chain_transactions
+                .get(&adjusted.signature)
+                .is_some_and(|transaction| matches!(transaction.status, ChainStatus::Pending | ChainStatus::Confirmed));
+            for (user, sign) in [(adjusted.maker, 1), (adjusted.taker, -1)] {
+                let (base, quote) = (sign * adjusted.base_to_maker, sign * adjusted.quote_to_maker);
+                let balance = ledger_entry(&mut balances, user);
+                balance.base_balance += base;
+                balance.quote_balance += quote;
+                if unfinalized {
+                    balance.base_held += base.max(0) as u64;
+                    balance.quote_held += quote.max(0) as u64;
+                }
+            }
+        }
+
+        for accrual in state.fee_accruals.values().filter(|accrual| before(accrual.timestamp)) {
+            ledger_entry(&mut balances, accrual.user).fees += accrual.fee;
+        }
+
+        Ok(balances
+            .into_values()
+            .filter(|balance| owner.map_or(true, |owner| balance.user == *owner))
+            .collect())
+    }
+
+    async fn get_user_deposits(&self, owner: &Pubkey, from: i64, until: i64) -> ClobResult<Vec<DepositRecord>> {
+        let mut deposits: Vec<DepositRecord> = self
+            .state()
+            .deposits
+            .values()
+            .filter(|deposit| deposit.user == *owner && (from..until).contains(&deposit.timestamp))
+            .cloned()
+            .collect();
+        deposits.sort_by_key(|deposit| (deposit.slot, deposit.event_index));
+        Ok(deposits)
+    }
+
+    async fn get_user_withdrawals(&self, owner: &Pubkey, from: i64, until: i64) -> ClobResult<Vec<WithdrawalRecord>> {
+        let mut withdrawals: Vec<WithdrawalRecord> = self
+            .state()
+            .withdrawals
+            .values()
+            .filter(|withdrawal| withdrawal.user == *owner && (from..until).contains(&withdrawal.timestamp))
+            .cloned()
+            .collect();
+        withdrawals.sort_by_key(|withdrawal| (withdrawal.slot, withdrawal.event_index));
+        Ok(withdrawals)
+    }
+
+    async fn get_user_sub_account_transfers(
+        &self,
+        owner: &Pubkey,
+        from: i64,
+        until: i64,
+    ) -> ClobResult<Vec<SubAccountTransferRecord>> {
+        let mut transfers: Vec<SubAccountTransferRecord> = self
+            .state()
+            .sub_account_transfers
+            .values()
+            .filter(|transfer| {
+                (transfer.from == *owner || transfer.to == *owner) && (from..until).contains(&transfer.timestamp)
+            })
+            .cloned()
+            .collect();
+        transfers.sort_by_key(|transfer| (transfer.slot, transfer.event_index));
+        Ok(transfers)
+    }
+
+    async fn get_settled_volume(&self) -> ClobResult<u64> {
+        Ok(self.state().settled_trades.values().map(|trade| trade.quantity).sum())
+    }
+
+    async fn get_last_trade_id(&self) -> ClobResult<u64> {
+        Ok(self.state().trades.iter().map(|trade| trade.trade_id).max().unwrap_or(0))
+    }
+
+    async fn get_reserved_order_ids(&self) -> ClobResult<u64> {
+        Ok(self
+            .state()
+            .journal
+            .iter()
+            .filter_map(|entry| match entry.event {
+                JournalEvent::OrderIdsReserved { through } => Some(through),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0))
+    }
+
+    async fn get_journal_head(&self) -> ClobResult<u64> {
+        Ok(self.state().journal.len() as u64)
+    }
+
+    async fn get_unindexed_settlements(&self, limit: u32) -> ClobResult<Vec<TradeSettlement>> {
+        let state = self.state();
+        let indexed: HashSet<&str> = state
+            .settled_trades
+            .keys()
+            .map(|(signature, _)| signature.as_str())
+            .collect();
+        Ok(state
+            .settlements
+            .values()
+            .filter(|settlement| settlement.status == SettlementStatus::Confirmed)
+            .filter(|settlement| {
+                settlement
+                    .signature
+                    .as_deref()
+                    .map_or(true, |signature| !indexed.contains(signature))
+            })
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn store_dead_letter(&self, letter: &DeadLetter) -> ClobResult<u64> {
+        let mut state = self.state();
+        let existing = state
+            .dead_letters
+            .values()
+            .find(|stored| stored.first_sequence == letter.first_sequence);
+        if let Some(existing) = existing {
+            return Ok(existing.id);
+        }
+
+        let id = state.dead_letters.len() as u64 + 1;
+        state.dead_letters.insert(id, DeadLetter { id, ..letter.clone() });
+        Ok(id)
+    }
+
+    async fn get_dead_letter(&self, id: u64) -> ClobResult<Option<DeadLetter>> {
+        Ok(self.state().dead_letters.get(&id).cloned())
+    }
+
+    async fn get_dead_letters(&self, status: Option<DeadLetterStatus>, page: &Paginated) -> ClobResult<Page<DeadLetter>> {
+        let state = self.state();
+        let letters = state
+            .dead_letters
+            .values()
+            .filter(|letter| status.map_or(true, |status| letter.status == status))
+            .cloned();
+        Page::slice(letters, page)
+    }
+
+    async fn update_dead_letter(&self, letter: &DeadLetter, expected: DeadLetterStatus) -> ClobResult<bool> {
+        let mut state = self.state();
+        match state.dead_letters.get_mut(&letter.id) {
+            Some(stored) if stored.status == expected => {
+                *stored = DeadLetter {
+                    first_sequence: stored.first_sequence,
+                    last_sequence: stored.last_sequence,
+                    created_at: stored.created_at,
+                    ..letter.clone()
+                };
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn try_acquire_lease(&self, name: &str, holder: &str, ttl: Duration) -> ClobResult<Option<u64>> {
+        let now = Instant::now();
+        let mut state = self.state();
+        let lease = state.leases.entry(name.to_string()).or_insert(Lease {
+            holder: holder.to_string(),
+            epoch: 0,
+            expires_at: now,
+        });
+
+        let held = lease.holder == holder && lease.expires_at > now;
+        if !held && lease.expires_at > now {
+            return Ok(None);
+        }
+        if !held {
+            lease.holder = holder.to_string();
+            lease.epoch += 1;
+        }
+        lease.expires_at = now + ttl;
+        Ok(Some(lease.epoch))
+    }
+
+    async fn release_lease(&self, name: &str, holder: &str) -> ClobResult<()> {
+        if let Some(lease) = self.state().leases.get_mut(name) {
+            if lease.holder == holder {
+                lease.expires_at = Instant::now();
+            }
+        }
+        Ok(())
+    }
+
+    async fn store_surveillance_alert(&self, alert: &SurveillanceAlert) -> ClobResult<u64> {
+        let mut state = self.state();
+        let existing = state.alerts.values().find(|stored| {
+            stored.kind == alert.kind
+                && stored.window_start == alert.window_start
+                && stored.accounts == alert.accounts
+        });
+        if let Some(existing) = existing {
+            return Ok(existing.id);
+        }
+
+        let id = state.alerts.len() as u64 + 1;
+        state.alerts.insert(id, SurveillanceAlert { id, ..alert.clone() });
+        Ok(id)
+    }
+
+    async fn get_surveillance_alerts(
+        &self,
+        kind: Option<AlertKind>,
+        min_score: f64,
+        page: &Paginated,
+    ) -> ClobResult<Page<SurveillanceAlert>> {
+        let state = self.state();
+        let alerts = state
+            .alerts
+            .values()
+            .rev()
+            .filter(|alert| kind.map_or(true, |kind| alert.kind == kind) && alert.score >= min_score)
+            .cloned();
+        Page::slice(alerts, page)
+    }
+
+    async fn get_surveillance_alert(&self, id: u64) -> ClobResult<Option<SurveillanceAlert>> {
+        Ok(self.state().alerts.get(&id).cloned())
+    }
+
+    async fn store_fee_accruals(&self, accruals: &[FeeAccrual]) -> ClobResult<()> {
+        let mut state = self.state();
+        for accrual in accruals {
+            state
+                .fee_accruals
+                .entry((accrual.journal_sequence, accrual.role as u8))
+                .or_insert_with(|| accrual.clone());
+        }
+        Ok(())
+    }
+
+    async fn get_fee_reports(&self, from: i64, until: i64, user: Option<&Pubkey>) -> ClobResult<Vec<FeeReport>> {
+        let state = self.state();
+        let mut reports: BTreeMap<String, FeeReport> = BTreeMap::new();
+
+        for accrual in state.fee_accruals.values() {
+            if accrual.timestamp < from || accrual.timestamp >= until || user.is_some_and(|user| accrual.user != *user) {
+                continue;
+            }
+            let report = reports.entry(accrual.user.to_string()).or_insert(FeeReport {
+                user: accrual.user,
+                period_start: from,
+                period_end: until,
+                fills: 0,
+                maker_volume: 0,
+                taker_volume: 0,
+                maker_fees: 0,
+                taker_fees: 0,
+                total_fees: 0,
+            });
+            // Accruals compensating a bust take their fill and its volume back out
+            let volume = match accrual.adjusted_sequence {
+                None => {
+                    report.fills += 1;
+                    accrual.quantity as i64
+                }
+                Some(_) => {
+                    if accrual.quantity > 0 {
+                        report.fills = report.fills.saturating_sub(1);
+                    }
+                    -(accrual.quantity as i64)
+                }
+            };
+            match accrual.role {
+                LiquidityRole::Maker => {
+                    report.maker_volume = report.maker_volume.saturating_add_signed(volume);
+                    report.maker_fees += accrual.fee;
+                }
+                LiquidityRole::Taker => {
+                    report.taker_volume = report.taker_volume.saturating_add_signed(volume);
+                    report.taker_fees += accrual.fee;
+                }
+            }
+            report.total_fees += accrual.fee;
+        }
+
+        Ok(reports.into_values().collect())
+    }
+
+    async fn get_traded_volumes(&self, from: i64, until: i64) -> ClobResult<Vec<(Pubkey, u64)>> {
+        let state = self.state();
+        let mut volumes: BTreeMap<String, (Pubkey, u64)> = BTreeMap::new();
+        let counted = |trade: &&TradeExecution| {
+            trade.timestamp >= from && trade.timestamp < until && trade.status != TradeStatus::Busted
+        };
+        for trade in state.trades.iter().filter(counted) {
+            for order_id in [trade.maker_order_id, trade.taker_order_id] {
+                if let Some(order) = state.orders.get(&order_id) {
+                    volumes.entry(order.owner.to_string()).or_insert((order.owner, 0)).1 += trade.quantity;
+                }
+            }
+        }
+        Ok(volumes.into_values().collect())
+    }
+
+    async fn replace_fee_tiers(&self, tiers: &[UserFeeTier]) -> ClobResult<()> {
+        self.state().fee_tiers = tiers.iter().map(|tier| (tier.user, tier.clone())).collect();
+        Ok(())
+    }
+
+    async fn get_fee_tier(&self, user: &Pubkey) -> ClobResult<Option<UserFeeTier>> {
+        Ok(self.state().fee_tiers.get(user).cloned())
+    }
+
+    async fn get_insurance_fund(&self, orderbook: &Pubkey) -> ClobResult<InsuranceFund> {
+        let state = self.state();
+        let accrued = state.fee_accruals.values().map(|accrual| accrual.insurance_contribution).sum();
+        let (mut funded, mut drawn) = (0, 0);
+        for event in state.insurance_fund_events.values().filter(|event| event.orderbook == *orderbook) {
+            match event.kind {
+                InsuranceFundEventKind::Funded => funded += event.amount,
+                InsuranceFundEventKind::Drawn => drawn += event.amount,
+            }
+        }
+        Ok(InsuranceFund::new(*orderbook, accrued, funded, drawn))
+    }
+
+    async fn get_insurance_fund_events(
+        &self,
+        orderbook: &Pubkey,
+        kind: Option<InsuranceFundEventKind>,
+        page: &Paginated,
+    ) -> ClobResult<Page<InsuranceFundEvent>> {
+        let state = self.state();
+        let mut events: Vec<InsuranceFundEvent> = state
+            .insurance_fund_events
+            .values()
+            .filter(|event| event.orderbook == *orderbook && kind.map_or(true, |kind| event.kind == kind))
+            .cloned()
+            .collect();
+        events.sort_by(|a, b| {
+            b.slot
+                .cmp(&a.slot)
+                .then_with(|| b.signature.cmp(&a.signature))
+                .then(b.event_index.cmp(&a.event_index))
+        });
+        Page::slice(events, page)
+    }
+
+    async fn store_admin_audit_entry(&self, entry: &AdminAuditEntry) -> ClobResult<u64> {
+        let mut state = self.state();
+        let id = state.admin_audit.len() as u64 + 1;
+        state.admin_audit.push(AdminAuditEntry { id, ..entry.clone() });
+        Ok(id)
+    }
+
+    async fn get_admin_audit_entries(&self, page: &Paginated) -> ClobResult<Page<AdminAuditEntry>> {
+        Page::slice(self.state().admin_audit.iter().rev().cloned(), page)
+    }
+
+    async fn store_api_audit_entry(&self, entry: &ApiAuditEntry) -> ClobResult<u64> {
+        let mut state = self.state();
+        let id = state.api_audit.len() as u64 + 1;
+        state.api_audit.push(ApiAuditEntry { id, ..entry.clone() });
+        Ok(id)
+    }
+
+    async fn get_api_audit_entries(&self, query: &ApiAuditQuery, page: &Paginated) -> ClobResult<Page<ApiAuditEntry>> {
+        let state = self.state();
+        let entries = state
+            .api_audit
+            .iter()
+            .rev()
+            .filter(|entry| {
+                query.actor.as_ref().map_or(true, |actor| entry.actor == *actor)
+                    && query.from.map_or(true, |from| entry.timestamp >= from)
+                    && query.until.map_or(true, |until| entry.timestamp < until)
+                    && query.request_id.as_ref().map_or(true, |id| entry.request_id.as_ref() == Some(id))
+            })
+            .cloned();
+        Page::slice(entries, page)
+    }
+
+    async fn store_tenant(&self, tenant: &Tenant) -> ClobResult<()> {
+        let mut state = self.state();
+        if let Some(code) = &tenant.referral_code {
+            let taken = state
+                .tenants
+                .values()
+                .any(|other| other.id != tenant.id && other.referral_code.as_ref() == Some(code));
+            if taken {
+                return Err(ClobError::StorageError(format!("Referral code {} is taken", code)));
+            }
+        }
+        let created_at = state.tenants.get(&tenant.id).map_or(tenant.created_at, |existing| existing.created_at);
+        state.tenants.insert(tenant.id.clone(), Tenant { created_at, ..tenant.clone() });
+        Ok(())
+    }
+
+    async fn get_tenant(&self, id: &str) -> ClobResult<Option<Tenant>> {
+        Ok(self.state().tenants.get(id).cloned())
+    }
+
+    async fn get_tenant_by_referral_code(&self, code: &str) -> ClobResult<Option<Tenant>> {
+        Ok(self
+            .state()
+            .tenants
+            .values()
+            .find(|tenant| tenant.referral_code.as_deref() == Some(code))
+            .cloned())
+    }
+
+    async fn get_tenants(&self, page: &Paginated) -> ClobResult<Page<Tenant>> {
+        Page::slice(self.state().tenants.values().cloned(), page)
+    }
+
+    async fn store_tenant_api_key(&self, key: &TenantApiKey) -> ClobResult<()> {
+        let mut state = self.state();
+        if !state.tenants.contains_key(&key.tenant_id) {
+            return Err(ClobError::StorageError(format!("Unknown tenant {}", key.tenant_id)));
+        }
+        if state.tenant_api_keys.contains_key(&key.key_hash)
+            || state.tenant_api_keys.values().any(|existing| existing.key_id == key.key_id)
+        {
+            return Err(ClobError::StorageError(format!("API key {} already exists", key.key_id)));
+        }
+        state.tenant_api_keys.insert(key.key_hash.clone(), key.clone());
+        Ok(())
+    }
+
+    async fn get_tenant_api_key(&self, key_hash: &str) -> ClobResult<Option<TenantApiKey>> {
+        Ok(self.state().tenant_api_keys.get(key_hash).cloned())
+    }
+
+    async fn get_tenant_api_keys(&self, tenant_id: &str) -> ClobResult<Vec<TenantApiKey>> {
+        let mut keys: Vec<TenantApiKey> = self
+            .state()
+            .tenant_api_keys
+            .values()
+            .filter(|key| key.tenant_id == tenant_id)
+            .cloned()
+            .collect();
+        keys.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.key_id.cmp(&b.key_id)));
+        Ok(keys)
+    }
+
+    async fn revoke_tenant_api_key(&self, tenant_id: &str, key_id: &str, revoked_at: i64) -> ClobResult<bool> {
+        let mut state = self.state();
+        let key = state
+            .tenant_api_keys
+            .values_mut()
+            .find(|key| key.tenant_id == tenant_id && key.key_id == key_id && key.revoked_at.is_none());
+        match key {
+            Some(key) => {
+                key.revoked_at = Some(revoked_at);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn set_tenant_api_key_entitlement(
+        &self,
+        tenant_id: &str,
+        key_id: &str,
+        entitlement: FeedEntitlement,
+    ) -> ClobResult<bool> {
+        let mut state = self.state();
+        let key = state
+            .tenant_api_keys
+            .values_mut()
+            .find(|key| key.tenant_id == tenant_id && key.key_id == key_id && key.revoked_at.is_none());
+        match key {
+            Some(key) => {
+                key.entitlement = entitlement;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn add_tenant_usage(&self, usage: &[TenantUsage]) -> ClobResult<()> {
+        let mut state = self.state();
+        for counts in usage {
+            state
+                .tenant_usage
+                .entry((counts.tenant_id.clone(), counts.period_start))
+                .or_insert_with(|| TenantUsage::new(&counts.tenant_id, counts.period_start))
+                .merge(counts);
+        }
+        Ok(())
+    }
+
+    async fn get_tenant_usage(&self, tenant_id: &str, from: i64, until: i64) -> ClobResult<Vec<TenantUsage>> {
+        Ok(self
+            .state()
+            .tenant_usage
+            .range((tenant_id.to_string(), from)..(tenant_id.to_string(), until.max(from)))
+            .map(|(_, usage)| usage.clone())
+            .collect())
+    }
+
+    async fn store_incentive_epoch(&self, epoch: &IncentiveEpoch, reports: &[IncentiveReport]) -> ClobResult<()> {
+        let mut state = self.state();
+        if state.incentive_epochs.contains_key(&epoch.epoch_start) {
+            return Ok(());
+        }
+        state.incentive_epochs.insert(epoch.epoch_start, epoch.clone());
+        for report in reports {
+            state
+                .incentive_reports
+                .insert((report.epoch_start, report.maker.to_string()), report.clone());
+        }
+        Ok(())
+    }
+
+    async fn get_last_incentive_epoch(&self) -> ClobResult<Option<IncentiveEpoch>> {
+        Ok(self.state().incentive_epochs.values().next_back().cloned())
+    }
+
+    async fn get_incentive_epochs(&self, page: &Paginated) -> ClobResult<Page<IncentiveEpoch>> {
+        Page::slice(self.state().incentive_epochs.values().rev().cloned(), page)
+    }
+
+    async fn store_market_maker(&self, obligation: &MarketMakerObligation) -> ClobResult<()> {
+        let mut state = self.state();
+        let key = obligation.maker.to_string();
+        let registered_at = state
+            .market_makers
+            .get(&key)
+            .map_or(obligation.registered_at, |existing| existing.registered_at);
+        state.market_makers.insert(key, MarketMakerObligation { registered_at, ..obligation.clone() });
+        Ok(())
+    }
+
+    async fn get_market_maker(&self, maker: &Pubkey) -> ClobResult<Option<MarketMakerObligation>> {
+        Ok(self.state().market_makers.get(&maker.to_string()).cloned())
+    }
+
+    async fn get_market_makers(&self, page: &Paginated) -> ClobResult<Page<MarketMakerObligation>> {
+        Page::slice(self.state().market_makers.values().cloned(), page)
+    }
+
+    async fn remove_market_maker(&self, maker: &Pubkey) -> ClobResult<bool> {
+        Ok(self.state().market_makers.remove(&maker.to_string()).is_some())
+    }
+
+    async fn store_obligation_period(
+        &self,
+        period: &ObligationPeriod,
+        scorecards: &[MarketMakerScorecard],
+    ) -> ClobResult<()> {
+        let mut state = self.state();
+        if state.obligation_periods.contains_key(&period.period_start) {
+            return Ok(());
+        }
+        state.obligation_periods.insert(period.period_start, period.clone());
+        for scorecard in scorecards {
+            state
+                .scorecards
+                .insert((scorecard.period_start, scorecard.maker.to_string()), scorecard.clone());
+        }
+        Ok(())
+    }
+
+    async fn get_last_obligation_period(&self) -> ClobResult<Option<ObligationPeriod>> {
+        Ok(self.state().obligation_periods.values().next_back().cloned())
+    }
+
+    async fn get_market_maker_scorecards(
+        &self,
+        query: &ScorecardQuery,
+        page: &Paginated,
+    ) -> ClobResult<Page<MarketMakerScorecard>> {
+        let state = self.state();
+        let mut scorecards: Vec<MarketMakerScorecard> = state
+            .scorecards
+            .values()
+            .filter(|scorecard| {
+                query.period_start.map_or(true, |start| scorecard.period_start == start)
+                    && query.maker.map_or(true, |maker| scorecard.maker == maker)
+            })
+            .cloned()
+            .collect();
+        scorecards.sort_by(|a, b| {
+            b.period_start
+                .cmp(&a.period_start)
+                .then_with(|| a.maker.to_string().cmp(&b.maker.to_string()))
+        });
+        Page::slice(scorecards, page)
+    }
+
+    async fn store_statement_day(&self, day: &StatementDay, statements: &[StatementArtifacts]) -> ClobResult<()> {
+        let mut state = self.state();
+        if state.statement_days.contains_key(&day.period_start) {
+            return Ok(());
+        }
+        state.statement_days.insert(day.period_start, day.clone());
+        for statement in statements {
+            state
+                .statements
+                .insert((statement.summary.owner, statement.summary.period_start), statement.clone());
+        }
+        Ok(())
+    }
+
+    async fn get_last_statement_day(&self) -> ClobResult<Option<StatementDay>> {
+        Ok(self.state().statement_days.values().next_back().cloned())
+    }
+
+    async fn get_statements(&self, owner: &Pubkey, page: &Paginated) -> ClobResult<Page<StatementSummary>> {
+        let state = self.state();
+        let statements = state
+            .statements
+            .range((*owner, i64::MIN)..=(*owner, i64::MAX))
+            .rev()
+            .map(|(_, statement)| statement.summary.clone());
+        Page::slice(statements, page)
+    }
+
+    async fn get_statement_artifact(
+        &self,
+        owner: &Pubkey,
+        period_start: i64,
+        format: StatementFormat,
+    ) -> ClobResult<Option<Vec<u8>>> {
+        Ok(self
+            .state()
+            .statements
+            .get(&(*owner, period_start))
+            .map(|statement| statement.get(format).to_vec()))
+    }
+
+    async fn get_incentive_reports(&self, query: &IncentiveQuery, page: &Paginated) -> ClobResult<Page<IncentiveReport>> {
+        let state = self.state();
+        let mut reports: Vec<IncentiveReport> = state
+            .incentive_reports
+            .values()
+            .filter(|report| {
+                query.epoch_start.map_or(true, |start| report.epoch_start == start)
+                    && query.maker.map_or(true, |maker| report.maker == maker)
+            })
+            .cloned()
+            .collect();
+        reports.sort_by(|a, b| {
+            b.epoch_start
+                .cmp(&a.epoch_start)
+                .then(b.reward.cmp(&a.reward))
+                .then_with(|| a.maker.to_string().cmp(&b.maker.to_string()))
+        });
+        Page::slice(reports, page)
+    }
+
+    async fn store_sequence_mappings(&self, mappings: &[SequenceMapping]) -> ClobResult<()> {
+        let mut state = self.state();
+        for mapping in mappings {
+            state
+                .sequence_map
+                .entry((mapping.journal_sequence, mapping.order_id))
+                .or_insert_with(|| mapping.clone());
+        }
+        Ok(())
+    }
+
+    async fn get_sequence_mappings(&self, lookup: &SequenceLookup, page: &Paginated) -> ClobResult<Page<SequenceMapping>> {
+        let state = self.state();
+        let mappings = state.sequence_map.values().map(|mapping| {
+            let mut mapping = mapping.clone();
+            let settlement = state.settlements.get(&mapping.journal_sequence);
+            if let (SequenceEvent::Filled, Some(settlement)) = (mapping.event, settlement) {
+                mapping.signature = settlement.signature.clone();
+                mapping.settlement_status = Some(settlement.status);
+                mapping.chain_status = settlement
+                    .signature
+                    .as_ref()
+                    .and_then(|signature| state.chain_transactions.get(signature))
+                    .map(|transaction| transaction.status);
+            }
+            mapping
+        });
+        let matching = mappings.filter(|mapping| match lookup {
+            SequenceLookup::Order(order_id) => mapping.order_id == *order_id,
+            SequenceLookup::JournalSequence(sequence) => mapping.journal_sequence == *sequence,
+            SequenceLookup::Signature(signature) => mapping.signature.as_ref() == Some(signature),
+            SequenceLookup::UserAccount(account) => mapping.user_account == *account,
+        });
+        Page::slice(matching, page)
+    }
+
+    async fn get_market_status(&self, market_id: &MarketId) -> ClobResult<Option<MarketStatus>> {
+        Ok(self.state().market_status.get(market_id).copied())
+    }
+
+    async fn set_market_status(&self, market_id: &MarketId, status: MarketStatus, _updated_at: i64) -> ClobResult<()> {
+        self.state().market_status.insert(*market_id, status);
+        Ok(())
+    }
+
+    async fn store_suspension(&self, suspension: &Suspension) -> ClobResult<()> {
+        self.state()
+            .suspensions
+            .insert(suspension.subject.clone(), suspension.clone());
+        Ok(())
+    }
+
+    async fn lift_suspension(&self, subject: &SuspensionSubject) -> ClobResult<bool> {
+        Ok(self.state().suspensions.remove(subject).is_some())
+    }
+
+    async fn get_suspensions(&self) -> ClobResult<Vec<Suspension>> {
+        let mut suspensions: Vec<Suspension> = self.state().suspensions.values().cloned().collect();
+        suspensions.sort_by(|a, b| {
+            (a.suspended_at, a.subject.to_string()).cmp(&(b.suspended_at, b.subject.to_string()))
+        });
+        Ok(suspensions)
+    }
+
+    async fn claim_signed_nonce(&self, owner: &Pubkey, nonce: u64, expiry: i64, now: i64) -> ClobResult<bool> {
+        let mut state = self.state();
+        match state.nonce_claims.get(&(*owner, nonce)) {
+            Some(&claimed_until) if claimed_until >= now => Ok(false),
+            _ => {
+                state.nonce_claims.insert((*owner, nonce), expiry);
+                Ok(true)
+            }
+        }
+    }
+
+    async fn signed_nonce_claimed(&self, owner: &Pubkey, nonce: u64, now: i64) -> ClobResult<bool> {
+        Ok(self.state().nonce_claims.get(&(*owner, nonce)).is_some_and(|&expiry| expiry >= now))
+    }
+
+    async fn store_vault_transfer(&self, transfer: &VaultTransfer) -> ClobResult<()> {
+        self.state()
+            .vault_transfers
+            .entry((transfer.signature.clone(), transfer.instruction_index))
+            .or_insert_with(|| transfer.clone());
+        Ok(())
+    }
+
+    async fn update_vault_transfer(&self, transfer: &VaultTransfer) -> ClobResult<()> {
+        let mut state = self.state();
+        if let Some(stored) = state
+            .vault_transfers
+            .get_mut(&(transfer.signature.clone(), transfer.instruction_index))
+        {
+            stored.status = transfer.status;
+            stored.confirmations = transfer.confirmations;
+            stored.reason = transfer.reason.clone();
+            stored.credit_signature = transfer.credit_signature.clone();
+            stored.updated_at = transfer.updated_at;
+        }
+        Ok(())
+    }
+
+    async fn get_vault_transfer(&self, signature: &str, instruction_index: u8) -> ClobResult<Option<VaultTransfer>> {
+        Ok(self
+            .state()
+            .vault_transfers
+            .get(&(signature.to_string(), instruction_index))
+            .cloned())
+    }
+
+    async fn get_vault_transfers_by_status(&self, status: VaultTransferStatus, limit: u32) -> ClobResult<Vec<VaultTransfer>> {
+        let mut transfers: Vec<VaultTransfer> = self
+            .state()
+            .vault_transfers
+            .values()
+            .filter(|transfer| transfer.status == status)
+            .cloned()
+            .collect();
+        transfers.sort_by(|a, b| {
+            a.slot
+                .cmp(&b.slot)
+                .then_with(|| a.signature.cmp(&b.signature))
+                .then(a.instruction_index.cmp(&b.instruction_index))
+        });
+        transfers.truncate(limit as usize);
+        Ok(transfers)
+    }
+
+    async fn get_user_vault_transfers(&self, sender: &Pubkey, page: &Paginated) -> ClobResult<Page<VaultTransfer>> {
+        let mut transfers: Vec<VaultTransfer> = self
+            .state()
+            .vault_transfers
+            .values()
+            .filter(|transfer| transfer.sender == *sender)
+            .cloned()
+            .collect();
+        transfers.sort_by(|a, b| {
+            b.slot
+                .cmp(&a.slot)
+                .then_with(|| b.signature.cmp(&a.signature))
+                .then(b.instruction_index.cmp(&a.instruction_index))
+        });
+        Page::slice(transfers, page)
+    }
+
+    async fn get_trade(&self, trade_id: u64) -> ClobResult<Option<TradeExecution>> {
+        Ok(self.state().trades.iter().find(|trade| trade.trade_id == trade_id).cloned())
+    }
+
+    async fn get_trade_journal_entry(&self, trade_id: u64) -> ClobResult<Option<JournalEntry>> {
+        Ok(self
+            .state()
+            .journal
+            .iter()
+            .find(|entry| matches!(&entry.event, JournalEvent::TradeExecuted { trade, .. } if trade.trade_id == trade_id))
+            .cloned())
+    }
+
+    async fn store_trade_adjustment(&self, adjustment: &TradeAdjustment) -> ClobResult<()> {
+        let mut state = self.state();
+        if state
+            .trade_adjustments
+            .values()
+            .any(|stored| stored.trade_id == adjustment.trade_id && stored.version == adjustment.version)
+        {
+            return Err(ClobError::StorageError(format!(
+                "Trade {} already has a version {}",
+                adjustment.trade_id, adjustment.version
+            )));
+        }
+        state.trade_adjustments.insert(adjustment.adjustment_id, adjustment.clone());
+        if let Some(trade) = state.trades.iter_mut().find(|trade| trade.trade_id == adjustment.trade_id) {
+            trade.price = adjustment.price;
+            trade.version = adjustment.version;
+            trade.status = adjustment.status;
+        }
+        Ok(())
+    }
+
+    async fn update_trade_adjustment(&self, adjustment: &TradeAdjustment) -> ClobResult<()> {
+        if let Some(stored) = self.state().trade_adjustments.get_mut(&adjustment.adjustment_id) {
+            stored.reversal = adjustment.reversal;
+            stored.reversal_signature = adjustment.reversal_signature.clone();
+            stored.reversal_error = adjustment.reversal_error.clone();
+            stored.updated_at = adjustment.updated_at;
+        }
+        Ok(())
+    }
+
+    async fn get_trade_adjustments(&self, trade_id: u64) -> ClobResult<Vec<TradeAdjustment>> {
+        let mut adjustments: Vec<TradeAdjustment> = self
+            .state()
+            .trade_adjustments
+            .values()
+            .filter(|adjustment| adjustment.trade_id == trade_id)
+            .cloned()
+            .collect();
+        adjustments.sort_by_key(|adjustment| adjustment.version);
+        Ok(adjustments)
+    }
+
+    async fn get_trade_adjustments_by_reversal(&self, reversal: ReversalStatus, limit: u32) -> ClobResult<Vec<TradeAdjustment>> {
+        Ok(self
+            .state()
+            .trade_adjustments
+            .values()
+            .filter(|adjustment| adjustment.reversal == reversal)
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_recent_trade_adjustments(&self, reversal: Option<ReversalStatus>, page: &Paginated) -> ClobResult<Page<TradeAdjustment>> {
+        let state = self.state();
+        let adjustments = state
+            .trade_adjustments
+            .values()
+            .rev()
+            .filter(|adjustment| reversal.map_or(true, |reversal| adjustment.reversal == reversal))
+            .cloned();
+        Page::slice(adjustments, page)
+    }
+
+    async fn store_settlement_adjustment(&self, record: &SettlementAdjustmentRecord) -> ClobResult<()> {
+        let mut state = self.state();
+        state
+            .settlement_adjustments
+            .insert((record.signature.clone(), record.event_index), record.clone());
+        if let Some(adjustment) = state.trade_adjustments.get_mut(&record.adjustment_id) {
+            adjustment.reversal = ReversalStatus::Reversed;
+            adjustment.reversal_signature = Some(record.signature.clone());
+            adjustment.reversal_error = None;
+            adjustment.updated_at = record.timestamp;
+        }
+        Ok(())
+    }
+
+    async fn store_receipt_tree(&self, record: &ReceiptTreeRecord) -> ClobResult<()> {
+        self.state()
+            .receipt_trees
+            .entry(record.merkle_tree)
+            .or_insert_with(|| record.clone());
+        Ok(())
+    }
+
+    async fn get_receipt_tree(&self, merkle_tree: &Pubkey) -> ClobResult<Option<ReceiptTreeRecord>> {
+        Ok(self.state().receipt_trees.get(merkle_tree).cloned())
+    }
+
+    async fn store_compressed_receipts(&self, receipts: &[CompressedReceipt]) -> ClobResult<()> {
+        let mut state = self.state();
+        for receipt in receipts {
+            state
+                .compressed_receipts
+                .entry((receipt.merkle_tree, receipt.leaf_index))
+                .or_insert_with(|| receipt.clone());
+        }
+        Ok(())
+    }
+
+    async fn get_compressed_receipts(&self, owner: &Pubkey, page: &Paginated) -> ClobResult<Page<CompressedReceipt>> {
+        let state = self.state();
+        let mut receipts: Vec<CompressedReceipt> = state
+            .compressed_receipts
+            .values()
+            .filter(|receipt| receipt.owner == *owner)
+            .cloned()
+            .collect();
+        receipts.sort_by(|a, b| (b.timestamp, b.leaf_index).cmp(&(a.timestamp, a.leaf_index)));
+        Page::slice(receipts, page)
+    }
+
+    async fn get_compressed_receipt(&self, merkle_tree: &Pubkey, leaf_index: u64) -> ClobResult<Option<CompressedReceipt>> {
+        Ok(self.state().compressed_receipts.get(&(*merkle_tree, leaf_index)).cloned())
+    }
+
+    async fn get_receipt_leaves(&self, merkle_tree: &Pubkey) -> ClobResult<Vec<[u8; 32]>> {
+        let state = self.state();
+        Ok(state
+            .compressed_receipts
+            .range((*merkle_tree, 0)..=(*merkle_tree, u64::MAX))
+            .enumerate()
+            .take_while(|(position, ((_, leaf_index), _))| *leaf_index == *position as u64)
+            .map(|(_, (_, receipt))| svm_clob_types::receipts::leaf_hash(&receipt.leaf()))
+            .collect())
+    }
+
+    async fn get_fee_accruals(&self, journal_sequence: u64) -> ClobResult<Vec<FeeAccrual>> {
+        Ok(self
+            .state()
+            .fee_accruals
+            .range((journal_sequence, 0)..=(journal_sequence, u8::MAX))
+            .map(|(_, accrual)| accrual.clone())
+            .collect())
+    }
+
+    async fn rewind(&self, journal_sequence: u64, timestamp: i64, journal_consumers: &[String]) -> ClobResult<()> {
+        let mut state = self.state();
+        state.settlements.retain(|&sequence, _| sequence <= journal_sequence);
+        state.dead_letters.retain(|_, letter| letter.first_sequence <= journal_sequence);
+        state.fee_accruals.retain(|&(sequence, _), _| sequence <= journal_sequence);
+        state.sequence_map.retain(|&(sequence, _), _| sequence <= journal_sequence);
+        state.alerts.retain(|_, alert| alert.last_sequence <= journal_sequence);
+        state.book_checkpoints.retain(|&sequence, _| sequence <= journal_sequence);
+        let removed = state.trade_adjustments.split_off(&(journal_sequence + 1));
+        // Versions are raised in journal order, so the first removed
+        // adjustment of a trade is the one it goes back before
+        for adjustment in removed.values().rev() {
+            if let Some(trade) = state.trades.iter_mut().find(|trade| trade.trade_id == adjustment.trade_id) {
+                trade.price = adjustment.previous_price;
+                trade.version = adjustment.version - 1;
+                trade.status = if trade.version > 0 { TradeStatus::Corrected } else { TradeStatus::Active };
+            }
+        }
+        state.journal.retain(|entry| entry.sequence <= journal_sequence);
+        state.trades.retain(|trade| trade.timestamp <= timestamp);
+        state.orders.retain(|_, order| order.timestamp <= timestamp);
+        let State { orders, arrivals, .. } = &mut *state;
+        arrivals.retain(|order_id| orders.contains_key(order_id));
+        state.snapshots.retain(|snapshot| snapshot.timestamp <= timestamp);
+        state.incentive_epochs.retain(|_, epoch| epoch.epoch_end <= timestamp);
+        state.incentive_reports.retain(|_, report| report.epoch_end <= timestamp);
+        state.obligation_periods.retain(|_, period| period.period_end <= timestamp);
+        state.scorecards.retain(|_, scorecard| scorecard.period_end <= timestamp);
+        state.statement_days.retain(|_, day| day.period_end <= timestamp);
+        state.statements.retain(|_, statement| statement.summary.period_end <= timestamp);
+        for service in journal_consumers {
+            if let Some(checkpoint) = state.checkpoints.get_mut(service) {
+                checkpoint.position = checkpoint.position.min(journal_sequence);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn ledger_entry(balances: &mut BTreeMap<String, LedgerBalance>, user: Pubkey) -> &mut LedgerBalance {
+    balances.entry(user.to_string()).or_insert_with(|| LedgerBalance::empty(user))
+}
@@ -0,0 +1,68 @@
+/// Binary encoding of order book snapshot levels
+///
+/// `PostgresStorage` keeps the bids and asks of a snapshot in one `levels`
+/// column instead of two JSON arrays. A value starts with a four byte header,
+/// the magic `OBS` and a format version, followed by a zstd frame of the
+/// borsh encoded bids, then asks, each a length and `(price, quantity)`
+/// pairs. Readers refuse versions they do not know, so the format can change
+/// without misreading older rows.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use svm_clob_types::{ClobError, ClobResult};
+
+/// First bytes of every encoded value
+const MAGIC: &[u8; 3] = b"OBS";
+
+/// Format of the bytes after the header
+pub const SNAPSHOT_FORMAT_VERSION: u8 = 1;
+
+/// zstd level levels are compressed at; favours write latency over size
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Price levels, `(price, quantity)` best first
+pub type Levels = Vec<(u64, u64)>;
+
+/// Encode the bids and asks of a snapshot
+pub fn encode_levels(bids: &Levels, asks: &Levels) -> ClobResult<Vec<u8>> {
+    let mut raw = Vec::with_capacity(8 + 16 * (bids.len() + asks.len()));
+    bids.serialize(&mut raw).map_err(serialization_error)?;
+    asks.serialize(&mut raw).map_err(serialization_error)?;
+    let compressed = zstd::bulk::compress(&raw, COMPRESSION_LEVEL).map_err(serialization_error)?;
+
+    let mut encoded = Vec::with_capacity(MAGIC.len() + 1 + compressed.len());
+    encoded.extend_from_slice(MAGIC);
+    encoded.push(SNAPSHOT_FORMAT_VERSION);
+    encoded.extend_from_slice(&compressed);
+    Ok(encoded)
+}
+
+/// Decode bids and asks written by `encode_levels`
+pub fn decode_levels(encoded: &[u8]) -> ClobResult<(Levels, Levels)> {
+    let body = encoded
+        .strip_prefix(MAGIC.as_slice())
+        .ok_or_else(|| ClobError::SerializationError("Not an encoded order book snapshot".to_string()))?;
+    let (&version, compressed) = body
+        .split_first()
+        .ok_or_else(|| ClobError::SerializationError("Truncated order book snapshot".to_string()))?;
+    if version != SNAPSHOT_FORMAT_VERSION {
+        return Err(ClobError::SerializationError(format!(
+            "Unsupported order book snapshot version {}",
+            version
+        )));
+    }
+
+    let raw = zstd::stream::decode_all(compressed).map_err(serialization_error)?;
+    let mut reader = raw.as_slice();
+    let bids = Levels::deserialize(&mut reader).map_err(serialization_error)?;
+    let asks = Levels::deserialize(&mut reader).map_err(serialization_error)?;
+    if !reader.is_empty() {
+        return Err(ClobError::SerializationError(
+            "Trailing bytes after order book snapshot levels".to_string(),
+        ));
+    }
+    Ok((bids, asks))
+}
+
+fn serialization_error(e: impl std::fmt::Display) -> ClobError {
+    ClobError::SerializationError(e.to_string())
+}
@@ -0,0 +1,381 @@
+/// Pattern detectors
+///
+/// Journal entries are grouped into tumbling windows aligned to multiples of
+/// the window length, so a rescan after a restart sees the same windows. Each
+/// detector looks at one closed window at a time.
+
+use crate::SurveillanceConfig;
+use svm_clob_types::*;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap};
+
+/// Resting order followed across windows
+struct TrackedOrder {
+    owner: Pubkey,
+    remaining: u64,
+    /// Placed within `touch_bps` of the last trade price
+    near_touch: bool,
+}
+
+/// Per-account quoting activity near the touch
+#[derive(Default)]
+struct QuoteActivity {
+    near_touch_orders: u64,
+    cancelled_quantity: u64,
+    filled_quantity: u64,
+}
+
+/// Fill as seen by the detectors
+struct WindowFill {
+    timestamp: i64,
+    buyer: Pubkey,
+    seller: Pubkey,
+    aggressor: Pubkey,
+    aggressor_side: OrderSide,
+    price: u64,
+    quantity: u64,
+}
+
+/// Activity of the open window
+struct Window {
+    start: i64,
+    first_sequence: u64,
+    last_sequence: u64,
+    /// Quantity bought, keyed by (buyer, seller)
+    flows: BTreeMap<(Pubkey, Pubkey), u64>,
+    quoting: BTreeMap<Pubkey, QuoteActivity>,
+    fills: Vec<WindowFill>,
+}
+
+impl Window {
+    fn new(start: i64, sequence: u64) -> Self {
+        Self {
+            start,
+            first_sequence: sequence,
+            last_sequence: sequence,
+            flows: BTreeMap::new(),
+            quoting: BTreeMap::new(),
+            fills: Vec::new(),
+        }
+    }
+}
+
+/// Streaming detector over journal entries
+pub struct Detector {
+    config: SurveillanceConfig,
+    window: Option<Window>,
+    orders: HashMap<u64, TrackedOrder>,
+    last_price: Option<u64>,
+    /// Last journal sequence of the most recently closed window
+    closed_through: u64,
+}
+
+impl Detector {
+    /// Create a detector resuming after journal sequence `position`
+    pub fn new(config: SurveillanceConfig, position: u64) -> Self {
+        Self {
+            config,
+            window: None,
+            orders: HashMap::new(),
+            last_price: None,
+            closed_through: position,
+        }
+    }
+
+    /// Every entry up to this sequence belongs to a window that was evaluated
+    pub fn closed_through(&self) -> u64 {
+        self.closed_through
+    }
+
+    /// Feed one journal entry, returning alerts of any window it closed
+    pub fn observe(&mut self, entry: &JournalEntry) -> Vec<SurveillanceAlert> {
+        let start = self.window_start(entry.timestamp);
+        let mut alerts = Vec::new();
+        if self.window.as_ref().is_some_and(|window| start > window.start) {
+            alerts = self.close();
+        }
+        let window = self.window.get_or_insert_with(|| Window::new(start, entry.sequence));
+        window.last_sequence = entry.sequence;
+
+        match &entry.event {
+            JournalEvent::OrderPlaced { order } => self.place(order),
+            JournalEvent::OrderCancelled { order_id } => self.cancel(*order_id),
+            // The engine, not the trader, pulled it; no cancel to count
+            JournalEvent::OrderExpired { order_id, .. } => {
+                self.orders.remove(order_id);
+            }
+            JournalEvent::MmpTriggered { order_ids, .. } => {
+                for order_id in order_ids {
+                    self.orders.remove(order_id);
+                }
+            }
+            JournalEvent::OrderReplaced { original_order_id, order } => {
+                self.cancel(*original_order_id);
+                self.place(order);
+            }
+            JournalEvent::TradeExecuted { trade, maker, taker } => self.fill(entry.timestamp, trade, *maker, *taker),
+            // Alerts stand on the orders as placed; an operator's bust or
+            // correction does not change what the trader did
+            JournalEvent::OrderIdsReserved { .. } | JournalEvent::TradeAdjusted { .. } => {}
+        }
+        alerts
+    }
+
+    /// Close the open window if journal time `now` is past its end
+    pub fn flush(&mut self, now: i64) -> Vec<SurveillanceAlert> {
+        match &self.window {
+            Some(window) if now >= window.start + self.config.window_secs => self.close(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn window_start(&self, timestamp: i64) -> i64 {
+        let length = self.config.window_secs.max(1);
+        timestamp.div_euclid(length) * length
+    }
+
+    fn place(&mut self, order: &Order) {
+        // Only orders that can rest are candidates for spoofing
+        let rests = order.order_type != OrderType::Market
+            && matches!(order.time_in_force, TimeInForce::GoodTillCancelled | TimeInForce::GoodTillTime);
+        if !rests {
+            return;
+        }
+
+        let near_touch = self
+            .last_price
+            .is_some_and(|price| Price(order.price).distance_bps(Price(price)) <= self.config.touch_bps as u128);
+        if near_touch {
+            self.activity(order.owner).near_touch_orders += 1;
+        }
+        self.orders.insert(
+            order.order_id,
+            TrackedOrder {
+                owner: order.owner,
+                remaining: order.remaining_quantity,
+                near_touch,
+            },
+        );
+    }
+
+    fn cancel(&mut self, order_id: u64) {
+        if let Some(order) = self.orders.remove(&order_id) {
+            if order.near_touch {
+                self.activity(order.owner).cancelled_quantity += order.remaining;
+            }
+        }
+    }
+
+    fn fill(&mut self, timestamp: i64, trade: &TradeExecution, maker: Pubkey, taker: Pubkey) {
+        let (buyer, seller) = match trade.maker_side {
+            OrderSide::Bid => (maker, taker),
+            OrderSide::Ask => (taker, maker),
+        };
+        let aggressor_side = match trade.maker_side {
+            OrderSide::Bid => OrderSide::Ask,
+            OrderSide::Ask => OrderSide::Bid,
+        };
+
+        for order_id in [trade.maker_order_id, trade.taker_order_id] {
+            let Some(order) = self.orders.get_mut(&order_id) else {
+                continue;
+            };
+            order.remaining = order.remaining.saturating_sub(trade.quantity);
+            let (owner, near_touch, done) = (order.owner, order.near_touch, order.remaining == 0);
+            if near_touch {
+                self.activity(owner).filled_quantity += trade.quantity;
+            }
+            if done {
+                self.orders.remove(&order_id);
+            }
+        }
+
+        let window = self.window.as_mut().expect("window opened before the event is applied");
+        *window.flows.entry((buyer, seller)).or_default() += trade.quantity;
+        window.fills.push(WindowFill {
+            timestamp,
+            buyer,
+            seller,
+            aggressor: taker,
+            aggressor_side,
+            price: trade.price,
+            quantity: trade.quantity,
+        });
+        self.last_price = Some(trade.price);
+    }
+
+    fn activity(&mut self, owner: Pubkey) -> &mut QuoteActivity {
+        self.window
+            .as_mut()
+            .expect("window opened before the event is applied")
+            .quoting
+            .entry(owner)
+            .or_default()
+    }
+
+    fn close(&mut self) -> Vec<SurveillanceAlert> {
+        let Some(window) = self.window.take() else {
+            return Vec::new();
+        };
+        self.closed_through = window.last_sequence;
+
+        let mut alerts = Vec::new();
+        self.detect_wash_trading(&window, &mut alerts);
+        self.detect_spoofing(&window, &mut alerts);
+        self.detect_momentum_ignition(&window, &mut alerts);
+        alerts
+    }
+
+    /// Pairs of accounts that bought from each other in both directions
+    ///
+    /// Score: share of the pair's volume that nets out, `2 * min(a->b, b->a) / (a->b + b->a)`.
+    fn detect_wash_trading(&self, window: &Window, alerts: &mut Vec<SurveillanceAlert>) {
+        for (&(buyer, seller), &bought) in &window.flows {
+            // Visit each unordered pair once
+            if buyer >= seller {
+                continue;
+            }
+            let Some(&sold) = window.flows.get(&(seller, buyer)) else {
+                continue;
+            };
+
+            let volume = bought + sold;
+            let score = 2.0 * bought.min(sold) as f64 / volume as f64;
+            if volume < self.config.wash_min_volume || score < self.config.wash_min_score {
+                continue;
+            }
+            alerts.push(self.alert(
+                window,
+                AlertKind::WashTrading,
+                vec![buyer, seller],
+                score,
+                format!("{} bought {} from {}, which bought {} back", buyer, bought, seller, sold),
+            ));
+        }
+    }
+
+    /// Accounts that cancel far more near-touch quantity than they let fill
+    ///
+    /// Score: cancelled share of near-touch quantity, `cancelled / (cancelled + filled)`.
+    fn detect_spoofing(&self, window: &Window, alerts: &mut Vec<SurveillanceAlert>) {
+        for (&owner, activity) in &window.quoting {
+            if activity.near_touch_orders < self.config.spoof_min_orders || activity.cancelled_quantity == 0 {
+                continue;
+            }
+            let ratio = activity.cancelled_quantity as f64 / activity.filled_quantity.max(1) as f64;
+            if ratio < self.config.spoof_min_cancel_to_fill {
+                continue;
+            }
+
+            let score = activity.cancelled_quantity as f64
+                / (activity.cancelled_quantity + activity.filled_quantity) as f64;
+            alerts.push(self.alert(
+                window,
+                AlertKind::Spoofing,
+                vec![owner],
+                score,
+                format!(
+                    "{} orders within {} bps of the last trade; {} cancelled vs {} filled",
+                    activity.near_touch_orders, self.config.touch_bps, activity.cancelled_quantity, activity.filled_quantity
+                ),
+            ));
+        }
+    }
+
+    /// Accounts whose aggressive burst moved the price and who then traded the
+    /// other way in the same window
+    ///
+    /// Score: mean of the price move relative to twice the threshold and the
+    /// reversed share of the burst quantity, each capped at 1.
+    fn detect_momentum_ignition(&self, window: &Window, alerts: &mut Vec<SurveillanceAlert>) {
+        let mut best: BTreeMap<Pubkey, (f64, String)> = BTreeMap::new();
+
+        for (index, first) in window.fills.iter().enumerate() {
+            let account = first.aggressor;
+            let side = first.aggressor_side;
+            // Each burst is evaluated from its first fill only
+            let continues_burst = window.fills[..index].iter().rev().find(|fill| fill.aggressor == account).is_some_and(|previous| {
+                previous.aggressor_side == side && first.timestamp - previous.timestamp <= self.config.ignition_burst_secs
+            });
+            if continues_burst {
+                continue;
+            }
+
+            // The burst: this account's same-side aggressive fills within the burst period
+            let mut burst_quantity = 0;
+            let mut last_price = first.price;
+            let mut burst_end = index;
+            for (offset, fill) in window.fills[index..].iter().enumerate() {
+                if fill.timestamp - first.timestamp > self.config.ignition_burst_secs {
+                    break;
+                }
+                if fill.aggressor == account {
+                    if fill.aggressor_side != side {
+                        break;
+                    }
+                    burst_quantity += fill.quantity;
+                    last_price = fill.price;
+                    burst_end = index + offset;
+                }
+            }
+
+            let moved = match side {
+                OrderSide::Bid => last_price.saturating_sub(first.price),
+                OrderSide::Ask => first.price.saturating_sub(last_price),
+            };
+            let move_bps = moved as f64 * 10_000.0 / first.price.max(1) as f64;
+            if move_bps < self.config.ignition_move_bps as f64 {
+                continue;
+            }
+
+            let reversed: u64 = window.fills[burst_end + 1..]
+                .iter()
+                .filter(|fill| match side {
+                    OrderSide::Bid => fill.seller == account,
+                    OrderSide::Ask => fill.buyer == account,
+                })
+                .map(|fill| fill.quantity)
+                .sum();
+            if reversed == 0 {
+                continue;
+            }
+
+            let move_score = (move_bps / (2.0 * self.config.ignition_move_bps.max(1) as f64)).min(1.0);
+            let reversal_score = (reversed as f64 / burst_quantity.max(1) as f64).min(1.0);
+            let score = (move_score + reversal_score) / 2.0;
+            let details = format!(
+                "{:?} burst of {} moved the price {} -> {} ({:.0} bps), then {} traded back",
+                side, burst_quantity, first.price, last_price, move_bps, reversed
+            );
+            if best.get(&account).map_or(true, |(current, _)| score > *current) {
+                best.insert(account, (score, details));
+            }
+        }
+
+        for (account, (score, details)) in best {
+            alerts.push(self.alert(window, AlertKind::MomentumIgnition, vec![account], score, details));
+        }
+    }
+
+    fn alert(
+        &self,
+        window: &Window,
+        kind: AlertKind,
+        mut accounts: Vec<Pubkey>,
+        score: f64,
+        details: String,
+    ) -> SurveillanceAlert {
+        accounts.sort();
+        SurveillanceAlert {
+            id: 0,
+            kind,
+            accounts,
+            score,
+            window_start: window.start,
+            window_end: window.start + self.config.window_secs,
+            first_sequence: window.first_sequence,
+            last_sequence: window.last_sequence,
+            details,
+        }
+    }
+}
@@ -0,0 +1,130 @@
+/// Trade Surveillance for SVM CLOB Infrastructure
+///
+/// This module scans the engine journal for wash trading, spoofing and momentum
+/// ignition, and stores scored alerts for review through the admin API.
+
+pub mod detector;
+
+use svm_clob_types::*;
+use svm_clob_storage::Storage;
+use detector::Detector;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Checkpoint service name used by surveillance
+pub const CHECKPOINT_SERVICE: &str = "surveillance";
+
+/// Surveillance configuration
+#[derive(Debug, Clone)]
+pub struct SurveillanceConfig {
+    /// Length of the tumbling window patterns are evaluated over, in seconds
+    pub window_secs: i64,
+    /// Delay between polls when the journal is drained
+    pub poll_interval: Duration,
+    /// Journal entries read per poll
+    pub batch_size: u32,
+    /// Minimum quantity traded between a pair before wash trading is considered
+    pub wash_min_volume: u64,
+    /// Minimum wash trading score to alert on
+    pub wash_min_score: f64,
+    /// Distance from the last trade price within which an order counts as near the touch
+    pub touch_bps: u64,
+    /// Minimum near-touch orders per account before spoofing is considered
+    pub spoof_min_orders: u64,
+    /// Minimum ratio of cancelled to filled near-touch quantity to alert on
+    pub spoof_min_cancel_to_fill: f64,
+    /// Period an aggressive burst must fit in, in seconds
+    pub ignition_burst_secs: i64,
+    /// Minimum price move of a burst to alert on
+    pub ignition_move_bps: u64,
+}
+
+impl Default for SurveillanceConfig {
+    fn default() -> Self {
+        Self {
+            window_secs: 300,
+            poll_interval: Duration::from_secs(1),
+            batch_size: 1000,
+            wash_min_volume: 1,
+            wash_min_score: 0.8,
+            touch_bps: 10,
+            spoof_min_orders: 5,
+            spoof_min_cancel_to_fill: 10.0,
+            ignition_burst_secs: 10,
+            ignition_move_bps: 50,
+        }
+    }
+}
+
+/// Journal consumer that raises surveillance alerts
+///
+/// The checkpoint only advances past windows that were evaluated, so a restart
+/// re-reads the open window. Orders resting from before the checkpoint are not
+/// known after a restart and are ignored until they are replaced.
+pub struct SurveillanceService<S: Storage> {
+    config: SurveillanceConfig,
+    storage: Arc<S>,
+}
+
+impl<S: Storage> SurveillanceService<S> {
+    /// Create a new surveillance service
+    pub fn new(storage: Arc<S>, config: SurveillanceConfig) -> Self {
+        Self { config, storage }
+    }
+
+    /// Run until storage fails
+    pub async fn run(&self) -> ClobResult<()> {
+        let mut position = self
+            .storage
+            .get_checkpoint(CHECKPOINT_SERVICE)
+            .await?
+            .map(|checkpoint| checkpoint.position)
+            .unwrap_or(0);
+        let mut detector = Detector::new(self.config.clone(), position);
+        info!("Surveillance resuming after journal sequence {}", position);
+
+        loop {
+            let checkpoint = detector.closed_through();
+            let entries = self.storage.get_journal_entries(position, self.config.batch_size).await?;
+
+            let mut alerts = Vec::new();
+            for entry in &entries {
+                alerts.extend(detector.observe(entry));
+                position = entry.sequence;
+            }
+            if entries.is_empty() {
+                // No activity: close the open window once it has ended
+                alerts.extend(detector.flush(chrono::Utc::now().timestamp()));
+            }
+
+            self.record(alerts).await?;
+            if detector.closed_through() > checkpoint {
+                self.save_checkpoint(detector.closed_through()).await?;
+            }
+
+            if entries.is_empty() {
+                tokio::time::sleep(self.config.poll_interval).await;
+            }
+        }
+    }
+
+    async fn record(&self, alerts: Vec<SurveillanceAlert>) -> ClobResult<()> {
+        for alert in alerts {
+            let id = self.storage.store_surveillance_alert(&alert).await?;
+            warn!(
+                "Surveillance alert {}: {:?} score {:.2} accounts {:?}: {}",
+                id, alert.kind, alert.score, alert.accounts, alert.details
+            );
+        }
+        Ok(())
+    }
+
+    async fn save_checkpoint(&self, sequence: u64) -> ClobResult<()> {
+        self.storage.save_checkpoint(&Checkpoint {
+            service: CHECKPOINT_SERVICE.to_string(),
+            position: sequence,
+            cursor: None,
+        }).await
+    }
+}
@@ -0,0 +1,146 @@
+/// Distributed Tracing for SVM CLOB Infrastructure
+///
+/// This module wires `tracing` spans to OpenTelemetry and exports them over OTLP,
+/// and carries W3C trace context across process boundaries (HTTP requests and
+/// the engine journal) so an order can be followed from request to settlement.
+/// Alongside it, every client call is given a request ID that is logged, stored
+/// with the rows it writes, and quoted in the memo of its settlement
+/// transactions, so one action can be found everywhere without a collector.
+
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::{TraceContextExt, TraceError};
+use opentelemetry::{global, Context, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Sampler, Tracer};
+use opentelemetry_sdk::Resource;
+use std::collections::HashMap;
+use tracing::Subscriber;
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// W3C trace context header name
+pub const TRACEPARENT: &str = "traceparent";
+
+/// Header carrying a client's request ID, echoed on every response
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest request ID accepted from a client
+pub const MAX_REQUEST_ID_LEN: usize = 64;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// OTLP exporter settings
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    /// `service.name` resource attribute
+    pub service_name: String,
+    /// OTLP gRPC collector endpoint, e.g. `http://localhost:4317`
+    pub otlp_endpoint: String,
+    /// Fraction of new traces to sample; propagated traces follow their parent
+    pub sample_ratio: f64,
+}
+
+/// Build a `tracing` layer that exports spans to the OTLP collector
+///
+/// Must be called from within a Tokio runtime, which drives the batch exporter.
+pub fn otel_layer<S>(config: &TelemetryConfig) -> Result<OpenTelemetryLayer<S, Tracer>, TraceError>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(config.otlp_endpoint.clone()),
+        )
+        .with_trace_config(
+            opentelemetry_sdk::trace::config()
+                .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                    config.sample_ratio,
+                ))))
+                .with_resource(Resource::new(vec![KeyValue::new(
+                    "service.name",
+                    config.service_name.clone(),
+                )])),
+        )
+        .install_batch(opentelemetry_sdk::runtime::Tokio)?;
+
+    Ok(tracing_opentelemetry::layer().with_tracer(tracer))
+}
+
+/// Flush pending spans; call before the process exits
+pub fn shutdown() {
+    global::shutdown_tracer_provider();
+}
+
+/// `traceparent` of the current span, if it belongs to a sampled trace
+pub fn current_traceparent() -> Option<String> {
+    let context = tracing::Span::current().context();
+    if !context.span().span_context().is_valid() {
+        return None;
+    }
+
+    let mut carrier = HashMap::new();
+    TraceContextPropagator::new().inject_context(&context, &mut carrier);
+    carrier.remove(TRACEPARENT)
+}
+
+/// Trace context encoded by `current_traceparent`
+pub fn context_from_traceparent(traceparent: &str) -> Context {
+    let mut carrier = HashMap::new();
+    carrier.insert(TRACEPARENT.to_string(), traceparent.to_string());
+    TraceContextPropagator::new().extract(&carrier)
+}
+
+/// Trace context carried by any header-like source (HTTP headers, ...)
+pub fn extract_context(carrier: &dyn Extractor) -> Context {
+    TraceContextPropagator::new().extract(carrier)
+}
+
+/// Make `span` a child of the remote context, if it carries a valid trace
+pub fn set_remote_parent(span: &tracing::Span, context: Context) {
+    if context.span().span_context().is_valid() {
+        span.set_parent(context);
+    }
+}
+
+/// Link `span` to the trace encoded in `traceparent`, for fan-in work like batching
+pub fn link_traceparent(span: &tracing::Span, traceparent: &str) {
+    let context = context_from_traceparent(traceparent);
+    let span_context = context.span().span_context().clone();
+    if span_context.is_valid() {
+        span.add_link(span_context);
+    }
+}
+
+/// Request ID of a call: the client's own when it sent a usable one (up to
+/// `MAX_REQUEST_ID_LEN` letters, digits, `-`, `_`, `.` or `:`), a new one otherwise
+pub fn request_id_or_new(requested: Option<&str>) -> String {
+    match requested {
+        Some(id)
+            if !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LEN
+                && id.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':')) =>
+        {
+            id.to_string()
+        }
+        _ => uuid::Uuid::new_v4().simple().to_string(),
+    }
+}
+
+/// Run `future` on behalf of the request `request_id`; storage writes made
+/// within it record the ID
+pub async fn with_request_id<F: std::future::Future>(request_id: String, future: F) -> F::Output {
+    REQUEST_ID.scope(request_id, future).await
+}
+
+/// ID of the request the current task works on, if any
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
@@ -0,0 +1,581 @@
+/// Zero-copy views of on-chain account data
+///
+/// `OrderBookView` and `UserAccountView` borrow raw account data fetched over
+/// RPC, check the Anchor discriminator, length and flag bytes once, and then
+/// read fields straight out of the `repr(C)` layout the program stores.
+/// `FillReceiptsView` reads the ring of recent fills a user opted into, and
+/// `SignedOrderFillView` the quantity settled against one signed order, and
+/// `ReceiptTreeView` the Merkle tree a market appends compressed receipts to.
+/// `OrderView` does the same for the program's `offchain_api::Order`
+/// encoding, which has no account of its own and no discriminator. Flag bytes
+/// become `bool`s and enum bytes become the shared enums here, so callers no
+/// longer compare raw `u8`s.
+
+use crate::program::{account_discriminator, FillReceipt, OrderBookAccount, UserAccountData};
+use crate::{ClobError, ClobResult, LiquidityRole, MarketId, MarketStatus, Notional, Order, OrderBook, OrderSide, OrderStatus, OrderType, OrderTypeWhitelist, SelfTradeBehavior, TimeInForce, UserAccount};
+use anchor_lang::solana_program::pubkey::Pubkey;
+
+const DISCRIMINATOR_LEN: usize = 8;
+
+/// Byte offsets of the `OrderBook` account body
+mod orderbook {
+    pub const AUTHORITY: usize = 0;
+    pub const BASE_MINT: usize = 32;
+    pub const QUOTE_MINT: usize = 64;
+    pub const TICK_SIZE: usize = 96;
+    pub const MIN_ORDER_SIZE: usize = 104;
+    pub const TOTAL_VOLUME: usize = 112;
+    pub const IS_INITIALIZED: usize = 120;
+    pub const STATUS: usize = 121;
+    pub const COMPRESSED_RECEIPTS: usize = 122;
+    pub const STATUS_CHANGED_AT: usize = 128;
+    pub const ORDER_TYPES: usize = 136;
+    pub const TIME_IN_FORCE: usize = 137;
+    pub const RESERVED: usize = 138;
+    /// Including padding and the reserved tail
+    pub const LEN: usize = 160;
+}
+
+/// Byte offsets of the `UserAccount` account body
+mod user_account {
+    pub const OWNER: usize = 0;
+    pub const TOTAL_VOLUME_TRADED: usize = 32;
+    pub const BASE_TOKEN_BALANCE: usize = 40;
+    pub const QUOTE_TOKEN_BALANCE: usize = 48;
+    pub const IS_INITIALIZED: usize = 56;
+    pub const SUB_ACCOUNT: usize = 58;
+    pub const PREVENTION_GROUP: usize = 60;
+    pub const WALLET: usize = 64;
+    /// Including padding
+    pub const LEN: usize = 96;
+}
+
+/// Byte offsets of the `FillReceipts` account body
+mod fill_receipts {
+    pub const OWNER: usize = 0;
+    pub const TOTAL_FILLS: usize = 32;
+    pub const RECEIPTS: usize = 40;
+    /// Receipts in the ring (`FILL_RECEIPTS_CAPACITY` in the program)
+    pub const CAPACITY: usize = 32;
+    pub const LEN: usize = RECEIPTS + CAPACITY * super::fill_receipt::LEN;
+}
+
+/// Byte offsets of one `FillReceipt` within the ring
+mod fill_receipt {
+    pub const ORDERBOOK: usize = 0;
+    pub const ORDER_ID: usize = 32;
+    pub const COUNTERPARTY_ORDER_ID: usize = 40;
+    pub const PRICE: usize = 48;
+    pub const QUANTITY: usize = 56;
+    pub const TIMESTAMP: usize = 64;
+    pub const SIDE: usize = 72;
+    pub const IS_MAKER: usize = 73;
+    /// Including padding
+    pub const LEN: usize = 80;
+}
+
+/// Byte offsets of the `SignedOrderFill` account body
+mod signed_order_fill {
+    pub const OWNER: usize = 0;
+    pub const AUTHORITY: usize = 32;
+    pub const MESSAGE_HASH: usize = 64;
+    pub const NONCE: usize = 96;
+    pub const FILLED: usize = 104;
+    pub const EXPIRY: usize = 112;
+    pub const LEN: usize = 120;
+}
+
+/// Byte offsets of the `ReceiptTree` account body
+mod receipt_tree {
+    pub const ORDERBOOK: usize = 0;
+    pub const MERKLE_TREE: usize = 32;
+    pub const LEAVES: usize = 64;
+    pub const CAPACITY: usize = 72;
+    pub const MAX_DEPTH: usize = 80;
+    pub const MAX_BUFFER_SIZE: usize = 84;
+    pub const GENERATION: usize = 88;
+    /// Including padding
+    pub const LEN: usize = 96;
+}
+
+/// Byte offsets of the borsh encoded `offchain_api::Order`
+mod order {
+    pub const ORDER_ID: usize = 0;
+    pub const OWNER: usize = 8;
+    pub const PRICE: usize = 40;
+    pub const QUANTITY: usize = 48;
+    pub const SIDE: usize = 56;
+    pub const ORDER_TYPE: usize = 57;
+    pub const TIMESTAMP: usize = 58;
+    pub const LEN: usize = 66;
+}
+
+/// Body of an Anchor account after checking its discriminator and length
+fn account_body<'a>(name: &str, data: &'a [u8], len: usize) -> ClobResult<&'a [u8]> {
+    if data.len() < DISCRIMINATOR_LEN || data[..DISCRIMINATOR_LEN] != account_discriminator(name) {
+        return Err(ClobError::SerializationError(format!("Account data is not a {} account", name)));
+    }
+    let body = &data[DISCRIMINATOR_LEN..];
+    if body.len() < len {
+        return Err(ClobError::SerializationError(format!(
+            "{} account is {} bytes, expected {}",
+            name,
+            body.len(),
+            len
+        )));
+    }
+    Ok(body)
+}
+
+/// Read a `u8` flag, rejecting anything but 0 and 1
+fn flag(name: &str, field: &str, byte: u8) -> ClobResult<bool> {
+    match byte {
+        0 => Ok(false),
+        1 => Ok(true),
+        other => Err(ClobError::SerializationError(format!("{}.{} is {}, not a flag", name, field, other))),
+    }
+}
+
+fn read_u64(body: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(body[at..at + 8].try_into().unwrap())
+}
+
+fn read_u16(body: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes(body[at..at + 2].try_into().unwrap())
+}
+
+fn read_u32(body: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(body[at..at + 4].try_into().unwrap())
+}
+
+fn read_i64(body: &[u8], at: usize) -> i64 {
+    i64::from_le_bytes(body[at..at + 8].try_into().unwrap())
+}
+
+fn read_pubkey(body: &[u8], at: usize) -> Pubkey {
+    Pubkey::new_from_array(body[at..at + 32].try_into().unwrap())
+}
+
+/// Borrowed view of an `OrderBook` account
+#[derive(Debug, Clone, Copy)]
+pub struct OrderBookView<'a> {
+    body: &'a [u8],
+}
+
+impl<'a> OrderBookView<'a> {
+    /// Size of the account, for `getProgramAccounts` data size filters
+    pub const ACCOUNT_LEN: usize = DISCRIMINATOR_LEN + orderbook::LEN;
+    /// Offsets of the mints in the account, for memcmp filters
+    pub const BASE_MINT_OFFSET: usize = DISCRIMINATOR_LEN + orderbook::BASE_MINT;
+    pub const QUOTE_MINT_OFFSET: usize = DISCRIMINATOR_LEN + orderbook::QUOTE_MINT;
+
+    /// Check raw account data and borrow it
+    pub fn new(data: &'a [u8]) -> ClobResult<Self> {
+        let body = account_body("OrderBook", data, orderbook::LEN)?;
+        flag("OrderBook", "is_initialized", body[orderbook::IS_INITIALIZED])?;
+        MarketStatus::try_from(body[orderbook::STATUS]).map_err(|e| {
+            ClobError::SerializationError(format!("OrderBook.status is {}, not a market status", e.0))
+        })?;
+        Ok(Self { body })
+    }
+
+    pub fn authority(&self) -> Pubkey {
+        read_pubkey(self.body, orderbook::AUTHORITY)
+    }
+
+    pub fn base_mint(&self) -> Pubkey {
+        read_pubkey(self.body, orderbook::BASE_MINT)
+    }
+
+    pub fn quote_mint(&self) -> Pubkey {
+        read_pubkey(self.body, orderbook::QUOTE_MINT)
+    }
+
+    pub fn tick_size(&self) -> u64 {
+        read_u64(self.body, orderbook::TICK_SIZE)
+    }
+
+    pub fn min_order_size(&self) -> u64 {
+        read_u64(self.body, orderbook::MIN_ORDER_SIZE)
+    }
+
+    pub fn total_volume(&self) -> u64 {
+        read_u64(self.body, orderbook::TOTAL_VOLUME)
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.body[orderbook::IS_INITIALIZED] == 1
+    }
+
+    pub fn status(&self) -> MarketStatus {
+        MarketStatus::try_from(self.body[orderbook::STATUS]).unwrap_or(MarketStatus::Paused)
+    }
+
+    /// When `status` last changed, unix seconds; zero if it never has
+    pub fn status_changed_at(&self) -> i64 {
+        read_i64(self.body, orderbook::STATUS_CHANGED_AT)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.status() == MarketStatus::Paused
+    }
+
+    /// Whether settlements append their fills to a compressed receipt tree
+    /// and so need the receipt tree accounts
+    pub fn compressed_receipts(&self) -> bool {
+        self.body[orderbook::COMPRESSED_RECEIPTS] == 1
+    }
+
+    /// Order types and times in force the market accepts
+    pub fn order_types(&self) -> OrderTypeWhitelist {
+        OrderTypeWhitelist {
+            order_types: self.body[orderbook::ORDER_TYPES],
+            time_in_force: self.body[orderbook::TIME_IN_FORCE],
+        }
+    }
+
+    /// Owned copy of the raw layout
+    pub fn to_account(&self) -> OrderBookAccount {
+        let mut padding = [0u8; 5];
+        padding.copy_from_slice(&self.body[orderbook::COMPRESSED_RECEIPTS + 1..orderbook::STATUS_CHANGED_AT]);
+        let mut reserved = [0u8; 22];
+        reserved.copy_from_slice(&self.body[orderbook::RESERVED..orderbook::LEN]);
+        OrderBookAccount {
+            authority: self.authority(),
+            base_mint: self.base_mint(),
+            quote_mint: self.quote_mint(),
+            tick_size: self.tick_size(),
+            min_order_size: self.min_order_size(),
+            total_volume: self.total_volume(),
+            is_initialized: self.is_initialized() as u8,
+            status: self.status().into(),
+            compressed_receipts: self.body[orderbook::COMPRESSED_RECEIPTS],
+            padding,
+            status_changed_at: self.status_changed_at(),
+            order_types: self.body[orderbook::ORDER_TYPES],
+            time_in_force: self.body[orderbook::TIME_IN_FORCE],
+            reserved,
+        }
+    }
+
+    /// Engine configuration for this market; book statistics the program
+    /// does not track start at zero
+    pub fn to_orderbook(&self) -> OrderBook {
+        OrderBook {
+            authority: self.authority(),
+            base_mint: self.base_mint(),
+            quote_mint: self.quote_mint(),
+            tick_size: self.tick_size(),
+            min_order_size: self.min_order_size(),
+            sequence_number: 0,
+            total_orders: 0,
+            best_bid: 0,
+            best_ask: 0,
+            total_volume: self.total_volume(),
+            is_initialized: self.is_initialized(),
+            is_paused: self.is_paused(),
+        }
+    }
+}
+
+/// Borrowed view of a `UserAccount` account
+#[derive(Debug, Clone, Copy)]
+pub struct UserAccountView<'a> {
+    body: &'a [u8],
+}
+
+impl<'a> UserAccountView<'a> {
+    /// Check raw account data and borrow it
+    pub fn new(data: &'a [u8]) -> ClobResult<Self> {
+        let body = account_body("UserAccount", data, user_account::LEN)?;
+        flag("UserAccount", "is_initialized", body[user_account::IS_INITIALIZED])?;
+        Ok(Self { body })
+    }
+
+    pub fn owner(&self) -> Pubkey {
+        read_pubkey(self.body, user_account::OWNER)
+    }
+
+    pub fn total_volume_traded(&self) -> u64 {
+        read_u64(self.body, user_account::TOTAL_VOLUME_TRADED)
+    }
+
+    pub fn base_token_balance(&self) -> u64 {
+        read_u64(self.body, user_account::BASE_TOKEN_BALANCE)
+    }
+
+    pub fn quote_token_balance(&self) -> u64 {
+        read_u64(self.body, user_account::QUOTE_TOKEN_BALANCE)
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.body[user_account::IS_INITIALIZED] == 1
+    }
+
+    /// Index among the wallet's sub-accounts; 0 for the wallet's own account
+    pub fn sub_account(&self) -> u16 {
+        read_u16(self.body, user_account::SUB_ACCOUNT)
+    }
+
+    /// Self-match prevention group; 0 for none
+    pub fn prevention_group(&self) -> u32 {
+        read_u32(self.body, user_account::PREVENTION_GROUP)
+    }
+
+    /// Wallet that signs for the account: a sub-account's wallet, otherwise
+    /// the owner
+    pub fn signer(&self) -> Pubkey {
+        match read_pubkey(self.body, user_account::WALLET) {
+            wallet if wallet == Pubkey::default() => self.owner(),
+            wallet => wallet,
+        }
+    }
+
+    /// Owned copy of the raw layout
+    pub fn to_account(&self) -> UserAccountData {
+        UserAccountData {
+            owner: self.owner(),
+            total_volume_traded: self.total_volume_traded(),
+            base_token_balance: self.base_token_balance(),
+            quote_token_balance: self.quote_token_balance(),
+            is_initialized: self.is_initialized() as u8,
+            padding: [self.body[user_account::IS_INITIALIZED + 1]],
+            sub_account: self.sub_account(),
+            prevention_group: self.prevention_group(),
+            wallet: read_pubkey(self.body, user_account::WALLET),
+        }
+    }
+
+    /// Off-chain view of the account; order counts are not kept on chain
+    pub fn to_user_account(&self) -> UserAccount {
+        UserAccount {
+            owner: self.owner(),
+            open_orders_count: 0,
+            total_orders_placed: 0,
+            total_volume_traded: self.total_volume_traded(),
+            base_token_balance: self.base_token_balance(),
+            quote_token_balance: self.quote_token_balance(),
+            is_initialized: self.is_initialized(),
+        }
+    }
+}
+
+/// Borrowed view of a `FillReceipts` account
+#[derive(Debug, Clone, Copy)]
+pub struct FillReceiptsView<'a> {
+    body: &'a [u8],
+}
+
+impl<'a> FillReceiptsView<'a> {
+    /// Check raw account data and borrow it
+    pub fn new(data: &'a [u8]) -> ClobResult<Self> {
+        let body = account_body("FillReceipts", data, fill_receipts::LEN)?;
+        Ok(Self { body })
+    }
+
+    pub fn owner(&self) -> Pubkey {
+        read_pubkey(self.body, fill_receipts::OWNER)
+    }
+
+    /// Fills recorded since the account was created, including overwritten ones
+    pub fn total_fills(&self) -> u64 {
+        read_u64(self.body, fill_receipts::TOTAL_FILLS)
+    }
+
+    /// Receipts still held, newest first
+    pub fn receipts(&self) -> ClobResult<Vec<FillReceipt>> {
+        let total = self.total_fills();
+        let held = total.min(fill_receipts::CAPACITY as u64);
+        (1..=held)
+            .map(|age| self.receipt(((total - age) % fill_receipts::CAPACITY as u64) as usize))
+            .collect()
+    }
+
+    fn receipt(&self, slot: usize) -> ClobResult<FillReceipt> {
+        let at = fill_receipts::RECEIPTS + slot * fill_receipt::LEN;
+        let side = OrderSide::try_from(self.body[at + fill_receipt::SIDE]).map_err(|_| ClobError::InvalidOrderSide)?;
+        let role = match flag("FillReceipt", "is_maker", self.body[at + fill_receipt::IS_MAKER])? {
+            true => LiquidityRole::Maker,
+            false => LiquidityRole::Taker,
+        };
+        Ok(FillReceipt {
+            orderbook: read_pubkey(self.body, at + fill_receipt::ORDERBOOK),
+            order_id: read_u64(self.body, at + fill_receipt::ORDER_ID),
+            counterparty_order_id: read_u64(self.body, at + fill_receipt::COUNTERPARTY_ORDER_ID),
+            price: read_u64(self.body, at + fill_receipt::PRICE),
+            quantity: read_u64(self.body, at + fill_receipt::QUANTITY),
+            timestamp: read_i64(self.body, at + fill_receipt::TIMESTAMP),
+            side,
+            role,
+        })
+    }
+}
+
+/// Borrowed view of a `SignedOrderFill` account
+#[derive(Debug, Clone, Copy)]
+pub struct SignedOrderFillView<'a> {
+    body: &'a [u8],
+}
+
+impl<'a> SignedOrderFillView<'a> {
+    /// Size of the account, for `getProgramAccounts` data size filters
+    pub const ACCOUNT_LEN: usize = DISCRIMINATOR_LEN + signed_order_fill::LEN;
+    /// Offset of the authority in the account, for memcmp filters
+    pub const AUTHORITY_OFFSET: usize = DISCRIMINATOR_LEN + signed_order_fill::AUTHORITY;
+
+    /// Check raw account data and borrow it
+    pub fn new(data: &'a [u8]) -> ClobResult<Self> {
+        let body = account_body("SignedOrderFill", data, signed_order_fill::LEN)?;
+        Ok(Self { body })
+    }
+
+    pub fn owner(&self) -> Pubkey {
+        read_pubkey(self.body, signed_order_fill::OWNER)
+    }
+
+    /// Market authority that settled the order and paid for the record
+    pub fn authority(&self) -> Pubkey {
+        read_pubkey(self.body, signed_order_fill::AUTHORITY)
+    }
+
+    /// Hash of the signed message the nonce was first used for
+    pub fn message_hash(&self) -> [u8; 32] {
+        self.body[signed_order_fill::MESSAGE_HASH..signed_order_fill::NONCE].try_into().unwrap()
+    }
+
+    pub fn nonce(&self) -> u64 {
+        read_u64(self.body, signed_order_fill::NONCE)
+    }
+
+    /// Quantity settled against the order so far
+    pub fn filled(&self) -> u64 {
+        read_u64(self.body, signed_order_fill::FILLED)
+    }
+
+    /// Expiry of the signature, unix seconds
+    pub fn expiry(&self) -> i64 {
+        read_i64(self.body, signed_order_fill::EXPIRY)
+    }
+}
+
+/// Borrowed view of a market's `ReceiptTree` account
+#[derive(Debug, Clone, Copy)]
+pub struct ReceiptTreeView<'a> {
+    body: &'a [u8],
+}
+
+impl<'a> ReceiptTreeView<'a> {
+    /// Check raw account data and borrow it
+    pub fn new(data: &'a [u8]) -> ClobResult<Self> {
+        let body = account_body("ReceiptTree", data, receipt_tree::LEN)?;
+        Ok(Self { body })
+    }
+
+    pub fn orderbook(&self) -> Pubkey {
+        read_pubkey(self.body, receipt_tree::ORDERBOOK)
+    }
+
+    /// Concurrent Merkle tree receipts are appended to
+    pub fn merkle_tree(&self) -> Pubkey {
+        read_pubkey(self.body, receipt_tree::MERKLE_TREE)
+    }
+
+    /// Receipts appended to the tree so far; the next one's leaf index
+    pub fn leaves(&self) -> u64 {
+        read_u64(self.body, receipt_tree::LEAVES)
+    }
+
+    /// Leaves the tree holds
+    pub fn capacity(&self) -> u64 {
+        read_u64(self.body, receipt_tree::CAPACITY)
+    }
+
+    pub fn max_depth(&self) -> u32 {
+        read_u32(self.body, receipt_tree::MAX_DEPTH)
+    }
+
+    pub fn max_buffer_size(&self) -> u32 {
+        read_u32(self.body, receipt_tree::MAX_BUFFER_SIZE)
+    }
+
+    /// Merkle trees the market has used, this one included
+    pub fn generation(&self) -> u32 {
+        read_u32(self.body, receipt_tree::GENERATION)
+    }
+}
+
+/// Borrowed view of an `offchain_api::Order` encoding
+#[derive(Debug, Clone, Copy)]
+pub struct OrderView<'a> {
+    body: &'a [u8],
+}
+
+impl<'a> OrderView<'a> {
+    /// Check encoded order bytes and borrow them
+    pub fn new(data: &'a [u8]) -> ClobResult<Self> {
+        if data.len() < order::LEN {
+            return Err(ClobError::SerializationError(format!(
+                "Order is {} bytes, expected {}",
+                data.len(),
+                order::LEN
+            )));
+        }
+        let view = Self { body: data };
+        view.side()?;
+        view.order_type()?;
+        Ok(view)
+    }
+
+    pub fn order_id(&self) -> u64 {
+        read_u64(self.body, order::ORDER_ID)
+    }
+
+    pub fn owner(&self) -> Pubkey {
+        read_pubkey(self.body, order::OWNER)
+    }
+
+    pub fn price(&self) -> u64 {
+        read_u64(self.body, order::PRICE)
+    }
+
+    pub fn quantity(&self) -> u64 {
+        read_u64(self.body, order::QUANTITY)
+    }
+
+    pub fn side(&self) -> ClobResult<OrderSide> {
+        OrderSide::try_from(self.body[order::SIDE]).map_err(|_| ClobError::InvalidOrderSide)
+    }
+
+    pub fn order_type(&self) -> ClobResult<OrderType> {
+        OrderType::try_from(self.body[order::ORDER_TYPE]).map_err(|_| ClobError::InvalidOrderType)
+    }
+
+    pub fn timestamp(&self) -> i64 {
+        read_i64(self.body, order::TIMESTAMP)
+    }
+
+    /// Open, unfilled off-chain order on `market_id`; the fields the program's
+    /// order does not carry take their defaults
+    pub fn to_order(&self, market_id: MarketId) -> ClobResult<Order> {
+        Ok(Order {
+            order_id: self.order_id(),
+            market_id,
+            owner: self.owner(),
+            price: self.price(),
+            quantity: self.quantity(),
+            remaining_quantity: self.quantity(),
+            timestamp: self.timestamp(),
+            client_order_id: 0,
+            expiry_timestamp: 0,
+            side: self.side()?,
+            order_type: self.order_type()?,
+            status: OrderStatus::Open,
+            self_trade_behavior: SelfTradeBehavior::CancelProvide,
+            prevention_group_id: None,
+            time_in_force: TimeInForce::GoodTillCancelled,
+            max_slippage_bps: None,
+            filled_notional: Notional::default(),
+            avg_fill_price: None,
+        })
+    }
+}
@@ -0,0 +1,146 @@
+/// Incremental order book feed
+///
+/// The WebSocket server sends a full `OrderBookSnapshot` when a client
+/// subscribes to a market's book, then a `BookDelta` for every change it
+/// publishes. A delta lists the levels whose quantity changed, with zero for a
+/// level that is gone, names the sequence of the book it applies to, and
+/// carries the checksum of the book it produces. A client keeping a local copy
+/// can therefore tell a missed or corrupted update from a good one, and
+/// resubscribes for a fresh snapshot when it sees either.
+
+use crate::{ClobError, ClobResult, MarketId, OrderBookSnapshot};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Levels per side covered by `book_checksum`
+pub const CHECKSUM_DEPTH: usize = 25;
+
+impl OrderBookSnapshot {
+    /// The book cut to its best `depth` levels a side, as served to
+    /// connections entitled to no more
+    pub fn truncated(&self, depth: usize) -> OrderBookSnapshot {
+        OrderBookSnapshot {
+            market_id: self.market_id,
+            bids: self.bids.iter().take(depth).copied().collect(),
+            asks: self.asks.iter().take(depth).copied().collect(),
+            sequence_number: self.sequence_number,
+            timestamp: self.timestamp,
+        }
+    }
+}
+
+/// Level changes between two consecutive published books of one market
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BookDelta {
+    pub market_id: MarketId,
+    /// Sequence of the book the delta applies to
+    pub prev_sequence_number: u64,
+    /// Sequence of the book it produces
+    pub sequence_number: u64,
+    /// Changed bid levels (price, new quantity); quantity 0 removes the level
+    pub bids: Vec<(u64, u64)>,
+    /// Changed ask levels, as `bids`
+    pub asks: Vec<(u64, u64)>,
+    /// `book_checksum` of the book it produces
+    pub checksum: u32,
+    pub timestamp: i64,
+}
+
+impl BookDelta {
+    /// Changes turning `previous` into `next`
+    pub fn between(previous: &OrderBookSnapshot, next: &OrderBookSnapshot) -> Self {
+        BookDelta {
+            market_id: next.market_id,
+            prev_sequence_number: previous.sequence_number,
+            sequence_number: next.sequence_number,
+            bids: changed_levels(&previous.bids, &next.bids),
+            asks: changed_levels(&previous.asks, &next.asks),
+            checksum: book_checksum(next),
+            timestamp: next.timestamp,
+        }
+    }
+
+    /// Apply to `book`, which must be at `prev_sequence_number`; fails without
+    /// touching `book` when the sequence or the resulting checksum is off
+    pub fn apply(&self, book: &mut OrderBookSnapshot) -> ClobResult<()> {
+        if book.sequence_number != self.prev_sequence_number {
+            return Err(ClobError::SerializationError(format!(
+                "Book delta {}..{} does not follow sequence {}",
+                self.prev_sequence_number, self.sequence_number, book.sequence_number
+            )));
+        }
+
+        let next = OrderBookSnapshot {
+            market_id: book.market_id,
+            bids: merge_levels(&book.bids, &self.bids).into_iter().rev().collect(),
+            asks: merge_levels(&book.asks, &self.asks).into_iter().collect(),
+            sequence_number: self.sequence_number,
+            timestamp: self.timestamp,
+        };
+
+        let checksum = book_checksum(&next);
+        if checksum != self.checksum {
+            return Err(ClobError::SerializationError(format!(
+                "Book checksum mismatch at sequence {}: expected {:08x}, got {:08x}",
+                self.sequence_number, self.checksum, checksum
+            )));
+        }
+        *book = next;
+        Ok(())
+    }
+}
+
+/// CRC-32 (IEEE) of the top `CHECKSUM_DEPTH` levels of each side
+///
+/// The input is the levels interleaved best first, bid then ask, each written
+/// as `price:quantity` and joined with `:`, e.g. `99:5:101:7:98:2`; a side that
+/// runs out of levels simply stops contributing.
+pub fn book_checksum(book: &OrderBookSnapshot) -> u32 {
+    let mut fields = Vec::new();
+    for index in 0..CHECKSUM_DEPTH {
+        for levels in [&book.bids, &book.asks] {
+            if let Some((price, quantity)) = levels.get(index) {
+                fields.push(format!("{}:{}", price, quantity));
+            }
+        }
+    }
+    crc32(fields.join(":").as_bytes())
+}
+
+/// Levels of `next` that differ from `previous`, with 0 for removed prices
+fn changed_levels(previous: &[(u64, u64)], next: &[(u64, u64)]) -> Vec<(u64, u64)> {
+    let before: BTreeMap<u64, u64> = previous.iter().copied().collect();
+    let after: BTreeMap<u64, u64> = next.iter().copied().collect();
+    let mut changes: Vec<(u64, u64)> = after
+        .iter()
+        .filter(|(price, quantity)| before.get(price) != Some(quantity))
+        .map(|(&price, &quantity)| (price, quantity))
+        .collect();
+    changes.extend(before.keys().filter(|price| !after.contains_key(price)).map(|&price| (price, 0)));
+    changes
+}
+
+/// `levels` with `changes` applied, by price
+fn merge_levels(levels: &[(u64, u64)], changes: &[(u64, u64)]) -> BTreeMap<u64, u64> {
+    let mut merged: BTreeMap<u64, u64> = levels.iter().copied().collect();
+    for &(price, quantity) in changes {
+        if quantity == 0 {
+            merged.remove(&price);
+        } else {
+            merged.insert(price, quantity);
+        }
+    }
+    merged
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
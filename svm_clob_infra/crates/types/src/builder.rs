@@ -0,0 +1,263 @@
+/// Order and request builders
+///
+/// `Order::builder()` and `PlaceOrderRequest::builder()` start from the
+/// engine's defaults (a good-till-cancelled limit order that cancels the
+/// resting side on self-trade) so callers only name what differs:
+///
+/// ```ignore
+/// let order = Order::builder().side(OrderSide::Bid).limit(100).qty(5).ioc().build()?;
+/// ```
+///
+/// `build` checks that the fields agree with each other; market parameters
+/// such as tick size and minimum size are still checked by the engine.
+
+use crate::{
+    ClobError, ClobResult, MarketId, Notional, Order, OrderSide, OrderStatus, OrderType, PlaceOrderRequest, SelfTradeBehavior,
+    TimeInForce,
+};
+use anchor_lang::solana_program::pubkey::Pubkey;
+use chrono::{DateTime, TimeZone};
+
+/// Builder for `Order` and `PlaceOrderRequest`
+#[derive(Debug, Clone)]
+pub struct OrderBuilder {
+    order_id: u64,
+    market_id: MarketId,
+    owner: Option<Pubkey>,
+    client_order_id: u64,
+    side: Option<OrderSide>,
+    order_type: OrderType,
+    price: Option<u64>,
+    quantity: Option<u64>,
+    time_in_force: TimeInForce,
+    expiry_timestamp: i64,
+    self_trade_behavior: SelfTradeBehavior,
+    prevention_group_id: Option<u32>,
+    max_slippage_bps: Option<u32>,
+    timestamp: Option<i64>,
+}
+
+impl Default for OrderBuilder {
+    fn default() -> Self {
+        Self {
+            order_id: 0,
+            market_id: MarketId::default(),
+            owner: None,
+            client_order_id: 0,
+            side: None,
+            order_type: OrderType::Limit,
+            price: None,
+            quantity: None,
+            time_in_force: TimeInForce::GoodTillCancelled,
+            expiry_timestamp: 0,
+            self_trade_behavior: SelfTradeBehavior::CancelProvide,
+            prevention_group_id: None,
+            max_slippage_bps: None,
+            timestamp: None,
+        }
+    }
+}
+
+impl Order {
+    /// Start building an order
+    pub fn builder() -> OrderBuilder {
+        OrderBuilder::default()
+    }
+}
+
+impl PlaceOrderRequest {
+    /// Start building a place order request; finish with `build_request`
+    pub fn builder() -> OrderBuilder {
+        OrderBuilder::default()
+    }
+}
+
+impl OrderBuilder {
+    pub fn order_id(mut self, order_id: u64) -> Self {
+        self.order_id = order_id;
+        self
+    }
+
+    pub fn market_id(mut self, market_id: MarketId) -> Self {
+        self.market_id = market_id;
+        self
+    }
+
+    pub fn owner(mut self, owner: Pubkey) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    pub fn client_order_id(mut self, client_order_id: u64) -> Self {
+        self.client_order_id = client_order_id;
+        self
+    }
+
+    pub fn side(mut self, side: OrderSide) -> Self {
+        self.side = Some(side);
+        self
+    }
+
+    pub fn bid(self) -> Self {
+        self.side(OrderSide::Bid)
+    }
+
+    pub fn ask(self) -> Self {
+        self.side(OrderSide::Ask)
+    }
+
+    /// Limit order at `price`
+    pub fn limit(mut self, price: u64) -> Self {
+        self.order_type = OrderType::Limit;
+        self.price = Some(price);
+        self
+    }
+
+    /// Post-only limit order at `price`
+    pub fn post_only(mut self, price: u64) -> Self {
+        self.order_type = OrderType::PostOnly;
+        self.price = Some(price);
+        self
+    }
+
+    /// Market order; it takes any price and never rests
+    pub fn market(mut self) -> Self {
+        self.order_type = OrderType::Market;
+        self.price = Some(0);
+        self
+    }
+
+    pub fn qty(mut self, quantity: u64) -> Self {
+        self.quantity = Some(quantity);
+        self
+    }
+
+    /// Time in force; anything but `good_till` clears the expiry
+    pub fn time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.time_in_force = time_in_force;
+        if time_in_force != TimeInForce::GoodTillTime {
+            self.expiry_timestamp = 0;
+        }
+        self
+    }
+
+    pub fn gtc(self) -> Self {
+        self.time_in_force(TimeInForce::GoodTillCancelled)
+    }
+
+    pub fn ioc(self) -> Self {
+        self.time_in_force(TimeInForce::ImmediateOrCancel)
+    }
+
+    pub fn fok(self) -> Self {
+        self.time_in_force(TimeInForce::FillOrKill)
+    }
+
+    /// Good until `expiry_timestamp` (unix seconds)
+    pub fn good_till(mut self, expiry_timestamp: i64) -> Self {
+        self.time_in_force = TimeInForce::GoodTillTime;
+        self.expiry_timestamp = expiry_timestamp;
+        self
+    }
+
+    /// Good until `expires_at`, in any time zone; fractions of a second are dropped
+    pub fn good_till_date<Tz: TimeZone>(self, expires_at: &DateTime<Tz>) -> Self {
+        self.good_till(expires_at.timestamp())
+    }
+
+    pub fn self_trade_behavior(mut self, self_trade_behavior: SelfTradeBehavior) -> Self {
+        self.self_trade_behavior = self_trade_behavior;
+        self
+    }
+
+    /// Also keep the order from matching other owners' orders in `group`
+    pub fn prevention_group(mut self, group: u32) -> Self {
+        self.prevention_group_id = Some(group);
+        self
+    }
+
+    /// Reject the market order when its average fill price would be more than
+    /// `bps` basis points from the best opposite price
+    pub fn max_slippage_bps(mut self, bps: u32) -> Self {
+        self.max_slippage_bps = Some(bps);
+        self
+    }
+
+    /// Creation time; the current time when not set
+    pub fn timestamp(mut self, timestamp: i64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Build an open order with nothing filled yet
+    pub fn build(self) -> ClobResult<Order> {
+        let (side, price, quantity) = self.validate()?;
+        Ok(Order {
+            order_id: self.order_id,
+            market_id: self.market_id,
+            owner: self.owner.unwrap_or_default(),
+            price,
+            quantity,
+            remaining_quantity: quantity,
+            timestamp: self.timestamp.unwrap_or_else(|| chrono::Utc::now().timestamp()),
+            client_order_id: self.client_order_id,
+            expiry_timestamp: self.expiry_timestamp,
+            side,
+            order_type: self.order_type,
+            status: OrderStatus::Open,
+            self_trade_behavior: self.self_trade_behavior,
+            prevention_group_id: self.prevention_group_id,
+            time_in_force: self.time_in_force,
+            max_slippage_bps: self.max_slippage_bps,
+            filled_notional: Notional::default(),
+            avg_fill_price: None,
+        })
+    }
+
+    /// Build a place order request; requests must name their owner
+    pub fn build_request(self) -> ClobResult<PlaceOrderRequest> {
+        let (side, price, quantity) = self.validate()?;
+        let owner = self.owner.ok_or(ClobError::Unauthorized)?;
+        Ok(PlaceOrderRequest {
+            market_id: (!self.market_id.is_unset()).then_some(self.market_id),
+            owner: owner.to_string(),
+            client_order_id: self.client_order_id,
+            side,
+            order_type: self.order_type,
+            price,
+            quantity,
+            time_in_force: self.time_in_force,
+            expiry_timestamp: (self.time_in_force == TimeInForce::GoodTillTime).then_some(self.expiry_timestamp),
+            self_trade_behavior: self.self_trade_behavior,
+            max_slippage_bps: self.max_slippage_bps,
+            prevention_group_id: self.prevention_group_id,
+            signature: None,
+        })
+    }
+
+    fn validate(&self) -> ClobResult<(OrderSide, u64, u64)> {
+        let side = self.side.ok_or(ClobError::InvalidOrderSide)?;
+        let price = match (self.order_type, self.price) {
+            (OrderType::Market, _) => 0,
+            (_, Some(price)) if price > 0 => price,
+            _ => return Err(ClobError::InvalidPrice("Limit and post-only orders need a positive price".to_string())),
+        };
+        let quantity = match self.quantity {
+            Some(quantity) if quantity > 0 => quantity,
+            _ => return Err(ClobError::InvalidQuantity("Quantity must be positive".to_string())),
+        };
+        if self.time_in_force == TimeInForce::GoodTillTime && self.expiry_timestamp <= 0 {
+            return Err(ClobError::OrderExpired);
+        }
+        let rests = matches!(self.time_in_force, TimeInForce::GoodTillCancelled | TimeInForce::GoodTillTime);
+        if self.order_type == OrderType::PostOnly && !rests {
+            // A post-only order that may not rest can never do anything
+            return Err(ClobError::InvalidOrderType);
+        }
+        if self.max_slippage_bps.is_some() && self.order_type != OrderType::Market {
+            // A limit price already bounds what the order pays
+            return Err(ClobError::InvalidOrderType);
+        }
+        Ok((side, price, quantity))
+    }
+}
@@ -0,0 +1,283 @@
+/// Canonical error codes
+///
+/// Every `ClobError` has one stable numeric code, shared by REST JSON-RPC error
+/// bodies, WebSocket `Error` frames and gRPC status details, plus the HTTP
+/// status and gRPC code it is reported with. Errors the program also raises
+/// keep its `#[error_code]` number (Anchor numbers custom errors from 6000 in
+/// declaration order), so a client can treat an on-chain failure and an
+/// off-chain rejection of the same order alike.
+///
+/// Ranges:
+/// - 6000-6999: mirrors the program's `ErrorCode`
+/// - 7000-7099: off-chain order and market rejections
+/// - 7100-7199: infrastructure failures
+///
+/// Codes are never reused; a retired variant keeps its number reserved.
+
+use crate::{ClobError, RateLimitStatus};
+
+pub const INVALID_PRICE: u32 = 6000;
+pub const INVALID_QUANTITY: u32 = 6001;
+pub const ORDER_SIZE_BELOW_MINIMUM: u32 = 6002;
+pub const PRICE_NOT_ALIGNED_TO_TICK_SIZE: u32 = 6003;
+pub const ORDERBOOK_PAUSED: u32 = 6004;
+pub const INSUFFICIENT_BALANCE: u32 = 6005;
+pub const UNAUTHORIZED: u32 = 6006;
+// 6007 InvalidMint and 6008 InvalidAuthority are raised on chain only
+pub const SLIPPAGE_EXCEEDED: u32 = 6009;
+/// `InvalidOrderSignature` on chain
+pub const INVALID_SIGNATURE: u32 = 6010;
+/// `SignedOrderExpired` on chain
+pub const SIGNATURE_EXPIRED: u32 = 6011;
+// 6012 OrderIntentMismatch is raised on chain only
+// 6013 DestinationNotWhitelisted, 6014 WhitelistChangeNotReady and 6015
+// WhitelistFull are raised on chain only
+pub const MARKET_CANCEL_ONLY: u32 = 6016;
+// 6017 SignedOrderOverfilled is raised on chain only
+/// `SignedOrderNonceReused` on chain
+pub const NONCE_REUSED: u32 = 6018;
+// 6019 SignedOrderNotExpired and 6020 InvalidSubAccount are raised on chain only
+// 6021 ReceiptTreeRequired and 6022 ReceiptTreeFull are raised on chain only
+
+pub const INVALID_ORDER_SIDE: u32 = 7000;
+pub const INVALID_ORDER_TYPE: u32 = 7001;
+pub const ORDER_NOT_FOUND: u32 = 7002;
+pub const SELF_TRADE_DETECTED: u32 = 7003;
+pub const ORDER_EXPIRED: u32 = 7004;
+pub const MARKET_ORDER_WOULD_CROSS_SPREAD: u32 = 7005;
+pub const POST_ONLY_ORDER_WOULD_MATCH: u32 = 7006;
+pub const RISK_LIMIT_EXCEEDED: u32 = 7007;
+pub const UNKNOWN_MARKET: u32 = 7008;
+pub const NOT_LEADER: u32 = 7009;
+/// Malformed request that never reached the engine
+pub const INVALID_REQUEST: u32 = 7010;
+pub const RATE_LIMITED: u32 = 7011;
+pub const DUPLICATE_CLIENT_ORDER_ID: u32 = 7012;
+pub const REDUCE_ONLY: u32 = 7013;
+pub const ENGINE_PAUSED: u32 = 7014;
+pub const MMP_FROZEN: u32 = 7015;
+pub const SUSPENDED: u32 = 7016;
+pub const TRADE_NOT_ADJUSTABLE: u32 = 7017;
+pub const NOT_ENTITLED: u32 = 7018;
+pub const ORDER_TYPE_DISABLED: u32 = 7019;
+pub const IDEMPOTENCY_KEY_REUSED: u32 = 7020;
+pub const INVALID_EXPIRY: u32 = 7021;
+
+pub const STORAGE_ERROR: u32 = 7100;
+pub const NETWORK_ERROR: u32 = 7101;
+pub const SERIALIZATION_ERROR: u32 = 7102;
+
+/// gRPC status codes used in status details
+pub mod grpc {
+    pub const INVALID_ARGUMENT: i32 = 3;
+    pub const NOT_FOUND: i32 = 5;
+    pub const ALREADY_EXISTS: i32 = 6;
+    pub const PERMISSION_DENIED: i32 = 7;
+    pub const RESOURCE_EXHAUSTED: i32 = 8;
+    pub const FAILED_PRECONDITION: i32 = 9;
+    pub const INTERNAL: i32 = 13;
+    pub const UNAVAILABLE: i32 = 14;
+    pub const UNAUTHENTICATED: i32 = 16;
+}
+
+impl ClobError {
+    /// Stable numeric code of this error
+    pub fn code(&self) -> u32 {
+        match self {
+            ClobError::InvalidPrice(_) => INVALID_PRICE,
+            ClobError::InvalidQuantity(_) => INVALID_QUANTITY,
+            ClobError::OrderSizeBelowMinimum => ORDER_SIZE_BELOW_MINIMUM,
+            ClobError::PriceNotAlignedToTickSize => PRICE_NOT_ALIGNED_TO_TICK_SIZE,
+            ClobError::OrderbookPaused => ORDERBOOK_PAUSED,
+            ClobError::MarketCancelOnly => MARKET_CANCEL_ONLY,
+            ClobError::InsufficientBalance => INSUFFICIENT_BALANCE,
+            ClobError::Unauthorized => UNAUTHORIZED,
+            ClobError::SlippageExceeded(_) => SLIPPAGE_EXCEEDED,
+            ClobError::InvalidSignature(_) => INVALID_SIGNATURE,
+            ClobError::SignatureExpired => SIGNATURE_EXPIRED,
+            ClobError::NonceReused(_) => NONCE_REUSED,
+            ClobError::InvalidOrderSide => INVALID_ORDER_SIDE,
+            ClobError::InvalidOrderType => INVALID_ORDER_TYPE,
+            ClobError::OrderNotFound => ORDER_NOT_FOUND,
+            ClobError::SelfTradeDetected => SELF_TRADE_DETECTED,
+            ClobError::OrderExpired => ORDER_EXPIRED,
+            ClobError::MarketOrderWouldCrossSpread => MARKET_ORDER_WOULD_CROSS_SPREAD,
+            ClobError::PostOnlyOrderWouldMatch => POST_ONLY_ORDER_WOULD_MATCH,
+            ClobError::RiskLimitExceeded(_) => RISK_LIMIT_EXCEEDED,
+            ClobError::UnknownMarket(_) => UNKNOWN_MARKET,
+            ClobError::NotLeader => NOT_LEADER,
+            ClobError::RateLimited(_) => RATE_LIMITED,
+            ClobError::DuplicateClientOrderId(_) => DUPLICATE_CLIENT_ORDER_ID,
+            ClobError::ReduceOnly(_) => REDUCE_ONLY,
+            ClobError::EnginePaused => ENGINE_PAUSED,
+            ClobError::MmpFrozen(_) => MMP_FROZEN,
+            ClobError::Suspended(_) => SUSPENDED,
+            ClobError::TradeNotAdjustable(_) => TRADE_NOT_ADJUSTABLE,
+            ClobError::NotEntitled(_) => NOT_ENTITLED,
+            ClobError::OrderTypeDisabled(_) => ORDER_TYPE_DISABLED,
+            ClobError::IdempotencyKeyReused(_) => IDEMPOTENCY_KEY_REUSED,
+            ClobError::InvalidExpiry(_) => INVALID_EXPIRY,
+            ClobError::StorageError(_) => STORAGE_ERROR,
+            ClobError::NetworkError(_) => NETWORK_ERROR,
+            ClobError::SerializationError(_) => SERIALIZATION_ERROR,
+        }
+    }
+
+    /// HTTP status the REST API answers this error with
+    pub fn http_status(&self) -> u16 {
+        match self {
+            ClobError::InvalidPrice(_)
+            | ClobError::InvalidQuantity(_)
+            | ClobError::OrderSizeBelowMinimum
+            | ClobError::PriceNotAlignedToTickSize
+            | ClobError::InvalidOrderSide
+            | ClobError::InvalidOrderType
+            | ClobError::InvalidExpiry(_)
+            | ClobError::SerializationError(_) => 400,
+            ClobError::Unauthorized | ClobError::InvalidSignature(_) | ClobError::SignatureExpired => 401,
+            ClobError::Suspended(_) | ClobError::NotEntitled(_) => 403,
+            ClobError::OrderNotFound | ClobError::UnknownMarket(_) => 404,
+            ClobError::OrderbookPaused
+            | ClobError::MarketCancelOnly
+            | ClobError::ReduceOnly(_)
+            | ClobError::SelfTradeDetected
+            | ClobError::OrderExpired
+            | ClobError::MarketOrderWouldCrossSpread
+            | ClobError::PostOnlyOrderWouldMatch
+            | ClobError::SlippageExceeded(_)
+            | ClobError::DuplicateClientOrderId(_)
+            | ClobError::NonceReused(_)
+            | ClobError::MmpFrozen(_)
+            | ClobError::TradeNotAdjustable(_)
+            | ClobError::OrderTypeDisabled(_) => 409,
+            ClobError::InsufficientBalance
+            | ClobError::RiskLimitExceeded(_)
+            | ClobError::IdempotencyKeyReused(_) => 422,
+            ClobError::RateLimited(_) => 429,
+            ClobError::StorageError(_) => 500,
+            ClobError::NetworkError(_) => 502,
+            // A standby answers 503 so clients and load balancers move on to
+            // the active matcher; a paused engine so clients retry later
+            ClobError::NotLeader | ClobError::EnginePaused => 503,
+        }
+    }
+
+    /// gRPC status code this error is reported with
+    pub fn grpc_code(&self) -> i32 {
+        match self {
+            ClobError::InvalidPrice(_)
+            | ClobError::InvalidQuantity(_)
+            | ClobError::OrderSizeBelowMinimum
+            | ClobError::PriceNotAlignedToTickSize
+            | ClobError::InvalidOrderSide
+            | ClobError::InvalidOrderType
+            | ClobError::IdempotencyKeyReused(_)
+            | ClobError::InvalidExpiry(_)
+            | ClobError::SerializationError(_) => grpc::INVALID_ARGUMENT,
+            ClobError::Unauthorized | ClobError::InvalidSignature(_) | ClobError::SignatureExpired => {
+                grpc::UNAUTHENTICATED
+            }
+            ClobError::Suspended(_) | ClobError::NotEntitled(_) => grpc::PERMISSION_DENIED,
+            ClobError::OrderNotFound | ClobError::UnknownMarket(_) => grpc::NOT_FOUND,
+            ClobError::DuplicateClientOrderId(_) | ClobError::NonceReused(_) => grpc::ALREADY_EXISTS,
+            ClobError::OrderbookPaused
+            | ClobError::MarketCancelOnly
+            | ClobError::ReduceOnly(_)
+            | ClobError::SelfTradeDetected
+            | ClobError::OrderExpired
+            | ClobError::MarketOrderWouldCrossSpread
+            | ClobError::PostOnlyOrderWouldMatch
+            | ClobError::SlippageExceeded(_)
+            | ClobError::MmpFrozen(_)
+            | ClobError::TradeNotAdjustable(_)
+            | ClobError::OrderTypeDisabled(_)
+            | ClobError::InsufficientBalance => grpc::FAILED_PRECONDITION,
+            ClobError::RiskLimitExceeded(_) | ClobError::RateLimited(_) => grpc::RESOURCE_EXHAUSTED,
+            ClobError::StorageError(_) => grpc::INTERNAL,
+            ClobError::NetworkError(_) | ClobError::NotLeader | ClobError::EnginePaused => grpc::UNAVAILABLE,
+        }
+    }
+
+    /// Rebuild an error from its code and the message it was reported with.
+    /// Variants with a detail field take `message` as that detail, and
+    /// `RateLimited` its status as JSON; unknown codes, and a rate limit
+    /// status that does not parse, return `None`.
+    pub fn from_code(code: u32, message: &str) -> Option<Self> {
+        let detail = message.to_string();
+        let error = match code {
+            INVALID_PRICE => ClobError::InvalidPrice(detail),
+            INVALID_QUANTITY => ClobError::InvalidQuantity(detail),
+            ORDER_SIZE_BELOW_MINIMUM => ClobError::OrderSizeBelowMinimum,
+            PRICE_NOT_ALIGNED_TO_TICK_SIZE => ClobError::PriceNotAlignedToTickSize,
+            ORDERBOOK_PAUSED => ClobError::OrderbookPaused,
+            MARKET_CANCEL_ONLY => ClobError::MarketCancelOnly,
+            INSUFFICIENT_BALANCE => ClobError::InsufficientBalance,
+            UNAUTHORIZED => ClobError::Unauthorized,
+            SLIPPAGE_EXCEEDED => ClobError::SlippageExceeded(detail),
+            INVALID_SIGNATURE => ClobError::InvalidSignature(detail),
+            SIGNATURE_EXPIRED => ClobError::SignatureExpired,
+            NONCE_REUSED => ClobError::NonceReused(detail),
+            INVALID_ORDER_SIDE => ClobError::InvalidOrderSide,
+            INVALID_ORDER_TYPE => ClobError::InvalidOrderType,
+            ORDER_NOT_FOUND => ClobError::OrderNotFound,
+            SELF_TRADE_DETECTED => ClobError::SelfTradeDetected,
+            ORDER_EXPIRED => ClobError::OrderExpired,
+            MARKET_ORDER_WOULD_CROSS_SPREAD => ClobError::MarketOrderWouldCrossSpread,
+            POST_ONLY_ORDER_WOULD_MATCH => ClobError::PostOnlyOrderWouldMatch,
+            RISK_LIMIT_EXCEEDED => ClobError::RiskLimitExceeded(detail),
+            UNKNOWN_MARKET => ClobError::UnknownMarket(detail),
+            NOT_LEADER => ClobError::NotLeader,
+            RATE_LIMITED => ClobError::RateLimited(serde_json::from_str(message).ok()?),
+            DUPLICATE_CLIENT_ORDER_ID => ClobError::DuplicateClientOrderId(detail),
+            REDUCE_ONLY => ClobError::ReduceOnly(detail),
+            ENGINE_PAUSED => ClobError::EnginePaused,
+            MMP_FROZEN => ClobError::MmpFrozen(detail),
+            SUSPENDED => ClobError::Suspended(detail),
+            TRADE_NOT_ADJUSTABLE => ClobError::TradeNotAdjustable(detail),
+            NOT_ENTITLED => ClobError::NotEntitled(detail),
+            ORDER_TYPE_DISABLED => ClobError::OrderTypeDisabled(detail),
+            IDEMPOTENCY_KEY_REUSED => ClobError::IdempotencyKeyReused(detail),
+            INVALID_EXPIRY => ClobError::InvalidExpiry(detail),
+            STORAGE_ERROR => ClobError::StorageError(detail),
+            NETWORK_ERROR => ClobError::NetworkError(detail),
+            SERIALIZATION_ERROR => ClobError::SerializationError(detail),
+            _ => return None,
+        };
+        Some(error)
+    }
+
+    /// Detail carried by the variant, as `from_code` expects it back
+    pub fn detail(&self) -> String {
+        match self {
+            ClobError::InvalidPrice(detail)
+            | ClobError::InvalidQuantity(detail)
+            | ClobError::RiskLimitExceeded(detail)
+            | ClobError::UnknownMarket(detail)
+            | ClobError::InvalidSignature(detail)
+            | ClobError::DuplicateClientOrderId(detail)
+            | ClobError::NonceReused(detail)
+            | ClobError::ReduceOnly(detail)
+            | ClobError::MmpFrozen(detail)
+            | ClobError::Suspended(detail)
+            | ClobError::TradeNotAdjustable(detail)
+            | ClobError::NotEntitled(detail)
+            | ClobError::OrderTypeDisabled(detail)
+            | ClobError::IdempotencyKeyReused(detail)
+            | ClobError::InvalidExpiry(detail)
+            | ClobError::SlippageExceeded(detail)
+            | ClobError::StorageError(detail)
+            | ClobError::NetworkError(detail)
+            | ClobError::SerializationError(detail) => detail.clone(),
+            ClobError::RateLimited(status) => serde_json::to_string(status).unwrap_or_else(|_| status.to_string()),
+            other => other.to_string(),
+        }
+    }
+
+    /// Standing against the rate limit, for a `RateLimited` rejection
+    pub fn rate_limit(&self) -> Option<&RateLimitStatus> {
+        match self {
+            ClobError::RateLimited(status) => Some(status),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,62 @@
+/// Good-till-date expiries
+///
+/// A good-till-time order, also accepted as `GoodTillDate`, rests until its
+/// `expiry_timestamp` in unix seconds. The API takes the expiry either as
+/// those seconds or as an RFC3339 datetime, which must carry its UTC offset
+/// (`2026-01-31T17:00:00Z`, `2026-01-31T12:00:00-05:00`) so that it names
+/// one instant wherever the client is. Fractions of a second are dropped, so
+/// an order never rests past the instant it names. An expiry is accepted
+/// when it lies after the time of placement and at most
+/// `MAX_EXPIRY_HORIZON_SECS` beyond it.
+
+use crate::{ClobError, ClobResult, PlaceOrderRequest, TimeInForce};
+use chrono::DateTime;
+use serde::{Deserialize, Deserializer};
+
+/// Furthest ahead of its placement an order may expire
+pub const MAX_EXPIRY_HORIZON_SECS: i64 = 366 * 86_400;
+
+/// Unix seconds of an RFC3339 datetime
+pub fn parse_expiry(value: &str) -> Result<i64, String> {
+    DateTime::parse_from_rfc3339(value.trim())
+        .map(|datetime| datetime.timestamp())
+        .map_err(|e| format!("'{}' is not an RFC3339 datetime with an offset: {}", value, e))
+}
+
+/// Check the expiry of a good-till-time request placed at `now`; other
+/// times in force ignore their expiry
+pub fn check_expiry(request: &PlaceOrderRequest, now: i64) -> ClobResult<()> {
+    if request.time_in_force != TimeInForce::GoodTillTime {
+        return Ok(());
+    }
+    let Some(expiry) = request.expiry_timestamp else {
+        return Err(ClobError::InvalidExpiry("good-till-date orders need an expiry".to_string()));
+    };
+    if expiry <= now {
+        return Err(ClobError::OrderExpired);
+    }
+    if expiry - now > MAX_EXPIRY_HORIZON_SECS {
+        return Err(ClobError::InvalidExpiry(format!(
+            "expiry {} lies more than {} seconds ahead",
+            expiry, MAX_EXPIRY_HORIZON_SECS
+        )));
+    }
+    Ok(())
+}
+
+/// Either form of an expiry in a request
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ExpiryValue {
+    Seconds(i64),
+    Datetime(String),
+}
+
+/// Deserialize an optional expiry given as unix seconds or an RFC3339 datetime
+pub fn deserialize_expiry<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<i64>, D::Error> {
+    match Option::<ExpiryValue>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(ExpiryValue::Seconds(seconds)) => Ok(Some(seconds)),
+        Some(ExpiryValue::Datetime(datetime)) => parse_expiry(&datetime).map(Some).map_err(serde::de::Error::custom),
+    }
+}
@@ -5,67 +5,129 @@
 
 use anchor_lang::prelude::*;
 use serde::{Deserialize, Serialize};
-use solana_sdk::pubkey::Pubkey;
 use std::fmt;
+use std::str::FromStr;
 use thiserror::Error;
 
 // Re-export contract types for compatibility
+pub use anchor_lang::prelude::Pubkey;
 pub use anchor_lang::{AnchorDeserialize, AnchorSerialize};
 
-/// Order side enumeration - matches contract exactly
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
-#[repr(u8)]
-pub enum OrderSide {
-    Bid = 0,  // Buy order
-    Ask = 1,  // Sell order
+pub mod accounts;
+pub mod book_feed;
+pub mod builder;
+pub mod clock;
+pub mod error_codes;
+pub mod expiry;
+pub mod market_data;
+pub mod pagination;
+pub mod portfolio;
+pub mod program;
+pub mod receipts;
+#[cfg(feature = "signing")]
+pub mod signing;
+pub mod statements;
+pub mod units;
+pub mod wire;
+pub mod ws;
+
+pub use accounts::{OrderBookView, OrderView, SignedOrderFillView, UserAccountView};
+pub use book_feed::BookDelta;
+pub use builder::OrderBuilder;
+pub use clock::{system_clock, Clock, SystemClock, VirtualClock};
+pub use expiry::MAX_EXPIRY_HORIZON_SECS;
+pub use market_data::{
+    BandDepth, Candle, CandleInterval, Depth, DepthLevel, MarketMetrics, OrderFlow, Ticker, WindowStats,
+};
+pub use pagination::{Cursor, Page, Paginated, DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT};
+pub use portfolio::{Balances, MarketBalance, Portfolio, Position, UserFill};
+pub use statements::{Statement, StatementArtifacts, StatementDay, StatementFormat, StatementSummary};
+pub use units::{Displayed, MarketUnits, Notional, Price, Priced, Quantity};
+pub use wire::{Envelope, WirePayload, WIRE_VERSION};
+
+// Order enums are shared with the on-chain program
+pub use svm_clob_core::{
+    FillReceiptLeaf, InvalidDiscriminant, MarketStatus, OrderIntent, OrderSide, OrderStatus, OrderType, OrderTypeWhitelist,
+    SelfTradeBehavior, SignedOrder, TimeInForce, RECEIPT_LEAF_DATA_LEN, RECEIPT_LEAF_DOMAIN, SIGNING_MESSAGE_LEN,
+};
+
+/// Market identifier: the address of the market's orderbook PDA
+///
+/// Unique per base/quote mint pair and program deployment. Serialized as a
+/// base58 string so it can appear in URLs and subscription messages. The
+/// default value stands for "the engine's market" in requests that predate
+/// multi-market support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct MarketId(pub Pubkey);
+
+impl MarketId {
+    /// Market of a base/quote pair under `program_id`
+    pub fn new(program_id: &Pubkey, base_mint: &Pubkey, quote_mint: &Pubkey) -> Self {
+        MarketId(program::orderbook_address(program_id, base_mint, quote_mint).0)
+    }
+
+    /// Market of a base/quote pair under the default program ID
+    pub fn from_mints(base_mint: &Pubkey, quote_mint: &Pubkey) -> Self {
+        Self::new(&program::program_id(), base_mint, quote_mint)
+    }
+
+    /// Whether this is the unset placeholder
+    pub fn is_unset(&self) -> bool {
+        self.0 == Pubkey::default()
+    }
 }
 
-/// Order type enumeration - matches contract exactly
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
-#[repr(u8)]
-pub enum OrderType {
-    Limit = 0,    // Limit order
-    Market = 1,   // Market order
-    PostOnly = 2, // Post-only limit order
+impl fmt::Display for MarketId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
 }
 
-/// Order status enumeration - matches contract exactly
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
-#[repr(u8)]
-pub enum OrderStatus {
-    Open = 0,        // Order is active
-    PartiallyFilled = 1, // Order is partially executed
-    Filled = 2,      // Order is completely executed
-    Cancelled = 3,   // Order is cancelled
-    Expired = 4,     // Order has expired
+impl FromStr for MarketId {
+    type Err = ClobError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Pubkey::from_str(s)
+            .map(MarketId)
+            .map_err(|_| ClobError::UnknownMarket(s.to_string()))
+    }
 }
 
-/// Self-trade prevention behavior - matches contract exactly
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
-#[repr(u8)]
-pub enum SelfTradeBehavior {
-    DecrementAndCancel = 0, // Cancel the smaller order
-    CancelProvide = 1,      // Cancel the resting order
-    CancelTake = 2,         // Cancel the incoming order
-    CancelBoth = 3,         // Cancel both orders
+impl Serialize for MarketId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
 }
 
-/// Time in force enumeration - matches contract exactly
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
-#[repr(u8)]
-pub enum TimeInForce {
-    GoodTillCancelled = 0, // GTC - remains until cancelled
-    ImmediateOrCancel = 1, // IOC - execute immediately or cancel
-    FillOrKill = 2,        // FOK - execute completely or cancel
-    GoodTillTime = 3,      // GTT - remains until expiry time
+impl<'de> Deserialize<'de> for MarketId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = <String as Deserialize>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for MarketId {
+    fn schema_name() -> String {
+        "MarketId".to_string()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        String::json_schema(gen)
+    }
 }
 
 /// Core order structure that mirrors the contract Order struct
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Order {
     /// Unique order ID
     pub order_id: u64,
+    /// Market the order trades on
+    #[serde(default)]
+    pub market_id: MarketId,
     /// Owner of the order
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub owner: Pubkey,
     /// Price in ticks
     pub price: u64,
@@ -87,8 +149,62 @@ pub struct Order {
     pub status: OrderStatus,
     /// Self-trade prevention mode
     pub self_trade_behavior: SelfTradeBehavior,
+    /// Self-match prevention group; the order is also kept from matching
+    /// orders of other owners that carry the same group
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prevention_group_id: Option<u32>,
     /// Time in force
     pub time_in_force: TimeInForce,
+    /// Market orders only: largest distance, in basis points, of the average
+    /// fill price from the best opposite price the order accepts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_slippage_bps: Option<u32>,
+    /// Value of the filled quantity at the prices it filled at
+    #[serde(default)]
+    pub filled_notional: Notional,
+    /// Volume-weighted average price of the fills; unset until the first one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avg_fill_price: Option<u64>,
+}
+
+impl Order {
+    /// Whether the order rests on the book for what it does not fill
+    pub fn can_rest(&self) -> bool {
+        self.order_type != OrderType::Market
+            && matches!(self.time_in_force, TimeInForce::GoodTillCancelled | TimeInForce::GoodTillTime)
+    }
+
+    /// Value of the unfilled quantity at the order's limit price
+    pub fn remaining_notional(&self) -> Notional {
+        Price(self.price).notional(Quantity(self.remaining_quantity))
+    }
+
+    /// Quantity filled so far, across every partial fill
+    pub fn filled_quantity(&self) -> u64 {
+        self.quantity - self.remaining_quantity
+    }
+
+    /// Take a fill of `quantity` at `price` off the remaining quantity and
+    /// into the average fill price, which rounds down to a native unit
+    pub fn record_fill(&mut self, price: u64, quantity: u64) {
+        self.remaining_quantity -= quantity;
+        self.filled_notional = Notional(self.filled_notional.0 + Price(price).notional(Quantity(quantity)).0);
+        let filled = self.filled_quantity();
+        self.avg_fill_price = (filled > 0).then(|| (self.filled_notional.0 / filled as u128) as u64);
+    }
+
+    /// The order as the program's `offchain_api` sees it
+    pub fn to_core(&self) -> svm_clob_core::Order {
+        svm_clob_core::Order {
+            order_id: self.order_id,
+            owner: self.owner.to_bytes(),
+            price: self.price,
+            quantity: self.quantity,
+            side: self.side,
+            order_type: self.order_type,
+            timestamp: self.timestamp,
+        }
+    }
 }
 
 /// OrderBook structure that mirrors the contract
@@ -120,6 +236,120 @@ pub struct OrderBook {
     pub is_paused: bool,
 }
 
+/// How a match splits an incoming order between resting orders at one price
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AllocationMode {
+    /// Oldest order first
+    #[default]
+    PriceTime,
+    /// In proportion to resting size; rounding remainders go oldest first
+    ProRata,
+    /// Largest order first, oldest first among equal sizes
+    SizeTime,
+}
+
+/// What a paused engine does with the order commands it receives
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum PauseMode {
+    /// Hold up to `max_buffered` commands and run them in arrival order on
+    /// resume; commands beyond that are refused with `EnginePaused`
+    Buffer { max_buffered: usize },
+    /// Refuse every command with `EnginePaused`
+    Reject,
+}
+
+/// Whether the engine is paused, and how many commands wait for it to resume
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EnginePause {
+    /// Mode of the pause in effect; unset while the engine matches
+    pub mode: Option<PauseMode>,
+    /// Commands held for the resume
+    pub buffered: usize,
+    /// When the engine was paused (unix seconds)
+    pub since: Option<i64>,
+}
+
+/// Market maker protection of one maker: once its maker fills within the
+/// rolling window reach any limit set, the engine pulls its resting orders
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MmpConfig {
+    /// Window fills are counted over, in milliseconds
+    pub window_ms: u64,
+    /// Number of maker fills
+    #[serde(default)]
+    pub max_fills: Option<u64>,
+    /// Base quantity filled, bought and sold alike
+    #[serde(default)]
+    pub max_quantity: Option<u64>,
+    /// Base quantity bought less sold, either way
+    #[serde(default)]
+    pub max_delta: Option<u64>,
+    /// How long after a trip the maker's orders that could rest are refused
+    /// with `MmpFrozen`, in milliseconds
+    #[serde(default)]
+    pub freeze_ms: u64,
+}
+
+/// Delay the gateway holds orders that only take for before they reach the
+/// engine, drawn uniformly from `min_delay_us..=max_delay_us`; equal bounds
+/// give a fixed delay
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpeedBump {
+    #[serde(default)]
+    pub min_delay_us: u64,
+    pub max_delay_us: u64,
+}
+
+impl SpeedBump {
+    /// Longest delay that can be set: one second
+    pub const MAX_DELAY_US: u64 = 1_000_000;
+
+    /// Whether the bounds are in order and within `MAX_DELAY_US`
+    pub fn is_valid(&self) -> bool {
+        self.min_delay_us <= self.max_delay_us && self.max_delay_us <= Self::MAX_DELAY_US
+    }
+}
+
+impl MmpConfig {
+    /// Whether the window is not empty and sets at least one limit, none of
+    /// them zero
+    pub fn is_valid(&self) -> bool {
+        let limits = [self.max_fills, self.max_quantity, self.max_delta];
+        self.window_ms > 0 && limits.iter().any(Option::is_some) && !limits.contains(&Some(0))
+    }
+}
+
+/// Protection limit a maker's fills reached
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum MmpLimit {
+    Fills,
+    Quantity,
+    Delta,
+}
+
+/// Market maker protection of one maker and its fills in the current window
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MmpStatus {
+    pub user: Pubkey,
+    pub config: MmpConfig,
+    /// Maker fills in the window
+    pub fills: u64,
+    /// Base quantity of those fills
+    pub quantity: u64,
+    /// Base quantity bought less sold by those fills
+    pub delta: i128,
+    /// Orders that could rest are refused until then (unix milliseconds)
+    pub frozen_until_ms: Option<i64>,
+    /// Times the protection pulled the maker's orders since it was set
+    pub trips: u64,
+    /// Limit reached by the last trip
+    pub last_trip: Option<MmpLimit>,
+}
+
 /// User account structure that mirrors the contract
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct UserAccount {
@@ -131,6 +361,12 @@ pub struct UserAccount {
     pub total_orders_placed: u64,
     /// Total volume traded by user
     pub total_volume_traded: u64,
+    /// Base tokens deposited and not locked
+    #[serde(default)]
+    pub base_token_balance: u64,
+    /// Quote tokens deposited and not locked
+    #[serde(default)]
+    pub quote_token_balance: u64,
     /// Whether account is initialized
     pub is_initialized: bool,
 }
@@ -148,7 +384,15 @@ pub struct PriceLevel {
 
 /// Trade execution result
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct TradeExecution {
+    /// Engine-assigned ID, unique and increasing across every fill; 0 on
+    /// trades recorded before IDs were assigned
+    #[serde(default)]
+    pub trade_id: u64,
+    /// Market the trade executed on
+    #[serde(default)]
+    pub market_id: MarketId,
     /// Maker order ID
     pub maker_order_id: u64,
     /// Taker order ID  
@@ -161,16 +405,98 @@ pub struct TradeExecution {
     pub timestamp: i64,
     /// Maker side
     pub maker_side: OrderSide,
+    /// When the engine received the taker order, in microseconds since the
+    /// epoch; 0 when not recorded
+    #[serde(default)]
+    pub received_at_us: i64,
+    /// When the engine matched this fill, in microseconds since the epoch;
+    /// 0 when not recorded
+    #[serde(default)]
+    pub matched_at_us: i64,
+    /// Version of the trade record: 0 as matched, raised by every adjustment
+    #[serde(default)]
+    pub version: u32,
+    /// Whether the trade stands as matched, was repriced or was busted
+    #[serde(default)]
+    pub status: TradeStatus,
+}
+
+/// Standing of an executed trade after operator adjustments
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum TradeStatus {
+    #[default]
+    Active = 0,    // Stands as matched
+    Corrected = 1, // Repriced by an operator; `price` is the corrected one
+    Busted = 2,    // Cancelled by an operator; it counts as never having taken place
+}
+
+impl TryFrom<u8> for TradeStatus {
+    type Error = InvalidDiscriminant;
+
+    fn try_from(value: u8) -> std::result::Result<Self, Self::Error> {
+        match value {
+            0 => Ok(TradeStatus::Active),
+            1 => Ok(TradeStatus::Corrected),
+            2 => Ok(TradeStatus::Busted),
+            _ => Err(InvalidDiscriminant(value)),
+        }
+    }
+}
+
+impl TradeExecution {
+    /// Quote amount exchanged for the traded quantity
+    pub fn notional(&self) -> Notional {
+        Price(self.price).notional(Quantity(self.quantity))
+    }
+
+    /// Microseconds from the engine receiving the taker order to this fill,
+    /// when both were recorded
+    pub fn match_latency_us(&self) -> Option<i64> {
+        (self.received_at_us > 0 && self.matched_at_us > 0).then(|| self.matched_at_us - self.received_at_us)
+    }
+
+    /// Quantity that stands; none once the trade is busted
+    pub fn effective_quantity(&self) -> u64 {
+        match self.status {
+            TradeStatus::Busted => 0,
+            TradeStatus::Active | TradeStatus::Corrected => self.quantity,
+        }
+    }
+
+    /// Base and quote the taker owes the maker to turn the settlement of
+    /// `previous` into that of this version, negative when the maker owes
+    /// the taker; fails when an amount does not fit an `i64`
+    pub fn settlement_delta(&self, previous: &TradeExecution) -> ClobResult<(i64, i64)> {
+        // The maker gives base and is paid quote when it sold
+        let sign: i128 = match self.maker_side {
+            OrderSide::Ask => 1,
+            OrderSide::Bid => -1,
+        };
+        let (quantity, previous_quantity) = (self.effective_quantity() as i128, previous.effective_quantity() as i128);
+        let base = sign * (previous_quantity - quantity);
+        let quote = sign * (quantity * self.price as i128 - previous_quantity * previous.price as i128);
+        let fit = |amount: i128| {
+            i64::try_from(amount).map_err(|_| ClobError::TradeNotAdjustable(format!("{} does not fit a settlement", amount)))
+        };
+        Ok((fit(base)?, fit(quote)?))
+    }
 }
 
 /// Order book snapshot for API responses
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct OrderBookSnapshot {
+    /// Market the book belongs to
+    #[serde(default)]
+    pub market_id: MarketId,
     /// Bid price levels (price, quantity)
     pub bids: Vec<(u64, u64)>,
     /// Ask price levels (price, quantity)
     pub asks: Vec<(u64, u64)>,
-    /// Sequence number for ordering updates
+    /// Sequence of the last journal entry the book reflects
     pub sequence_number: u64,
     /// Timestamp of the snapshot
     pub timestamp: i64,
@@ -178,34 +504,1573 @@ pub struct OrderBookSnapshot {
 
 /// Market data update for WebSocket feeds
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct MarketDataUpdate {
+    /// Market the update concerns
+    pub market_id: MarketId,
     /// Update type
     pub update_type: MarketDataUpdateType,
     /// Updated order book snapshot (optional)
     pub order_book: Option<OrderBookSnapshot>,
+    /// Changes since the previous book (optional)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub book_delta: Option<BookDelta>,
     /// Trade execution (optional)
     pub trade: Option<TradeExecution>,
     /// Updated order (optional)
     pub order: Option<Order>,
+    /// Why `order` expired, when it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiry_reason: Option<ExpiryReason>,
+    /// Order that `order` replaced, when a modification placed it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaced_order_id: Option<u64>,
+    /// Protection limit whose breach pulled `order`, when it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mmp_limit: Option<MmpLimit>,
+    /// Place of a resting order in its price level's queue (optional)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_position: Option<QueuePosition>,
+    /// Owners of the maker and taker orders of `trade`, on drop copy only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    pub maker: Option<Pubkey>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    pub taker: Option<Pubkey>,
+    /// Position of the update in the WebSocket server's stream, which a
+    /// resumed session replays from; absent on frames sent outside the
+    /// stream, such as the snapshot answering a subscription and conflated
+    /// book frames
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u64>,
     /// Timestamp of the update
     pub timestamp: i64,
 }
 
 /// Market data update types
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum MarketDataUpdateType {
     OrderBookUpdate,
+    OrderBookDelta,
     TradeExecution,
     OrderUpdate,
+    /// New figures of `queue_position`, sent to its owner's `UserOrders`
+    QueuePosition,
+}
+
+/// Place of a resting order in the queue of its price level
+///
+/// Orders of a level fill in arrival order, so everything resting ahead of
+/// the order at its price fills before it does; orders at better prices
+/// fill first as well and are not counted. The figures are exact for the
+/// matcher's book when taken and change as orders ahead fill, leave or are
+/// replaced.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct QueuePosition {
+    pub order_id: u64,
+    #[serde(default)]
+    pub market_id: MarketId,
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
+    pub owner: Pubkey,
+    pub side: OrderSide,
+    pub price: u64,
+    pub remaining_quantity: u64,
+    /// Orders resting ahead of this one at its price
+    pub orders_ahead: u32,
+    /// Remaining quantity of those orders
+    pub quantity_ahead: u64,
+    /// Remaining quantity of the whole level, this order's included
+    pub level_quantity: u64,
 }
 
-/// Market statistics for API responses
+/// Market statistics for API responses; the flat 24h fields repeat
+/// `window_24h` for clients that predate the windows
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MarketStats {
     pub last_price: Option<u64>,
     pub volume_24h: u64,
     pub high_24h: Option<u64>,
     pub low_24h: Option<u64>,
+    #[serde(default)]
+    pub window_1h: WindowStats,
+    #[serde(default)]
+    pub window_24h: WindowStats,
+}
+
+impl MarketStats {
+    /// Stats from the last trade price and the rolling windows
+    pub fn new(last_price: Option<u64>, window_1h: WindowStats, window_24h: WindowStats) -> Self {
+        Self {
+            last_price,
+            volume_24h: window_24h.volume.0,
+            high_24h: window_24h.high.map(|price| price.0),
+            low_24h: window_24h.low.map(|price| price.0),
+            window_1h,
+            window_24h,
+        }
+    }
+}
+
+/// Market listing for API responses
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MarketInfo {
+    pub market_id: MarketId,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub tick_size: u64,
+    pub min_order_size: u64,
+    /// Whether `status` is `Paused`, kept for clients that predate it
+    pub is_paused: bool,
+    #[serde(default)]
+    pub status: MarketStatus,
+    /// Longest an order may rest; unlimited when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resting_lifetime: Option<RestingLifetime>,
+    /// Order types and times in force the market accepts; both lists are
+    /// empty from servers that predate them
+    #[serde(flatten)]
+    pub accepted: AcceptedOrderTypes,
+    #[serde(flatten)]
+    pub metadata: MarketMetadata,
+}
+
+/// Order types and times in force a market accepts, as listed to clients
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct AcceptedOrderTypes {
+    #[serde(default)]
+    pub order_types: Vec<OrderType>,
+    #[serde(default)]
+    pub time_in_force: Vec<TimeInForce>,
+}
+
+impl From<OrderTypeWhitelist> for AcceptedOrderTypes {
+    fn from(whitelist: OrderTypeWhitelist) -> Self {
+        Self {
+            order_types: OrderType::ALL.into_iter().filter(|&order_type| whitelist.accepts_order_type(order_type)).collect(),
+            time_in_force: TimeInForce::ALL
+                .into_iter()
+                .filter(|&time_in_force| whitelist.accepts_time_in_force(time_in_force))
+                .collect(),
+        }
+    }
+}
+
+/// Display metadata of a market: token symbols and mint decimals
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct MarketMetadata {
+    /// Base token symbol, e.g. `SOL`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_symbol: Option<String>,
+    /// Quote token symbol, e.g. `USDC`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quote_symbol: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_decimals: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quote_decimals: Option<u8>,
+}
+
+impl MarketMetadata {
+    /// `BASE/QUOTE`, when both symbols are known
+    pub fn symbol(&self) -> Option<String> {
+        Some(format!("{}/{}", self.base_symbol.as_ref()?, self.quote_symbol.as_ref()?))
+    }
+
+    /// Units to render prices and sizes in, when both decimals are known
+    pub fn units(&self, tick_size: u64) -> Option<MarketUnits> {
+        Some(MarketUnits {
+            base_decimals: self.base_decimals?,
+            quote_decimals: self.quote_decimals?,
+            tick_size,
+            lot_size: 1,
+        })
+    }
+}
+
+/// On-chain deposit observed by the indexer
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DepositRecord {
+    /// Transaction signature
+    pub signature: String,
+    /// Position of the event within the transaction logs
+    pub event_index: u32,
+    /// Slot the transaction landed in
+    pub slot: u64,
+    /// Owner of the credited user account: a wallet or a sub-account
+    pub user: Pubkey,
+    /// Token mint deposited
+    pub mint: Pubkey,
+    /// Amount in native token units
+    pub amount: u64,
+    /// On-chain timestamp
+    pub timestamp: i64,
+}
+
+/// On-chain withdrawal observed by the indexer
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WithdrawalRecord {
+    /// Transaction signature
+    pub signature: String,
+    /// Position of the event within the transaction logs
+    pub event_index: u32,
+    /// Slot the transaction landed in
+    pub slot: u64,
+    /// Owner of the debited user account: a wallet or a sub-account
+    pub user: Pubkey,
+    /// Token mint withdrawn
+    pub mint: Pubkey,
+    /// Amount in native token units
+    pub amount: u64,
+    /// On-chain timestamp
+    pub timestamp: i64,
+}
+
+/// Where a plain transfer into a vault is on its way to being credited
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum VaultTransferStatus {
+    Pending = 0,   // Seen; its transaction is not finalized yet
+    Ready = 1,     // Finalized and creditable; waiting for the settler
+    Submitted = 2, // `credit_transfer` sent by the settler
+    Credited = 3,  // `TransferCredited` read from chain
+    Rejected = 4,  // Not creditable; see the reason
+}
+
+impl TryFrom<u8> for VaultTransferStatus {
+    type Error = InvalidDiscriminant;
+
+    fn try_from(value: u8) -> std::result::Result<Self, Self::Error> {
+        match value {
+            0 => Ok(VaultTransferStatus::Pending),
+            1 => Ok(VaultTransferStatus::Ready),
+            2 => Ok(VaultTransferStatus::Submitted),
+            3 => Ok(VaultTransferStatus::Credited),
+            4 => Ok(VaultTransferStatus::Rejected),
+            _ => Err(InvalidDiscriminant(value)),
+        }
+    }
+}
+
+/// SPL transfer into one of the program's vaults made outside `deposit`,
+/// detected by the indexer and credited to the sender's user account by the
+/// orderbook authority once it is finalized
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VaultTransfer {
+    /// Signature of the transaction holding the transfer
+    pub signature: String,
+    /// Top-level instruction of the transfer in the transaction
+    pub instruction_index: u8,
+    /// Slot the transaction landed in
+    pub slot: u64,
+    pub mint: Pubkey,
+    /// Token account the tokens came from
+    pub source: Pubkey,
+    /// Wallet that owns `source` and signed the transfer; its own user
+    /// account is credited
+    pub sender: Pubkey,
+    /// Amount in native token units
+    pub amount: u64,
+    pub status: VaultTransferStatus,
+    /// Confirmations of the transaction's block when last polled; `None`
+    /// once it is finalized
+    pub confirmations: Option<u64>,
+    /// Why the transfer cannot be credited
+    pub reason: Option<String>,
+    /// `credit_transfer` transaction, once sent
+    pub credit_signature: Option<String>,
+    pub detected_at: i64,
+    pub updated_at: i64,
+}
+
+/// Sub-account of a wallet, opened on chain and observed by the indexer
+///
+/// A sub-account trades as its own owner key, with its own user account,
+/// balances and orders; its wallet signs for it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SubAccount {
+    /// Key the sub-account trades as
+    pub owner: Pubkey,
+    pub wallet: Pubkey,
+    /// Index among the wallet's sub-accounts, from 1
+    pub index: u16,
+    pub name: String,
+    /// Transaction that opened it
+    pub signature: String,
+    /// On-chain timestamp
+    pub created_at: i64,
+}
+
+/// On-chain transfer between two user accounts of one wallet observed by the
+/// indexer
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SubAccountTransferRecord {
+    /// Transaction signature
+    pub signature: String,
+    /// Position of the event within the transaction logs
+    pub event_index: u32,
+    /// Slot the transaction landed in
+    pub slot: u64,
+    pub wallet: Pubkey,
+    /// Owners of the user accounts debited and credited
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub base_amount: u64,
+    pub quote_amount: u64,
+    /// On-chain timestamp
+    pub timestamp: i64,
+}
+
+/// On-chain change of a user account's self-match prevention group observed
+/// by the indexer
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PreventionGroupRecord {
+    /// Transaction signature
+    pub signature: String,
+    /// Position of the event within the transaction logs
+    pub event_index: u32,
+    /// Slot the transaction landed in
+    pub slot: u64,
+    /// Market whose authority vouched for the group
+    pub market_id: MarketId,
+    /// Owner of the user account
+    pub owner: Pubkey,
+    /// 0 when the account left its group
+    pub group: u32,
+    /// On-chain timestamp
+    pub timestamp: i64,
+}
+
+/// On-chain trade settlement observed by the indexer
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SettledTradeRecord {
+    /// Transaction signature
+    pub signature: String,
+    /// Position of the event within the transaction logs
+    pub event_index: u32,
+    /// Slot the transaction landed in
+    pub slot: u64,
+    /// Taker order ID
+    pub taker_order_id: u64,
+    /// Maker order ID
+    pub maker_order_id: u64,
+    /// Taker wallet
+    pub taker: Pubkey,
+    /// Maker wallet
+    pub maker: Pubkey,
+    /// Execution price
+    pub price: u64,
+    /// Execution quantity
+    pub quantity: u64,
+    /// On-chain timestamp
+    pub timestamp: i64,
+}
+
+/// How final a program transaction seen by the indexer is
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum ChainStatus {
+    Pending = 0,   // Processed by a leader; a fork may still drop it
+    Confirmed = 1, // Voted on by a supermajority of stake
+    Finalized = 2, // Rooted; can no longer be rolled back
+    Failed = 3,    // Landed with an error; its records were rolled back
+    Dropped = 4,   // Left out of the finalized chain; its records were rolled back
+}
+
+impl ChainStatus {
+    /// Whether the transaction's records are in storage
+    pub fn is_live(self) -> bool {
+        matches!(self, ChainStatus::Pending | ChainStatus::Confirmed | ChainStatus::Finalized)
+    }
+}
+
+/// Program transaction the indexer stored records of, followed until it is
+/// finalized or rolled back
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChainTransaction {
+    /// Transaction signature
+    pub signature: String,
+    /// Slot the transaction was last seen in
+    pub slot: u64,
+    pub status: ChainStatus,
+    /// Error the transaction failed with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Engine journal entry
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JournalEntry {
+    /// Gap-free sequence assigned by storage
+    pub sequence: u64,
+    /// Timestamp when the entry was appended
+    pub timestamp: i64,
+    /// Recorded engine event
+    pub event: JournalEvent,
+    /// W3C traceparent of the request that produced the event
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_context: Option<String>,
+    /// `X-Request-Id` of the client call that produced the event
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// Resting orders of a book after a journal entry
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BookCheckpoint {
+    pub market_id: MarketId,
+    /// Last journal entry the book reflects
+    pub journal_sequence: u64,
+    /// Earliest arrival first, so adding them back in order keeps time priority
+    pub orders: Vec<Order>,
+    pub timestamp: i64,
+}
+
+/// Engine events recorded in the journal, in processing order
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", content = "data")]
+pub enum JournalEvent {
+    /// Order accepted by the engine, as submitted (before matching)
+    OrderPlaced { order: Order },
+    /// Resting order cancelled
+    OrderCancelled { order_id: u64 },
+    /// Resting order replaced by a modified copy
+    OrderReplaced { original_order_id: u64, order: Order },
+    /// Resting order taken off the book by the engine when it expired
+    OrderExpired { order_id: u64, reason: ExpiryReason },
+    /// Fill produced by matching
+    TradeExecuted {
+        trade: TradeExecution,
+        maker: Pubkey,
+        taker: Pubkey,
+    },
+    /// Order IDs up to `through` taken by the engine before handing them out
+    OrderIdsReserved { through: u64 },
+    /// Resting orders of `owner` pulled by market maker protection after
+    /// its fills reached `limit`
+    MmpTriggered {
+        owner: Pubkey,
+        limit: MmpLimit,
+        order_ids: Vec<u64>,
+    },
+    /// Executed trade busted or repriced by an operator; `trade` is its new
+    /// version and `previous_price` the price of the version it replaces.
+    /// The entry's sequence is the adjustment's ID.
+    TradeAdjusted {
+        trade: TradeExecution,
+        maker: Pubkey,
+        taker: Pubkey,
+        /// Journal sequence of the `TradeExecuted` entry
+        trade_sequence: u64,
+        previous_price: u64,
+        reason: String,
+        actor: String,
+    },
+}
+
+/// Why the engine expired an order
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiryReason {
+    /// A good-till-time order reached its `expiry_timestamp`
+    GoodTillTime,
+    /// The order rested for the market's maximum lifetime
+    MaxLifetime,
+}
+
+/// Orders a market's maximum resting lifetime applies to
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LifetimeScope {
+    /// Good-till-cancelled orders only
+    #[default]
+    GoodTillCancelled,
+    /// Good-till-time orders as well, when their expiry lies beyond the lifetime
+    AllResting,
+}
+
+/// Longest an order may rest before the expiry sweep takes it off the book,
+/// counted from its placement; a replacement keeps the age of the order it
+/// replaced
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestingLifetime {
+    /// Seconds, e.g. 7_776_000 for 90 days
+    pub max_resting_secs: i64,
+    #[serde(default)]
+    pub scope: LifetimeScope,
+}
+
+impl RestingLifetime {
+    /// Time at which `order` has rested for the lifetime, when it applies to the order
+    pub fn deadline(&self, order: &Order) -> Option<i64> {
+        let applies = match order.time_in_force {
+            TimeInForce::GoodTillCancelled => true,
+            TimeInForce::GoodTillTime => self.scope == LifetimeScope::AllResting,
+            TimeInForce::ImmediateOrCancel | TimeInForce::FillOrKill => false,
+        };
+        applies.then(|| order.timestamp.saturating_add(self.max_resting_secs))
+    }
+}
+
+/// On-chain settlement state of a journaled trade
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SettlementStatus {
+    Submitted = 0, // Signed transaction sent, outcome unknown
+    Confirmed = 1, // Settlement transaction confirmed
+    Failed = 2,    // Retries exhausted
+}
+
+impl TryFrom<u8> for SettlementStatus {
+    type Error = InvalidDiscriminant;
+
+    fn try_from(value: u8) -> std::result::Result<Self, Self::Error> {
+        match value {
+            0 => Ok(SettlementStatus::Submitted),
+            1 => Ok(SettlementStatus::Confirmed),
+            2 => Ok(SettlementStatus::Failed),
+            _ => Err(InvalidDiscriminant(value)),
+        }
+    }
+}
+
+/// Settlement record linking a journaled trade to its transaction
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TradeSettlement {
+    /// Journal sequence of the `TradeExecuted` entry
+    pub journal_sequence: u64,
+    /// Maker order ID
+    pub maker_order_id: u64,
+    /// Taker order ID
+    pub taker_order_id: u64,
+    /// Settlement transaction signature
+    pub signature: Option<String>,
+    /// Settlement status
+    pub status: SettlementStatus,
+    /// Number of submission attempts
+    pub attempts: u32,
+    /// Last error, if any
+    pub error: Option<String>,
+    /// Version of the trade settled; later adjustments are reversed on chain
+    #[serde(default)]
+    pub version: u32,
+}
+
+/// State of a settlement batch in the dead letter queue
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum DeadLetterStatus {
+    Open = 0,         // Waiting for an operator
+    Resubmitting = 1, // Queued for the settler to submit again
+    Settled = 2,      // Settled by a resubmission
+    Discarded = 3,    // Dropped by an operator; its fills stay off chain
+}
+
+/// Trade of a dead-lettered settlement batch
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeadLetterTrade {
+    /// Journal sequence of the `TradeExecuted` entry
+    pub journal_sequence: u64,
+    pub trade: TradeExecution,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    /// `X-Request-Id` of the call that matched the trade
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// Settlement batch the settler gave up on, kept until an operator resubmits
+/// or discards it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeadLetter {
+    /// Assigned by storage
+    pub id: u64,
+    /// Journal sequences bounding the batch as it was dead-lettered
+    pub first_sequence: u64,
+    pub last_sequence: u64,
+    /// Trades to settle, as last edited by an operator
+    pub trades: Vec<DeadLetterTrade>,
+    pub status: DeadLetterStatus,
+    /// Why the last submission failed
+    pub reason: String,
+    /// Submission attempts, resubmissions included
+    pub attempts: u32,
+    /// Transaction of the resubmission that settled the batch
+    pub signature: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Where the on-chain side of a trade adjustment stands
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum ReversalStatus {
+    Pending = 0,     // Waiting for the settler to learn whether the trade settled
+    NotRequired = 1, // The trade never settled, or settles as adjusted
+    Submitted = 2,   // `adjust_settlement` sent by the settler
+    Reversed = 3,    // `SettlementAdjusted` read from chain
+    Failed = 4,      // Refused on chain, e.g. a party no longer holds what it gives back; see the error
+}
+
+impl TryFrom<u8> for ReversalStatus {
+    type Error = InvalidDiscriminant;
+
+    fn try_from(value: u8) -> std::result::Result<Self, Self::Error> {
+        match value {
+            0 => Ok(ReversalStatus::Pending),
+            1 => Ok(ReversalStatus::NotRequired),
+            2 => Ok(ReversalStatus::Submitted),
+            3 => Ok(ReversalStatus::Reversed),
+            4 => Ok(ReversalStatus::Failed),
+            _ => Err(InvalidDiscriminant(value)),
+        }
+    }
+}
+
+/// Bust or price correction of an executed trade by an operator, one per
+/// version of the trade after the first
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TradeAdjustment {
+    /// Journal sequence of the `TradeAdjusted` entry
+    pub adjustment_id: u64,
+    pub trade_id: u64,
+    pub market_id: MarketId,
+    /// Version of the trade the adjustment produced, from 1
+    pub version: u32,
+    /// `Busted` or `Corrected`
+    pub status: TradeStatus,
+    /// Journal sequence of the `TradeExecuted` entry
+    pub trade_sequence: u64,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub maker_order_id: u64,
+    pub taker_order_id: u64,
+    pub maker_side: OrderSide,
+    pub quantity: u64,
+    /// Price of the version the adjustment replaces
+    pub previous_price: u64,
+    /// Price of the adjusted version; unchanged by a bust
+    pub price: u64,
+    /// Base and quote the taker gives the maker to move a settlement of the
+    /// previous version to this one; negative the other way
+    pub base_to_maker: i64,
+    pub quote_to_maker: i64,
+    pub reason: String,
+    /// Operator who made the adjustment
+    pub actor: String,
+    pub reversal: ReversalStatus,
+    /// `adjust_settlement` transaction, once sent
+    pub reversal_signature: Option<String>,
+    /// Why the reversal failed
+    pub reversal_error: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// A trade as it stands, with the adjustments that made it so
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TradeHistory {
+    pub trade: TradeExecution,
+    /// Oldest first
+    pub adjustments: Vec<TradeAdjustment>,
+}
+
+/// Balances moved back on chain between the parties of an adjusted trade,
+/// observed by the indexer
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SettlementAdjustmentRecord {
+    /// Transaction signature
+    pub signature: String,
+    /// Position of the event within the transaction logs
+    pub event_index: u32,
+    /// Slot the transaction landed in
+    pub slot: u64,
+    pub adjustment_id: u64,
+    pub trade_id: u64,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    /// Base and quote moved from the taker to the maker; negative the other way
+    pub base_to_maker: i64,
+    pub quote_to_maker: i64,
+    /// On-chain timestamp
+    pub timestamp: i64,
+}
+
+/// Compressed receipt tree a market started appending its fills to,
+/// observed by the indexer
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReceiptTreeRecord {
+    pub merkle_tree: Pubkey,
+    pub orderbook: Pubkey,
+    pub max_depth: u32,
+    pub max_buffer_size: u32,
+    /// Merkle trees the market has used, this one included
+    pub generation: u32,
+    /// Transaction signature
+    pub signature: String,
+    /// Slot the transaction landed in
+    pub slot: u64,
+    /// On-chain timestamp
+    pub timestamp: i64,
+}
+
+/// One side of a settled fill, as a leaf of a market's compressed receipt
+/// tree, observed by the indexer
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CompressedReceipt {
+    pub merkle_tree: Pubkey,
+    /// Position of the receipt's leaf in `merkle_tree`
+    pub leaf_index: u64,
+    /// Transaction signature
+    pub signature: String,
+    /// Slot the transaction landed in
+    pub slot: u64,
+    pub orderbook: Pubkey,
+    pub owner: Pubkey,
+    /// The owner's order
+    pub order_id: u64,
+    pub counterparty_order_id: u64,
+    pub price: u64,
+    pub quantity: u64,
+    /// Settlement time on chain
+    pub timestamp: i64,
+    /// Side of the owner's order
+    pub side: OrderSide,
+    pub role: LiquidityRole,
+}
+
+impl CompressedReceipt {
+    /// The receipt at `leaf_index` of `merkle_tree`
+    pub fn new(merkle_tree: Pubkey, leaf_index: u64, signature: String, slot: u64, leaf: &FillReceiptLeaf) -> Self {
+        Self {
+            merkle_tree,
+            leaf_index,
+            signature,
+            slot,
+            orderbook: Pubkey::new_from_array(leaf.orderbook),
+            owner: Pubkey::new_from_array(leaf.owner),
+            order_id: leaf.order_id,
+            counterparty_order_id: leaf.counterparty_order_id,
+            price: leaf.price,
+            quantity: leaf.quantity,
+            timestamp: leaf.timestamp,
+            side: leaf.side,
+            role: if leaf.is_maker { LiquidityRole::Maker } else { LiquidityRole::Taker },
+        }
+    }
+
+    /// The receipt as the program hashed it into the tree
+    pub fn leaf(&self) -> FillReceiptLeaf {
+        FillReceiptLeaf {
+            orderbook: self.orderbook.to_bytes(),
+            owner: self.owner.to_bytes(),
+            order_id: self.order_id,
+            counterparty_order_id: self.counterparty_order_id,
+            price: self.price,
+            quantity: self.quantity,
+            timestamp: self.timestamp,
+            side: self.side,
+            is_maker: self.role == LiquidityRole::Maker,
+        }
+    }
+}
+
+/// Progress marker for background services (indexer, settler, ...)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Checkpoint {
+    /// Service name owning the checkpoint
+    pub service: String,
+    /// Monotonic position (slot, journal sequence, ...)
+    pub position: u64,
+    /// Opaque resume cursor (e.g. last processed signature)
+    pub cursor: Option<String>,
+}
+
+/// Expected on-chain `UserAccount` state derived from indexed events
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LedgerBalance {
+    /// Account owner
+    pub user: Pubkey,
+    /// Deposits - withdrawals +/- settled base quantities
+    pub base_balance: i64,
+    /// Deposits - withdrawals +/- settled quote amounts
+    pub quote_balance: i64,
+    /// Settled quantity as maker or taker
+    pub total_volume_traded: u64,
+    /// Accrued trading fees net of maker rebates, in quote units; not yet
+    /// collected on-chain, so not part of `quote_balance`
+    pub fees: i64,
+    /// Base credited by settled fills whose transaction is not finalized
+    /// yet; part of `base_balance`, but held from withdrawal
+    #[serde(default)]
+    pub base_held: u64,
+    /// Quote credited by settled fills whose transaction is not finalized
+    /// yet; part of `quote_balance`, but held from withdrawal
+    #[serde(default)]
+    pub quote_held: u64,
+}
+
+impl LedgerBalance {
+    /// Balance of a user with no recorded movements
+    pub fn empty(user: Pubkey) -> Self {
+        Self {
+            user,
+            base_balance: 0,
+            quote_balance: 0,
+            total_volume_traded: 0,
+            fees: 0,
+            base_held: 0,
+            quote_held: 0,
+        }
+    }
+}
+
+/// Side of a fill a participant was on
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum LiquidityRole {
+    Maker = 0, // Resting order that provided liquidity
+    Taker = 1, // Incoming order that removed liquidity
+}
+
+impl TryFrom<u8> for LiquidityRole {
+    type Error = InvalidDiscriminant;
+
+    fn try_from(value: u8) -> std::result::Result<Self, Self::Error> {
+        match value {
+            0 => Ok(LiquidityRole::Maker),
+            1 => Ok(LiquidityRole::Taker),
+            _ => Err(InvalidDiscriminant(value)),
+        }
+    }
+}
+
+/// Fee rates for users whose trailing 30-day volume reaches `min_volume`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FeeTier {
+    /// Base quantity traded in the trailing 30 days required for the tier
+    pub min_volume: u64,
+    /// Maker fee in basis points of notional; negative for a rebate
+    pub maker_fee_bps: i64,
+    /// Taker fee in basis points of notional
+    pub taker_fee_bps: i64,
+}
+
+/// Fee tier a user was assigned at the last nightly recomputation
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UserFeeTier {
+    pub user: Pubkey,
+    /// Base quantity traded, as maker or taker, in the 30 days before `computed_at`
+    pub volume_30d: u64,
+    /// Position of the tier in the schedule, lowest threshold first; unset
+    /// when the volume is below every tier, which pays nothing
+    pub tier: Option<u32>,
+    pub maker_fee_bps: i64,
+    pub taker_fee_bps: i64,
+    /// When the tier was assigned (unix seconds)
+    pub computed_at: i64,
+}
+
+/// Fee charged to one side of a fill
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FeeAccrual {
+    /// Journal sequence of the fill, or of the adjustment an accrual
+    /// compensates for
+    pub journal_sequence: u64,
+    pub user: Pubkey,
+    pub role: LiquidityRole,
+    pub price: u64,
+    pub quantity: u64,
+    /// Rate applied, from the user's tier
+    pub fee_bps: i64,
+    /// Fee in quote units; negative for a rebate
+    pub fee: i64,
+    /// Part of `fee` earmarked for the insurance fund; rebates earmark nothing
+    #[serde(default)]
+    pub insurance_contribution: i64,
+    /// Timestamp of the fill
+    pub timestamp: i64,
+    /// For an accrual that compensates a busted or repriced fill, the fill's
+    /// journal sequence; `quantity` is then what a bust takes back out of
+    /// the fill's volume, and `fee` and `insurance_contribution` the change
+    #[serde(default)]
+    pub adjusted_sequence: Option<u64>,
+}
+
+/// Fees of one user over a billing period
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FeeReport {
+    pub user: Pubkey,
+    /// Period bounds, start inclusive and end exclusive
+    pub period_start: i64,
+    pub period_end: i64,
+    /// Fills the user took part in
+    pub fills: u64,
+    pub maker_volume: u64,
+    pub taker_volume: u64,
+    pub maker_fees: i64,
+    pub taker_fees: i64,
+    /// `maker_fees + taker_fees`
+    pub total_fees: i64,
+}
+
+/// Movement of an insurance fund observed on chain
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum InsuranceFundEventKind {
+    Funded = 0, // `fund_insurance` paid tokens in
+    Drawn = 1,  // `draw_insurance` paid tokens out
+}
+
+/// Payment into or out of a market's insurance fund, recorded by the indexer
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InsuranceFundEvent {
+    /// Transaction signature
+    pub signature: String,
+    /// Position of the event within the transaction logs
+    pub event_index: u32,
+    /// Slot the transaction landed in
+    pub slot: u64,
+    /// Orderbook PDA of the market the fund belongs to
+    pub orderbook: Pubkey,
+    pub kind: InsuranceFundEventKind,
+    /// Funding wallet, or the token account a draw paid out to
+    pub account: Pubkey,
+    /// Amount in quote token units
+    pub amount: u64,
+    /// On-chain timestamp
+    pub timestamp: i64,
+}
+
+/// State of a market's insurance fund
+///
+/// The fee service earmarks a share of every fee as `accrued`; the operator
+/// pays it in on chain with `fund_insurance`, and `unfunded` is what has been
+/// earmarked but not paid in yet. Payments from anyone else also count as
+/// funding, so `unfunded` stops at zero.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InsuranceFund {
+    /// Orderbook PDA of the market
+    pub orderbook: Pubkey,
+    /// Fee contributions earmarked for the fund, in quote units
+    pub accrued: i64,
+    /// Total paid into the fund on chain
+    pub funded: u64,
+    /// Total drawn from the fund on chain
+    pub drawn: u64,
+    /// Held by the fund on chain, `funded - drawn`
+    pub balance: u64,
+    /// Earmarked but not yet paid in, `accrued - funded` floored at zero
+    pub unfunded: i64,
+}
+
+impl InsuranceFund {
+    /// Fund with `accrued` earmarked and `funded` and `drawn` moved on chain
+    pub fn new(orderbook: Pubkey, accrued: i64, funded: u64, drawn: u64) -> Self {
+        Self {
+            orderbook,
+            accrued,
+            funded,
+            drawn,
+            balance: funded.saturating_sub(drawn),
+            unfunded: (accrued as i128 - funded as i128).clamp(0, i64::MAX as i128) as i64,
+        }
+    }
+}
+
+/// Market abuse pattern flagged by trade surveillance
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum AlertKind {
+    WashTrading = 0,      // Accounts trading back and forth with each other
+    Spoofing = 1,         // Orders near the touch cancelled instead of filled
+    MomentumIgnition = 2, // Aggressive burst moving the price, then reversed
+}
+
+impl TryFrom<u8> for AlertKind {
+    type Error = InvalidDiscriminant;
+
+    fn try_from(value: u8) -> std::result::Result<Self, Self::Error> {
+        match value {
+            0 => Ok(AlertKind::WashTrading),
+            1 => Ok(AlertKind::Spoofing),
+            2 => Ok(AlertKind::MomentumIgnition),
+            _ => Err(InvalidDiscriminant(value)),
+        }
+    }
+}
+
+/// Scored surveillance finding over one journal window
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SurveillanceAlert {
+    /// Assigned by storage
+    pub id: u64,
+    pub kind: AlertKind,
+    /// Accounts involved, sorted
+    pub accounts: Vec<Pubkey>,
+    /// Confidence between 0 and 1
+    pub score: f64,
+    /// Journal timestamps bounding the window the pattern was found in
+    pub window_start: i64,
+    pub window_end: i64,
+    /// Journal sequences bounding the entries that contributed
+    pub first_sequence: u64,
+    pub last_sequence: u64,
+    /// Human-readable evidence
+    pub details: String,
+}
+
+/// Runtime setting changed through the admin API
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AdminAuditEntry {
+    /// Assigned by storage
+    pub id: u64,
+    /// Operator named by the request, `admin` when it names none
+    pub actor: String,
+    /// What was changed, e.g. `risk.user_limits.set`
+    pub action: String,
+    /// User or other object the change applies to, if not market-wide
+    pub target: Option<String>,
+    /// Setting before the change; `null` when there was none
+    pub before: serde_json::Value,
+    /// Setting after the change; `null` when it was removed
+    pub after: serde_json::Value,
+    pub timestamp: i64,
+}
+
+/// Why an operator suspended an owner or API key
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum SuspensionReason {
+    Abuse = 0,       // Abusive order flow, e.g. spam or manipulation
+    Compromised = 1, // Key or wallet believed to be in the wrong hands
+    Compliance = 2,  // Legal or compliance hold
+    Other = 3,       // Explained in the note
+}
+
+impl TryFrom<u8> for SuspensionReason {
+    type Error = InvalidDiscriminant;
+
+    fn try_from(value: u8) -> std::result::Result<Self, Self::Error> {
+        match value {
+            0 => Ok(SuspensionReason::Abuse),
+            1 => Ok(SuspensionReason::Compromised),
+            2 => Ok(SuspensionReason::Compliance),
+            3 => Ok(SuspensionReason::Other),
+            _ => Err(InvalidDiscriminant(value)),
+        }
+    }
+}
+
+/// Owner or API key a suspension applies to
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(tag = "kind", content = "id", rename_all = "snake_case")]
+pub enum SuspensionSubject {
+    /// Orders of this owner, and of its sub-accounts when it is a wallet
+    Owner(Pubkey),
+    /// Orders placed with this tenant API key, by key ID
+    ApiKey(String),
+}
+
+impl fmt::Display for SuspensionSubject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SuspensionSubject::Owner(owner) => write!(f, "owner {}", owner),
+            SuspensionSubject::ApiKey(key_id) => write!(f, "API key {}", key_id),
+        }
+    }
+}
+
+/// Suspension set through the admin API; new orders of its subject are
+/// refused with `Suspended` until it is lifted
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Suspension {
+    pub subject: SuspensionSubject,
+    pub reason: SuspensionReason,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Operator who set it
+    pub actor: String,
+    pub suspended_at: i64,
+}
+
+/// Mutating API call as answered
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ApiAuditEntry {
+    /// Assigned by storage
+    pub id: u64,
+    /// Operator of an admin call, or the owner(s) named in an order call;
+    /// `anonymous` when the call names nobody
+    pub actor: String,
+    /// HTTP method
+    pub method: String,
+    /// Route template, e.g. `/api/v1/orders/:order_id`
+    pub route: String,
+    /// Path as requested
+    pub path: String,
+    /// Request body; `null` when empty or not JSON
+    pub params: serde_json::Value,
+    /// HTTP status of the answer
+    pub status: u16,
+    /// Response body; `null` when empty or not JSON
+    pub result: serde_json::Value,
+    /// Time from receiving the call to answering it
+    pub latency_us: u64,
+    pub timestamp: i64,
+    /// `X-Request-Id` the call was answered with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// Filter of the API audit log
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ApiAuditQuery {
+    /// Only calls by this actor
+    pub actor: Option<String>,
+    /// Only calls at or after this time (unix seconds)
+    pub from: Option<i64>,
+    /// Only calls before this time (unix seconds)
+    pub until: Option<i64>,
+    /// Only the call answered with this `X-Request-Id`
+    pub request_id: Option<String>,
+}
+
+/// Organization integrating against the deployment, such as a frontend,
+/// identified by its API keys
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Tenant {
+    /// Chosen by the operator, e.g. `acme`
+    pub id: String,
+    pub name: String,
+    /// Requests per second across the tenant's keys, in place of the per-IP
+    /// limit; `None` leaves its requests under the per-IP limit
+    pub max_requests_per_second: Option<u32>,
+    /// Order placements per second across the tenant's keys, on top of the
+    /// per-owner limit; `None` leaves them unlimited
+    pub max_orders_per_second: Option<u32>,
+    /// Share of the fees on the tenant's flow owed to it, in basis points
+    pub fee_share_bps: u64,
+    /// Referral code of the tenant, unique across tenants
+    pub referral_code: Option<String>,
+    /// Refuse every call made with the tenant's keys
+    pub disabled: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// API key of a tenant; the key itself is only shown when issued
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TenantApiKey {
+    /// Public part of the key, before the `.`
+    pub key_id: String,
+    pub tenant_id: String,
+    /// SHA-256 of the whole key
+    #[serde(skip_serializing, default)]
+    pub key_hash: String,
+    pub label: Option<String>,
+    /// Calls made with the key trade in the paper sandbox, not the market
+    #[serde(default)]
+    pub paper: bool,
+    /// WebSocket feeds a connection made with the key may subscribe to
+    #[serde(default)]
+    pub entitlement: FeedEntitlement,
+    pub created_at: i64,
+    pub revoked_at: Option<i64>,
+}
+
+#[cfg(feature = "signing")]
+impl TenantApiKey {
+    /// SHA-256 of a whole API key, as stored in `key_hash`
+    pub fn hash(api_key: &str) -> String {
+        solana_sdk::hash::hash(api_key.as_bytes()).to_string()
+    }
+}
+
+/// Levels of WebSocket market data, each including the ones below it;
+/// connections without an API key get `PublicL2`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum FeedEntitlement {
+    #[default]
+    PublicL2 = 0,    // Books to `PUBLIC_BOOK_DEPTH` levels a side, trades and own orders
+    FullDepthL2 = 1, // Books at every level
+    L3 = 2,          // Every order of a market, without its owner (`OrderBookL3`)
+    DropCopy = 3,    // Every order and fill of a market with their owners (`DropCopy`, `AllMarkets`)
+}
+
+impl TryFrom<u8> for FeedEntitlement {
+    type Error = InvalidDiscriminant;
+
+    fn try_from(value: u8) -> std::result::Result<Self, Self::Error> {
+        match value {
+            0 => Ok(FeedEntitlement::PublicL2),
+            1 => Ok(FeedEntitlement::FullDepthL2),
+            2 => Ok(FeedEntitlement::L3),
+            3 => Ok(FeedEntitlement::DropCopy),
+            _ => Err(InvalidDiscriminant(value)),
+        }
+    }
+}
+
+/// Levels a side of the books served under `FeedEntitlement::PublicL2`
+pub const PUBLIC_BOOK_DEPTH: usize = 10;
+
+impl FeedEntitlement {
+    /// Deepest book levels a side the entitlement serves; `None` for all
+    pub fn book_depth(self) -> Option<usize> {
+        match self {
+            FeedEntitlement::PublicL2 => Some(PUBLIC_BOOK_DEPTH),
+            _ => None,
+        }
+    }
+}
+
+/// Calls made with one tenant's keys over one metering period
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct TenantUsage {
+    pub tenant_id: String,
+    /// Start of the period (unix seconds); periods last an hour
+    pub period_start: i64,
+    pub requests: u64,
+    /// Requests answered with a 4xx or 5xx status
+    pub errors: u64,
+    /// Requests refused by a rate limit
+    pub rate_limited: u64,
+    /// Orders accepted by the engine
+    pub orders_placed: u64,
+    /// Sum of `price * quantity` of the accepted orders
+    pub notional_placed: u64,
+}
+
+/// Length of a tenant usage period in seconds
+pub const TENANT_USAGE_PERIOD_SECS: i64 = 3600;
+
+impl TenantUsage {
+    /// Empty usage of `tenant_id` in the period containing `timestamp`
+    pub fn new(tenant_id: &str, timestamp: i64) -> Self {
+        Self {
+            tenant_id: tenant_id.to_string(),
+            period_start: timestamp.div_euclid(TENANT_USAGE_PERIOD_SECS) * TENANT_USAGE_PERIOD_SECS,
+            ..Self::default()
+        }
+    }
+
+    /// Add the counts of `other`, of the same tenant and period
+    pub fn merge(&mut self, other: &TenantUsage) {
+        self.requests += other.requests;
+        self.errors += other.errors;
+        self.rate_limited += other.rate_limited;
+        self.orders_placed += other.orders_placed;
+        self.notional_placed = self.notional_placed.saturating_add(other.notional_placed);
+    }
+}
+
+/// Liquidity incentive epoch, as closed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IncentiveEpoch {
+    /// Epoch bounds, start inclusive and end exclusive
+    pub epoch_start: i64,
+    pub epoch_end: i64,
+    /// Largest distance from mid of an order that earns credit
+    pub band_bps: u64,
+    /// Reward shared between the epoch's makers, in reward token base units
+    pub reward_pool: u64,
+    /// Makers credited with any depth
+    pub makers: u32,
+    /// Depth-seconds of every maker
+    pub total_depth_seconds: u64,
+    /// Last journal entry of the epoch
+    pub last_sequence: u64,
+}
+
+/// Quoting credit and reward of one maker over an incentive epoch
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IncentiveReport {
+    pub epoch_start: i64,
+    pub epoch_end: i64,
+    pub maker: Pubkey,
+    /// Base quantity resting within the band on each side, integrated over
+    /// the epoch in quantity-seconds
+    pub bid_depth_seconds: u64,
+    pub ask_depth_seconds: u64,
+    /// Quantity quoted within the band on average over the epoch
+    pub average_depth: u64,
+    /// Share of the epoch's depth-seconds, between 0 and 1
+    pub share: f64,
+    /// Share of the reward pool, rounded down, in reward token base units
+    pub reward: u64,
+}
+
+/// Filter of incentive reports
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct IncentiveQuery {
+    /// Only the epoch starting at this time (unix seconds)
+    pub epoch_start: Option<i64>,
+    /// Only this maker
+    pub maker: Option<Pubkey>,
+}
+
+/// Quoting obligations of a designated market maker
+///
+/// A sample of the book is compliant when the maker quotes both sides, its
+/// best bid and ask are at most `max_spread_bps` of their mid apart, and it
+/// rests at least `min_depth` on each side within half that spread of the
+/// mid. The maker meets its obligations over a period when at least
+/// `min_uptime_bps` of the period's samples are compliant.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MarketMakerObligation {
+    pub maker: Pubkey,
+    /// Operator's name for the maker
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub max_spread_bps: u64,
+    /// Base quantity, in native units
+    pub min_depth: u64,
+    /// Share of samples to be compliant in, in basis points (9500 = 95%)
+    pub min_uptime_bps: u64,
+    /// Samples before this time are not held against the maker
+    pub registered_at: i64,
+    pub updated_at: i64,
+}
+
+impl MarketMakerObligation {
+    /// Evaluate the maker against one sample of the book, `orders` being
+    /// every resting order of the market
+    pub fn sample(&self, orders: &[Order]) -> QuotingSample {
+        let own = || orders.iter().filter(|order| order.owner == self.maker && order.remaining_quantity > 0);
+        let best_bid = own().filter(|order| order.side == OrderSide::Bid).map(|order| order.price).max();
+        let best_ask = own().filter(|order| order.side == OrderSide::Ask).map(|order| order.price).min();
+        let (Some(bid), Some(ask)) = (best_bid, best_ask) else {
+            return QuotingSample::default();
+        };
+        let mid = (bid as u128 + ask as u128) / 2;
+        let spread_bps = (ask.saturating_sub(bid) as u128 * 10_000 / mid.max(1)) as u64;
+        // Depth counts within half the allowed spread of the maker's mid
+        let within = |price: u64| price.abs_diff(mid as u64) as u128 * 20_000 <= self.max_spread_bps as u128 * mid;
+        let depth = |side: OrderSide| {
+            own()
+                .filter(|order| order.side == side && within(order.price))
+                .fold(0u64, |depth, order| depth.saturating_add(order.remaining_quantity))
+        };
+        let (bid_depth, ask_depth) = (depth(OrderSide::Bid), depth(OrderSide::Ask));
+        QuotingSample {
+            spread_bps: Some(spread_bps),
+            bid_depth,
+            ask_depth,
+            compliant: spread_bps <= self.max_spread_bps && bid_depth >= self.min_depth && ask_depth >= self.min_depth,
+        }
+    }
+}
+
+/// A maker's quotes in one sample of the book
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuotingSample {
+    /// Spread between the maker's best bid and ask in basis points of their
+    /// mid; `None` unless it quotes both sides
+    pub spread_bps: Option<u64>,
+    /// Quantity rested within half the allowed spread of the maker's mid
+    pub bid_depth: u64,
+    pub ask_depth: u64,
+    pub compliant: bool,
+}
+
+/// Period over which designated makers were scored, as closed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ObligationPeriod {
+    /// Period bounds, start inclusive and end exclusive
+    pub period_start: i64,
+    pub period_end: i64,
+    /// Seconds between two samples of the book
+    pub sample_secs: i64,
+    /// Makers scored
+    pub makers: u32,
+    /// Last journal entry of the period
+    pub last_sequence: u64,
+}
+
+/// How well a designated maker met its obligations over a period
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MarketMakerScorecard {
+    pub period_start: i64,
+    pub period_end: i64,
+    pub maker: Pubkey,
+    /// Obligations the maker was scored against, as registered when the
+    /// period closed
+    pub max_spread_bps: u64,
+    pub min_depth: u64,
+    pub min_uptime_bps: u64,
+    /// Samples taken while the maker was registered
+    pub samples: u32,
+    /// Samples in which the maker quoted both sides
+    pub two_sided_samples: u32,
+    pub compliant_samples: u32,
+    /// Share of samples compliant, in basis points
+    pub uptime_bps: u64,
+    /// Spread averaged over the two-sided samples
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub average_spread_bps: Option<u64>,
+    /// Depth averaged over every sample
+    pub average_bid_depth: u64,
+    pub average_ask_depth: u64,
+    pub meets_obligations: bool,
+}
+
+/// Filter of market maker scorecards
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ScorecardQuery {
+    /// Only the period starting at this time (unix seconds)
+    pub period_start: Option<i64>,
+    /// Only this maker
+    pub maker: Option<Pubkey>,
+}
+
+/// What a journal entry did to the order it is mapped to
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum SequenceEvent {
+    Placed = 0,   // Order accepted by the engine
+    Replaced = 1, // Order placed as the modified copy of another
+    Filled = 2,   // Order took part in a fill
+    Adjusted = 3, // A fill of the order was busted or repriced by an operator
+}
+
+impl TryFrom<u8> for SequenceEvent {
+    type Error = InvalidDiscriminant;
+
+    fn try_from(value: u8) -> std::result::Result<Self, Self::Error> {
+        match value {
+            0 => Ok(SequenceEvent::Placed),
+            1 => Ok(SequenceEvent::Replaced),
+            2 => Ok(SequenceEvent::Filled),
+            3 => Ok(SequenceEvent::Adjusted),
+            _ => Err(InvalidDiscriminant(value)),
+        }
+    }
+}
+
+/// Link between a journal entry, an order it concerns and that order's
+/// on-chain accounts and settlement
+///
+/// Engine order IDs are the IDs settled on chain: `execute_trade` carries them
+/// and `TradeSettled` events emit them unchanged. The journal sequence orders
+/// every command and fill, and is what settlements are keyed by.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SequenceMapping {
+    pub journal_sequence: u64,
+    pub order_id: u64,
+    pub event: SequenceEvent,
+    /// Fill and the order's side of it; fills only
+    pub trade_id: Option<u64>,
+    pub role: Option<LiquidityRole>,
+    pub owner: Pubkey,
+    /// Orderbook PDA
+    pub market_id: MarketId,
+    /// User account PDA of `owner`
+    pub user_account: Pubkey,
+    /// Settlement transaction of the fill, once the settler submitted it
+    #[serde(default)]
+    pub signature: Option<String>,
+    #[serde(default)]
+    pub settlement_status: Option<SettlementStatus>,
+    /// Commitment of the settlement transaction, once the indexer saw it
+    #[serde(default)]
+    pub chain_status: Option<ChainStatus>,
+    /// Timestamp of the journal entry
+    pub timestamp: i64,
+}
+
+impl SequenceMapping {
+    /// Mappings of one journal entry, without settlement; cancellations map to
+    /// nothing, since the placement of the cancelled order is already mapped
+    pub fn from_journal_entry(program_id: &Pubkey, entry: &JournalEntry) -> Vec<SequenceMapping> {
+        let mapping = |order_id, event, trade_id, role, owner: Pubkey, market_id| SequenceMapping {
+            journal_sequence: entry.sequence,
+            order_id,
+            event,
+            trade_id,
+            role,
+            owner,
+            market_id,
+            user_account: program::user_account_address(program_id, &owner).0,
+            signature: None,
+            settlement_status: None,
+            chain_status: None,
+            timestamp: entry.timestamp,
+        };
+        match &entry.event {
+            JournalEvent::OrderPlaced { order } => vec![mapping(
+                order.order_id,
+                SequenceEvent::Placed,
+                None,
+                None,
+                order.owner,
+                order.market_id,
+            )],
+            JournalEvent::OrderReplaced { order, .. } => vec![mapping(
+                order.order_id,
+                SequenceEvent::Replaced,
+                None,
+                None,
+                order.owner,
+                order.market_id,
+            )],
+            JournalEvent::OrderCancelled { .. }
+            | JournalEvent::OrderExpired { .. }
+            | JournalEvent::OrderIdsReserved { .. }
+            | JournalEvent::MmpTriggered { .. } => Vec::new(),
+            JournalEvent::TradeExecuted { trade, maker, taker } | JournalEvent::TradeAdjusted { trade, maker, taker, .. } => {
+                let event = match entry.event {
+                    JournalEvent::TradeExecuted { .. } => SequenceEvent::Filled,
+                    _ => SequenceEvent::Adjusted,
+                };
+                vec![
+                    mapping(
+                        trade.maker_order_id,
+                        event,
+                        Some(trade.trade_id),
+                        Some(LiquidityRole::Maker),
+                        *maker,
+                        trade.market_id,
+                    ),
+                    mapping(
+                        trade.taker_order_id,
+                        event,
+                        Some(trade.trade_id),
+                        Some(LiquidityRole::Taker),
+                        *taker,
+                        trade.market_id,
+                    ),
+                ]
+            }
+        }
+    }
+}
+
+/// Key to translate from through the sequence map
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SequenceLookup {
+    /// REST order ID
+    Order(u64),
+    /// Engine journal sequence
+    JournalSequence(u64),
+    /// Settlement transaction signature
+    Signature(String),
+    /// User account PDA
+    UserAccount(Pubkey),
 }
 
 /// Request structures for RPC API
@@ -213,6 +2078,9 @@ pub struct MarketStats {
 /// Place order request
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PlaceOrderRequest {
+    /// Target market; the engine's market when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub market_id: Option<MarketId>,
     pub owner: String,
     pub client_order_id: u64,
     pub side: OrderSide,
@@ -220,8 +2088,41 @@ pub struct PlaceOrderRequest {
     pub price: u64,
     pub quantity: u64,
     pub time_in_force: TimeInForce,
+    /// Expiry of a good-till-time order, as unix seconds or an RFC3339
+    /// datetime with its offset; see `expiry`
+    #[serde(default, deserialize_with = "expiry::deserialize_expiry")]
     pub expiry_timestamp: Option<i64>,
     pub self_trade_behavior: SelfTradeBehavior,
+    /// Slippage tolerance of a market order, see `Order::max_slippage_bps`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_slippage_bps: Option<u32>,
+    /// Self-match prevention group to match in, see
+    /// `Order::prevention_group_id`; the owner's user account must have been
+    /// put in it on chain through this market
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prevention_group_id: Option<u32>,
+    /// Owner's signature over the order's intent, see `signing`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<OrderSignature>,
+}
+
+impl PlaceOrderRequest {
+    /// Whether the order would rest on the book for what it does not fill,
+    /// see `Order::can_rest`
+    pub fn can_rest(&self) -> bool {
+        self.order_type != OrderType::Market
+            && matches!(self.time_in_force, TimeInForce::GoodTillCancelled | TimeInForce::GoodTillTime)
+    }
+}
+
+/// Signature part of a `SignedOrder` sent beside a place order request; the
+/// intent is rebuilt from the request's own fields
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct OrderSignature {
+    pub nonce: u64,
+    pub expiry: i64,
+    /// Base58 encoded ed25519 signature
+    pub signature: String,
 }
 
 /// Cancel order request
@@ -240,6 +2141,100 @@ pub struct ModifyOrderRequest {
     pub new_quantity: Option<u64>,
 }
 
+/// Batch cancel-replace request: cancels are applied before placements
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BatchOrderRequest {
+    /// Order IDs to cancel
+    pub cancel: Vec<u64>,
+    /// Orders to place after the cancels
+    pub place: Vec<PlaceOrderRequest>,
+}
+
+/// Outcome of a batch cancel-replace
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BatchOrderResponse {
+    /// Orders that were cancelled
+    pub cancelled: Vec<Order>,
+    /// Orders that were accepted, as submitted
+    pub placed: Vec<Order>,
+    /// Per-item failures; the rest of the batch still applies
+    pub errors: Vec<BatchOrderError>,
+}
+
+/// Failure of a single batch item
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BatchOrderError {
+    /// Order ID for cancels, index into `place` for placements
+    pub item: BatchOrderItem,
+    pub message: String,
+    /// Canonical error code, see `error_codes`
+    #[serde(default)]
+    pub code: u32,
+    /// Standing against the rate limit, when the item was rate limited
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimitStatus>,
+}
+
+/// Batch item a failure refers to
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchOrderItem {
+    Cancel(u64),
+    Place(usize),
+}
+
+/// What a rate limit counts
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitScope {
+    /// Order placements of one owner
+    Orders,
+    /// Requests from one client IP
+    Requests,
+    /// Order placements made with one tenant's API keys
+    TenantOrders,
+    /// Requests made with one tenant's API keys
+    TenantRequests,
+}
+
+/// Standing of a caller against the rate limit that rejected it, so a client
+/// can pace itself instead of retrying blind
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    pub scope: RateLimitScope,
+    /// Calls admitted per second
+    pub limit: u32,
+    /// Calls counted against the current second's allowance
+    pub used: u32,
+    /// Milliseconds until a call would be admitted again
+    pub retry_after_ms: u64,
+    /// Milliseconds until the whole allowance has refilled
+    pub reset_after_ms: u64,
+}
+
+impl RateLimitStatus {
+    /// Calls that may still be made before the limit is reached
+    pub fn remaining(&self) -> u32 {
+        self.limit.saturating_sub(self.used)
+    }
+}
+
+impl fmt::Display for RateLimitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scope = match self.scope {
+            RateLimitScope::Orders => "orders",
+            RateLimitScope::Requests => "requests from this address",
+            RateLimitScope::TenantOrders => "orders for this tenant",
+            RateLimitScope::TenantRequests => "requests for this tenant",
+        };
+        write!(
+            f,
+            "more than {} {} per second, retry in {} ms",
+            self.limit, scope, self.retry_after_ms
+        )
+    }
+}
+
 /// Error types for the infrastructure
 #[derive(Error, Debug)]
 pub enum ClobError {
@@ -271,45 +2266,128 @@ pub enum ClobError {
     MarketOrderWouldCrossSpread,
     #[error("Post-only order would match")]
     PostOnlyOrderWouldMatch,
+    #[error("Risk limit exceeded: {0}")]
+    RiskLimitExceeded(String),
     #[error("Storage error: {0}")]
     StorageError(String),
     #[error("Network error: {0}")]
     NetworkError(String),
     #[error("Serialization error: {0}")]
     SerializationError(String),
+    #[error("Engine is not the active matcher")]
+    NotLeader,
+    #[error("Unknown market: {0}")]
+    UnknownMarket(String),
+    #[error("Invalid order signature: {0}")]
+    InvalidSignature(String),
+    #[error("Order signature expired")]
+    SignatureExpired,
+    #[error("Rate limit exceeded: {0}")]
+    RateLimited(RateLimitStatus),
+    #[error("Duplicate client order ID: {0}")]
+    DuplicateClientOrderId(String),
+    #[error("Slippage exceeded: {0}")]
+    SlippageExceeded(String),
+    #[error("Market only accepts cancellations")]
+    MarketCancelOnly,
+    #[error("Reduce-only: {0}")]
+    ReduceOnly(String),
+    #[error("Order nonce already used: {0}")]
+    NonceReused(String),
+    #[error("Matching is paused")]
+    EnginePaused,
+    #[error("Market maker protection tripped: {0}")]
+    MmpFrozen(String),
+    #[error("Suspended: {0}")]
+    Suspended(String),
+    #[error("Trade cannot be adjusted: {0}")]
+    TradeNotAdjustable(String),
+    #[error("Not entitled: {0}")]
+    NotEntitled(String),
+    #[error("Order type disabled: {0}")]
+    OrderTypeDisabled(String),
+    #[error("Idempotency key reused: {0}")]
+    IdempotencyKeyReused(String),
+    #[error("Invalid expiry: {0}")]
+    InvalidExpiry(String),
 }
 
 /// Result type for CLOB operations
-pub type ClobResult<T> = Result<T, ClobError>;
+pub type ClobResult<T> = std::result::Result<T, ClobError>;
 
-/// Display implementations for better logging
-impl fmt::Display for OrderSide {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            OrderSide::Bid => write!(f, "Bid"),
-            OrderSide::Ask => write!(f, "Ask"),
-        }
-    }
+/// Failure of a server or CLI command, by what a supervisor can do about it
+///
+/// Configuration errors need an operator, and so does a listener that cannot
+/// be bound unless its address is merely still in use, or state found to
+/// disagree between sources of truth. Storage and network failures may pass,
+/// as may a background service that stopped, so the process can be
+/// restarted; `is_retryable` tells the two apart. Any other `ClobError` an
+/// operation fails with is carried as `Engine`.
+#[derive(Error, Debug)]
+pub enum InfraError {
+    /// Configuration or command line arguments are missing, malformed or
+    /// inconsistent
+    #[error("Configuration error: {0}")]
+    Config(String),
+    /// A server could not listen where it was configured to
+    #[error("Failed to listen on {address}: {source}")]
+    Bind {
+        address: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Storage error: {0}")]
+    Storage(String),
+    /// Solana RPC, Redis or another remote service failed
+    #[error("Network error: {0}")]
+    Network(String),
+    /// The matching engine or another component refused an operation
+    #[error(transparent)]
+    Engine(ClobError),
+    /// A background service or runtime stopped
+    #[error("Task failed: {0}")]
+    Task(String),
+    /// Storage, the engine or the chain disagree, as a reconciliation or a
+    /// replay found
+    #[error("State diverged: {0}")]
+    Diverged(String),
+    /// A local file could not be read or written
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
 }
 
-impl fmt::Display for OrderType {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl InfraError {
+    pub fn config(e: impl fmt::Display) -> Self {
+        InfraError::Config(e.to_string())
+    }
+
+    pub fn network(e: impl fmt::Display) -> Self {
+        InfraError::Network(e.to_string())
+    }
+
+    pub fn task(e: impl fmt::Display) -> Self {
+        InfraError::Task(e.to_string())
+    }
+
+    /// Whether running the same command again may succeed without an operator
+    pub fn is_retryable(&self) -> bool {
         match self {
-            OrderType::Limit => write!(f, "Limit"),
-            OrderType::Market => write!(f, "Market"),
-            OrderType::PostOnly => write!(f, "PostOnly"),
+            InfraError::Storage(_) | InfraError::Network(_) | InfraError::Task(_) => true,
+            InfraError::Bind { source, .. } => source.kind() == std::io::ErrorKind::AddrInUse,
+            InfraError::Config(_) | InfraError::Engine(_) | InfraError::Diverged(_) | InfraError::Io(_) => false,
         }
     }
 }
 
-impl fmt::Display for OrderStatus {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            OrderStatus::Open => write!(f, "Open"),
-            OrderStatus::PartiallyFilled => write!(f, "PartiallyFilled"),
-            OrderStatus::Filled => write!(f, "Filled"),
-            OrderStatus::Cancelled => write!(f, "Cancelled"),
-            OrderStatus::Expired => write!(f, "Expired"),
+impl From<ClobError> for InfraError {
+    fn from(e: ClobError) -> Self {
+        match e {
+            ClobError::StorageError(message) => InfraError::Storage(message),
+            ClobError::NetworkError(message) => InfraError::Network(message),
+            e => InfraError::Engine(e),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Result type for servers and CLI commands
+pub type InfraResult<T> = std::result::Result<T, InfraError>;
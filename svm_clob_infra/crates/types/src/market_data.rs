@@ -0,0 +1,395 @@
+/// Market data response types
+///
+/// `Candle`, `Ticker`, `WindowStats` and `Depth` are what the REST endpoints return, the
+/// storage layer aggregates and the SDK decodes. `MarketMetrics` is the
+/// engine's periodic sample of its book and order flow for dashboards. Prices are `Price`s and sizes
+/// `Quantity`s in native units, quote volumes `Notional`s, and times unix
+/// seconds, as everywhere else on the wire.
+
+use crate::{ClobError, MarketId, MarketUnits, Notional, OrderBookSnapshot, Price, Quantity, TradeExecution};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Seconds in the window `Ticker` covers
+pub const TICKER_WINDOW_SECS: i64 = 24 * 60 * 60;
+
+/// Distances from the mid, in basis points, within which `MarketMetrics`
+/// measures resting depth
+pub const DEPTH_BANDS_BPS: [u32; 3] = [5, 25, 100];
+
+/// Candle width
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum CandleInterval {
+    #[serde(rename = "1m")]
+    OneMinute,
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "15m")]
+    FifteenMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "4h")]
+    FourHours,
+    #[serde(rename = "1d")]
+    OneDay,
+}
+
+impl CandleInterval {
+    pub fn seconds(self) -> i64 {
+        match self {
+            CandleInterval::OneMinute => 60,
+            CandleInterval::FiveMinutes => 5 * 60,
+            CandleInterval::FifteenMinutes => 15 * 60,
+            CandleInterval::OneHour => 60 * 60,
+            CandleInterval::FourHours => 4 * 60 * 60,
+            CandleInterval::OneDay => 24 * 60 * 60,
+        }
+    }
+
+    /// Start of the candle containing `timestamp`
+    pub fn open_time(self, timestamp: i64) -> i64 {
+        timestamp - timestamp.rem_euclid(self.seconds())
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            CandleInterval::OneMinute => "1m",
+            CandleInterval::FiveMinutes => "5m",
+            CandleInterval::FifteenMinutes => "15m",
+            CandleInterval::OneHour => "1h",
+            CandleInterval::FourHours => "4h",
+            CandleInterval::OneDay => "1d",
+        }
+    }
+}
+
+impl fmt::Display for CandleInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for CandleInterval {
+    type Err = ClobError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1m" => Ok(CandleInterval::OneMinute),
+            "5m" => Ok(CandleInterval::FiveMinutes),
+            "15m" => Ok(CandleInterval::FifteenMinutes),
+            "1h" => Ok(CandleInterval::OneHour),
+            "4h" => Ok(CandleInterval::FourHours),
+            "1d" => Ok(CandleInterval::OneDay),
+            other => Err(ClobError::SerializationError(format!("Unknown candle interval {}", other))),
+        }
+    }
+}
+
+/// OHLCV bar over one interval
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Candle {
+    pub market_id: MarketId,
+    pub interval: CandleInterval,
+    /// Start of the interval
+    pub open_time: i64,
+    pub open: Price,
+    pub high: Price,
+    pub low: Price,
+    pub close: Price,
+    /// Base quantity traded
+    pub volume: Quantity,
+    /// Quote amount traded
+    pub quote_volume: Notional,
+    pub trade_count: u32,
+}
+
+impl Candle {
+    /// Candles of `interval` over `trades`, oldest first; intervals without
+    /// trades are left out
+    pub fn from_trades(market_id: MarketId, interval: CandleInterval, trades: &[TradeExecution]) -> Vec<Candle> {
+        let mut ordered: Vec<&TradeExecution> = trades.iter().collect();
+        ordered.sort_by_key(|trade| trade.timestamp);
+
+        let mut candles: Vec<Candle> = Vec::new();
+        for trade in ordered {
+            let open_time = interval.open_time(trade.timestamp);
+            let price = Price(trade.price);
+            match candles.last_mut() {
+                Some(candle) if candle.open_time == open_time => {
+                    candle.high = candle.high.max(price);
+                    candle.low = candle.low.min(price);
+                    candle.close = price;
+                    candle.volume += Quantity(trade.quantity);
+                    candle.quote_volume += trade.notional();
+                    candle.trade_count += 1;
+                }
+                _ => candles.push(Candle {
+                    market_id,
+                    interval,
+                    open_time,
+                    open: price,
+                    high: price,
+                    low: price,
+                    close: price,
+                    volume: Quantity(trade.quantity),
+                    quote_volume: trade.notional(),
+                    trade_count: 1,
+                }),
+            }
+        }
+        candles
+    }
+}
+
+/// Top of book and 24h summary of one market
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Ticker {
+    pub market_id: MarketId,
+    pub best_bid: Option<Price>,
+    pub best_ask: Option<Price>,
+    pub last_price: Option<Price>,
+    /// First trade price in the window
+    pub open_24h: Option<Price>,
+    pub high_24h: Option<Price>,
+    pub low_24h: Option<Price>,
+    /// Base quantity traded in the window
+    pub volume_24h: Quantity,
+    /// Quote amount traded in the window
+    pub quote_volume_24h: Notional,
+    pub timestamp: i64,
+}
+
+impl Ticker {
+    /// Ticker at `now` from the current book and recent trades, newest first
+    pub fn new(market_id: MarketId, book: &OrderBookSnapshot, trades: &[TradeExecution], now: i64) -> Self {
+        let window: Vec<&TradeExecution> =
+            trades.iter().filter(|trade| trade.timestamp > now - TICKER_WINDOW_SECS).collect();
+        Ticker {
+            market_id,
+            best_bid: book.bids.first().map(|&(price, _)| Price(price)),
+            best_ask: book.asks.first().map(|&(price, _)| Price(price)),
+            last_price: trades.first().map(|trade| Price(trade.price)),
+            open_24h: window.last().map(|trade| Price(trade.price)),
+            high_24h: window.iter().map(|trade| Price(trade.price)).max(),
+            low_24h: window.iter().map(|trade| Price(trade.price)).min(),
+            volume_24h: window.iter().map(|trade| Quantity(trade.quantity)).sum(),
+            quote_volume_24h: window.iter().map(|trade| trade.notional()).sum(),
+            timestamp: now,
+        }
+    }
+}
+
+impl Ticker {
+    /// Ticker at `now` from the current book, the last trade price and the
+    /// engine's rolling 24h window
+    pub fn from_window(
+        market_id: MarketId,
+        book: &OrderBookSnapshot,
+        last_price: Option<Price>,
+        window: &WindowStats,
+        now: i64,
+    ) -> Self {
+        Ticker {
+            market_id,
+            best_bid: book.bids.first().map(|&(price, _)| Price(price)),
+            best_ask: book.asks.first().map(|&(price, _)| Price(price)),
+            last_price,
+            open_24h: window.open,
+            high_24h: window.high,
+            low_24h: window.low,
+            volume_24h: window.volume,
+            quote_volume_24h: window.quote_volume,
+            timestamp: now,
+        }
+    }
+}
+
+/// Trades of one market over a rolling window ending at the time it was read
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WindowStats {
+    /// Length of the window in seconds
+    pub window_secs: i64,
+    pub trades: u64,
+    /// Base quantity traded
+    pub volume: Quantity,
+    /// Quote amount traded
+    pub quote_volume: Notional,
+    /// Volume-weighted average price, rounded down
+    pub vwap: Option<Price>,
+    /// First trade price in the window
+    pub open: Option<Price>,
+    pub high: Option<Price>,
+    pub low: Option<Price>,
+}
+
+/// Aggregated quantity at one price
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DepthLevel {
+    pub price: Price,
+    pub quantity: Quantity,
+    /// `price` in whole quote tokens per whole base token, when decimals are known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ui_price: Option<String>,
+    /// `quantity` in whole base tokens, when decimals are known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ui_quantity: Option<String>,
+}
+
+/// Order book levels nearest the spread, best first on each side
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Depth {
+    pub market_id: MarketId,
+    pub bids: Vec<DepthLevel>,
+    pub asks: Vec<DepthLevel>,
+    /// Sequence number of the book the levels were taken from
+    pub sequence_number: u64,
+    pub timestamp: i64,
+}
+
+impl Depth {
+    /// Up to `levels` levels per side of a snapshot
+    pub fn from_snapshot(snapshot: &OrderBookSnapshot, levels: usize) -> Self {
+        let side = |levels_in: &[(u64, u64)]| {
+            levels_in
+                .iter()
+                .take(levels)
+                .map(|&(price, quantity)| DepthLevel {
+                    price: Price(price),
+                    quantity: Quantity(quantity),
+                    ui_price: None,
+                    ui_quantity: None,
+                })
+                .collect()
+        };
+        Depth {
+            market_id: snapshot.market_id,
+            bids: side(&snapshot.bids),
+            asks: side(&snapshot.asks),
+            sequence_number: snapshot.sequence_number,
+            timestamp: snapshot.timestamp,
+        }
+    }
+
+    /// Render every level's price and size in whole tokens
+    pub fn with_units(mut self, units: Option<&MarketUnits>) -> Self {
+        if let Some(units) = units {
+            for level in self.bids.iter_mut().chain(self.asks.iter_mut()) {
+                level.ui_price = Some(units.price_to_ui(level.price));
+                level.ui_quantity = Some(units.quantity_to_ui(level.quantity));
+            }
+        }
+        self
+    }
+}
+
+/// Resting quantity within `band_bps` of the mid on each side
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BandDepth {
+    pub band_bps: u32,
+    pub bids: Quantity,
+    pub asks: Quantity,
+}
+
+/// Orders and trades of one market over a sampling interval
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OrderFlow {
+    /// Orders accepted, replacements included
+    pub orders_placed: u64,
+    /// Orders cancelled by their owners, replaced ones included
+    pub orders_cancelled: u64,
+    pub trades: u64,
+}
+
+/// Book and order flow of one market, sampled on a fixed interval
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MarketMetrics {
+    pub market_id: MarketId,
+    pub timestamp: i64,
+    pub best_bid: Option<Price>,
+    pub best_ask: Option<Price>,
+    /// Depth within each of `DEPTH_BANDS_BPS`; empty unless both sides rest
+    pub depth: Vec<BandDepth>,
+    /// Seconds `flow` covers, since the previous sample
+    pub interval_secs: f64,
+    pub flow: OrderFlow,
+}
+
+impl MarketMetrics {
+    /// Sample of `book` and of `flow` over the last `interval_secs`
+    pub fn new(book: &OrderBookSnapshot, flow: OrderFlow, interval_secs: f64) -> Self {
+        let best_bid = book.bids.first().map(|&(price, _)| price);
+        let best_ask = book.asks.first().map(|&(price, _)| price);
+        let depth = match (best_bid, best_ask) {
+            (Some(bid), Some(ask)) => {
+                // Twice the mid, so bands are compared without rounding
+                let mid2 = u128::from(bid) + u128::from(ask);
+                DEPTH_BANDS_BPS
+                    .iter()
+                    .map(|&band_bps| {
+                        let floor = mid2 * u128::from(10_000 - band_bps);
+                        let ceiling = mid2 * u128::from(10_000 + band_bps);
+                        BandDepth {
+                            band_bps,
+                            bids: band_quantity(&book.bids, |price| price * 20_000 >= floor),
+                            asks: band_quantity(&book.asks, |price| price * 20_000 <= ceiling),
+                        }
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+        MarketMetrics {
+            market_id: book.market_id,
+            timestamp: book.timestamp,
+            best_bid: best_bid.map(Price),
+            best_ask: best_ask.map(Price),
+            depth,
+            interval_secs,
+            flow,
+        }
+    }
+
+    /// Best ask less best bid, when both sides rest
+    pub fn spread(&self) -> Option<u64> {
+        Some(self.best_ask?.0.saturating_sub(self.best_bid?.0))
+    }
+
+    /// Spread relative to the mid, in basis points
+    pub fn spread_bps(&self) -> Option<f64> {
+        let (bid, ask) = (self.best_bid?.0 as f64, self.best_ask?.0 as f64);
+        Some((ask - bid) * 20_000.0 / (ask + bid))
+    }
+
+    /// Orders accepted per second over the interval
+    pub fn order_arrival_rate(&self) -> f64 {
+        if self.interval_secs > 0.0 {
+            self.flow.orders_placed as f64 / self.interval_secs
+        } else {
+            0.0
+        }
+    }
+
+    /// Cancellations per trade over the interval, `None` without trades
+    pub fn cancel_to_trade_ratio(&self) -> Option<f64> {
+        (self.flow.trades > 0).then(|| self.flow.orders_cancelled as f64 / self.flow.trades as f64)
+    }
+}
+
+/// Quantity of the levels, best first, whose price passes `inside`
+fn band_quantity(levels: &[(u64, u64)], inside: impl Fn(u128) -> bool) -> Quantity {
+    levels
+        .iter()
+        .take_while(|&&(price, _)| inside(u128::from(price)))
+        .map(|&(_, quantity)| Quantity(quantity))
+        .sum()
+}
@@ -0,0 +1,121 @@
+/// Pagination
+///
+/// Listing endpoints take `limit` and `cursor` query parameters (`Paginated`)
+/// and answer with a `Page`: up to `limit` items plus the cursor of the page
+/// after them, if there is one. Storage listings take and return the same
+/// types, so a handler passes the request through unchanged. Cursors are
+/// opaque to clients; they currently carry an offset into the listing's order.
+
+use crate::{ClobError, ClobResult};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Page size when a request names none
+pub const DEFAULT_PAGE_LIMIT: u32 = 100;
+/// Largest page size served
+pub const MAX_PAGE_LIMIT: u32 = 1000;
+
+/// Position in a listing, as handed out in `Page::next_cursor`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(transparent)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Cursor(String);
+
+impl Cursor {
+    pub fn from_offset(offset: u64) -> Self {
+        Cursor(URL_SAFE_NO_PAD.encode(offset.to_be_bytes()))
+    }
+
+    pub fn offset(&self) -> ClobResult<u64> {
+        let invalid = || ClobError::SerializationError(format!("Invalid cursor {}", self.0));
+        let bytes = URL_SAFE_NO_PAD.decode(&self.0).map_err(|_| invalid())?;
+        let bytes: [u8; 8] = bytes.try_into().map_err(|_| invalid())?;
+        Ok(u64::from_be_bytes(bytes))
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Page request of a listing
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Paginated {
+    /// Page size; `DEFAULT_PAGE_LIMIT` when unset, at most `MAX_PAGE_LIMIT`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// Where to start; the first page when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<Cursor>,
+}
+
+impl Paginated {
+    /// First page of up to `limit` items
+    pub fn first(limit: u32) -> Self {
+        Paginated {
+            limit: Some(limit),
+            cursor: None,
+        }
+    }
+
+    /// Page size actually served
+    pub fn limit(&self) -> u32 {
+        self.limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT)
+    }
+
+    /// Number of items before this page
+    pub fn offset(&self) -> ClobResult<u64> {
+        self.cursor.as_ref().map_or(Ok(0), Cursor::offset)
+    }
+
+    /// Request for the page after `page`, if there is one
+    pub fn next<T>(&self, page: &Page<T>) -> Option<Paginated> {
+        page.next_cursor.clone().map(|cursor| Paginated {
+            limit: self.limit,
+            cursor: Some(cursor),
+        })
+    }
+}
+
+/// One page of a listing
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Cursor of the next page; `None` on the last page
+    #[serde(default)]
+    pub next_cursor: Option<Cursor>,
+}
+
+impl<T> Page<T> {
+    /// Page from up to `limit + 1` items fetched at the request's offset; the
+    /// extra item only tells whether another page follows
+    pub fn from_fetched(mut items: Vec<T>, request: &Paginated) -> ClobResult<Self> {
+        let limit = request.limit() as usize;
+        let next_cursor = if items.len() > limit {
+            items.truncate(limit);
+            Some(Cursor::from_offset(request.offset()? + limit as u64))
+        } else {
+            None
+        };
+        Ok(Page { items, next_cursor })
+    }
+
+    /// Requested page of a listing held in memory, already in listing order
+    pub fn slice(items: impl IntoIterator<Item = T>, request: &Paginated) -> ClobResult<Self> {
+        let offset = usize::try_from(request.offset()?).unwrap_or(usize::MAX);
+        let fetched = items.into_iter().skip(offset).take(request.limit() as usize + 1).collect();
+        Self::from_fetched(fetched, request)
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+        }
+    }
+}
@@ -0,0 +1,242 @@
+/// Portfolio accounting
+///
+/// `Portfolio` is what `GET /api/v1/users/{user}/portfolio` returns: one
+/// `Position` per market a user traded, built from their fills in execution
+/// order with average-cost accounting. Adding to a position moves its average
+/// entry price; reducing it realizes P&L against that price, and a fill
+/// through zero closes the old position before opening the new one at the fill
+/// price. Unrealized P&L marks the open position to the mid price. Amounts are
+/// quote native units before fees, which the fee reports cover.
+///
+/// `Balances` is what `GET /api/v1/users/{user}/balances` returns: the
+/// collateral of a user in each market, from the indexed ledger, split into
+/// what open orders hold and what is free to trade or withdraw.
+
+use crate::{LedgerBalance, LiquidityRole, MarketId, Order, OrderBookSnapshot, OrderSide, Price, Quantity, TradeExecution};
+use serde::{Deserialize, Serialize};
+use anchor_lang::solana_program::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap};
+
+/// One participant's side of a fill
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UserFill {
+    pub trade: TradeExecution,
+    pub role: LiquidityRole,
+}
+
+impl UserFill {
+    /// Side the participant traded on
+    pub fn side(&self) -> OrderSide {
+        match (self.role, self.trade.maker_side) {
+            (LiquidityRole::Maker, side) => side,
+            (LiquidityRole::Taker, OrderSide::Bid) => OrderSide::Ask,
+            (LiquidityRole::Taker, OrderSide::Ask) => OrderSide::Bid,
+        }
+    }
+}
+
+/// Open position and P&L in one market
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Position {
+    pub market_id: MarketId,
+    /// Base native units; negative when short
+    pub net_position: i128,
+    /// Quote paid for the open position, or received for it when short
+    /// (negative); zero when flat
+    pub cost_basis: i128,
+    /// `cost_basis / net_position`; `None` when flat
+    pub average_entry_price: Option<Price>,
+    pub realized_pnl: i128,
+    /// Open position marked to `mid_price`; `None` without a two-sided book
+    pub unrealized_pnl: Option<i128>,
+    pub mid_price: Option<Price>,
+    pub bought: Quantity,
+    pub sold: Quantity,
+    pub fills: u64,
+}
+
+impl Position {
+    fn new(market_id: MarketId) -> Self {
+        Position {
+            market_id,
+            net_position: 0,
+            cost_basis: 0,
+            average_entry_price: None,
+            realized_pnl: 0,
+            unrealized_pnl: None,
+            mid_price: None,
+            bought: Quantity(0),
+            sold: Quantity(0),
+            fills: 0,
+        }
+    }
+
+    /// Apply one fill in execution order
+    pub fn apply(&mut self, fill: &UserFill) {
+        let quantity = fill.trade.quantity as i128;
+        let price = fill.trade.price as i128;
+        let delta = match fill.side() {
+            OrderSide::Bid => {
+                self.bought += Quantity(fill.trade.quantity);
+                quantity
+            }
+            OrderSide::Ask => {
+                self.sold += Quantity(fill.trade.quantity);
+                -quantity
+            }
+        };
+        self.fills += 1;
+
+        // Reduce the open position first, releasing its share of the cost basis
+        let mut opening = delta;
+        if self.net_position != 0 && self.net_position.signum() != delta.signum() {
+            let closing = delta.abs().min(self.net_position.abs()) * delta.signum();
+            let released = self.cost_basis * closing.abs() / self.net_position.abs();
+            self.realized_pnl += -closing * price - released;
+            self.cost_basis -= released;
+            self.net_position += closing;
+            opening -= closing;
+        }
+        self.cost_basis += opening * price;
+        self.net_position += opening;
+
+        self.average_entry_price = (self.net_position != 0)
+            .then(|| Price((self.cost_basis / self.net_position).clamp(0, u64::MAX as i128) as u64));
+    }
+
+    /// Mark the open position to `mid_price`
+    pub fn mark(&mut self, mid_price: Option<Price>) {
+        self.mid_price = mid_price;
+        self.unrealized_pnl = mid_price.map(|mid| self.net_position * mid.0 as i128 - self.cost_basis);
+    }
+}
+
+/// Positions and P&L of one user across markets
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Portfolio {
+    pub user: Pubkey,
+    /// One per market traded, ordered by market
+    pub positions: Vec<Position>,
+    pub realized_pnl: i128,
+    /// Sum over the positions that could be marked
+    pub unrealized_pnl: i128,
+    pub timestamp: i64,
+}
+
+impl Portfolio {
+    /// Portfolio from a user's fills in any order, marked to `mids`
+    pub fn new(user: Pubkey, mut fills: Vec<UserFill>, mids: &HashMap<MarketId, Price>, now: i64) -> Self {
+        fills.sort_by_key(|fill| (fill.trade.timestamp, fill.trade.trade_id));
+
+        let mut positions: BTreeMap<MarketId, Position> = BTreeMap::new();
+        for fill in &fills {
+            positions
+                .entry(fill.trade.market_id)
+                .or_insert_with(|| Position::new(fill.trade.market_id))
+                .apply(fill);
+        }
+        let positions: Vec<Position> = positions
+            .into_values()
+            .map(|mut position| {
+                position.mark(mids.get(&position.market_id).copied());
+                position
+            })
+            .collect();
+
+        Portfolio {
+            user,
+            realized_pnl: positions.iter().map(|position| position.realized_pnl).sum(),
+            unrealized_pnl: positions.iter().filter_map(|position| position.unrealized_pnl).sum(),
+            positions,
+            timestamp: now,
+        }
+    }
+}
+
+/// Collateral of one user in one market, in native units
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MarketBalance {
+    pub market_id: MarketId,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    /// Deposits - withdrawals +/- settled fills
+    pub base_balance: i64,
+    pub quote_balance: i64,
+    /// Remaining quantity of open asks
+    pub base_locked: u64,
+    /// Remaining notional of open bids
+    pub quote_locked: u64,
+    /// Accrued fees net of rebates, owed in quote
+    pub fees: i64,
+    /// Balance less what is locked
+    pub base_free: i64,
+    /// Balance less what is locked and the fees owed
+    pub quote_free: i64,
+    /// Credited by settled fills whose settlement is not finalized yet;
+    /// free to trade, but not to withdraw until it is
+    pub base_held: u64,
+    pub quote_held: u64,
+    /// Free balance less what is held
+    pub base_withdrawable: i64,
+    pub quote_withdrawable: i64,
+}
+
+impl MarketBalance {
+    /// Balance of `ledger` (zero without one) with the `open_orders` of the
+    /// user holding their share of it
+    pub fn new(
+        market_id: MarketId,
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+        ledger: Option<&LedgerBalance>,
+        open_orders: &[Order],
+    ) -> Self {
+        let mut base_locked: u64 = 0;
+        let mut quote_locked: u128 = 0;
+        for order in open_orders {
+            match order.side {
+                OrderSide::Ask => base_locked = base_locked.saturating_add(order.remaining_quantity),
+                OrderSide::Bid => quote_locked += Price(order.price).notional(Quantity(order.remaining_quantity)).0,
+            }
+        }
+        let quote_locked = quote_locked.min(u64::MAX as u128) as u64;
+        let (base_balance, quote_balance, fees) =
+            ledger.map_or((0, 0, 0), |ledger| (ledger.base_balance, ledger.quote_balance, ledger.fees));
+        let (base_held, quote_held) = ledger.map_or((0, 0), |ledger| (ledger.base_held, ledger.quote_held));
+        let base_free = base_balance.saturating_sub_unsigned(base_locked);
+        let quote_free = quote_balance.saturating_sub_unsigned(quote_locked).saturating_sub(fees);
+
+        MarketBalance {
+            market_id,
+            base_mint,
+            quote_mint,
+            base_balance,
+            quote_balance,
+            base_locked,
+            quote_locked,
+            fees,
+            base_free,
+            quote_free,
+            base_held,
+            quote_held,
+            base_withdrawable: base_free.saturating_sub_unsigned(base_held),
+            quote_withdrawable: quote_free.saturating_sub_unsigned(quote_held),
+        }
+    }
+}
+
+/// Collateral of one user across markets
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Balances {
+    pub user: Pubkey,
+    /// One per market the node serves
+    pub markets: Vec<MarketBalance>,
+    pub timestamp: i64,
+}
+
+/// Mid of the best bid and ask; `None` unless both sides have orders
+pub fn mid_price(book: &OrderBookSnapshot) -> Option<Price> {
+    let (bid, _) = book.bids.first()?;
+    let (ask, _) = book.asks.first()?;
+    Some(Price(((*bid as u128 + *ask as u128) / 2) as u64))
+}
@@ -0,0 +1,1053 @@
+/// On-chain program interface for SVM CLOB Infrastructure
+///
+/// This module mirrors the PDAs, instructions and events of the SVM CLOB Anchor
+/// program and provides helpers for building transactions and decoding logs.
+
+use crate::accounts::{FillReceiptsView, OrderBookView, UserAccountView};
+use crate::{ClobError, ClobResult, FillReceiptLeaf, LiquidityRole, MarketStatus, OrderSide, OrderTypeWhitelist, SignedOrder};
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::pubkey::Pubkey;
+use anchor_lang::solana_program::{ed25519_program, system_program, sysvar};
+use anchor_lang::{AnchorDeserialize, AnchorSerialize};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// SVM CLOB program ID (matches `declare_id!` in the program)
+pub const PROGRAM_ID: &str = "JBphRWHYzHCiVvYB89vGM9NpaDmHbe1A9W156sRV52Bo";
+
+/// SPL Token program ID, which holds the vaults
+pub const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGWPFXCCkeX5xN8DDx5Lfb7wm";
+
+/// SPL Memo program ID
+pub const MEMO_PROGRAM_ID: &str = "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr";
+
+/// SPL Associated Token Account program ID, which derives wallets' token accounts
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+
+/// SPL account compression program ID, which keeps compressed receipt trees
+pub const SPL_ACCOUNT_COMPRESSION_PROGRAM_ID: &str = "cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK";
+
+/// SPL Noop program ID, through which account compression logs tree changes
+pub const SPL_NOOP_PROGRAM_ID: &str = "noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV";
+
+/// Log prefix Anchor uses for `emit!` payloads
+const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+
+/// Parsed program ID
+pub fn program_id() -> Pubkey {
+    Pubkey::from_str(PROGRAM_ID).expect("PROGRAM_ID is a valid pubkey")
+}
+
+/// Orderbook PDA: `["orderbook", base_mint, quote_mint]`
+pub fn orderbook_address(program_id: &Pubkey, base_mint: &Pubkey, quote_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"orderbook", base_mint.as_ref(), quote_mint.as_ref()], program_id)
+}
+
+/// User account PDA: `["user_account", owner]`
+pub fn user_account_address(program_id: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"user_account", owner.as_ref()], program_id)
+}
+
+/// Key sub-account `index` of `wallet` trades as: `["sub_account", wallet,
+/// index]`, with the index in little-endian bytes. Its user account is the
+/// user account PDA of this key; `wallet` signs for it.
+pub fn sub_account_address(program_id: &Pubkey, wallet: &Pubkey, index: u16) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"sub_account", wallet.as_ref(), &index.to_le_bytes()], program_id)
+}
+
+/// Fill receipts PDA: `["fill_receipts", owner]`
+pub fn fill_receipts_address(program_id: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"fill_receipts", owner.as_ref()], program_id)
+}
+
+/// Signed order fill record PDA: `["signed_order", owner, nonce]`, with the
+/// nonce in little-endian bytes
+pub fn signed_order_fill_address(program_id: &Pubkey, owner: &Pubkey, nonce: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"signed_order", owner.as_ref(), &nonce.to_le_bytes()], program_id)
+}
+
+/// Token vault PDA: `["clob_vault", mint]`
+pub fn vault_address(program_id: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"clob_vault", mint.as_ref()], program_id)
+}
+
+/// Withdrawal whitelist PDA: `["withdrawal_whitelist", owner]`
+pub fn withdrawal_whitelist_address(program_id: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"withdrawal_whitelist", owner.as_ref()], program_id)
+}
+
+/// Insurance fund PDA: `["insurance_fund", orderbook]`
+pub fn insurance_fund_address(program_id: &Pubkey, orderbook: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"insurance_fund", orderbook.as_ref()], program_id)
+}
+
+/// Record of a plain transfer into a vault credited to a user account:
+/// `["credited_transfer", signature[..32], signature[32..], [instruction_index]]`
+pub fn credited_transfer_address(program_id: &Pubkey, transfer_signature: &[u8; 64], instruction_index: u8) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"credited_transfer",
+            &transfer_signature[..32],
+            &transfer_signature[32..],
+            &[instruction_index],
+        ],
+        program_id,
+    )
+}
+
+/// Record of an adjustment of a busted or corrected trade's settlement:
+/// `["settlement_adjustment", orderbook, adjustment_id]`, the ID in
+/// little-endian bytes
+pub fn settlement_adjustment_address(program_id: &Pubkey, orderbook: &Pubkey, adjustment_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"settlement_adjustment", orderbook.as_ref(), &adjustment_id.to_le_bytes()],
+        program_id,
+    )
+}
+
+/// Compressed receipt tree PDA of a market: `["receipt_tree", orderbook]`
+pub fn receipt_tree_address(program_id: &Pubkey, orderbook: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"receipt_tree", orderbook.as_ref()], program_id)
+}
+
+/// Associated token account of `wallet` for `mint`, where wallets hold tokens
+/// by default
+pub fn associated_token_address(wallet: &Pubkey, mint: &Pubkey) -> Pubkey {
+    let associated_token_program =
+        Pubkey::from_str(ASSOCIATED_TOKEN_PROGRAM_ID).expect("ASSOCIATED_TOKEN_PROGRAM_ID is a valid pubkey");
+    Pubkey::find_program_address(&[wallet.as_ref(), token_program_id().as_ref(), mint.as_ref()], &associated_token_program).0
+}
+
+/// Anchor instruction discriminator: first 8 bytes of `sha256("global:<name>")`
+pub fn instruction_discriminator(name: &str) -> [u8; 8] {
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash(format!("global:{}", name).as_bytes()).to_bytes()[..8]);
+    discriminator
+}
+
+/// Build the `initialize_user_account` instruction `user` signs and pays for
+/// before its first deposit
+pub fn initialize_user_account_instruction(program_id: &Pubkey, user: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(user_account_address(program_id, user).0, false),
+            AccountMeta::new(*user, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: instruction_discriminator("initialize_user_account").to_vec(),
+    }
+}
+
+/// Build the `initialize_sub_account` instruction `user` signs and pays for to
+/// open its sub-account `index`, from 1
+pub fn initialize_sub_account_instruction(program_id: &Pubkey, user: &Pubkey, index: u16, name: &str) -> Instruction {
+    let sub_account = sub_account_address(program_id, user, index).0;
+    let mut data = instruction_discriminator("initialize_sub_account").to_vec();
+    data.extend_from_slice(&index.to_le_bytes());
+    data.extend_from_slice(&(name.len() as u32).to_le_bytes());
+    data.extend_from_slice(name.as_bytes());
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(sub_account, false),
+            AccountMeta::new(user_account_address(program_id, &sub_account).0, false),
+            AccountMeta::new(*user, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data,
+    }
+}
+
+/// Build the `transfer_between_sub_accounts` instruction `user` signs to move
+/// balances from the user account of `from` to that of `to`, each `user`
+/// itself or one of its sub-accounts
+pub fn transfer_between_sub_accounts_instruction(
+    program_id: &Pubkey,
+    user: &Pubkey,
+    from: &Pubkey,
+    to: &Pubkey,
+    base_amount: u64,
+    quote_amount: u64,
+) -> Instruction {
+    let mut data = instruction_discriminator("transfer_between_sub_accounts").to_vec();
+    data.extend_from_slice(&base_amount.to_le_bytes());
+    data.extend_from_slice(&quote_amount.to_le_bytes());
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(user_account_address(program_id, from).0, false),
+            AccountMeta::new(user_account_address(program_id, to).0, false),
+            AccountMeta::new_readonly(*user, true),
+        ],
+        data,
+    }
+}
+
+/// Build the `deposit` instruction moving `amount` of `mint` from `user`'s
+/// token account into the vault and crediting the user account of `owner`,
+/// `user` itself or one of its sub-accounts; `user` signs and pays for the
+/// vault when it is the mint's first deposit
+pub fn deposit_instruction(
+    program_id: &Pubkey,
+    orderbook: &Pubkey,
+    user: &Pubkey,
+    owner: &Pubkey,
+    user_token_account: &Pubkey,
+    mint: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut data = instruction_discriminator("deposit").to_vec();
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*orderbook, false),
+            AccountMeta::new(user_account_address(program_id, owner).0, false),
+            AccountMeta::new(*user_token_account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(vault_address(program_id, mint).0, false),
+            AccountMeta::new(*user, true),
+            AccountMeta::new_readonly(token_program_id(), false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(sysvar::rent::ID, false),
+        ],
+        data,
+    }
+}
+
+/// Build the `withdraw` instruction paying `amount` of `mint` from the user
+/// account of `owner`, `user` itself or one of its sub-accounts, to
+/// `user_token_account`, which must be whitelisted once `user` keeps a
+/// withdrawal whitelist
+pub fn withdraw_instruction(
+    program_id: &Pubkey,
+    orderbook: &Pubkey,
+    user: &Pubkey,
+    owner: &Pubkey,
+    user_token_account: &Pubkey,
+    mint: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut data = instruction_discriminator("withdraw").to_vec();
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*orderbook, false),
+            AccountMeta::new(user_account_address(program_id, owner).0, false),
+            AccountMeta::new(*user_token_account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(vault_address(program_id, mint).0, false),
+            AccountMeta::new(*user, true),
+            AccountMeta::new_readonly(token_program_id(), false),
+            AccountMeta::new_readonly(withdrawal_whitelist_address(program_id, user).0, false),
+        ],
+        data,
+    }
+}
+
+/// Build the `credit_transfer` instruction the orderbook authority signs and
+/// pays for to credit the user account of `owner` with a plain transfer of
+/// `amount` of `mint` into the vault, made by top-level instruction
+/// `instruction_index` of the transaction `transfer_signature`
+pub fn credit_transfer_instruction(
+    program_id: &Pubkey,
+    orderbook: &Pubkey,
+    authority: &Pubkey,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    transfer_signature: &[u8; 64],
+    instruction_index: u8,
+    amount: u64,
+) -> Instruction {
+    let mut data = instruction_discriminator("credit_transfer").to_vec();
+    data.extend_from_slice(transfer_signature);
+    data.push(instruction_index);
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*orderbook, false),
+            AccountMeta::new(user_account_address(program_id, owner).0, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(vault_address(program_id, mint).0, false),
+            AccountMeta::new(credited_transfer_address(program_id, transfer_signature, instruction_index).0, false),
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data,
+    }
+}
+
+/// Build the `adjust_settlement` instruction the orderbook authority signs
+/// and pays for to move `base_to_maker` and `quote_to_maker` from the taker
+/// of settled trade `trade_id` to its maker, or back when negative
+pub fn adjust_settlement_instruction(
+    program_id: &Pubkey,
+    orderbook: &Pubkey,
+    authority: &Pubkey,
+    maker: &Pubkey,
+    taker: &Pubkey,
+    adjustment_id: u64,
+    trade_id: u64,
+    base_to_maker: i64,
+    quote_to_maker: i64,
+) -> Instruction {
+    let mut data = instruction_discriminator("adjust_settlement").to_vec();
+    data.extend_from_slice(&adjustment_id.to_le_bytes());
+    data.extend_from_slice(&trade_id.to_le_bytes());
+    data.extend_from_slice(&base_to_maker.to_le_bytes());
+    data.extend_from_slice(&quote_to_maker.to_le_bytes());
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*orderbook, false),
+            AccountMeta::new(user_account_address(program_id, maker).0, false),
+            AccountMeta::new(user_account_address(program_id, taker).0, false),
+            AccountMeta::new(settlement_adjustment_address(program_id, orderbook, adjustment_id).0, false),
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data,
+    }
+}
+
+fn token_program_id() -> Pubkey {
+    Pubkey::from_str(TOKEN_PROGRAM_ID).expect("TOKEN_PROGRAM_ID is a valid pubkey")
+}
+
+/// Build the `initialize_fill_receipts` instruction `user` signs and pays for
+/// to have its settled fills recorded on chain
+pub fn initialize_fill_receipts_instruction(program_id: &Pubkey, user: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(fill_receipts_address(program_id, user).0, false),
+            AccountMeta::new(*user, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: instruction_discriminator("initialize_fill_receipts").to_vec(),
+    }
+}
+
+/// `execute_trade` argument, the same type the program decodes
+pub type TradeArgs = svm_clob_core::Trade;
+
+/// Build an `execute_trade` instruction signed by the orderbook authority
+pub fn execute_trade_instruction(
+    program_id: &Pubkey,
+    orderbook: &Pubkey,
+    authority: &Pubkey,
+    trade: &TradeArgs,
+) -> ClobResult<Instruction> {
+    let mut data = instruction_discriminator("execute_trade").to_vec();
+    svm_clob_core::borsh::to_writer(&mut data, trade)
+        .map_err(|e| ClobError::SerializationError(e.to_string()))?;
+    let taker = Pubkey::new_from_array(trade.taker);
+    let maker = Pubkey::new_from_array(trade.maker);
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*orderbook, false),
+            AccountMeta::new(user_account_address(program_id, &taker).0, false),
+            AccountMeta::new(user_account_address(program_id, &maker).0, false),
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(fill_receipts_address(program_id, &taker).0, false),
+            AccountMeta::new(fill_receipts_address(program_id, &maker).0, false),
+        ],
+        data,
+    })
+}
+
+/// Account an instruction reads or writes, for clients that build their own
+/// transactions
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AccountSpec {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+/// Instruction a client signs, with its data in base64
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InstructionSpec {
+    /// Program instruction name, e.g. `deposit`
+    pub name: String,
+    pub program_id: Pubkey,
+    pub accounts: Vec<AccountSpec>,
+    pub data: String,
+}
+
+impl InstructionSpec {
+    pub fn new(name: &str, instruction: &Instruction) -> Self {
+        Self {
+            name: name.to_string(),
+            program_id: instruction.program_id,
+            accounts: instruction
+                .accounts
+                .iter()
+                .map(|meta| AccountSpec {
+                    pubkey: meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: STANDARD.encode(&instruction.data),
+        }
+    }
+}
+
+/// Accounts and instructions a wallet uses to deposit into a market
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DepositInstructions {
+    pub program_id: Pubkey,
+    pub orderbook: Pubkey,
+    /// Wallet that signs and pays
+    pub wallet: Pubkey,
+    /// Owner of the credited user account: the wallet or one of its sub-accounts
+    pub owner: Pubkey,
+    pub user_account: Pubkey,
+    pub mint: Pubkey,
+    /// The wallet's associated token account for `mint`, the default source
+    pub token_account: Pubkey,
+    /// Vault the tokens are paid into; a plain SPL transfer of the wallet's
+    /// own tokens to it is credited to the wallet's user account once final
+    pub vault: Pubkey,
+    pub amount: u64,
+    /// Creates the wallet's own user account; sent once, before its first
+    /// deposit. `None` for a sub-account, which is opened with
+    /// `initialize_sub_account`
+    pub initialize_user_account: Option<InstructionSpec>,
+    pub deposit: InstructionSpec,
+}
+
+/// Accounts and instructions for `wallet` to deposit `amount` of `mint` into
+/// the user account of `owner` in `orderbook`, from its associated token
+/// account
+pub fn deposit_instructions(
+    program_id: &Pubkey,
+    orderbook: &Pubkey,
+    wallet: &Pubkey,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    amount: u64,
+) -> DepositInstructions {
+    let token_account = associated_token_address(wallet, mint);
+    DepositInstructions {
+        program_id: *program_id,
+        orderbook: *orderbook,
+        wallet: *wallet,
+        owner: *owner,
+        user_account: user_account_address(program_id, owner).0,
+        mint: *mint,
+        token_account,
+        vault: vault_address(program_id, mint).0,
+        amount,
+        initialize_user_account: (owner == wallet).then(|| {
+            InstructionSpec::new("initialize_user_account", &initialize_user_account_instruction(program_id, wallet))
+        }),
+        deposit: InstructionSpec::new(
+            "deposit",
+            &deposit_instruction(program_id, orderbook, wallet, owner, &token_account, mint, amount),
+        ),
+    }
+}
+
+/// Build the `set_market_status` instruction the orderbook authority signs to
+/// move the market to `status`
+pub fn set_market_status_instruction(
+    program_id: &Pubkey,
+    orderbook: &Pubkey,
+    authority: &Pubkey,
+    status: MarketStatus,
+) -> Instruction {
+    let mut data = instruction_discriminator("set_market_status").to_vec();
+    data.push(status.into());
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![AccountMeta::new(*orderbook, false), AccountMeta::new_readonly(*authority, true)],
+        data,
+    }
+}
+
+/// Build the `set_order_types` instruction the orderbook authority signs to
+/// restrict the order types and times in force the market accepts
+pub fn set_order_types_instruction(
+    program_id: &Pubkey,
+    orderbook: &Pubkey,
+    authority: &Pubkey,
+    whitelist: OrderTypeWhitelist,
+) -> Instruction {
+    let mut data = instruction_discriminator("set_order_types").to_vec();
+    data.extend_from_slice(&[whitelist.order_types, whitelist.time_in_force]);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![AccountMeta::new(*orderbook, false), AccountMeta::new_readonly(*authority, true)],
+        data,
+    }
+}
+
+/// Build the `set_prevention_group` instruction that puts the user account
+/// of `owner` in self-match prevention `group`, 0 taking it out; `user`, the
+/// account's owner or a sub-account's wallet, and the orderbook authority
+/// both sign
+pub fn set_prevention_group_instruction(
+    program_id: &Pubkey,
+    orderbook: &Pubkey,
+    authority: &Pubkey,
+    user: &Pubkey,
+    owner: &Pubkey,
+    group: u32,
+) -> Instruction {
+    let mut data = instruction_discriminator("set_prevention_group").to_vec();
+    data.extend_from_slice(&group.to_le_bytes());
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*orderbook, false),
+            AccountMeta::new(user_account_address(program_id, owner).0, false),
+            AccountMeta::new_readonly(*user, true),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+        data,
+    }
+}
+
+fn compression_program_ids() -> (Pubkey, Pubkey) {
+    (
+        Pubkey::from_str(SPL_ACCOUNT_COMPRESSION_PROGRAM_ID).expect("SPL_ACCOUNT_COMPRESSION_PROGRAM_ID is a valid pubkey"),
+        Pubkey::from_str(SPL_NOOP_PROGRAM_ID).expect("SPL_NOOP_PROGRAM_ID is a valid pubkey"),
+    )
+}
+
+/// Build the `initialize_receipt_tree` instruction the orderbook authority
+/// signs to append the market's receipts to `merkle_tree` from then on. The
+/// tree account must be created in the same transaction, owned by account
+/// compression and `merkle_tree_account_size` bytes long.
+pub fn initialize_receipt_tree_instruction(
+    program_id: &Pubkey,
+    orderbook: &Pubkey,
+    authority: &Pubkey,
+    merkle_tree: &Pubkey,
+    max_depth: u32,
+    max_buffer_size: u32,
+) -> Instruction {
+    let (compression, noop) = compression_program_ids();
+    let mut data = instruction_discriminator("initialize_receipt_tree").to_vec();
+    data.extend_from_slice(&max_depth.to_le_bytes());
+    data.extend_from_slice(&max_buffer_size.to_le_bytes());
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*orderbook, false),
+            AccountMeta::new(receipt_tree_address(program_id, orderbook).0, false),
+            AccountMeta::new(*merkle_tree, false),
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(noop, false),
+            AccountMeta::new_readonly(compression, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data,
+    }
+}
+
+/// Accounts `execute_trade` and `settle_match` take after their own in a
+/// market that appends its fills to the compressed receipt tree `merkle_tree`
+pub fn receipt_tree_accounts(program_id: &Pubkey, orderbook: &Pubkey, merkle_tree: &Pubkey) -> Vec<AccountMeta> {
+    let (compression, noop) = compression_program_ids();
+    vec![
+        AccountMeta::new(receipt_tree_address(program_id, orderbook).0, false),
+        AccountMeta::new(*merkle_tree, false),
+        AccountMeta::new_readonly(noop, false),
+        AccountMeta::new_readonly(compression, false),
+    ]
+}
+
+/// Build an SPL Memo instruction recording `memo` in the transaction; it
+/// names no signers, so the memo program only checks the text is UTF-8
+pub fn memo_instruction(memo: &str) -> Instruction {
+    Instruction {
+        program_id: Pubkey::from_str(MEMO_PROGRAM_ID).expect("MEMO_PROGRAM_ID is a valid pubkey"),
+        accounts: vec![],
+        data: memo.as_bytes().to_vec(),
+    }
+}
+
+/// Build the instructions settling a match of two signed orders: an Ed25519
+/// program instruction verifying each signature, by the taker's and the
+/// maker's `signers`, then `settle_match`. The authority pays for the fill
+/// record of an order settled for the first time.
+pub fn settle_match_instructions(
+    program_id: &Pubkey,
+    orderbook: &Pubkey,
+    authority: &Pubkey,
+    trade: &TradeArgs,
+    taker_order: &SignedOrder,
+    maker_order: &SignedOrder,
+    signers: [&Pubkey; 2],
+) -> ClobResult<Vec<Instruction>> {
+    let mut data = instruction_discriminator("settle_match").to_vec();
+    svm_clob_core::borsh::to_writer(&mut data, &(trade, taker_order, maker_order))
+        .map_err(|e| ClobError::SerializationError(e.to_string()))?;
+    let taker = Pubkey::new_from_array(trade.taker);
+    let maker = Pubkey::new_from_array(trade.maker);
+
+    Ok(vec![
+        ed25519_verify_instruction(taker_order, signers[0]),
+        ed25519_verify_instruction(maker_order, signers[1]),
+        Instruction {
+            program_id: *program_id,
+            accounts: vec![
+                AccountMeta::new(*orderbook, false),
+                AccountMeta::new(user_account_address(program_id, &taker).0, false),
+                AccountMeta::new(user_account_address(program_id, &maker).0, false),
+                AccountMeta::new(*authority, true),
+                AccountMeta::new_readonly(sysvar::instructions::ID, false),
+                AccountMeta::new(fill_receipts_address(program_id, &taker).0, false),
+                AccountMeta::new(fill_receipts_address(program_id, &maker).0, false),
+                AccountMeta::new(signed_order_fill_address(program_id, &taker, taker_order.nonce).0, false),
+                AccountMeta::new(signed_order_fill_address(program_id, &maker, maker_order.nonce).0, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data,
+        },
+    ])
+}
+
+/// Ed25519 program instruction verifying a signed order's signature by
+/// `signer`, the order's owner or, for a sub-account, its wallet; the key,
+/// signature and message follow the single offsets entry in its own data
+pub fn ed25519_verify_instruction(signed: &SignedOrder, signer: &Pubkey) -> Instruction {
+    const KEY_OFFSET: u16 = 16;
+    const SIGNATURE_OFFSET: u16 = KEY_OFFSET + 32;
+    const MESSAGE_OFFSET: u16 = SIGNATURE_OFFSET + 64;
+    const THIS_INSTRUCTION: u16 = u16::MAX;
+
+    let message = signed.message();
+    let mut data = vec![1u8, 0];
+    for field in [
+        SIGNATURE_OFFSET,
+        THIS_INSTRUCTION,
+        KEY_OFFSET,
+        THIS_INSTRUCTION,
+        MESSAGE_OFFSET,
+        message.len() as u16,
+        THIS_INSTRUCTION,
+    ] {
+        data.extend_from_slice(&field.to_le_bytes());
+    }
+    data.extend_from_slice(signer.as_ref());
+    data.extend_from_slice(&signed.signature);
+    data.extend_from_slice(&message);
+
+    Instruction {
+        program_id: ed25519_program::ID,
+        accounts: vec![],
+        data,
+    }
+}
+
+/// Anchor account discriminator: first 8 bytes of `sha256("account:<Name>")`
+pub fn account_discriminator(name: &str) -> [u8; 8] {
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash(format!("account:{}", name).as_bytes()).to_bytes()[..8]);
+    discriminator
+}
+
+/// On-chain `OrderBook` account layout (zero-copy, `repr(C)`)
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct OrderBookAccount {
+    pub authority: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub tick_size: u64,
+    pub min_order_size: u64,
+    pub total_volume: u64,
+    pub is_initialized: u8,
+    /// `MarketStatus` byte
+    pub status: u8,
+    /// 1 once settlements append to a compressed receipt tree
+    pub compressed_receipts: u8,
+    pub padding: [u8; 5],
+    pub status_changed_at: i64,
+    /// `OrderTypeWhitelist` bit sets
+    pub order_types: u8,
+    pub time_in_force: u8,
+    pub reserved: [u8; 22],
+}
+
+/// On-chain `UserAccount` account layout (zero-copy, `repr(C)`)
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UserAccountData {
+    pub owner: Pubkey,
+    pub total_volume_traded: u64,
+    pub base_token_balance: u64,
+    pub quote_token_balance: u64,
+    pub is_initialized: u8,
+    pub padding: [u8; 1],
+    /// Index among the wallet's sub-accounts; 0 for the wallet's own account
+    pub sub_account: u16,
+    /// Self-match prevention group; 0 for none
+    pub prevention_group: u32,
+    /// Wallet of a sub-account; the default key for the wallet's own account
+    pub wallet: Pubkey,
+}
+
+/// Decode raw `OrderBook` account data fetched over RPC
+pub fn decode_orderbook_account(data: &[u8]) -> ClobResult<OrderBookAccount> {
+    OrderBookView::new(data).map(|view| view.to_account())
+}
+
+/// Decode raw `UserAccount` account data fetched over RPC
+pub fn decode_user_account(data: &[u8]) -> ClobResult<UserAccountData> {
+    UserAccountView::new(data).map(|view| view.to_account())
+}
+
+/// One fill from a `FillReceipts` account, as seen by the account's owner
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FillReceipt {
+    pub orderbook: Pubkey,
+    /// The owner's order
+    pub order_id: u64,
+    pub counterparty_order_id: u64,
+    pub price: u64,
+    pub quantity: u64,
+    /// Settlement time on chain
+    pub timestamp: i64,
+    /// Side of the owner's order
+    pub side: OrderSide,
+    pub role: LiquidityRole,
+}
+
+/// Decode raw `FillReceipts` account data fetched over RPC, newest fill first
+pub fn decode_fill_receipts(data: &[u8]) -> ClobResult<Vec<FillReceipt>> {
+    FillReceiptsView::new(data)?.receipts()
+}
+
+/// `TradeSettled` event emitted by `execute_trade`
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TradeSettledEvent {
+    pub taker_order_id: u64,
+    pub maker_order_id: u64,
+    pub taker: Pubkey,
+    pub maker: Pubkey,
+    pub price: u64,
+    pub quantity: u64,
+    pub timestamp: i64,
+}
+
+/// `DepositMade` event emitted by `deposit`
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DepositMadeEvent {
+    /// Owner of the credited user account
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+/// `WithdrawalMade` event emitted by `withdraw`
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WithdrawalMadeEvent {
+    /// Owner of the debited user account
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+/// `SubAccountCreated` event emitted by `initialize_sub_account`
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SubAccountCreatedEvent {
+    pub wallet: Pubkey,
+    /// Key the sub-account trades as
+    pub sub_account: Pubkey,
+    pub index: u16,
+    pub name: String,
+    pub timestamp: i64,
+}
+
+/// `SubAccountTransfer` event emitted by `transfer_between_sub_accounts`
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SubAccountTransferEvent {
+    pub wallet: Pubkey,
+    /// Owners of the user accounts debited and credited
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub base_amount: u64,
+    pub quote_amount: u64,
+    pub timestamp: i64,
+}
+
+/// `WithdrawalWhitelistChangeRequested` event emitted when an owner asks to
+/// widen or remove its withdrawal whitelist
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WithdrawalWhitelistChangeRequestedEvent {
+    pub owner: Pubkey,
+    /// Destination to add; the default key when the request removes the whitelist
+    pub destination: Pubkey,
+    pub removes_whitelist: bool,
+    pub effective_at: i64,
+}
+
+/// `InsuranceFunded` event emitted by `fund_insurance`
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InsuranceFundedEvent {
+    pub orderbook: Pubkey,
+    pub funder: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+/// `InsuranceDrawn` event emitted by `draw_insurance`
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InsuranceDrawnEvent {
+    pub orderbook: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+/// `MarketStatusChanged` event emitted by `set_market_status`
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MarketStatusChangedEvent {
+    pub orderbook: Pubkey,
+    /// `MarketStatus` bytes before and after the change
+    pub previous: u8,
+    pub status: u8,
+    pub timestamp: i64,
+}
+
+/// `OrderTypesChanged` event emitted by `set_order_types`; each pair of
+/// bytes is an `OrderTypeWhitelist`
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct OrderTypesChangedEvent {
+    pub orderbook: Pubkey,
+    pub previous_order_types: u8,
+    pub previous_time_in_force: u8,
+    pub order_types: u8,
+    pub time_in_force: u8,
+    pub timestamp: i64,
+}
+
+impl OrderTypesChangedEvent {
+    pub fn previous(&self) -> OrderTypeWhitelist {
+        OrderTypeWhitelist {
+            order_types: self.previous_order_types,
+            time_in_force: self.previous_time_in_force,
+        }
+    }
+
+    pub fn whitelist(&self) -> OrderTypeWhitelist {
+        OrderTypeWhitelist {
+            order_types: self.order_types,
+            time_in_force: self.time_in_force,
+        }
+    }
+}
+
+/// `PreventionGroupSet` event emitted by `set_prevention_group`
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PreventionGroupSetEvent {
+    /// Market whose authority vouched for the group
+    pub orderbook: Pubkey,
+    /// Owner of the user account
+    pub owner: Pubkey,
+    /// 0 when the account left its group
+    pub group: u32,
+    pub timestamp: i64,
+}
+
+/// `TransferCredited` event emitted by `credit_transfer`
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TransferCreditedEvent {
+    /// Owner of the credited user account
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    /// Transaction and top-level instruction of the transfer
+    pub transfer_signature: [u8; 64],
+    pub instruction_index: u8,
+    pub timestamp: i64,
+}
+
+/// `SettlementAdjusted` event emitted by `adjust_settlement`
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SettlementAdjustedEvent {
+    pub adjustment_id: u64,
+    pub trade_id: u64,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    /// Base and quote moved from the taker to the maker; negative the other way
+    pub base_to_maker: i64,
+    pub quote_to_maker: i64,
+    pub timestamp: i64,
+}
+
+/// `ReceiptTreeInitialized` event emitted by `initialize_receipt_tree`
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReceiptTreeInitializedEvent {
+    pub orderbook: Pubkey,
+    pub merkle_tree: Pubkey,
+    pub max_depth: u32,
+    pub max_buffer_size: u32,
+    /// Merkle trees the market has used, this one included
+    pub generation: u32,
+    pub timestamp: i64,
+}
+
+/// `ReceiptsAppended` event emitted by `execute_trade` and `settle_match` in
+/// a market with a compressed receipt tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceiptsAppendedEvent {
+    pub orderbook: Pubkey,
+    pub merkle_tree: Pubkey,
+    /// Leaf of the taker's receipt; the maker's is the next one
+    pub first_leaf_index: u64,
+    pub trade: TradeArgs,
+    pub timestamp: i64,
+}
+
+impl ReceiptsAppendedEvent {
+    /// The taker's and the maker's receipt, as the program hashed them
+    pub fn receipts(&self) -> [FillReceiptLeaf; 2] {
+        FillReceiptLeaf::from_trade(self.orderbook.to_bytes(), &self.trade, self.timestamp)
+    }
+
+    /// The trade is encoded by the core crate's borsh rather than Anchor's,
+    /// so the event is read field by field
+    fn decode(body: &mut &[u8]) -> ClobResult<Self> {
+        Ok(Self {
+            orderbook: deserialize(body)?,
+            merkle_tree: deserialize(body)?,
+            first_leaf_index: deserialize(body)?,
+            trade: svm_clob_core::borsh::BorshDeserialize::deserialize(body)
+                .map_err(|e| ClobError::SerializationError(e.to_string()))?,
+            timestamp: deserialize(body)?,
+        })
+    }
+}
+
+/// Decoded program event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgramEvent {
+    TradeSettled(TradeSettledEvent),
+    DepositMade(DepositMadeEvent),
+    WithdrawalMade(WithdrawalMadeEvent),
+    SubAccountCreated(SubAccountCreatedEvent),
+    SubAccountTransfer(SubAccountTransferEvent),
+    WithdrawalWhitelistChangeRequested(WithdrawalWhitelistChangeRequestedEvent),
+    InsuranceFunded(InsuranceFundedEvent),
+    InsuranceDrawn(InsuranceDrawnEvent),
+    MarketStatusChanged(MarketStatusChangedEvent),
+    OrderTypesChanged(OrderTypesChangedEvent),
+    PreventionGroupSet(PreventionGroupSetEvent),
+    TransferCredited(TransferCreditedEvent),
+    SettlementAdjusted(SettlementAdjustedEvent),
+    ReceiptTreeInitialized(ReceiptTreeInitializedEvent),
+    ReceiptsAppended(ReceiptsAppendedEvent),
+}
+
+/// Anchor event discriminator: first 8 bytes of `sha256("event:<Name>")`
+pub fn event_discriminator(name: &str) -> [u8; 8] {
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash(format!("event:{}", name).as_bytes()).to_bytes()[..8]);
+    discriminator
+}
+
+/// Decode a raw event payload (discriminator + borsh body)
+///
+/// Returns `Ok(None)` for events this crate does not know about.
+pub fn decode_event(data: &[u8]) -> ClobResult<Option<ProgramEvent>> {
+    if data.len() < 8 {
+        return Err(ClobError::SerializationError("Event payload shorter than discriminator".to_string()));
+    }
+    let (discriminator, mut body) = data.split_at(8);
+
+    let event = if discriminator == event_discriminator("TradeSettled") {
+        ProgramEvent::TradeSettled(deserialize(&mut body)?)
+    } else if discriminator == event_discriminator("DepositMade") {
+        ProgramEvent::DepositMade(deserialize(&mut body)?)
+    } else if discriminator == event_discriminator("WithdrawalMade") {
+        ProgramEvent::WithdrawalMade(deserialize(&mut body)?)
+    } else if discriminator == event_discriminator("SubAccountCreated") {
+        ProgramEvent::SubAccountCreated(deserialize(&mut body)?)
+    } else if discriminator == event_discriminator("SubAccountTransfer") {
+        ProgramEvent::SubAccountTransfer(deserialize(&mut body)?)
+    } else if discriminator == event_discriminator("WithdrawalWhitelistChangeRequested") {
+        ProgramEvent::WithdrawalWhitelistChangeRequested(deserialize(&mut body)?)
+    } else if discriminator == event_discriminator("InsuranceFunded") {
+        ProgramEvent::InsuranceFunded(deserialize(&mut body)?)
+    } else if discriminator == event_discriminator("InsuranceDrawn") {
+        ProgramEvent::InsuranceDrawn(deserialize(&mut body)?)
+    } else if discriminator == event_discriminator("MarketStatusChanged") {
+        ProgramEvent::MarketStatusChanged(deserialize(&mut body)?)
+    } else if discriminator == event_discriminator("OrderTypesChanged") {
+        ProgramEvent::OrderTypesChanged(deserialize(&mut body)?)
+    } else if discriminator == event_discriminator("PreventionGroupSet") {
+        ProgramEvent::PreventionGroupSet(deserialize(&mut body)?)
+    } else if discriminator == event_discriminator("TransferCredited") {
+        ProgramEvent::TransferCredited(deserialize(&mut body)?)
+    } else if discriminator == event_discriminator("SettlementAdjusted") {
+        ProgramEvent::SettlementAdjusted(deserialize(&mut body)?)
+    } else if discriminator == event_discriminator("ReceiptTreeInitialized") {
+        ProgramEvent::ReceiptTreeInitialized(deserialize(&mut body)?)
+    } else if discriminator == event_discriminator("ReceiptsAppended") {
+        ProgramEvent::ReceiptsAppended(ReceiptsAppendedEvent::decode(&mut body)?)
+    } else {
+        return Ok(None);
+    };
+
+    Ok(Some(event))
+}
+
+/// Extract the events emitted by `program_id` from a transaction's log messages
+///
+/// Only `Program data:` lines logged while `program_id` is the innermost
+/// executing program are considered, so CPI callees cannot spoof events.
+pub fn parse_log_events(program_id: &Pubkey, logs: &[String]) -> ClobResult<Vec<ProgramEvent>> {
+    let program = program_id.to_string();
+    let mut call_stack: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+
+    for log in logs {
+        if let Some(rest) = log.strip_prefix("Program ") {
+            let mut parts = rest.split_whitespace();
+            match (parts.next(), parts.next()) {
+                (Some(id), Some("invoke")) => {
+                    call_stack.push(id);
+                    continue;
+                }
+                (Some(id), Some("success")) | (Some(id), Some("failed:")) => {
+                    if call_stack.last() == Some(&id) {
+                        call_stack.pop();
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(payload) = log.strip_prefix(PROGRAM_DATA_PREFIX) {
+            if call_stack.last().copied() != Some(program.as_str()) {
+                continue;
+            }
+            let data = STANDARD
+                .decode(payload.trim())
+                .map_err(|e| ClobError::SerializationError(e.to_string()))?;
+            if let Some(event) = decode_event(&data)? {
+                events.push(event);
+            }
+        }
+    }
+
+    Ok(events)
+}
+
+fn deserialize<T: AnchorDeserialize>(body: &mut &[u8]) -> ClobResult<T> {
+    T::deserialize(body).map_err(|e| ClobError::SerializationError(e.to_string()))
+}
@@ -0,0 +1,128 @@
+/// Proofs of compressed fill receipts
+///
+/// A market with a receipt tree appends both sides of every settled fill to
+/// an SPL account compression concurrent Merkle tree. Leaf `i` is the
+/// keccak-256 hash of the `i`th receipt's `FillReceiptLeaf::leaf_data`, a
+/// node is the hash of its two children concatenated, and a leaf not yet
+/// appended is 32 zero bytes. Rebuilding the tree from the indexed receipts
+/// gives its root and the proof of any leaf, which anyone can check against
+/// the roots the tree account holds on chain.
+
+use crate::{ClobError, ClobResult, CompressedReceipt, FillReceiptLeaf};
+use anchor_lang::solana_program::keccak::{hashv, Hash};
+use serde::{Deserialize, Serialize};
+
+/// Size of a concurrent Merkle tree account for `max_depth` and
+/// `max_buffer_size` with the top `canopy_depth` levels cached on chain:
+/// the account compression header, the tree's sequence number, active
+/// index and buffer size, the change log buffer, the rightmost proof and
+/// the canopy
+pub fn merkle_tree_account_size(max_depth: u32, max_buffer_size: u32, canopy_depth: u32) -> usize {
+    let depth = max_depth as usize;
+    let header = 56;
+    let counters = 24;
+    let change_log = max_buffer_size as usize * (40 + 32 * depth);
+    let rightmost_proof = 32 * depth + 40;
+    let canopy = if canopy_depth == 0 { 0 } else { 32 * ((1usize << (canopy_depth + 1)) - 2) };
+    header + counters + change_log + rightmost_proof + canopy
+}
+
+/// Leaf the program appends for `receipt`
+pub fn leaf_hash(receipt: &FillReceiptLeaf) -> [u8; 32] {
+    hashv(&[&receipt.leaf_data()]).to_bytes()
+}
+
+fn parent(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    hashv(&[left, right]).to_bytes()
+}
+
+/// Proof of the leaf at `index` among `leaves`, the leaves of a tree of
+/// `max_depth` in order from the first: the sibling of each node from the
+/// leaf up, and the root
+pub fn merkle_proof(leaves: &[[u8; 32]], index: usize, max_depth: u32) -> ClobResult<(Vec<[u8; 32]>, [u8; 32])> {
+    if index >= leaves.len() {
+        return Err(ClobError::StorageError(format!(
+            "leaf {} is not among the {} leaves of the tree",
+            index,
+            leaves.len()
+        )));
+    }
+    if max_depth >= usize::BITS || leaves.len() > 1 << max_depth {
+        return Err(ClobError::StorageError(format!(
+            "{} leaves do not fit a tree of depth {}",
+            leaves.len(),
+            max_depth
+        )));
+    }
+
+    // Nodes past the last leaf are roots of empty subtrees, the same at each level
+    let mut empty = [0u8; 32];
+    let mut level = leaves.to_vec();
+    let mut position = index;
+    let mut proof = Vec::with_capacity(max_depth as usize);
+    for _ in 0..max_depth {
+        proof.push(level.get(position ^ 1).copied().unwrap_or(empty));
+        level = level
+            .chunks(2)
+            .map(|pair| parent(&pair[0], pair.get(1).unwrap_or(&empty)))
+            .collect();
+        empty = parent(&empty, &empty);
+        position /= 2;
+    }
+    Ok((proof, level.first().copied().unwrap_or(empty)))
+}
+
+/// Whether `proof` takes `leaf` at `index` up to `root`
+pub fn verify_merkle_proof(root: &[u8; 32], leaf: &[u8; 32], index: u64, proof: &[[u8; 32]]) -> bool {
+    let node = proof.iter().enumerate().fold(*leaf, |node, (level, sibling)| {
+        if (index >> level) & 1 == 0 {
+            parent(&node, sibling)
+        } else {
+            parent(sibling, &node)
+        }
+    });
+    node == *root
+}
+
+/// A compressed receipt with the proof of its leaf; hashes are base58, as
+/// Solana tools print them
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReceiptProof {
+    pub receipt: CompressedReceipt,
+    /// Hash of the receipt's leaf data
+    pub leaf: String,
+    /// Siblings of the nodes from the leaf up to the root
+    pub proof: Vec<String>,
+    /// Root of the tree holding the indexed receipts
+    pub root: String,
+    /// Receipts the root covers; the on-chain tree may be a few ahead or
+    /// behind, and accepts proofs against its recent roots
+    pub leaf_count: u64,
+}
+
+impl ReceiptProof {
+    /// Proof of `receipt` among `leaves`, every leaf of its tree so far
+    pub fn new(receipt: CompressedReceipt, leaves: &[[u8; 32]], max_depth: u32) -> ClobResult<Self> {
+        let leaf = leaf_hash(&receipt.leaf());
+        let index = usize::try_from(receipt.leaf_index)
+            .map_err(|_| ClobError::StorageError(format!("leaf index {} out of range", receipt.leaf_index)))?;
+        if leaves.get(index) != Some(&leaf) {
+            return Err(ClobError::StorageError(format!(
+                "indexed leaf {} of {} does not match its receipt",
+                receipt.leaf_index, receipt.merkle_tree
+            )));
+        }
+        let (proof, root) = merkle_proof(leaves, index, max_depth)?;
+        Ok(Self {
+            receipt,
+            leaf: encode(&leaf),
+            proof: proof.iter().map(encode).collect(),
+            root: encode(&root),
+            leaf_count: leaves.len() as u64,
+        })
+    }
+}
+
+fn encode(hash: &[u8; 32]) -> String {
+    Hash::new_from_array(*hash).to_string()
+}
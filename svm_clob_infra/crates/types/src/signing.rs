@@ -0,0 +1,108 @@
+/// Signed order helpers
+///
+/// A `SignedOrder` is an order intent plus the owner's ed25519 signature over
+/// `SignedOrder::signing_message`; a sub-account has no private key, so its
+/// wallet signs in its place. The same bytes are checked by the REST API on
+/// order entry and by the program's `settle_match` instruction, which reads
+/// them from an Ed25519 program instruction in the settling transaction.
+
+use crate::{ClobError, ClobResult, MarketId, OrderSignature, PlaceOrderRequest};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Signature, Signer};
+use svm_clob_core::{OrderIntent, SignedOrder};
+
+/// Intent of a place order request on `market`
+pub fn order_intent(request: &PlaceOrderRequest, market: &MarketId) -> ClobResult<OrderIntent> {
+    let owner = request
+        .owner
+        .parse::<Pubkey>()
+        .map_err(|e| ClobError::InvalidSignature(format!("Invalid owner: {}", e)))?;
+    Ok(OrderIntent {
+        market: market.0.to_bytes(),
+        owner: owner.to_bytes(),
+        client_order_id: request.client_order_id,
+        side: request.side,
+        order_type: request.order_type,
+        price: request.price,
+        quantity: request.quantity,
+        time_in_force: request.time_in_force,
+        self_trade_behavior: request.self_trade_behavior,
+    })
+}
+
+/// Sign an intent; `signer` must be the intent's owner, or the wallet of a
+/// sub-account owner, for it to verify
+pub fn sign_order<S: Signer + ?Sized>(order_intent: OrderIntent, nonce: u64, expiry: i64, signer: &S) -> SignedOrder {
+    let message = SignedOrder::signing_message(&order_intent, nonce, expiry);
+    SignedOrder {
+        order_intent,
+        nonce,
+        expiry,
+        signature: signature_bytes(&signer.sign_message(&message)),
+    }
+}
+
+/// Check that `signer`, the intent's owner or its wallet, signed this order
+pub fn verify_signature(signed: &SignedOrder, signer: &Pubkey) -> ClobResult<()> {
+    let signature = Signature::from(signed.signature);
+    if signature.verify(signer.as_ref(), &signed.message()) {
+        Ok(())
+    } else {
+        Err(ClobError::InvalidSignature("Signature does not match the order owner".to_string()))
+    }
+}
+
+/// Check the signature by `signer` and that it has not expired at `now`
+/// (unix seconds)
+pub fn verify_signed_order(signed: &SignedOrder, signer: &Pubkey, now: i64) -> ClobResult<()> {
+    if signed.is_expired(now) {
+        return Err(ClobError::SignatureExpired);
+    }
+    verify_signature(signed, signer)
+}
+
+/// Sign a place order request in place for `market`
+pub fn sign_request<S: Signer + ?Sized>(
+    request: &mut PlaceOrderRequest,
+    market: &MarketId,
+    nonce: u64,
+    expiry: i64,
+    signer: &S,
+) -> ClobResult<()> {
+    let signed = sign_order(order_intent(request, market)?, nonce, expiry, signer);
+    request.signature = Some(OrderSignature::from(&signed));
+    Ok(())
+}
+
+/// Rebuild the signed order a place order request carries, if any
+pub fn signed_request(request: &PlaceOrderRequest, market: &MarketId) -> ClobResult<Option<SignedOrder>> {
+    let Some(signature) = &request.signature else {
+        return Ok(None);
+    };
+    let parsed = signature
+        .signature
+        .parse::<Signature>()
+        .map_err(|e| ClobError::InvalidSignature(e.to_string()))?;
+    Ok(Some(SignedOrder {
+        order_intent: order_intent(request, market)?,
+        nonce: signature.nonce,
+        expiry: signature.expiry,
+        signature: signature_bytes(&parsed),
+    }))
+}
+
+impl From<&SignedOrder> for OrderSignature {
+    fn from(signed: &SignedOrder) -> Self {
+        OrderSignature {
+            nonce: signed.nonce,
+            expiry: signed.expiry,
+            signature: Signature::from(signed.signature).to_string(),
+        }
+    }
+}
+
+fn signature_bytes(signature: &Signature) -> [u8; 64] {
+    let mut bytes = [0u8; 64];
+    bytes.copy_from_slice(signature.as_ref());
+    bytes
+}
@@ -0,0 +1,157 @@
+/// End-of-day statements
+///
+/// A statement is one user's account activity over a UTC day: the fills they
+/// took part in, the fees those accrued, deposits, withdrawals and transfers
+/// between their wallet's accounts, and their ledger balance at the start and
+/// end of the day. Statements are generated once a day has closed and stored
+/// as JSON, CSV and PDF artifacts for back offices to download.
+
+use crate::*;
+use chrono::{NaiveDate, TimeZone, Utc};
+
+/// Length of a statement day
+pub const STATEMENT_DAY_SECS: i64 = 86_400;
+
+/// Format of a statement artifact
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum StatementFormat {
+    Json = 0,
+    Csv = 1,
+    Pdf = 2,
+}
+
+impl StatementFormat {
+    /// Media type the artifact is served with
+    pub fn content_type(self) -> &'static str {
+        match self {
+            StatementFormat::Json => "application/json",
+            StatementFormat::Csv => "text/csv",
+            StatementFormat::Pdf => "application/pdf",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            StatementFormat::Json => "json",
+            StatementFormat::Csv => "csv",
+            StatementFormat::Pdf => "pdf",
+        }
+    }
+}
+
+/// UTC date of the day starting at `period_start`, as `YYYY-MM-DD`
+pub fn statement_date(period_start: i64) -> String {
+    Utc.timestamp_opt(period_start, 0)
+        .single()
+        .map(|time| time.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| period_start.to_string())
+}
+
+/// Start of the UTC day `date` names, as `YYYY-MM-DD`
+pub fn statement_period_start(date: &str) -> Option<i64> {
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp())
+}
+
+/// Statement day, as generated
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StatementDay {
+    /// Day bounds, start inclusive and end exclusive
+    pub period_start: i64,
+    pub period_end: i64,
+    /// Users a statement was generated for
+    pub statements: u32,
+    pub generated_at: i64,
+}
+
+/// One user's account activity over a statement day
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Statement {
+    pub owner: Pubkey,
+    /// `YYYY-MM-DD` of the UTC day covered
+    pub date: String,
+    /// Day bounds, start inclusive and end exclusive
+    pub period_start: i64,
+    pub period_end: i64,
+    pub market_id: MarketId,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    #[serde(flatten)]
+    pub metadata: MarketMetadata,
+    /// Ledger balance before the day's first movement
+    pub opening_balance: LedgerBalance,
+    /// Ledger balance after the day's last movement; amounts held reflect
+    /// finality when the statement was generated
+    pub ending_balance: LedgerBalance,
+    /// Fills matched in the day, oldest first, whether settled or not
+    pub fills: Vec<UserFill>,
+    /// Fees accrued by the day's fills, net of rebates and adjustments
+    pub fees: FeeReport,
+    pub deposits: Vec<DepositRecord>,
+    pub withdrawals: Vec<WithdrawalRecord>,
+    /// Moves between the user's account and others of the same wallet
+    pub transfers: Vec<SubAccountTransferRecord>,
+    pub generated_at: i64,
+}
+
+impl Statement {
+    pub fn summary(&self) -> StatementSummary {
+        StatementSummary {
+            owner: self.owner,
+            date: self.date.clone(),
+            period_start: self.period_start,
+            period_end: self.period_end,
+            fills: self.fills.len() as u32,
+            deposits: self.deposits.len() as u32,
+            withdrawals: self.withdrawals.len() as u32,
+            transfers: self.transfers.len() as u32,
+            total_fees: self.fees.total_fees,
+            ending_base_balance: self.ending_balance.base_balance,
+            ending_quote_balance: self.ending_balance.quote_balance,
+            generated_at: self.generated_at,
+        }
+    }
+}
+
+/// What a stored statement covers, as listed to its user
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StatementSummary {
+    pub owner: Pubkey,
+    pub date: String,
+    pub period_start: i64,
+    pub period_end: i64,
+    pub fills: u32,
+    pub deposits: u32,
+    pub withdrawals: u32,
+    pub transfers: u32,
+    pub total_fees: i64,
+    pub ending_base_balance: i64,
+    pub ending_quote_balance: i64,
+    pub generated_at: i64,
+}
+
+/// A statement rendered in every format, as stored
+#[derive(Debug, Clone)]
+pub struct StatementArtifacts {
+    pub summary: StatementSummary,
+    pub json: Vec<u8>,
+    pub csv: Vec<u8>,
+    pub pdf: Vec<u8>,
+}
+
+impl StatementArtifacts {
+    pub fn get(&self, format: StatementFormat) -> &[u8] {
+        match format {
+            StatementFormat::Json => &self.json,
+            StatementFormat::Csv => &self.csv,
+            StatementFormat::Pdf => &self.pdf,
+        }
+    }
+
+    /// File name the artifact is downloaded as
+    pub fn file_name(owner: &Pubkey, date: &str, format: StatementFormat) -> String {
+        format!("statement-{}-{}.{}", owner, date, format.extension())
+    }
+}
@@ -0,0 +1,304 @@
+/// Fixed-point units
+///
+/// Prices and quantities travel as native `u64` integers, exactly as the program
+/// stores them: a `Quantity` counts base-mint native units, a `Price` counts
+/// quote-mint native units per base native unit, and their product is a
+/// `Notional` in quote native units (the amount `execute_trade` transfers).
+/// `MarketUnits` converts between these and the decimal strings users see.
+
+use crate::{ClobError, ClobResult, Order, TradeExecution};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+
+/// Quote native units per base native unit
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[serde(transparent)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Price(pub u64);
+
+/// Base native units
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[serde(transparent)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Quantity(pub u64);
+
+/// Quote native units, wide enough for any `Price * Quantity`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[serde(transparent)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Notional(pub u128);
+
+impl Price {
+    /// Value of `quantity` at this price
+    pub fn notional(self, quantity: Quantity) -> Notional {
+        Notional(self.0 as u128 * quantity.0 as u128)
+    }
+
+    /// Whether the price is a multiple of `tick_size`
+    pub fn is_aligned(self, tick_size: u64) -> bool {
+        tick_size == 0 || self.0 % tick_size == 0
+    }
+
+    /// Distance to `reference` in basis points of `reference`
+    pub fn distance_bps(self, reference: Price) -> u128 {
+        if reference.0 == 0 {
+            return u128::MAX;
+        }
+        self.0.abs_diff(reference.0) as u128 * 10_000 / reference.0 as u128
+    }
+}
+
+impl Quantity {
+    pub fn saturating_sub(self, other: Quantity) -> Quantity {
+        Quantity(self.0.saturating_sub(other.0))
+    }
+}
+
+impl Notional {
+    /// Amount as transferred on-chain, or `None` when it does not fit a `u64`
+    pub fn to_native(self) -> Option<u64> {
+        u64::try_from(self.0).ok()
+    }
+
+    /// Amount as a signed ledger delta, or `None` when it does not fit an `i64`
+    pub fn to_signed(self) -> Option<i64> {
+        i64::try_from(self.0).ok()
+    }
+
+    /// `fee_bps` basis points of this amount; positive results round up and
+    /// negative ones (rebates) round toward zero
+    pub fn fee(self, fee_bps: i64) -> i128 {
+        let scaled = self.0 as i128 * fee_bps as i128;
+        if scaled > 0 {
+            (scaled + 9_999) / 10_000
+        } else {
+            scaled / 10_000
+        }
+    }
+}
+
+macro_rules! impl_unit {
+    ($unit:ident, $inner:ty) => {
+        impl From<$inner> for $unit {
+            fn from(value: $inner) -> Self {
+                $unit(value)
+            }
+        }
+
+        impl From<$unit> for $inner {
+            fn from(value: $unit) -> Self {
+                value.0
+            }
+        }
+
+        impl fmt::Display for $unit {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl Add for $unit {
+            type Output = $unit;
+            fn add(self, other: $unit) -> $unit {
+                $unit(self.0 + other.0)
+            }
+        }
+
+        impl AddAssign for $unit {
+            fn add_assign(&mut self, other: $unit) {
+                self.0 += other.0;
+            }
+        }
+
+        impl Sub for $unit {
+            type Output = $unit;
+            fn sub(self, other: $unit) -> $unit {
+                $unit(self.0 - other.0)
+            }
+        }
+
+        impl SubAssign for $unit {
+            fn sub_assign(&mut self, other: $unit) {
+                self.0 -= other.0;
+            }
+        }
+
+        impl Sum for $unit {
+            fn sum<I: Iterator<Item = $unit>>(iter: I) -> $unit {
+                $unit(iter.map(|unit| unit.0).sum())
+            }
+        }
+    };
+}
+
+impl_unit!(Price, u64);
+impl_unit!(Quantity, u64);
+impl_unit!(Notional, u128);
+
+/// Decimal and increment context of one market
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarketUnits {
+    /// Decimals of the base mint
+    pub base_decimals: u8,
+    /// Decimals of the quote mint
+    pub quote_decimals: u8,
+    /// Price increment in native units
+    pub tick_size: u64,
+    /// Quantity increment in native units
+    pub lot_size: u64,
+}
+
+impl MarketUnits {
+    /// Native price of a decimal price in whole quote tokens per whole base token
+    ///
+    /// Fails when the price is not exactly representable or not tick aligned.
+    pub fn price_from_ui(&self, ui: &str) -> ClobResult<Price> {
+        let invalid = |reason: &str| ClobError::InvalidPrice(format!("'{}' {}", ui, reason));
+        let (mantissa, scale) = parse_decimal(ui).ok_or_else(|| invalid("is not a decimal number"))?;
+
+        // native = ui * 10^quote / 10^base
+        let numerator = mantissa
+            .checked_mul(pow10(self.quote_decimals as u32).ok_or_else(|| invalid("is out of range"))?)
+            .ok_or_else(|| invalid("is out of range"))?;
+        let denominator = pow10(scale + self.base_decimals as u32).ok_or_else(|| invalid("has too many decimals"))?;
+        if numerator % denominator != 0 {
+            return Err(invalid("is finer than one native unit"));
+        }
+        let native = u64::try_from(numerator / denominator).map_err(|_| invalid("is out of range"))?;
+
+        let price = Price(native);
+        if !price.is_aligned(self.tick_size) {
+            return Err(ClobError::PriceNotAlignedToTickSize);
+        }
+        Ok(price)
+    }
+
+    /// Decimal price in whole quote tokens per whole base token
+    pub fn price_to_ui(&self, price: Price) -> String {
+        let whole = price.0 as u128 * pow10(self.base_decimals as u32).unwrap_or(1);
+        format_decimal(whole, self.quote_decimals as u32)
+    }
+
+    /// Native quantity of a decimal amount of whole base tokens
+    ///
+    /// Fails when the amount is not exactly representable or not lot aligned.
+    pub fn quantity_from_ui(&self, ui: &str) -> ClobResult<Quantity> {
+        let invalid = |reason: &str| ClobError::InvalidQuantity(format!("'{}' {}", ui, reason));
+        let (mantissa, scale) = parse_decimal(ui).ok_or_else(|| invalid("is not a decimal number"))?;
+
+        let base = self.base_decimals as u32;
+        if scale > base && mantissa % pow10(scale - base).ok_or_else(|| invalid("has too many decimals"))? != 0 {
+            return Err(invalid("is finer than one native unit"));
+        }
+        let native = if scale > base {
+            mantissa / pow10(scale - base).unwrap_or(1)
+        } else {
+            mantissa
+                .checked_mul(pow10(base - scale).ok_or_else(|| invalid("is out of range"))?)
+                .ok_or_else(|| invalid("is out of range"))?
+        };
+        let native = u64::try_from(native).map_err(|_| invalid("is out of range"))?;
+
+        if self.lot_size > 1 && native % self.lot_size != 0 {
+            return Err(invalid(&format!("is not a multiple of the lot size {}", self.lot_size)));
+        }
+        Ok(Quantity(native))
+    }
+
+    /// Decimal amount of whole base tokens
+    pub fn quantity_to_ui(&self, quantity: Quantity) -> String {
+        format_decimal(quantity.0 as u128, self.base_decimals as u32)
+    }
+
+    /// Decimal amount of whole quote tokens
+    pub fn notional_to_ui(&self, notional: Notional) -> String {
+        format_decimal(notional.0, self.quote_decimals as u32)
+    }
+}
+
+/// Something with one price and one size
+pub trait Priced {
+    fn price(&self) -> Price;
+    fn quantity(&self) -> Quantity;
+}
+
+impl Priced for Order {
+    fn price(&self) -> Price {
+        Price(self.price)
+    }
+
+    fn quantity(&self) -> Quantity {
+        Quantity(self.quantity)
+    }
+}
+
+impl Priced for TradeExecution {
+    fn price(&self) -> Price {
+        Price(self.price)
+    }
+
+    fn quantity(&self) -> Quantity {
+        Quantity(self.quantity)
+    }
+}
+
+/// A value with its price and size also rendered in whole tokens
+///
+/// Serializes as the value's own fields plus `ui_price` and `ui_quantity`,
+/// which are left out when the market's decimals are unknown, so readers of
+/// the plain value are unaffected.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Displayed<T> {
+    #[serde(flatten)]
+    pub value: T,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ui_price: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ui_quantity: Option<String>,
+}
+
+impl<T: Priced> Displayed<T> {
+    pub fn new(value: T, units: Option<&MarketUnits>) -> Self {
+        Displayed {
+            ui_price: units.map(|units| units.price_to_ui(value.price())),
+            ui_quantity: units.map(|units| units.quantity_to_ui(value.quantity())),
+            value,
+        }
+    }
+}
+
+fn pow10(exponent: u32) -> Option<u128> {
+    10u128.checked_pow(exponent)
+}
+
+/// Split a non-negative decimal string into mantissa and number of fraction digits
+fn parse_decimal(value: &str) -> Option<(u128, u32)> {
+    let value = value.trim();
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+    if !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let digits = format!("{}{}", whole, fraction);
+    let mantissa = if digits.is_empty() { 0 } else { digits.parse().ok()? };
+    Some((mantissa, fraction.len() as u32))
+}
+
+/// Render `value / 10^scale` without trailing fraction zeros
+fn format_decimal(value: u128, scale: u32) -> String {
+    let Some(divisor) = pow10(scale) else {
+        return value.to_string();
+    };
+    let whole = value / divisor;
+    let fraction = value % divisor;
+    if fraction == 0 {
+        return whole.to_string();
+    }
+    let fraction = format!("{:0width$}", fraction, width = scale as usize);
+    format!("{}.{}", whole, fraction.trim_end_matches('0'))
+}
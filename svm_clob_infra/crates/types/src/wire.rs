@@ -0,0 +1,365 @@
+/// Versioned wire envelopes
+///
+/// Every REST result and WebSocket frame travels as `{"v", "type", "payload"}`.
+/// Readers accept any version up to `WIRE_VERSION` and upgrade older payloads
+/// one version at a time before deserializing, so schema changes only need an
+/// upgrade step here rather than a coordinated client release. Payloads that
+/// arrive without an envelope are read as version 1.
+///
+/// Versions:
+/// - 1: unversioned payloads, before market IDs
+/// - 2: `market_id` on orders, trades, snapshots and market data updates
+/// - 3: listings return a `Page` instead of a bare list
+
+use crate::program::DepositInstructions;
+use crate::{
+    AdminAuditEntry, ApiAuditEntry, Balances, BatchOrderResponse, Candle, ChainTransaction, ClobError, ClobResult, DeadLetter, Depth, Displayed,
+    EnginePause, FeeReport, IncentiveEpoch, IncentiveReport, InsuranceFund, InsuranceFundEvent, MarketDataUpdate,
+    MarketId, MarketInfo, MarketMakerObligation, MarketMakerScorecard, MarketStats, MmpStatus, Order,
+    OrderBookSnapshot, Page, Portfolio, QueuePosition, SequenceMapping, SubAccount, SurveillanceAlert, Suspension, Tenant, TenantApiKey, TenantUsage, Ticker, TradeAdjustment, TradeExecution,
+    TradeHistory, UserFeeTier, VaultTransfer,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Wire version written by this build
+pub const WIRE_VERSION: u16 = 3;
+
+/// Versioned message envelope
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Envelope<T> {
+    /// Schema version of `payload`
+    pub v: u16,
+    /// Payload kind
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub payload: T,
+}
+
+/// Payload that can travel in an envelope
+pub trait WirePayload: Serialize + DeserializeOwned {
+    /// Envelope `type` of this payload
+    const TYPE: &'static str;
+
+    /// Rewrite a payload written at `version` into `version + 1`
+    fn upgrade(_version: u16, _payload: &mut Value) -> ClobResult<()> {
+        Ok(())
+    }
+}
+
+impl<T: WirePayload> Envelope<T> {
+    /// Wrap a payload at the current version
+    pub fn new(payload: T) -> Self {
+        Self {
+            v: WIRE_VERSION,
+            kind: T::TYPE.to_string(),
+            payload,
+        }
+    }
+}
+
+impl Envelope<Value> {
+    /// Read an envelope of any version; a value without one is a version 1 payload
+    pub fn from_value(value: Value) -> ClobResult<Self> {
+        match &value {
+            Value::Object(fields) if fields.contains_key("v") && fields.contains_key("payload") => {
+                serde_json::from_value(value).map_err(|e| ClobError::SerializationError(e.to_string()))
+            }
+            _ => Ok(Self {
+                v: 1,
+                kind: String::new(),
+                payload: value,
+            }),
+        }
+    }
+
+    /// Upgrade the payload to the current version and decode it
+    pub fn open<T: WirePayload>(mut self) -> ClobResult<T> {
+        if self.v > WIRE_VERSION {
+            return Err(ClobError::SerializationError(format!(
+                "Unsupported wire version {} (newest known is {})",
+                self.v, WIRE_VERSION
+            )));
+        }
+        if !self.kind.is_empty() && self.kind != T::TYPE {
+            return Err(ClobError::SerializationError(format!(
+                "Expected a {} payload, got {}",
+                T::TYPE,
+                self.kind
+            )));
+        }
+        upgrade_to_current::<T>(self.v, &mut self.payload)?;
+        serde_json::from_value(self.payload).map_err(|e| ClobError::SerializationError(e.to_string()))
+    }
+}
+
+/// Apply every upgrade step from `version` to `WIRE_VERSION`
+pub fn upgrade_to_current<T: WirePayload>(version: u16, payload: &mut Value) -> ClobResult<()> {
+    for step in version.max(1)..WIRE_VERSION {
+        T::upgrade(step, payload)?;
+    }
+    Ok(())
+}
+
+/// Lists upgrade element by element and share the element's type
+impl<T: WirePayload> WirePayload for Vec<T> {
+    const TYPE: &'static str = T::TYPE;
+
+    fn upgrade(version: u16, payload: &mut Value) -> ClobResult<()> {
+        if let Value::Array(items) = payload {
+            for item in items {
+                T::upgrade(version, item)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Pages share the element's type; version 2 listings were bare lists
+impl<T: WirePayload> WirePayload for Page<T> {
+    const TYPE: &'static str = T::TYPE;
+
+    fn upgrade(version: u16, payload: &mut Value) -> ClobResult<()> {
+        match payload {
+            Value::Array(_) => Vec::<T>::upgrade(version, payload)?,
+            Value::Object(fields) => {
+                if let Some(items) = fields.get_mut("items") {
+                    Vec::<T>::upgrade(version, items)?;
+                }
+            }
+            _ => {}
+        }
+        if version == 2 {
+            if let Value::Array(items) = payload {
+                *payload = serde_json::json!({ "items": std::mem::take(items), "next_cursor": null });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rendered values are the value's fields plus UI strings
+impl<T: WirePayload> WirePayload for Displayed<T> {
+    const TYPE: &'static str = T::TYPE;
+
+    fn upgrade(version: u16, payload: &mut Value) -> ClobResult<()> {
+        T::upgrade(version, payload)
+    }
+}
+
+impl<T: WirePayload> WirePayload for Option<T> {
+    const TYPE: &'static str = T::TYPE;
+
+    fn upgrade(version: u16, payload: &mut Value) -> ClobResult<()> {
+        match payload {
+            Value::Null => Ok(()),
+            payload => T::upgrade(version, payload),
+        }
+    }
+}
+
+/// Version 1 payloads predate market IDs; they belong to the unset market
+fn add_market_id(version: u16, payload: &mut Value) {
+    if version == 1 {
+        if let Value::Object(fields) = payload {
+            fields
+                .entry("market_id")
+                .or_insert_with(|| Value::String(MarketId::default().to_string()));
+        }
+    }
+}
+
+impl WirePayload for Order {
+    const TYPE: &'static str = "Order";
+
+    fn upgrade(version: u16, payload: &mut Value) -> ClobResult<()> {
+        add_market_id(version, payload);
+        Ok(())
+    }
+}
+
+impl WirePayload for TradeExecution {
+    const TYPE: &'static str = "Trade";
+
+    fn upgrade(version: u16, payload: &mut Value) -> ClobResult<()> {
+        add_market_id(version, payload);
+        Ok(())
+    }
+}
+
+impl WirePayload for OrderBookSnapshot {
+    const TYPE: &'static str = "OrderBook";
+
+    fn upgrade(version: u16, payload: &mut Value) -> ClobResult<()> {
+        add_market_id(version, payload);
+        Ok(())
+    }
+}
+
+impl WirePayload for QueuePosition {
+    const TYPE: &'static str = "QueuePosition";
+}
+
+impl WirePayload for MarketDataUpdate {
+    const TYPE: &'static str = "MarketData";
+
+    fn upgrade(version: u16, payload: &mut Value) -> ClobResult<()> {
+        add_market_id(version, payload);
+        if let Value::Object(fields) = payload {
+            if let Some(order_book) = fields.get_mut("order_book") {
+                Option::<OrderBookSnapshot>::upgrade(version, order_book)?;
+            }
+            if let Some(trade) = fields.get_mut("trade") {
+                Option::<TradeExecution>::upgrade(version, trade)?;
+            }
+            if let Some(order) = fields.get_mut("order") {
+                Option::<Order>::upgrade(version, order)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl WirePayload for BatchOrderResponse {
+    const TYPE: &'static str = "BatchOrderResponse";
+
+    fn upgrade(version: u16, payload: &mut Value) -> ClobResult<()> {
+        if let Value::Object(fields) = payload {
+            for key in ["cancelled", "placed"] {
+                if let Some(orders) = fields.get_mut(key) {
+                    Vec::<Order>::upgrade(version, orders)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl WirePayload for MarketStats {
+    const TYPE: &'static str = "MarketStats";
+}
+
+impl WirePayload for Ticker {
+    const TYPE: &'static str = "Ticker";
+}
+
+impl WirePayload for Depth {
+    const TYPE: &'static str = "Depth";
+}
+
+impl WirePayload for Candle {
+    const TYPE: &'static str = "Candle";
+}
+
+impl WirePayload for MarketInfo {
+    const TYPE: &'static str = "Market";
+}
+
+impl WirePayload for FeeReport {
+    const TYPE: &'static str = "FeeReport";
+}
+
+impl WirePayload for EnginePause {
+    const TYPE: &'static str = "EnginePause";
+}
+
+impl WirePayload for MmpStatus {
+    const TYPE: &'static str = "MmpStatus";
+}
+
+impl WirePayload for UserFeeTier {
+    const TYPE: &'static str = "FeeTier";
+}
+
+impl WirePayload for SubAccount {
+    const TYPE: &'static str = "SubAccount";
+}
+
+impl WirePayload for SurveillanceAlert {
+    const TYPE: &'static str = "SurveillanceAlert";
+}
+
+impl WirePayload for AdminAuditEntry {
+    const TYPE: &'static str = "AdminAuditEntry";
+}
+
+impl WirePayload for ApiAuditEntry {
+    const TYPE: &'static str = "ApiAuditEntry";
+}
+
+impl WirePayload for Suspension {
+    const TYPE: &'static str = "Suspension";
+}
+
+impl WirePayload for VaultTransfer {
+    const TYPE: &'static str = "VaultTransfer";
+}
+
+impl WirePayload for DepositInstructions {
+    const TYPE: &'static str = "DepositInstructions";
+}
+
+impl WirePayload for TradeAdjustment {
+    const TYPE: &'static str = "TradeAdjustment";
+}
+
+impl WirePayload for TradeHistory {
+    const TYPE: &'static str = "TradeHistory";
+}
+
+impl WirePayload for IncentiveEpoch {
+    const TYPE: &'static str = "IncentiveEpoch";
+}
+
+impl WirePayload for IncentiveReport {
+    const TYPE: &'static str = "IncentiveReport";
+}
+
+impl WirePayload for MarketMakerObligation {
+    const TYPE: &'static str = "MarketMakerObligation";
+}
+
+impl WirePayload for MarketMakerScorecard {
+    const TYPE: &'static str = "MarketMakerScorecard";
+}
+
+impl WirePayload for InsuranceFund {
+    const TYPE: &'static str = "InsuranceFund";
+}
+
+impl WirePayload for InsuranceFundEvent {
+    const TYPE: &'static str = "InsuranceFundEvent";
+}
+
+impl WirePayload for Portfolio {
+    const TYPE: &'static str = "Portfolio";
+}
+
+impl WirePayload for Balances {
+    const TYPE: &'static str = "Balances";
+}
+
+impl WirePayload for SequenceMapping {
+    const TYPE: &'static str = "SequenceMapping";
+}
+
+impl WirePayload for Tenant {
+    const TYPE: &'static str = "Tenant";
+}
+
+impl WirePayload for TenantApiKey {
+    const TYPE: &'static str = "TenantApiKey";
+}
+
+impl WirePayload for TenantUsage {
+    const TYPE: &'static str = "TenantUsage";
+}
+
+impl WirePayload for DeadLetter {
+    const TYPE: &'static str = "DeadLetter";
+}
+
+impl WirePayload for ChainTransaction {
+    const TYPE: &'static str = "ChainTransaction";
+}
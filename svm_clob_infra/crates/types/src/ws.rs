@@ -0,0 +1,181 @@
+/// WebSocket protocol
+///
+/// Frames exchanged with the WebSocket server, shared by the server and the
+/// SDK. Every frame is a JSON object `{"v", "type", "payload"}` at the wire
+/// version of its writer; readers accept older versions and upgrade them, as
+/// for REST envelopes.
+
+use crate::{wire, ClobError, ClobResult, FeedEntitlement, MarketDataUpdate, MarketId, WIRE_VERSION};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Subscription types
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type")]
+pub enum Subscription {
+    OrderBook {
+        market: MarketId,
+        /// Conflate book frames to at most this many a second, each carrying
+        /// the latest book; absent or 0 sends every delta
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_frames_per_second: Option<u32>,
+        /// Levels a side to send; absent sends as many as the connection is
+        /// entitled to
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        depth: Option<u32>,
+    },
+    Trades { market: MarketId },
+    UserOrders { user: String },
+    /// Every order placed, changed or taken off the market, and its fills,
+    /// without owners
+    OrderBookL3 { market: MarketId },
+    /// Every order and fill of the market with their owners
+    DropCopy { market: MarketId },
+    AllMarkets,
+}
+
+impl Subscription {
+    /// Entitlement a connection needs to subscribe
+    pub fn required_entitlement(&self) -> FeedEntitlement {
+        match self {
+            Subscription::OrderBook { depth: None, .. } | Subscription::Trades { .. } | Subscription::UserOrders { .. } => {
+                FeedEntitlement::PublicL2
+            }
+            Subscription::OrderBook { depth: Some(depth), .. } => match FeedEntitlement::PublicL2.book_depth() {
+                Some(public) if *depth as usize <= public => FeedEntitlement::PublicL2,
+                _ => FeedEntitlement::FullDepthL2,
+            },
+            Subscription::OrderBookL3 { .. } => FeedEntitlement::L3,
+            Subscription::DropCopy { .. } | Subscription::AllMarkets => FeedEntitlement::DropCopy,
+        }
+    }
+}
+
+/// WebSocket message types, framed as `{"v", "type", "payload"}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type", content = "payload")]
+pub enum WebSocketMessage {
+    Subscribe(Subscription),
+    Unsubscribe(Subscription),
+    MarketData(MarketDataUpdate),
+    Error(WebSocketError),
+    Ping,
+    Pong,
+    /// Sent by the server first on every connection
+    Session(WebSocketSession),
+}
+
+/// Session of a connection; reconnecting with its token within the server's
+/// session TTL restores its subscriptions and replays the updates missed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WebSocketSession {
+    /// Token to pass as the `session` query parameter when reconnecting
+    pub token: String,
+    /// Whether the connection resumed the session of an earlier one
+    pub resumed: bool,
+    /// Subscriptions restored from the earlier connection
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subscriptions: Vec<Subscription>,
+    /// Restored subscriptions whose missed updates the server no longer
+    /// keeps, for the client to resync through the REST API
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stale: Vec<Subscription>,
+}
+
+/// Error reported to a WebSocket client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WebSocketError {
+    pub message: String,
+    pub code: u32,
+}
+
+impl From<&ClobError> for WebSocketError {
+    fn from(e: &ClobError) -> Self {
+        WebSocketError {
+            message: e.to_string(),
+            code: e.code(),
+        }
+    }
+}
+
+impl WebSocketMessage {
+    /// Encode as a frame at the current wire version
+    pub fn to_frame(&self) -> ClobResult<String> {
+        let mut frame = serde_json::to_value(self).map_err(|e| ClobError::SerializationError(e.to_string()))?;
+        if let Value::Object(fields) = &mut frame {
+            fields.insert("v".to_string(), Value::from(WIRE_VERSION));
+        }
+        Ok(frame.to_string())
+    }
+
+    /// Decode a frame of any supported wire version; frames without `v` are version 1
+    pub fn from_frame(text: &str) -> ClobResult<Self> {
+        let invalid = |e: serde_json::Error| ClobError::SerializationError(e.to_string());
+        let mut frame: Value = serde_json::from_str(text).map_err(invalid)?;
+        let Value::Object(fields) = &mut frame else {
+            return Err(ClobError::SerializationError("Frame is not an object".to_string()));
+        };
+
+        let version = fields.remove("v").and_then(|v| v.as_u64()).unwrap_or(1);
+        if version > WIRE_VERSION as u64 {
+            return Err(ClobError::SerializationError(format!("Unsupported wire version {}", version)));
+        }
+        let version = version as u16;
+        if version == 1 {
+            move_v1_payload(fields);
+        }
+        if fields.get("type").and_then(Value::as_str) == Some("MarketData") {
+            if let Some(payload) = fields.get_mut("payload") {
+                wire::upgrade_to_current::<MarketDataUpdate>(version, payload)?;
+            }
+        }
+
+        serde_json::from_value(frame).map_err(invalid)
+    }
+}
+
+/// JSON Schema of the frames at the current wire version
+///
+/// One `oneOf` branch per `WebSocketMessage` variant, each an object with its
+/// `type` tag, its `payload` and the frame's `v`; `MarketDataUpdate` and the
+/// order, trade and book types it carries are under `definitions`. Generated
+/// from these types, so bindings generated from it follow them.
+#[cfg(feature = "schema")]
+pub fn frame_schema() -> Value {
+    let mut schema =
+        serde_json::to_value(schemars::schema_for!(WebSocketMessage)).expect("JSON Schema always serializes");
+    let version = serde_json::json!({
+        "description": "Wire version of the frame's writer; frames without it are version 1",
+        "type": "integer",
+        "minimum": 1,
+        "maximum": WIRE_VERSION,
+    });
+    if let Some(Value::Array(variants)) = schema.get_mut("oneOf") {
+        for variant in variants {
+            if let Some(Value::Object(properties)) = variant.get_mut("properties") {
+                properties.insert("v".to_string(), version.clone());
+            }
+        }
+    }
+    schema
+}
+
+/// Version 1 frames carried their content in named fields beside `type`
+fn move_v1_payload(fields: &mut serde_json::Map<String, Value>) {
+    let payload = match fields.get("type").and_then(Value::as_str) {
+        Some("Subscribe" | "Unsubscribe") => fields.remove("subscription"),
+        Some("MarketData") => fields.remove("data"),
+        Some("Error") => Some(serde_json::json!({
+            "message": fields.remove("message").unwrap_or_default(),
+            "code": fields.remove("code").unwrap_or_default(),
+        })),
+        _ => None,
+    };
+    if let Some(payload) = payload {
+        fields.insert("payload".to_string(), payload);
+    }
+}
@@ -0,0 +1,92 @@
+/// Book update conflation
+///
+/// A client that subscribes to a book with `max_frames_per_second` gets at
+/// most that many frames of it a second. Updates arriving faster are not
+/// queued: when the next frame is due, the client gets one `BookDelta` from
+/// the book it holds to the latest published book. It therefore sees the
+/// latest state, with the same sequence and checksum guarantees as a client
+/// that receives every delta.
+
+use crate::{delta_update, snapshot_update};
+use svm_clob_types::*;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Conflated books of one client
+#[derive(Debug, Default)]
+pub struct Conflator {
+    books: HashMap<MarketId, ConflatedBook>,
+}
+
+#[derive(Debug)]
+struct ConflatedBook {
+    /// Book the client holds; `None` until it got a snapshot
+    sent: Option<OrderBookSnapshot>,
+    /// Earliest time the next frame may go out
+    next_frame: Instant,
+    /// Whether the published book moved since the last frame
+    pending: bool,
+}
+
+impl ConflatedBook {
+    fn new() -> Self {
+        Self {
+            sent: None,
+            next_frame: Instant::now(),
+            pending: false,
+        }
+    }
+}
+
+impl Conflator {
+    /// Note that the book of `market` changed; it goes out with the next frame
+    pub fn defer(&mut self, market: MarketId) {
+        self.books.entry(market).or_insert_with(ConflatedBook::new).pending = true;
+    }
+
+    /// Record a book sent outside conflation, such as the snapshot answering a subscription
+    pub fn sent(&mut self, book: &OrderBookSnapshot) {
+        self.books.entry(book.market_id).or_insert_with(ConflatedBook::new).sent = Some(book.clone());
+    }
+
+    /// Drop the state of a market the client no longer conflates
+    pub fn forget(&mut self, market: &MarketId) {
+        self.books.remove(market);
+    }
+
+    /// When the earliest pending frame is due
+    pub fn next_due(&self) -> Option<Instant> {
+        self.books.values().filter(|book| book.pending).map(|book| book.next_frame).min()
+    }
+
+    /// Markets whose pending frame is due at `now`
+    pub fn due(&self, now: Instant) -> Vec<MarketId> {
+        self.books
+            .iter()
+            .filter(|(_, book)| book.pending && book.next_frame <= now)
+            .map(|(market, _)| *market)
+            .collect()
+    }
+
+    /// Frame taking the client from the book it holds to `latest`, the next of
+    /// which may go out `interval` after `now`; `None` when it already holds it
+    pub fn frame(&mut self, latest: OrderBookSnapshot, interval: Duration, now: Instant) -> Option<MarketDataUpdate> {
+        let book = self.books.entry(latest.market_id).or_insert_with(ConflatedBook::new);
+        book.pending = false;
+        let update = match &book.sent {
+            Some(sent) if sent.sequence_number == latest.sequence_number => return None,
+            Some(sent) => delta_update(sent, &latest),
+            None => snapshot_update(latest.clone()),
+        };
+        book.next_frame = now + interval;
+        book.sent = Some(latest);
+        Some(update)
+    }
+}
+
+/// Interval between frames at `max_frames_per_second`; `None` for 0, which
+/// sends every update
+pub fn frame_interval(max_frames_per_second: u32) -> Option<Duration> {
+    (max_frames_per_second > 0).then(|| Duration::from_secs(1) / max_frames_per_second)
+}
@@ -0,0 +1,35 @@
+/// Feed entitlements
+///
+/// Market data is served in levels, each including the ones below it: books
+/// cut to `PUBLIC_BOOK_DEPTH` levels a side, books at every level, every
+/// order of a market without its owner (L3), and every order and fill with
+/// their owners (drop copy). Connections without an API key get the public
+/// level. A connection opened with a tenant's key, in an `X-Api-Key` header
+/// or an `api_key` query parameter for browsers, gets the entitlement the
+/// operator attached to the key; an unknown or revoked key, or one of a
+/// disabled tenant, is refused. The key is resolved once, when the connection
+/// opens, and every subscription is checked against its entitlement, so a
+/// change to the key applies to connections opened after it.
+
+use svm_clob_storage::Storage;
+use svm_clob_types::{ClobResult, FeedEntitlement, TenantApiKey};
+use async_trait::async_trait;
+
+/// Where the entitlement of an API key is looked up
+#[async_trait]
+pub trait EntitlementSource: Send + Sync {
+    /// Entitlement of a live API key of an enabled tenant; `None` otherwise
+    async fn entitlement(&self, api_key: &str) -> ClobResult<Option<FeedEntitlement>>;
+}
+
+#[async_trait]
+impl<S: Storage> EntitlementSource for S {
+    async fn entitlement(&self, api_key: &str) -> ClobResult<Option<FeedEntitlement>> {
+        let key = match self.get_tenant_api_key(&TenantApiKey::hash(api_key)).await? {
+            Some(key) if key.revoked_at.is_none() => key,
+            _ => return Ok(None),
+        };
+        let enabled = self.get_tenant(&key.tenant_id).await?.is_some_and(|tenant| !tenant.disabled);
+        Ok(enabled.then_some(key.entitlement))
+    }
+}
@@ -1,31 +1,65 @@
 /// WebSocket Server for SVM CLOB Infrastructure
 /// 
 /// This module provides real-time market data feeds and order book updates
-/// via WebSocket connections for the SVM CLOB infrastructure.
+/// via WebSocket connections for the SVM CLOB infrastructure. Order book
+/// subscribers get a snapshot when they subscribe and `BookDelta`s after it,
+/// conflated to a frame rate when the subscription asks for one. The JSON
+/// Schema of every frame is served at `/ws/schema` for client codegen. Each
+/// connection is logged under the request ID of its upgrade, the client's
+/// `X-Request-Id` when usable, which the upgrade response echoes. What a
+/// connection may subscribe to depends on the entitlement of the API key it
+/// was opened with, see `entitlement`. A dropped connection can be resumed
+/// with the token of its session, see `session`.
+
+mod conflation;
+mod entitlement;
+mod session;
 
 use svm_clob_types::*;
+use svm_clob_serve::ListenOptions;
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        State,
+        Query, State,
     },
-    response::Response,
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
     routing::get,
     Router,
 };
+use serde::Deserialize;
 use futures_util::{sink::SinkExt, stream::StreamExt};
-use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, sync::Arc};
-use tokio::sync::{broadcast, RwLock};
-use tracing::{info, warn, error, debug};
+use conflation::{frame_interval, Conflator};
+use session::{Positions, ReplayBuffer, Session, Sessions};
+use std::{collections::HashMap, sync::{Arc, Mutex}, time::Duration};
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tracing::{info, warn, error, debug, info_span, Instrument};
 use uuid::Uuid;
 
+pub use entitlement::EntitlementSource;
+pub use session::{REPLAY_BUFFER, SESSION_TTL};
+pub use svm_clob_types::ws::{Subscription, WebSocketError, WebSocketMessage, WebSocketSession};
+
+/// Header carrying a tenant's API key, as on the RPC server
+const API_KEY_HEADER: &str = "x-api-key";
+
 /// WebSocket server state
 pub struct WebSocketServerState {
     /// Broadcast sender for market data updates
     pub market_data_tx: broadcast::Sender<MarketDataUpdate>,
     /// Connected clients
     pub clients: Arc<RwLock<HashMap<Uuid, ClientConnection>>>,
+    /// Last book published per market, which deltas are computed against
+    books: RwLock<HashMap<MarketId, OrderBookSnapshot>>,
+    /// Updates last broadcast, for resumed sessions to replay
+    replay: Mutex<ReplayBuffer>,
+    /// Sessions of dropped connections
+    sessions: Sessions,
+    /// Time source for update timestamps
+    clock: Arc<dyn Clock>,
+    /// Entitlements of API keys; without it keys are not accepted and every
+    /// connection is public
+    entitlements: Option<Arc<dyn EntitlementSource>>,
 }
 
 /// Client connection information
@@ -33,69 +67,297 @@ pub struct WebSocketServerState {
 pub struct ClientConnection {
     pub id: Uuid,
     pub subscriptions: Vec<Subscription>,
+    /// Feeds the connection may subscribe to
+    pub entitlement: FeedEntitlement,
     pub connected_at: chrono::DateTime<chrono::Utc>,
 }
 
-/// Subscription types
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type")]
-pub enum Subscription {
-    OrderBook { market: String },
-    Trades { market: String },
-    UserOrders { user: String },
-    AllMarkets,
-}
-
-/// WebSocket message types
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type")]
-pub enum WebSocketMessage {
-    Subscribe {
-        subscription: Subscription,
-    },
-    Unsubscribe {
-        subscription: Subscription,
-    },
-    MarketData {
-        data: MarketDataUpdate,
-    },
-    Error {
-        message: String,
-        code: u32,
-    },
-    Ping,
-    Pong,
+/// Query parameters of the upgrade request
+#[derive(Deserialize)]
+struct ConnectParams {
+    /// API key, for clients that cannot set headers
+    api_key: Option<String>,
+    /// Token of a session to resume
+    session: Option<String>,
+    /// Sequence of the last update the client got in that session
+    last_sequence: Option<u64>,
 }
 
 impl WebSocketServerState {
     /// Create new WebSocket server state
     pub fn new() -> Self {
+        Self::with_clock(system_clock())
+    }
+    
+    /// Create new WebSocket server state stamping updates from `clock`
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
         let (market_data_tx, _) = broadcast::channel(1000);
         
         Self {
             market_data_tx,
             clients: Arc::new(RwLock::new(HashMap::new())),
+            books: RwLock::new(HashMap::new()),
+            replay: Mutex::new(ReplayBuffer::default()),
+            sessions: Sessions::default(),
+            clock,
+            entitlements: None,
         }
     }
+
+    /// Accept API keys, looking up their entitlements in `source`
+    pub fn with_entitlements(mut self, source: Arc<dyn EntitlementSource>) -> Self {
+        self.entitlements = Some(source);
+        self
+    }
+    
+    /// Publish a market's current book: a snapshot the first time, then a
+    /// `BookDelta` against the previous book whenever the sequence moves
+    pub async fn publish_order_book(&self, book: OrderBookSnapshot) {
+        let mut books = self.books.write().await;
+        let update = match books.get(&book.market_id) {
+            Some(previous) if previous.sequence_number == book.sequence_number => return,
+            Some(previous) => delta_update(previous, &book),
+            None => snapshot_update(book.clone()),
+        };
+        books.insert(book.market_id, book);
+        // Broadcast under the lock so deltas go out in sequence order
+        self.broadcast_market_data(update).await;
+    }
+    
+    /// Tell the owner of `order` about its new state; `expiry_reason` is set
+    /// when the engine expired it
+    pub async fn publish_order_update(&self, order: Order, expiry_reason: Option<ExpiryReason>) {
+        self.broadcast_market_data(MarketDataUpdate {
+            market_id: order.market_id,
+            update_type: MarketDataUpdateType::OrderUpdate,
+            order_book: None,
+            book_delta: None,
+            trade: None,
+            order: Some(order),
+            expiry_reason,
+            replaced_order_id: None,
+            mmp_limit: None,
+            queue_position: None,
+            maker: None,
+            taker: None,
+            sequence: None,
+            timestamp: self.clock.now(),
+        })
+        .await;
+    }
+
+    /// Publish a fill between orders of `maker` and `taker`; only drop copy
+    /// subscribers see the owners
+    pub async fn publish_trade(&self, trade: TradeExecution, maker: Pubkey, taker: Pubkey) {
+        self.broadcast_market_data(MarketDataUpdate {
+            market_id: trade.market_id,
+            update_type: MarketDataUpdateType::TradeExecution,
+            order_book: None,
+            book_delta: None,
+            trade: Some(trade),
+            order: None,
+            expiry_reason: None,
+            replaced_order_id: None,
+            mmp_limit: None,
+            queue_position: None,
+            maker: Some(maker),
+            taker: Some(taker),
+            sequence: None,
+            timestamp: self.clock.now(),
+        })
+        .await;
+    }
+
+    /// Tell the owner that market maker protection pulled `order` when its
+    /// fills reached `limit`
+    pub async fn publish_mmp_pull(&self, order: Order, limit: MmpLimit) {
+        self.broadcast_market_data(MarketDataUpdate {
+            market_id: order.market_id,
+            update_type: MarketDataUpdateType::OrderUpdate,
+            order_book: None,
+            book_delta: None,
+            trade: None,
+            order: Some(order),
+            expiry_reason: None,
+            replaced_order_id: None,
+            mmp_limit: Some(limit),
+            queue_position: None,
+            maker: None,
+            taker: None,
+            sequence: None,
+            timestamp: self.clock.now(),
+        })
+        .await;
+    }
+
+    /// Tell the owner that a modification cancelled `original` and placed
+    /// `replacement` in its stead, at the back of its price level
+    pub async fn publish_order_replaced(&self, original: Order, replacement: Order) {
+        let replaced_order_id = original.order_id;
+        self.publish_order_update(original, None).await;
+        self.broadcast_market_data(MarketDataUpdate {
+            market_id: replacement.market_id,
+            update_type: MarketDataUpdateType::OrderUpdate,
+            order_book: None,
+            book_delta: None,
+            trade: None,
+            order: Some(replacement),
+            expiry_reason: None,
+            replaced_order_id: Some(replaced_order_id),
+            mmp_limit: None,
+            queue_position: None,
+            maker: None,
+            taker: None,
+            sequence: None,
+            timestamp: self.clock.now(),
+        })
+        .await;
+    }
+    
+    /// Tell the owner of a resting order its new place in its level's queue
+    pub async fn publish_queue_position(&self, position: QueuePosition) {
+        self.broadcast_market_data(MarketDataUpdate {
+            market_id: position.market_id,
+            update_type: MarketDataUpdateType::QueuePosition,
+            order_book: None,
+            book_delta: None,
+            trade: None,
+            order: None,
+            expiry_reason: None,
+            replaced_order_id: None,
+            mmp_limit: None,
+            queue_position: Some(position),
+            maker: None,
+            taker: None,
+            sequence: None,
+            timestamp: self.clock.now(),
+        })
+        .await;
+    }
+
+    /// Owners some connected client follows through `UserOrders`
+    pub async fn subscribed_users(&self) -> Vec<Pubkey> {
+        let clients = self.clients.read().await;
+        let mut users: Vec<Pubkey> = clients
+            .values()
+            .flat_map(|client| &client.subscriptions)
+            .filter_map(|subscription| match subscription {
+                Subscription::UserOrders { user } => user.parse().ok(),
+                _ => None,
+            })
+            .collect();
+        users.sort();
+        users.dedup();
+        users
+    }
     
-    /// Broadcast market data update to subscribed clients
+    /// Last book published for `market`
+    async fn latest_book(&self, market: &MarketId) -> Option<OrderBookSnapshot> {
+        self.books.read().await.get(market).cloned()
+    }
+    
+    /// Snapshot update of the last book published for `market`, cut to
+    /// `depth` levels a side when given
+    async fn order_book_snapshot(&self, market: &MarketId, depth: Option<usize>) -> Option<MarketDataUpdate> {
+        let book = self.latest_book(market).await?;
+        Some(snapshot_update(match depth {
+            Some(depth) => book.truncated(depth),
+            None => book,
+        }))
+    }
+    
+    /// Broadcast market data update to subscribed clients, numbered as the
+    /// next in the stream
     pub async fn broadcast_market_data(&self, update: MarketDataUpdate) {
+        // Sent under the lock so updates go out in the order they are numbered
+        let mut replay = self.replay.lock().expect("replay buffer lock poisoned");
+        let update = replay.push(update);
         if let Err(e) = self.market_data_tx.send(update) {
             error!("Failed to broadcast market data: {}", e);
         }
     }
+
+    /// Start `client` on the stream: the frames opening the connection, its
+    /// `Session` first, and the receiver of the updates after them. A client
+    /// resuming a session comes with the subscriptions restored from it and
+    /// how far along the stream each was served, and is sent the updates
+    /// each missed, or a snapshot of a book whose missed deltas it cannot
+    /// replay.
+    async fn open_session(
+        &self,
+        client: &ClientConnection,
+        token: &str,
+        restored: Option<Vec<(Subscription, u64)>>,
+        last_sequence: Option<u64>,
+    ) -> (broadcast::Receiver<MarketDataUpdate>, Positions, Vec<WebSocketMessage>) {
+        // Read under the locks books and updates are published under, so the
+        // receiver starts right after what is sent here
+        let books = self.books.read().await;
+        let replay = self.replay.lock().expect("replay buffer lock poisoned");
+        let receiver = self.market_data_tx.subscribe();
+        let positions = Positions::starting_at(replay.last());
+        let mut session = WebSocketSession {
+            token: token.to_string(),
+            resumed: restored.is_some(),
+            subscriptions: client.subscriptions.clone(),
+            stale: Vec::new(),
+        };
+        let mut frames = Vec::new();
+
+        // Subscriptions whose missed updates are replayed, with their position
+        let mut replayed = Vec::new();
+        for (subscription, position) in restored.unwrap_or_default() {
+            let position = last_sequence.map_or(position, |last| position.min(last));
+            match &subscription {
+                Subscription::OrderBook { market, .. }
+                    if book_frame_interval(client, market).is_some() || !replay.covers(position) =>
+                {
+                    if let Some(book) = books.get(market) {
+                        let book = match book_depth(client, market) {
+                            Some(depth) => book.truncated(depth),
+                            None => book.clone(),
+                        };
+                        frames.push(WebSocketMessage::MarketData(snapshot_update(book)));
+                    }
+                }
+                _ if !replay.covers(position) => session.stale.push(subscription),
+                _ => replayed.push((subscription, position)),
+            }
+        }
+        if let Some(from) = replayed.iter().map(|(_, position)| *position).min() {
+            for update in replay.since(from) {
+                let sequence = update.sequence.unwrap_or_default();
+                let missed = ClientConnection {
+                    subscriptions: replayed
+                        .iter()
+                        .filter(|(_, position)| *position < sequence)
+                        .map(|(subscription, _)| subscription.clone())
+                        .collect(),
+                    ..client.clone()
+                };
+                if let Some(update) = client_view(&missed, update) {
+                    frames.push(WebSocketMessage::MarketData(update));
+                }
+            }
+        }
+        frames.insert(0, WebSocketMessage::Session(session));
+        (receiver, positions, frames)
+    }
     
     /// Add new client connection
     pub async fn add_client(&self, client: ClientConnection) {
         let mut clients = self.clients.write().await;
         clients.insert(client.id, client);
+        svm_clob_observability::ws_client_connected();
         info!("New WebSocket client connected, total clients: {}", clients.len());
     }
     
     /// Remove client connection
     pub async fn remove_client(&self, client_id: Uuid) {
         let mut clients = self.clients.write().await;
-        clients.remove(&client_id);
+        if clients.remove(&client_id).is_some() {
+            svm_clob_observability::ws_client_disconnected();
+        }
         info!("WebSocket client disconnected, total clients: {}", clients.len());
     }
     
@@ -119,43 +381,138 @@ impl WebSocketServerState {
 pub fn create_router() -> Router<Arc<WebSocketServerState>> {
     Router::new()
         .route("/ws", get(websocket_handler))
+        .route("/ws/schema", get(schema_handler))
         .route("/health", get(health_check_handler))
 }
 
 /// WebSocket connection handler
 async fn websocket_handler(
     ws: WebSocketUpgrade,
+    headers: HeaderMap,
+    Query(params): Query<ConnectParams>,
     State(state): State<Arc<WebSocketServerState>>,
 ) -> Response {
-    ws.on_upgrade(|socket| handle_websocket(socket, state))
+    let request_id = svm_clob_telemetry::request_id_or_new(
+        headers
+            .get(svm_clob_telemetry::REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok()),
+    );
+    let api_key = headers
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .or(params.api_key.as_deref());
+    let entitlement = match connection_entitlement(&state, api_key).await {
+        Ok(entitlement) => entitlement,
+        Err(status) => return status.into_response(),
+    };
+    let header = HeaderValue::from_str(&request_id).ok();
+    let span = info_span!("ws.connection", request_id = %request_id, entitlement = ?entitlement);
+    let resume = params.session.map(|token| (token, params.last_sequence));
+    let mut response =
+        ws.on_upgrade(move |socket| handle_websocket(socket, state, entitlement, resume).instrument(span));
+    if let Some(header) = header {
+        response.headers_mut().insert(svm_clob_telemetry::REQUEST_ID_HEADER, header);
+    }
+    response
 }
 
-/// Handle individual WebSocket connection
-async fn handle_websocket(socket: WebSocket, state: Arc<WebSocketServerState>) {
+/// Entitlement of a connection opened with `api_key`; public without one
+async fn connection_entitlement(
+    state: &WebSocketServerState,
+    api_key: Option<&str>,
+) -> Result<FeedEntitlement, StatusCode> {
+    let Some(api_key) = api_key else {
+        return Ok(FeedEntitlement::default());
+    };
+    let Some(source) = &state.entitlements else {
+        warn!("Refused a WebSocket connection with an API key: keys are not accepted");
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+    match source.entitlement(api_key).await {
+        Ok(Some(entitlement)) => Ok(entitlement),
+        Ok(None) => {
+            warn!("Refused a WebSocket connection with an unknown or revoked API key");
+            Err(StatusCode::UNAUTHORIZED)
+        }
+        Err(e) => {
+            error!("Failed to resolve the API key of a WebSocket connection: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Handle individual WebSocket connection; `resume` names the session it
+/// resumes and the last update the client got in it
+async fn handle_websocket(
+    socket: WebSocket,
+    state: Arc<WebSocketServerState>,
+    entitlement: FeedEntitlement,
+    resume: Option<(String, Option<u64>)>,
+) {
     let client_id = Uuid::new_v4();
+    let (resumed, last_sequence) = match resume {
+        Some((token, last_sequence)) => match state.sessions.resume(&token) {
+            Some(session) => (Some((token, session)), last_sequence),
+            None => {
+                info!("Session to resume is unknown or expired, opening a new one");
+                (None, None)
+            }
+        },
+        None => (None, None),
+    };
+    let (token, restored) = match resumed {
+        Some((token, session)) => {
+            let (entitled, refused): (Vec<_>, Vec<_>) = session
+                .subscriptions
+                .into_iter()
+                .partition(|(subscription, _)| subscription.required_entitlement() <= entitlement);
+            for (subscription, _) in refused {
+                warn!("Not restoring {:?}: the connection has the {:?} entitlement", subscription, entitlement);
+            }
+            (token, Some(entitled))
+        }
+        None => (Sessions::token(), None),
+    };
     let client = ClientConnection {
         id: client_id,
-        subscriptions: Vec::new(),
+        subscriptions: restored
+            .iter()
+            .flatten()
+            .map(|(subscription, _)| subscription.clone())
+            .collect(),
+        entitlement,
         connected_at: chrono::Utc::now(),
     };
     
+    // Create market data receiver, after the frames restoring a resumed session
+    let (mut market_data_rx, positions, opening) =
+        state.open_session(&client, &token, restored, last_sequence).await;
+    let positions = Arc::new(Mutex::new(positions));
+    
     // Add client to state
     state.add_client(client).await;
     
-    // Create market data receiver
-    let mut market_data_rx = state.market_data_tx.subscribe();
-    
     // Split socket into sender and receiver
     let (mut sender, mut receiver) = socket.split();
     
+    // Replies to client frames, including errors, are sent by the outgoing task
+    let (reply_tx, mut reply_rx) = mpsc::unbounded_channel::<WebSocketMessage>();
+    
     // Spawn task to handle incoming messages
     let state_clone = state.clone();
     let incoming_task = tokio::spawn(async move {
         while let Some(msg) = receiver.next().await {
             match msg {
                 Ok(Message::Text(text)) => {
-                    if let Err(e) = handle_incoming_message(&state_clone, client_id, &text).await {
-                        error!("Error handling incoming message: {}", e);
+                    match handle_incoming_message(&state_clone, client_id, &text).await {
+                        Ok(Some(reply)) => {
+                            let _ = reply_tx.send(reply);
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            error!("Error handling incoming message: {}", e);
+                            let _ = reply_tx.send(WebSocketMessage::Error(WebSocketError::from(&e)));
+                        }
                     }
                 }
                 Ok(Message::Binary(_)) => {
@@ -179,11 +536,25 @@ async fn handle_websocket(socket: WebSocket, state: Arc<WebSocketServerState>) {
                 }
             }
         }
-    });
+    }.in_current_span());
     
     // Spawn task to handle outgoing messages
+    let served = positions.clone();
     let outgoing_task = tokio::spawn(async move {
+        let mut conflator = Conflator::default();
+        for message in opening {
+            // Conflated deltas continue from a book restored by snapshot
+            if let WebSocketMessage::MarketData(MarketDataUpdate { order_book: Some(book), .. }) = &message {
+                conflator.sent(book);
+            }
+            if let Ok(json) = message.to_frame() {
+                if sender.send(Message::Text(json)).await.is_err() {
+                    return;
+                }
+            }
+        }
         loop {
+            let next_frame = conflator.next_due();
             tokio::select! {
                 // Handle market data broadcasts
                 market_data = market_data_rx.recv() => {
@@ -191,14 +562,22 @@ async fn handle_websocket(socket: WebSocket, state: Arc<WebSocketServerState>) {
                         Ok(update) => {
                             // Check if client is subscribed to this update
                             if let Some(client) = state.get_client(client_id).await {
-                                if should_send_update(&client, &update) {
-                                    let message = WebSocketMessage::MarketData { data: update };
-                                    if let Ok(json) = serde_json::to_string(&message) {
+                                if is_book_update(&update) && book_frame_interval(&client, &update.market_id).is_some() {
+                                    conflator.defer(update.market_id);
+                                } else if let Some(update) = client_view(&client, &update) {
+                                    let message = WebSocketMessage::MarketData(update);
+                                    if let Ok(json) = message.to_frame() {
                                         if sender.send(Message::Text(json)).await.is_err() {
                                             break;
                                         }
                                     }
                                 }
+                                if let Some(sequence) = update.sequence {
+                                    served
+                                        .lock()
+                                        .expect("session positions lock poisoned")
+                                        .advance(&streamed_subscriptions(&client), sequence);
+                                }
                             }
                         }
                         Err(broadcast::error::RecvError::Lagged(skipped)) => {
@@ -211,6 +590,55 @@ async fn handle_websocket(socket: WebSocket, state: Arc<WebSocketServerState>) {
                     }
                 }
                 
+                // Send the conflated books that are due
+                _ = tokio::time::sleep_until(next_frame.unwrap_or_else(tokio::time::Instant::now)), if next_frame.is_some() => {
+                    let Some(client) = state.get_client(client_id).await else {
+                        break;
+                    };
+                    let now = tokio::time::Instant::now();
+                    let mut frames = Vec::new();
+                    for market in conflator.due(now) {
+                        let (Some(interval), Some(book)) = (book_frame_interval(&client, &market), state.latest_book(&market).await) else {
+                            conflator.forget(&market);
+                            continue;
+                        };
+                        let book = match book_depth(&client, &market) {
+                            Some(depth) => book.truncated(depth),
+                            None => book,
+                        };
+                        frames.extend(conflator.frame(book, interval, now));
+                    }
+                    let mut closed = false;
+                    for update in frames {
+                        if let Ok(json) = WebSocketMessage::MarketData(update).to_frame() {
+                            if sender.send(Message::Text(json)).await.is_err() {
+                                closed = true;
+                                break;
+                            }
+                        }
+                    }
+                    if closed {
+                        break;
+                    }
+                }
+                
+                // Answer client frames
+                Some(reply) = reply_rx.recv() => {
+                    // Conflated deltas continue from the snapshot a subscription is answered with
+                    if let WebSocketMessage::MarketData(MarketDataUpdate { order_book: Some(book), .. }) = &reply {
+                        let conflated = state.get_client(client_id).await
+                            .is_some_and(|client| book_frame_interval(&client, &book.market_id).is_some());
+                        if conflated {
+                            conflator.sent(book);
+                        }
+                    }
+                    if let Ok(json) = reply.to_frame() {
+                        if sender.send(Message::Text(json)).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+                
                 // Send periodic ping
                 _ = tokio::time::sleep(tokio::time::Duration::from_secs(30)) => {
                     if sender.send(Message::Ping(vec![])).await.is_err() {
@@ -219,7 +647,7 @@ async fn handle_websocket(socket: WebSocket, state: Arc<WebSocketServerState>) {
                 }
             }
         }
-    });
+    }.in_current_span());
     
     // Wait for either task to complete
     tokio::select! {
@@ -227,28 +655,44 @@ async fn handle_websocket(socket: WebSocket, state: Arc<WebSocketServerState>) {
         _ = outgoing_task => {},
     }
     
-    // Remove client from state
+    // Keep the session for the client to resume, then remove client from state
+    if let Some(client) = state.get_client(client_id).await {
+        let positions = positions.lock().expect("session positions lock poisoned").clone();
+        state.sessions.park(token, Session::new(&client.subscriptions, &positions));
+    }
     state.remove_client(client_id).await;
 }
 
-/// Handle incoming WebSocket messages
+/// Handle incoming WebSocket messages, returning the reply to send, if any
 async fn handle_incoming_message(
     state: &Arc<WebSocketServerState>,
     client_id: Uuid,
     text: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let message: WebSocketMessage = serde_json::from_str(text)?;
+) -> ClobResult<Option<WebSocketMessage>> {
+    let message = WebSocketMessage::from_frame(text)?;
     
     match message {
-        WebSocketMessage::Subscribe { subscription } => {
-            // Add subscription to client
-            if let Some(mut client) = state.get_client(client_id).await {
-                client.subscriptions.push(subscription.clone());
-                state.update_client_subscriptions(client_id, client.subscriptions).await;
-                info!("Client {} subscribed to: {:?}", client_id, subscription);
+        WebSocketMessage::Subscribe(subscription) => {
+            let Some(mut client) = state.get_client(client_id).await else {
+                return Ok(None);
+            };
+            let required = subscription.required_entitlement();
+            if required > client.entitlement {
+                return Err(ClobError::NotEntitled(format!(
+                    "{:?} needs the {:?} entitlement, the connection has {:?}",
+                    subscription, required, client.entitlement
+                )));
+            }
+            client.subscriptions.push(subscription.clone());
+            state.update_client_subscriptions(client_id, client.subscriptions.clone()).await;
+            info!("Client {} subscribed to: {:?}", client_id, subscription);
+            // Book subscribers start from a snapshot; deltas follow on the broadcast
+            if let Subscription::OrderBook { market, .. } = &subscription {
+                let depth = book_depth(&client, market);
+                return Ok(state.order_book_snapshot(market, depth).await.map(WebSocketMessage::MarketData));
             }
         }
-        WebSocketMessage::Unsubscribe { subscription } => {
+        WebSocketMessage::Unsubscribe(subscription) => {
             // Remove subscription from client
             if let Some(mut client) = state.get_client(client_id).await {
                 client.subscriptions.retain(|s| !subscriptions_match(s, &subscription));
@@ -265,55 +709,176 @@ async fn handle_incoming_message(
         }
     }
     
-    Ok(())
+    Ok(None)
 }
 
-/// Check if client should receive a market data update
-fn should_send_update(client: &ClientConnection, update: &MarketDataUpdate) -> bool {
+/// The market data update as the client gets it, `None` when none of its
+/// subscriptions covers it; trades and order flow come without owners unless
+/// a subscription that shows them covers the update too
+fn client_view(client: &ClientConnection, update: &MarketDataUpdate) -> Option<MarketDataUpdate> {
+    let in_market = |market: &MarketId| *market == update.market_id;
+    let mut without_owners = false;
     for subscription in &client.subscriptions {
         match (subscription, &update.update_type) {
-            (Subscription::OrderBook { .. }, MarketDataUpdateType::OrderBookUpdate) => return true,
-            (Subscription::Trades { .. }, MarketDataUpdateType::TradeExecution) => return true,
-            (Subscription::UserOrders { .. }, MarketDataUpdateType::OrderUpdate) => return true,
-            (Subscription::AllMarkets, _) => return true,
+            (Subscription::OrderBook { market, .. }, MarketDataUpdateType::OrderBookUpdate | MarketDataUpdateType::OrderBookDelta)
+                if in_market(market) => return Some(update.clone()),
+            (Subscription::Trades { market }, MarketDataUpdateType::TradeExecution)
+                if in_market(market) => without_owners = true,
+            (Subscription::UserOrders { user }, MarketDataUpdateType::OrderUpdate)
+                if update.order.as_ref().is_some_and(|order| order.owner.to_string() == *user) => return Some(update.clone()),
+            (Subscription::UserOrders { user }, MarketDataUpdateType::QueuePosition)
+                if update.queue_position.as_ref().is_some_and(|position| position.owner.to_string() == *user) => {
+                return Some(update.clone())
+            }
+            (Subscription::OrderBookL3 { market }, MarketDataUpdateType::OrderUpdate | MarketDataUpdateType::TradeExecution)
+                if in_market(market) => without_owners = true,
+            (Subscription::DropCopy { market }, MarketDataUpdateType::OrderUpdate | MarketDataUpdateType::TradeExecution)
+                if in_market(market) => return Some(update.clone()),
+            // Queue figures are private to their owner and not market data
+            (Subscription::AllMarkets, update_type) if *update_type != MarketDataUpdateType::QueuePosition => {
+                return Some(update.clone())
+            }
             _ => {}
         }
     }
-    false
+    without_owners.then(|| MarketDataUpdate {
+        order: update.order.as_ref().map(|order| Order {
+            owner: Pubkey::default(),
+            client_order_id: 0,
+            prevention_group_id: None,
+            ..order.clone()
+        }),
+        maker: None,
+        taker: None,
+        ..update.clone()
+    })
+}
+
+/// Subscriptions of a client that are served every update they cover, as
+/// opposed to books conflated into frames
+fn streamed_subscriptions(client: &ClientConnection) -> Vec<Subscription> {
+    client
+        .subscriptions
+        .iter()
+        .filter(|subscription| match subscription {
+            Subscription::OrderBook { market, .. } => book_frame_interval(client, market).is_none(),
+            _ => true,
+        })
+        .cloned()
+        .collect()
+}
+
+fn is_book_update(update: &MarketDataUpdate) -> bool {
+    matches!(update.update_type, MarketDataUpdateType::OrderBookUpdate | MarketDataUpdateType::OrderBookDelta)
+}
+
+/// Frame interval of a client's conflated book subscription to `market`, if
+/// any; a book cut to a depth is conflated without delay, so its deltas are
+/// taken between cut books
+fn book_frame_interval(client: &ClientConnection, market: &MarketId) -> Option<Duration> {
+    client.subscriptions.iter().find_map(|subscription| match subscription {
+        Subscription::OrderBook { market: subscribed, max_frames_per_second, .. } if subscribed == market => max_frames_per_second
+            .and_then(frame_interval)
+            .or_else(|| book_depth(client, market).map(|_| Duration::ZERO)),
+        _ => None,
+    })
+}
+
+/// Levels a side of `market`'s book the client gets: what its subscription
+/// asks for, within its entitlement; `None` for every level
+fn book_depth(client: &ClientConnection, market: &MarketId) -> Option<usize> {
+    let requested = client.subscriptions.iter().find_map(|subscription| match subscription {
+        Subscription::OrderBook { market: subscribed, depth, .. } if subscribed == market => {
+            Some(depth.filter(|depth| *depth > 0).map(|depth| depth as usize))
+        }
+        _ => None,
+    })?;
+    match (requested, client.entitlement.book_depth()) {
+        (Some(requested), Some(entitled)) => Some(requested.min(entitled)),
+        (requested, entitled) => requested.or(entitled),
+    }
+}
+
+/// Full book update
+fn snapshot_update(book: OrderBookSnapshot) -> MarketDataUpdate {
+    MarketDataUpdate {
+        market_id: book.market_id,
+        update_type: MarketDataUpdateType::OrderBookUpdate,
+        timestamp: book.timestamp,
+        order_book: Some(book),
+        book_delta: None,
+        trade: None,
+        order: None,
+        expiry_reason: None,
+        replaced_order_id: None,
+        mmp_limit: None,
+        queue_position: None,
+        maker: None,
+        taker: None,
+        sequence: None,
+    }
+}
+
+/// Update carrying the changes from `previous` to `book`
+fn delta_update(previous: &OrderBookSnapshot, book: &OrderBookSnapshot) -> MarketDataUpdate {
+    MarketDataUpdate {
+        market_id: book.market_id,
+        update_type: MarketDataUpdateType::OrderBookDelta,
+        order_book: None,
+        book_delta: Some(BookDelta::between(previous, book)),
+        trade: None,
+        order: None,
+        expiry_reason: None,
+        replaced_order_id: None,
+        mmp_limit: None,
+        queue_position: None,
+        maker: None,
+        taker: None,
+        sequence: None,
+        timestamp: book.timestamp,
+    }
 }
 
 /// Check if two subscriptions match for unsubscription
 fn subscriptions_match(a: &Subscription, b: &Subscription) -> bool {
     match (a, b) {
-        (Subscription::OrderBook { market: m1 }, Subscription::OrderBook { market: m2 }) => m1 == m2,
+        (Subscription::OrderBook { market: m1, .. }, Subscription::OrderBook { market: m2, .. }) => m1 == m2,
         (Subscription::Trades { market: m1 }, Subscription::Trades { market: m2 }) => m1 == m2,
         (Subscription::UserOrders { user: u1 }, Subscription::UserOrders { user: u2 }) => u1 == u2,
+        (Subscription::OrderBookL3 { market: m1 }, Subscription::OrderBookL3 { market: m2 }) => m1 == m2,
+        (Subscription::DropCopy { market: m1 }, Subscription::DropCopy { market: m2 }) => m1 == m2,
         (Subscription::AllMarkets, Subscription::AllMarkets) => true,
         _ => false,
     }
 }
 
 /// Health check handler
-async fn health_check_handler() -> axum::Json<serde_json::Value> {
+async fn health_check_handler(State(state): State<Arc<WebSocketServerState>>) -> axum::Json<serde_json::Value> {
     axum::Json(serde_json::json!({
         "status": "healthy",
-        "timestamp": chrono::Utc::now().timestamp(),
+        "timestamp": state.clock.now(),
         "service": "svm-clob-websocket-server"
     }))
 }
 
+/// JSON Schema of the frames, generated once from the message types
+async fn schema_handler() -> axum::Json<serde_json::Value> {
+    static SCHEMA: std::sync::OnceLock<serde_json::Value> = std::sync::OnceLock::new();
+    axum::Json(SCHEMA.get_or_init(svm_clob_types::ws::frame_schema).clone())
+}
+
 /// Start the WebSocket server
 pub async fn start_server(
     state: Arc<WebSocketServerState>,
-    port: u16,
-) -> Result<(), Box<dyn std::error::Error>> {
+    options: ListenOptions,
+) -> InfraResult<()> {
     let app = create_router().with_state(state);
     
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port))
-        .await?;
-    
-    info!("WebSocket server starting on port {}", port);
-    axum::serve(listener, app).await?;
+    info!("WebSocket server starting on {}", options.describe());
+    svm_clob_serve::serve(app, &options).await.map_err(|source| InfraError::Bind {
+        address: options.describe(),
+        source,
+    })?;
     
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,162 @@
+/// Session resumption
+///
+/// Every connection opens with a `Session` frame carrying a token. When the
+/// connection drops, the server keeps its subscriptions, and how far along the
+/// update stream each of them was served, for `SESSION_TTL`. Every update the
+/// server publishes is numbered in `sequence` and the last `REPLAY_BUFFER` of
+/// them are kept, so a client reconnecting with `/ws?session=<token>`, and the
+/// `sequence` of the last update it got in `last_sequence`, has its
+/// subscriptions restored and the updates it missed replayed in order before
+/// live ones, without resubscribing or resyncing. A conflated or depth-limited
+/// book, or one whose missed deltas are no longer kept, is sent a fresh
+/// snapshot instead; any other subscription whose missed updates are gone is
+/// listed as `stale` for the client to resync. Restored subscriptions are
+/// checked again against the entitlement of the new connection.
+///
+/// Sessions and the buffer live in the replica that served them: a token
+/// presented to another replica, after it expired or while its connection is
+/// still open opens a new session.
+
+use crate::subscriptions_match;
+use svm_clob_types::ws::Subscription;
+use svm_clob_types::MarketDataUpdate;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// How long a dropped connection's session can be resumed
+pub const SESSION_TTL: Duration = Duration::from_secs(300);
+
+/// Updates kept for replay
+pub const REPLAY_BUFFER: usize = 10_000;
+
+/// Sessions kept before the oldest are forgotten early
+const MAX_PARKED_SESSIONS: usize = 100_000;
+
+/// Updates last published, numbered in the order they went out
+#[derive(Debug, Default)]
+pub(crate) struct ReplayBuffer {
+    updates: VecDeque<MarketDataUpdate>,
+    /// Sequence of the newest update; 0 before the first
+    last: u64,
+}
+
+impl ReplayBuffer {
+    /// Number `update` as the next in the stream and keep it
+    pub(crate) fn push(&mut self, mut update: MarketDataUpdate) -> MarketDataUpdate {
+        self.last += 1;
+        update.sequence = Some(self.last);
+        if self.updates.len() >= REPLAY_BUFFER {
+            self.updates.pop_front();
+        }
+        self.updates.push_back(update.clone());
+        update
+    }
+
+    pub(crate) fn last(&self) -> u64 {
+        self.last
+    }
+
+    /// Whether every update after `position` is still kept
+    pub(crate) fn covers(&self, position: u64) -> bool {
+        position + 1 + self.updates.len() as u64 > self.last
+    }
+
+    /// Kept updates after `position`, oldest first
+    pub(crate) fn since(&self, position: u64) -> impl Iterator<Item = &MarketDataUpdate> {
+        let first = self.last + 1 - self.updates.len() as u64;
+        self.updates.iter().skip(position.saturating_add(1).saturating_sub(first) as usize)
+    }
+}
+
+/// How far along the stream each subscription of a connection was served
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Positions {
+    /// Subscriptions served up to an update, with its sequence
+    served: Vec<(Subscription, u64)>,
+    /// Sequence of the last update the connection went through; subscriptions
+    /// made since missed nothing before it
+    through: u64,
+}
+
+impl Positions {
+    pub(crate) fn starting_at(through: u64) -> Self {
+        Self {
+            served: Vec::new(),
+            through,
+        }
+    }
+
+    /// Record that the update at `sequence` went through the connection,
+    /// served to `subscriptions` if it concerned them
+    pub(crate) fn advance(&mut self, subscriptions: &[Subscription], sequence: u64) {
+        self.served
+            .retain(|(served, _)| subscriptions.iter().any(|subscription| subscriptions_match(served, subscription)));
+        for subscription in subscriptions {
+            match self.served.iter_mut().find(|(served, _)| subscriptions_match(served, subscription)) {
+                Some((_, position)) => *position = (*position).max(sequence),
+                None => self.served.push((subscription.clone(), sequence)),
+            }
+        }
+        self.through = self.through.max(sequence);
+    }
+
+    /// Sequence `subscription` was served up to
+    pub(crate) fn position(&self, subscription: &Subscription) -> u64 {
+        self.served
+            .iter()
+            .find(|(served, _)| subscriptions_match(served, subscription))
+            .map_or(self.through, |(_, position)| *position)
+    }
+}
+
+/// Subscriptions of a dropped connection, waiting to be resumed
+#[derive(Debug, Clone)]
+pub(crate) struct Session {
+    pub(crate) subscriptions: Vec<(Subscription, u64)>,
+    parked_at: Instant,
+}
+
+impl Session {
+    pub(crate) fn new(subscriptions: &[Subscription], positions: &Positions) -> Self {
+        Self {
+            subscriptions: subscriptions
+                .iter()
+                .map(|subscription| (subscription.clone(), positions.position(subscription)))
+                .collect(),
+            parked_at: Instant::now(),
+        }
+    }
+}
+
+/// Sessions of dropped connections by token
+#[derive(Default)]
+pub(crate) struct Sessions {
+    parked: Mutex<HashMap<String, Session>>,
+}
+
+impl Sessions {
+    /// Token of a new session
+    pub(crate) fn token() -> String {
+        Uuid::new_v4().simple().to_string()
+    }
+
+    /// Keep the session of a dropped connection for `SESSION_TTL`
+    pub(crate) fn park(&self, token: String, session: Session) {
+        let mut parked = self.parked.lock().expect("session lock poisoned");
+        parked.retain(|_, session| session.parked_at.elapsed() < SESSION_TTL);
+        if parked.len() >= MAX_PARKED_SESSIONS {
+            if let Some(oldest) = parked.iter().min_by_key(|(_, session)| session.parked_at).map(|(token, _)| token.clone()) {
+                parked.remove(&oldest);
+            }
+        }
+        parked.insert(token, session);
+    }
+
+    /// Take the session `token` names, if it is parked and has not expired
+    pub(crate) fn resume(&self, token: &str) -> Option<Session> {
+        let mut parked = self.parked.lock().expect("session lock poisoned");
+        parked.remove(token).filter(|session| session.parked_at.elapsed() < SESSION_TTL)
+    }
+}
@@ -0,0 +1,148 @@
+//! Order book against the reference book
+//!
+//! Decodes the input into adds, removals, partial fills and maker lookups
+//! and applies each to `OrderBookManager`, with its per-price queues, and to
+//! `SortedVecBook`. Both must accept and reject the same commands, and after
+//! every command agree on levels, best prices, priority order, the makers a
+//! taker reaches, arrival order, owners' orders and client order IDs.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use svm_clob_fuzz::{decode_book_commands, BookCommand, SortedVecBook, OWNERS, TICK};
+use svm_clob_order_book::OrderBookManager;
+use svm_clob_types::*;
+
+fuzz_target!(|data: &[u8]| {
+    let mut book = OrderBookManager::new(TICK, 1);
+    let mut reference = SortedVecBook::new(TICK);
+    let mut next_id = 1;
+
+    for command in decode_book_commands(data) {
+        match command {
+            BookCommand::Add { owner: index, side, price, quantity, client_order_id } => {
+                let order = Order::builder()
+                    .order_id(next_id)
+                    .client_order_id(u64::from(client_order_id))
+                    .owner(owner(index))
+                    .side(side)
+                    .limit(price)
+                    .qty(quantity)
+                    .timestamp(0)
+                    .build()
+                    .expect("decoded order builds");
+                next_id += 1;
+                let added = book.add_order(order.clone());
+                let expected = reference.add_order(order);
+                assert_eq!(added.is_ok(), expected.is_ok(), "add of order {}", next_id - 1);
+            }
+            BookCommand::Remove { index } => {
+                let Some(order_id) = pick(&reference, index) else {
+                    continue;
+                };
+                let removed = book.remove_order(order_id).expect("resting order removed");
+                let expected = reference.remove_order(order_id).expect("reference order removed");
+                assert_eq!(removed.remaining_quantity, expected.remaining_quantity, "removal of {}", order_id);
+            }
+            BookCommand::RemoveUnknown => {
+                assert!(book.remove_order(next_id).is_err(), "removed unknown order {}", next_id);
+            }
+            BookCommand::Fill { index, quantity } => {
+                let Some(order_id) = pick(&reference, index) else {
+                    continue;
+                };
+                let remaining = reference.get_order(order_id).expect("picked order").remaining_quantity;
+                let new_remaining = remaining.saturating_sub(quantity);
+                let filled = book.update_order_quantity(order_id, new_remaining);
+                let expected = reference.update_order_quantity(order_id, new_remaining);
+                assert_eq!(filled.is_ok(), expected.is_ok(), "fill of {}", order_id);
+                // A remaining quantity never grows, and a filled order is gone
+                assert!(book.update_order_quantity(order_id, remaining + 1).is_err(), "remaining of {} grew", order_id);
+            }
+            BookCommand::Match { owner: index, side, limit_price, quantity } => {
+                let taker = Order::builder()
+                    .order_id(0)
+                    .owner(owner(index))
+                    .side(side)
+                    .limit(TICK)
+                    .qty(quantity.max(1))
+                    .timestamp(0)
+                    .build()
+                    .expect("decoded taker builds");
+                let taker = Order { remaining_quantity: quantity, ..taker };
+                let mut makers = Vec::new();
+                book.matching_orders_into(&taker, limit_price, &mut makers);
+                assert_eq!(
+                    ids(&makers),
+                    ids(&reference.matching_orders(&taker, limit_price)),
+                    "makers of a {:?} taker for {} up to {}",
+                    side,
+                    quantity,
+                    limit_price
+                );
+            }
+        }
+
+        compare(&book, &reference);
+    }
+});
+
+fn owner(index: u8) -> Pubkey {
+    Pubkey::new_from_array([index + 1; 32])
+}
+
+fn ids(orders: &[Order]) -> Vec<u64> {
+    orders.iter().map(|order| order.order_id).collect()
+}
+
+/// Resting order picked by `index`
+fn pick(reference: &SortedVecBook, index: usize) -> Option<u64> {
+    let ids = reference.order_ids();
+    (!ids.is_empty()).then(|| ids[index % ids.len()])
+}
+
+fn compare(book: &OrderBookManager, reference: &SortedVecBook) {
+    let snapshot = book.get_snapshot();
+    assert_eq!(snapshot.bids, reference.levels(OrderSide::Bid), "bid levels");
+    assert_eq!(snapshot.asks, reference.levels(OrderSide::Ask), "ask levels");
+    assert_eq!(book.get_best_bid(), reference.best_bid(), "best bid");
+    assert_eq!(book.get_best_ask(), reference.best_ask(), "best ask");
+
+    for ticks in [0, 4, 8, 16] {
+        let price = ticks * TICK;
+        let bids = book.get_bids_down_to_price(price).expect("bids");
+        assert_eq!(ids(&bids), ids(&reference.orders_within(OrderSide::Bid, price)), "bids down to {}", price);
+        let asks = book.get_asks_up_to_price(price).expect("asks");
+        assert_eq!(ids(&asks), ids(&reference.orders_within(OrderSide::Ask, price)), "asks up to {}", price);
+    }
+
+    let by_arrival: Vec<u64> = book.get_open_orders_by_arrival().iter().map(|order| order.order_id).collect();
+    assert_eq!(by_arrival, reference.ids_by_arrival(), "arrival order");
+
+    for index in 0..OWNERS {
+        let owner = owner(index);
+        let mut owned: Vec<u64> = book.get_user_orders(&owner).iter().map(|order| order.order_id).collect();
+        owned.sort_unstable();
+        assert_eq!(owned, reference.owner_order_ids(&owner), "orders of owner {}", index);
+        for client_order_id in 0..4 {
+            assert_eq!(
+                book.has_client_order_id(&owner, client_order_id),
+                reference.has_client_order_id(&owner, client_order_id),
+                "client order ID {} of owner {}",
+                client_order_id,
+                index
+            );
+        }
+    }
+
+    for order_id in reference.order_ids() {
+        let order = book.get_order(order_id).expect("resting order in the book");
+        let expected = reference.get_order(order_id).expect("resting order in the reference");
+        assert_eq!(order.remaining_quantity, expected.remaining_quantity, "remaining of {}", order_id);
+        assert_eq!(order.status, expected.status, "status of {}", order_id);
+    }
+
+    let stats = book.get_market_stats();
+    assert_eq!(stats.total_bid_orders, reference.order_count(OrderSide::Bid) as u64);
+    assert_eq!(stats.total_ask_orders, reference.order_count(OrderSide::Ask) as u64);
+}
@@ -0,0 +1,230 @@
+//! Matching engine commands
+//!
+//! Decodes the input into place, cancel, modify and owner mass cancel
+//! commands and runs them through `MatchingEngine` over `InMemoryStorage`.
+//! Rejections are fine; panics are not, and after every command the fills
+//! and the book must hold together: fills at the maker's price and within
+//! the taker's limit, never between orders of one owner, never more than
+//! either order had left, and a book whose levels add up to its resting
+//! orders and never cross.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::collections::HashMap;
+use std::sync::Arc;
+use svm_clob_fuzz::{decode_engine_commands, EngineCommand, MIN_SIZE, TICK};
+use svm_clob_matching_engine::MatchingEngine;
+use svm_clob_storage::InMemoryStorage;
+use svm_clob_types::*;
+
+fuzz_target!(|data: &[u8]| {
+    let commands = decode_engine_commands(data);
+    if commands.is_empty() {
+        return;
+    }
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("runtime")
+        .block_on(run(commands));
+});
+
+fn owner(index: u8) -> Pubkey {
+    Pubkey::new_from_array([index + 1; 32])
+}
+
+fn orderbook() -> OrderBook {
+    OrderBook {
+        authority: Pubkey::default(),
+        base_mint: Pubkey::default(),
+        quote_mint: Pubkey::default(),
+        tick_size: TICK,
+        min_order_size: MIN_SIZE,
+        sequence_number: 0,
+        total_orders: 0,
+        best_bid: 0,
+        best_ask: u64::MAX,
+        total_volume: 0,
+        is_initialized: true,
+        is_paused: false,
+    }
+}
+
+fn new_order(order_id: u64, command: &EngineCommand) -> Option<Order> {
+    let EngineCommand::Place { owner: index, side, order_type, time_in_force, self_trade_behavior, price, quantity } =
+        *command
+    else {
+        return None;
+    };
+    Some(Order {
+        order_id,
+        market_id: MarketId::default(),
+        owner: owner(index),
+        price: if order_type == OrderType::Market { 0 } else { price },
+        quantity,
+        remaining_quantity: quantity,
+        timestamp: 0,
+        client_order_id: order_id,
+        expiry_timestamp: 0,
+        side,
+        order_type,
+        status: OrderStatus::Open,
+        self_trade_behavior,
+        prevention_group_id: None,
+        time_in_force,
+        max_slippage_bps: None,
+        filled_notional: Notional::default(),
+        avg_fill_price: None,
+    })
+}
+
+/// Order picked by `index` among those placed so far
+fn pick(ids: &[u64], index: usize) -> Option<u64> {
+    (!ids.is_empty()).then(|| ids[index % ids.len()])
+}
+
+async fn run(commands: Vec<EngineCommand>) {
+    let engine = MatchingEngine::new(Arc::new(InMemoryStorage::new()), orderbook());
+    // Orders accepted so far, replacements included
+    let mut ids: Vec<u64> = Vec::new();
+    let mut last_trade_id = 0;
+
+    for command in commands {
+        let before: HashMap<u64, Order> = engine
+            .get_open_orders()
+            .await
+            .into_iter()
+            .map(|order| (order.order_id, order))
+            .collect();
+
+        let trades = match &command {
+            EngineCommand::Place { .. } => {
+                let order_id = engine.allocate_order_id().await.expect("order ID");
+                let order = new_order(order_id, &command).expect("place command");
+                match engine.place_order(order.clone()).await {
+                    Ok(trades) => {
+                        check_taker(&order, &before, &trades);
+                        ids.push(order_id);
+                        trades
+                    }
+                    Err(_) => Vec::new(),
+                }
+            }
+            EngineCommand::Cancel { index } => {
+                if let Some(order_id) = pick(&ids, *index) {
+                    let cancelled = engine.cancel_order(order_id).await;
+                    assert_eq!(cancelled.is_ok(), before.contains_key(&order_id), "cancel of {}", order_id);
+                }
+                Vec::new()
+            }
+            EngineCommand::Modify { index, price, quantity } => {
+                if let Some(order_id) = pick(&ids, *index) {
+                    if let Ok(replacement) = engine.modify_order(order_id, *price, *quantity).await {
+                        assert!(before.contains_key(&order_id), "modified order {} was not resting", order_id);
+                        ids.push(replacement.order_id);
+                    }
+                }
+                Vec::new()
+            }
+            EngineCommand::CancelOwner { owner: index } => {
+                if let Ok(cancelled) = engine.cancel_owner_orders(&owner(*index)).await {
+                    assert!(cancelled.iter().all(|order| order.owner == owner(*index)));
+                }
+                Vec::new()
+            }
+        };
+
+        for trade in &trades {
+            assert!(trade.trade_id > last_trade_id, "trade IDs must increase");
+            last_trade_id = trade.trade_id;
+        }
+        check_makers(&before, &trades);
+        check_book(&engine, &before, &trades).await;
+    }
+}
+
+/// Fills of one taker respect its limit, type, size and owner
+fn check_taker(taker: &Order, before: &HashMap<u64, Order>, trades: &[TradeExecution]) {
+    // Sums are widened so full-width quantities cannot overflow the checks
+    let filled: u128 = trades.iter().map(|trade| u128::from(trade.quantity)).sum();
+    let quantity = u128::from(taker.quantity);
+    assert!(filled <= quantity, "taker {} overfilled: {} of {}", taker.order_id, filled, quantity);
+    if taker.order_type == OrderType::PostOnly {
+        assert!(trades.is_empty(), "post-only order {} took liquidity", taker.order_id);
+    }
+    if taker.time_in_force == TimeInForce::FillOrKill {
+        assert!(filled == 0 || filled == quantity, "fill-or-kill order {} filled in part", taker.order_id);
+    }
+    for trade in trades {
+        assert_eq!(trade.taker_order_id, taker.order_id);
+        let maker = before.get(&trade.maker_order_id).expect("maker resting before the fill");
+        assert_ne!(maker.owner, taker.owner, "order {} traded with its owner's order {}", taker.order_id, maker.order_id);
+        assert_ne!(maker.side, taker.side);
+        if taker.order_type != OrderType::Market {
+            match taker.side {
+                OrderSide::Bid => assert!(trade.price <= taker.price, "bid {} filled above its limit", taker.order_id),
+                OrderSide::Ask => assert!(trade.price >= taker.price, "ask {} filled below its limit", taker.order_id),
+            }
+        }
+    }
+}
+
+/// Makers fill at their own price and for no more than they had left
+fn check_makers(before: &HashMap<u64, Order>, trades: &[TradeExecution]) {
+    let mut filled: HashMap<u64, u128> = HashMap::new();
+    for trade in trades {
+        assert!(trade.quantity > 0, "empty fill of maker {}", trade.maker_order_id);
+        let maker = before.get(&trade.maker_order_id).expect("maker resting before the fill");
+        assert_eq!(trade.price, maker.price, "maker {} filled off its price", maker.order_id);
+        *filled.entry(maker.order_id).or_default() += u128::from(trade.quantity);
+    }
+    for (order_id, quantity) in filled {
+        assert!(quantity <= u128::from(before[&order_id].remaining_quantity), "maker {} overfilled", order_id);
+    }
+}
+
+/// Resting orders shrink only by their fills and the book adds up to them
+async fn check_book(engine: &MatchingEngine<InMemoryStorage>, before: &HashMap<u64, Order>, trades: &[TradeExecution]) {
+    let open = engine.get_open_orders().await;
+    for order in &open {
+        assert!(order.remaining_quantity > 0, "empty order {} resting", order.order_id);
+        assert!(order.remaining_quantity <= order.quantity, "order {} rests more than ordered", order.order_id);
+        if let Some(previous) = before.get(&order.order_id) {
+            let filled: u128 = trades
+                .iter()
+                .filter(|trade| trade.maker_order_id == order.order_id)
+                .map(|trade| u128::from(trade.quantity))
+                .sum();
+            assert_eq!(
+                u128::from(order.remaining_quantity) + filled,
+                u128::from(previous.remaining_quantity),
+                "remaining of order {}",
+                order.order_id
+            );
+        }
+    }
+
+    let snapshot = engine.get_order_book_snapshot().await.expect("snapshot");
+    for (levels, side) in [(&snapshot.bids, OrderSide::Bid), (&snapshot.asks, OrderSide::Ask)] {
+        assert!(levels.iter().all(|(_, quantity)| *quantity > 0), "empty {:?} level", side);
+        assert!(
+            levels.windows(2).all(|pair| match side {
+                OrderSide::Bid => pair[0].0 > pair[1].0,
+                OrderSide::Ask => pair[0].0 < pair[1].0,
+            }),
+            "{:?} levels out of order",
+            side
+        );
+        let total: u128 = levels.iter().map(|(_, quantity)| u128::from(*quantity)).sum();
+        let resting: u128 = open
+            .iter()
+            .filter(|order| order.side == side)
+            .map(|order| u128::from(order.remaining_quantity))
+            .sum();
+        assert_eq!(total, resting, "{:?} levels against resting orders", side);
+    }
+    if let (Some((best_bid, _)), Some((best_ask, _))) = (snapshot.bids.first(), snapshot.asks.first()) {
+        assert!(best_bid < best_ask, "crossed book: {} >= {}", best_bid, best_ask);
+    }
+}
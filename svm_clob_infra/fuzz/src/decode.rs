@@ -0,0 +1,202 @@
+//! Command decoding
+//!
+//! Each command starts with a tag byte followed by its fields. Reads past the
+//! end of the input yield zeros, so every input decodes and a truncated last
+//! command still counts. Prices and quantities are mostly small, so orders
+//! cross and fill often, with a flag for full-width values that probe
+//! rejections and arithmetic at the extremes.
+
+use crate::{MAX_COMMANDS, OWNERS, TICK};
+use svm_clob_types::*;
+
+/// Reads an input front to back
+pub struct ByteReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn u8(&mut self) -> u8 {
+        match self.data.split_first() {
+            Some((byte, rest)) => {
+                self.data = rest;
+                *byte
+            }
+            None => 0,
+        }
+    }
+
+    pub fn u16(&mut self) -> u16 {
+        u16::from_le_bytes([self.u8(), self.u8()])
+    }
+
+    pub fn u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        bytes.iter_mut().for_each(|byte| *byte = self.u8());
+        u64::from_le_bytes(bytes)
+    }
+
+    /// A price around the first sixteen ticks, off the tick one time in
+    /// sixteen, or any `u64` when `wide`
+    fn price(&mut self, wide: bool) -> u64 {
+        if wide {
+            return self.u64();
+        }
+        match self.u8() {
+            byte @ 240..=u8::MAX => u64::from(byte),
+            byte => (u64::from(byte % 16) + 1) * TICK,
+        }
+    }
+
+    /// A quantity below 256, so some fall under the minimum size, or any
+    /// `u64` when `wide`
+    fn quantity(&mut self, wide: bool) -> u64 {
+        if wide {
+            self.u64()
+        } else {
+            u64::from(self.u8())
+        }
+    }
+
+    fn side(&mut self) -> OrderSide {
+        if self.u8() & 1 == 0 {
+            OrderSide::Bid
+        } else {
+            OrderSide::Ask
+        }
+    }
+}
+
+/// Command to the matching engine; indexes pick among the orders placed so
+/// far, wrapping around
+#[derive(Debug, Clone)]
+pub enum EngineCommand {
+    Place {
+        owner: u8,
+        side: OrderSide,
+        order_type: OrderType,
+        time_in_force: TimeInForce,
+        self_trade_behavior: SelfTradeBehavior,
+        price: u64,
+        quantity: u64,
+    },
+    Cancel { index: usize },
+    Modify { index: usize, price: Option<u64>, quantity: Option<u64> },
+    CancelOwner { owner: u8 },
+}
+
+impl EngineCommand {
+    fn decode(reader: &mut ByteReader) -> Self {
+        match reader.u8() % 8 {
+            0..=4 => {
+                let flags = reader.u8();
+                let wide = flags & 0x80 != 0;
+                let order_type = match (flags >> 1) % 4 {
+                    0 | 1 => OrderType::Limit,
+                    2 => OrderType::PostOnly,
+                    _ => OrderType::Market,
+                };
+                let time_in_force = match (flags >> 3) % 4 {
+                    0 | 1 => TimeInForce::GoodTillCancelled,
+                    2 => TimeInForce::ImmediateOrCancel,
+                    _ => TimeInForce::FillOrKill,
+                };
+                let self_trade_behavior = match (flags >> 5) % 4 {
+                    0 => SelfTradeBehavior::DecrementAndCancel,
+                    1 => SelfTradeBehavior::CancelProvide,
+                    2 => SelfTradeBehavior::CancelTake,
+                    _ => SelfTradeBehavior::CancelBoth,
+                };
+                EngineCommand::Place {
+                    owner: reader.u8() % OWNERS,
+                    side: if flags & 1 == 0 { OrderSide::Bid } else { OrderSide::Ask },
+                    order_type,
+                    time_in_force,
+                    self_trade_behavior,
+                    price: reader.price(wide),
+                    quantity: reader.quantity(wide),
+                }
+            }
+            5 => EngineCommand::Cancel { index: usize::from(reader.u16()) },
+            6 => {
+                let flags = reader.u8();
+                let wide = flags & 0x80 != 0;
+                let index = usize::from(reader.u16());
+                let price = (flags & 1 != 0).then(|| reader.price(wide));
+                let quantity = (flags & 2 != 0).then(|| reader.quantity(wide));
+                EngineCommand::Modify { index, price, quantity }
+            }
+            _ => EngineCommand::CancelOwner { owner: reader.u8() % OWNERS },
+        }
+    }
+}
+
+/// Command to an order book; indexes pick among the resting orders, wrapping
+/// around
+#[derive(Debug, Clone)]
+pub enum BookCommand {
+    Add { owner: u8, side: OrderSide, price: u64, quantity: u64, client_order_id: u8 },
+    Remove { index: usize },
+    /// Remove an order ID the book never held
+    RemoveUnknown,
+    Fill { index: usize, quantity: u64 },
+    /// Look up the makers a taker of `owner` would reach
+    Match { owner: u8, side: OrderSide, limit_price: u64, quantity: u64 },
+}
+
+impl BookCommand {
+    fn decode(reader: &mut ByteReader) -> Self {
+        match reader.u8() % 8 {
+            0..=2 => BookCommand::Add {
+                owner: reader.u8() % OWNERS,
+                side: reader.side(),
+                price: reader.price(false),
+                // Books take any quantity; the minimum is the engine's to enforce
+                quantity: reader.quantity(false).max(1),
+                client_order_id: reader.u8() % 4,
+            },
+            3 => BookCommand::Remove { index: usize::from(reader.u16()) },
+            4 => BookCommand::RemoveUnknown,
+            5 => BookCommand::Fill {
+                index: usize::from(reader.u16()),
+                quantity: reader.quantity(false),
+            },
+            _ => {
+                let owner = reader.u8() % OWNERS;
+                let side = reader.side();
+                let limit_price = match reader.u8() {
+                    0 => u64::MAX,
+                    1 => 0,
+                    byte => (u64::from(byte % 16) + 1) * TICK,
+                };
+                BookCommand::Match { owner, side, limit_price, quantity: reader.quantity(false) }
+            }
+        }
+    }
+}
+
+/// Engine commands encoded in `data`
+pub fn decode_engine_commands(data: &[u8]) -> Vec<EngineCommand> {
+    decode_all(data, EngineCommand::decode)
+}
+
+/// Book commands encoded in `data`
+pub fn decode_book_commands(data: &[u8]) -> Vec<BookCommand> {
+    decode_all(data, BookCommand::decode)
+}
+
+fn decode_all<T>(data: &[u8], decode: impl Fn(&mut ByteReader) -> T) -> Vec<T> {
+    let mut reader = ByteReader::new(data);
+    let mut commands = Vec::new();
+    while !reader.is_empty() && commands.len() < MAX_COMMANDS {
+        commands.push(decode(&mut reader));
+    }
+    commands
+}
@@ -0,0 +1,197 @@
+//! Reference order book
+//!
+//! Each side is one vector of resting orders kept sorted by matching
+//! priority, best price first and then arrival. Every query walks the
+//! vectors, with no levels, indexes or per-owner sets to keep in step, so
+//! it is easy to check by reading and slow enough that only tests use it.
+
+use svm_clob_types::*;
+
+#[derive(Debug, Clone)]
+struct Resting {
+    arrival: u64,
+    order: Order,
+}
+
+/// Order book answering what `OrderBookManager` answers, the plain way
+#[derive(Debug)]
+pub struct SortedVecBook {
+    bids: Vec<Resting>,
+    asks: Vec<Resting>,
+    tick_size: u64,
+    arrivals: u64,
+}
+
+impl SortedVecBook {
+    pub fn new(tick_size: u64) -> Self {
+        Self {
+            bids: Vec::new(),
+            asks: Vec::new(),
+            tick_size,
+            arrivals: 0,
+        }
+    }
+
+    fn side(&self, side: OrderSide) -> &Vec<Resting> {
+        match side {
+            OrderSide::Bid => &self.bids,
+            OrderSide::Ask => &self.asks,
+        }
+    }
+
+    fn side_mut(&mut self, side: OrderSide) -> &mut Vec<Resting> {
+        match side {
+            OrderSide::Bid => &mut self.bids,
+            OrderSide::Ask => &mut self.asks,
+        }
+    }
+
+    /// Whether a resting order at `price` goes before one at `other` on `side`
+    fn better(side: OrderSide, price: u64, other: u64) -> bool {
+        match side {
+            OrderSide::Bid => price > other,
+            OrderSide::Ask => price < other,
+        }
+    }
+
+    fn find(&self, order_id: u64) -> Option<(OrderSide, usize)> {
+        [OrderSide::Bid, OrderSide::Ask].into_iter().find_map(|side| {
+            self.side(side)
+                .iter()
+                .position(|resting| resting.order.order_id == order_id)
+                .map(|index| (side, index))
+        })
+    }
+
+    pub fn add_order(&mut self, order: Order) -> ClobResult<()> {
+        if order.price % self.tick_size != 0 {
+            return Err(ClobError::PriceNotAlignedToTickSize);
+        }
+        self.arrivals += 1;
+        let side = order.side;
+        let price = order.price;
+        let resting = self.side_mut(side);
+        // Behind every order at the same or a better price
+        let index = resting
+            .iter()
+            .position(|other| Self::better(side, price, other.order.price))
+            .unwrap_or(resting.len());
+        let arrival = self.arrivals;
+        self.side_mut(side).insert(index, Resting { arrival, order });
+        Ok(())
+    }
+
+    pub fn remove_order(&mut self, order_id: u64) -> ClobResult<Order> {
+        let (side, index) = self.find(order_id).ok_or(ClobError::OrderNotFound)?;
+        Ok(self.side_mut(side).remove(index).order)
+    }
+
+    pub fn update_order_quantity(&mut self, order_id: u64, new_remaining_quantity: u64) -> ClobResult<()> {
+        let (side, index) = self.find(order_id).ok_or(ClobError::OrderNotFound)?;
+        let order = &mut self.side_mut(side)[index].order;
+        if new_remaining_quantity > order.remaining_quantity {
+            return Err(ClobError::InvalidQuantity(format!(
+                "remaining quantity of order {} cannot grow",
+                order_id
+            )));
+        }
+        let price = order.price;
+        order.record_fill(price, order.remaining_quantity - new_remaining_quantity);
+        if new_remaining_quantity == 0 {
+            self.side_mut(side).remove(index);
+        } else if new_remaining_quantity < order.quantity {
+            order.status = OrderStatus::PartiallyFilled;
+        }
+        Ok(())
+    }
+
+    pub fn best_bid(&self) -> Option<u64> {
+        self.bids.first().map(|resting| resting.order.price)
+    }
+
+    pub fn best_ask(&self) -> Option<u64> {
+        self.asks.first().map(|resting| resting.order.price)
+    }
+
+    /// (price, quantity) levels of `side`, best first
+    pub fn levels(&self, side: OrderSide) -> Vec<(u64, u64)> {
+        let mut levels: Vec<(u64, u64)> = Vec::new();
+        for resting in self.side(side) {
+            match levels.last_mut() {
+                Some((price, quantity)) if *price == resting.order.price => *quantity += resting.order.remaining_quantity,
+                _ => levels.push((resting.order.price, resting.order.remaining_quantity)),
+            }
+        }
+        levels
+    }
+
+    /// Orders of `side` at `limit_price` or better, in priority order
+    pub fn orders_within(&self, side: OrderSide, limit_price: u64) -> Vec<Order> {
+        self.side(side)
+            .iter()
+            .filter(|resting| !Self::better(side, limit_price, resting.order.price))
+            .map(|resting| resting.order.clone())
+            .collect()
+    }
+
+    /// The makers `taker` reaches up to `limit_price`: whole levels in
+    /// priority order, until orders of other owners cover its remaining
+    /// quantity
+    pub fn matching_orders(&self, taker: &Order, limit_price: u64) -> Vec<Order> {
+        let candidates = self.orders_within(taker.side.opposite(), limit_price);
+        let mut makers: Vec<Order> = Vec::new();
+        let mut covered = 0u64;
+        for maker in candidates {
+            let new_level = makers.last().map_or(true, |last| last.price != maker.price);
+            if new_level && covered >= taker.remaining_quantity {
+                break;
+            }
+            if maker.owner != taker.owner {
+                covered = covered.saturating_add(maker.remaining_quantity);
+            }
+            makers.push(maker);
+        }
+        makers
+    }
+
+    pub fn get_order(&self, order_id: u64) -> Option<Order> {
+        self.find(order_id).map(|(side, index)| self.side(side)[index].order.clone())
+    }
+
+    /// IDs of the resting orders of `owner`, ascending
+    pub fn owner_order_ids(&self, owner: &Pubkey) -> Vec<u64> {
+        let mut ids: Vec<u64> = self
+            .bids
+            .iter()
+            .chain(&self.asks)
+            .filter(|resting| resting.order.owner == *owner)
+            .map(|resting| resting.order.order_id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    pub fn has_client_order_id(&self, owner: &Pubkey, client_order_id: u64) -> bool {
+        self.bids
+            .iter()
+            .chain(&self.asks)
+            .any(|resting| resting.order.owner == *owner && resting.order.client_order_id == client_order_id)
+    }
+
+    /// IDs of every resting order, earliest arrival first
+    pub fn ids_by_arrival(&self) -> Vec<u64> {
+        let mut resting: Vec<&Resting> = self.bids.iter().chain(&self.asks).collect();
+        resting.sort_by_key(|resting| resting.arrival);
+        resting.into_iter().map(|resting| resting.order.order_id).collect()
+    }
+
+    pub fn order_count(&self, side: OrderSide) -> usize {
+        self.side(side).len()
+    }
+
+    /// Resting order IDs, bids then asks in priority order; commands pick
+    /// orders by index into it
+    pub fn order_ids(&self) -> Vec<u64> {
+        self.bids.iter().chain(&self.asks).map(|resting| resting.order.order_id).collect()
+    }
+}
@@ -0,0 +1,63 @@
+-- On-chain event tables populated by the indexer
+-- Rows are keyed by (signature, event_index) so replays are idempotent
+
+-- Deposits observed on-chain
+CREATE TABLE IF NOT EXISTS deposits (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot BIGINT NOT NULL,
+    owner TEXT NOT NULL,
+    mint TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    timestamp BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (signature, event_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_deposits_owner ON deposits (owner);
+CREATE INDEX IF NOT EXISTS idx_deposits_slot ON deposits (slot);
+
+-- Withdrawals observed on-chain
+CREATE TABLE IF NOT EXISTS withdrawals (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot BIGINT NOT NULL,
+    owner TEXT NOT NULL,
+    mint TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    timestamp BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (signature, event_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_withdrawals_owner ON withdrawals (owner);
+CREATE INDEX IF NOT EXISTS idx_withdrawals_slot ON withdrawals (slot);
+
+-- Trade settlements observed on-chain
+CREATE TABLE IF NOT EXISTS settled_trades (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot BIGINT NOT NULL,
+    taker_order_id BIGINT NOT NULL,
+    maker_order_id BIGINT NOT NULL,
+    taker TEXT NOT NULL,
+    maker TEXT NOT NULL,
+    price BIGINT NOT NULL,
+    quantity BIGINT NOT NULL,
+    timestamp BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (signature, event_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_settled_trades_orders ON settled_trades (maker_order_id, taker_order_id);
+CREATE INDEX IF NOT EXISTS idx_settled_trades_slot ON settled_trades (slot);
+
+-- Resume points for background services
+CREATE TABLE IF NOT EXISTS service_checkpoints (
+    service TEXT PRIMARY KEY,
+    position BIGINT NOT NULL,
+    cursor TEXT,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TRIGGER update_service_checkpoints_updated_at BEFORE UPDATE ON service_checkpoints FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
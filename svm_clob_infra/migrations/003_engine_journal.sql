@@ -0,0 +1,29 @@
+-- Engine journal and on-chain settlement tracking
+
+-- Append-only log of engine events in processing order
+CREATE TABLE IF NOT EXISTS engine_journal (
+    sequence BIGSERIAL PRIMARY KEY,
+    timestamp BIGINT NOT NULL,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_engine_journal_timestamp ON engine_journal (timestamp);
+
+-- Settlement outcome of each journaled trade
+CREATE TABLE IF NOT EXISTS trade_settlements (
+    journal_sequence BIGINT PRIMARY KEY REFERENCES engine_journal(sequence),
+    maker_order_id BIGINT NOT NULL,
+    taker_order_id BIGINT NOT NULL,
+    signature TEXT,
+    status SMALLINT NOT NULL CHECK (status IN (0, 1, 2)), -- 0 = Submitted, 1 = Confirmed, 2 = Failed
+    attempts INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_trade_settlements_signature ON trade_settlements (signature);
+CREATE INDEX IF NOT EXISTS idx_trade_settlements_orders ON trade_settlements (maker_order_id, taker_order_id);
+
+CREATE TRIGGER update_trade_settlements_updated_at BEFORE UPDATE ON trade_settlements FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
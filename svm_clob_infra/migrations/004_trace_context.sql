@@ -0,0 +1,5 @@
+-- Distributed tracing context for engine events
+
+-- W3C traceparent of the request that produced each journal entry, so
+-- downstream consumers (settlement crank) can continue the trace
+ALTER TABLE engine_journal ADD COLUMN IF NOT EXISTS trace_context TEXT;
@@ -0,0 +1,10 @@
+-- Leader election for hot-standby matching engines
+
+-- One row per elected role; the holder renews `expires_at` while it is alive.
+-- `epoch` increases every time the lease changes hands or lapses.
+CREATE TABLE IF NOT EXISTS leader_leases (
+    name TEXT PRIMARY KEY,
+    holder TEXT NOT NULL,
+    epoch BIGINT NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
@@ -0,0 +1,19 @@
+-- Trade surveillance findings
+
+-- One row per pattern found in a journal window; (kind, window_start, accounts)
+-- is unique so rescanning a window after a restart does not duplicate alerts
+CREATE TABLE IF NOT EXISTS surveillance_alerts (
+    id BIGSERIAL PRIMARY KEY,
+    kind SMALLINT NOT NULL CHECK (kind IN (0, 1, 2)), -- 0 = WashTrading, 1 = Spoofing, 2 = MomentumIgnition
+    accounts TEXT[] NOT NULL,
+    score DOUBLE PRECISION NOT NULL,
+    window_start BIGINT NOT NULL,
+    window_end BIGINT NOT NULL,
+    first_sequence BIGINT NOT NULL,
+    last_sequence BIGINT NOT NULL,
+    details TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (kind, window_start, accounts)
+);
+
+CREATE INDEX IF NOT EXISTS idx_surveillance_alerts_kind_score ON surveillance_alerts (kind, score);
@@ -0,0 +1,19 @@
+-- Trading fees accrued per fill
+
+-- One row per side of a journaled fill; (journal_sequence, role) is unique so
+-- re-reading the journal after a restart does not charge a fill twice
+CREATE TABLE IF NOT EXISTS fee_accruals (
+    journal_sequence BIGINT NOT NULL,
+    role SMALLINT NOT NULL CHECK (role IN (0, 1)), -- 0 = Maker, 1 = Taker
+    owner TEXT NOT NULL,
+    price BIGINT NOT NULL,
+    quantity BIGINT NOT NULL,
+    fee_bps BIGINT NOT NULL,
+    fee BIGINT NOT NULL, -- Quote units, negative for a rebate
+    timestamp BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (journal_sequence, role)
+);
+
+CREATE INDEX IF NOT EXISTS idx_fee_accruals_owner_timestamp ON fee_accruals (owner, timestamp);
+CREATE INDEX IF NOT EXISTS idx_fee_accruals_timestamp ON fee_accruals (timestamp);
@@ -0,0 +1,11 @@
+-- Market identifiers
+
+-- Every order, fill and snapshot belongs to one market, identified by its
+-- orderbook PDA; rows written before markets were tracked default to the
+-- unset market ID
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS market_id TEXT NOT NULL DEFAULT '11111111111111111111111111111111';
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS market_id TEXT NOT NULL DEFAULT '11111111111111111111111111111111';
+ALTER TABLE orderbook_snapshots ADD COLUMN IF NOT EXISTS market_id TEXT NOT NULL DEFAULT '11111111111111111111111111111111';
+
+CREATE INDEX IF NOT EXISTS idx_orders_market_id ON orders (market_id);
+CREATE INDEX IF NOT EXISTS idx_trades_market_id_timestamp ON trades (market_id, timestamp);
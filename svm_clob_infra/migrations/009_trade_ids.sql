@@ -0,0 +1,19 @@
+-- Per-fill trade IDs
+
+-- The engine numbers every fill; trades stored before that are numbered in
+-- the order they were recorded, below any ID the engine hands out later.
+-- The unique index makes storing a journaled fill again a no-op.
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS trade_id BIGINT;
+
+UPDATE trades SET trade_id = numbered.trade_id
+FROM (
+    SELECT id, ROW_NUMBER() OVER (ORDER BY timestamp, created_at, id) AS trade_id
+    FROM trades
+    WHERE trade_id IS NULL
+) AS numbered
+WHERE trades.id = numbered.id;
+
+ALTER TABLE trades ALTER COLUMN trade_id SET NOT NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_trades_trade_id ON trades (trade_id);
+CREATE INDEX IF NOT EXISTS idx_trades_market_id_trade_id ON trades (market_id, trade_id);
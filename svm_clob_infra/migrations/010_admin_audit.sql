@@ -0,0 +1,16 @@
+-- Admin audit log
+
+-- One row per runtime setting changed through the admin API; `before` and
+-- `after` hold the setting as JSON, 'null' when it was unset or removed
+CREATE TABLE IF NOT EXISTS admin_audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    actor TEXT NOT NULL,
+    action TEXT NOT NULL,
+    target TEXT,
+    before TEXT NOT NULL,
+    after TEXT NOT NULL,
+    timestamp BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_admin_audit_log_target ON admin_audit_log (target);
@@ -0,0 +1,22 @@
+-- Sequence map
+
+-- One row per order a journal entry placed, replaced or filled, linking the
+-- engine's journal sequence and order ID to the orderbook and user account
+-- PDAs the order settles against. Settlement signatures are joined in from
+-- `trade_settlements` on the journal sequence of fills.
+CREATE TABLE IF NOT EXISTS sequence_map (
+    journal_sequence BIGINT NOT NULL,
+    order_id BIGINT NOT NULL,
+    event SMALLINT NOT NULL CHECK (event IN (0, 1, 2)), -- 0 = Placed, 1 = Replaced, 2 = Filled
+    trade_id BIGINT,
+    role SMALLINT CHECK (role IN (0, 1)), -- 0 = Maker, 1 = Taker; fills only
+    owner TEXT NOT NULL,
+    market_id TEXT NOT NULL,
+    user_account TEXT NOT NULL,
+    timestamp BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (journal_sequence, order_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_sequence_map_order_id ON sequence_map (order_id);
+CREATE INDEX IF NOT EXISTS idx_sequence_map_user_account ON sequence_map (user_account, journal_sequence);
@@ -0,0 +1,20 @@
+-- API audit log
+
+-- One row per mutating API call, written once it is answered; `params` and
+-- `result` hold the request and response bodies as JSON, 'null' when empty
+CREATE TABLE IF NOT EXISTS api_audit (
+    id BIGSERIAL PRIMARY KEY,
+    actor TEXT NOT NULL,
+    method TEXT NOT NULL,
+    route TEXT NOT NULL,
+    path TEXT NOT NULL,
+    params TEXT NOT NULL,
+    status INTEGER NOT NULL,
+    result TEXT NOT NULL,
+    latency_us BIGINT NOT NULL,
+    timestamp BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_audit_actor ON api_audit (actor, id);
+CREATE INDEX IF NOT EXISTS idx_api_audit_timestamp ON api_audit (timestamp);
@@ -0,0 +1,9 @@
+-- Client order IDs unique among an owner's open orders
+
+-- The engine rejects a placement reusing the client order ID of one of its
+-- owner's open orders; this index keeps storage consistent with it. 0 means
+-- the client set no ID and is not constrained. Duplicates already open must
+-- be cancelled before this migration can run.
+CREATE UNIQUE INDEX IF NOT EXISTS idx_orders_open_client_order_id
+    ON orders (market_id, owner, client_order_id)
+    WHERE status IN (0, 1) AND client_order_id <> 0;
@@ -0,0 +1,5 @@
+-- Engine receive and match times of fills
+
+-- Microseconds since the epoch; 0 on trades recorded before they were kept
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS received_at_us BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS matched_at_us BIGINT NOT NULL DEFAULT 0;
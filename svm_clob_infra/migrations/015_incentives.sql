@@ -0,0 +1,29 @@
+-- Liquidity incentive epochs and per-maker rewards
+
+-- One row per closed epoch; an epoch is written together with its reports,
+-- and the newest one tells a restarted service where to resume
+CREATE TABLE IF NOT EXISTS incentive_epochs (
+    epoch_start BIGINT PRIMARY KEY,
+    epoch_end BIGINT NOT NULL,
+    band_bps BIGINT NOT NULL,
+    reward_pool BIGINT NOT NULL,
+    makers INTEGER NOT NULL,
+    total_depth_seconds BIGINT NOT NULL,
+    last_sequence BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- One row per maker credited in an epoch
+CREATE TABLE IF NOT EXISTS incentive_reports (
+    epoch_start BIGINT NOT NULL REFERENCES incentive_epochs (epoch_start) ON DELETE CASCADE,
+    epoch_end BIGINT NOT NULL,
+    maker TEXT NOT NULL,
+    bid_depth_seconds BIGINT NOT NULL,
+    ask_depth_seconds BIGINT NOT NULL,
+    average_depth BIGINT NOT NULL,
+    share DOUBLE PRECISION NOT NULL,
+    reward BIGINT NOT NULL, -- Reward token base units
+    PRIMARY KEY (epoch_start, maker)
+);
+
+CREATE INDEX IF NOT EXISTS idx_incentive_reports_maker ON incentive_reports (maker, epoch_start);
@@ -0,0 +1,9 @@
+-- Resting orders of the book at a journal sequence; a book at any later
+-- sequence is rebuilt from the newest checkpoint before it and the journal
+CREATE TABLE IF NOT EXISTS book_checkpoints (
+    journal_sequence BIGINT PRIMARY KEY,
+    market_id TEXT NOT NULL,
+    orders TEXT NOT NULL, -- JSON array of orders, earliest arrival first
+    timestamp BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,21 @@
+-- Insurance fund: fee contributions earmarked off chain, payments observed on chain
+
+-- Share of each fee earmarked for the fund; rows accrued before this column
+-- existed earmarked nothing
+ALTER TABLE fee_accruals ADD COLUMN IF NOT EXISTS insurance_contribution BIGINT NOT NULL DEFAULT 0;
+
+-- `InsuranceFunded` and `InsuranceDrawn` events decoded by the indexer
+CREATE TABLE IF NOT EXISTS insurance_fund_events (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot BIGINT NOT NULL,
+    orderbook TEXT NOT NULL,
+    kind SMALLINT NOT NULL CHECK (kind IN (0, 1)), -- 0 = Funded, 1 = Drawn
+    account TEXT NOT NULL, -- Funding wallet, or the token account a draw paid out to
+    amount BIGINT NOT NULL,
+    timestamp BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (signature, event_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_insurance_fund_events_orderbook ON insurance_fund_events (orderbook, slot);
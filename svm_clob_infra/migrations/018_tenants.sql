@@ -0,0 +1,37 @@
+-- Tenants integrating against one deployment, their API keys and metered usage
+
+CREATE TABLE IF NOT EXISTS tenants (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    max_requests_per_second INTEGER, -- NULL leaves requests under the per-IP limit
+    max_orders_per_second INTEGER, -- NULL leaves placements unlimited
+    fee_share_bps BIGINT NOT NULL DEFAULT 0,
+    referral_code TEXT UNIQUE,
+    disabled BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL
+);
+
+-- Keys are looked up by the SHA-256 of the whole key; the key itself is not stored
+CREATE TABLE IF NOT EXISTS tenant_api_keys (
+    key_id TEXT PRIMARY KEY,
+    tenant_id TEXT NOT NULL REFERENCES tenants (id) ON DELETE CASCADE,
+    key_hash TEXT NOT NULL UNIQUE,
+    label TEXT,
+    created_at BIGINT NOT NULL,
+    revoked_at BIGINT
+);
+
+CREATE INDEX IF NOT EXISTS idx_tenant_api_keys_tenant ON tenant_api_keys (tenant_id, created_at);
+
+-- Hourly counts; replicas add their metered usage to the same rows
+CREATE TABLE IF NOT EXISTS tenant_usage (
+    tenant_id TEXT NOT NULL REFERENCES tenants (id) ON DELETE CASCADE,
+    period_start BIGINT NOT NULL,
+    requests BIGINT NOT NULL DEFAULT 0,
+    errors BIGINT NOT NULL DEFAULT 0,
+    rate_limited BIGINT NOT NULL DEFAULT 0,
+    orders_placed BIGINT NOT NULL DEFAULT 0,
+    notional_placed BIGINT NOT NULL DEFAULT 0, -- Sum of price * quantity
+    PRIMARY KEY (tenant_id, period_start)
+);
@@ -0,0 +1,19 @@
+-- Commitment of indexed program transactions
+
+-- One row per program transaction the indexer decoded. Records in deposits,
+-- withdrawals, settled_trades and insurance_fund_events are stored as soon as
+-- their transaction is seen at the configured commitment; the indexer then
+-- follows it here until it is finalized, or deletes its records and marks it
+-- failed or dropped when the chain settles without it.
+CREATE TABLE IF NOT EXISTS chain_transactions (
+    signature TEXT PRIMARY KEY,
+    slot BIGINT NOT NULL,
+    status SMALLINT NOT NULL CHECK (status IN (0, 1, 2, 3, 4)), -- 0 = Pending, 1 = Confirmed, 2 = Finalized, 3 = Failed, 4 = Dropped
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_chain_transactions_unfinalized ON chain_transactions (slot) WHERE status IN (0, 1);
+
+CREATE TRIGGER update_chain_transactions_updated_at BEFORE UPDATE ON chain_transactions FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,20 @@
+-- Dead letter queue of the settlement crank
+
+-- One row per settlement batch the settler gave up on. The batch's trades are
+-- kept as a JSON array of `DeadLetterTrade` so an operator can edit them
+-- before resubmitting; `first_sequence` is unique so a batch retried after a
+-- restart does not queue twice.
+CREATE TABLE IF NOT EXISTS settlement_dead_letters (
+    id BIGSERIAL PRIMARY KEY,
+    first_sequence BIGINT NOT NULL UNIQUE,
+    last_sequence BIGINT NOT NULL,
+    trades JSONB NOT NULL,
+    status SMALLINT NOT NULL CHECK (status IN (0, 1, 2, 3)), -- 0 = Open, 1 = Resubmitting, 2 = Settled, 3 = Discarded
+    reason TEXT NOT NULL,
+    attempts INTEGER NOT NULL,
+    signature TEXT,
+    created_at BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_settlement_dead_letters_status ON settlement_dead_letters (status, id);
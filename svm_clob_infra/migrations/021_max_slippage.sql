@@ -0,0 +1,5 @@
+-- Slippage tolerance of market orders
+
+-- Largest distance, in basis points, of a market order's average fill price
+-- from the best opposite price; NULL for orders placed without one
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS max_slippage_bps INTEGER;
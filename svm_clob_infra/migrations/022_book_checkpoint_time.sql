@@ -0,0 +1,5 @@
+-- Book checkpoints by time
+
+-- Historical book ranges start from the newest checkpoint taken before the
+-- range does
+CREATE INDEX IF NOT EXISTS idx_book_checkpoints_timestamp ON book_checkpoints (timestamp);
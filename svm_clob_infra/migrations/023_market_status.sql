@@ -0,0 +1,9 @@
+-- Trading state of each market, set by operators
+
+-- One row per market whose status was ever changed; a market without one is
+-- active. Kept across a point-in-time restore like other operator settings.
+CREATE TABLE IF NOT EXISTS market_status (
+    market_id TEXT PRIMARY KEY,
+    status SMALLINT NOT NULL, -- MarketStatus discriminant
+    updated_at BIGINT NOT NULL
+);
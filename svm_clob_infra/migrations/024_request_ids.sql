@@ -0,0 +1,14 @@
+-- Request IDs
+
+-- `X-Request-Id` of the client call that wrote each row, so one action can be
+-- followed from the API audit log through the journal to its orders and
+-- trades; NULL for rows written outside a call, such as expiry sweeps
+ALTER TABLE engine_journal ADD COLUMN IF NOT EXISTS request_id TEXT;
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS request_id TEXT;
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS request_id TEXT;
+ALTER TABLE api_audit ADD COLUMN IF NOT EXISTS request_id TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_engine_journal_request_id ON engine_journal (request_id) WHERE request_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_orders_request_id ON orders (request_id) WHERE request_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_trades_request_id ON trades (request_id) WHERE request_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_api_audit_request_id ON api_audit (request_id) WHERE request_id IS NOT NULL;
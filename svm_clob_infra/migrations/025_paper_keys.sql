@@ -0,0 +1,5 @@
+-- Paper trading API keys
+
+-- Calls made with a paper key trade in the RPC server's in-memory sandbox
+-- rather than the market
+ALTER TABLE tenant_api_keys ADD COLUMN IF NOT EXISTS paper BOOLEAN NOT NULL DEFAULT FALSE;
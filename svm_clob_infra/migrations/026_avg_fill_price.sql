@@ -0,0 +1,20 @@
+-- Cumulative fill value and average fill price of each order
+
+-- The value of an order's fills overflows BIGINT, so it is kept as decimal text
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS filled_notional TEXT NOT NULL DEFAULT '0';
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS avg_fill_price BIGINT;
+
+-- Orders filled before the columns existed take them from their trades
+UPDATE orders o SET
+    filled_notional = fills.notional::TEXT,
+    avg_fill_price = FLOOR(fills.notional / fills.quantity)::BIGINT
+FROM (
+    SELECT order_id, SUM(quantity::NUMERIC * price) AS notional, SUM(quantity::NUMERIC) AS quantity
+    FROM (
+        SELECT maker_order_id AS order_id, price, quantity FROM trades
+        UNION ALL
+        SELECT taker_order_id AS order_id, price, quantity FROM trades
+    ) sides
+    GROUP BY order_id
+) fills
+WHERE o.order_id = fills.order_id AND o.avg_fill_price IS NULL;
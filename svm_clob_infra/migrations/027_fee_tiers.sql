@@ -0,0 +1,13 @@
+-- Fee tiers
+
+-- Tier each user was assigned at the last nightly recomputation from their
+-- traded volume in the trailing 30 days; the whole table is replaced at
+-- every run, so a user who stopped trading drops back to the base rates
+CREATE TABLE IF NOT EXISTS fee_tiers (
+    owner TEXT PRIMARY KEY,
+    volume_30d BIGINT NOT NULL,
+    tier INTEGER,
+    maker_fee_bps BIGINT NOT NULL,
+    taker_fee_bps BIGINT NOT NULL,
+    computed_at BIGINT NOT NULL
+);
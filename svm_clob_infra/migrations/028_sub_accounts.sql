@@ -0,0 +1,29 @@
+-- Sub-accounts
+
+-- Sub-accounts wallets opened on chain; each trades as `owner`, with its own
+-- user account, balances and orders, and its wallet signs for it
+CREATE TABLE IF NOT EXISTS sub_accounts (
+    owner TEXT PRIMARY KEY,
+    wallet TEXT NOT NULL,
+    sub_account_index INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    signature TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    UNIQUE (wallet, sub_account_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_sub_accounts_signature ON sub_accounts(signature);
+
+-- Balance moves between user accounts of one wallet, part of the ledger
+CREATE TABLE IF NOT EXISTS sub_account_transfers (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot BIGINT NOT NULL,
+    wallet TEXT NOT NULL,
+    from_owner TEXT NOT NULL,
+    to_owner TEXT NOT NULL,
+    base_amount BIGINT NOT NULL,
+    quote_amount BIGINT NOT NULL,
+    timestamp BIGINT NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
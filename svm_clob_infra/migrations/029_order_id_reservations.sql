@@ -0,0 +1,8 @@
+-- Order ID reservations
+
+-- The engine journals `OrderIdsReserved` before handing out a block of order
+-- IDs and resumes past the highest reservation on restart; this index keeps
+-- finding it cheap as the journal grows
+CREATE INDEX IF NOT EXISTS idx_engine_journal_order_id_reservations
+    ON engine_journal (((payload->'data'->>'through')::BIGINT))
+    WHERE payload->>'type' = 'OrderIdsReserved';
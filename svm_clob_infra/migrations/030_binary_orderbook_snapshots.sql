@@ -0,0 +1,8 @@
+-- Binary order book snapshots
+
+-- Snapshot levels are written as one zstd-compressed borsh value with a
+-- versioned header (see `svm_clob_storage::snapshot_codec`) instead of two
+-- JSON arrays; rows written before keep their JSON and are still read
+ALTER TABLE orderbook_snapshots ADD COLUMN IF NOT EXISTS levels BYTEA;
+ALTER TABLE orderbook_snapshots ALTER COLUMN bids DROP NOT NULL;
+ALTER TABLE orderbook_snapshots ALTER COLUMN asks DROP NOT NULL;
@@ -0,0 +1,19 @@
+-- Self-trade prevention groups
+
+-- Prevention group changes read from chain, per market; the latest one of an
+-- owner is its group, and group 0 takes it out of every group
+CREATE TABLE IF NOT EXISTS prevention_groups (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot BIGINT NOT NULL,
+    market_id TEXT NOT NULL,
+    owner TEXT NOT NULL,
+    prevention_group BIGINT NOT NULL,
+    timestamp BIGINT NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_prevention_groups_owner ON prevention_groups(market_id, owner, slot DESC, event_index DESC);
+
+-- Prevention group an order was placed in; NULL for orders outside one
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS prevention_group_id BIGINT;
@@ -0,0 +1,14 @@
+-- Suspensions of owners and API keys, set by operators
+
+-- One row per suspended subject, deleted when the suspension is lifted; the
+-- admin audit log keeps the history. Kept across a point-in-time restore
+-- like other operator settings.
+CREATE TABLE IF NOT EXISTS suspensions (
+    subject_kind SMALLINT NOT NULL, -- 0 owner, 1 API key
+    subject TEXT NOT NULL,          -- owner pubkey or API key ID
+    reason SMALLINT NOT NULL,       -- SuspensionReason discriminant
+    note TEXT,
+    actor TEXT NOT NULL,
+    suspended_at BIGINT NOT NULL,
+    PRIMARY KEY (subject_kind, subject)
+);
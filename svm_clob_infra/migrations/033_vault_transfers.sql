@@ -0,0 +1,27 @@
+-- Plain SPL transfers into the program's vaults, credited by the authority
+
+-- One row per transfer instruction, written by the indexer when it sees the
+-- transfer and moved along as the transfer is finalized, credited with
+-- `credit_transfer` by the settler, and read back from chain. A credit whose
+-- transaction is rolled back returns its transfer to `ready`.
+CREATE TABLE IF NOT EXISTS vault_transfers (
+    signature TEXT NOT NULL,
+    instruction_index SMALLINT NOT NULL,
+    slot BIGINT NOT NULL,
+    mint TEXT NOT NULL,
+    source TEXT NOT NULL,           -- token account debited
+    sender TEXT NOT NULL,           -- wallet credited
+    amount BIGINT NOT NULL,
+    status SMALLINT NOT NULL,       -- VaultTransferStatus discriminant
+    confirmations BIGINT,           -- NULL once finalized
+    reason TEXT,
+    credit_signature TEXT,
+    detected_at BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL,
+    PRIMARY KEY (signature, instruction_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_vault_transfers_status ON vault_transfers (status, slot);
+CREATE INDEX IF NOT EXISTS idx_vault_transfers_sender ON vault_transfers (sender, slot DESC);
+CREATE INDEX IF NOT EXISTS idx_vault_transfers_credit ON vault_transfers (credit_signature)
+    WHERE credit_signature IS NOT NULL;
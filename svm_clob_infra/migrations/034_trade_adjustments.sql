@@ -0,0 +1,71 @@
+-- Busts and price corrections of executed trades by operators
+
+-- Current version of each trade: 0 as matched, raised by every adjustment;
+-- status 0 = active, 1 = corrected, 2 = busted
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS status SMALLINT NOT NULL DEFAULT 0;
+
+-- One row per adjustment, keyed by the journal sequence of its
+-- `TradeAdjusted` entry, with the progress of its on-chain reversal
+CREATE TABLE IF NOT EXISTS trade_adjustments (
+    adjustment_id BIGINT PRIMARY KEY REFERENCES engine_journal(sequence),
+    trade_id BIGINT NOT NULL,
+    market_id TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    status SMALLINT NOT NULL,        -- TradeStatus discriminant
+    trade_sequence BIGINT NOT NULL,  -- journal sequence of the fill
+    maker TEXT NOT NULL,
+    taker TEXT NOT NULL,
+    maker_order_id BIGINT NOT NULL,
+    taker_order_id BIGINT NOT NULL,
+    maker_side SMALLINT NOT NULL,
+    quantity BIGINT NOT NULL,
+    previous_price BIGINT NOT NULL,
+    price BIGINT NOT NULL,
+    base_to_maker BIGINT NOT NULL,
+    quote_to_maker BIGINT NOT NULL,
+    reason TEXT NOT NULL,
+    actor TEXT NOT NULL,
+    reversal SMALLINT NOT NULL,      -- ReversalStatus discriminant
+    reversal_signature TEXT,
+    reversal_error TEXT,
+    created_at BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL,
+    UNIQUE (trade_id, version)
+);
+
+CREATE INDEX IF NOT EXISTS idx_trade_adjustments_reversal ON trade_adjustments (reversal, adjustment_id);
+CREATE INDEX IF NOT EXISTS idx_trade_adjustments_signature ON trade_adjustments (reversal_signature)
+    WHERE reversal_signature IS NOT NULL;
+
+-- Version of the trade a settlement moved on chain; adjustments made after
+-- it was submitted are reversed
+ALTER TABLE trade_settlements ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 0;
+
+-- `SettlementAdjusted` events decoded by the indexer
+CREATE TABLE IF NOT EXISTS settlement_adjustments (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot BIGINT NOT NULL,
+    adjustment_id BIGINT NOT NULL,
+    trade_id BIGINT NOT NULL,
+    maker TEXT NOT NULL,
+    taker TEXT NOT NULL,
+    base_to_maker BIGINT NOT NULL,
+    quote_to_maker BIGINT NOT NULL,
+    timestamp BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (signature, event_index)
+);
+
+-- Accruals compensating an adjusted fill point at the fill's journal entry
+ALTER TABLE fee_accruals ADD COLUMN IF NOT EXISTS adjusted_sequence BIGINT;
+
+-- Orders of an adjusted fill are mapped to the adjustment too (3 = Adjusted)
+ALTER TABLE sequence_map DROP CONSTRAINT IF EXISTS sequence_map_event_check;
+ALTER TABLE sequence_map ADD CONSTRAINT sequence_map_event_check CHECK (event IN (0, 1, 2, 3));
+
+-- Adjustments look up the fill they adjust by trade ID
+CREATE INDEX IF NOT EXISTS idx_engine_journal_trade_id
+    ON engine_journal (((payload->'data'->'trade'->>'trade_id')::BIGINT))
+    WHERE payload->>'type' = 'TradeExecuted';
@@ -0,0 +1,5 @@
+-- WebSocket feed entitlements of API keys
+
+-- Market data a connection made with the key may subscribe to:
+-- 0 = public L2 (top of book), 1 = full-depth L2, 2 = L3, 3 = drop copy
+ALTER TABLE tenant_api_keys ADD COLUMN IF NOT EXISTS entitlement SMALLINT NOT NULL DEFAULT 0;
@@ -0,0 +1,12 @@
+-- Signed order nonces claimed while the shared Redis cache is unavailable
+
+-- One row per claimed owner and nonce. A claim binds until its signature's
+-- expiry, after which the pair may be claimed again and the row is reused.
+-- Kept across a point-in-time restore, as replay protection must outlive it.
+CREATE TABLE IF NOT EXISTS nonce_claims (
+    owner TEXT NOT NULL,
+    nonce BIGINT NOT NULL,        -- u64 stored as BIGINT
+    expiry BIGINT NOT NULL,       -- unix seconds
+    claimed_at BIGINT NOT NULL,   -- unix seconds
+    PRIMARY KEY (owner, nonce)
+);
@@ -0,0 +1,37 @@
+-- Compressed receipt trees markets append their settled fills to
+CREATE TABLE IF NOT EXISTS receipt_trees (
+    merkle_tree TEXT PRIMARY KEY,
+    orderbook TEXT NOT NULL,
+    max_depth INTEGER NOT NULL,
+    max_buffer_size INTEGER NOT NULL,
+    generation INTEGER NOT NULL,
+    signature TEXT NOT NULL,
+    slot BIGINT NOT NULL,
+    timestamp BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- One side of a settled fill per leaf; `leaf` is the hash the program
+-- appended, kept so proofs are built without hashing every receipt again
+CREATE TABLE IF NOT EXISTS compressed_receipts (
+    merkle_tree TEXT NOT NULL,
+    leaf_index BIGINT NOT NULL,
+    signature TEXT NOT NULL,
+    slot BIGINT NOT NULL,
+    orderbook TEXT NOT NULL,
+    owner TEXT NOT NULL,
+    order_id BIGINT NOT NULL,
+    counterparty_order_id BIGINT NOT NULL,
+    price BIGINT NOT NULL,
+    quantity BIGINT NOT NULL,
+    timestamp BIGINT NOT NULL,
+    side SMALLINT NOT NULL,
+    role SMALLINT NOT NULL,
+    leaf BYTEA NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (merkle_tree, leaf_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_compressed_receipts_owner
+    ON compressed_receipts (owner, timestamp DESC, leaf_index DESC);
+CREATE INDEX IF NOT EXISTS idx_compressed_receipts_signature ON compressed_receipts (signature);
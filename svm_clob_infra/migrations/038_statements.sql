@@ -0,0 +1,33 @@
+-- End-of-day statements
+
+-- One row per generated UTC day; a day is written together with its
+-- statements, and the newest one tells a restarted job where to resume
+CREATE TABLE IF NOT EXISTS statement_days (
+    period_start BIGINT PRIMARY KEY,
+    period_end BIGINT NOT NULL,
+    statements INTEGER NOT NULL,
+    generated_at BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- One row per user and day, with the statement rendered in every format
+CREATE TABLE IF NOT EXISTS statements (
+    period_start BIGINT NOT NULL REFERENCES statement_days (period_start) ON DELETE CASCADE,
+    period_end BIGINT NOT NULL,
+    owner TEXT NOT NULL,
+    statement_date TEXT NOT NULL, -- YYYY-MM-DD, UTC
+    fills INTEGER NOT NULL,
+    deposits INTEGER NOT NULL,
+    withdrawals INTEGER NOT NULL,
+    transfers INTEGER NOT NULL,
+    total_fees BIGINT NOT NULL,
+    ending_base_balance BIGINT NOT NULL,
+    ending_quote_balance BIGINT NOT NULL,
+    generated_at BIGINT NOT NULL,
+    json BYTEA NOT NULL,
+    csv BYTEA NOT NULL,
+    pdf BYTEA NOT NULL,
+    PRIMARY KEY (period_start, owner)
+);
+
+CREATE INDEX IF NOT EXISTS idx_statements_owner ON statements (owner, period_start DESC);
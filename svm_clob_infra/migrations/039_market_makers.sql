@@ -0,0 +1,44 @@
+-- Designated market makers, their quoting obligations and scorecards
+
+-- One row per registered maker; deregistering deletes the row and keeps its scorecards
+CREATE TABLE IF NOT EXISTS market_makers (
+    maker TEXT PRIMARY KEY,
+    label TEXT,
+    max_spread_bps BIGINT NOT NULL,
+    min_depth BIGINT NOT NULL, -- Base quantity, native units
+    min_uptime_bps BIGINT NOT NULL,
+    registered_at BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL
+);
+
+-- One row per closed period; a period is written together with its
+-- scorecards, and the newest one tells a restarted job where to resume
+CREATE TABLE IF NOT EXISTS obligation_periods (
+    period_start BIGINT PRIMARY KEY,
+    period_end BIGINT NOT NULL,
+    sample_secs BIGINT NOT NULL,
+    makers INTEGER NOT NULL,
+    last_sequence BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- One row per maker scored in a period, with the obligations it was scored against
+CREATE TABLE IF NOT EXISTS market_maker_scorecards (
+    period_start BIGINT NOT NULL REFERENCES obligation_periods (period_start) ON DELETE CASCADE,
+    period_end BIGINT NOT NULL,
+    maker TEXT NOT NULL,
+    max_spread_bps BIGINT NOT NULL,
+    min_depth BIGINT NOT NULL,
+    min_uptime_bps BIGINT NOT NULL,
+    samples INTEGER NOT NULL,
+    two_sided_samples INTEGER NOT NULL,
+    compliant_samples INTEGER NOT NULL,
+    uptime_bps BIGINT NOT NULL,
+    average_spread_bps BIGINT,
+    average_bid_depth BIGINT NOT NULL,
+    average_ask_depth BIGINT NOT NULL,
+    meets_obligations BOOLEAN NOT NULL,
+    PRIMARY KEY (period_start, maker)
+);
+
+CREATE INDEX IF NOT EXISTS idx_market_maker_scorecards_maker ON market_maker_scorecards (maker, period_start);